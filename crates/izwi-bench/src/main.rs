@@ -0,0 +1,287 @@
+//! Izwi bench - drive the engine with synthetic TTS load and report latency,
+//! real-time factor, throughput and KV cache utilization
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+mod report;
+mod workload;
+
+use izwi_core::{
+    CacheControl, ConfigLoader, Engine, EngineCoreConfig, EngineCoreRequest, JobStatus,
+    ModelVariant,
+};
+use report::{BenchReport, RequestSample};
+use workload::{arrival_offsets, synthetic_text, Arrival, Rng};
+
+/// How often the KV cache is sampled during a run
+const KV_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+/// How often a pending request's result is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+const STDOUT: &str = "-";
+
+/// Synthetic load benchmark for the Izwi engine
+#[derive(Parser, Debug)]
+#[command(name = "izwi-bench", version, about)]
+struct Args {
+    /// Configuration file (TOML, YAML or JSON)
+    #[arg(short, long, env = "IZWI_CONFIG_PATH")]
+    config: Option<PathBuf>,
+
+    /// Directory holding downloaded models
+    #[arg(long)]
+    models_dir: Option<PathBuf>,
+
+    /// Model to benchmark (defaults to the engine's default model)
+    #[arg(short, long, value_parser = parse_variant)]
+    model: Option<ModelVariant>,
+
+    /// Requests sent in total
+    #[arg(short = 'n', long, default_value_t = 32)]
+    requests: usize,
+
+    /// Clients in closed-loop runs
+    #[arg(short = 'j', long, default_value_t = 4)]
+    concurrency: usize,
+
+    /// Arrival process
+    #[arg(long, value_enum, default_value_t = Arrival::Closed)]
+    arrival: Arrival,
+
+    /// Mean requests per second for open-loop arrivals
+    #[arg(long, default_value_t = 2.0)]
+    rate: f64,
+
+    /// Text lengths in characters, used in turn
+    #[arg(long, value_delimiter = ',', default_values_t = [100, 400])]
+    text_lengths: Vec<usize>,
+
+    /// Seed for texts and arrival times
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Skip the warmup pass before measuring
+    #[arg(long)]
+    skip_warmup: bool,
+
+    /// Write the JSON report to a file, or `-` for stdout
+    #[arg(long)]
+    json: Option<PathBuf>,
+}
+
+/// Parse a model name such as `Qwen3-TTS-12Hz-0.6B-Base` or its HuggingFace repo ID
+fn parse_variant(s: &str) -> Result<ModelVariant, String> {
+    ModelVariant::all()
+        .iter()
+        .copied()
+        .find(|v| v.dir_name().eq_ignore_ascii_case(s) || v.repo_id().eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("unknown model '{}'", s))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "izwi_core=warn".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    if args.requests == 0 || args.concurrency == 0 {
+        bail!("--requests and --concurrency must be at least 1");
+    }
+    if args.text_lengths.is_empty() || args.text_lengths.contains(&0) {
+        bail!("--text-lengths must be positive");
+    }
+    if args.arrival != Arrival::Closed && args.rate <= 0.0 {
+        bail!("--rate must be positive for open-loop arrivals");
+    }
+
+    let mut loader = ConfigLoader::new();
+    if let Some(path) = &args.config {
+        loader = loader.with_file(path);
+    }
+    if let Some(models_dir) = &args.models_dir {
+        loader = loader.set_override("engine.models_dir", models_dir.display());
+    }
+    let config = loader.load()?.engine;
+    let engine = Arc::new(Engine::new(EngineCoreConfig {
+        models_dir: config.models_dir.clone(),
+        max_batch_size: config.max_batch_size,
+        ..Default::default()
+    })?);
+
+    if let Some(model) = args.model {
+        if engine.current_model().await != model {
+            engine
+                .swap_model(model)
+                .await
+                .with_context(|| format!("cannot load {}", model.dir_name()))?;
+        }
+    }
+    let model = engine.current_model().await;
+    if !args.skip_warmup {
+        let warmup = engine.warmup().await?;
+        eprintln!(
+            "Warmed up {} in {:.0} ms",
+            model.dir_name(),
+            warmup.total_ms
+        );
+    }
+
+    let mut rng = Rng::new(args.seed);
+    let texts: Vec<String> = (0..args.requests)
+        .map(|i| synthetic_text(&mut rng, args.text_lengths[i % args.text_lengths.len()]))
+        .collect();
+    let offsets = arrival_offsets(args.arrival, args.rate, args.requests, &mut rng);
+
+    engine.reset_profile().await?;
+    let tokens_before = engine.metrics().await.tokens_generated;
+    let sampling = Arc::new(AtomicBool::new(true));
+    let sampler = tokio::spawn(sample_kv_cache(engine.clone(), sampling.clone()));
+
+    eprintln!(
+        "Sending {} requests to {} ({:?} arrivals)",
+        args.requests,
+        model.dir_name(),
+        args.arrival
+    );
+    let start = Instant::now();
+    let texts = Arc::new(texts);
+    let mut tasks = JoinSet::new();
+    match args.arrival {
+        Arrival::Closed => {
+            let next = Arc::new(AtomicUsize::new(0));
+            for _ in 0..args.concurrency.min(args.requests) {
+                let (engine, texts, next) = (engine.clone(), texts.clone(), next.clone());
+                tasks.spawn(async move {
+                    let mut samples = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(text) = texts.get(index) else {
+                            break;
+                        };
+                        samples.push(run_request(&engine, text).await);
+                    }
+                    samples
+                });
+            }
+        }
+        Arrival::Poisson | Arrival::Uniform => {
+            for (index, offset) in offsets.into_iter().enumerate() {
+                tokio::time::sleep_until((start + offset).into()).await;
+                let (engine, texts) = (engine.clone(), texts.clone());
+                tasks.spawn(async move { vec![run_request(&engine, &texts[index]).await] });
+            }
+        }
+    }
+    let mut samples = Vec::with_capacity(args.requests);
+    while let Some(batch) = tasks.join_next().await {
+        samples.extend(batch?);
+    }
+    let duration_secs = start.elapsed().as_secs_f64();
+
+    sampling.store(false, Ordering::Relaxed);
+    let kv = sampler.await?;
+
+    let mut report = BenchReport::from_samples(
+        model.dir_name().to_string(),
+        args.arrival,
+        args.concurrency,
+        (args.arrival != Arrival::Closed).then_some(args.rate),
+        duration_secs,
+        samples,
+    );
+    report.tokens = engine
+        .metrics()
+        .await
+        .tokens_generated
+        .saturating_sub(tokens_before);
+    report.tokens_per_sec = report.tokens as f64 / duration_secs.max(f64::EPSILON);
+    report.mean_batch_size = engine.profile_snapshot(None).await?.summary.mean_batch_size;
+    if !kv.is_empty() {
+        report.kv_utilization_mean = kv.iter().sum::<f64>() / kv.len() as f64;
+        report.kv_utilization_peak = kv.iter().copied().fold(0.0, f64::max);
+    }
+
+    match &args.json {
+        Some(path) if path.as_os_str() == STDOUT => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            eprint!("\n{}", report.render());
+        }
+        Some(path) => {
+            std::fs::write(path, serde_json::to_string_pretty(&report)?)
+                .with_context(|| format!("cannot write {:?}", path))?;
+            print!("{}", report.render());
+        }
+        None => print!("{}", report.render()),
+    }
+    Ok(())
+}
+
+/// Submit one request and wait for its audio
+async fn run_request(engine: &Engine, text: &str) -> RequestSample {
+    let mut sample = RequestSample {
+        text_chars: text.chars().count(),
+        latency_ms: 0.0,
+        ttfb_ms: None,
+        ttfa_ms: None,
+        audio_secs: 0.0,
+        error: None,
+    };
+    // Bypass the output cache so repeated texts are still generated
+    let request = EngineCoreRequest::tts(text).with_cache_control(CacheControl::NoStore);
+    let request_id = match engine.submit(request).await {
+        Ok(id) => id,
+        Err(e) => {
+            sample.error = Some(e.to_string());
+            return sample;
+        }
+    };
+
+    let store = engine.result_store();
+    let result = loop {
+        match store.get(&request_id) {
+            Some(result) if matches!(result.status, JobStatus::Completed | JobStatus::Failed) => {
+                break result;
+            }
+            Some(_) => tokio::time::sleep(POLL_INTERVAL).await,
+            None => {
+                sample.error = Some("result expired before it was read".to_string());
+                return sample;
+            }
+        }
+    };
+    if let Some(finished_at) = result.finished_at {
+        sample.latency_ms = (finished_at - result.submitted_at).as_secs_f64() * 1000.0;
+    }
+    sample.error = result.error;
+    sample.audio_secs = result.audio.map_or(0.0, |a| f64::from(a.duration_secs));
+
+    if let Some(latency) = engine.latency_report(&request_id).await {
+        let ttfb = latency.queue_ms + latency.prefill_ms;
+        sample.ttfb_ms = Some(ttfb);
+        sample.ttfa_ms = latency.decode_chunk_ms.first().map(|chunk| ttfb + chunk);
+    }
+    sample
+}
+
+/// KV cache utilization sampled while `running` is set
+async fn sample_kv_cache(engine: Arc<Engine>, running: Arc<AtomicBool>) -> Vec<f64> {
+    let mut samples = Vec::new();
+    let mut interval = tokio::time::interval(KV_SAMPLE_INTERVAL);
+    while running.load(Ordering::Relaxed) {
+        interval.tick().await;
+        samples.push(engine.kv_cache_stats().await.utilization());
+    }
+    samples
+}
@@ -0,0 +1,225 @@
+//! Benchmark measurements and their JSON / text reports
+
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::workload::Arrival;
+
+/// Measurements of one request
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestSample {
+    pub text_chars: usize,
+    /// Submission to finished audio
+    pub latency_ms: f64,
+    /// Submission to the first generated token (queue + prefill)
+    pub ttfb_ms: Option<f64>,
+    /// Submission to the first decoded audio (first token plus one decode step)
+    pub ttfa_ms: Option<f64>,
+    pub audio_secs: f64,
+    pub error: Option<String>,
+}
+
+/// Summary statistics of one measure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    /// `None` for no values
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut values: Vec<f64> = values.into_iter().filter(|v| v.is_finite()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let at = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+/// Result of a benchmark run
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub model: String,
+    pub arrival: Arrival,
+    /// Clients in a closed-loop run
+    pub concurrency: usize,
+    /// Requests per second in an open-loop run
+    pub rate: Option<f64>,
+    pub requests: usize,
+    pub failed: usize,
+    pub duration_secs: f64,
+    /// Completed requests per second
+    pub throughput_rps: f64,
+    /// Seconds of audio produced per second of wall time
+    pub audio_secs_per_sec: f64,
+    pub latency_ms: Option<Distribution>,
+    pub ttfb_ms: Option<Distribution>,
+    pub ttfa_ms: Option<Distribution>,
+    /// Real-time factor: generation time over audio duration (below 1 is
+    /// faster than real time)
+    pub rtf: Option<Distribution>,
+    /// Prefill and decode tokens processed by the engine
+    pub tokens: u64,
+    pub tokens_per_sec: f64,
+    pub mean_batch_size: f64,
+    /// Allocated share of KV cache blocks, sampled during the run
+    pub kv_utilization_mean: f64,
+    pub kv_utilization_peak: f64,
+    pub samples: Vec<RequestSample>,
+}
+
+impl BenchReport {
+    /// Summarize samples. Engine counters are filled in by the caller.
+    pub fn from_samples(
+        model: String,
+        arrival: Arrival,
+        concurrency: usize,
+        rate: Option<f64>,
+        duration_secs: f64,
+        samples: Vec<RequestSample>,
+    ) -> Self {
+        let ok: Vec<&RequestSample> = samples.iter().filter(|s| s.error.is_none()).collect();
+        let per_sec = |value: f64| {
+            if duration_secs > 0.0 {
+                value / duration_secs
+            } else {
+                0.0
+            }
+        };
+        let audio_secs = ok.iter().fold(0.0, |sum, s| sum + s.audio_secs);
+        Self {
+            model,
+            arrival,
+            concurrency,
+            rate,
+            requests: samples.len(),
+            failed: samples.len() - ok.len(),
+            duration_secs,
+            throughput_rps: per_sec(ok.len() as f64),
+            audio_secs_per_sec: per_sec(audio_secs),
+            latency_ms: Distribution::of(ok.iter().map(|s| s.latency_ms)),
+            ttfb_ms: Distribution::of(ok.iter().filter_map(|s| s.ttfb_ms)),
+            ttfa_ms: Distribution::of(ok.iter().filter_map(|s| s.ttfa_ms)),
+            rtf: Distribution::of(
+                ok.iter()
+                    .filter(|s| s.audio_secs > 0.0)
+                    .map(|s| s.latency_ms / 1000.0 / s.audio_secs),
+            ),
+            tokens: 0,
+            tokens_per_sec: 0.0,
+            mean_batch_size: 0.0,
+            kv_utilization_mean: 0.0,
+            kv_utilization_peak: 0.0,
+            samples,
+        }
+    }
+
+    /// Human-readable summary
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let load = match self.rate {
+            Some(rate) => format!("{:?} arrivals at {:.2} req/s", self.arrival, rate),
+            None => format!("{} closed-loop clients", self.concurrency),
+        };
+        let _ = writeln!(out, "Model        {}", self.model);
+        let _ = writeln!(out, "Load         {}", load);
+        let _ = writeln!(
+            out,
+            "Requests     {} ({} failed) in {:.1} s",
+            self.requests, self.failed, self.duration_secs
+        );
+        let _ = writeln!(
+            out,
+            "Throughput   {:.2} req/s, {:.2} audio s/s, {:.1} tokens/s",
+            self.throughput_rps, self.audio_secs_per_sec, self.tokens_per_sec
+        );
+        let _ = writeln!(
+            out,
+            "Engine       {} tokens, mean batch {:.2}, KV cache {:.0}% mean / {:.0}% peak",
+            self.tokens,
+            self.mean_batch_size,
+            self.kv_utilization_mean * 100.0,
+            self.kv_utilization_peak * 100.0
+        );
+        let _ = writeln!(
+            out,
+            "\n{:<14} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "", "mean", "p50", "p90", "p99", "max"
+        );
+        for (name, dist) in [
+            ("latency (ms)", &self.latency_ms),
+            ("TTFB (ms)", &self.ttfb_ms),
+            ("TTFA (ms)", &self.ttfa_ms),
+            ("RTF", &self.rtf),
+        ] {
+            match dist {
+                Some(d) => {
+                    let _ = writeln!(
+                        out,
+                        "{:<14} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                        name, d.mean, d.p50, d.p90, d.p99, d.max
+                    );
+                }
+                None => {
+                    let _ = writeln!(out, "{:<14} {:>10}", name, "-");
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(latency_ms: f64, audio_secs: f64, error: Option<&str>) -> RequestSample {
+        RequestSample {
+            text_chars: 100,
+            latency_ms,
+            ttfb_ms: Some(latency_ms / 4.0),
+            ttfa_ms: None,
+            audio_secs,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = Distribution::of((1..=100).map(f64::from)).unwrap();
+        assert_eq!(dist.mean, 50.5);
+        assert_eq!(
+            (dist.p50, dist.p90, dist.p99, dist.max),
+            (51.0, 90.0, 99.0, 100.0)
+        );
+        assert_eq!(Distribution::of([]), None);
+    }
+
+    #[test]
+    fn test_report_skips_failed_requests() {
+        let samples = vec![
+            sample(500.0, 2.0, None),
+            sample(1500.0, 3.0, None),
+            sample(10.0, 0.0, Some("boom")),
+        ];
+        let report = BenchReport::from_samples("m".into(), Arrival::Closed, 2, None, 2.0, samples);
+        assert_eq!((report.requests, report.failed), (3, 1));
+        assert_eq!(report.throughput_rps, 1.0);
+        assert_eq!(report.audio_secs_per_sec, 2.5);
+        assert_eq!(report.rtf.as_ref().unwrap().max, 0.5);
+        assert!(report.ttfa_ms.is_none());
+        assert!(report.render().contains("3 (1 failed)"));
+    }
+}
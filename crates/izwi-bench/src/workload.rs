@@ -0,0 +1,118 @@
+//! Synthetic workloads: request texts and arrival times
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Words synthetic texts are drawn from
+const WORDS: &[&str] = &[
+    "the", "quick", "brown", "fox", "jumps", "over", "a", "lazy", "dog", "while", "seven",
+    "bright", "engines", "hum", "quietly", "across", "northern", "valleys", "every", "morning",
+    "people", "gather", "near", "old", "stone", "bridges", "to", "share", "stories", "about",
+    "rivers", "weather", "music", "and", "distant", "cities", "under", "silver", "clouds",
+];
+
+/// How requests arrive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrival {
+    /// Each of `concurrency` clients sends its next request when the last finishes
+    Closed,
+    /// Open loop with exponential gaps averaging `1 / rate`
+    Poisson,
+    /// Open loop with fixed gaps of `1 / rate`
+    Uniform,
+}
+
+/// Small deterministic generator (SplitMix64), so runs are repeatable
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Sentences of about `chars` characters
+pub fn synthetic_text(rng: &mut Rng, chars: usize) -> String {
+    let mut text = String::with_capacity(chars + 16);
+    let mut sentence_words = 0;
+    while text.len() < chars.max(1) {
+        let word = WORDS[(rng.next_u64() % WORDS.len() as u64) as usize];
+        if sentence_words == 0 {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let mut letters = word.chars();
+            if let Some(first) = letters.next() {
+                text.extend(first.to_uppercase());
+                text.push_str(letters.as_str());
+            }
+        } else {
+            text.push(' ');
+            text.push_str(word);
+        }
+        sentence_words += 1;
+        if sentence_words >= 6 + (rng.next_u64() % 8) as usize {
+            text.push('.');
+            sentence_words = 0;
+        }
+    }
+    if sentence_words > 0 {
+        text.push('.');
+    }
+    text
+}
+
+/// Offsets from the start of the run at which each of `count` requests is sent
+/// (open-loop arrivals only)
+pub fn arrival_offsets(arrival: Arrival, rate: f64, count: usize, rng: &mut Rng) -> Vec<Duration> {
+    let mean_gap = if rate > 0.0 { 1.0 / rate } else { 0.0 };
+    let mut at = 0.0;
+    (0..count)
+        .map(|_| {
+            let offset = Duration::from_secs_f64(at);
+            at += match arrival {
+                Arrival::Poisson => -(1.0 - rng.next_f64()).ln() * mean_gap,
+                Arrival::Uniform | Arrival::Closed => mean_gap,
+            };
+            offset
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_text_length_and_determinism() {
+        let text = synthetic_text(&mut Rng::new(7), 200);
+        assert!((200..230).contains(&text.len()), "{}", text.len());
+        assert!(text.ends_with('.'));
+        assert_eq!(text, synthetic_text(&mut Rng::new(7), 200));
+    }
+
+    #[test]
+    fn test_arrival_offsets() {
+        let uniform = arrival_offsets(Arrival::Uniform, 4.0, 3, &mut Rng::new(1));
+        assert_eq!(uniform, [0, 250, 500].map(Duration::from_millis).to_vec());
+
+        let poisson = arrival_offsets(Arrival::Poisson, 10.0, 2000, &mut Rng::new(1));
+        let mean_gap = poisson.last().unwrap().as_secs_f64() / 1999.0;
+        assert!((mean_gap - 0.1).abs() < 0.01, "{}", mean_gap);
+    }
+}
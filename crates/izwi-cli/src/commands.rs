@@ -0,0 +1,452 @@
+//! Subcommand implementations
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::{Args, Subcommand, ValueEnum};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use izwi_core::audio::{AudioEncoder, AudioFormat};
+use izwi_core::engine::{BenchmarkResult, MetricsCollector};
+use izwi_core::inference::GenerationRequest;
+use izwi_core::model::Quantization;
+use izwi_core::{GenerationConfig, InferenceEngine, ModelVariant};
+
+/// Path argument meaning stdin/stdout
+const STDIO: &str = "-";
+
+/// Output audio encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// WAV container, 16-bit PCM
+    Wav,
+    /// Headerless 16-bit little-endian PCM
+    Pcm16,
+    /// Headerless 32-bit float PCM
+    F32,
+}
+
+impl OutputFormat {
+    /// Guess the format from a file extension, defaulting to WAV
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("pcm") | Some("raw") => Self::Pcm16,
+            Some("f32") => Self::F32,
+            _ => Self::Wav,
+        }
+    }
+}
+
+impl From<OutputFormat> for AudioFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Wav => AudioFormat::Wav,
+            OutputFormat::Pcm16 => AudioFormat::RawI16,
+            OutputFormat::F32 => AudioFormat::RawF32,
+        }
+    }
+}
+
+/// Arguments for `tts`
+#[derive(Args, Debug)]
+pub struct TtsArgs {
+    /// Text to synthesize; read from stdin when omitted or `-`
+    text: Option<String>,
+
+    /// Model to use
+    #[arg(short, long, value_parser = parse_variant, default_value = "Qwen3-TTS-12Hz-0.6B-Base")]
+    model: ModelVariant,
+
+    /// Voice name
+    #[arg(short, long)]
+    speaker: Option<String>,
+
+    /// Sampling temperature
+    #[arg(long, default_value_t = 0.7)]
+    temperature: f32,
+
+    /// Speed factor
+    #[arg(long, default_value_t = 1.0)]
+    speed: f32,
+
+    /// Load a quantized copy of the model (`q8` or `q4`)
+    #[arg(short, long)]
+    quantization: Option<Quantization>,
+
+    /// Output file, or `-` for stdout
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Output encoding (inferred from the file extension by default)
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+}
+
+/// Arguments for `asr`
+#[derive(Args, Debug)]
+pub struct AsrArgs {
+    /// Audio file to transcribe, or `-` for stdin
+    input: PathBuf,
+
+    /// Model to use
+    #[arg(short, long, value_parser = parse_variant, default_value = "Qwen3-ASR-0.6B")]
+    model: ModelVariant,
+
+    /// Language hint (auto-detected when omitted)
+    #[arg(short, long)]
+    language: Option<String>,
+
+    /// Print the result as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// `models` subcommands
+#[derive(Subcommand, Debug)]
+pub enum ModelsCommand {
+    /// List models and their local status
+    List,
+    /// Download a model from HuggingFace
+    Download {
+        #[arg(value_parser = parse_variant)]
+        model: ModelVariant,
+    },
+    /// Verify checksums and re-download missing or corrupt files
+    Repair {
+        #[arg(value_parser = parse_variant)]
+        model: ModelVariant,
+    },
+    /// Write a quantized copy of a downloaded model
+    Quantize {
+        #[arg(value_parser = parse_variant)]
+        model: ModelVariant,
+        /// Target precision: `q8` or `q4`
+        #[arg(long, default_value = "q8")]
+        to: Quantization,
+    },
+    /// Delete a downloaded model
+    Delete {
+        #[arg(value_parser = parse_variant)]
+        model: ModelVariant,
+    },
+}
+
+/// Arguments for `bench`
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Model to use
+    #[arg(short, long, value_parser = parse_variant, default_value = "Qwen3-TTS-12Hz-0.6B-Base")]
+    model: ModelVariant,
+
+    /// Text synthesized on each iteration
+    #[arg(
+        long,
+        default_value = "The quick brown fox jumps over the lazy dog. How vexingly quick daft zebras jump."
+    )]
+    text: String,
+
+    /// Voice name
+    #[arg(short, long)]
+    speaker: Option<String>,
+
+    /// Measured iterations
+    #[arg(short = 'n', long, default_value_t = 5)]
+    iterations: u64,
+
+    /// Unmeasured iterations run first
+    #[arg(long, default_value_t = 1)]
+    warmup: u64,
+
+    /// Print the result as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Parse a model name such as `Qwen3-TTS-12Hz-0.6B-Base` or its HuggingFace repo ID
+pub fn parse_variant(s: &str) -> std::result::Result<ModelVariant, String> {
+    ModelVariant::all()
+        .iter()
+        .copied()
+        .find(|v| v.dir_name().eq_ignore_ascii_case(s) || v.repo_id().eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("unknown model '{}' (see `izwi-cli models list`)", s))
+}
+
+/// Read text from the argument, or stdin when absent or `-`
+fn read_text(text: Option<&str>) -> Result<String> {
+    let text = match text {
+        Some(text) if text != STDIO => text.to_string(),
+        _ => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .context("failed to read text from stdin")?;
+            buf
+        }
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("no text to synthesize");
+    }
+    Ok(text.to_string())
+}
+
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    if path.as_os_str() == STDIO {
+        let mut buf = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .context("failed to read audio from stdin")?;
+        return Ok(buf);
+    }
+    std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    if path.as_os_str() == STDIO {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(bytes)?;
+        return Ok(stdout.flush()?);
+    }
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn generation_request(
+    text: &str,
+    speaker: Option<&str>,
+    config: GenerationConfig,
+) -> GenerationRequest {
+    let request = GenerationRequest::new(text).with_config(config);
+    match speaker {
+        Some(speaker) => request.with_speaker(speaker),
+        None => request,
+    }
+}
+
+/// Synthesize text to an audio file
+pub async fn tts(engine: &InferenceEngine, args: TtsArgs) -> Result<()> {
+    let text = read_text(args.text.as_deref())?;
+    let format = args
+        .format
+        .unwrap_or_else(|| OutputFormat::from_path(&args.output));
+
+    match args.quantization {
+        Some(quant) => engine.load_model_with(args.model, quant).await?,
+        None => engine.load_model(args.model).await?,
+    }
+
+    let config = GenerationConfig {
+        temperature: args.temperature,
+        speed: args.speed,
+        ..Default::default()
+    };
+    let request = generation_request(&text, args.speaker.as_deref(), config);
+    let result = engine.generate(request).await?;
+    for warning in &result.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    let bytes = AudioEncoder::new(result.sample_rate, 1).encode(&result.samples, format.into())?;
+    write_output(&args.output, &bytes)?;
+
+    eprintln!(
+        "Wrote {:.2}s of audio to {} in {:.0}ms (RTF {:.3})",
+        result.duration_secs(),
+        args.output.display(),
+        result.total_time_ms,
+        result.rtf()
+    );
+    Ok(())
+}
+
+/// Transcribe an audio file and print the text
+pub async fn asr(engine: &InferenceEngine, args: AsrArgs) -> Result<()> {
+    if !args.model.is_asr() {
+        bail!("{} is not an ASR model", args.model);
+    }
+    let audio = read_input(&args.input)?;
+
+    // Prefer the local copy, letting the daemon fetch from the Hub otherwise
+    let model_id = engine
+        .model_manager()
+        .get_model_info(args.model)
+        .await
+        .and_then(|info| info.local_path)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| args.model.repo_id().to_string());
+
+    let start = Instant::now();
+    let response = engine
+        .asr_transcribe(
+            &BASE64.encode(&audio),
+            Some(&model_id),
+            args.language.as_deref(),
+        )
+        .await?;
+    if let Some(error) = response.error {
+        bail!("transcription failed: {}", error);
+    }
+    let transcription = response.transcription.unwrap_or_default();
+
+    if args.json {
+        let output = serde_json::json!({
+            "transcription": transcription,
+            "language": response.language,
+            "processing_time_ms": start.elapsed().as_secs_f64() * 1000.0,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("{}", transcription);
+    }
+    Ok(())
+}
+
+/// Run a `models` subcommand
+pub async fn models(engine: &InferenceEngine, command: ModelsCommand) -> Result<()> {
+    match command {
+        ModelsCommand::List => {
+            let mut models = engine.list_models().await;
+            models.sort_by_key(|m| m.variant.dir_name());
+            println!("{:<36} {:<16} {:>10}  QUANTIZED", "MODEL", "STATUS", "SIZE");
+            for model in models {
+                let status = serde_json::to_value(model.status)?;
+                let size = model
+                    .size_bytes
+                    .unwrap_or_else(|| model.variant.estimated_size());
+                let quantized: Vec<&str> = model
+                    .quantized_variants
+                    .iter()
+                    .map(|q| q.as_str())
+                    .collect();
+                println!(
+                    "{:<36} {:<16} {:>10}  {}",
+                    model.variant.dir_name(),
+                    status.as_str().unwrap_or_default(),
+                    format_bytes(size),
+                    quantized.join(",")
+                );
+            }
+        }
+        ModelsCommand::Download { model } => {
+            eprintln!("Downloading {} from {}", model, model.repo_id());
+            let path = engine.model_manager().download_model(model).await?;
+            eprintln!("Downloaded {} to {}", model, path.display());
+        }
+        ModelsCommand::Repair { model } => {
+            let report = engine.model_manager().repair_model(model).await?;
+            eprintln!(
+                "{}: {} verified, {} unchecked, {} repaired, {} failed",
+                model,
+                report.verified.len(),
+                report.unchecked.len(),
+                report.repaired.len(),
+                report.failed.len()
+            );
+            if !report.is_complete() {
+                bail!("could not repair: {}", report.failed.join(", "));
+            }
+        }
+        ModelsCommand::Quantize { model, to } => {
+            eprintln!("Quantizing {} to {}", model, to);
+            let report = engine.quantize_model(model, to).await?;
+            eprintln!(
+                "{} tensors quantized, {} kept: {} -> {}",
+                report.quantized_tensors,
+                report.kept_tensors,
+                format_bytes(report.source_bytes),
+                format_bytes(report.output_bytes)
+            );
+        }
+        ModelsCommand::Delete { model } => {
+            engine.model_manager().delete_model(model).await?;
+            eprintln!("Deleted {}", model);
+        }
+    }
+    Ok(())
+}
+
+/// Benchmark repeated generations of the same text
+pub async fn bench(engine: &InferenceEngine, args: BenchArgs) -> Result<()> {
+    if args.iterations == 0 {
+        bail!("--iterations must be at least 1");
+    }
+    engine.load_model(args.model).await?;
+
+    for _ in 0..args.warmup {
+        let request = generation_request(&args.text, args.speaker.as_deref(), Default::default());
+        engine.generate(request).await?;
+    }
+
+    let metrics = Arc::new(MetricsCollector::new());
+    let start = Instant::now();
+    for i in 0..args.iterations {
+        let request = generation_request(&args.text, args.speaker.as_deref(), Default::default());
+        let result = engine
+            .generate(request)
+            .await
+            .map_err(|e| anyhow!("iteration {} failed: {}", i + 1, e))?;
+        metrics
+            .record_request(
+                Duration::from_secs_f32(result.total_time_ms / 1000.0),
+                result.total_tokens as u64,
+                Duration::from_secs_f32(result.duration_secs()),
+            )
+            .await;
+    }
+
+    let result = BenchmarkResult::new(
+        args.model.dir_name(),
+        args.iterations,
+        start.elapsed(),
+        metrics.snapshot().await,
+    );
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        println!("{}", result.summary());
+    }
+    Ok(())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1_000_000_000.0;
+    const MB: f64 = 1_000_000.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else {
+        format!("{:.0} MB", bytes / MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variant() {
+        assert_eq!(
+            parse_variant("qwen3-asr-0.6b"),
+            Ok(ModelVariant::Qwen3Asr06B)
+        );
+        assert_eq!(
+            parse_variant("Qwen/Qwen3-TTS-12Hz-1.7B-VoiceDesign"),
+            Ok(ModelVariant::Qwen3Tts12Hz17BVoiceDesign)
+        );
+        assert!(parse_variant("whisper").is_err());
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.wav")),
+            OutputFormat::Wav
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a.pcm")),
+            OutputFormat::Pcm16
+        );
+        assert_eq!(OutputFormat::from_path(Path::new("-")), OutputFormat::Wav);
+    }
+}
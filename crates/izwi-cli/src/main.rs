@@ -0,0 +1,89 @@
+//! Izwi CLI - local TTS/ASR and model management without the server
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+mod commands;
+
+use commands::{AsrArgs, BenchArgs, ModelsCommand, TtsArgs};
+use izwi_core::{ConfigLoader, InferenceEngine};
+
+/// Izwi command-line interface
+#[derive(Parser, Debug)]
+#[command(
+    name = "izwi-cli",
+    version,
+    about = "Run Izwi TTS/ASR locally without the server"
+)]
+struct Cli {
+    /// Configuration file (TOML, YAML or JSON)
+    #[arg(short, long, env = "IZWI_CONFIG_PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// Directory to store downloaded models
+    #[arg(long, global = true)]
+    models_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Synthesize speech from text
+    Tts(TtsArgs),
+    /// Transcribe an audio file
+    Asr(AsrArgs),
+    /// Manage local models
+    #[command(subcommand)]
+    Models(ModelsCommand),
+    /// Measure generation latency and real-time factor
+    Bench(BenchArgs),
+}
+
+impl Cli {
+    /// Layer file, environment and flag settings
+    fn loader(&self) -> ConfigLoader {
+        let mut loader = ConfigLoader::new();
+        if let Some(path) = &self.config {
+            loader = loader.with_file(path);
+        }
+        if let Some(models_dir) = &self.models_dir {
+            loader = loader.set_override("engine.models_dir", models_dir.display());
+        }
+        loader
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    // Log to stderr so audio and transcripts can be piped from stdout
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "izwi_core=warn".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    // The engine holds a blocking HTTP client, so it is created and dropped
+    // outside the async runtime
+    let config = cli.loader().load()?.engine;
+    let engine = InferenceEngine::new(config)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(async {
+        match cli.command {
+            Command::Tts(args) => commands::tts(&engine, args).await,
+            Command::Asr(args) => commands::asr(&engine, args).await,
+            Command::Models(command) => commands::models(&engine, command).await,
+            Command::Bench(args) => commands::bench(&engine, args).await,
+        }
+    });
+    drop(runtime);
+
+    let _ = engine.stop_all_daemons();
+    result
+}
@@ -0,0 +1,100 @@
+//! Per-chunk post-processing kernels against their scalar versions
+//!
+//! Run with `cargo bench -p izwi-core --bench postprocess`.
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use izwi_core::audio::{simd, Resampler};
+
+/// One 100 ms streaming chunk at 24 kHz, and a full 10 s utterance
+const SIZES: [usize; 2] = [2_400, 240_000];
+
+/// Speech-level test tone, below the limiter knee
+fn signal(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (i as f32 * 0.05).sin() * 0.2 + (i as f32 * 0.013).sin() * 0.1)
+        .collect()
+}
+
+fn bench_gain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_gain");
+    for len in SIZES {
+        let input = signal(len);
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("simd", len), &input, |b, input| {
+            b.iter_batched_ref(
+                || input.clone(),
+                |samples| simd::apply_gain(samples, 1.2, 1e-6, 0.9),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", len), &input, |b, input| {
+            b.iter_batched_ref(
+                || input.clone(),
+                |samples| simd::scalar::apply_gain(samples, 1.2, 1e-6, 0.9),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_crossfade(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crossfade");
+    for len in SIZES {
+        let tail = signal(len);
+        let next = signal(len);
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("simd", len), &tail, |b, tail| {
+            b.iter_batched_ref(
+                || tail.clone(),
+                |samples| simd::crossfade(samples, black_box(&next)),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", len), &tail, |b, tail| {
+            b.iter_batched_ref(
+                || tail.clone(),
+                |samples| simd::scalar::crossfade(samples, black_box(&next)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_i16(c: &mut Criterion) {
+    let mut group = c.benchmark_group("f32_to_i16");
+    for len in SIZES {
+        let input = signal(len);
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("simd", len), &input, |b, input| {
+            b.iter(|| simd::f32_to_i16(black_box(input)))
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", len), &input, |b, input| {
+            b.iter(|| simd::scalar::f32_to_i16(black_box(input)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_resample(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resample_24k_to_16k");
+    let input = signal(SIZES[0]);
+    group.throughput(Throughput::Elements(input.len() as u64));
+    group.bench_function("chunk", |b| {
+        let mut resampler = Resampler::new(24_000, 16_000).unwrap();
+        b.iter(|| resampler.process(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_gain,
+    bench_crossfade,
+    bench_i16,
+    bench_resample
+);
+criterion_main!(benches);
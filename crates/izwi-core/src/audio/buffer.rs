@@ -0,0 +1,137 @@
+//! Reference-counted sample buffers
+
+use bytes::Bytes;
+use std::fmt;
+use std::ops::{Bound, Deref, RangeBounds};
+
+const SAMPLE_BYTES: usize = std::mem::size_of::<f32>();
+
+/// Mono f32 samples shared between the stages of the audio path.
+///
+/// Clones and slices share one allocation, so a chunk can be handed from the
+/// generator to the streaming channel and on to the encoder without copying.
+/// The samples are held as native-endian bytes in a [`Bytes`], which raw f32
+/// output on little-endian targets sends as is.
+#[derive(Clone, Default, PartialEq)]
+pub struct SampleBuffer {
+    bytes: Bytes,
+}
+
+/// Owner of the allocation behind a [`SampleBuffer`]
+struct Samples(Vec<f32>);
+
+impl AsRef<[u8]> for Samples {
+    fn as_ref(&self) -> &[u8] {
+        // SAFETY: f32 has no padding and u8 has no alignment requirement
+        unsafe {
+            std::slice::from_raw_parts(self.0.as_ptr().cast::<u8>(), self.0.len() * SAMPLE_BYTES)
+        }
+    }
+}
+
+impl SampleBuffer {
+    /// Number of samples
+    pub fn len(&self) -> usize {
+        self.bytes.len() / SAMPLE_BYTES
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The samples
+    pub fn as_slice(&self) -> &[f32] {
+        if self.bytes.is_empty() {
+            return &[];
+        }
+        // SAFETY: the bytes come from a `Vec<f32>` and are only sliced on
+        // sample boundaries, so they are aligned, initialized f32 values
+        unsafe { std::slice::from_raw_parts(self.bytes.as_ptr().cast::<f32>(), self.len()) }
+    }
+
+    /// Samples in `range` (sample indices), sharing this buffer's allocation
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        Self {
+            bytes: self.bytes.slice(start * SAMPLE_BYTES..end * SAMPLE_BYTES),
+        }
+    }
+
+    /// The samples as native-endian bytes
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+}
+
+impl From<Vec<f32>> for SampleBuffer {
+    fn from(samples: Vec<f32>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        Self {
+            bytes: Bytes::from_owner(Samples(samples)),
+        }
+    }
+}
+
+impl From<&[f32]> for SampleBuffer {
+    fn from(samples: &[f32]) -> Self {
+        samples.to_vec().into()
+    }
+}
+
+impl From<SampleBuffer> for Vec<f32> {
+    fn from(buffer: SampleBuffer) -> Self {
+        buffer.as_slice().to_vec()
+    }
+}
+
+impl Deref for SampleBuffer {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        self.as_slice()
+    }
+}
+
+impl AsRef<[f32]> for SampleBuffer {
+    fn as_ref(&self) -> &[f32] {
+        self.as_slice()
+    }
+}
+
+impl fmt::Debug for SampleBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SampleBuffer")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slices_share_allocation() {
+        let buffer = SampleBuffer::from(vec![0.0, 0.25, 0.5, 0.75]);
+        let tail = buffer.slice(2..);
+        assert_eq!(&*tail, &[0.5, 0.75]);
+        assert_eq!(tail.as_bytes().as_ptr(), buffer.as_bytes()[8..].as_ptr());
+        assert_eq!(buffer.clone(), buffer);
+
+        let empty = buffer.slice(4..);
+        assert!(empty.is_empty());
+        assert_eq!(empty.as_slice(), &[] as &[f32]);
+        assert_eq!(Vec::from(buffer.slice(..=1)), vec![0.0, 0.25]);
+    }
+}
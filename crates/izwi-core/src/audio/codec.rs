@@ -1,13 +1,23 @@
 //! Audio codec for Qwen3-TTS (12Hz tokenizer)
 //!
 //! The Qwen3-TTS-Tokenizer-12Hz uses a 16-layer multi-codebook design
-//! operating at 12.5Hz with a lightweight causal ConvNet decoder.
+//! operating at 12.5Hz with a lightweight causal ConvNet decoder. The encoder
+//! direction (waveform to tokens) lives in `codec_encoder`.
 
+use std::collections::HashMap;
 use std::path::Path;
 use tracing::{debug, info};
 
-use crate::error::Result;
-use crate::model::weights::ModelWeights;
+use super::codec_encoder::EncoderWeights;
+use super::conv::{CausalConv1d, ConvBackend, ConvState};
+use super::resample::resample;
+use crate::device::Device;
+use crate::error::{Error, Result};
+use crate::model::layout::{self, LayoutOp, PACKED_SUFFIX};
+use crate::model::weights::{ModelWeights, TensorData};
+
+/// Audio tokens per second of the 12Hz tokenizer
+pub const TOKEN_RATE_HZ: f32 = 12.5;
 
 /// Configuration for the audio codec
 #[derive(Debug, Clone)]
@@ -27,7 +37,7 @@ impl Default for CodecConfig {
         Self {
             sample_rate: 24000,
             num_codebooks: 16,
-            token_rate_hz: 12.5,
+            token_rate_hz: TOKEN_RATE_HZ,
             channels: 1,
         }
     }
@@ -44,15 +54,39 @@ impl CodecConfig {
 pub struct AudioCodec {
     config: CodecConfig,
     decoder_weights: Option<DecoderWeights>,
+    encoder_weights: Option<EncoderWeights>,
+    backend: ConvBackend,
+    /// Device the decoder convolutions run on
+    device: Device,
+    /// Cache conv weights packed for the CPU kernel next to the codec files
+    layout_cache: bool,
+}
+
+/// Per-request state of a streaming decode.
+///
+/// Holds each conv layer's recent inputs so every new token column is decoded
+/// in constant time, without re-running the history.
+#[derive(Debug, Clone)]
+pub struct DecoderState {
+    layers: Vec<ConvState>,
+    /// Token columns decoded so far
+    frames: usize,
+}
+
+impl DecoderState {
+    /// Number of token columns decoded so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
 }
 
 /// Decoder network weights
 struct DecoderWeights {
-    /// Embedding for each codebook
+    /// Embedding table of each codebook, `[vocab][hidden]`
     codebook_embeddings: Vec<Vec<f32>>,
     /// Causal ConvNet layers for the decoder
-    conv_layers: Vec<ConvLayer>,
-    /// Final projection to audio samples
+    conv_layers: Vec<CausalConv1d>,
+    /// Projection from a hidden frame to one token's samples, `[samples][hidden]`
     output_proj_weight: Vec<f32>,
     output_proj_bias: Vec<f32>,
     /// Hidden dimension
@@ -61,74 +95,170 @@ struct DecoderWeights {
     vocab_size: usize,
 }
 
-/// A causal 1D convolution layer
-struct ConvLayer {
-    weight: Vec<f32>,
-    bias: Vec<f32>,
-    kernel_size: usize,
-    in_channels: usize,
-    out_channels: usize,
-}
+impl DecoderWeights {
+    /// Load the decoder from `codec_decoder.safetensors`.
+    ///
+    /// Expected tensors: `codebooks.{i}.weight` `[vocab, hidden]`,
+    /// `layers.{i}.weight` `[out, in, kernel]` with `layers.{i}.bias`, and
+    /// `output.weight` `[samples_per_token, hidden]` with `output.bias`.
+    fn load(path: &Path, samples_per_token: usize, layout_cache: bool) -> Result<Self> {
+        let tensors = load_tensors(path, layout_cache)?;
+        let codebooks = load_codebooks(&tensors)?;
+        let hidden_dim = codebooks.hidden_dim;
+        let conv_layers = load_conv_layers(&tensors, hidden_dim, hidden_dim)?;
+
+        let output = tensor(&tensors, "output.weight")?;
+        if output.shape[..] != [samples_per_token, hidden_dim] {
+            return Err(bad_shape(output));
+        }
 
-impl ConvLayer {
-    /// Apply causal conv1d: output[t] only depends on input[0..=t]
-    fn forward(&self, input: &[f32], seq_len: usize) -> Vec<f32> {
-        let mut output = vec![0.0f32; self.out_channels * seq_len];
-
-        // Causal convolution: pad on the left
-        let padding = self.kernel_size - 1;
-
-        for t in 0..seq_len {
-            for out_c in 0..self.out_channels {
-                let mut sum = self.bias[out_c];
-
-                for k in 0..self.kernel_size {
-                    let input_t = t as isize - (padding as isize - k as isize);
-                    if input_t >= 0 && (input_t as usize) < seq_len {
-                        for in_c in 0..self.in_channels {
-                            let weight_idx = out_c * self.in_channels * self.kernel_size
-                                + in_c * self.kernel_size
-                                + k;
-                            let input_idx = (input_t as usize) * self.in_channels + in_c;
-                            sum += self.weight[weight_idx] * input[input_idx];
-                        }
-                    }
-                }
+        Ok(Self {
+            codebook_embeddings: codebooks.embeddings,
+            conv_layers,
+            output_proj_weight: output.to_f32()?,
+            output_proj_bias: tensor(&tensors, "output.bias")?.to_f32()?,
+            hidden_dim,
+            vocab_size: codebooks.vocab_size,
+        })
+    }
 
-                output[t * self.out_channels + out_c] = sum;
+    /// Sum the codebook embeddings of the first `len` frames, `[time][hidden]`
+    fn embed(&self, tokens: &[Vec<u32>], len: usize) -> Vec<f32> {
+        let mut hidden = vec![0.0f32; len * self.hidden_dim];
+        for (t, frame) in hidden.chunks_mut(self.hidden_dim).enumerate() {
+            self.embed_frame(tokens, t, frame);
+        }
+        hidden
+    }
+
+    /// Sum the codebook embeddings of token column `t` into `frame`
+    fn embed_frame(&self, tokens: &[Vec<u32>], t: usize, frame: &mut [f32]) {
+        for (codebook, embeddings) in tokens.iter().zip(&self.codebook_embeddings) {
+            let Some(&token) = codebook.get(t) else {
+                continue;
+            };
+            let offset = (token as usize).min(self.vocab_size - 1) * self.hidden_dim;
+            let row = &embeddings[offset..offset + self.hidden_dim];
+            for (h, value) in frame.iter_mut().zip(row) {
+                *h += value;
             }
         }
+    }
 
-        output
+    /// Project hidden frame `t` to its audio samples
+    fn project(&self, hidden: &[f32], t: usize, output: &mut [f32]) {
+        let frame = &hidden[t * self.hidden_dim..(t + 1) * self.hidden_dim];
+        for (s, sample) in output.iter_mut().enumerate() {
+            let row = &self.output_proj_weight[s * self.hidden_dim..(s + 1) * self.hidden_dim];
+            let value: f32 = row.iter().zip(frame).map(|(w, h)| w * h).sum();
+            let bias = self.output_proj_bias.get(s).copied().unwrap_or(0.0);
+            *sample = (value + bias).clamp(-1.0, 1.0);
+        }
     }
+}
 
-    /// Apply causal conv1d for a single timestep (incremental)
-    fn forward_incremental(&self, input_history: &[f32], current_t: usize) -> Vec<f32> {
-        let mut output = vec![0.0f32; self.out_channels];
-        let padding = self.kernel_size - 1;
-        let history_len = input_history.len() / self.in_channels;
+/// Codebook embedding tables shared by the encoder and decoder
+pub(super) struct Codebooks {
+    /// `[vocab][hidden]` per codebook
+    pub embeddings: Vec<Vec<f32>>,
+    pub vocab_size: usize,
+    pub hidden_dim: usize,
+}
 
-        for out_c in 0..self.out_channels {
-            let mut sum = self.bias[out_c];
+/// Map a codec weights file, with conv weights packed for the CPU kernel
+/// when `layout_cache` is set
+pub(super) fn load_tensors(path: &Path, layout_cache: bool) -> Result<HashMap<String, TensorData>> {
+    let (mut tensors, _) = ModelWeights::load_safetensors(path)?;
+    if layout_cache {
+        layout::apply(
+            &mut tensors,
+            &[path.to_path_buf()],
+            &layout::cache_path(path),
+            &[LayoutOp::PackConv],
+        )?;
+    }
+    Ok(tensors)
+}
 
-            for k in 0..self.kernel_size {
-                let input_t = current_t as isize - (padding as isize - k as isize);
-                if input_t >= 0 && (input_t as usize) < history_len {
-                    for in_c in 0..self.in_channels {
-                        let weight_idx = out_c * self.in_channels * self.kernel_size
-                            + in_c * self.kernel_size
-                            + k;
-                        let input_idx = (input_t as usize) * self.in_channels + in_c;
-                        sum += self.weight[weight_idx] * input_history[input_idx];
-                    }
-                }
-            }
+pub(super) fn tensor<'a>(
+    tensors: &'a HashMap<String, TensorData>,
+    name: &str,
+) -> Result<&'a TensorData> {
+    tensors
+        .get(name)
+        .ok_or_else(|| Error::ModelLoadError(format!("Codec is missing tensor {}", name)))
+}
+
+pub(super) fn bad_shape(tensor: &TensorData) -> Error {
+    Error::ModelLoadError(format!(
+        "Codec tensor {} has unexpected shape {:?}",
+        tensor.name, tensor.shape
+    ))
+}
 
-            output[out_c] = sum;
+/// Load `codebooks.{i}.weight` tables `[vocab, hidden]`, which must all match
+pub(super) fn load_codebooks(tensors: &HashMap<String, TensorData>) -> Result<Codebooks> {
+    let mut embeddings = Vec::new();
+    let (mut vocab_size, mut hidden_dim) = (0, 0);
+    while let Some(embedding) = tensors.get(&format!("codebooks.{}.weight", embeddings.len())) {
+        let [vocab, hidden] = embedding.shape[..] else {
+            return Err(bad_shape(embedding));
+        };
+        if !embeddings.is_empty() && (vocab, hidden) != (vocab_size, hidden_dim) {
+            return Err(bad_shape(embedding));
         }
+        (vocab_size, hidden_dim) = (vocab, hidden);
+        embeddings.push(embedding.to_f32()?);
+    }
+    if embeddings.is_empty() {
+        return Err(Error::ModelLoadError(
+            "Codec has no codebook embeddings".to_string(),
+        ));
+    }
+    Ok(Codebooks {
+        embeddings,
+        vocab_size,
+        hidden_dim,
+    })
+}
 
-        output
+/// Load the `layers.{i}` causal convolutions, which must map `in_channels`
+/// to `out_channels`
+pub(super) fn load_conv_layers(
+    tensors: &HashMap<String, TensorData>,
+    in_channels: usize,
+    out_channels: usize,
+) -> Result<Vec<CausalConv1d>> {
+    let mut layers = Vec::new();
+    let mut channels = in_channels;
+    while let Some(weight) = tensors.get(&format!("layers.{}.weight", layers.len())) {
+        let [layer_out, layer_in, kernel_size] = weight.shape[..] else {
+            return Err(bad_shape(weight));
+        };
+        if layer_in != channels || kernel_size == 0 {
+            return Err(bad_shape(weight));
+        }
+        let bias = tensor(tensors, &format!("layers.{}.bias", layers.len()))?.to_f32()?;
+        let packed = tensors.get(&format!("{}{}", weight.name, PACKED_SUFFIX));
+        let layer = match packed {
+            Some(packed) if packed.shape[..] == [layer_out, kernel_size, layer_in] => {
+                CausalConv1d::from_packed(packed.to_f32()?, bias, layer_out, layer_in, kernel_size)
+            }
+            Some(packed) => return Err(bad_shape(packed)),
+            None => {
+                CausalConv1d::from_torch(&weight.to_f32()?, bias, layer_out, layer_in, kernel_size)
+            }
+        };
+        layers.push(layer);
+        channels = layer_out;
     }
+    if channels != out_channels {
+        return Err(Error::ModelLoadError(format!(
+            "Codec layers end with {} channels, expected {}",
+            channels, out_channels
+        )));
+    }
+    Ok(layers)
 }
 
 /// Apply GELU activation function
@@ -137,7 +267,7 @@ fn gelu(x: f32) -> f32 {
 }
 
 /// Apply GELU activation to a slice in-place
-fn apply_gelu(data: &mut [f32]) {
+pub(super) fn apply_gelu(data: &mut [f32]) {
     for x in data.iter_mut() {
         *x = gelu(*x);
     }
@@ -146,10 +276,7 @@ fn apply_gelu(data: &mut [f32]) {
 impl AudioCodec {
     /// Create a new codec with default configuration
     pub fn new() -> Self {
-        Self {
-            config: CodecConfig::default(),
-            decoder_weights: None,
-        }
+        Self::with_config(CodecConfig::default())
     }
 
     /// Create codec with custom configuration
@@ -157,9 +284,27 @@ impl AudioCodec {
         Self {
             config,
             decoder_weights: None,
+            encoder_weights: None,
+            backend: ConvBackend::Cpu,
+            device: Device::Cpu,
+            layout_cache: false,
         }
     }
 
+    /// Run the decoder on `device` (Metal or CUDA) when weights are loaded,
+    /// falling back to the CPU when the device is unavailable
+    pub fn with_device(mut self, device: Device) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Cache conv weights in the CPU kernel's layout next to the codec
+    /// files, so later loads skip the reordering
+    pub fn with_layout_cache(mut self, layout_cache: bool) -> Self {
+        self.layout_cache = layout_cache;
+        self
+    }
+
     /// Load codec weights from a tokenizer model directory
     pub fn load_weights(&mut self, model_dir: &Path) -> Result<()> {
         info!("Loading audio codec from {:?}", model_dir);
@@ -168,17 +313,72 @@ impl AudioCodec {
         let decoder_path = model_dir.join("codec_decoder.safetensors");
 
         if decoder_path.exists() {
-            let weights = ModelWeights::load(model_dir)?;
-            // Extract decoder-specific weights
-            // Note: Actual weight names depend on the model structure
-            debug!("Codec weights loaded: {} tensors", weights.tensors.len());
+            let weights = DecoderWeights::load(
+                &decoder_path,
+                self.config.samples_per_token(),
+                self.layout_cache,
+            )?;
+            self.backend = ConvBackend::new(self.device, &weights.conv_layers);
+            info!(
+                "Codec decoder loaded: {} codebooks, {} layers, hidden {} ({})",
+                weights.codebook_embeddings.len(),
+                weights.conv_layers.len(),
+                weights.hidden_dim,
+                self.backend.name()
+            );
+            self.decoder_weights = Some(weights);
         } else {
             info!("No codec weights found, using placeholder decoder");
         }
 
+        let encoder_path = model_dir.join("codec_encoder.safetensors");
+        if encoder_path.exists() {
+            let weights = EncoderWeights::load(
+                &encoder_path,
+                self.config.sample_rate,
+                self.config.samples_per_token(),
+                self.layout_cache,
+            )?;
+            info!(
+                "Codec encoder loaded: {} codebooks, {} mel bins",
+                weights.num_codebooks(),
+                weights.num_mels()
+            );
+            self.encoder_weights = Some(weights);
+        }
+
         Ok(())
     }
 
+    /// Whether real decoder weights are loaded
+    pub fn has_weights(&self) -> bool {
+        self.decoder_weights.is_some()
+    }
+
+    /// Whether encoder weights are loaded, so audio can be tokenized natively
+    pub fn has_encoder(&self) -> bool {
+        self.encoder_weights.is_some()
+    }
+
+    /// Encode a mono waveform at `sample_rate` to audio tokens
+    ///
+    /// Output: tokens of shape [num_codebooks, sequence_length], one column
+    /// per `samples_per_token` samples at the codec rate (the last one padded)
+    pub fn encode(&self, samples: &[f32], sample_rate: u32) -> Result<Vec<Vec<u32>>> {
+        let weights = self
+            .encoder_weights
+            .as_ref()
+            .ok_or_else(|| Error::InferenceError("No codec encoder weights loaded".to_string()))?;
+        let samples = resample(samples, sample_rate, self.config.sample_rate)?;
+        let tokens = weights.encode(&samples);
+        debug!(
+            "Encoded {} samples to {} token columns",
+            samples.len(),
+            tokens.first().map_or(0, Vec::len)
+        );
+        Ok(tokens)
+    }
+
     /// Decode audio tokens to waveform
     ///
     /// Input: Audio tokens of shape [num_codebooks, sequence_length]
@@ -199,13 +399,9 @@ impl AudioCodec {
         // Calculate output length
         let samples_per_token = self.config.samples_per_token();
         let output_length = sequence_length * samples_per_token;
-
-        // Placeholder: Generate silence or simple waveform
-        // In a real implementation, this would run the ConvNet decoder
         let mut output = vec![0.0f32; output_length];
 
         if self.decoder_weights.is_some() {
-            // Run actual decoder network
             self.run_decoder(tokens, &mut output)?;
         } else {
             // Placeholder: generate simple tone based on token values
@@ -215,178 +411,95 @@ impl AudioCodec {
         Ok(output)
     }
 
-    /// Decode a single chunk of audio tokens (for streaming)
-    pub fn decode_chunk(&self, tokens: &[Vec<u32>], chunk_idx: usize) -> Result<Vec<f32>> {
-        // For streaming, we process one token column at a time
+    /// Fresh state for streaming a request through [`Self::decode_chunk`]
+    pub fn decoder_state(&self) -> DecoderState {
+        let layers = self
+            .decoder_weights
+            .as_ref()
+            .map(|w| w.conv_layers.iter().map(ConvState::new).collect())
+            .unwrap_or_default();
+        DecoderState { layers, frames: 0 }
+    }
+
+    /// Decode the token columns `state` has not seen yet (for streaming).
+    ///
+    /// `tokens` is the request's full token history; only columns after
+    /// `state.frames()` are decoded, each in constant time.
+    pub fn decode_chunk(&self, state: &mut DecoderState, tokens: &[Vec<u32>]) -> Result<Vec<f32>> {
+        let seq_len = tokens.first().map_or(0, Vec::len);
         let samples_per_token = self.config.samples_per_token();
-        let mut chunk = vec![0.0f32; samples_per_token];
+        let new_frames = seq_len.saturating_sub(state.frames);
+        let mut chunk = vec![0.0f32; new_frames * samples_per_token];
 
-        if self.decoder_weights.is_some() {
-            // Run incremental decoder
-            self.run_decoder_incremental(tokens, chunk_idx, &mut chunk)?;
-        } else {
-            // Placeholder decode
-            self.placeholder_decode_chunk(tokens, chunk_idx, &mut chunk);
+        for frame in chunk.chunks_mut(samples_per_token) {
+            if self.decoder_weights.is_some() {
+                self.run_decoder_incremental(state, tokens, frame)?;
+            } else {
+                self.placeholder_decode_chunk(tokens, state.frames, frame);
+            }
+            state.frames += 1;
         }
 
         Ok(chunk)
     }
 
+    /// Embed and run the ConvNet stack over the first `len` frames
+    fn hidden_states(
+        &self,
+        weights: &DecoderWeights,
+        tokens: &[Vec<u32>],
+        len: usize,
+    ) -> Result<Vec<f32>> {
+        let mut hidden = weights.embed(tokens, len);
+        for (index, layer) in weights.conv_layers.iter().enumerate() {
+            hidden = self.backend.forward(index, layer, &hidden, len)?;
+            apply_gelu(&mut hidden);
+        }
+        Ok(hidden)
+    }
+
     /// Run the full ConvNet decoder forward pass
     fn run_decoder(&self, tokens: &[Vec<u32>], output: &mut [f32]) -> Result<()> {
         let weights = self.decoder_weights.as_ref().unwrap();
-        let num_codebooks = tokens.len().min(weights.codebook_embeddings.len());
         let seq_len = tokens[0].len();
         let samples_per_token = self.config.samples_per_token();
 
-        // Step 1: Sum embeddings from all codebooks
-        let mut hidden = vec![0.0f32; seq_len * weights.hidden_dim];
-
-        for cb in 0..num_codebooks {
-            for (t, &token) in tokens[cb].iter().enumerate() {
-                let token_idx = (token as usize).min(weights.vocab_size - 1);
-                let embed_offset = token_idx * weights.hidden_dim;
-
-                for h in 0..weights.hidden_dim {
-                    let hidden_idx = t * weights.hidden_dim + h;
-                    if embed_offset + h < weights.codebook_embeddings[cb].len() {
-                        hidden[hidden_idx] += weights.codebook_embeddings[cb][embed_offset + h];
-                    }
-                }
-            }
-        }
-
-        // Step 2: Apply causal ConvNet layers with GELU activation
-        for layer in &weights.conv_layers {
-            hidden = layer.forward(&hidden, seq_len);
-            apply_gelu(&mut hidden);
+        let hidden = self.hidden_states(weights, tokens, seq_len)?;
+        for (t, frame) in output
+            .chunks_mut(samples_per_token)
+            .enumerate()
+            .take(seq_len)
+        {
+            weights.project(&hidden, t, frame);
         }
-
-        // Step 3: Apply output projection to get audio samples
-        // Upsample from token rate to sample rate
-        let out_channels = weights.output_proj_weight.len() / weights.hidden_dim;
-
-        for t in 0..seq_len {
-            for s in 0..samples_per_token {
-                let output_idx = t * samples_per_token + s;
-                if output_idx >= output.len() {
-                    break;
-                }
-
-                let mut sample = 0.0f32;
-
-                // Linear interpolation between timesteps for smoother audio
-                let interp = s as f32 / samples_per_token as f32;
-                let h_idx_curr = t * weights.hidden_dim;
-                let h_idx_next = if t + 1 < seq_len {
-                    (t + 1) * weights.hidden_dim
-                } else {
-                    h_idx_curr
-                };
-
-                for h in 0..weights.hidden_dim.min(out_channels) {
-                    let h_val =
-                        hidden[h_idx_curr + h] * (1.0 - interp) + hidden[h_idx_next + h] * interp;
-                    let w_idx = h; // Simplified projection
-                    if w_idx < weights.output_proj_weight.len() {
-                        sample += h_val * weights.output_proj_weight[w_idx];
-                    }
-                }
-
-                if !weights.output_proj_bias.is_empty() {
-                    sample += weights.output_proj_bias[0];
-                }
-
-                // Clamp output to valid audio range
-                output[output_idx] = sample.clamp(-1.0, 1.0);
-            }
-        }
-
         Ok(())
     }
 
-    /// Run incremental causal decoding for streaming
-    /// Only processes the specified chunk using cached hidden states
+    /// Decode token column `state.frames` through the conv ring buffers.
+    ///
+    /// Single frames always run on the CPU; a GPU dispatch per frame costs
+    /// more than the work it would offload.
     fn run_decoder_incremental(
         &self,
+        state: &mut DecoderState,
         tokens: &[Vec<u32>],
-        chunk_idx: usize,
         output: &mut [f32],
     ) -> Result<()> {
         let weights = self.decoder_weights.as_ref().unwrap();
-        let num_codebooks = tokens.len().min(weights.codebook_embeddings.len());
-        let samples_per_token = self.config.samples_per_token();
-
-        // For causal decoding, we need to process all tokens up to and including chunk_idx
-        // to maintain causality (each position only sees past positions)
-        let context_len = chunk_idx + 1;
-
-        // Step 1: Build hidden states for context
-        let mut hidden = vec![0.0f32; context_len * weights.hidden_dim];
-
-        for cb in 0..num_codebooks {
-            for t in 0..context_len {
-                if t < tokens[cb].len() {
-                    let token = tokens[cb][t];
-                    let token_idx = (token as usize).min(weights.vocab_size - 1);
-                    let embed_offset = token_idx * weights.hidden_dim;
-
-                    for h in 0..weights.hidden_dim {
-                        let hidden_idx = t * weights.hidden_dim + h;
-                        if embed_offset + h < weights.codebook_embeddings[cb].len() {
-                            hidden[hidden_idx] += weights.codebook_embeddings[cb][embed_offset + h];
-                        }
-                    }
-                }
-            }
+        if state.layers.len() != weights.conv_layers.len() {
+            return Err(Error::InferenceError(
+                "Decoder state was created before the codec weights were loaded".to_string(),
+            ));
         }
-
-        // Step 2: Apply causal ConvNet layers
-        for layer in &weights.conv_layers {
-            hidden = layer.forward(&hidden, context_len);
-            apply_gelu(&mut hidden);
-        }
-
-        // Step 3: Extract output for just the current chunk
-        let h_idx = chunk_idx * weights.hidden_dim;
-        let out_channels = weights.output_proj_weight.len() / weights.hidden_dim;
-
-        for s in 0..samples_per_token.min(output.len()) {
-            let mut sample = 0.0f32;
-            let interp = s as f32 / samples_per_token as f32;
-
-            // Get next timestep hidden for interpolation (if available)
-            let h_idx_next = if chunk_idx + 1 < context_len {
-                (chunk_idx + 1) * weights.hidden_dim
-            } else {
-                h_idx
-            };
-
-            for h in 0..weights.hidden_dim.min(out_channels) {
-                let h_val = if h_idx + h < hidden.len() {
-                    let curr = hidden[h_idx + h];
-                    let next = if h_idx_next + h < hidden.len() {
-                        hidden[h_idx_next + h]
-                    } else {
-                        curr
-                    };
-                    curr * (1.0 - interp) + next * interp
-                } else {
-                    0.0
-                };
-
-                if h < weights.output_proj_weight.len() {
-                    sample += h_val * weights.output_proj_weight[h];
-                }
-            }
-
-            if !weights.output_proj_bias.is_empty() {
-                sample += weights.output_proj_bias[0];
-            }
-
-            output[s] = sample.clamp(-1.0, 1.0);
+        let mut hidden = vec![0.0f32; weights.hidden_dim];
+        weights.embed_frame(tokens, state.frames, &mut hidden);
+        for (layer, layer_state) in weights.conv_layers.iter().zip(&mut state.layers) {
+            let mut next = vec![0.0f32; layer.out_channels];
+            layer.forward_step(layer_state, &hidden, &mut next);
+            apply_gelu(&mut next);
+            hidden = next;
         }
-
+        weights.project(&hidden, 0, output);
         Ok(())
     }
 
@@ -438,3 +551,144 @@ impl Default for AudioCodec {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use safetensors::tensor::TensorView;
+    use safetensors::Dtype;
+
+    #[test]
+    fn test_decoder_loads_weights_and_decodes() {
+        let dir = std::env::temp_dir().join(format!("izwi-codec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 2 codebooks of 3 tokens, hidden 2, one identity conv, 4 samples per token
+        let bytes =
+            |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        let codebook = bytes(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        let conv = bytes(&[1.0, 0.0, 0.0, 1.0]);
+        let conv_bias = bytes(&[0.0, 0.0]);
+        let output = bytes(&[1.0, 0.0, 0.0, 1.0, 0.5, 0.5, -1.0, 0.0]);
+        let output_bias = bytes(&[0.0; 4]);
+        let tensors = vec![
+            (
+                "codebooks.0.weight",
+                TensorView::new(Dtype::F32, vec![3, 2], &codebook).unwrap(),
+            ),
+            (
+                "codebooks.1.weight",
+                TensorView::new(Dtype::F32, vec![3, 2], &codebook).unwrap(),
+            ),
+            (
+                "layers.0.weight",
+                TensorView::new(Dtype::F32, vec![2, 2, 1], &conv).unwrap(),
+            ),
+            (
+                "layers.0.bias",
+                TensorView::new(Dtype::F32, vec![2], &conv_bias).unwrap(),
+            ),
+            (
+                "output.weight",
+                TensorView::new(Dtype::F32, vec![4, 2], &output).unwrap(),
+            ),
+            (
+                "output.bias",
+                TensorView::new(Dtype::F32, vec![4], &output_bias).unwrap(),
+            ),
+        ];
+        safetensors::serialize_to_file(tensors, &None, &dir.join("codec_decoder.safetensors"))
+            .unwrap();
+
+        let config = CodecConfig {
+            sample_rate: 50,
+            token_rate_hz: 12.5,
+            num_codebooks: 2,
+            channels: 1,
+        };
+        let mut codec = AudioCodec::with_config(config);
+        codec.load_weights(&dir).unwrap();
+        assert!(codec.has_weights());
+
+        // Frame 0 embeds to [1, 0], frame 1 to [1, 1]
+        let tokens = vec![vec![1, 1], vec![0, 2]];
+        let audio = codec.decode(&tokens).unwrap();
+        let g = |x: f32| gelu(x);
+        let expected = [
+            g(1.0),
+            0.0,
+            0.5 * g(1.0),
+            -g(1.0),
+            g(1.0),
+            g(1.0),
+            g(1.0),
+            -g(1.0),
+        ];
+        for (a, e) in audio.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?}", audio);
+        }
+
+        // Streaming one column at a time matches the full decode
+        let mut state = codec.decoder_state();
+        let first: Vec<Vec<u32>> = tokens.iter().map(|cb| cb[..1].to_vec()).collect();
+        let mut streamed = codec.decode_chunk(&mut state, &first).unwrap();
+        streamed.extend(codec.decode_chunk(&mut state, &tokens).unwrap());
+        assert_eq!(state.frames(), 2);
+        assert_eq!(streamed, audio);
+        assert!(codec.decode_chunk(&mut state, &tokens).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encoder_tokenizes_waveform() {
+        let dir = std::env::temp_dir().join(format!("izwi-codec-enc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 64 samples per token: 8 mel frames of 2 bins -> 16 input channels
+        let bytes =
+            |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        let conv: Vec<f32> = (0..32).map(|i| (i as f32 * 0.7).sin() * 0.1).collect();
+        let conv = bytes(&conv);
+        let conv_bias = bytes(&[0.0, 0.0]);
+        let codebook = bytes(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+        let tensors = vec![
+            (
+                "layers.0.weight",
+                TensorView::new(Dtype::F32, vec![2, 16, 1], &conv).unwrap(),
+            ),
+            (
+                "layers.0.bias",
+                TensorView::new(Dtype::F32, vec![2], &conv_bias).unwrap(),
+            ),
+            (
+                "codebooks.0.weight",
+                TensorView::new(Dtype::F32, vec![3, 2], &codebook).unwrap(),
+            ),
+            (
+                "codebooks.1.weight",
+                TensorView::new(Dtype::F32, vec![3, 2], &codebook).unwrap(),
+            ),
+        ];
+        safetensors::serialize_to_file(tensors, &None, &dir.join("codec_encoder.safetensors"))
+            .unwrap();
+
+        let mut codec = AudioCodec::with_config(CodecConfig {
+            sample_rate: 800,
+            token_rate_hz: 12.5,
+            num_codebooks: 2,
+            channels: 1,
+        });
+        assert!(codec.encode(&[0.0; 10], 800).is_err());
+        codec.load_weights(&dir).unwrap();
+        assert!(codec.has_encoder() && !codec.has_weights());
+
+        // 1.5 tokens of audio at twice the codec rate
+        let samples: Vec<f32> = (0..192).map(|i| (i as f32 * 0.3).sin()).collect();
+        let tokens = codec.encode(&samples, 1600).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens
+            .iter()
+            .all(|cb| cb.len() == 2 && cb.iter().all(|&t| t < 3)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! Audio codec encoder: waveform to multi-codebook audio tokens
+//!
+//! Causal log-mel frames are stacked per token column, run through a causal
+//! ConvNet and quantized with residual vector quantization (RVQ): each
+//! codebook encodes what the previous ones left over. Summing the chosen
+//! embeddings is exactly what the decoder's input embedding does.
+
+use std::path::Path;
+
+use super::codec::{apply_gelu, load_codebooks, load_conv_layers, load_tensors, Codebooks};
+use super::conv::CausalConv1d;
+use super::mel::{MelConfig, MelSpectrogram};
+use crate::error::{Error, Result};
+
+/// Mel frames stacked into each token column
+pub(super) const FRAMES_PER_TOKEN: usize = 8;
+
+/// Encoder network weights
+pub(super) struct EncoderWeights {
+    mel: MelSpectrogram,
+    /// Causal ConvNet from stacked mel frames to the latent
+    conv_layers: Vec<CausalConv1d>,
+    /// RVQ codebooks, `[vocab][hidden]` each
+    codebooks: Codebooks,
+    samples_per_token: usize,
+}
+
+impl EncoderWeights {
+    /// Load the encoder from `codec_encoder.safetensors`.
+    ///
+    /// Expected tensors: `layers.{i}.weight` `[out, in, kernel]` with
+    /// `layers.{i}.bias`, where the first layer takes
+    /// `FRAMES_PER_TOKEN * num_mels` channels and the last produces the
+    /// codebook dimension, and `codebooks.{i}.weight` `[vocab, hidden]`.
+    pub(super) fn load(
+        path: &Path,
+        sample_rate: u32,
+        samples_per_token: usize,
+        layout_cache: bool,
+    ) -> Result<Self> {
+        if !samples_per_token.is_multiple_of(FRAMES_PER_TOKEN) {
+            return Err(Error::ModelLoadError(format!(
+                "{} samples per token do not split into {} mel frames",
+                samples_per_token, FRAMES_PER_TOKEN
+            )));
+        }
+        let tensors = load_tensors(path, layout_cache)?;
+        let codebooks = load_codebooks(&tensors)?;
+
+        let input_channels = tensors
+            .get("layers.0.weight")
+            .and_then(|w| w.shape.get(1).copied())
+            .ok_or_else(|| Error::ModelLoadError("Codec encoder has no conv layers".to_string()))?;
+        if !input_channels.is_multiple_of(FRAMES_PER_TOKEN) {
+            return Err(Error::ModelLoadError(format!(
+                "Codec encoder input of {} channels is not {} stacked mel frames",
+                input_channels, FRAMES_PER_TOKEN
+            )));
+        }
+        let conv_layers = load_conv_layers(&tensors, input_channels, codebooks.hidden_dim)?;
+
+        let hop_length = samples_per_token / FRAMES_PER_TOKEN;
+        let mel = MelSpectrogram::new(
+            MelConfig {
+                n_fft: (4 * hop_length).next_power_of_two(),
+                hop_length,
+                num_mels: input_channels / FRAMES_PER_TOKEN,
+                f_min: 0.0,
+            },
+            sample_rate,
+        );
+
+        Ok(Self {
+            mel,
+            conv_layers,
+            codebooks,
+            samples_per_token,
+        })
+    }
+
+    pub(super) fn num_codebooks(&self) -> usize {
+        self.codebooks.embeddings.len()
+    }
+
+    pub(super) fn num_mels(&self) -> usize {
+        self.mel.config().num_mels
+    }
+
+    /// Tokenize samples at the codec rate, `[codebook][time]`
+    pub(super) fn encode(&self, samples: &[f32]) -> Vec<Vec<u32>> {
+        let seq_len = samples.len().div_ceil(self.samples_per_token);
+        let mut padded = samples.to_vec();
+        padded.resize(seq_len * self.samples_per_token, 0.0);
+
+        // [frame][mel] with FRAMES_PER_TOKEN consecutive frames is already
+        // the [time][FRAMES_PER_TOKEN * mel] layout of the first layer
+        let mut hidden = self.mel.compute(&padded);
+        let last = self.conv_layers.len().saturating_sub(1);
+        for (index, layer) in self.conv_layers.iter().enumerate() {
+            hidden = layer.forward_cpu(&hidden, seq_len);
+            if index < last {
+                apply_gelu(&mut hidden);
+            }
+        }
+
+        quantize(&hidden, seq_len, &self.codebooks)
+    }
+}
+
+/// Residual vector quantization of `[time][hidden]` latents
+fn quantize(latent: &[f32], seq_len: usize, codebooks: &Codebooks) -> Vec<Vec<u32>> {
+    let hidden_dim = codebooks.hidden_dim;
+    let mut tokens = vec![Vec::with_capacity(seq_len); codebooks.embeddings.len()];
+    for frame in latent.chunks(hidden_dim).take(seq_len) {
+        let mut residual = frame.to_vec();
+        for (codebook, column) in codebooks.embeddings.iter().zip(tokens.iter_mut()) {
+            let (index, entry) = codebook
+                .chunks(hidden_dim)
+                .enumerate()
+                .map(|(i, entry)| {
+                    let distance: f32 = entry
+                        .iter()
+                        .zip(&residual)
+                        .map(|(e, r)| (e - r) * (e - r))
+                        .sum();
+                    (i, entry, distance)
+                })
+                .min_by(|a, b| a.2.total_cmp(&b.2))
+                .map(|(i, entry, _)| (i, entry))
+                .unwrap_or((0, &[]));
+            for (r, e) in residual.iter_mut().zip(entry) {
+                *r -= e;
+            }
+            column.push(index as u32);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_residual_quantization() {
+        // Codebook 0 holds coarse values, codebook 1 the refinements
+        let codebooks = Codebooks {
+            embeddings: vec![
+                vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 0.25, 0.0, 0.0, -0.25],
+            ],
+            vocab_size: 3,
+            hidden_dim: 2,
+        };
+        let latent = [1.2, 0.0, 0.1, 0.8, 0.0, 0.0];
+        let tokens = quantize(&latent, 3, &codebooks);
+        assert_eq!(tokens, vec![vec![1, 2, 0], vec![1, 2, 0]]);
+    }
+}
@@ -0,0 +1,565 @@
+//! Causal 1-D convolution kernels for the codec decoder
+//!
+//! The CPU path accumulates in fixed-width lanes so the compiler emits SIMD
+//! (NEON / AVX) for the channel dot products. On Apple Silicon the layers can
+//! instead run as a Metal compute kernel, and with the `cuda` feature as a
+//! CUDA kernel compiled at load, with weights uploaded once.
+
+use tracing::{info, warn};
+
+use crate::device::Device;
+use crate::error::Result;
+use crate::model::layout::pack_conv_weight;
+
+/// Accumulator width of the CPU dot product
+const LANES: usize = 8;
+
+/// A causal conv1d layer: `output[t]` depends only on `input[..=t]`.
+///
+/// Weights are stored as `[out][kernel][in]` so each tap is a contiguous dot
+/// product with one input frame (inputs are `[time][channel]`).
+#[derive(Debug, Clone)]
+pub struct CausalConv1d {
+    pub(crate) weight: Vec<f32>,
+    pub(crate) bias: Vec<f32>,
+    pub(crate) kernel_size: usize,
+    pub(crate) in_channels: usize,
+    pub(crate) out_channels: usize,
+}
+
+impl CausalConv1d {
+    /// Build a layer from PyTorch `Conv1d` weights laid out `[out][in][kernel]`
+    pub fn from_torch(
+        weight: &[f32],
+        bias: Vec<f32>,
+        out_channels: usize,
+        in_channels: usize,
+        kernel_size: usize,
+    ) -> Self {
+        let packed = pack_conv_weight(weight, out_channels, in_channels, kernel_size);
+        Self::from_packed(packed, bias, out_channels, in_channels, kernel_size)
+    }
+
+    /// Build a layer from weights already laid out `[out][kernel][in]`
+    pub fn from_packed(
+        weight: Vec<f32>,
+        bias: Vec<f32>,
+        out_channels: usize,
+        in_channels: usize,
+        kernel_size: usize,
+    ) -> Self {
+        Self {
+            weight,
+            bias,
+            kernel_size,
+            in_channels,
+            out_channels,
+        }
+    }
+
+    /// Run the layer over `seq_len` frames on the CPU
+    pub fn forward_cpu(&self, input: &[f32], seq_len: usize) -> Vec<f32> {
+        let mut output = vec![0.0f32; self.out_channels * seq_len];
+        for t in 0..seq_len {
+            let frame = &mut output[t * self.out_channels..(t + 1) * self.out_channels];
+            self.forward_frame(input, t, frame);
+        }
+        output
+    }
+
+    /// Compute output frame `t` into `out`
+    pub(crate) fn forward_frame(&self, input: &[f32], t: usize, out: &mut [f32]) {
+        let padding = self.kernel_size - 1;
+        for (o, value) in out.iter_mut().enumerate() {
+            let mut sum = self.bias[o];
+            for k in 0..self.kernel_size {
+                let Some(input_t) = (t + k).checked_sub(padding) else {
+                    continue;
+                };
+                let x = &input[input_t * self.in_channels..(input_t + 1) * self.in_channels];
+                let start = (o * self.kernel_size + k) * self.in_channels;
+                sum += dot(&self.weight[start..start + self.in_channels], x);
+            }
+            *value = sum;
+        }
+    }
+
+    /// Compute the output frame for the next input `frame`, using and then
+    /// updating the past frames held in `state`
+    pub(crate) fn forward_step(&self, state: &mut ConvState, frame: &[f32], out: &mut [f32]) {
+        let padding = self.kernel_size - 1;
+        for (o, value) in out.iter_mut().enumerate() {
+            let mut sum = self.bias[o];
+            for k in 0..padding {
+                let Some(input_t) = (state.position + k).checked_sub(padding) else {
+                    continue;
+                };
+                let slot = (input_t % padding) * self.in_channels;
+                let x = &state.history[slot..slot + self.in_channels];
+                let start = (o * self.kernel_size + k) * self.in_channels;
+                sum += dot(&self.weight[start..start + self.in_channels], x);
+            }
+            let start = (o * self.kernel_size + padding) * self.in_channels;
+            sum += dot(&self.weight[start..start + self.in_channels], frame);
+            *value = sum;
+        }
+        if padding > 0 {
+            let slot = (state.position % padding) * self.in_channels;
+            state.history[slot..slot + self.in_channels].copy_from_slice(frame);
+        }
+        state.position += 1;
+    }
+}
+
+/// Ring buffer of the last `kernel_size - 1` input frames of one layer
+#[derive(Debug, Clone)]
+pub struct ConvState {
+    history: Vec<f32>,
+    /// Input frames consumed so far
+    position: usize,
+}
+
+impl ConvState {
+    pub fn new(layer: &CausalConv1d) -> Self {
+        Self {
+            history: vec![0.0; (layer.kernel_size - 1) * layer.in_channels],
+            position: 0,
+        }
+    }
+}
+
+/// Dot product accumulated in `LANES` independent sums
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut acc = [0.0f32; LANES];
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for lane in 0..LANES {
+            acc[lane] += x[lane] * y[lane];
+        }
+    }
+    acc.iter().sum::<f32>() + tail
+}
+
+/// Where decoder convolutions run
+pub enum ConvBackend {
+    Cpu,
+    #[cfg(target_os = "macos")]
+    Metal(metal_backend::MetalConv),
+    #[cfg(feature = "cuda")]
+    Cuda(cuda_backend::CudaConv),
+}
+
+impl ConvBackend {
+    /// Use the requested GPU when available, otherwise the CPU
+    pub fn new(device: Device, layers: &[CausalConv1d]) -> Self {
+        let backend = match device {
+            Device::Metal => Self::metal(layers),
+            Device::Cuda(ordinal) => Self::cuda(ordinal, layers),
+            _ => return Self::Cpu,
+        };
+        match backend {
+            Ok(Some(backend)) => {
+                info!("Codec decoder running on {}", device);
+                backend
+            }
+            Ok(None) => Self::Cpu,
+            Err(e) => {
+                warn!("{} codec decoder unavailable, using CPU: {}", device, e);
+                Self::Cpu
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn metal(layers: &[CausalConv1d]) -> Result<Option<Self>> {
+        Ok(metal_backend::MetalConv::new(layers)?.map(Self::Metal))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn metal(_layers: &[CausalConv1d]) -> Result<Option<Self>> {
+        Ok(None)
+    }
+
+    #[cfg(feature = "cuda")]
+    fn cuda(ordinal: usize, layers: &[CausalConv1d]) -> Result<Option<Self>> {
+        Ok(cuda_backend::CudaConv::new(ordinal, layers)?.map(Self::Cuda))
+    }
+
+    #[cfg(not(feature = "cuda"))]
+    fn cuda(_ordinal: usize, _layers: &[CausalConv1d]) -> Result<Option<Self>> {
+        Ok(None)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            #[cfg(target_os = "macos")]
+            Self::Metal(_) => "metal",
+            #[cfg(feature = "cuda")]
+            Self::Cuda(_) => "cuda",
+        }
+    }
+
+    /// Run layer `index` of the stack the backend was built for
+    #[cfg_attr(
+        not(any(target_os = "macos", feature = "cuda")),
+        allow(unused_variables)
+    )]
+    pub fn forward(
+        &self,
+        index: usize,
+        layer: &CausalConv1d,
+        input: &[f32],
+        seq_len: usize,
+    ) -> Result<Vec<f32>> {
+        match self {
+            Self::Cpu => Ok(layer.forward_cpu(input, seq_len)),
+            #[cfg(target_os = "macos")]
+            Self::Metal(metal) => metal.forward(index, layer, input, seq_len),
+            #[cfg(feature = "cuda")]
+            Self::Cuda(cuda) => cuda.forward(index, layer, input, seq_len),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod metal_backend {
+    use metal::{
+        Buffer, CommandQueue, CompileOptions, ComputePipelineState, Device, MTLResourceOptions,
+        MTLSize,
+    };
+    use std::ffi::c_void;
+    use std::sync::Mutex;
+
+    use super::CausalConv1d;
+    use crate::error::{Error, Result};
+
+    const KERNEL_SOURCE: &str = r#"
+#include <metal_stdlib>
+using namespace metal;
+
+// dims: seq_len, in_channels, out_channels, kernel_size
+kernel void causal_conv1d(
+    device const float* input [[buffer(0)]],
+    device const float* weight [[buffer(1)]],
+    device const float* bias [[buffer(2)]],
+    device float* output [[buffer(3)]],
+    constant uint4& dims [[buffer(4)]],
+    uint2 gid [[thread_position_in_grid]])
+{
+    uint t = gid.x;
+    uint o = gid.y;
+    if (t >= dims.x || o >= dims.z) {
+        return;
+    }
+    uint in_channels = dims.y;
+    uint kernel_size = dims.w;
+    float sum = bias[o];
+    for (uint k = 0; k < kernel_size; k++) {
+        int input_t = int(t) + int(k) - int(kernel_size - 1);
+        if (input_t < 0) {
+            continue;
+        }
+        device const float* w = weight + (o * kernel_size + k) * in_channels;
+        device const float* x = input + uint(input_t) * in_channels;
+        for (uint i = 0; i < in_channels; i++) {
+            sum += w[i] * x[i];
+        }
+    }
+    output[t * dims.z + o] = sum;
+}
+"#;
+
+    /// Weights of one layer resident on the GPU
+    struct LayerBuffers {
+        weight: Buffer,
+        bias: Buffer,
+    }
+
+    pub struct MetalConv {
+        device: Device,
+        queue: Mutex<CommandQueue>,
+        pipeline: ComputePipelineState,
+        layers: Vec<LayerBuffers>,
+    }
+
+    // Metal objects are reference counted and safe to use across threads;
+    // command submission is serialized through the queue mutex.
+    unsafe impl Send for MetalConv {}
+    unsafe impl Sync for MetalConv {}
+
+    fn buffer(device: &Device, data: &[f32]) -> Buffer {
+        device.new_buffer_with_data(
+            data.as_ptr() as *const c_void,
+            std::mem::size_of_val(data) as u64,
+            MTLResourceOptions::StorageModeShared,
+        )
+    }
+
+    impl MetalConv {
+        /// `None` when the machine has no Metal device
+        pub fn new(layers: &[CausalConv1d]) -> Result<Option<Self>> {
+            let Some(device) = Device::system_default() else {
+                return Ok(None);
+            };
+            let library = device
+                .new_library_with_source(KERNEL_SOURCE, &CompileOptions::new())
+                .map_err(Error::InferenceError)?;
+            let function = library
+                .get_function("causal_conv1d", None)
+                .map_err(Error::InferenceError)?;
+            let pipeline = device
+                .new_compute_pipeline_state_with_function(&function)
+                .map_err(Error::InferenceError)?;
+            let layers = layers
+                .iter()
+                .map(|layer| LayerBuffers {
+                    weight: buffer(&device, &layer.weight),
+                    bias: buffer(&device, &layer.bias),
+                })
+                .collect();
+            let queue = Mutex::new(device.new_command_queue());
+            Ok(Some(Self {
+                device,
+                queue,
+                pipeline,
+                layers,
+            }))
+        }
+
+        pub fn forward(
+            &self,
+            index: usize,
+            layer: &CausalConv1d,
+            input: &[f32],
+            seq_len: usize,
+        ) -> Result<Vec<f32>> {
+            let buffers = self.layers.get(index).ok_or_else(|| {
+                Error::InferenceError(format!("No Metal buffers for codec layer {}", index))
+            })?;
+            let out_len = seq_len * layer.out_channels;
+            let input_buffer = buffer(&self.device, input);
+            let output_buffer = self.device.new_buffer(
+                (out_len * std::mem::size_of::<f32>()) as u64,
+                MTLResourceOptions::StorageModeShared,
+            );
+            let dims: [u32; 4] = [
+                seq_len as u32,
+                layer.in_channels as u32,
+                layer.out_channels as u32,
+                layer.kernel_size as u32,
+            ];
+
+            let queue = self.queue.lock().unwrap();
+            let command_buffer = queue.new_command_buffer();
+            let encoder = command_buffer.new_compute_command_encoder();
+            encoder.set_compute_pipeline_state(&self.pipeline);
+            encoder.set_buffer(0, Some(&input_buffer), 0);
+            encoder.set_buffer(1, Some(&buffers.weight), 0);
+            encoder.set_buffer(2, Some(&buffers.bias), 0);
+            encoder.set_buffer(3, Some(&output_buffer), 0);
+            encoder.set_bytes(
+                4,
+                std::mem::size_of_val(&dims) as u64,
+                dims.as_ptr() as *const c_void,
+            );
+            let width = self.pipeline.thread_execution_width();
+            let height = (self.pipeline.max_total_threads_per_threadgroup() / width).max(1);
+            encoder.dispatch_threads(
+                MTLSize::new(seq_len as u64, layer.out_channels as u64, 1),
+                MTLSize::new(width, height, 1),
+            );
+            encoder.end_encoding();
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+
+            // Safety: the shared buffer holds `out_len` floats written by the kernel
+            let output = unsafe {
+                std::slice::from_raw_parts(output_buffer.contents() as *const f32, out_len)
+            };
+            Ok(output.to_vec())
+        }
+    }
+}
+
+#[cfg(feature = "cuda")]
+mod cuda_backend {
+    use cudarc::driver::{
+        CudaContext, CudaFunction, CudaSlice, CudaStream, LaunchConfig, PushKernelArg,
+    };
+    use std::sync::Arc;
+
+    use super::CausalConv1d;
+    use crate::device::cuda;
+    use crate::error::{Error, Result};
+
+    const KERNEL_SOURCE: &str = r#"
+extern "C" __global__ void causal_conv1d(
+    const float* input,
+    const float* weight,
+    const float* bias,
+    float* output,
+    unsigned int seq_len,
+    unsigned int in_channels,
+    unsigned int out_channels,
+    unsigned int kernel_size)
+{
+    unsigned int t = blockIdx.x * blockDim.x + threadIdx.x;
+    unsigned int o = blockIdx.y * blockDim.y + threadIdx.y;
+    if (t >= seq_len || o >= out_channels) {
+        return;
+    }
+    float sum = bias[o];
+    for (unsigned int k = 0; k < kernel_size; k++) {
+        int input_t = (int)t + (int)k - (int)(kernel_size - 1);
+        if (input_t < 0) {
+            continue;
+        }
+        const float* w = weight + (o * kernel_size + k) * in_channels;
+        const float* x = input + (unsigned int)input_t * in_channels;
+        for (unsigned int i = 0; i < in_channels; i++) {
+            sum += w[i] * x[i];
+        }
+    }
+    output[t * out_channels + o] = sum;
+}
+"#;
+
+    /// Threads per block along time and output channels
+    const BLOCK: (u32, u32) = (32, 8);
+
+    /// Weights of one layer resident on the GPU
+    struct LayerBuffers {
+        weight: CudaSlice<f32>,
+        bias: CudaSlice<f32>,
+    }
+
+    pub struct CudaConv {
+        stream: Arc<CudaStream>,
+        function: CudaFunction,
+        layers: Vec<LayerBuffers>,
+    }
+
+    fn cuda_error(e: impl std::fmt::Debug) -> Error {
+        Error::InferenceError(format!("CUDA error: {:?}", e))
+    }
+
+    impl CudaConv {
+        /// `None` when device `ordinal` is not present
+        pub fn new(ordinal: usize, layers: &[CausalConv1d]) -> Result<Option<Self>> {
+            if ordinal >= cuda::device_count() {
+                return Ok(None);
+            }
+            let context = CudaContext::new(ordinal).map_err(cuda_error)?;
+            let ptx = cudarc::nvrtc::compile_ptx(KERNEL_SOURCE).map_err(cuda_error)?;
+            let function = context
+                .load_module(ptx)
+                .and_then(|module| module.load_function("causal_conv1d"))
+                .map_err(cuda_error)?;
+            let stream = context.default_stream();
+            let layers = layers
+                .iter()
+                .map(|layer| {
+                    Ok(LayerBuffers {
+                        weight: stream.memcpy_stod(&layer.weight).map_err(cuda_error)?,
+                        bias: stream.memcpy_stod(&layer.bias).map_err(cuda_error)?,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Some(Self {
+                stream,
+                function,
+                layers,
+            }))
+        }
+
+        pub fn forward(
+            &self,
+            index: usize,
+            layer: &CausalConv1d,
+            input: &[f32],
+            seq_len: usize,
+        ) -> Result<Vec<f32>> {
+            let buffers = self.layers.get(index).ok_or_else(|| {
+                Error::InferenceError(format!("No CUDA buffers for codec layer {}", index))
+            })?;
+            let input_buffer = self.stream.memcpy_stod(input).map_err(cuda_error)?;
+            let mut output_buffer = self
+                .stream
+                .alloc_zeros::<f32>(seq_len * layer.out_channels)
+                .map_err(cuda_error)?;
+            let dims = [
+                seq_len as u32,
+                layer.in_channels as u32,
+                layer.out_channels as u32,
+                layer.kernel_size as u32,
+            ];
+            let config = LaunchConfig {
+                grid_dim: (dims[0].div_ceil(BLOCK.0), dims[2].div_ceil(BLOCK.1), 1),
+                block_dim: (BLOCK.0, BLOCK.1, 1),
+                shared_mem_bytes: 0,
+            };
+
+            let mut launch = self.stream.launch_builder(&self.function);
+            launch
+                .arg(&input_buffer)
+                .arg(&buffers.weight)
+                .arg(&buffers.bias)
+                .arg(&mut output_buffer);
+            for dim in &dims {
+                launch.arg(dim);
+            }
+            // Safety: the arguments match the kernel signature and the output
+            // holds `seq_len * out_channels` floats
+            unsafe { launch.launch(config) }.map_err(cuda_error)?;
+            self.stream.memcpy_dtov(&output_buffer).map_err(cuda_error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_causal_conv_matches_reference() {
+        let (out_c, in_c, k, seq) = (3, 11, 3, 5);
+        let weight: Vec<f32> = (0..out_c * in_c * k)
+            .map(|i| (i as f32 * 0.37).sin())
+            .collect();
+        let bias = vec![0.1, -0.2, 0.3];
+        let input: Vec<f32> = (0..seq * in_c).map(|i| (i as f32 * 0.11).cos()).collect();
+        let layer = CausalConv1d::from_torch(&weight, bias.clone(), out_c, in_c, k);
+
+        let output = ConvBackend::Cpu.forward(0, &layer, &input, seq).unwrap();
+        for t in 0..seq {
+            for o in 0..out_c {
+                let mut expected = bias[o];
+                for j in 0..k {
+                    let Some(it) = (t + j).checked_sub(k - 1) else {
+                        continue;
+                    };
+                    for i in 0..in_c {
+                        expected += weight[(o * in_c + i) * k + j] * input[it * in_c + i];
+                    }
+                }
+                assert!((output[t * out_c + o] - expected).abs() < 1e-5);
+            }
+        }
+
+        // Stepping frame by frame through the ring buffer gives the same output
+        let mut state = ConvState::new(&layer);
+        let mut frame = vec![0.0; out_c];
+        for t in 0..seq {
+            layer.forward_step(&mut state, &input[t * in_c..(t + 1) * in_c], &mut frame);
+            assert_eq!(frame, output[t * out_c..(t + 1) * out_c]);
+        }
+    }
+}
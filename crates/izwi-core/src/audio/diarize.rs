@@ -0,0 +1,266 @@
+//! Speaker diarization ("who spoke when")
+//!
+//! Speech is split into segments at pauses, each segment is summarized by
+//! the shape of its log-mel spectrum, and segments are grouped by
+//! average-linkage clustering.
+
+use std::ops::Range;
+
+use super::mel::{MelConfig, MelSpectrogram};
+use super::silence::{frame_db, SilenceConfig};
+
+/// Segmentation and clustering settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiarizeConfig {
+    pub silence: SilenceConfig,
+    /// Pause that ends a segment, in milliseconds
+    pub pause_ms: u32,
+    /// Segments are split once they reach this length, in milliseconds
+    pub max_segment_ms: u32,
+    /// Speech shorter than this is dropped, in milliseconds
+    pub min_segment_ms: u32,
+    /// Clusters whose segments differ by less than this, as an RMS
+    /// difference of log-mel statistics in nats, are the same speaker
+    pub threshold: f32,
+    /// Upper bound on the number of speakers, when known
+    pub max_speakers: Option<usize>,
+}
+
+impl Default for DiarizeConfig {
+    fn default() -> Self {
+        Self {
+            silence: SilenceConfig::default(),
+            pause_ms: 400,
+            max_segment_ms: 15_000,
+            min_segment_ms: 200,
+            threshold: 1.5,
+            max_speakers: None,
+        }
+    }
+}
+
+/// A stretch of speech attributed to one speaker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerSegment {
+    /// Sample range within the input
+    pub range: Range<usize>,
+    /// Speakers are numbered from 0 in order of first appearance
+    pub speaker: usize,
+}
+
+/// Split `samples` into speech segments and label each with a speaker
+pub fn diarize(samples: &[f32], sample_rate: u32, config: &DiarizeConfig) -> Vec<SpeakerSegment> {
+    let segments = speech_segments(samples, sample_rate, config);
+    if segments.is_empty() {
+        return Vec::new();
+    }
+    let embeddings = embed_segments(samples, sample_rate, &segments);
+    let labels = cluster(&embeddings, config.threshold, config.max_speakers);
+    let margin = sample_rate as usize * config.silence.margin_ms as usize / 1000;
+    segments
+        .into_iter()
+        .zip(labels)
+        .map(|(range, speaker)| SpeakerSegment {
+            range: range.start.saturating_sub(margin)..(range.end + margin).min(samples.len()),
+            speaker,
+        })
+        .collect()
+}
+
+/// Voiced regions separated by at least `pause_ms` of silence
+fn speech_segments(samples: &[f32], sample_rate: u32, config: &DiarizeConfig) -> Vec<Range<usize>> {
+    let per_ms = |ms: u32| sample_rate as usize * ms as usize / 1000;
+    let frame_len = per_ms(config.silence.frame_ms).max(1);
+    let pause_frames = per_ms(config.pause_ms).div_ceil(frame_len).max(1);
+    let max_len = per_ms(config.max_segment_ms).max(frame_len);
+
+    let mut voiced = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    let mut silent_frames = 0;
+    for (i, frame) in samples.chunks(frame_len).enumerate() {
+        let start = i * frame_len;
+        let end = start + frame.len();
+        if frame_db(frame) >= config.silence.threshold_db {
+            silent_frames = 0;
+            match current.as_mut() {
+                Some(segment) if end - segment.start <= max_len => segment.end = end,
+                _ => voiced.extend(current.replace(start..end)),
+            }
+        } else if current.is_some() {
+            silent_frames += 1;
+            if silent_frames >= pause_frames {
+                voiced.extend(current.take());
+            }
+        }
+    }
+    voiced.extend(current);
+
+    voiced
+        .into_iter()
+        .filter(|segment| segment.len() >= per_ms(config.min_segment_ms))
+        .collect()
+}
+
+/// Frames quieter than the segment's loudest by more than this (about
+/// 30 dB) are left out of its embedding
+const VOICED_RANGE_NATS: f32 = 7.0;
+
+/// Per-segment mean and spread of each mel band, with loudness removed
+fn embed_segments(samples: &[f32], sample_rate: u32, segments: &[Range<usize>]) -> Vec<Vec<f32>> {
+    let config = MelConfig {
+        n_fft: (sample_rate as usize * 25 / 1000).next_power_of_two(),
+        hop_length: (sample_rate as usize / 100).max(1),
+        num_mels: 40,
+        f_min: 60.0,
+    };
+    let mel = MelSpectrogram::new(config, sample_rate);
+    let num_mels = config.num_mels;
+
+    segments
+        .iter()
+        .map(|segment| {
+            let features = mel.compute(&samples[segment.clone()]);
+            let level = |frame: &[f32]| frame.iter().sum::<f32>() / num_mels as f32;
+            let loudest = features
+                .chunks_exact(num_mels)
+                .map(level)
+                .fold(f32::NEG_INFINITY, f32::max);
+            // Pauses within the segment would skew the statistics
+            let voiced: Vec<&[f32]> = features
+                .chunks_exact(num_mels)
+                .filter(|frame| level(frame) >= loudest - VOICED_RANGE_NATS)
+                .collect();
+            let frames = voiced.len().max(1) as f32;
+            let mut mean = vec![0.0f32; num_mels];
+            for frame in &voiced {
+                mean.iter_mut()
+                    .zip(frame.iter())
+                    .for_each(|(m, v)| *m += v / frames);
+            }
+            let mut spread = vec![0.0f32; num_mels];
+            for frame in &voiced {
+                for ((s, v), m) in spread.iter_mut().zip(frame.iter()).zip(&mean) {
+                    *s += (v - m).powi(2) / frames;
+                }
+            }
+            let level = mean.iter().sum::<f32>() / num_mels as f32;
+            mean.iter()
+                .map(|m| m - level)
+                .chain(spread.iter().map(|s| s.sqrt()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Root-mean-square difference, in nats
+fn rms_distance(a: &[f32], b: &[f32]) -> f32 {
+    let sum: f32 = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum();
+    (sum / a.len().max(1) as f32).sqrt()
+}
+
+/// Average-linkage agglomerative clustering; returns a label per embedding
+fn cluster(embeddings: &[Vec<f32>], threshold: f32, max_speakers: Option<usize>) -> Vec<usize> {
+    let n = embeddings.len();
+    let mut distance = vec![vec![0.0f32; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let d = rms_distance(&embeddings[i], &embeddings[j]);
+            distance[i][j] = d;
+            distance[j][i] = d;
+        }
+    }
+
+    let mut clusters: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    let max_speakers = max_speakers.unwrap_or(n).max(1);
+    while clusters.len() > 1 {
+        let mut closest = (f32::INFINITY, 0, 0);
+        for a in 0..clusters.len() {
+            for b in a + 1..clusters.len() {
+                let total: f32 = clusters[a]
+                    .iter()
+                    .flat_map(|&i| clusters[b].iter().map(move |&j| (i, j)))
+                    .map(|(i, j)| distance[i][j])
+                    .sum();
+                let average = total / (clusters[a].len() * clusters[b].len()) as f32;
+                if average < closest.0 {
+                    closest = (average, a, b);
+                }
+            }
+        }
+        let (average, a, b) = closest;
+        if average > threshold && clusters.len() <= max_speakers {
+            break;
+        }
+        let merged = clusters.swap_remove(b);
+        clusters[a].extend(merged);
+    }
+
+    let mut labels = vec![0; n];
+    for (label, members) in clusters.iter().enumerate() {
+        for &i in members {
+            labels[i] = label;
+        }
+    }
+    // Renumber in order of first appearance
+    let mut order = Vec::new();
+    for &label in &labels {
+        if !order.contains(&label) {
+            order.push(label);
+        }
+    }
+    labels
+        .iter()
+        .map(|label| order.iter().position(|l| l == label).unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A voice-like buzz: a fundamental with decaying harmonics
+    fn voice(hz: f32, rate: u32, secs: f32) -> Vec<f32> {
+        (0..(rate as f32 * secs) as usize)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                (1..=12)
+                    .map(|h| (2.0 * std::f32::consts::PI * hz * h as f32 * t).sin() / h as f32)
+                    .sum::<f32>()
+                    * 0.2
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_alternating_speakers() {
+        let rate = 16000;
+        let pause = vec![0.0; rate as usize / 2];
+        let mut samples = Vec::new();
+        for hz in [110.0, 240.0, 110.0, 240.0] {
+            samples.extend(voice(hz, rate, 1.0));
+            samples.extend(&pause);
+        }
+
+        let segments = diarize(&samples, rate, &DiarizeConfig::default());
+        let speakers: Vec<usize> = segments.iter().map(|s| s.speaker).collect();
+        assert_eq!(speakers, vec![0, 1, 0, 1]);
+        // Each segment starts within its margin of the speech onset
+        assert!(segments[1].range.start.abs_diff(24000) <= 480);
+
+        let one = DiarizeConfig {
+            max_speakers: Some(1),
+            ..Default::default()
+        };
+        assert!(diarize(&samples, rate, &one).iter().all(|s| s.speaker == 0));
+        assert!(diarize(&pause, rate, &DiarizeConfig::default()).is_empty());
+
+        let mut monologue = Vec::new();
+        for _ in 0..3 {
+            monologue.extend(voice(110.0, rate, 1.0));
+            monologue.extend(&pause);
+        }
+        let segments = diarize(&monologue, rate, &DiarizeConfig::default());
+        assert_eq!(segments.len(), 3);
+        assert!(segments.iter().all(|s| s.speaker == 0));
+    }
+}
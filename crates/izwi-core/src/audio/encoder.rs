@@ -1,9 +1,17 @@
 //! Audio encoding to various output formats
 
-use hound::{WavSpec, WavWriter};
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
 use std::io::Cursor;
+use std::str::FromStr;
 use tracing::debug;
 
+use super::buffer::SampleBuffer;
+use super::g711;
+use super::loudness::{normalize_loudness, LoudnessConfig};
+use super::resample::{downmix_to_mono, resample};
+use super::simd;
 use crate::error::{Error, Result};
 
 /// Supported audio output formats
@@ -15,12 +23,86 @@ pub enum AudioFormat {
     RawF32,
     /// Raw PCM samples (i16)
     RawI16,
+    /// Big-endian 16-bit PCM (`audio/L16`, RFC 2586)
+    L16,
+    /// G.711 mu-law, 8 kHz (headerless, one byte per sample)
+    MuLaw,
+    /// G.711 A-law, 8 kHz (headerless, one byte per sample)
+    ALaw,
+}
+
+impl AudioFormat {
+    /// Sample rate the format is defined for, if it has a fixed one
+    pub fn required_sample_rate(&self) -> Option<u32> {
+        match self {
+            AudioFormat::MuLaw | AudioFormat::ALaw => Some(G711_SAMPLE_RATE),
+            AudioFormat::Wav | AudioFormat::RawF32 | AudioFormat::RawI16 | AudioFormat::L16 => None,
+        }
+    }
+}
+
+/// Sample encoding of WAV output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitDepth {
+    /// 16-bit integer PCM
+    #[default]
+    Int16,
+    /// 24-bit integer PCM
+    Int24,
+    /// 32-bit IEEE float
+    Float32,
+}
+
+impl BitDepth {
+    /// Bytes per sample
+    pub fn bytes(&self) -> usize {
+        match self {
+            BitDepth::Int16 => 2,
+            BitDepth::Int24 => 3,
+            BitDepth::Float32 => 4,
+        }
+    }
+}
+
+impl FromStr for BitDepth {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "16" => Ok(BitDepth::Int16),
+            "24" => Ok(BitDepth::Int24),
+            "32" | "32f" | "f32" => Ok(BitDepth::Float32),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown bit depth {} (expected 16, 24 or 32f)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Accepts `16`, `24` and `32` as numbers, or `"16"`, `"24"` and `"32f"`
+impl<'de> Deserialize<'de> for BitDepth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bits(u32),
+            Name(String),
+        }
+        let name = match Repr::deserialize(deserializer)? {
+            Repr::Bits(bits) => bits.to_string(),
+            Repr::Name(name) => name,
+        };
+        name.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 /// Audio encoder for converting f32 samples to various formats
 pub struct AudioEncoder {
     sample_rate: u32,
     channels: u16,
+    bit_depth: BitDepth,
+    loudness: Option<LoudnessConfig>,
 }
 
 impl AudioEncoder {
@@ -29,70 +111,160 @@ impl AudioEncoder {
         Self {
             sample_rate,
             channels,
+            bit_depth: BitDepth::Int16,
+            loudness: None,
         }
     }
 
+    /// Sample encoding of WAV output (16-bit PCM by default)
+    pub fn with_bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    /// Normalize loudness before encoding
+    pub fn with_loudness(mut self, config: LoudnessConfig) -> Self {
+        self.loudness = Some(config);
+        self
+    }
+
     /// Encode samples to the specified format
     pub fn encode(&self, samples: &[f32], format: AudioFormat) -> Result<Vec<u8>> {
-        match format {
-            AudioFormat::Wav => self.encode_wav(samples),
-            AudioFormat::RawF32 => self.encode_raw_f32(samples),
-            AudioFormat::RawI16 => self.encode_raw_i16(samples),
-        }
+        self.encode_bytes(samples, format).map(Vec::from)
     }
 
-    /// Encode to WAV format
-    fn encode_wav(&self, samples: &[f32]) -> Result<Vec<u8>> {
-        let spec = WavSpec {
-            channels: self.channels,
-            sample_rate: self.sample_rate,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
+    /// Encode samples into a new reference-counted buffer
+    pub fn encode_bytes(&self, samples: &[f32], format: AudioFormat) -> Result<Bytes> {
+        let mut out = BytesMut::new();
+        self.encode_into(samples, format, &mut out)?;
+        Ok(out.freeze())
+    }
 
-        let mut buffer = Cursor::new(Vec::new());
+    /// Encode a shared sample buffer. Raw f32 output without loudness
+    /// normalization reuses the buffer's bytes on little-endian targets.
+    pub fn encode_buffer(&self, samples: &SampleBuffer, format: AudioFormat) -> Result<Bytes> {
+        if cfg!(target_endian = "little")
+            && format == AudioFormat::RawF32
+            && self.loudness.is_none()
         {
-            let mut writer =
-                WavWriter::new(&mut buffer, spec).map_err(|e| Error::AudioError(e.to_string()))?;
-
-            for &sample in samples {
-                // Convert f32 [-1.0, 1.0] to i16
-                let sample_i16 = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
-                writer
-                    .write_sample(sample_i16)
-                    .map_err(|e| Error::AudioError(e.to_string()))?;
+            return Ok(samples.as_bytes().clone());
+        }
+        self.encode_bytes(samples, format)
+    }
+
+    /// Append the encoded samples to `out`. Formats with a fixed sample
+    /// rate (G.711) are resampled to it first; streams should produce audio
+    /// at that rate instead, as each call is resampled on its own.
+    pub fn encode_into(
+        &self,
+        samples: &[f32],
+        format: AudioFormat,
+        out: &mut BytesMut,
+    ) -> Result<()> {
+        let samples = self.normalized(samples);
+        match format.required_sample_rate() {
+            Some(rate) if rate != self.sample_rate => {
+                let resampled = resample(&samples, self.sample_rate, rate)?;
+                self.encode_samples(&resampled, format, out)
             }
+            _ => self.encode_samples(&samples, format, out),
+        }
+    }
+
+    /// The samples with loudness normalization applied, if configured
+    fn normalized<'a>(&self, samples: &'a [f32]) -> Cow<'a, [f32]> {
+        let Some(config) = &self.loudness else {
+            return Cow::Borrowed(samples);
+        };
+        let mut normalized = samples.to_vec();
+        if let Some(gain_db) = normalize_loudness(&mut normalized, self.sample_rate, config) {
+            debug!(
+                "Applied {:.1} dB gain towards {} LUFS",
+                gain_db, config.target_lufs
+            );
+        }
+        Cow::Owned(normalized)
+    }
 
-            writer
-                .finalize()
-                .map_err(|e| Error::AudioError(e.to_string()))?;
+    fn encode_samples(
+        &self,
+        samples: &[f32],
+        format: AudioFormat,
+        out: &mut BytesMut,
+    ) -> Result<()> {
+        match format {
+            AudioFormat::Wav => self.encode_wav(samples, out),
+            AudioFormat::RawF32 => {
+                encode_raw_f32(samples, out);
+                Ok(())
+            }
+            AudioFormat::RawI16 => {
+                encode_raw_i16(samples, out);
+                Ok(())
+            }
+            AudioFormat::L16 => {
+                encode_l16(samples, out);
+                Ok(())
+            }
+            AudioFormat::MuLaw => {
+                encode_g711(samples, out, g711::linear_to_mulaw);
+                Ok(())
+            }
+            AudioFormat::ALaw => {
+                encode_g711(samples, out, g711::linear_to_alaw);
+                Ok(())
+            }
         }
+    }
+
+    /// Encode to WAV at the configured bit depth
+    fn encode_wav(&self, samples: &[f32], out: &mut BytesMut) -> Result<()> {
+        let data_len = u32::try_from(samples.len() * self.bit_depth.bytes())
+            .ok()
+            .filter(|len| *len <= u32::MAX - WAV_HEADER_LEN as u32)
+            .ok_or_else(|| Error::AudioError("Audio too long for a WAV file".into()))?;
+        out.reserve(WAV_HEADER_LEN + data_len as usize);
+        self.put_wav_header(data_len + WAV_HEADER_LEN as u32 - 8, data_len, out);
+        self.put_wav_data(samples, out);
 
         debug!(
             "Encoded {} samples to WAV ({} bytes)",
             samples.len(),
-            buffer.get_ref().len()
+            WAV_HEADER_LEN + data_len as usize
         );
-        Ok(buffer.into_inner())
+        Ok(())
     }
 
-    /// Encode to raw f32 samples
-    fn encode_raw_f32(&self, samples: &[f32]) -> Result<Vec<u8>> {
-        let mut bytes = Vec::with_capacity(samples.len() * 4);
-        for &sample in samples {
-            bytes.extend_from_slice(&sample.to_le_bytes());
-        }
-        Ok(bytes)
+    /// Write a WAV header with the given RIFF and data sizes
+    fn put_wav_header(&self, riff_len: u32, data_len: u32, out: &mut BytesMut) {
+        let sample_bytes = self.bit_depth.bytes() as u16;
+        let block_align = self.channels * sample_bytes;
+        // WAVE_FORMAT_PCM or WAVE_FORMAT_IEEE_FLOAT
+        let format_tag = match self.bit_depth {
+            BitDepth::Int16 | BitDepth::Int24 => 1,
+            BitDepth::Float32 => 3,
+        };
+        out.put_slice(b"RIFF");
+        out.put_u32_le(riff_len);
+        out.put_slice(b"WAVEfmt ");
+        out.put_u32_le(16);
+        out.put_u16_le(format_tag);
+        out.put_u16_le(self.channels);
+        out.put_u32_le(self.sample_rate);
+        out.put_u32_le(self.sample_rate * block_align as u32);
+        out.put_u16_le(block_align);
+        out.put_u16_le(sample_bytes * 8);
+        out.put_slice(b"data");
+        out.put_u32_le(data_len);
     }
 
-    /// Encode to raw i16 samples
-    fn encode_raw_i16(&self, samples: &[f32]) -> Result<Vec<u8>> {
-        let mut bytes = Vec::with_capacity(samples.len() * 2);
-        for &sample in samples {
-            let sample_i16 = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
-            bytes.extend_from_slice(&sample_i16.to_le_bytes());
+    /// Write the WAV sample data at the configured bit depth
+    fn put_wav_data(&self, samples: &[f32], out: &mut BytesMut) {
+        match self.bit_depth {
+            BitDepth::Int16 => encode_raw_i16(samples, out),
+            BitDepth::Int24 => encode_raw_i24(samples, out),
+            BitDepth::Float32 => encode_raw_f32(samples, out),
         }
-        Ok(bytes)
     }
 
     /// Get content type for format
@@ -101,10 +273,140 @@ impl AudioEncoder {
             AudioFormat::Wav => "audio/wav",
             AudioFormat::RawF32 => "application/octet-stream",
             AudioFormat::RawI16 => "application/octet-stream",
+            AudioFormat::L16 => "audio/L16",
+            AudioFormat::MuLaw => "audio/basic",
+            AudioFormat::ALaw => "audio/x-alaw-basic",
+        }
+    }
+}
+
+/// Length of the canonical PCM WAV header
+const WAV_HEADER_LEN: usize = 44;
+
+/// Sample rate of G.711 telephony audio
+const G711_SAMPLE_RATE: u32 = 8000;
+
+/// RIFF and data size of a WAV stream whose length isn't known up front
+const STREAMING_WAV_LEN: u32 = u32::MAX;
+
+/// Samples converted per pass, keeping the scratch on the stack
+const CONVERT_BLOCK: usize = 1024;
+
+/// Append raw little-endian f32 samples
+fn encode_raw_f32(samples: &[f32], out: &mut BytesMut) {
+    out.reserve(samples.len() * 4);
+    if cfg!(target_endian = "little") {
+        // SAFETY: f32 has no padding and u8 has no alignment requirement
+        out.put_slice(unsafe {
+            std::slice::from_raw_parts(samples.as_ptr().cast::<u8>(), samples.len() * 4)
+        });
+    } else {
+        for &sample in samples {
+            out.put_f32_le(sample);
+        }
+    }
+}
+
+/// Append samples converted to little-endian i16
+fn encode_raw_i16(samples: &[f32], out: &mut BytesMut) {
+    out.reserve(samples.len() * 2);
+    let mut block = [0i16; CONVERT_BLOCK];
+    for chunk in samples.chunks(CONVERT_BLOCK) {
+        let converted = &mut block[..chunk.len()];
+        simd::f32_to_i16_into(chunk, converted);
+        if cfg!(target_endian = "little") {
+            // SAFETY: i16 has no padding and u8 has no alignment requirement
+            out.put_slice(unsafe {
+                std::slice::from_raw_parts(converted.as_ptr().cast::<u8>(), converted.len() * 2)
+            });
+        } else {
+            for &sample in converted.iter() {
+                out.put_i16_le(sample);
+            }
+        }
+    }
+}
+
+/// Append samples converted to big-endian i16
+fn encode_l16(samples: &[f32], out: &mut BytesMut) {
+    out.reserve(samples.len() * 2);
+    let mut block = [0i16; CONVERT_BLOCK];
+    for chunk in samples.chunks(CONVERT_BLOCK) {
+        let converted = &mut block[..chunk.len()];
+        simd::f32_to_i16_into(chunk, converted);
+        for &sample in converted.iter() {
+            out.put_i16(sample);
+        }
+    }
+}
+
+/// Append samples companded to one G.711 byte each
+fn encode_g711(samples: &[f32], out: &mut BytesMut, compress: fn(i16) -> u8) {
+    out.reserve(samples.len());
+    let mut block = [0i16; CONVERT_BLOCK];
+    for chunk in samples.chunks(CONVERT_BLOCK) {
+        let converted = &mut block[..chunk.len()];
+        simd::f32_to_i16_into(chunk, converted);
+        for &sample in converted.iter() {
+            out.put_u8(compress(sample));
         }
     }
 }
 
+/// Append samples converted to little-endian 24-bit integers
+fn encode_raw_i24(samples: &[f32], out: &mut BytesMut) {
+    const SCALE: f32 = 8_388_607.0;
+    out.reserve(samples.len() * 3);
+    for &sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * SCALE).round() as i32;
+        out.put_slice(&value.to_le_bytes()[..3]);
+    }
+}
+
+/// Encodes the successive chunks of one stream.
+///
+/// Chunks are written into a single arena whose memory is reclaimed once
+/// the previously returned chunks have been dropped, so a steady stream
+/// doesn't allocate per chunk.
+///
+/// WAV streams get one header up front with the RIFF and data sizes set to
+/// `0xFFFFFFFF` (length unknown), followed by bare samples in every chunk, which
+/// browsers and most players treat as progressive audio.
+pub struct ChunkEncoder {
+    encoder: AudioEncoder,
+    format: AudioFormat,
+    arena: BytesMut,
+    header_written: bool,
+}
+
+impl ChunkEncoder {
+    pub fn new(encoder: AudioEncoder, format: AudioFormat) -> Self {
+        Self {
+            encoder,
+            format,
+            arena: BytesMut::new(),
+            header_written: false,
+        }
+    }
+
+    /// Encode the next chunk
+    pub fn encode(&mut self, samples: &[f32]) -> Result<Bytes> {
+        if self.format == AudioFormat::Wav {
+            if !self.header_written {
+                self.encoder
+                    .put_wav_header(STREAMING_WAV_LEN, STREAMING_WAV_LEN, &mut self.arena);
+                self.header_written = true;
+            }
+            let samples = self.encoder.normalized(samples);
+            self.encoder.put_wav_data(&samples, &mut self.arena);
+        } else {
+            self.encoder
+                .encode_into(samples, self.format, &mut self.arena)?;
+        }
+        Ok(self.arena.split().freeze())
+    }
+}
+
 /// Streaming audio chunk for real-time output
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -127,3 +429,212 @@ impl EncodedChunk {
         }
     }
 }
+
+/// Decode headerless audio in one of the raw formats to samples. The sample
+/// rate is implied by the format (8 kHz for G.711) or known to the caller.
+pub fn decode_raw(bytes: &[u8], format: AudioFormat) -> Result<Vec<f32>> {
+    let samples = match format {
+        AudioFormat::Wav => {
+            return Err(Error::InvalidInput(
+                "WAV input has a header; use decode_wav".into(),
+            ))
+        }
+        AudioFormat::RawF32 => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        AudioFormat::RawI16 => bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        AudioFormat::L16 => bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        AudioFormat::MuLaw => bytes
+            .iter()
+            .map(|&b| g711::mulaw_to_linear(b) as f32 / 32768.0)
+            .collect(),
+        AudioFormat::ALaw => bytes
+            .iter()
+            .map(|&b| g711::alaw_to_linear(b) as f32 / 32768.0)
+            .collect(),
+    };
+    Ok(samples)
+}
+
+/// Decode a WAV file to mono samples and its sample rate
+pub fn decode_wav(bytes: &[u8]) -> Result<(Vec<f32>, u32)> {
+    let mut reader = hound::WavReader::new(Cursor::new(bytes))
+        .map_err(|e| Error::InvalidAudio(format!("Failed to parse WAV: {}", e)))?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let max_val = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .filter_map(|s| s.ok())
+                .map(|s| s as f32 / max_val)
+                .collect()
+        }
+        hound::SampleFormat::Float => reader.samples::<f32>().filter_map(|s| s.ok()).collect(),
+    };
+    Ok((
+        downmix_to_mono(&interleaved, spec.channels),
+        spec.sample_rate,
+    ))
+}
+
+/// Re-encode WAV audio as mono at `sample_rate`. `None` when it is mono at
+/// that rate already, or not WAV, and can be passed on as it is.
+pub fn conform_wav(bytes: &[u8], sample_rate: u32) -> Result<Option<Vec<u8>>> {
+    let Ok(reader) = hound::WavReader::new(Cursor::new(bytes)) else {
+        return Ok(None);
+    };
+    let spec = reader.spec();
+    if spec.sample_rate == sample_rate && spec.channels == 1 {
+        return Ok(None);
+    }
+    let (samples, rate) = decode_wav(bytes)?;
+    let samples = resample(&samples, rate, sample_rate)?;
+    AudioEncoder::new(sample_rate, 1)
+        .encode(&samples, AudioFormat::Wav)
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_matches_hound() {
+        let samples: Vec<f32> = (0..480).map(|i| (i as f32 * 0.05).sin() * 0.8).collect();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 24000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut expected = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut expected, spec).unwrap();
+        for sample in simd::f32_to_i16(&samples) {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let encoder = AudioEncoder::new(24000, 1);
+        let wav = encoder.encode(&samples, AudioFormat::Wav).unwrap();
+        assert_eq!(wav, expected.into_inner());
+    }
+
+    #[test]
+    fn test_conform_wav() {
+        let samples = vec![0.25f32; 44100];
+        let wav = AudioEncoder::new(44100, 2)
+            .encode(&samples, AudioFormat::Wav)
+            .unwrap();
+        let conformed = conform_wav(&wav, 16000).unwrap().unwrap();
+        let (mono, rate) = decode_wav(&conformed).unwrap();
+        assert_eq!(rate, 16000);
+        assert_eq!(mono.len(), 8000);
+
+        assert!(conform_wav(&conformed, 16000).unwrap().is_none());
+        assert!(conform_wav(b"ID3 not a wav", 16000).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chunk_encoder_reuses_arena() {
+        let samples = vec![0.5f32; 256];
+        let mut chunks = ChunkEncoder::new(AudioEncoder::new(24000, 1), AudioFormat::RawI16);
+        let first = chunks.encode(&samples).unwrap();
+        let ptr = first.as_ptr();
+        assert_eq!(first.len(), 512);
+        drop(first);
+        let second = chunks.encode(&samples).unwrap();
+        assert_eq!(second.as_ptr(), ptr);
+
+        let buffer = SampleBuffer::from(samples);
+        let raw = AudioEncoder::new(24000, 1)
+            .encode_buffer(&buffer, AudioFormat::RawF32)
+            .unwrap();
+        assert_eq!(&raw[..4], &0.5f32.to_le_bytes());
+    }
+    #[test]
+    fn test_streaming_wav_has_single_open_header() {
+        let samples = vec![0.25f32; 100];
+        let mut chunks = ChunkEncoder::new(AudioEncoder::new(24000, 1), AudioFormat::Wav);
+        let first = chunks.encode(&samples).unwrap();
+        let second = chunks.encode(&samples).unwrap();
+        assert_eq!(first.len(), WAV_HEADER_LEN + 200);
+        assert_eq!(second.len(), 200);
+        assert_eq!(&first[4..8], &u32::MAX.to_le_bytes());
+        assert_eq!(&first[40..44], &u32::MAX.to_le_bytes());
+
+        // The header matches a complete file apart from the sizes
+        let whole = AudioEncoder::new(24000, 1)
+            .encode(&samples, AudioFormat::Wav)
+            .unwrap();
+        assert_eq!(&first[8..40], &whole[8..40]);
+        assert_eq!(&first[WAV_HEADER_LEN..], &whole[WAV_HEADER_LEN..]);
+    }
+    #[test]
+    fn test_wav_bit_depths_round_trip() {
+        let samples = vec![0.0f32, 0.5, -0.5, 1.0, -1.0];
+        for (bit_depth, bits) in [
+            (BitDepth::Int16, 16),
+            (BitDepth::Int24, 24),
+            (BitDepth::Float32, 32),
+        ] {
+            let wav = AudioEncoder::new(8000, 1)
+                .with_bit_depth(bit_depth)
+                .encode(&samples, AudioFormat::Wav)
+                .unwrap();
+            assert_eq!(
+                wav.len(),
+                WAV_HEADER_LEN + samples.len() * bit_depth.bytes()
+            );
+            let spec = hound::WavReader::new(Cursor::new(&wav)).unwrap().spec();
+            assert_eq!(spec.bits_per_sample, bits);
+            assert_eq!(spec.sample_rate, 8000);
+
+            let (decoded, rate) = decode_wav(&wav).unwrap();
+            assert_eq!(rate, 8000);
+            for (a, b) in decoded.iter().zip(&samples) {
+                assert!((a - b).abs() < 1e-3, "{:?}: {} vs {}", bit_depth, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_depth_parsing() {
+        let parse = |json: &str| serde_json::from_str::<BitDepth>(json);
+        assert_eq!(parse("16").unwrap(), BitDepth::Int16);
+        assert_eq!(parse("\"24\"").unwrap(), BitDepth::Int24);
+        assert_eq!(parse("\"32f\"").unwrap(), BitDepth::Float32);
+        assert!(parse("8").is_err());
+    }
+    #[test]
+    fn test_g711_round_trip() {
+        let samples: Vec<f32> = (0..160).map(|i| (i as f32 * 0.2).sin() * 0.5).collect();
+        let encoder = AudioEncoder::new(8000, 1);
+        let at_24k = AudioEncoder::new(24000, 1).encode(&[0.0; 480], AudioFormat::MuLaw);
+        assert_eq!(at_24k.unwrap().len(), 160);
+        for format in [AudioFormat::MuLaw, AudioFormat::ALaw] {
+            assert_eq!(format.required_sample_rate(), Some(8000));
+            let bytes = encoder.encode(&samples, format).unwrap();
+            assert_eq!(bytes.len(), samples.len());
+            let decoded = decode_raw(&bytes, format).unwrap();
+            for (a, b) in decoded.iter().zip(&samples) {
+                assert!((a - b).abs() < 0.02, "{:?}: {} vs {}", format, a, b);
+            }
+        }
+    }
+    #[test]
+    fn test_l16_is_big_endian() {
+        let encoder = AudioEncoder::new(24000, 1);
+        let bytes = encoder.encode(&[0.5, -1.0], AudioFormat::L16).unwrap();
+        assert_eq!(bytes, [0x3F, 0xFF, 0x80, 0x01]);
+        let decoded = decode_raw(&bytes, AudioFormat::L16).unwrap();
+        assert!((decoded[0] - 0.5).abs() < 1e-4);
+    }
+}
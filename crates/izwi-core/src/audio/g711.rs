@@ -0,0 +1,104 @@
+//! G.711 mu-law and A-law companding (ITU-T G.711) for telephony output
+
+/// Bias added to magnitudes before mu-law segment lookup
+const MULAW_BIAS: i32 = 0x84;
+
+/// Largest magnitude representable in mu-law after biasing
+const MULAW_CLIP: i32 = 32635;
+
+/// Compress a 16-bit sample to a mu-law byte
+pub fn linear_to_mulaw(sample: i16) -> u8 {
+    let mut pcm = sample as i32;
+    let sign = if pcm < 0 {
+        pcm = -pcm;
+        0x80
+    } else {
+        0
+    };
+    pcm = pcm.min(MULAW_CLIP) + MULAW_BIAS;
+    let exponent = 31 - ((pcm >> 7) as u32).leading_zeros();
+    let mantissa = (pcm >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) as i32 | mantissa) as u8
+}
+
+/// Expand a mu-law byte to a 16-bit sample
+pub fn mulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i32;
+    let magnitude = (((mantissa << 3) + MULAW_BIAS) << exponent) - MULAW_BIAS;
+    if byte & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+/// Compress a 16-bit sample to an A-law byte
+pub fn linear_to_alaw(sample: i16) -> u8 {
+    // A-law works on 13-bit magnitudes
+    let mut pcm = (sample as i32) >> 3;
+    let mask = if pcm >= 0 {
+        0xD5
+    } else {
+        pcm = -pcm - 1;
+        0x55
+    };
+    // Segment ends are 0x1F, 0x3F, ..., 0xFFF
+    let segment = (32 - (pcm as u32 >> 5).leading_zeros()) as i32;
+    if segment >= 8 {
+        return (0x7F ^ mask) as u8;
+    }
+    let shift = if segment < 2 { 1 } else { segment };
+    let value = (segment << 4) | ((pcm >> shift) & 0x0F);
+    (value ^ mask) as u8
+}
+
+/// Expand an A-law byte to a 16-bit sample
+pub fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let segment = (byte & 0x70) >> 4;
+    let mut magnitude = ((byte & 0x0F) as i32) << 4;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+    if byte & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence_codes() {
+        assert_eq!(linear_to_mulaw(0), 0xFF);
+        assert_eq!(mulaw_to_linear(0xFF), 0);
+        assert_eq!(linear_to_alaw(0), 0xD5);
+        assert_eq!(alaw_to_linear(0xD5), 8);
+    }
+
+    #[test]
+    fn test_round_trip_within_quantization() {
+        for sample in (i16::MIN..=i16::MAX).step_by(7) {
+            // Step size doubles per segment, so error scales with magnitude
+            let tolerance = (sample as i32).abs() / 8 + 16;
+            let mulaw = mulaw_to_linear(linear_to_mulaw(sample)) - sample;
+            let alaw = alaw_to_linear(linear_to_alaw(sample)) - sample;
+            assert!((mulaw as i32).abs() <= tolerance, "mu-law {}", sample);
+            assert!((alaw as i32).abs() <= tolerance, "A-law {}", sample);
+        }
+        // Every code decodes to a value that encodes back to itself
+        for byte in 0..=255u8 {
+            assert_eq!(linear_to_alaw(alaw_to_linear(byte)), byte);
+            let mulaw = linear_to_mulaw(mulaw_to_linear(byte));
+            // 0x7F and 0xFF both mean zero
+            assert!(mulaw == byte || mulaw_to_linear(byte) == 0, "{:#x}", byte);
+        }
+    }
+}
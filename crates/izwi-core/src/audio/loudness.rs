@@ -0,0 +1,388 @@
+//! Loudness measurement and normalization (ITU-R BS.1770 / EBU R128)
+
+use std::collections::VecDeque;
+
+use super::simd;
+
+/// Absolute gating threshold for integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Relative gating threshold below the ungated loudness
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// Gating blocks are four 100ms steps (400ms with 75% overlap)
+const STEPS_PER_BLOCK: usize = 4;
+
+/// Loudness normalization settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessConfig {
+    /// Target integrated loudness in LUFS
+    pub target_lufs: f32,
+    /// Output ceiling enforced by the soft limiter (dBFS)
+    pub ceiling_db: f32,
+    /// Maximum gain applied in either direction (dB)
+    pub max_gain_db: f32,
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            target_lufs: -16.0,
+            ceiling_db: -1.0,
+            max_gain_db: 24.0,
+        }
+    }
+}
+
+impl LoudnessConfig {
+    /// Config targeting the given loudness with default limiter settings
+    pub fn with_target(target_lufs: f32) -> Self {
+        Self {
+            target_lufs,
+            ..Self::default()
+        }
+    }
+
+    fn ceiling_linear(&self) -> f32 {
+        db_to_linear(self.ceiling_db)
+    }
+
+    /// Linear gain that moves `measured` to the target, clamped to `max_gain_db`
+    fn gain_for(&self, measured_lufs: f32) -> f32 {
+        let gain_db = (self.target_lufs - measured_lufs).clamp(-self.max_gain_db, self.max_gain_db);
+        db_to_linear(gain_db)
+    }
+}
+
+/// Direct form I biquad section
+#[derive(Debug, Clone, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, input: f64) -> f64 {
+        let out = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [out, self.y[0]];
+        out
+    }
+
+    fn reset(&mut self) {
+        self.x = [0.0; 2];
+        self.y = [0.0; 2];
+    }
+}
+
+/// K-weighting filter pair for an arbitrary sample rate
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    // Stage 1: high-shelf modelling the acoustic effect of the head
+    let f0 = 1_681.974_450_955_533;
+    let gain_db = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    // Stage 2: RLB high-pass
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    [shelf, highpass]
+}
+
+fn energy_to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Convert decibels to a linear amplitude factor
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Incremental gated loudness meter for mono audio
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    filters: [Biquad; 2],
+    step_len: usize,
+    step_energy: f64,
+    step_fill: usize,
+    recent_steps: VecDeque<f64>,
+    block_energies: Vec<f64>,
+    total_energy: f64,
+    total_samples: usize,
+}
+
+impl LoudnessMeter {
+    /// Create a meter for the given sample rate
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            filters: k_weighting(sample_rate),
+            step_len: (sample_rate as usize / 10).max(1),
+            step_energy: 0.0,
+            step_fill: 0,
+            recent_steps: VecDeque::with_capacity(STEPS_PER_BLOCK),
+            block_energies: Vec::new(),
+            total_energy: 0.0,
+            total_samples: 0,
+        }
+    }
+
+    /// Feed samples into the meter
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            let mut weighted = sample as f64;
+            for filter in self.filters.iter_mut() {
+                weighted = filter.process(weighted);
+            }
+            let energy = weighted * weighted;
+            self.step_energy += energy;
+            self.total_energy += energy;
+            self.step_fill += 1;
+            self.total_samples += 1;
+
+            if self.step_fill == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            self.recent_steps.pop_front();
+        }
+        self.recent_steps
+            .push_back(self.step_energy / self.step_len as f64);
+        self.step_energy = 0.0;
+        self.step_fill = 0;
+
+        if self.recent_steps.len() == STEPS_PER_BLOCK {
+            let block = self.recent_steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64;
+            self.block_energies.push(block);
+        }
+    }
+
+    /// Gated integrated loudness of everything pushed so far.
+    ///
+    /// Inputs shorter than one gating block fall back to ungated loudness.
+    /// Returns `None` for silence or empty input.
+    pub fn integrated_lufs(&self) -> Option<f32> {
+        if self.block_energies.is_empty() {
+            if self.total_samples == 0 || self.total_energy <= 0.0 {
+                return None;
+            }
+            let lufs = energy_to_lufs(self.total_energy / self.total_samples as f64);
+            return (lufs > ABSOLUTE_GATE_LUFS).then_some(lufs as f32);
+        }
+
+        let gated: Vec<f64> = self
+            .block_energies
+            .iter()
+            .copied()
+            .filter(|&e| e > 0.0 && energy_to_lufs(e) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if gated.is_empty() {
+            return None;
+        }
+
+        let ungated = gated.iter().sum::<f64>() / gated.len() as f64;
+        let relative_gate = energy_to_lufs(ungated) + RELATIVE_GATE_LU;
+
+        let (sum, count) = gated
+            .iter()
+            .filter(|&&e| energy_to_lufs(e) > relative_gate)
+            .fold((0.0, 0usize), |(s, c), &e| (s + e, c + 1));
+        if count == 0 {
+            return None;
+        }
+
+        Some(energy_to_lufs(sum / count as f64) as f32)
+    }
+
+    /// Clear all measurement state
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.reset();
+        }
+        self.step_energy = 0.0;
+        self.step_fill = 0;
+        self.recent_steps.clear();
+        self.block_energies.clear();
+        self.total_energy = 0.0;
+        self.total_samples = 0;
+    }
+}
+
+/// Measure the integrated loudness of a complete mono buffer
+pub fn measure_loudness(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let mut meter = LoudnessMeter::new(sample_rate);
+    meter.push(samples);
+    meter.integrated_lufs()
+}
+
+/// Soft-knee limiter: transparent below half the ceiling, never exceeds it
+pub fn soft_limit(sample: f32, ceiling: f32) -> f32 {
+    let knee = ceiling * 0.5;
+    let magnitude = sample.abs();
+    if magnitude <= knee {
+        return sample;
+    }
+    let range = ceiling - knee;
+    let limited = knee + range * ((magnitude - knee) / range).tanh();
+    limited.copysign(sample)
+}
+
+/// Normalize a complete mono buffer in place.
+///
+/// Returns the applied gain in dB, or `None` if the buffer is silent.
+pub fn normalize_loudness(
+    samples: &mut [f32],
+    sample_rate: u32,
+    config: &LoudnessConfig,
+) -> Option<f32> {
+    let measured = measure_loudness(samples, sample_rate)?;
+    let gain = config.gain_for(measured);
+    simd::apply_gain(samples, gain, 0.0, config.ceiling_linear());
+    Some(20.0 * gain.log10())
+}
+
+/// Streaming loudness normalizer.
+///
+/// The gain tracks the running integrated loudness of the stream and is
+/// ramped across each chunk to avoid zipper noise.
+#[derive(Debug, Clone)]
+pub struct LoudnessNormalizer {
+    config: LoudnessConfig,
+    meter: LoudnessMeter,
+    current_gain: f32,
+}
+
+impl LoudnessNormalizer {
+    /// Create a normalizer for the given sample rate
+    pub fn new(config: LoudnessConfig, sample_rate: u32) -> Self {
+        Self {
+            config,
+            meter: LoudnessMeter::new(sample_rate),
+            current_gain: 1.0,
+        }
+    }
+
+    /// Normalization settings
+    pub fn config(&self) -> &LoudnessConfig {
+        &self.config
+    }
+
+    /// Process a chunk in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        self.meter.push(samples);
+        let target_gain = self
+            .meter
+            .integrated_lufs()
+            .map(|lufs| self.config.gain_for(lufs))
+            .unwrap_or(self.current_gain);
+
+        let ceiling = self.config.ceiling_linear();
+        let start_gain = self.current_gain;
+        let step = (target_gain - start_gain) / samples.len() as f32;
+        simd::apply_gain(samples, start_gain, step, ceiling);
+        self.current_gain = target_gain;
+    }
+
+    /// Reset measurement and gain state
+    pub fn reset(&mut self) {
+        self.meter.reset();
+        self.current_gain = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, freq: f32, rate: u32, secs: f32) -> Vec<f32> {
+        let len = (rate as f32 * secs) as usize;
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_measure_reference_tone() {
+        // A full-scale 997 Hz sine reads about -3.01 LUFS
+        let tone = sine(1.0, 997.0, 48000, 2.0);
+        let lufs = measure_loudness(&tone, 48000).unwrap();
+        assert!((lufs + 3.01).abs() < 0.1, "measured {}", lufs);
+    }
+
+    #[test]
+    fn test_silence_is_unmeasurable() {
+        assert!(measure_loudness(&vec![0.0; 24000], 24000).is_none());
+        assert!(measure_loudness(&[], 24000).is_none());
+    }
+
+    #[test]
+    fn test_normalize_hits_target() {
+        let mut quiet = sine(0.05, 440.0, 24000, 3.0);
+        let config = LoudnessConfig::with_target(-20.0);
+        let gain_db = normalize_loudness(&mut quiet, 24000, &config).unwrap();
+        assert!(gain_db > 0.0);
+
+        let lufs = measure_loudness(&quiet, 24000).unwrap();
+        assert!((lufs + 20.0).abs() < 0.5, "measured {}", lufs);
+    }
+
+    #[test]
+    fn test_limiter_respects_ceiling() {
+        let mut loud = sine(0.9, 440.0, 24000, 1.0);
+        let config = LoudnessConfig {
+            target_lufs: -3.0,
+            ceiling_db: -1.0,
+            max_gain_db: 24.0,
+        };
+        normalize_loudness(&mut loud, 24000, &config);
+        let ceiling = db_to_linear(-1.0);
+        assert!(loud.iter().all(|s| s.abs() <= ceiling));
+    }
+
+    #[test]
+    fn test_streaming_normalizer_converges() {
+        let tone = sine(0.05, 440.0, 24000, 4.0);
+        let mut normalizer = LoudnessNormalizer::new(LoudnessConfig::with_target(-18.0), 24000);
+
+        let mut output = Vec::new();
+        for chunk in tone.chunks(2400) {
+            let mut chunk = chunk.to_vec();
+            normalizer.process(&mut chunk);
+            output.extend(chunk);
+        }
+
+        let tail = measure_loudness(&output[output.len() - 24000..], 24000).unwrap();
+        assert!((tail + 18.0).abs() < 0.5, "measured {}", tail);
+    }
+}
@@ -0,0 +1,155 @@
+//! Log-mel spectrogram features
+//!
+//! Frames are causal: frame `t` covers the `n_fft` samples ending at
+//! `(t + 1) * hop_length`, zero-padded on the left, so features line up with
+//! the codec's token columns.
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+/// Spectrogram settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MelConfig {
+    pub n_fft: usize,
+    pub hop_length: usize,
+    pub num_mels: usize,
+    /// Lowest filter frequency in Hz (the highest is Nyquist)
+    pub f_min: f32,
+}
+
+/// Computes log-mel frames with a precomputed window, filterbank and FFT plan
+pub struct MelSpectrogram {
+    config: MelConfig,
+    window: Vec<f32>,
+    /// `[mel][bin]` triangular filters
+    filterbank: Vec<Vec<f32>>,
+    fft: Arc<dyn Fft<f32>>,
+}
+
+impl MelSpectrogram {
+    pub fn new(config: MelConfig, sample_rate: u32) -> Self {
+        let window = (0..config.n_fft)
+            .map(|i| {
+                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / config.n_fft as f32).cos())
+            })
+            .collect();
+        let filterbank = mel_filterbank(&config, sample_rate);
+        let fft = FftPlanner::new().plan_fft_forward(config.n_fft);
+        Self {
+            config,
+            window,
+            filterbank,
+            fft,
+        }
+    }
+
+    pub fn config(&self) -> &MelConfig {
+        &self.config
+    }
+
+    /// Number of frames produced for `len` samples
+    pub fn num_frames(&self, len: usize) -> usize {
+        len.div_ceil(self.config.hop_length)
+    }
+
+    /// Natural-log mel energies, `[frame][mel]`
+    pub fn compute(&self, samples: &[f32]) -> Vec<f32> {
+        let n_fft = self.config.n_fft;
+        let num_frames = self.num_frames(samples.len());
+        let mut features = Vec::with_capacity(num_frames * self.config.num_mels);
+        let mut buffer = vec![Complex::new(0.0f32, 0.0); n_fft];
+        let mut power = vec![0.0f32; n_fft / 2 + 1];
+
+        for t in 0..num_frames {
+            let end = (t + 1) * self.config.hop_length;
+            for (i, value) in buffer.iter_mut().enumerate() {
+                let sample = (end + i)
+                    .checked_sub(n_fft)
+                    .and_then(|s| samples.get(s))
+                    .copied()
+                    .unwrap_or(0.0);
+                *value = Complex::new(sample * self.window[i], 0.0);
+            }
+            self.fft.process(&mut buffer);
+            for (p, value) in power.iter_mut().zip(&buffer) {
+                *p = value.norm_sqr();
+            }
+            features.extend(self.filterbank.iter().map(|filter| {
+                let energy: f32 = filter.iter().zip(&power).map(|(f, p)| f * p).sum();
+                energy.max(1e-10).ln()
+            }));
+        }
+        features
+    }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10.0f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular filters evaluated at each bin's frequency, so narrow filters
+/// never collapse to zero width
+fn mel_filterbank(config: &MelConfig, sample_rate: u32) -> Vec<Vec<f32>> {
+    let num_bins = config.n_fft / 2 + 1;
+    let nyquist = sample_rate as f32 / 2.0;
+    let (mel_min, mel_max) = (hz_to_mel(config.f_min), hz_to_mel(nyquist));
+    let edges: Vec<f32> = (0..config.num_mels + 2)
+        .map(|i| mel_to_hz(mel_min + (mel_max - mel_min) * i as f32 / (config.num_mels + 1) as f32))
+        .collect();
+
+    edges
+        .windows(3)
+        .map(|edge| {
+            let (left, center, right) = (edge[0], edge[1], edge[2]);
+            (0..num_bins)
+                .map(|bin| {
+                    let hz = bin as f32 * nyquist / (num_bins - 1) as f32;
+                    let rising = (hz - left) / (center - left);
+                    let falling = (right - hz) / (right - center);
+                    rising.min(falling).max(0.0)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_peaks_in_matching_mel_bin() {
+        let rate = 16000;
+        let config = MelConfig {
+            n_fft: 512,
+            hop_length: 160,
+            num_mels: 40,
+            f_min: 0.0,
+        };
+        let mel = MelSpectrogram::new(config, rate);
+        let tone = |hz: f32| -> Vec<f32> {
+            (0..rate)
+                .map(|i| (2.0 * std::f32::consts::PI * hz * i as f32 / rate as f32).sin())
+                .collect()
+        };
+        let peak_bin = |samples: &[f32]| {
+            let features = mel.compute(samples);
+            assert_eq!(features.len(), 100 * 40);
+            // A frame well past the zero-padded start
+            let frame = &features[50 * 40..51 * 40];
+            (0..40)
+                .max_by(|&a, &b| frame[a].total_cmp(&frame[b]))
+                .unwrap()
+        };
+        let low = peak_bin(&tone(300.0));
+        let high = peak_bin(&tone(3000.0));
+        assert!(low < high, "{} vs {}", low, high);
+        let expected = (hz_to_mel(3000.0) / hz_to_mel(8000.0) * 41.0).round() as usize - 1;
+        assert!(high.abs_diff(expected) <= 1, "{} vs {}", high, expected);
+    }
+}
@@ -0,0 +1,229 @@
+//! Memory accounting for buffered output audio
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::{Error, Result};
+
+/// Bytes occupied by one buffered f32 sample
+pub const BYTES_PER_SAMPLE: usize = std::mem::size_of::<f32>();
+
+/// What to do when a buffer would exceed its memory cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Fail the push; the request should be aborted
+    #[default]
+    Reject,
+    /// Discard the oldest buffered samples to make room
+    DropOldest,
+}
+
+/// Process-wide tracker for bytes held in output buffers
+#[derive(Debug)]
+pub struct OutputMemoryTracker {
+    limit_bytes: usize,
+    used_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+impl OutputMemoryTracker {
+    /// Create a tracker with a global cap (0 disables the cap)
+    pub fn new(limit_bytes: usize) -> Self {
+        Self {
+            limit_bytes,
+            used_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Try to reserve `bytes`; fails without side effects if over the cap
+    pub fn try_reserve(&self, bytes: usize) -> bool {
+        let mut current = self.used_bytes.load(Ordering::Relaxed);
+        loop {
+            let next = current + bytes;
+            if self.limit_bytes > 0 && next > self.limit_bytes {
+                return false;
+            }
+            match self.used_bytes.compare_exchange_weak(
+                current,
+                next,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.peak_bytes.fetch_max(next, Ordering::Relaxed);
+                    return true;
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Return previously reserved bytes
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .used_bytes
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Bytes available before hitting the cap
+    pub fn available(&self) -> usize {
+        if self.limit_bytes == 0 {
+            return usize::MAX;
+        }
+        self.limit_bytes.saturating_sub(self.used())
+    }
+
+    /// Bytes currently reserved
+    pub fn used(&self) -> usize {
+        self.used_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Snapshot of current usage
+    pub fn stats(&self) -> OutputMemoryStats {
+        OutputMemoryStats {
+            used_bytes: self.used(),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            limit_bytes: self.limit_bytes,
+        }
+    }
+}
+
+impl Default for OutputMemoryTracker {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Make room for `incoming` samples in a buffer already holding `buffered`,
+/// under a per-buffer cap of `max_buffer_bytes` (0 = none) and the global
+/// cap of `tracker`.
+///
+/// With `DropOldest`, `drop_oldest(n)` is called to discard the oldest `n`
+/// buffered samples, whose memory is returned to `tracker` here. Returns
+/// how many of the newest incoming samples fit; their memory is reserved.
+pub fn reserve_samples(
+    buffered: usize,
+    incoming: usize,
+    max_buffer_bytes: usize,
+    tracker: Option<&OutputMemoryTracker>,
+    policy: OverflowPolicy,
+    mut drop_oldest: impl FnMut(usize),
+) -> Result<usize> {
+    let release = |count: usize| {
+        if let Some(tracker) = tracker {
+            tracker.release(count * BYTES_PER_SAMPLE);
+        }
+    };
+    let mut buffered = buffered;
+    let limit = if max_buffer_bytes > 0 {
+        max_buffer_bytes / BYTES_PER_SAMPLE
+    } else {
+        usize::MAX
+    };
+
+    // Per-buffer cap
+    if buffered.saturating_add(incoming) > limit {
+        if policy == OverflowPolicy::Reject {
+            return Err(Error::BufferOverflow(format!(
+                "request buffer would exceed {} bytes",
+                max_buffer_bytes
+            )));
+        }
+        let excess = (buffered + incoming.min(limit)).saturating_sub(limit);
+        drop_oldest(excess);
+        release(excess);
+        buffered -= excess;
+    }
+    let incoming = incoming.min(limit);
+
+    // Global cap
+    let Some(tracker) = tracker else {
+        return Ok(incoming);
+    };
+    let needed = incoming * BYTES_PER_SAMPLE;
+    if tracker.try_reserve(needed) {
+        return Ok(incoming);
+    }
+    if policy == OverflowPolicy::DropOldest {
+        let shortfall = needed.saturating_sub(tracker.available());
+        let count = shortfall.div_ceil(BYTES_PER_SAMPLE).min(buffered);
+        drop_oldest(count);
+        release(count);
+        if tracker.try_reserve(needed) {
+            return Ok(incoming);
+        }
+    }
+    Err(Error::BufferOverflow(format!(
+        "global output buffer limit of {} bytes reached",
+        tracker.stats().limit_bytes
+    )))
+}
+
+/// Output buffer memory statistics
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OutputMemoryStats {
+    /// Bytes currently held in output buffers
+    pub used_bytes: usize,
+    /// High-water mark since startup
+    pub peak_bytes: usize,
+    /// Global cap (0 = unlimited)
+    pub limit_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_enforces_limit() {
+        let tracker = OutputMemoryTracker::new(1000);
+        assert!(tracker.try_reserve(600));
+        assert!(!tracker.try_reserve(500));
+        assert_eq!(tracker.used(), 600);
+        assert_eq!(tracker.available(), 400);
+
+        tracker.release(600);
+        assert!(tracker.try_reserve(1000));
+
+        let stats = tracker.stats();
+        assert_eq!(stats.used_bytes, 1000);
+        assert_eq!(stats.peak_bytes, 1000);
+    }
+
+    #[test]
+    fn test_reserve_samples() {
+        // 100 buffered samples, a 120-sample buffer cap and room for 150 globally
+        let tracker = OutputMemoryTracker::new(150 * BYTES_PER_SAMPLE);
+        assert!(tracker.try_reserve(100 * BYTES_PER_SAMPLE));
+        let mut dropped = 0;
+        let reject = reserve_samples(100, 50, 480, Some(&tracker), OverflowPolicy::Reject, |n| {
+            dropped += n
+        });
+        assert!(matches!(reject, Err(Error::BufferOverflow(_))));
+
+        // Dropping the oldest makes room under both caps
+        let keep = reserve_samples(
+            100,
+            60,
+            480,
+            Some(&tracker),
+            OverflowPolicy::DropOldest,
+            |n| dropped += n,
+        );
+        assert_eq!(keep.unwrap(), 60);
+        assert_eq!(dropped, 40);
+        assert_eq!(tracker.used(), 120 * BYTES_PER_SAMPLE);
+    }
+
+    #[test]
+    fn test_unlimited_tracker() {
+        let tracker = OutputMemoryTracker::default();
+        assert!(tracker.try_reserve(usize::MAX / 2));
+        tracker.release(usize::MAX);
+        assert_eq!(tracker.used(), 0);
+    }
+}
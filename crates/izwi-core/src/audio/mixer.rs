@@ -0,0 +1,250 @@
+//! Mixing synthesized speech over a background track
+//!
+//! The background is normalized to a fixed loudness, looped or cut to the
+//! length of the speech, faded in and out, and ducked (sidechain gain
+//! reduction) wherever the speech is voiced.
+
+use serde::{Deserialize, Serialize};
+
+use super::encoder::decode_wav;
+use super::loudness::{db_to_linear, normalize_loudness, LoudnessConfig};
+use super::resample::resample;
+use crate::error::{Error, Result};
+
+/// Background mixing settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixConfig {
+    /// Loudness of the background before ducking, in LUFS
+    pub background_lufs: f32,
+    /// Gain reduction applied to the background under speech, in dB
+    pub duck_db: f32,
+    /// Speech frames louder than this (dBFS) trigger ducking
+    pub threshold_db: f32,
+    /// Time for the ducking to reach full reduction, in milliseconds
+    pub attack_ms: u32,
+    /// Time for the background to recover after speech, in milliseconds
+    pub release_ms: u32,
+    pub fade_in_ms: u32,
+    pub fade_out_ms: u32,
+    /// Repeat a background shorter than the speech
+    pub loop_background: bool,
+}
+
+impl Default for MixConfig {
+    fn default() -> Self {
+        Self {
+            background_lufs: -30.0,
+            duck_db: -12.0,
+            threshold_db: -40.0,
+            attack_ms: 50,
+            release_ms: 400,
+            fade_in_ms: 500,
+            fade_out_ms: 1000,
+            loop_background: true,
+        }
+    }
+}
+
+impl MixConfig {
+    /// Check settings that deserialize fine but cannot be used
+    pub fn validate(&self) -> Result<()> {
+        if !(-70.0..=0.0).contains(&self.background_lufs) {
+            return Err(Error::InvalidInput(format!(
+                "background_lufs must be between -70 and 0, got {}",
+                self.background_lufs
+            )));
+        }
+        if self.duck_db > 0.0 {
+            return Err(Error::InvalidInput(format!(
+                "duck_db must not be positive, got {}",
+                self.duck_db
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Mono background audio
+#[derive(Debug, Clone)]
+pub struct BackgroundTrack {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl BackgroundTrack {
+    /// Decode a WAV file, downmixing it to mono
+    pub fn from_wav(bytes: &[u8]) -> Result<Self> {
+        let (samples, sample_rate) = decode_wav(bytes)?;
+        Ok(Self {
+            samples,
+            sample_rate,
+        })
+    }
+
+    /// Decode a base64-encoded WAV file
+    pub fn from_base64(audio_base64: &str) -> Result<Self> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(audio_base64)
+            .map_err(|e| Error::InvalidInput(format!("Invalid background audio: {}", e)))?;
+        Self::from_wav(&bytes)
+    }
+
+    /// Download a WAV file, refusing bodies over `max_bytes` (0 = unlimited)
+    pub async fn fetch(url: &str, max_bytes: usize) -> Result<Self> {
+        let mut response = reqwest::get(url).await?.error_for_status()?;
+        let too_large = || {
+            Error::InvalidInput(format!(
+                "Background track exceeds the {} byte limit",
+                max_bytes
+            ))
+        };
+        if max_bytes > 0 && response.content_length().unwrap_or(0) > max_bytes as u64 {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+            if max_bytes > 0 && bytes.len() > max_bytes {
+                return Err(too_large());
+            }
+        }
+        Self::from_wav(&bytes)
+    }
+}
+
+/// Mix `speech` over the background track, returning audio as long as the speech
+pub fn mix_background(
+    speech: &[f32],
+    sample_rate: u32,
+    track: &BackgroundTrack,
+    config: &MixConfig,
+) -> Result<Vec<f32>> {
+    if speech.is_empty() || track.samples.is_empty() {
+        return Ok(speech.to_vec());
+    }
+
+    let mut background = resample(&track.samples, track.sample_rate, sample_rate)?;
+    normalize_loudness(
+        &mut background,
+        sample_rate,
+        &LoudnessConfig::with_target(config.background_lufs),
+    );
+    let background = fit_length(&background, speech.len(), config.loop_background);
+
+    let ducking = duck_envelope(speech, sample_rate, config);
+    let fade_in = ms_to_samples(config.fade_in_ms, sample_rate);
+    let fade_out = ms_to_samples(config.fade_out_ms, sample_rate);
+    let len = speech.len();
+
+    Ok(speech
+        .iter()
+        .zip(&background)
+        .zip(&ducking)
+        .enumerate()
+        .map(|(i, ((s, b), duck))| {
+            let fade = fade_gain(i, fade_in).min(fade_gain(len - 1 - i, fade_out));
+            (s + b * duck * fade).clamp(-1.0, 1.0)
+        })
+        .collect())
+}
+
+fn ms_to_samples(ms: u32, sample_rate: u32) -> usize {
+    (sample_rate as usize * ms as usize) / 1000
+}
+
+/// Linear ramp over `len` samples
+fn fade_gain(position: usize, len: usize) -> f32 {
+    if position >= len {
+        1.0
+    } else {
+        position as f32 / len as f32
+    }
+}
+
+/// Loop or cut `background` to `len` samples (silence after it when not looping)
+fn fit_length(background: &[f32], len: usize, looped: bool) -> Vec<f32> {
+    if looped {
+        background.iter().copied().cycle().take(len).collect()
+    } else {
+        let mut fitted: Vec<f32> = background.iter().copied().take(len).collect();
+        fitted.resize(len, 0.0);
+        fitted
+    }
+}
+
+/// Per-sample background gain: `duck_db` under voiced speech, 1.0 elsewhere,
+/// smoothed with the attack and release times
+fn duck_envelope(speech: &[f32], sample_rate: u32, config: &MixConfig) -> Vec<f32> {
+    let frame_len = ms_to_samples(10, sample_rate).max(1);
+    let ducked = db_to_linear(config.duck_db);
+    let coefficient = |ms: u32| {
+        let samples = ms_to_samples(ms, sample_rate).max(1) as f32;
+        (-1.0 / samples).exp()
+    };
+    let attack = coefficient(config.attack_ms);
+    let release = coefficient(config.release_ms);
+
+    let mut gain = 1.0f32;
+    let mut envelope = Vec::with_capacity(speech.len());
+    for frame in speech.chunks(frame_len) {
+        let mean_square = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+        let level_db = 10.0 * mean_square.max(1e-12).log10();
+        let target = if level_db >= config.threshold_db {
+            ducked
+        } else {
+            1.0
+        };
+        let coeff = if target < gain { attack } else { release };
+        for _ in frame {
+            gain = target + (gain - target) * coeff;
+            envelope.push(gain);
+        }
+    }
+    envelope
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_is_ducked_under_speech() {
+        let rate = 16000;
+        // One second of silence, then one second of speech
+        let mut speech = vec![0.0f32; rate as usize];
+        speech.extend((0..rate).map(|i| 0.3 * (i as f32 * 0.05).sin()));
+        let track = BackgroundTrack {
+            samples: (0..rate / 2)
+                .map(|i| 0.2 * (i as f32 * 0.3).sin())
+                .collect(),
+            sample_rate: rate,
+        };
+        let config = MixConfig {
+            fade_in_ms: 0,
+            fade_out_ms: 0,
+            ..Default::default()
+        };
+
+        let envelope = duck_envelope(&speech, rate, &config);
+        assert!((envelope[rate as usize / 2] - 1.0).abs() < 1e-3);
+        let floor = db_to_linear(config.duck_db);
+        assert!((envelope[speech.len() - 1] - floor).abs() < 1e-2);
+
+        let mixed = mix_background(&speech, rate, &track, &config).unwrap();
+        assert_eq!(mixed.len(), speech.len());
+        // The half-second track is looped under the leading silence
+        assert!(mixed[..rate as usize].iter().any(|s| s.abs() > 0.0));
+        assert!(mixed[rate as usize / 2..rate as usize]
+            .iter()
+            .any(|s| s.abs() > 0.0));
+
+        assert!(MixConfig {
+            duck_db: 3.0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -1,9 +1,46 @@
 //! Audio processing utilities for TTS output
 
+mod buffer;
 mod codec;
+mod codec_encoder;
+mod conv;
+mod diarize;
 mod encoder;
+mod g711;
+mod loudness;
+mod mel;
+mod memory;
+mod mixer;
+mod range;
+mod resample;
+mod segment;
+mod silence;
+pub mod simd;
+mod spool;
+mod store;
 mod streaming;
 
-pub use codec::{AudioCodec, CodecConfig};
-pub use encoder::{AudioEncoder, AudioFormat};
+pub use buffer::SampleBuffer;
+pub use codec::{AudioCodec, CodecConfig, DecoderState, TOKEN_RATE_HZ};
+pub use diarize::{diarize, DiarizeConfig, SpeakerSegment};
+pub use encoder::{
+    conform_wav, decode_raw, decode_wav, AudioEncoder, AudioFormat, BitDepth, ChunkEncoder,
+};
+pub use loudness::{
+    measure_loudness, normalize_loudness, LoudnessConfig, LoudnessMeter, LoudnessNormalizer,
+};
+pub use mel::{MelConfig, MelSpectrogram};
+pub use memory::{
+    reserve_samples, OutputMemoryStats, OutputMemoryTracker, OverflowPolicy, BYTES_PER_SAMPLE,
+};
+pub use mixer::{mix_background, BackgroundTrack, MixConfig};
+pub use range::{extract_range, frame_aligned_range, parse_timestamp, RangeConfig};
+pub use resample::{downmix_to_mono, resample, to_mono, Resampler};
+pub use segment::{plan_segments, SegmentConfig};
+pub use silence::{
+    detect_voiced_range, pad_silence, trim_silence, truncate_at_silence, SilenceConfig,
+    SilenceStop, UtteranceDetector, UtteranceEvent,
+};
+pub use spool::{estimate_speech_secs, AudioSpool, SpooledAudio};
+pub use store::{OutputStore, StoredOutput};
 pub use streaming::{AudioChunkBuffer, StreamingConfig};
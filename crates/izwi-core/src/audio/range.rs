@@ -0,0 +1,145 @@
+//! Sub-range extraction from completed audio
+
+use crate::error::{Error, Result};
+
+/// Settings for cutting a span out of generated audio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeConfig {
+    /// Codec frame rate; cut points snap outward to frame boundaries
+    pub frame_rate_hz: f32,
+    /// Fade applied at each interior cut point, in milliseconds
+    pub fade_ms: u32,
+}
+
+impl Default for RangeConfig {
+    fn default() -> Self {
+        Self {
+            frame_rate_hz: 12.5,
+            fade_ms: 10,
+        }
+    }
+}
+
+/// Parse a timestamp such as `150`, `2:30`, `1:02:30.5` into seconds
+pub fn parse_timestamp(s: &str) -> Result<f32> {
+    let invalid = || Error::InvalidInput(format!("Invalid timestamp: '{}'", s));
+
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    let mut seconds = 0.0f32;
+    for (i, part) in parts.iter().enumerate() {
+        let value: f32 = part.parse().map_err(|_| invalid())?;
+        // Only the last component may be fractional or exceed 59
+        let is_last = i == parts.len() - 1;
+        if !value.is_finite() || value < 0.0 || (!is_last && value.fract() != 0.0) {
+            return Err(invalid());
+        }
+        if i > 0 && value >= 60.0 {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Ok(seconds)
+}
+
+/// Sample range covering `start_secs..end_secs`, widened to frame boundaries.
+///
+/// `end_secs` of `None` means the end of the audio. Fails if the range is
+/// empty or starts past the end.
+pub fn frame_aligned_range(
+    num_samples: usize,
+    sample_rate: u32,
+    start_secs: f32,
+    end_secs: Option<f32>,
+    config: &RangeConfig,
+) -> Result<std::ops::Range<usize>> {
+    let duration = num_samples as f32 / sample_rate as f32;
+    let end_secs = end_secs.unwrap_or(duration).min(duration);
+    if start_secs < 0.0 || start_secs >= end_secs {
+        return Err(Error::InvalidInput(format!(
+            "Range {:.3}s-{:.3}s is empty or outside the {:.3}s output",
+            start_secs, end_secs, duration
+        )));
+    }
+
+    let frame_len = ((sample_rate as f32 / config.frame_rate_hz).round() as usize).max(1);
+    let start = (start_secs * sample_rate as f32) as usize / frame_len * frame_len;
+    let end = ((end_secs * sample_rate as f32).ceil() as usize).div_ceil(frame_len) * frame_len;
+    Ok(start..end.min(num_samples))
+}
+
+/// Cut a span out of `samples` with short fades at interior cut points.
+///
+/// Edges that coincide with the start or end of the audio are left
+/// untouched, so extracting the full range returns the input unchanged.
+pub fn extract_range(
+    samples: &[f32],
+    sample_rate: u32,
+    start_secs: f32,
+    end_secs: Option<f32>,
+    config: &RangeConfig,
+) -> Result<Vec<f32>> {
+    let range = frame_aligned_range(samples.len(), sample_rate, start_secs, end_secs, config)?;
+    let fade_in = range.start > 0;
+    let fade_out = range.end < samples.len();
+
+    let mut clip = samples[range].to_vec();
+    let fade_len = (sample_rate as usize * config.fade_ms as usize / 1000).min(clip.len() / 2);
+    for i in 0..fade_len {
+        // Raised-cosine ramp from 0 to 1
+        let gain = 0.5 - 0.5 * (std::f32::consts::PI * i as f32 / fade_len as f32).cos();
+        if fade_in {
+            clip[i] *= gain;
+        }
+        if fade_out {
+            let j = clip.len() - 1 - i;
+            clip[j] *= gain;
+        }
+    }
+    Ok(clip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("150").unwrap(), 150.0);
+        assert_eq!(parse_timestamp("2:30").unwrap(), 150.0);
+        assert_eq!(parse_timestamp("1:02:30.5").unwrap(), 3750.5);
+        assert!(parse_timestamp("1:75").is_err());
+        assert!(parse_timestamp("-3").is_err());
+        assert!(parse_timestamp("a:b").is_err());
+    }
+
+    #[test]
+    fn test_range_snaps_to_frames() {
+        let config = RangeConfig::default();
+        // 1920 samples per frame at 24kHz / 12.5Hz
+        let range = frame_aligned_range(240_000, 24000, 1.0, Some(2.01), &config).unwrap();
+        assert_eq!(range, 23_040..49_920);
+
+        let tail = frame_aligned_range(240_000, 24000, 9.5, None, &config).unwrap();
+        assert_eq!(tail.end, 240_000);
+
+        assert!(frame_aligned_range(240_000, 24000, 10.0, None, &config).is_err());
+        assert!(frame_aligned_range(240_000, 24000, 3.0, Some(2.0), &config).is_err());
+    }
+
+    #[test]
+    fn test_extract_fades_interior_cuts_only() {
+        let samples = vec![1.0; 48_000];
+        let config = RangeConfig::default();
+
+        let full = extract_range(&samples, 24000, 0.0, None, &config).unwrap();
+        assert_eq!(full, samples);
+
+        let clip = extract_range(&samples, 24000, 0.5, Some(1.5), &config).unwrap();
+        assert_eq!(clip[0], 0.0);
+        assert_eq!(clip[clip.len() - 1], 0.0);
+        assert_eq!(clip[clip.len() / 2], 1.0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Sample-rate conversion and channel mixdown
+
+use rubato::{
+    Resampler as _, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+use tracing::debug;
+
+use crate::error::{Error, Result};
+
+/// Number of input frames fed to the sinc interpolator per call
+const CHUNK_FRAMES: usize = 1024;
+
+/// Windowed-sinc sample-rate converter for mono f32 audio.
+///
+/// The resampler is stateful so it can be fed streaming chunks; call
+/// [`Resampler::flush`] once the input is exhausted to drain the filter tail.
+/// The concatenated output is time-aligned with the input and has exactly
+/// `ceil(len * output_rate / input_rate)` samples.
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    inner: Option<SincFixedIn<f32>>,
+    pending: Vec<f32>,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl Resampler {
+    /// Create a resampler converting from `input_rate` to `output_rate`
+    pub fn new(input_rate: u32, output_rate: u32) -> Result<Self> {
+        if input_rate == 0 || output_rate == 0 {
+            return Err(Error::AudioError(format!(
+                "Invalid sample rate conversion: {} Hz -> {} Hz",
+                input_rate, output_rate
+            )));
+        }
+
+        let inner = if input_rate == output_rate {
+            None
+        } else {
+            let params = SincInterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.95,
+                interpolation: SincInterpolationType::Linear,
+                oversampling_factor: 128,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            let ratio = output_rate as f64 / input_rate as f64;
+            let resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, CHUNK_FRAMES, 1)
+                .map_err(|e| Error::AudioError(e.to_string()))?;
+            Some(resampler)
+        };
+
+        debug!("Created resampler {} Hz -> {} Hz", input_rate, output_rate);
+
+        Ok(Self {
+            input_rate,
+            output_rate,
+            inner,
+            pending: Vec::new(),
+            input_frames: 0,
+            output_frames: 0,
+        })
+    }
+
+    /// Input sample rate
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    /// Output sample rate
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// Whether this resampler is a no-op
+    pub fn is_passthrough(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// Feed a chunk of samples, returning whatever output is ready
+    pub fn process(&mut self, samples: &[f32]) -> Result<Vec<f32>> {
+        self.input_frames += samples.len() as u64;
+
+        let Some(inner) = self.inner.as_mut() else {
+            self.output_frames += samples.len() as u64;
+            return Ok(samples.to_vec());
+        };
+
+        self.pending.extend_from_slice(samples);
+
+        let mut output = Vec::new();
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= inner.input_frames_next() {
+            let needed = inner.input_frames_next();
+            let frames = inner
+                .process(&[&self.pending[consumed..consumed + needed]], None)
+                .map_err(|e| Error::AudioError(e.to_string()))?;
+            consumed += needed;
+            output.extend_from_slice(&frames[0]);
+        }
+        self.pending.drain(..consumed);
+
+        self.output_frames += output.len() as u64;
+        Ok(output)
+    }
+
+    /// Drain buffered input and filter tail; the resampler is reset afterwards
+    pub fn flush(&mut self) -> Result<Vec<f32>> {
+        let expected_total = self.expected_output_frames();
+        let Some(inner) = self.inner.as_mut() else {
+            self.reset();
+            return Ok(Vec::new());
+        };
+
+        let mut output = Vec::new();
+
+        let pending = std::mem::take(&mut self.pending);
+        if !pending.is_empty() {
+            let frames = inner
+                .process_partial(Some(&[pending.as_slice()]), None)
+                .map_err(|e| Error::AudioError(e.to_string()))?;
+            output.extend_from_slice(&frames[0]);
+        }
+
+        // Push zeros through until the filter tail has been produced
+        let mut produced = self.output_frames + output.len() as u64;
+        while produced < expected_total {
+            let frames = inner
+                .process_partial::<&[f32]>(None, None)
+                .map_err(|e| Error::AudioError(e.to_string()))?;
+            if frames[0].is_empty() {
+                break;
+            }
+            produced += frames[0].len() as u64;
+            output.extend_from_slice(&frames[0]);
+        }
+
+        output.truncate(expected_total.saturating_sub(self.output_frames) as usize);
+
+        self.reset();
+        Ok(output)
+    }
+
+    /// Clear all internal state
+    pub fn reset(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.reset();
+        }
+        self.pending.clear();
+        self.input_frames = 0;
+        self.output_frames = 0;
+    }
+
+    /// Total output frames the input seen so far should map to
+    fn expected_output_frames(&self) -> u64 {
+        (self.input_frames * self.output_rate as u64).div_ceil(self.input_rate as u64)
+    }
+}
+
+/// Resample a complete mono buffer in one call
+pub fn resample(samples: &[f32], input_rate: u32, output_rate: u32) -> Result<Vec<f32>> {
+    if input_rate == output_rate {
+        return Ok(samples.to_vec());
+    }
+    let mut resampler = Resampler::new(input_rate, output_rate)?;
+    let mut output = resampler.process(samples)?;
+    output.extend(resampler.flush()?);
+    Ok(output)
+}
+
+/// Average interleaved multi-channel audio down to mono
+pub fn downmix_to_mono(interleaved: &[f32], channels: u16) -> Vec<f32> {
+    match channels {
+        0 | 1 => interleaved.to_vec(),
+        n => {
+            let n = n as usize;
+            interleaved
+                .chunks(n)
+                .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+                .collect()
+        }
+    }
+}
+
+/// Downmix and resample interleaved audio to mono at `target_rate`.
+///
+/// Typical use is normalizing arbitrary client audio to 16 kHz mono before
+/// ASR, or 24 kHz mono for voice-cloning references.
+pub fn to_mono(
+    interleaved: &[f32],
+    channels: u16,
+    input_rate: u32,
+    target_rate: u32,
+) -> Result<Vec<f32>> {
+    let mono = downmix_to_mono(interleaved, channels);
+    resample(&mono, input_rate, target_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_downmix_stereo() {
+        let stereo = vec![1.0, 0.0, 0.5, 0.5, -1.0, 1.0];
+        assert_eq!(downmix_to_mono(&stereo, 2), vec![0.5, 0.5, 0.0]);
+        assert_eq!(downmix_to_mono(&stereo, 1), stereo);
+    }
+
+    #[test]
+    fn test_resample_length_and_alignment() {
+        let input = sine(440.0, 24000, 24000);
+        let output = resample(&input, 24000, 16000).unwrap();
+        assert_eq!(output.len(), 16000);
+
+        // Output should track the reference tone at the new rate
+        let reference = sine(440.0, 16000, 16000);
+        let err: f32 = output[1000..15000]
+            .iter()
+            .zip(&reference[1000..15000])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(err < 0.1, "max error {}", err);
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let input = sine(300.0, 16000, 5000);
+        let batch = resample(&input, 16000, 24000).unwrap();
+
+        let mut resampler = Resampler::new(16000, 24000).unwrap();
+        let mut streamed = Vec::new();
+        for chunk in input.chunks(333) {
+            streamed.extend(resampler.process(chunk).unwrap());
+        }
+        streamed.extend(resampler.flush().unwrap());
+
+        assert_eq!(streamed.len(), batch.len());
+        assert_eq!(streamed.len(), 7500);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let input = vec![0.1, 0.2, 0.3];
+        let mut resampler = Resampler::new(24000, 24000).unwrap();
+        assert!(resampler.is_passthrough());
+        assert_eq!(resampler.process(&input).unwrap(), input);
+        assert!(resampler.flush().unwrap().is_empty());
+        assert!(Resampler::new(0, 16000).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Cutting long recordings into pieces an ASR model can take in one pass
+//!
+//! Cuts go in the middle of pauses where possible. Speech that runs on past
+//! the longest allowed segment is cut anyway, and the next segment starts a
+//! little before the cut so words spoken across it are heard in full by
+//! one of the two.
+
+use std::ops::Range;
+
+use super::silence::{frame_db, SilenceConfig};
+
+/// Segmentation settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentConfig {
+    pub silence: SilenceConfig,
+    /// Shortest pause a segment may end at, in milliseconds
+    pub pause_ms: u32,
+    /// Longest segment, in milliseconds
+    pub max_segment_ms: u32,
+    /// Overlap of segments cut mid-speech, in milliseconds
+    pub overlap_ms: u32,
+}
+
+impl Default for SegmentConfig {
+    fn default() -> Self {
+        Self {
+            silence: SilenceConfig::default(),
+            pause_ms: 300,
+            max_segment_ms: 30_000,
+            overlap_ms: 1_000,
+        }
+    }
+}
+
+/// Split `samples` into segments no longer than `max_segment_ms`, in order.
+/// Segments without speech are left out; a segment cut mid-speech overlaps
+/// the one after it.
+pub fn plan_segments(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &SegmentConfig,
+) -> Vec<Range<usize>> {
+    let per_ms = |ms: u32| sample_rate as usize * ms as usize / 1000;
+    let frame_len = per_ms(config.silence.frame_ms).max(1);
+    let pause_frames = per_ms(config.pause_ms).div_ceil(frame_len).max(1);
+    let max_len = per_ms(config.max_segment_ms).max(frame_len);
+    let overlap = per_ms(config.overlap_ms).min(max_len / 2);
+
+    let voiced: Vec<bool> = samples
+        .chunks(frame_len)
+        .map(|frame| frame_db(frame) >= config.silence.threshold_db)
+        .collect();
+
+    // Candidate cuts in the middle of every long enough pause
+    let mut cuts = Vec::new();
+    let mut pause_start = None;
+    for (i, &is_voiced) in voiced.iter().chain([&true]).enumerate() {
+        if !is_voiced {
+            pause_start.get_or_insert(i);
+        } else if let Some(start) = pause_start.take() {
+            if i - start >= pause_frames {
+                cuts.push((start + i) / 2 * frame_len);
+            }
+        }
+    }
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    while start < samples.len() {
+        let limit = start + max_len;
+        if limit >= samples.len() {
+            segments.push(start..samples.len());
+            break;
+        }
+        match cuts.iter().rev().find(|&&cut| cut > start && cut <= limit) {
+            Some(&cut) => {
+                segments.push(start..cut);
+                start = cut;
+            }
+            None => {
+                segments.push(start..limit);
+                start = limit - overlap;
+            }
+        }
+    }
+
+    segments.retain(|segment| {
+        voiced[segment.start / frame_len..segment.end.div_ceil(frame_len)].contains(&true)
+    });
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_segments() {
+        let rate = 1000;
+        let tone = |ms: usize| vec![0.5f32; ms];
+        let pause = |ms: usize| vec![0.0f32; ms];
+        let config = SegmentConfig {
+            max_segment_ms: 1500,
+            overlap_ms: 100,
+            ..Default::default()
+        };
+
+        // Cut in the last pause before the limit; the silent tail is dropped
+        let samples = [
+            tone(400),
+            pause(400),
+            tone(300),
+            pause(400),
+            tone(600),
+            pause(2000),
+        ]
+        .concat();
+        let segments = plan_segments(&samples, rate, &config);
+        assert_eq!(segments, vec![0..1300, 1300..2800]);
+
+        // Unbroken speech is cut at the limit with an overlap
+        let segments = plan_segments(&tone(4000), rate, &config);
+        assert_eq!(segments, vec![0..1500, 1400..2900, 2800..4000]);
+
+        assert!(plan_segments(&pause(5000), rate, &config).is_empty());
+    }
+}
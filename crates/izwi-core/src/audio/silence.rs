@@ -0,0 +1,357 @@
+//! Leading/trailing silence trimming, padding and stop-on-silence detection
+
+/// Settings for energy-based silence detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceConfig {
+    /// Frames with RMS below this level (dBFS) count as silence
+    pub threshold_db: f32,
+    /// Analysis frame length in milliseconds
+    pub frame_ms: u32,
+    /// Audio kept on either side of detected speech, in milliseconds
+    pub margin_ms: u32,
+}
+
+impl Default for SilenceConfig {
+    fn default() -> Self {
+        Self {
+            threshold_db: -45.0,
+            frame_ms: 10,
+            margin_ms: 30,
+        }
+    }
+}
+
+/// RMS level of a frame in dBFS
+pub(super) fn frame_db(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let mean_square = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+    10.0 * mean_square.max(1e-12).log10()
+}
+
+/// Locate the voiced region as a sample range, or `None` if all silent
+pub fn detect_voiced_range(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &SilenceConfig,
+) -> Option<std::ops::Range<usize>> {
+    let frame_len = ((sample_rate as usize * config.frame_ms as usize) / 1000).max(1);
+    let is_voiced = |frame: &[f32]| frame_db(frame) >= config.threshold_db;
+
+    let first = samples.chunks(frame_len).position(is_voiced)?;
+    let last = samples.chunks(frame_len).rposition(is_voiced)?;
+
+    let margin = (sample_rate as usize * config.margin_ms as usize) / 1000;
+    let start = (first * frame_len).saturating_sub(margin);
+    let end = ((last + 1) * frame_len + margin).min(samples.len());
+    Some(start..end)
+}
+
+/// Remove leading and trailing silence.
+///
+/// Fully silent input is returned empty.
+pub fn trim_silence(samples: &[f32], sample_rate: u32, config: &SilenceConfig) -> Vec<f32> {
+    match detect_voiced_range(samples, sample_rate, config) {
+        Some(range) => samples[range].to_vec(),
+        None => Vec::new(),
+    }
+}
+
+/// Add `pad_ms` of silence to both ends
+pub fn pad_silence(samples: &[f32], sample_rate: u32, pad_ms: u32) -> Vec<f32> {
+    let pad = (sample_rate as usize * pad_ms as usize) / 1000;
+    let mut padded = Vec::with_capacity(samples.len() + 2 * pad);
+    padded.resize(pad, 0.0);
+    padded.extend_from_slice(samples);
+    padded.resize(padded.len() + pad, 0.0);
+    padded
+}
+
+/// Detects sustained silence after speech in audio arriving in pieces,
+/// so generation can stop instead of emitting dead air until max tokens.
+#[derive(Debug, Clone)]
+pub struct SilenceStop {
+    threshold_db: f32,
+    frame_len: usize,
+    stop_frames: usize,
+    pending: Vec<f32>,
+    /// Samples analysed so far
+    position: usize,
+    silent_frames: usize,
+    voiced: bool,
+}
+
+impl SilenceStop {
+    /// Stop after `stop_ms` of silence following voiced audio
+    pub fn new(stop_ms: u32, sample_rate: u32, config: &SilenceConfig) -> Self {
+        let frame_len = ((sample_rate as usize * config.frame_ms as usize) / 1000).max(1);
+        let stop_len = (sample_rate as usize * stop_ms as usize) / 1000;
+        Self {
+            threshold_db: config.threshold_db,
+            frame_len,
+            stop_frames: stop_len.div_ceil(frame_len).max(1),
+            pending: Vec::new(),
+            position: 0,
+            silent_frames: 0,
+            voiced: false,
+        }
+    }
+
+    /// Feed the next samples; once the silence has lasted long enough,
+    /// returns the sample offset (from the first sample fed) where it began
+    pub fn push(&mut self, samples: &[f32]) -> Option<usize> {
+        self.pending.extend_from_slice(samples);
+        let mut consumed = 0;
+        let mut stop = None;
+        for frame in self.pending.chunks_exact(self.frame_len) {
+            consumed += self.frame_len;
+            self.position += self.frame_len;
+            if frame_db(frame) >= self.threshold_db {
+                self.voiced = true;
+                self.silent_frames = 0;
+            } else if self.voiced {
+                self.silent_frames += 1;
+                if self.silent_frames >= self.stop_frames {
+                    stop = Some(self.position - self.silent_frames * self.frame_len);
+                    break;
+                }
+            }
+        }
+        self.pending.drain(..consumed);
+        stop
+    }
+}
+
+/// What an [`UtteranceDetector`] found in the audio it was fed
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtteranceEvent {
+    /// Speech began (enough voiced audio to rule out a click)
+    Started,
+    /// Speech ended; the utterance with `margin_ms` of context either side
+    Ended(Vec<f32>),
+}
+
+/// Splits live input, such as a phone call, into utterances: speech that
+/// ends after `end_ms` of silence or once it reaches `max_ms`.
+#[derive(Debug, Clone)]
+pub struct UtteranceDetector {
+    threshold_db: f32,
+    frame_len: usize,
+    margin: usize,
+    min_voiced_frames: usize,
+    end_frames: usize,
+    max_len: usize,
+    pending: Vec<f32>,
+    /// Recent silence kept as lead-in for the next utterance
+    lead_in: Vec<f32>,
+    utterance: Vec<f32>,
+    voiced_frames: usize,
+    silent_frames: usize,
+}
+
+/// Voiced audio needed before an utterance counts as started
+const MIN_SPEECH_MS: usize = 60;
+
+impl UtteranceDetector {
+    pub fn new(end_ms: u32, max_ms: u32, sample_rate: u32, config: &SilenceConfig) -> Self {
+        let per_ms = |ms: usize| sample_rate as usize * ms / 1000;
+        let frame_len = per_ms(config.frame_ms as usize).max(1);
+        Self {
+            threshold_db: config.threshold_db,
+            frame_len,
+            margin: per_ms(config.margin_ms as usize),
+            min_voiced_frames: per_ms(MIN_SPEECH_MS).div_ceil(frame_len).max(1),
+            end_frames: per_ms(end_ms as usize).div_ceil(frame_len).max(1),
+            max_len: per_ms(max_ms as usize),
+            pending: Vec::new(),
+            lead_in: Vec::new(),
+            utterance: Vec::new(),
+            voiced_frames: 0,
+            silent_frames: 0,
+        }
+    }
+
+    /// Whether speech is under way
+    pub fn in_speech(&self) -> bool {
+        self.voiced_frames >= self.min_voiced_frames
+    }
+
+    /// Feed the next samples
+    pub fn push(&mut self, samples: &[f32]) -> Vec<UtteranceEvent> {
+        self.pending.extend_from_slice(samples);
+        let mut events = Vec::new();
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= self.frame_len {
+            let frame = &self.pending[consumed..consumed + self.frame_len];
+            consumed += self.frame_len;
+            let voiced = frame_db(frame) >= self.threshold_db;
+
+            if self.utterance.is_empty() {
+                if !voiced {
+                    self.lead_in.extend_from_slice(frame);
+                    let excess = self.lead_in.len().saturating_sub(self.margin);
+                    self.lead_in.drain(..excess);
+                    continue;
+                }
+                self.utterance.append(&mut self.lead_in);
+            }
+            self.utterance.extend_from_slice(frame);
+            if voiced {
+                self.voiced_frames += 1;
+                self.silent_frames = 0;
+                if self.voiced_frames == self.min_voiced_frames {
+                    events.push(UtteranceEvent::Started);
+                }
+            } else {
+                self.silent_frames += 1;
+            }
+
+            if self.silent_frames >= self.end_frames || self.utterance.len() >= self.max_len {
+                let trailing = (self.silent_frames * self.frame_len).saturating_sub(self.margin);
+                let mut utterance = std::mem::take(&mut self.utterance);
+                if trailing > 0 {
+                    // The end of the silence leads into the next utterance
+                    self.lead_in = utterance[utterance.len() - self.margin..].to_vec();
+                }
+                utterance.truncate(utterance.len() - trailing);
+                // A click or pop too short to be speech is dropped
+                if self.in_speech() {
+                    events.push(UtteranceEvent::Ended(utterance));
+                }
+                self.voiced_frames = 0;
+                self.silent_frames = 0;
+            }
+        }
+        self.pending.drain(..consumed);
+        events
+    }
+}
+
+/// Cut `samples` where `stop_ms` of silence after speech begins, keeping
+/// `margin_ms` of it so the last word isn't clipped
+pub fn truncate_at_silence(
+    samples: &mut Vec<f32>,
+    sample_rate: u32,
+    stop_ms: u32,
+    config: &SilenceConfig,
+) -> bool {
+    match SilenceStop::new(stop_ms, sample_rate, config).push(samples) {
+        Some(start) => {
+            let margin = (sample_rate as usize * config.margin_ms as usize) / 1000;
+            samples.truncate(start + margin);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len).map(|i| 0.5 * (i as f32 * 0.1).sin()).collect()
+    }
+
+    #[test]
+    fn test_trim_leading_and_trailing() {
+        let mut samples = vec![0.0; 4800];
+        samples.extend(tone(2400));
+        samples.extend(vec![0.0; 9600]);
+
+        let config = SilenceConfig {
+            margin_ms: 0,
+            ..Default::default()
+        };
+        let range = detect_voiced_range(&samples, 24000, &config).unwrap();
+        assert_eq!(range, 4800..7200);
+
+        let trimmed = trim_silence(&samples, 24000, &SilenceConfig::default());
+        // 30ms margin on both sides at 24kHz
+        assert_eq!(trimmed.len(), 2400 + 2 * 720);
+    }
+
+    #[test]
+    fn test_all_silent() {
+        let silent = vec![0.0; 1000];
+        assert!(trim_silence(&silent, 24000, &SilenceConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_silence_stop_across_pushes() {
+        let config = SilenceConfig::default();
+        let mut stop = SilenceStop::new(100, 24000, &config);
+        // Leading silence doesn't count
+        assert_eq!(stop.push(&vec![0.0; 4800]), None);
+        assert_eq!(stop.push(&tone(2400)), None);
+        assert_eq!(stop.push(&vec![0.0; 1000]), None);
+        // 100ms of silence is 2400 samples, reached within this push
+        assert_eq!(stop.push(&vec![0.0; 2000]), Some(7200));
+    }
+
+    #[test]
+    fn test_truncate_at_silence() {
+        let mut samples = tone(2400);
+        samples.extend(vec![0.0; 2400]);
+        samples.extend(tone(2400));
+        samples.extend(vec![0.0; 24000]);
+
+        let mut short = samples.clone();
+        // Pauses shorter than the stop length are kept
+        assert!(truncate_at_silence(
+            &mut short,
+            24000,
+            200,
+            &SilenceConfig::default()
+        ));
+        assert_eq!(short.len(), 7200 + 720);
+
+        assert!(truncate_at_silence(
+            &mut samples,
+            24000,
+            50,
+            &SilenceConfig::default()
+        ));
+        assert_eq!(samples.len(), 2400 + 720);
+
+        let mut voiced = tone(4800);
+        assert!(!truncate_at_silence(
+            &mut voiced,
+            24000,
+            50,
+            &SilenceConfig::default()
+        ));
+    }
+
+    #[test]
+    fn test_utterance_detection() {
+        let config = SilenceConfig::default();
+        let mut detector = UtteranceDetector::new(200, 10_000, 8000, &config);
+        assert!(detector.push(&vec![0.0; 800]).is_empty());
+        // A 20ms click isn't speech
+        assert!(detector.push(&tone(160)).is_empty());
+        assert!(detector.push(&vec![0.0; 1600]).is_empty());
+
+        assert_eq!(detector.push(&tone(800)), vec![UtteranceEvent::Started]);
+        assert!(detector.in_speech());
+        // Pauses shorter than 200ms don't end it
+        assert!(detector.push(&vec![0.0; 800]).is_empty());
+        assert!(detector.push(&tone(800)).is_empty());
+        // 30ms of context either side
+        let speech = [tone(800), vec![0.0; 800], tone(800)].concat();
+        let expected = [vec![0.0; 240], speech, vec![0.0; 240]].concat();
+        let events = detector.push(&vec![0.0; 1600]);
+        assert_eq!(events, vec![UtteranceEvent::Ended(expected)]);
+        assert!(!detector.in_speech());
+    }
+
+    #[test]
+    fn test_pad() {
+        let padded = pad_silence(&[1.0, 1.0], 1000, 5);
+        assert_eq!(padded.len(), 12);
+        assert_eq!(padded[5], 1.0);
+        assert_eq!(padded[0], 0.0);
+        assert_eq!(padded[11], 0.0);
+    }
+}
@@ -0,0 +1,363 @@
+//! SIMD kernels for per-chunk post-processing
+//!
+//! Crossfades, loudness gain and the f32 -> i16 conversion run on every
+//! streamed chunk. Each kernel has an AVX2 (x86_64) and a NEON (aarch64)
+//! version, picked by runtime feature detection, with the scalar loop as the
+//! fallback. All versions give bit-identical results.
+//!
+//! Resampling is not handled here: rubato's sinc interpolator already
+//! dispatches to AVX, SSE or NEON at runtime.
+
+use super::loudness::soft_limit;
+
+/// Instruction set the kernels run with on this CPU
+pub fn simd_backend() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        return "avx2";
+    }
+    #[cfg(target_arch = "aarch64")]
+    if has_neon() {
+        return "neon";
+    }
+    "scalar"
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[cfg(target_arch = "aarch64")]
+fn has_neon() -> bool {
+    std::arch::is_aarch64_feature_detected!("neon")
+}
+
+/// Run the vector version of a kernel when the CPU supports it, the scalar
+/// one otherwise
+macro_rules! dispatch {
+    ($kernel:ident($($arg:expr),*)) => {{
+        #[cfg(target_arch = "x86_64")]
+        if has_avx2() {
+            // SAFETY: AVX2 is available
+            return unsafe { avx2::$kernel($($arg),*) };
+        }
+        #[cfg(target_arch = "aarch64")]
+        if has_neon() {
+            // SAFETY: NEON is available
+            return unsafe { neon::$kernel($($arg),*) };
+        }
+        scalar::$kernel($($arg),*)
+    }};
+}
+
+/// Multiply by a gain ramping from `start_gain` by `step` per sample (the
+/// first sample gets `start_gain + step`), soft-limiting to `ceiling`
+pub fn apply_gain(samples: &mut [f32], start_gain: f32, step: f32, ceiling: f32) {
+    dispatch!(apply_gain(samples, start_gain, step, ceiling))
+}
+
+/// Fade `tail` out linearly while fading the start of `next` in over it.
+///
+/// `next` may be shorter than `tail`; the rest of `tail` is only faded out.
+pub fn crossfade(tail: &mut [f32], next: &[f32]) {
+    dispatch!(crossfade(tail, next))
+}
+
+/// Convert samples in [-1.0, 1.0] to i16, clamping out-of-range values
+pub fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
+    let mut out = vec![0; samples.len()];
+    f32_to_i16_into(samples, &mut out);
+    out
+}
+
+/// [`f32_to_i16`] into an existing buffer, up to the shorter of the two
+pub fn f32_to_i16_into(samples: &[f32], out: &mut [i16]) {
+    dispatch!(convert_i16(samples, out))
+}
+
+/// Plain scalar kernels: the fallback, the tail of the vector loops, and the
+/// reference for tests and benchmarks
+#[doc(hidden)]
+pub mod scalar {
+    use super::soft_limit;
+
+    pub fn apply_gain(samples: &mut [f32], start_gain: f32, step: f32, ceiling: f32) {
+        apply_gain_at(samples, 0, start_gain, step, ceiling);
+    }
+
+    /// `apply_gain` for samples starting at index `first` of the ramp
+    pub(super) fn apply_gain_at(
+        samples: &mut [f32],
+        first: usize,
+        start_gain: f32,
+        step: f32,
+        ceiling: f32,
+    ) {
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let gain = start_gain + step * (first + i + 1) as f32;
+            *sample = soft_limit(*sample * gain, ceiling);
+        }
+    }
+
+    pub fn crossfade(tail: &mut [f32], next: &[f32]) {
+        crossfade_at(tail, next, 0, tail.len() as f32);
+    }
+
+    /// `crossfade` for samples starting at index `first` of a fade of `len`
+    pub(super) fn crossfade_at(tail: &mut [f32], next: &[f32], first: usize, len: f32) {
+        for (i, sample) in tail.iter_mut().enumerate() {
+            let fade = (first + i) as f32 / len;
+            *sample *= 1.0 - fade;
+            if let Some(&next) = next.get(i) {
+                *sample += next * fade;
+            }
+        }
+    }
+
+    pub fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
+        let mut out = vec![0; samples.len()];
+        convert_i16(samples, &mut out);
+        out
+    }
+
+    pub(super) fn convert_i16(samples: &[f32], out: &mut [i16]) {
+        for (value, &sample) in out.iter_mut().zip(samples) {
+            *value = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::{scalar, soft_limit};
+
+    const LANES: usize = 8;
+
+    /// Indices `first..first + 8` as floats
+    #[target_feature(enable = "avx2")]
+    unsafe fn indices(first: usize) -> __m256 {
+        let iota = _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7);
+        _mm256_cvtepi32_ps(_mm256_add_epi32(_mm256_set1_epi32(first as i32), iota))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn apply_gain(samples: &mut [f32], start_gain: f32, step: f32, ceiling: f32) {
+        let start = _mm256_set1_ps(start_gain);
+        let step_v = _mm256_set1_ps(step);
+        let knee = _mm256_set1_ps(ceiling * 0.5);
+        let abs_mask = _mm256_castsi256_ps(_mm256_set1_epi32(i32::MAX));
+
+        let blocks = samples.len() / LANES;
+        for block in 0..blocks {
+            let first = block * LANES;
+            let ptr = samples.as_mut_ptr().add(first);
+            let gain = _mm256_add_ps(start, _mm256_mul_ps(step_v, indices(first + 1)));
+            let scaled = _mm256_mul_ps(_mm256_loadu_ps(ptr), gain);
+            // The limiter is transparent below the knee, so only blocks with
+            // a louder sample take the slow path
+            let over = _mm256_cmp_ps(_mm256_and_ps(scaled, abs_mask), knee, _CMP_GT_OQ);
+            if _mm256_movemask_ps(over) == 0 {
+                _mm256_storeu_ps(ptr, scaled);
+            } else {
+                let mut lanes = [0.0f32; LANES];
+                _mm256_storeu_ps(lanes.as_mut_ptr(), scaled);
+                for (i, value) in lanes.into_iter().enumerate() {
+                    *ptr.add(i) = soft_limit(value, ceiling);
+                }
+            }
+        }
+
+        let done = blocks * LANES;
+        scalar::apply_gain_at(&mut samples[done..], done, start_gain, step, ceiling);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn crossfade(tail: &mut [f32], next: &[f32]) {
+        let len = tail.len() as f32;
+        let len_v = _mm256_set1_ps(len);
+        let one = _mm256_set1_ps(1.0);
+
+        let blocks = tail.len().min(next.len()) / LANES;
+        for block in 0..blocks {
+            let first = block * LANES;
+            let ptr = tail.as_mut_ptr().add(first);
+            let fade = _mm256_div_ps(indices(first), len_v);
+            let faded_out = _mm256_mul_ps(_mm256_loadu_ps(ptr), _mm256_sub_ps(one, fade));
+            let faded_in = _mm256_mul_ps(_mm256_loadu_ps(next.as_ptr().add(first)), fade);
+            _mm256_storeu_ps(ptr, _mm256_add_ps(faded_out, faded_in));
+        }
+
+        let done = blocks * LANES;
+        scalar::crossfade_at(&mut tail[done..], &next[done..], done, len);
+    }
+
+    /// Clamp to [-1.0, 1.0] and scale to i16 range; NaN converts to 0, as
+    /// with `as`
+    #[target_feature(enable = "avx2")]
+    unsafe fn to_i32(x: __m256) -> __m256i {
+        let x = _mm256_and_ps(x, _mm256_cmp_ps(x, x, _CMP_ORD_Q));
+        let x = _mm256_min_ps(_mm256_max_ps(x, _mm256_set1_ps(-1.0)), _mm256_set1_ps(1.0));
+        _mm256_cvttps_epi32(_mm256_mul_ps(x, _mm256_set1_ps(32767.0)))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn convert_i16(samples: &[f32], out: &mut [i16]) {
+        let len = samples.len().min(out.len());
+        let blocks = len / (2 * LANES);
+        for block in 0..blocks {
+            let first = block * 2 * LANES;
+            let ptr = samples.as_ptr().add(first);
+            let a = to_i32(_mm256_loadu_ps(ptr));
+            let b = to_i32(_mm256_loadu_ps(ptr.add(LANES)));
+            // Packing works per 128-bit half, so restore sample order after
+            let packed = _mm256_permute4x64_epi64(_mm256_packs_epi32(a, b), 0b11_01_10_00);
+            _mm256_storeu_si256(out.as_mut_ptr().add(first) as *mut __m256i, packed);
+        }
+
+        let done = blocks * 2 * LANES;
+        scalar::convert_i16(&samples[done..len], &mut out[done..len]);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::{scalar, soft_limit};
+
+    const LANES: usize = 4;
+
+    /// Indices `first..first + 4` as floats
+    #[target_feature(enable = "neon")]
+    unsafe fn indices(first: usize) -> float32x4_t {
+        let iota: [u32; LANES] = [0, 1, 2, 3];
+        vcvtq_f32_u32(vaddq_u32(
+            vdupq_n_u32(first as u32),
+            vld1q_u32(iota.as_ptr()),
+        ))
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn apply_gain(samples: &mut [f32], start_gain: f32, step: f32, ceiling: f32) {
+        let start = vdupq_n_f32(start_gain);
+        let step_v = vdupq_n_f32(step);
+        let knee = vdupq_n_f32(ceiling * 0.5);
+
+        let blocks = samples.len() / LANES;
+        for block in 0..blocks {
+            let first = block * LANES;
+            let ptr = samples.as_mut_ptr().add(first);
+            let gain = vaddq_f32(start, vmulq_f32(step_v, indices(first + 1)));
+            let scaled = vmulq_f32(vld1q_f32(ptr), gain);
+            // The limiter is transparent below the knee, so only blocks with
+            // a louder sample take the slow path
+            if vmaxvq_u32(vcgtq_f32(vabsq_f32(scaled), knee)) == 0 {
+                vst1q_f32(ptr, scaled);
+            } else {
+                let mut lanes = [0.0f32; LANES];
+                vst1q_f32(lanes.as_mut_ptr(), scaled);
+                for (i, value) in lanes.into_iter().enumerate() {
+                    *ptr.add(i) = soft_limit(value, ceiling);
+                }
+            }
+        }
+
+        let done = blocks * LANES;
+        scalar::apply_gain_at(&mut samples[done..], done, start_gain, step, ceiling);
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn crossfade(tail: &mut [f32], next: &[f32]) {
+        let len = tail.len() as f32;
+        let len_v = vdupq_n_f32(len);
+        let one = vdupq_n_f32(1.0);
+
+        let blocks = tail.len().min(next.len()) / LANES;
+        for block in 0..blocks {
+            let first = block * LANES;
+            let ptr = tail.as_mut_ptr().add(first);
+            let fade = vdivq_f32(indices(first), len_v);
+            let faded_out = vmulq_f32(vld1q_f32(ptr), vsubq_f32(one, fade));
+            let faded_in = vmulq_f32(vld1q_f32(next.as_ptr().add(first)), fade);
+            vst1q_f32(ptr, vaddq_f32(faded_out, faded_in));
+        }
+
+        let done = blocks * LANES;
+        scalar::crossfade_at(&mut tail[done..], &next[done..], done, len);
+    }
+
+    /// Clamp to [-1.0, 1.0] and scale to i16 range; NaN propagates through
+    /// min/max and converts to 0, as with `as`
+    #[target_feature(enable = "neon")]
+    unsafe fn to_i32(x: float32x4_t) -> int32x4_t {
+        let x = vminq_f32(vmaxq_f32(x, vdupq_n_f32(-1.0)), vdupq_n_f32(1.0));
+        vcvtq_s32_f32(vmulq_f32(x, vdupq_n_f32(32767.0)))
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn convert_i16(samples: &[f32], out: &mut [i16]) {
+        let len = samples.len().min(out.len());
+        let blocks = len / (2 * LANES);
+        for block in 0..blocks {
+            let first = block * 2 * LANES;
+            let ptr = samples.as_ptr().add(first);
+            let a = vqmovn_s32(to_i32(vld1q_f32(ptr)));
+            let b = vqmovn_s32(to_i32(vld1q_f32(ptr.add(LANES))));
+            vst1q_s16(out.as_mut_ptr().add(first), vcombine_s16(a, b));
+        }
+
+        let done = blocks * 2 * LANES;
+        scalar::convert_i16(&samples[done..len], &mut out[done..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic test signal with peaks past full scale
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (i as f32 * 0.37).sin() * 1.3 + (i as f32 * 0.011).cos() * 0.2)
+            .collect()
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        // Odd lengths exercise the remainder loops
+        for len in [0, 5, 8, 17, 257, 1000] {
+            let input = signal(len);
+
+            for (start_gain, step) in [(0.8, 0.001), (0.3, 0.0)] {
+                let mut fast = input.clone();
+                let mut reference = input.clone();
+                apply_gain(&mut fast, start_gain, step, 0.9);
+                scalar::apply_gain(&mut reference, start_gain, step, 0.9);
+                assert_eq!(fast, reference);
+            }
+
+            for next_len in [len / 2, len, len + 3] {
+                let next = signal(next_len);
+                let mut fast = input.clone();
+                let mut reference = input.clone();
+                crossfade(&mut fast, &next);
+                scalar::crossfade(&mut reference, &next);
+                assert_eq!(fast, reference);
+            }
+
+            assert_eq!(f32_to_i16(&input), scalar::f32_to_i16(&input));
+        }
+    }
+
+    #[test]
+    fn test_i16_conversion_clamps() {
+        let mut input = vec![-2.0, -1.0, 0.0, 0.5, 1.0, 2.0, f32::NAN, -0.25];
+        input.extend_from_within(..);
+        let expected = [-32767, -32767, 0, 16383, 32767, 32767, 0, -8191];
+        assert_eq!(f32_to_i16(&input), [expected, expected].concat());
+        assert!(["avx2", "neon", "scalar"].contains(&simd_backend()));
+    }
+}
@@ -0,0 +1,183 @@
+//! Disk-spooled assembly of long outputs
+//!
+//! Holding an hour of generated audio in memory before encoding it takes
+//! hundreds of megabytes per job. A spool instead appends each chunk to a
+//! WAV file as it is generated and fixes up the header sizes once the
+//! generation ends, so only one chunk is ever held at a time.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use super::encoder::{AudioEncoder, AudioFormat, ChunkEncoder};
+use crate::error::{Error, Result};
+
+/// Speaking rate used to estimate output length from input text
+const SPEECH_CHARS_PER_SEC: f64 = 15.0;
+
+/// Offsets of the RIFF and data chunk sizes in the WAV header
+const RIFF_LEN_OFFSET: u64 = 4;
+const DATA_LEN_OFFSET: u64 = 40;
+const WAV_HEADER_LEN: u64 = 44;
+
+/// Rough length of speech synthesized from `text`, in seconds
+pub fn estimate_speech_secs(text: &str) -> f64 {
+    text.chars().count() as f64 / SPEECH_CHARS_PER_SEC
+}
+
+/// WAV file being written chunk by chunk.
+///
+/// Dropping an unfinished spool deletes its partial file.
+pub struct AudioSpool {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+    encoder: ChunkEncoder,
+    sample_rate: u32,
+    num_samples: usize,
+}
+
+impl AudioSpool {
+    /// Start `<request_id>.wav` in `dir`, creating the directory if needed
+    pub fn create(dir: &Path, request_id: &str, sample_rate: u32) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.wav", request_id));
+        let mut file = BufWriter::new(File::create(&path)?);
+        let mut encoder = ChunkEncoder::new(AudioEncoder::new(sample_rate, 1), AudioFormat::Wav);
+        // The header goes out with the first (empty) chunk
+        file.write_all(&encoder.encode(&[])?)?;
+        debug!("Spooling {} to {:?}", request_id, path);
+        Ok(Self {
+            path,
+            file: Some(file),
+            encoder,
+            sample_rate,
+            num_samples: 0,
+        })
+    }
+
+    /// Append samples
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        file.write_all(&self.encoder.encode(samples)?)?;
+        self.num_samples += samples.len();
+        Ok(())
+    }
+
+    /// Samples written so far
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Write the final header sizes and hand over the finished file
+    pub fn finish(mut self) -> Result<SpooledAudio> {
+        let mut file = self
+            .file
+            .take()
+            .expect("spool file is open until finished")
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        let data_len = file.stream_position()? - WAV_HEADER_LEN;
+        let data_len = u32::try_from(data_len)
+            .ok()
+            .filter(|len| *len <= u32::MAX - WAV_HEADER_LEN as u32)
+            .ok_or_else(|| Error::AudioError("Audio too long for a WAV file".into()))?;
+        file.seek(SeekFrom::Start(RIFF_LEN_OFFSET))?;
+        file.write_all(&(data_len + WAV_HEADER_LEN as u32 - 8).to_le_bytes())?;
+        file.seek(SeekFrom::Start(DATA_LEN_OFFSET))?;
+        file.write_all(&data_len.to_le_bytes())?;
+        file.sync_data()?;
+
+        Ok(SpooledAudio {
+            path: std::mem::take(&mut self.path),
+            sample_rate: self.sample_rate,
+            num_samples: self.num_samples,
+            file_len: WAV_HEADER_LEN + data_len as u64,
+        })
+    }
+}
+
+impl Drop for AudioSpool {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            remove_spool_file(&self.path);
+        }
+    }
+}
+
+/// Finished WAV file of a spooled output.
+///
+/// The file is deleted when the last handle is dropped.
+#[derive(Debug)]
+pub struct SpooledAudio {
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub num_samples: usize,
+    /// Size of the WAV file in bytes
+    pub file_len: u64,
+}
+
+impl SpooledAudio {
+    /// Duration in seconds
+    pub fn duration_secs(&self) -> f32 {
+        self.num_samples as f32 / self.sample_rate as f32
+    }
+}
+
+impl Drop for SpooledAudio {
+    fn drop(&mut self) {
+        remove_spool_file(&self.path);
+    }
+}
+
+fn remove_spool_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove spool file {:?}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decode_wav;
+
+    #[test]
+    fn test_spool_matches_in_memory_encoding() {
+        let dir = std::env::temp_dir().join(format!("izwi-spool-{}", uuid::Uuid::new_v4()));
+        let samples: Vec<f32> = (0..24_000).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+
+        let mut spool = AudioSpool::create(&dir, "job", 24_000).unwrap();
+        for chunk in samples.chunks(1000) {
+            spool.write(chunk).unwrap();
+        }
+        let spooled = spool.finish().unwrap();
+        assert_eq!(spooled.num_samples, samples.len());
+        assert_eq!(spooled.duration_secs(), 1.0);
+
+        // Byte for byte what encoding the whole take in memory produces
+        let bytes = fs::read(&spooled.path).unwrap();
+        let expected = AudioEncoder::new(24_000, 1)
+            .encode(&samples, AudioFormat::Wav)
+            .unwrap();
+        assert_eq!(bytes, expected);
+        assert_eq!(spooled.file_len, bytes.len() as u64);
+        let (decoded, rate) = decode_wav(&bytes).unwrap();
+        assert_eq!((decoded.len(), rate), (samples.len(), 24_000));
+
+        // Files go away with their handles, finished or not
+        let path = spooled.path.clone();
+        drop(spooled);
+        assert!(!path.exists());
+        let mut partial = AudioSpool::create(&dir, "aborted", 24_000).unwrap();
+        partial.write(&samples[..100]).unwrap();
+        drop(partial);
+        assert!(!dir.join("aborted.wav").exists());
+
+        assert_eq!(estimate_speech_secs(&"a".repeat(150)), 10.0);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,238 @@
+//! In-memory store of completed generations
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::tenant::{SealedData, TenantKeyring};
+
+const BYTES_PER_SAMPLE: usize = std::mem::size_of::<f32>();
+
+/// Audio kept after a generation completes
+#[derive(Debug, Clone)]
+pub struct StoredOutput {
+    pub samples: Arc<[f32]>,
+    pub sample_rate: u32,
+    pub created_at: Instant,
+}
+
+impl StoredOutput {
+    /// Duration in seconds
+    pub fn duration_secs(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+}
+
+/// A stored output, sealed under its tenant's key
+#[derive(Debug)]
+struct Entry {
+    tenant: Option<String>,
+    data: SealedData,
+    sample_rate: u32,
+    created_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct StoreInner {
+    outputs: HashMap<String, Entry>,
+    order: VecDeque<String>,
+    bytes: usize,
+}
+
+impl StoreInner {
+    fn remove(&mut self, request_id: &str) -> Option<Entry> {
+        let entry = self.outputs.remove(request_id)?;
+        self.bytes -= entry.data.len();
+        self.order.retain(|id| id != request_id);
+        Some(entry)
+    }
+}
+
+/// Byte-bounded store of finished outputs keyed by request ID.
+///
+/// The oldest outputs are evicted first once the byte budget is exceeded.
+/// A budget of 0 disables storage. Outputs owned by a tenant are encrypted
+/// with the tenant's key and only returned to that tenant.
+#[derive(Debug)]
+pub struct OutputStore {
+    max_bytes: usize,
+    keyring: Arc<TenantKeyring>,
+    inner: Mutex<StoreInner>,
+}
+
+impl OutputStore {
+    /// Create a store holding at most `max_bytes` of samples
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            keyring: Arc::new(TenantKeyring::default()),
+            inner: Mutex::new(StoreInner::default()),
+        }
+    }
+
+    /// Use the given keyring for tenant outputs
+    pub fn with_keyring(mut self, keyring: Arc<TenantKeyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Keep the audio for a completed request.
+    ///
+    /// Outputs larger than the whole budget are not stored.
+    pub fn insert(
+        &self,
+        request_id: impl Into<String>,
+        tenant: Option<&str>,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<()> {
+        if samples.len() * BYTES_PER_SAMPLE > self.max_bytes {
+            return Ok(());
+        }
+
+        let plaintext: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let data = self.keyring.seal(tenant, &plaintext)?;
+        let size = data.len();
+        if size > self.max_bytes {
+            return Ok(());
+        }
+
+        let request_id = request_id.into();
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.remove(&request_id);
+        while inner.bytes + size > self.max_bytes {
+            let Some(oldest) = inner.order.front().cloned() else {
+                break;
+            };
+            inner.remove(&oldest);
+        }
+
+        inner.bytes += size;
+        inner.order.push_back(request_id.clone());
+        inner.outputs.insert(
+            request_id,
+            Entry {
+                tenant: tenant.map(String::from),
+                data,
+                sample_rate,
+                created_at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Look up a stored output on behalf of a tenant.
+    ///
+    /// Outputs belonging to another tenant are reported as missing.
+    pub fn get(&self, request_id: &str, tenant: Option<&str>) -> Result<Option<StoredOutput>> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = inner.outputs.get(request_id) else {
+            return Ok(None);
+        };
+        if entry.tenant.as_deref() != tenant {
+            return Ok(None);
+        }
+
+        let bytes = self.keyring.open(tenant, &entry.data)?;
+        let samples: Vec<f32> = bytes
+            .chunks_exact(BYTES_PER_SAMPLE)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Some(StoredOutput {
+            samples: samples.into(),
+            sample_rate: entry.sample_rate,
+            created_at: entry.created_at,
+        }))
+    }
+
+    /// Drop every output of a tenant, returning how many were removed
+    pub fn purge_tenant(&self, tenant: &str) -> usize {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let ids: Vec<String> = inner
+            .outputs
+            .iter()
+            .filter(|(_, e)| e.tenant.as_deref() == Some(tenant))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &ids {
+            inner.remove(id);
+        }
+        ids.len()
+    }
+
+    /// Re-encrypt a tenant's outputs under its active key
+    pub fn reseal_tenant(&self, tenant: &str) -> Result<usize> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut count = 0;
+        let mut delta = 0isize;
+        for entry in inner.outputs.values_mut() {
+            if entry.tenant.as_deref() == Some(tenant) {
+                let data = self.keyring.reseal(tenant, &entry.data)?;
+                delta += data.len() as isize - entry.data.len() as isize;
+                entry.data = data;
+                count += 1;
+            }
+        }
+        inner.bytes = inner.bytes.saturating_add_signed(delta);
+        Ok(count)
+    }
+
+    /// Bytes currently stored
+    pub fn bytes_used(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EncryptionConfig;
+
+    #[test]
+    fn test_evicts_oldest_over_budget() {
+        // Room for 1000 samples
+        let store = OutputStore::new(4000);
+        store.insert("a", None, &[0.0; 400], 24000).unwrap();
+        store.insert("b", None, &[0.0; 400], 24000).unwrap();
+        store.insert("c", None, &[0.0; 400], 24000).unwrap();
+
+        assert!(store.get("a", None).unwrap().is_none());
+        assert_eq!(store.get("b", None).unwrap().unwrap().samples.len(), 400);
+        assert_eq!(store.bytes_used(), 3200);
+
+        // Larger than the whole budget
+        store.insert("d", None, &[0.0; 2000], 24000).unwrap();
+        assert!(store.get("d", None).unwrap().is_none());
+        assert!(OutputStore::new(0).get("a", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tenant_outputs_are_isolated() {
+        let mut config = EncryptionConfig::default();
+        config.tenant_keys.insert(
+            "acme".into(),
+            vec!["QUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUE=".into()],
+        );
+        let keyring = Arc::new(TenantKeyring::from_config(&config).unwrap());
+        let store = OutputStore::new(1 << 20).with_keyring(keyring.clone());
+
+        store
+            .insert("a", Some("acme"), &[0.25, -0.5], 24000)
+            .unwrap();
+        store.insert("b", None, &[0.1], 24000).unwrap();
+        assert!(store.get("a", None).unwrap().is_none());
+        assert!(store.get("a", Some("globex")).unwrap().is_none());
+        let out = store.get("a", Some("acme")).unwrap().unwrap();
+        assert_eq!(&out.samples[..], &[0.25, -0.5]);
+
+        keyring.rotate("acme", None).unwrap();
+        assert_eq!(store.reseal_tenant("acme").unwrap(), 1);
+        keyring.retire_old_keys("acme");
+        assert!(store.get("a", Some("acme")).unwrap().is_some());
+
+        assert_eq!(store.purge_tenant("acme"), 1);
+        assert!(store.get("a", Some("acme")).unwrap().is_none());
+        assert!(store.get("b", None).unwrap().is_some());
+    }
+}
@@ -1,7 +1,14 @@
 //! Streaming audio buffer and configuration
 
 use std::collections::VecDeque;
-use tracing::debug;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use super::buffer::SampleBuffer;
+use super::loudness::{LoudnessConfig, LoudnessNormalizer};
+use super::memory::{reserve_samples, OutputMemoryTracker, OverflowPolicy, BYTES_PER_SAMPLE};
+use super::simd;
+use crate::error::Result;
 
 /// Configuration for streaming audio generation
 #[derive(Debug, Clone)]
@@ -16,6 +23,10 @@ pub struct StreamingConfig {
     pub crossfade_enabled: bool,
     /// Crossfade duration in samples
     pub crossfade_samples: usize,
+    /// Maximum decoded audio held per request, in bytes (0 = unlimited)
+    pub max_buffer_bytes: usize,
+    /// Behaviour when the buffer cap is reached
+    pub overflow_policy: OverflowPolicy,
 }
 
 impl Default for StreamingConfig {
@@ -26,6 +37,8 @@ impl Default for StreamingConfig {
             chunk_duration_ms: 100,
             crossfade_enabled: true,
             crossfade_samples: 256,
+            max_buffer_bytes: 32 * 1024 * 1024,
+            overflow_policy: OverflowPolicy::Reject,
         }
     }
 }
@@ -37,6 +50,9 @@ pub struct AudioChunkBuffer {
     sample_buffer: VecDeque<f32>,
     total_tokens_processed: usize,
     sample_rate: u32,
+    loudness: Option<LoudnessNormalizer>,
+    memory: Option<Arc<OutputMemoryTracker>>,
+    dropped_samples: usize,
 }
 
 impl AudioChunkBuffer {
@@ -48,17 +64,59 @@ impl AudioChunkBuffer {
             sample_buffer: VecDeque::new(),
             total_tokens_processed: 0,
             sample_rate,
+            loudness: None,
+            memory: None,
+            dropped_samples: 0,
         }
     }
 
+    /// Account buffered samples against a shared global budget
+    pub fn with_memory_tracker(mut self, tracker: Arc<OutputMemoryTracker>) -> Self {
+        self.memory = Some(tracker);
+        self
+    }
+
+    /// Normalize loudness of emitted chunks
+    pub fn with_loudness(mut self, config: LoudnessConfig) -> Self {
+        self.loudness = Some(LoudnessNormalizer::new(config, self.sample_rate));
+        self
+    }
+
     /// Add audio tokens to the buffer
     pub fn push_tokens(&mut self, tokens: Vec<u32>) {
         self.token_buffer.push_back(tokens);
     }
 
-    /// Add decoded samples to the buffer
-    pub fn push_samples(&mut self, samples: &[f32]) {
-        self.sample_buffer.extend(samples);
+    /// Add decoded samples to the buffer.
+    ///
+    /// Enforces the per-request and global memory caps according to the
+    /// configured overflow policy.
+    pub fn push_samples(&mut self, samples: &[f32]) -> Result<()> {
+        let sample_buffer = &mut self.sample_buffer;
+        let dropped_samples = &mut self.dropped_samples;
+        let keep = reserve_samples(
+            sample_buffer.len(),
+            samples.len(),
+            self.config.max_buffer_bytes,
+            self.memory.as_deref(),
+            self.config.overflow_policy,
+            |count| {
+                sample_buffer.drain(..count);
+                *dropped_samples += count;
+                if count > 0 {
+                    warn!("Output buffer full, dropped {} oldest samples", count);
+                }
+            },
+        )?;
+        self.sample_buffer.extend(&samples[samples.len() - keep..]);
+        Ok(())
+    }
+
+    /// Return buffer memory for `count` samples to the global budget
+    fn release(&self, count: usize) {
+        if let Some(tracker) = &self.memory {
+            tracker.release(count * BYTES_PER_SAMPLE);
+        }
     }
 
     /// Check if we have enough data to emit a chunk
@@ -74,7 +132,7 @@ impl AudioChunkBuffer {
     }
 
     /// Take a chunk of samples from the buffer
-    pub fn take_chunk(&mut self) -> Option<Vec<f32>> {
+    pub fn take_chunk(&mut self) -> Option<SampleBuffer> {
         let chunk_samples =
             (self.sample_rate as f32 * self.config.chunk_duration_ms as f32 / 1000.0) as usize;
 
@@ -82,21 +140,42 @@ impl AudioChunkBuffer {
             return None;
         }
 
-        let mut chunk: Vec<f32> = self.sample_buffer.drain(..chunk_samples).collect();
+        // The one copy on the way out: crossfade and loudness then work in
+        // place and the chunk is shared from here on
+        let mut chunk = self.drain_front(chunk_samples);
 
         // Apply crossfade if enabled and there's more data
         if self.config.crossfade_enabled && !self.sample_buffer.is_empty() {
             self.apply_crossfade(&mut chunk);
         }
 
+        if let Some(normalizer) = self.loudness.as_mut() {
+            normalizer.process(&mut chunk);
+        }
+
         self.total_tokens_processed += 1;
         debug!("Emitting chunk of {} samples", chunk.len());
-        Some(chunk)
+        Some(chunk.into())
     }
 
     /// Take all remaining samples
-    pub fn take_remaining(&mut self) -> Vec<f32> {
-        self.sample_buffer.drain(..).collect()
+    pub fn take_remaining(&mut self) -> SampleBuffer {
+        let mut remaining = self.drain_front(self.sample_buffer.len());
+        if let Some(normalizer) = self.loudness.as_mut() {
+            normalizer.process(&mut remaining);
+        }
+        remaining.into()
+    }
+
+    /// Move the oldest `count` samples out in one contiguous copy
+    fn drain_front(&mut self, count: usize) -> Vec<f32> {
+        let (front, back) = self.sample_buffer.as_slices();
+        let mut samples = Vec::with_capacity(count);
+        samples.extend_from_slice(&front[..count.min(front.len())]);
+        samples.extend_from_slice(&back[..count - samples.len()]);
+        self.sample_buffer.drain(..count);
+        self.release(count);
+        samples
     }
 
     /// Apply crossfade to smooth chunk boundaries
@@ -104,17 +183,11 @@ impl AudioChunkBuffer {
         let fade_len = self.config.crossfade_samples.min(chunk.len());
         let start = chunk.len() - fade_len;
 
-        // Fade out end of current chunk
-        for (i, sample) in chunk[start..].iter_mut().enumerate() {
-            let fade = 1.0 - (i as f32 / fade_len as f32);
-            *sample *= fade;
-        }
-
-        // Fade in start of next chunk (peek at buffer)
-        for (i, &next_sample) in self.sample_buffer.iter().take(fade_len).enumerate() {
-            let fade = i as f32 / fade_len as f32;
-            chunk[start + i] += next_sample * fade;
-        }
+        // Fade out end of current chunk while fading in the start of the
+        // next one (peeked from the buffer)
+        let next = self.sample_buffer.make_contiguous();
+        let next = &next[..fade_len.min(next.len())];
+        simd::crossfade(&mut chunk[start..], next);
     }
 
     /// Get current buffer statistics
@@ -125,13 +198,19 @@ impl AudioChunkBuffer {
             total_processed: self.total_tokens_processed,
             buffer_duration_ms: (self.sample_buffer.len() as f32 / self.sample_rate as f32)
                 * 1000.0,
+            buffered_bytes: self.sample_buffer.len() * BYTES_PER_SAMPLE,
+            dropped_samples: self.dropped_samples,
         }
     }
 
     /// Clear the buffer
     pub fn clear(&mut self) {
         self.token_buffer.clear();
+        self.release(self.sample_buffer.len());
         self.sample_buffer.clear();
+        if let Some(normalizer) = self.loudness.as_mut() {
+            normalizer.reset();
+        }
     }
 }
 
@@ -142,4 +221,63 @@ pub struct BufferStats {
     pub samples_buffered: usize,
     pub total_processed: usize,
     pub buffer_duration_ms: f32,
+    pub buffered_bytes: usize,
+    pub dropped_samples: usize,
+}
+
+impl Drop for AudioChunkBuffer {
+    fn drop(&mut self) {
+        self.release(self.sample_buffer.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_buffer_bytes: usize, overflow_policy: OverflowPolicy) -> StreamingConfig {
+        StreamingConfig {
+            max_buffer_bytes,
+            overflow_policy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reject_policy() {
+        let mut buffer = AudioChunkBuffer::new(config(400, OverflowPolicy::Reject), 24000);
+        assert!(buffer.push_samples(&[0.0; 80]).is_ok());
+        assert!(buffer.push_samples(&[0.0; 40]).is_err());
+        assert_eq!(buffer.stats().buffered_bytes, 320);
+    }
+
+    #[test]
+    fn test_drop_oldest_policy() {
+        let mut buffer = AudioChunkBuffer::new(config(400, OverflowPolicy::DropOldest), 24000);
+        buffer.push_samples(&[1.0; 80]).unwrap();
+        buffer.push_samples(&[2.0; 40]).unwrap();
+
+        let stats = buffer.stats();
+        assert_eq!(stats.samples_buffered, 100);
+        assert_eq!(stats.dropped_samples, 20);
+        assert_eq!(buffer.take_remaining().last(), Some(&2.0));
+    }
+
+    #[test]
+    fn test_global_budget_released() {
+        let tracker = Arc::new(OutputMemoryTracker::new(1000));
+        {
+            let mut buffer = AudioChunkBuffer::new(config(0, OverflowPolicy::Reject), 24000)
+                .with_memory_tracker(tracker.clone());
+            buffer.push_samples(&[0.0; 200]).unwrap();
+            assert_eq!(tracker.used(), 800);
+            assert!(buffer.push_samples(&[0.0; 100]).is_err());
+
+            buffer.take_remaining();
+            assert_eq!(tracker.used(), 0);
+            buffer.push_samples(&[0.0; 50]).unwrap();
+        }
+        assert_eq!(tracker.used(), 0);
+        assert_eq!(tracker.stats().peak_bytes, 800);
+    }
 }
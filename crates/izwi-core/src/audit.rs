@@ -0,0 +1,383 @@
+//! Audit log of API requests.
+//!
+//! Every API request is appended to a JSON-lines file with the API key that
+//! made it, the endpoint, its outcome and how long it took. Depending on
+//! `privacy`, the input text is left out, recorded as a SHA-256 hash or kept
+//! in full. The file is rotated once it reaches `max_file_bytes`, keeping
+//! `max_files` older files as `audit.jsonl.1`, `audit.jsonl.2` and so on,
+//! and can be searched for compliance reviews.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::config::{AuditConfig, AuditPrivacy, PrivacyConfig};
+use crate::error::Result;
+
+/// How a request ended, from its HTTP status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    /// Rejected as invalid, unauthorized or over a limit
+    ClientError,
+    ServerError,
+}
+
+impl AuditOutcome {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            500.. => Self::ServerError,
+            400..=499 => Self::ClientError,
+            _ => Self::Success,
+        }
+    }
+}
+
+/// One audited request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix time in milliseconds when the request arrived
+    pub timestamp_ms: u64,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Name of the API key that made the request
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub method: String,
+    pub endpoint: String,
+    pub status: u16,
+    pub outcome: AuditOutcome,
+    /// Time until the response headers were sent
+    pub duration_ms: u64,
+    /// Hex SHA-256 of the input text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl AuditRecord {
+    /// Attach the request's input text as `privacy` allows, redacting the
+    /// kept text with the engine's privacy settings
+    pub fn with_text(
+        mut self,
+        text: Option<&str>,
+        privacy: AuditPrivacy,
+        redaction: &PrivacyConfig,
+    ) -> Self {
+        let Some(text) = text else {
+            return self;
+        };
+        match privacy {
+            AuditPrivacy::None => {}
+            AuditPrivacy::Hash => self.text_sha256 = Some(sha256_hex(text)),
+            AuditPrivacy::Full => {
+                self.text_sha256 = Some(sha256_hex(text));
+                self.text = Some(redaction.redact(text).into_owned());
+            }
+        }
+        self
+    }
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Unix time in milliseconds
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Filter for searching the audit log
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    /// Earliest arrival, inclusive, in Unix milliseconds
+    #[serde(default)]
+    pub since_ms: Option<u64>,
+    /// Latest arrival, exclusive, in Unix milliseconds
+    #[serde(default)]
+    pub until_ms: Option<u64>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Only requests whose endpoint starts with this path
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub outcome: Option<AuditOutcome>,
+    /// Most recent records returned (0 = all)
+    #[serde(default)]
+    pub limit: usize,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.since_ms
+            .is_none_or(|since| record.timestamp_ms >= since)
+            && self
+                .until_ms
+                .is_none_or(|until| record.timestamp_ms < until)
+            && self
+                .api_key
+                .as_ref()
+                .is_none_or(|key| record.api_key.as_ref() == Some(key))
+            && self
+                .endpoint
+                .as_ref()
+                .is_none_or(|prefix| record.endpoint.starts_with(prefix.as_str()))
+            && self.outcome.is_none_or(|outcome| record.outcome == outcome)
+    }
+}
+
+/// Append-only audit log with size-based rotation.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    privacy: AuditPrivacy,
+    max_file_bytes: u64,
+    max_files: usize,
+    /// Open file and its size, locked together
+    inner: Mutex<(File, u64)>,
+}
+
+impl AuditLog {
+    /// Open the configured log, or `None` when auditing is disabled
+    pub fn from_config(config: &AuditConfig) -> Result<Option<Self>> {
+        match &config.path {
+            Some(path) => Ok(Some(Self::open(
+                path,
+                config.privacy,
+                config.max_file_bytes,
+                config.max_files,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Open or create the log at `path` for appending
+    pub fn open(
+        path: impl AsRef<Path>,
+        privacy: AuditPrivacy,
+        max_file_bytes: u64,
+        max_files: usize,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        info!("Audit log at {:?} (privacy: {:?})", path, privacy);
+        Ok(Self {
+            path,
+            privacy,
+            max_file_bytes,
+            max_files,
+            inner: Mutex::new((file, size)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How much of the input text records keep
+    pub fn privacy(&self) -> AuditPrivacy {
+        self.privacy
+    }
+
+    /// `path.N`, the Nth most recent rotated file
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// Shift rotated files up by one, dropping the oldest, and start a new
+    /// current file
+    fn rotate(&self) -> Result<File> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        Ok(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?)
+    }
+
+    /// Append a record, rotating first if it would overflow the file
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (file, size) = &mut *inner;
+        if self.max_file_bytes > 0 && *size > 0 && *size + line.len() as u64 > self.max_file_bytes {
+            *file = self.rotate()?;
+            *size = 0;
+        }
+        file.write_all(&line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Append a record, logging rather than returning failures
+    pub fn record(&self, record: AuditRecord) {
+        if let Err(e) = self.append(&record) {
+            warn!("Failed to write audit record to {:?}: {}", self.path, e);
+        }
+    }
+
+    /// Records matching `query`, oldest first, across the current and
+    /// rotated files
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>> {
+        // Hold the lock so a rotation cannot move files mid-read
+        let _inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let files = (1..=self.max_files)
+            .rev()
+            .map(|n| self.rotated_path(n))
+            .chain([self.path.clone()]);
+
+        let mut records = VecDeque::new();
+        for path in files.filter(|p| p.exists()) {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // A crash mid-write can leave a torn last line
+                let Ok(record) = serde_json::from_str::<AuditRecord>(&line) else {
+                    continue;
+                };
+                if query.matches(&record) {
+                    records.push_back(record);
+                    if query.limit > 0 && records.len() > query.limit {
+                        records.pop_front();
+                    }
+                }
+            }
+        }
+        Ok(records.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(api_key: &str, endpoint: &str, timestamp_ms: u64, status: u16) -> AuditRecord {
+        AuditRecord {
+            timestamp_ms,
+            request_id: None,
+            api_key: Some(api_key.to_string()),
+            method: "POST".to_string(),
+            endpoint: endpoint.to_string(),
+            status,
+            outcome: AuditOutcome::from_status(status),
+            duration_ms: 12,
+            text_sha256: None,
+            text: None,
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_query() {
+        let dir = std::env::temp_dir().join(format!("izwi-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("audit.jsonl");
+        let line_len = serde_json::to_vec(&record("alice", "/api/v1/tts", 1000, 200))
+            .unwrap()
+            .len() as u64
+            + 1;
+
+        // Two records per file, two rotated files kept
+        let log = AuditLog::open(&path, AuditPrivacy::Hash, 2 * line_len, 2).unwrap();
+        for i in 0..7 {
+            let key = if i % 2 == 0 { "alice" } else { "bobby" };
+            log.append(&record(key, "/api/v1/tts", 1000 + i, 200))
+                .unwrap();
+        }
+        assert!(log.rotated_path(2).exists());
+        assert!(!log.rotated_path(3).exists());
+
+        // The oldest file was dropped: 1000 and 1001 are gone
+        let all = log.query(&AuditQuery::default()).unwrap();
+        let times: Vec<_> = all.iter().map(|r| r.timestamp_ms).collect();
+        assert_eq!(times, vec![1002, 1003, 1004, 1005, 1006]);
+
+        let alice = log
+            .query(&AuditQuery {
+                api_key: Some("alice".to_string()),
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        let times: Vec<_> = alice.iter().map(|r| r.timestamp_ms).collect();
+        assert_eq!(times, vec![1004, 1006]);
+
+        let keep = PrivacyConfig::default();
+        let hashed = record("alice", "/api/v1/tts", 0, 500).with_text(
+            Some("hello"),
+            AuditPrivacy::Hash,
+            &keep,
+        );
+        assert_eq!(hashed.outcome, AuditOutcome::ServerError);
+        assert_eq!(
+            hashed.text_sha256.as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+        assert_eq!(hashed.text, None);
+        let full = record("alice", "/api/v1/tts", 0, 200).with_text(
+            Some("hello"),
+            AuditPrivacy::Full,
+            &keep,
+        );
+        assert_eq!(full.text.as_deref(), Some("hello"));
+        let none = record("alice", "/api/v1/tts", 0, 200).with_text(
+            Some("hello"),
+            AuditPrivacy::None,
+            &keep,
+        );
+        assert_eq!(none.text_sha256, None);
+
+        // Redaction applies to the kept text but not to its hash
+        let truncate = PrivacyConfig {
+            mode: crate::config::RedactionMode::Truncate,
+            truncate_chars: 2,
+        };
+        let redacted = record("alice", "/api/v1/tts", 0, 200).with_text(
+            Some("hello"),
+            AuditPrivacy::Full,
+            &truncate,
+        );
+        assert_eq!(redacted.text.as_deref(), Some("he... (5 chars)"));
+        assert_eq!(redacted.text_sha256, hashed.text_sha256);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
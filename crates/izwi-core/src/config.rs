@@ -1,7 +1,24 @@
 //! Configuration types for the Izwi TTS engine
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::device::Device;
+use crate::engine::KVCacheDtype;
+use crate::engine::{ContextPolicy, Priority, SchedulingPolicy};
+use crate::error::{Error, Result};
+use crate::inference::presets::{GenerationPreset, BUILTIN_PRESETS};
+use crate::inference::transport::{
+    NamedPipeTransport, SharedTransport, TcpTransport, TransportKind, UnixTransport,
+};
+use crate::lexicon::LEXICON_FILE;
+use crate::model::ModelVariant;
+use crate::text::PROFANITY_FILE;
 
 /// Main engine configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +27,10 @@ pub struct EngineConfig {
     #[serde(default = "default_models_dir")]
     pub models_dir: PathBuf,
 
+    /// More directories scanned for locally present model folders
+    #[serde(default)]
+    pub extra_model_dirs: Vec<PathBuf>,
+
     /// Maximum batch size for inference
     #[serde(default = "default_max_batch_size")]
     pub max_batch_size: usize,
@@ -22,30 +43,378 @@ pub struct EngineConfig {
     #[serde(default = "default_chunk_size")]
     pub chunk_size: usize,
 
-    /// Data type for KV cache
+    /// Data type for KV cache: float16, bfloat16, float32 or int8
     #[serde(default = "default_kv_cache_dtype")]
     pub kv_cache_dtype: String,
 
-    /// Enable Metal GPU acceleration
+    /// Enable Metal GPU acceleration when `device` is `auto`
     #[serde(default = "default_use_metal")]
     pub use_metal: bool,
 
+    /// Device for the codec decoder and KV caches: auto, cpu, metal or
+    /// cuda:N
+    #[serde(default)]
+    pub device: Device,
+
+    /// CUDA GPUs to split models over layer by layer, e.g.
+    /// `["cuda:0", "cuda:1"]`; overrides `device` when set
+    #[serde(default)]
+    pub devices: Vec<Device>,
+
     /// Number of threads for CPU operations
     #[serde(default = "default_num_threads")]
     pub num_threads: usize,
+
+    /// Order in which waiting requests are started (fcfs, priority or
+    /// fair_share)
+    #[serde(default)]
+    pub scheduling_policy: SchedulingPolicy,
+
+    /// Global cap on decoded audio held in output buffers, in bytes (0 = unlimited)
+    #[serde(default = "default_max_output_buffer_bytes")]
+    pub max_output_buffer_bytes: usize,
+
+    /// Cap on generation requests queued or in flight (0 = unlimited)
+    #[serde(default = "default_max_waiting_requests")]
+    pub max_waiting_requests: usize,
+
+    /// Cap on prompt tokens of generation requests queued or in flight
+    /// (0 = unlimited)
+    #[serde(default = "default_max_queued_tokens")]
+    pub max_queued_tokens: usize,
+
+    /// Budget for completed outputs kept for range extraction, in bytes (0 = disabled)
+    #[serde(default = "default_max_stored_output_bytes")]
+    pub max_stored_output_bytes: usize,
+
+    /// Async jobs whose speech is estimated to run longer than this many
+    /// seconds are written to a WAV file as they generate instead of being
+    /// assembled in memory (0 = never)
+    #[serde(default = "default_spool_threshold_secs")]
+    pub spool_threshold_secs: f64,
+
+    /// Directory for spooled job outputs
+    #[serde(default = "default_spool_dir")]
+    pub spool_dir: PathBuf,
+
+    /// Files of a model fetched in parallel
+    #[serde(default = "default_download_concurrency")]
+    pub download_concurrency: usize,
+
+    /// Cap on combined download speed, in bytes per second (0 = unlimited)
+    #[serde(default)]
+    pub download_bandwidth_limit: u64,
+
+    /// HuggingFace token for private and gated models (defaults to `HF_TOKEN`
+    /// or the `huggingface-cli login` token file)
+    #[serde(default)]
+    pub hf_token: Option<String>,
+
+    /// HTTP(S) proxy for model downloads
+    #[serde(default)]
+    pub download_proxy: Option<String>,
+
+    /// Disk space downloaded models may use before the least recently used
+    /// are evicted, in bytes (0 = unlimited)
+    #[serde(default)]
+    pub model_cache_limit: u64,
+
+    /// Read model weights into the page cache while loading, instead of
+    /// paging them in during the first requests
+    #[serde(default = "default_prefetch_weights")]
+    pub prefetch_weights: bool,
+
+    /// Rewrite weights into the layouts the kernels use (fused QKV, packed
+    /// conv weights) and cache the results next to the model
+    #[serde(default = "default_layout_cache")]
+    pub layout_cache: bool,
+
+    /// Voice aliases (old name -> new name), resolved before generation
+    #[serde(default)]
+    pub voice_aliases: HashMap<String, VoiceAlias>,
+
+    /// Sampling presets requests can name with `preset`, in addition to
+    /// (or replacing) the built-in fast, balanced and quality
+    #[serde(default)]
+    pub presets: HashMap<String, GenerationPreset>,
+
+    /// Sampling defaults per model (model directory name -> settings),
+    /// beneath any preset and request values
+    #[serde(default)]
+    pub model_defaults: HashMap<String, GenerationPreset>,
+
+    /// Silence between dialogue lines that don't set their own, in milliseconds
+    #[serde(default = "default_dialogue_pause_ms")]
+    pub dialogue_pause_ms: u32,
+
+    /// Lines accepted in one dialogue script (0 = unlimited)
+    #[serde(default = "default_max_dialogue_lines")]
+    pub max_dialogue_lines: usize,
+
+    /// Prompt tokens a chat turn may use before older turns are dropped or
+    /// summarized (0 = three quarters of the maximum sequence length)
+    #[serde(default)]
+    pub chat_context_tokens: usize,
+
+    /// How chat history past `chat_context_tokens` is shortened (drop_oldest
+    /// or summarize)
+    #[serde(default)]
+    pub chat_context_policy: ContextPolicy,
+
+    /// KV cache tokens a sequence keeps resident, evicting its oldest
+    /// blocks as it grows (0 = keep everything)
+    #[serde(default)]
+    pub kv_window_tokens: usize,
+
+    /// Leading tokens kept outside the window as attention sinks
+    #[serde(default)]
+    pub kv_sink_tokens: usize,
+
+    /// Let requests name a background track by URL for the server to fetch
+    #[serde(default)]
+    pub allow_background_urls: bool,
+
+    /// Largest background track accepted, in bytes (0 = unlimited)
+    #[serde(default = "default_max_background_bytes")]
+    pub max_background_bytes: usize,
+
+    /// Pronunciation lexicon (defaults to `lexicon.toml` in the models dir)
+    #[serde(default)]
+    pub lexicon_path: Option<PathBuf>,
+
+    /// Profanity word list (defaults to `profanity.txt` in the models dir,
+    /// or a built-in list when that is missing)
+    #[serde(default)]
+    pub profanity_path: Option<PathBuf>,
+
+    /// Sentence splitting of text into subtitle cues
+    #[serde(default)]
+    pub segmentation: SegmentationConfig,
+
+    /// Per-tenant encryption of stored outputs and saved voices
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    /// Write-ahead journal of accepted requests (disabled when unset)
+    #[serde(default)]
+    pub journal_path: Option<PathBuf>,
+
+    /// Requests kept in the journal, oldest dropped first (0 = unlimited)
+    #[serde(default = "default_journal_max_entries")]
+    pub journal_max_entries: usize,
+
+    /// Python daemons backing the TTS and ASR bridges
+    #[serde(default)]
+    pub bridge: BridgeConfig,
+
+    /// Cache of synthesized audio for repeated requests
+    #[serde(default)]
+    pub output_cache: OutputCacheConfig,
+
+    /// Dummy requests run after model loads
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+
+    /// Size limits checked before requests are queued
+    #[serde(default)]
+    pub limits: InputLimits,
+
+    /// Redaction of user text in logs and the audit trail
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
 }
 
 impl Default for EngineConfig {
     fn default() -> Self {
         Self {
             models_dir: default_models_dir(),
+            extra_model_dirs: Vec::new(),
             max_batch_size: default_max_batch_size(),
             max_sequence_length: default_max_sequence_length(),
             chunk_size: default_chunk_size(),
             kv_cache_dtype: default_kv_cache_dtype(),
             use_metal: default_use_metal(),
+            device: Device::default(),
+            devices: Vec::new(),
             num_threads: default_num_threads(),
+            scheduling_policy: SchedulingPolicy::default(),
+            max_output_buffer_bytes: default_max_output_buffer_bytes(),
+            max_waiting_requests: default_max_waiting_requests(),
+            max_queued_tokens: default_max_queued_tokens(),
+            max_stored_output_bytes: default_max_stored_output_bytes(),
+            spool_threshold_secs: default_spool_threshold_secs(),
+            spool_dir: default_spool_dir(),
+            download_concurrency: default_download_concurrency(),
+            download_bandwidth_limit: 0,
+            hf_token: None,
+            download_proxy: None,
+            model_cache_limit: 0,
+            prefetch_weights: default_prefetch_weights(),
+            layout_cache: default_layout_cache(),
+            voice_aliases: HashMap::new(),
+            presets: HashMap::new(),
+            model_defaults: HashMap::new(),
+            dialogue_pause_ms: default_dialogue_pause_ms(),
+            max_dialogue_lines: default_max_dialogue_lines(),
+            chat_context_tokens: 0,
+            chat_context_policy: ContextPolicy::default(),
+            kv_window_tokens: 0,
+            kv_sink_tokens: 0,
+            allow_background_urls: false,
+            max_background_bytes: default_max_background_bytes(),
+            lexicon_path: None,
+            profanity_path: None,
+            segmentation: SegmentationConfig::default(),
+            encryption: EncryptionConfig::default(),
+            journal_path: None,
+            journal_max_entries: default_journal_max_entries(),
+            bridge: BridgeConfig::default(),
+            output_cache: OutputCacheConfig::default(),
+            warmup: WarmupConfig::default(),
+            limits: InputLimits::default(),
+            privacy: PrivacyConfig::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Location of the pronunciation lexicon
+    pub fn lexicon_path(&self) -> PathBuf {
+        self.lexicon_path
+            .clone()
+            .unwrap_or_else(|| self.models_dir.join(LEXICON_FILE))
+    }
+
+    /// Location of the profanity word list
+    pub fn profanity_path(&self) -> PathBuf {
+        self.profanity_path
+            .clone()
+            .unwrap_or_else(|| self.models_dir.join(PROFANITY_FILE))
+    }
+
+    /// The device to run on, with `auto` resolved
+    pub fn compute_device(&self) -> Device {
+        self.device.resolve(self.use_metal)
+    }
+
+    /// Preset `name`, configured or built in
+    pub fn preset(&self, name: &str) -> Option<GenerationPreset> {
+        self.presets.get(name).copied().or_else(|| {
+            BUILTIN_PRESETS
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, preset)| *preset)
+        })
+    }
+
+    /// Every preset by name, configured ones replacing built-ins
+    pub fn all_presets(&self) -> BTreeMap<String, GenerationPreset> {
+        BUILTIN_PRESETS
+            .iter()
+            .map(|(name, preset)| (name.to_string(), *preset))
+            .chain(
+                self.presets
+                    .iter()
+                    .map(|(name, preset)| (name.clone(), *preset)),
+            )
+            .collect()
+    }
+
+    /// Sampling defaults configured for `variant`. Model names match
+    /// case-insensitively, as config keys may arrive lowercased.
+    pub fn model_defaults(&self, variant: ModelVariant) -> GenerationPreset {
+        self.model_defaults
+            .iter()
+            .find(|(model, _)| model.eq_ignore_ascii_case(variant.dir_name()))
+            .map(|(_, defaults)| *defaults)
+            .unwrap_or_default()
+    }
+
+    /// The KV cache element type; unknown names are rejected by `validate`
+    pub fn kv_dtype(&self) -> KVCacheDtype {
+        self.kv_cache_dtype.parse().unwrap_or_default()
+    }
+
+    /// Check values that deserialize fine but cannot be used
+    pub fn validate(&self) -> Result<()> {
+        if self.max_batch_size == 0 {
+            return Err(Error::ConfigError(
+                "engine.max_batch_size must be at least 1".into(),
+            ));
+        }
+        if self.max_sequence_length == 0 {
+            return Err(Error::ConfigError(
+                "engine.max_sequence_length must be at least 1".into(),
+            ));
+        }
+        if self.chunk_size == 0 {
+            return Err(Error::ConfigError(
+                "engine.chunk_size must be at least 1".into(),
+            ));
+        }
+        if self.num_threads == 0 {
+            return Err(Error::ConfigError(
+                "engine.num_threads must be at least 1".into(),
+            ));
         }
+        if self.download_concurrency == 0 {
+            return Err(Error::ConfigError(
+                "engine.download_concurrency must be at least 1".into(),
+            ));
+        }
+        self.kv_cache_dtype.parse::<KVCacheDtype>()?;
+        if self.bridge.startup_timeout_secs == 0 {
+            return Err(Error::ConfigError(
+                "engine.bridge.startup_timeout_secs must be at least 1".into(),
+            ));
+        }
+        self.device.validate()?;
+        if !self.devices.is_empty() {
+            if self.devices.len() < 2 {
+                return Err(Error::ConfigError(
+                    "engine.devices must list at least two devices; use engine.device for one"
+                        .into(),
+                ));
+            }
+            for (i, device) in self.devices.iter().enumerate() {
+                if !matches!(device, Device::Cuda(_)) {
+                    return Err(Error::ConfigError(format!(
+                        "engine.devices only supports CUDA devices, got '{}'",
+                        device
+                    )));
+                }
+                if self.devices[..i].contains(device) {
+                    return Err(Error::ConfigError(format!(
+                        "engine.devices lists '{}' more than once",
+                        device
+                    )));
+                }
+                device.validate()?;
+            }
+        }
+        if !self.bridge.transport.is_supported() {
+            return Err(Error::ConfigError(format!(
+                "engine.bridge.transport {:?} is not available on this platform",
+                self.bridge.transport
+            )));
+        }
+        if self.bridge.transport.resolve() == TransportKind::Tcp && self.bridge.tcp_base_port == 0 {
+            return Err(Error::ConfigError(
+                "engine.bridge.tcp_base_port must be set for the tcp transport".into(),
+            ));
+        }
+        for model in self.model_defaults.keys() {
+            if !ModelVariant::all()
+                .iter()
+                .any(|v| v.dir_name().eq_ignore_ascii_case(model))
+            {
+                return Err(Error::ConfigError(format!(
+                    "engine.model_defaults names unknown model '{}'",
+                    model
+                )));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -76,10 +445,504 @@ fn default_use_metal() -> bool {
     cfg!(target_os = "macos")
 }
 
+fn default_prefetch_weights() -> bool {
+    true
+}
+
+fn default_layout_cache() -> bool {
+    true
+}
+
 fn default_num_threads() -> usize {
     get_num_cpus().min(8)
 }
 
+pub(crate) fn default_max_output_buffer_bytes() -> usize {
+    512 * 1024 * 1024
+}
+
+pub(crate) fn default_max_waiting_requests() -> usize {
+    256
+}
+
+pub(crate) fn default_max_queued_tokens() -> usize {
+    262_144
+}
+
+fn default_max_stored_output_bytes() -> usize {
+    256 * 1024 * 1024
+}
+
+fn default_spool_threshold_secs() -> f64 {
+    600.0
+}
+
+fn default_spool_dir() -> PathBuf {
+    std::env::temp_dir().join("izwi-spool")
+}
+
+fn default_download_concurrency() -> usize {
+    4
+}
+
+fn default_journal_max_entries() -> usize {
+    100_000
+}
+
+fn default_max_background_bytes() -> usize {
+    50 * 1024 * 1024
+}
+
+fn default_dialogue_pause_ms() -> u32 {
+    400
+}
+
+fn default_max_dialogue_lines() -> usize {
+    200
+}
+
+/// Tenant encryption keys.
+///
+/// Keys are base64-encoded 256-bit values. The last key listed for a tenant
+/// is active; earlier ones are kept for reading data sealed before a rotation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Static keys per tenant
+    #[serde(default)]
+    pub tenant_keys: HashMap<String, Vec<String>>,
+
+    /// Command printing a tenant's base64 key, called with the tenant ID as
+    /// its argument (e.g. a KMS decrypt wrapper)
+    #[serde(default)]
+    pub key_command: Option<String>,
+
+    /// Refuse to store tenant data when no key is available
+    #[serde(default)]
+    pub require_key: bool,
+}
+
+/// Python daemon locations and interpreter.
+///
+/// Socket and pipe names include an instance ID (the process ID unless set),
+/// so several servers on one host each run their own daemons. TCP ports are
+/// fixed, so servers sharing a host need different `tcp_base_port`s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// How the server reaches its daemons
+    #[serde(default)]
+    pub transport: TransportKind,
+
+    /// Directory for daemon sockets
+    #[serde(default = "default_socket_dir")]
+    pub socket_dir: PathBuf,
+
+    /// Port of the TTS daemon with the TCP transport; the ASR daemon uses
+    /// the next one. Daemons listen on the loopback interface only.
+    #[serde(default = "default_tcp_base_port")]
+    pub tcp_base_port: u16,
+
+    /// Directory containing the daemon scripts
+    #[serde(default = "default_script_dir")]
+    pub script_dir: PathBuf,
+
+    /// Python interpreter; when unset, `IZWI_PYTHON`, the active virtualenv,
+    /// `./.venv` and then `python3` are tried in order
+    #[serde(default)]
+    pub python: Option<PathBuf>,
+
+    /// Seconds a daemon has to start answering on its socket
+    #[serde(default = "default_startup_timeout_secs")]
+    pub startup_timeout_secs: u64,
+
+    /// Retries of a daemon call that could not reach the daemon, with the
+    /// delay doubling each time
+    #[serde(default = "default_daemon_retries")]
+    pub retries: u32,
+
+    /// Delay before the first retry, in milliseconds
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+
+    /// Consecutive failed calls after which calls fail fast (0 = never)
+    #[serde(default = "default_breaker_threshold")]
+    pub breaker_threshold: u32,
+
+    /// Seconds calls fail fast before the daemon is tried again
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+
+    /// Name distinguishing this instance's sockets (defaults to the process ID)
+    #[serde(default)]
+    pub instance_id: Option<String>,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            transport: TransportKind::default(),
+            socket_dir: default_socket_dir(),
+            tcp_base_port: default_tcp_base_port(),
+            script_dir: default_script_dir(),
+            python: None,
+            startup_timeout_secs: default_startup_timeout_secs(),
+            retries: default_daemon_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            breaker_threshold: default_breaker_threshold(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
+            instance_id: None,
+        }
+    }
+}
+
+impl BridgeConfig {
+    /// Socket for the named daemon, e.g. `izwi-1234-tts.sock`
+    pub fn socket_path(&self, daemon: &str) -> PathBuf {
+        self.socket_dir
+            .join(format!("{}.sock", self.endpoint_name(daemon)))
+    }
+
+    /// Transport to the named daemon (`tts` or `asr`)
+    pub fn transport(&self, daemon: &str) -> SharedTransport {
+        match self.transport.resolve() {
+            TransportKind::Tcp => {
+                let offset = u16::from(daemon != "tts");
+                let port = self.tcp_base_port.saturating_add(offset);
+                Arc::new(TcpTransport::new(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    port,
+                ))))
+            }
+            TransportKind::NamedPipe => {
+                Arc::new(NamedPipeTransport::new(self.endpoint_name(daemon)))
+            }
+            _ => Arc::new(UnixTransport::new(self.socket_path(daemon))),
+        }
+    }
+
+    /// Per-instance name of a daemon's socket or pipe, e.g. `izwi-1234-tts`
+    fn endpoint_name(&self, daemon: &str) -> String {
+        let instance = self
+            .instance_id
+            .clone()
+            .unwrap_or_else(|| std::process::id().to_string());
+        format!("izwi-{}-{}", instance, daemon)
+    }
+
+    /// Path of a script in the script directory
+    pub fn script_path(&self, script: &str) -> PathBuf {
+        self.script_dir.join(script)
+    }
+
+    /// Interpreter used to run the daemons
+    pub fn python_executable(&self) -> PathBuf {
+        if let Some(python) = &self.python {
+            return python.clone();
+        }
+        if let Some(python) = std::env::var_os("IZWI_PYTHON").filter(|v| !v.is_empty()) {
+            return PathBuf::from(python);
+        }
+        let venvs = std::env::var_os("VIRTUAL_ENV")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(std::env::current_dir().ok().map(|dir| dir.join(".venv")));
+        for venv in venvs {
+            let python = venv_python(&venv);
+            if python.is_file() {
+                return python;
+            }
+        }
+        PathBuf::from("python3")
+    }
+
+    pub fn startup_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.startup_timeout_secs)
+    }
+}
+
+/// Interpreter inside a virtualenv
+fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python")
+    }
+}
+
+/// Cache of synthesized audio keyed by text, voice and parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputCacheConfig {
+    /// Serve repeated requests from the cache
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Audio kept in memory, least recently used evicted first (0 = none)
+    #[serde(default = "default_cache_memory_bytes")]
+    pub max_memory_bytes: usize,
+
+    /// Directory of the disk tier, kept across restarts (disabled when unset)
+    #[serde(default)]
+    pub disk_dir: Option<PathBuf>,
+
+    /// Audio kept on disk, oldest evicted first (0 = unlimited)
+    #[serde(default)]
+    pub max_disk_bytes: usize,
+}
+
+impl Default for OutputCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_memory_bytes: default_cache_memory_bytes(),
+            disk_dir: None,
+            max_disk_bytes: 0,
+        }
+    }
+}
+
+/// Short dummy requests run right after a model loads, so pipeline
+/// compilation and memory allocation don't land on the first real request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    /// Warm up models at startup and whenever one is loaded
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Concurrent requests per warmup pass, one pass per size (capped at the
+    /// maximum batch size)
+    #[serde(default = "default_warmup_batch_sizes")]
+    pub batch_sizes: Vec<usize>,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_sizes: default_warmup_batch_sizes(),
+        }
+    }
+}
+
+fn default_warmup_batch_sizes() -> Vec<usize> {
+    vec![1]
+}
+
+/// Request size limits, checked before requests reach the scheduler or the
+/// daemons
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputLimits {
+    /// Longest accepted input text, in characters
+    #[serde(default = "default_max_text_chars")]
+    pub max_text_chars: usize,
+
+    /// Largest accepted audio upload, in decoded bytes
+    #[serde(default = "default_max_audio_bytes")]
+    pub max_audio_bytes: usize,
+
+    /// Longest accepted audio upload, in seconds
+    #[serde(default = "default_max_audio_upload_seconds")]
+    pub max_audio_seconds: f64,
+
+    /// Sample rates accepted for uploaded audio (empty = any)
+    #[serde(default = "default_sample_rates")]
+    pub sample_rates: Vec<u32>,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            max_text_chars: default_max_text_chars(),
+            max_audio_bytes: default_max_audio_bytes(),
+            max_audio_seconds: default_max_audio_upload_seconds(),
+            sample_rates: default_sample_rates(),
+        }
+    }
+}
+
+impl InputLimits {
+    /// Largest HTTP request body: the base64 form of the largest audio
+    /// upload plus room for the other fields
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_audio_bytes.div_ceil(3) * 4 + 1024 * 1024
+    }
+}
+
+/// How user text and call identifiers are written to logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionMode {
+    /// Written as given
+    #[default]
+    Off,
+    /// Cut to the first `truncate_chars` characters
+    Truncate,
+    /// Replaced by a short SHA-256 digest, equal for equal text
+    Hash,
+}
+
+/// Privacy settings for running where user text must not reach logs, e.g.
+/// under the GDPR.
+///
+/// Sentence segmentation settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentationConfig {
+    /// Abbreviations per language, in addition to the built-in ones
+    /// ("English" = ["approx", "min"]); a full stop after them does not end
+    /// a sentence
+    #[serde(default)]
+    pub abbreviations: HashMap<String, Vec<String>>,
+}
+
+/// Redaction covers tracing logs and the text kept by a `full` audit log.
+/// Audit hashes are always taken of the original text, so they still match
+/// a known input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    #[serde(default)]
+    pub mode: RedactionMode,
+
+    /// Characters kept by `truncate`
+    #[serde(default = "default_truncate_chars")]
+    pub truncate_chars: usize,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            mode: RedactionMode::default(),
+            truncate_chars: default_truncate_chars(),
+        }
+    }
+}
+
+impl PrivacyConfig {
+    /// `text` as it may be logged
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let chars = text.chars().count();
+        match self.mode {
+            RedactionMode::Off => Cow::Borrowed(text),
+            RedactionMode::Truncate if chars <= self.truncate_chars => Cow::Borrowed(text),
+            RedactionMode::Truncate => {
+                let kept: String = text.chars().take(self.truncate_chars).collect();
+                Cow::Owned(format!("{}... ({} chars)", kept, chars))
+            }
+            RedactionMode::Hash => {
+                let digest = Sha256::digest(text.as_bytes());
+                let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+                Cow::Owned(format!("sha256:{} ({} chars)", hex, chars))
+            }
+        }
+    }
+}
+
+fn default_truncate_chars() -> usize {
+    16
+}
+
+fn default_max_text_chars() -> usize {
+    10_000
+}
+
+fn default_max_audio_bytes() -> usize {
+    50 * 1024 * 1024
+}
+
+fn default_max_audio_upload_seconds() -> f64 {
+    1800.0
+}
+
+fn default_sample_rates() -> Vec<u32> {
+    vec![8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000]
+}
+
+fn default_cache_memory_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_socket_dir() -> PathBuf {
+    std::env::temp_dir()
+}
+
+fn default_tcp_base_port() -> u16 {
+    47800
+}
+
+fn default_script_dir() -> PathBuf {
+    std::env::current_dir()
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("scripts")
+}
+
+fn default_startup_timeout_secs() -> u64 {
+    10
+}
+
+fn default_daemon_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    200
+}
+
+fn default_breaker_threshold() -> u32 {
+    5
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    30
+}
+
+/// A voice alias entry.
+///
+/// Accepts either a bare target name (`Anna = "Ono_anna"`) or a table with
+/// deprecation details (`Old = { target = "Ryan", deprecated = true }`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "VoiceAliasRepr")]
+pub struct VoiceAlias {
+    /// Voice (or further alias) this name maps to
+    pub target: String,
+    /// Emit a deprecation warning when this alias is used
+    #[serde(default)]
+    pub deprecated: bool,
+    /// Custom warning text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VoiceAliasRepr {
+    Target(String),
+    Detailed {
+        target: String,
+        #[serde(default)]
+        deprecated: bool,
+        #[serde(default)]
+        message: Option<String>,
+    },
+}
+
+impl From<VoiceAliasRepr> for VoiceAlias {
+    fn from(repr: VoiceAliasRepr) -> Self {
+        match repr {
+            VoiceAliasRepr::Target(target) => Self {
+                target,
+                deprecated: false,
+                message: None,
+            },
+            VoiceAliasRepr::Detailed {
+                target,
+                deprecated,
+                message,
+            } => Self {
+                target,
+                deprecated,
+                message,
+            },
+        }
+    }
+}
+
 /// Model-specific configuration from config.json (Qwen3-TTS format)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
@@ -209,11 +1072,39 @@ pub struct ServerConfig {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    /// Port for the gRPC API (when built with the `grpc` feature)
+    #[serde(default = "default_grpc_port")]
+    pub grpc_port: u16,
+
     #[serde(default = "default_cors_enabled")]
     pub cors_enabled: bool,
 
     #[serde(default)]
     pub cors_origins: Vec<String>,
+
+    /// API key authentication and per-key limits
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    /// Per-request usage accounting
+    #[serde(default)]
+    pub usage: UsageConfig,
+
+    /// Audit log of API requests
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Object storage for async job results
+    #[serde(default)]
+    pub storage: StorageConfig,
+
+    /// Chunked uploads of large ASR inputs
+    #[serde(default)]
+    pub uploads: UploadConfig,
+
+    /// Long-form transcription jobs
+    #[serde(default)]
+    pub longform: LongformConfig,
 }
 
 impl Default for ServerConfig {
@@ -221,12 +1112,47 @@ impl Default for ServerConfig {
         Self {
             host: default_host(),
             port: default_port(),
+            grpc_port: default_grpc_port(),
             cors_enabled: default_cors_enabled(),
             cors_origins: vec!["*".to_string()],
+            auth: AuthConfig::default(),
+            usage: UsageConfig::default(),
+            audit: AuditConfig::default(),
+            storage: StorageConfig::default(),
+            uploads: UploadConfig::default(),
+            longform: LongformConfig::default(),
         }
     }
 }
 
+impl ServerConfig {
+    /// Check values that deserialize fine but cannot be used
+    pub fn validate(&self) -> Result<()> {
+        if self.host.trim().is_empty() {
+            return Err(Error::ConfigError("server.host must not be empty".into()));
+        }
+        if self.port == 0 {
+            return Err(Error::ConfigError("server.port must not be 0".into()));
+        }
+        if self.longform.concurrency == 0 {
+            return Err(Error::ConfigError(
+                "server.longform.concurrency must not be 0".into(),
+            ));
+        }
+        if self.longform.max_segment_secs <= 0.0 {
+            return Err(Error::ConfigError(
+                "server.longform.max_segment_secs must be positive".into(),
+            ));
+        }
+        self.auth.validate()
+    }
+
+    /// Address to bind, as `host:port`
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -235,12 +1161,648 @@ fn default_port() -> u16 {
     8080
 }
 
+fn default_grpc_port() -> u16 {
+    50051
+}
+
 fn default_cors_enabled() -> bool {
     true
 }
 
+/// Usage records kept for `/usage` reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageConfig {
+    /// How long records are kept, in seconds (0 = forever)
+    #[serde(default = "default_usage_retention_secs")]
+    pub retention_secs: u64,
+
+    /// Records kept, oldest dropped first (0 = unlimited)
+    #[serde(default = "default_max_usage_records")]
+    pub max_records: usize,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            retention_secs: default_usage_retention_secs(),
+            max_records: default_max_usage_records(),
+        }
+    }
+}
+
+fn default_usage_retention_secs() -> u64 {
+    30 * 24 * 60 * 60
+}
+
+fn default_max_usage_records() -> usize {
+    1_000_000
+}
+
+/// How much of a request's input text the audit log keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditPrivacy {
+    /// No trace of the text
+    None,
+    /// SHA-256 of the text, so a known text can be matched to requests
+    #[default]
+    Hash,
+    /// The text itself alongside its hash
+    Full,
+}
+
+/// Audit log written for compliance reviews.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// JSON-lines file to append to (auditing is off when unset)
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    #[serde(default)]
+    pub privacy: AuditPrivacy,
+
+    /// Size at which the file is rotated (0 = never)
+    #[serde(default = "default_audit_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Rotated files kept, oldest deleted first
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            privacy: AuditPrivacy::default(),
+            max_file_bytes: default_audit_max_file_bytes(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+fn default_audit_max_file_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> usize {
+    10
+}
+
+/// Resumable chunked uploads of audio too large for a JSON body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadConfig {
+    /// Directory parts and assembled uploads are kept in
+    #[serde(default = "default_upload_dir")]
+    pub dir: PathBuf,
+
+    /// Largest assembled upload, in bytes
+    #[serde(default = "default_upload_max_bytes")]
+    pub max_bytes: u64,
+
+    /// Time after creation when an upload and its parts are deleted
+    #[serde(default = "default_upload_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_upload_dir(),
+            max_bytes: default_upload_max_bytes(),
+            ttl_secs: default_upload_ttl_secs(),
+        }
+    }
+}
+
+fn default_upload_dir() -> PathBuf {
+    std::env::temp_dir().join("izwi-uploads")
+}
+
+fn default_upload_max_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024
+}
+
+fn default_upload_ttl_secs() -> u64 {
+    3600
+}
+
+/// Long-form transcription of recordings cut into segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LongformConfig {
+    /// Segments of one job transcribed at the same time
+    #[serde(default = "default_longform_concurrency")]
+    pub concurrency: usize,
+
+    /// Longest segment, in seconds
+    #[serde(default = "default_longform_max_segment_secs")]
+    pub max_segment_secs: f64,
+
+    /// Overlap of segments cut mid-speech, in seconds
+    #[serde(default = "default_longform_overlap_secs")]
+    pub overlap_secs: f64,
+
+    /// How long a finished job's transcript is kept, in seconds
+    #[serde(default = "default_longform_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for LongformConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_longform_concurrency(),
+            max_segment_secs: default_longform_max_segment_secs(),
+            overlap_secs: default_longform_overlap_secs(),
+            ttl_secs: default_longform_ttl_secs(),
+        }
+    }
+}
+
+fn default_longform_concurrency() -> usize {
+    4
+}
+
+fn default_longform_max_segment_secs() -> f64 {
+    30.0
+}
+
+fn default_longform_overlap_secs() -> f64 {
+    1.0
+}
+
+fn default_longform_ttl_secs() -> u64 {
+    3600
+}
+
+/// Where finished async job outputs are published.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Keep results in memory and serve them from the API
+    #[default]
+    None,
+    /// Write results to a local directory
+    Local,
+    /// Upload results to an S3-compatible bucket
+    S3,
+}
+
+/// Output storage for async job results.
+///
+/// With a backend configured, a finished job's audio is written to storage
+/// and the API hands out its URL instead of holding the audio in memory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+
+    /// Directory results are written to (local backend)
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Base URL objects are reachable under, e.g. a CDN in front of the
+    /// bucket or directory. Without it S3 objects get presigned URLs and
+    /// local files are streamed by the API.
+    #[serde(default)]
+    pub public_url: Option<String>,
+
+    /// Prefix prepended to every object key
+    #[serde(default)]
+    pub prefix: String,
+
+    #[serde(default)]
+    pub s3: S3Config,
+}
+
+/// Bucket settings for the S3 backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    /// Service endpoint, e.g. `https://s3.us-east-1.amazonaws.com` or a
+    /// MinIO/R2 URL
+    #[serde(default)]
+    pub endpoint: String,
+
+    #[serde(default)]
+    pub bucket: String,
+
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+
+    /// Access key ID (defaults to `AWS_ACCESS_KEY_ID`)
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+
+    /// Secret access key (defaults to `AWS_SECRET_ACCESS_KEY`)
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+
+    /// Address the bucket in the path (`endpoint/bucket/key`) rather than
+    /// the host name (`bucket.endpoint/key`)
+    #[serde(default = "default_s3_path_style")]
+    pub path_style: bool,
+
+    /// Lifetime of presigned download URLs, in seconds
+    #[serde(default = "default_s3_url_ttl_secs")]
+    pub url_ttl_secs: u64,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            bucket: String::new(),
+            region: default_s3_region(),
+            access_key_id: None,
+            secret_access_key: None,
+            path_style: default_s3_path_style(),
+            url_ttl_secs: default_s3_url_ttl_secs(),
+        }
+    }
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_path_style() -> bool {
+    true
+}
+
+fn default_s3_url_ttl_secs() -> u64 {
+    3600
+}
+
+/// API key authentication settings.
+///
+/// Authentication is enabled when at least one key is configured, either
+/// inline or in `api_keys_file`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Keys accepted in `Authorization: Bearer` headers
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+
+    /// TOML file with further keys as `[[keys]]` tables
+    #[serde(default)]
+    pub api_keys_file: Option<PathBuf>,
+
+    /// Default requests per minute per key (0 = unlimited)
+    #[serde(default)]
+    pub requests_per_minute: u32,
+
+    /// Default concurrent streaming requests per key (0 = unlimited)
+    #[serde(default)]
+    pub max_concurrent_streams: u32,
+
+    /// Default highest priority a key may request (`high` when unset)
+    #[serde(default)]
+    pub max_priority: Option<Priority>,
+}
+
+impl AuthConfig {
+    /// Highest priority granted to keys without their own `max_priority`
+    pub fn default_max_priority(&self) -> Priority {
+        self.max_priority.unwrap_or(Priority::High)
+    }
+
+    /// Check inline keys for empty or duplicate values
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for key in &self.api_keys {
+            key.validate()?;
+            if !seen.insert(key.key.as_str()) {
+                return Err(Error::ConfigError(format!(
+                    "server.auth: duplicate API key for '{}'",
+                    key.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One accepted API key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Client identity reported in logs and used for fair-share scheduling
+    pub name: String,
+
+    /// Secret presented by the client
+    pub key: String,
+
+    /// Requests per minute for this key (overrides the default)
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+
+    /// Concurrent streaming requests for this key (overrides the default)
+    #[serde(default)]
+    pub max_concurrent_streams: Option<u32>,
+
+    /// Highest priority this key may request (overrides the default)
+    #[serde(default)]
+    pub max_priority: Option<Priority>,
+
+    /// Tenant whose saved voices and stored outputs this key acts for
+    /// (unset = the shared, untenanted store)
+    #[serde(default)]
+    pub tenant: Option<String>,
+
+    /// May rotate tenant keys, purge tenants and use the operator
+    /// endpoints (model swap/unload/quantize/delete, warmup, stats)
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl ApiKeyConfig {
+    /// Check that the key has a name, a secret and a valid tenant
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::ConfigError(
+                "server.auth: API key name must not be empty".into(),
+            ));
+        }
+        if self.key.trim().is_empty() {
+            return Err(Error::ConfigError(format!(
+                "server.auth: API key for '{}' must not be empty",
+                self.name
+            )));
+        }
+        if let Some(tenant) = &self.tenant {
+            crate::tenant::validate_tenant(tenant)?;
+        }
+        Ok(())
+    }
+}
+
+/// Complete configuration file layout
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IzwiConfig {
+    #[serde(default)]
+    pub engine: EngineConfig,
+
+    #[serde(default)]
+    pub server: ServerConfig,
+}
+
+/// Default prefix for environment overrides
+pub const ENV_PREFIX: &str = "IZWI";
+
+/// Layered configuration loader.
+///
+/// Sources are applied in order, later ones winning: built-in defaults, the
+/// config file (TOML, YAML or JSON by extension), `IZWI_`-prefixed
+/// environment variables, then explicit overrides such as CLI flags.
+/// Environment variables use `__` between sections, e.g.
+/// `IZWI_SERVER__PORT=9000` or `IZWI_ENGINE__MODELS_DIR=/data/models`.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    file: Option<PathBuf>,
+    env_prefix: Option<String>,
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    /// Create a loader reading `IZWI_` environment variables
+    pub fn new() -> Self {
+        Self {
+            file: None,
+            env_prefix: Some(ENV_PREFIX.to_string()),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Read a configuration file; it must exist
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Use a different environment prefix, or `None` to ignore the environment
+    pub fn with_env_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.env_prefix = prefix.map(String::from);
+        self
+    }
+
+    /// Override a single key (dotted path, e.g. `server.port`)
+    pub fn set_override(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.overrides.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Build and validate the layered configuration
+    pub fn load(&self) -> Result<IzwiConfig> {
+        use ::config::{Config, Environment, File};
+
+        let mut builder = Config::builder();
+
+        if let Some(path) = &self.file {
+            if !path.exists() {
+                return Err(Error::ConfigError(format!(
+                    "Config file not found: {}",
+                    path.display()
+                )));
+            }
+            builder = builder.add_source(File::from(path.as_path()));
+        }
+
+        if let Some(prefix) = &self.env_prefix {
+            builder = builder.add_source(
+                Environment::with_prefix(prefix)
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            );
+        }
+
+        for (key, value) in &self.overrides {
+            builder = builder
+                .set_override(key.as_str(), value.as_str())
+                .map_err(|e| Error::ConfigError(format!("Invalid override '{}': {}", key, e)))?;
+        }
+
+        let config: IzwiConfig = builder
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .map_err(|e| Error::ConfigError(e.to_string()))?;
+
+        config.engine.validate()?;
+        config.server.validate()?;
+        Ok(config)
+    }
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn get_num_cpus() -> usize {
     std::thread::available_parallelism()
         .map(|p| p.get())
         .unwrap_or(4)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("izwi-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_layered_loading() {
+        let path = write_temp(
+            "layered.toml",
+            "[engine]\nmax_batch_size = 4\nscheduling_policy = \"priority\"\n\n\
+             [server]\nport = 9000\n\n[server.auth]\nmax_priority = \"normal\"\n",
+        );
+
+        std::env::set_var("IZWI_TEST_LAYERED_SERVER__HOST", "127.0.0.1");
+        let config = ConfigLoader::new()
+            .with_file(&path)
+            .with_env_prefix(Some("IZWI_TEST_LAYERED"))
+            .set_override("server.port", 9100)
+            .load()
+            .unwrap();
+        std::env::remove_var("IZWI_TEST_LAYERED_SERVER__HOST");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(config.engine.max_batch_size, 4);
+        assert_eq!(config.engine.chunk_size, default_chunk_size());
+        assert_eq!(config.engine.scheduling_policy, SchedulingPolicy::Priority);
+        assert_eq!(config.server.bind_address(), "127.0.0.1:9100");
+        assert_eq!(config.server.auth.default_max_priority(), Priority::Normal);
+    }
+
+    #[test]
+    fn test_presets_and_model_defaults() {
+        let path = write_temp(
+            "presets.toml",
+            "[engine.presets.fast]\ntemperature = 0.2\n\n\
+             [engine.presets.calm]\ntemperature = 0.5\ntop_k = 20\n\n\
+             [engine.model_defaults.\"Qwen3-TTS-12Hz-0.6B-Base\"]\ntop_p = 0.95\n",
+        );
+        let config = ConfigLoader::new().with_file(&path).load().unwrap().engine;
+        std::fs::remove_file(&path).ok();
+        config.validate().unwrap();
+
+        // Configured presets replace built-ins of the same name
+        assert_eq!(config.preset("fast").unwrap().temperature, Some(0.2));
+        assert_eq!(config.preset("fast").unwrap().top_k, None);
+        assert_eq!(config.preset("quality").unwrap().temperature, Some(0.9));
+        assert!(config.preset("missing").is_none());
+        let names: Vec<String> = config.all_presets().into_keys().collect();
+        assert_eq!(names, ["balanced", "calm", "fast", "quality"]);
+
+        let mut generation = crate::GenerationConfig::default();
+        config
+            .model_defaults(ModelVariant::Qwen3Tts12Hz06BBase)
+            .apply_to(&mut generation);
+        config.preset("calm").unwrap().apply_to(&mut generation);
+        assert_eq!(
+            (generation.temperature, generation.top_p, generation.top_k),
+            (0.5, 0.95, 20)
+        );
+
+        let mut config = config;
+        config
+            .model_defaults
+            .insert("Qwen9".into(), GenerationPreset::default());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_bridge_paths() {
+        let bridge = BridgeConfig {
+            socket_dir: PathBuf::from("/run/izwi"),
+            python: Some(PathBuf::from("/opt/venv/bin/python")),
+            ..Default::default()
+        };
+        let tts = bridge.socket_path("tts");
+        assert!(tts.starts_with("/run/izwi"));
+        assert_ne!(tts, bridge.socket_path("asr"));
+        assert!(tts
+            .to_string_lossy()
+            .contains(&std::process::id().to_string()));
+        assert_eq!(
+            bridge.python_executable(),
+            PathBuf::from("/opt/venv/bin/python")
+        );
+
+        let named = BridgeConfig {
+            instance_id: Some("a".into()),
+            ..bridge
+        };
+        assert_eq!(
+            named.socket_path("tts"),
+            PathBuf::from("/run/izwi/izwi-a-tts.sock")
+        );
+
+        let tcp = BridgeConfig {
+            transport: TransportKind::Tcp,
+            tcp_base_port: 9300,
+            ..named
+        };
+        assert_eq!(tcp.transport("tts").to_string(), "tcp:127.0.0.1:9300");
+        assert_eq!(tcp.transport("asr").to_string(), "tcp:127.0.0.1:9301");
+    }
+
+    #[test]
+    fn test_yaml_file() {
+        let path = write_temp("config.yaml", "server:\n  port: 7000\n");
+        let config = ConfigLoader::new()
+            .with_file(&path)
+            .with_env_prefix(None)
+            .load()
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(config.server.port, 7000);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let loader = ConfigLoader::new().with_env_prefix(None);
+
+        let err = loader
+            .clone()
+            .set_override("engine.max_batch_size", 0)
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, Error::ConfigError(_)));
+
+        let err = loader
+            .clone()
+            .set_override("server.port", "not-a-port")
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, Error::ConfigError(_)));
+
+        let err = loader
+            .with_file("/nonexistent/izwi.toml")
+            .load()
+            .unwrap_err();
+        assert!(matches!(err, Error::ConfigError(_)));
+    }
+
+    #[test]
+    fn test_privacy_redaction() {
+        let text = "Call me at 555-0100 tomorrow";
+        let mut privacy = PrivacyConfig::default();
+        assert_eq!(privacy.redact(text), text);
+
+        privacy.mode = RedactionMode::Truncate;
+        privacy.truncate_chars = 7;
+        assert_eq!(privacy.redact(text), "Call me... (28 chars)");
+        assert_eq!(privacy.redact("short"), "short");
+
+        privacy.mode = RedactionMode::Hash;
+        let hashed = privacy.redact(text);
+        assert!(hashed.starts_with("sha256:") && !hashed.contains("555"));
+        assert_eq!(hashed, privacy.redact(text));
+    }
+}
@@ -0,0 +1,186 @@
+//! Compute device selection for the native executor, codec decoder and KV
+//! caches.
+//!
+//! Devices are written `auto`, `cpu`, `metal` or `cuda:N` (`cuda` alone
+//! means `cuda:0`). `auto` picks the first CUDA GPU when the crate is built
+//! with the `cuda` feature and a GPU is present, then Metal on macOS, then
+//! the CPU. CUDA is loaded at runtime, so a `cuda` build still starts on
+//! machines without a driver.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// Where models, codec kernels and KV caches live.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Device {
+    /// Best available device
+    #[default]
+    Auto,
+    Cpu,
+    /// Apple Silicon GPU
+    Metal,
+    /// NVIDIA GPU with the given ordinal
+    Cuda(usize),
+}
+
+impl Device {
+    /// The concrete device to run on.
+    ///
+    /// `use_metal` is the legacy switch: with `auto` it keeps Metal off.
+    pub fn resolve(self, use_metal: bool) -> Self {
+        match self {
+            Self::Auto if cuda::device_count() > 0 => Self::Cuda(0),
+            Self::Auto if use_metal && cfg!(target_os = "macos") => Self::Metal,
+            Self::Auto => Self::Cpu,
+            device => device,
+        }
+    }
+
+    /// Check that this build and machine can use the device
+    pub fn validate(self) -> Result<()> {
+        match self {
+            Self::Metal if !cfg!(target_os = "macos") => Err(Error::ConfigError(
+                "device 'metal' is only available on macOS".into(),
+            )),
+            Self::Cuda(_) if !cfg!(feature = "cuda") => Err(Error::ConfigError(
+                "device 'cuda' needs izwi built with the cuda feature".into(),
+            )),
+            Self::Cuda(ordinal) => {
+                let count = cuda::device_count();
+                if ordinal >= count {
+                    return Err(Error::ConfigError(format!(
+                        "device 'cuda:{}' not found ({} CUDA devices present)",
+                        ordinal, count
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Device name as the PyTorch daemons expect it
+    pub fn torch_name(self) -> String {
+        match self {
+            Self::Metal => "mps".to_string(),
+            Self::Cuda(ordinal) => format!("cuda:{}", ordinal),
+            _ => "cpu".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Cpu => f.write_str("cpu"),
+            Self::Metal => f.write_str("metal"),
+            Self::Cuda(ordinal) => write!(f, "cuda:{}", ordinal),
+        }
+    }
+}
+
+impl FromStr for Device {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "cpu" => Ok(Self::Cpu),
+            "metal" | "mps" => Ok(Self::Metal),
+            "cuda" => Ok(Self::Cuda(0)),
+            other => other
+                .strip_prefix("cuda:")
+                .and_then(|ordinal| ordinal.parse().ok())
+                .map(Self::Cuda)
+                .ok_or_else(|| {
+                    Error::ConfigError(format!(
+                        "unknown device '{}' (expected auto, cpu, metal or cuda:N)",
+                        s
+                    ))
+                }),
+        }
+    }
+}
+
+impl Serialize for Device {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Device {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Runtime queries against the CUDA driver
+#[cfg(feature = "cuda")]
+pub(crate) mod cuda {
+    use cudarc::driver::{result, CudaContext};
+
+    /// CUDA devices present, 0 when the driver cannot be loaded
+    pub fn device_count() -> usize {
+        // The driver library is loaded on first use and panics when missing
+        std::panic::catch_unwind(CudaContext::device_count)
+            .ok()
+            .and_then(|count| count.ok())
+            .map_or(0, |count| count.max(0) as usize)
+    }
+
+    /// Free memory on device `ordinal` in bytes
+    pub fn free_memory(ordinal: usize) -> Option<u64> {
+        if ordinal >= device_count() {
+            return None;
+        }
+        // Creating the context binds it to this thread
+        let _context = CudaContext::new(ordinal).ok()?;
+        let (free, _total) = result::mem_get_info().ok()?;
+        Some(free as u64)
+    }
+}
+
+#[cfg(not(feature = "cuda"))]
+pub(crate) mod cuda {
+    pub fn device_count() -> usize {
+        0
+    }
+
+    pub fn free_memory(_ordinal: usize) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_devices() {
+        assert_eq!("auto".parse::<Device>().unwrap(), Device::Auto);
+        assert_eq!("CPU".parse::<Device>().unwrap(), Device::Cpu);
+        assert_eq!("mps".parse::<Device>().unwrap(), Device::Metal);
+        assert_eq!("cuda".parse::<Device>().unwrap(), Device::Cuda(0));
+        assert_eq!("cuda:2".parse::<Device>().unwrap(), Device::Cuda(2));
+        assert!("cuda:x".parse::<Device>().is_err());
+        assert!("tpu".parse::<Device>().is_err());
+
+        assert_eq!(Device::Cuda(1).to_string(), "cuda:1");
+        assert_eq!(Device::Cuda(1).torch_name(), "cuda:1");
+        assert_eq!(Device::Metal.torch_name(), "mps");
+        assert_eq!(Device::Cpu.resolve(true), Device::Cpu);
+        assert_ne!(Device::Auto.resolve(false), Device::Metal);
+
+        let json = serde_json::to_string(&Device::Cuda(3)).unwrap();
+        assert_eq!(json, "\"cuda:3\"");
+        assert_eq!(
+            serde_json::from_str::<Device>(&json).unwrap(),
+            Device::Cuda(3)
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Best-of-N candidate generation.
+//!
+//! A request with `n_candidates > 1` is synthesized several times from the
+//! same prompt. The takes share the prompt's KV blocks (forked copy-on-write
+//! by the engine core), are scored, and the best one becomes the output.
+
+use super::types::{AudioOutput, CandidateScoring, RequestId};
+
+/// Most takes a single request may ask for
+pub const MAX_CANDIDATES: usize = 8;
+
+/// One generated take of a best-of-N request.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// Position in generation order
+    pub index: usize,
+    /// Generated audio
+    pub audio: AudioOutput,
+    /// Mean token log-probability, if the executor reports it
+    pub log_prob: Option<f32>,
+    /// Word error rate of the transcript against the input text
+    pub wer: Option<f32>,
+    /// ASR transcript used for WER scoring
+    pub transcript: Option<String>,
+    /// Cut short at sustained silence (`stop_on_silence_ms`)
+    pub stopped_on_silence: bool,
+}
+
+impl Candidate {
+    pub fn new(index: usize, audio: AudioOutput) -> Self {
+        Self {
+            index,
+            audio,
+            log_prob: None,
+            wer: None,
+            transcript: None,
+            stopped_on_silence: false,
+        }
+    }
+
+    /// Score under `scoring` (higher is better), or `None` if unscored
+    pub fn score(&self, scoring: CandidateScoring) -> Option<f32> {
+        match scoring {
+            CandidateScoring::LogProb => self.log_prob,
+            CandidateScoring::Wer => self.wer.map(|wer| -wer),
+        }
+    }
+}
+
+/// Sort candidates best first.
+///
+/// Unscored takes rank last and ties keep generation order, so without
+/// scores the first take wins.
+pub fn rank(candidates: &mut [Candidate], scoring: CandidateScoring) {
+    let key = |c: &Candidate| c.score(scoring).unwrap_or(f32::NEG_INFINITY);
+    candidates.sort_by(|a, b| key(b).total_cmp(&key(a)));
+}
+
+/// KV sequence holding take `index` of a request (take 0 is the request itself)
+pub(crate) fn candidate_sequence_id(request_id: &RequestId, index: usize) -> RequestId {
+    format!("{}#{}", request_id, index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(index: usize, wer: Option<f32>) -> Candidate {
+        let mut candidate = Candidate::new(index, AudioOutput::empty(24000));
+        candidate.wer = wer;
+        candidate
+    }
+
+    #[test]
+    fn test_rank_candidates() {
+        let mut candidates = vec![take(0, Some(0.4)), take(1, None), take(2, Some(0.1))];
+        rank(&mut candidates, CandidateScoring::Wer);
+        let order: Vec<usize> = candidates.iter().map(|c| c.index).collect();
+        assert_eq!(order, vec![2, 0, 1]);
+
+        // No log-probs reported: generation order is kept
+        rank(&mut candidates, CandidateScoring::LogProb);
+        assert_eq!(candidates[0].index, 2);
+    }
+}
@@ -0,0 +1,91 @@
+//! Time source abstraction for the engine.
+//!
+//! Engine components read the current time through a [`Clock`] instead of
+//! calling `Instant::now()` directly, so time-dependent behavior (waiting
+//! times, latencies, uptime) can be driven deterministically in tests with
+//! [`MockClock`].
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of monotonic time.
+pub trait Clock: Send + Sync + Debug {
+    /// Current instant.
+    fn now(&self) -> Instant;
+
+    /// Time elapsed since `earlier` (zero if `earlier` is in the future).
+    fn elapsed_since(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+/// Clock shared between engine components.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Real monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Shared handle to the system clock.
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Manually advanced clock for tests.
+#[derive(Debug)]
+pub struct MockClock {
+    current: Mutex<Instant>,
+}
+
+impl MockClock {
+    /// Create a clock frozen at the current instant.
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        *current += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        assert_eq!(clock.elapsed_since(start), Duration::ZERO);
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(clock.elapsed_since(start), Duration::from_millis(250));
+
+        // Instants after the clock's current time saturate to zero
+        let future = clock.now() + Duration::from_secs(1);
+        assert_eq!(clock.elapsed_since(future), Duration::ZERO);
+    }
+}
@@ -1,10 +1,21 @@
 //! Engine configuration types.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use super::kv_cache::KVCacheDtype;
+use super::output::StreamBackpressure;
 use super::scheduler::SchedulingPolicy;
+use super::session::ContextPolicy;
 use super::types::ModelType;
+use crate::audio::OverflowPolicy;
+use crate::config::{
+    default_max_queued_tokens, default_max_waiting_requests, InputLimits, OutputCacheConfig,
+    WarmupConfig,
+};
+use crate::device::Device;
+use crate::model::ModelVariant;
 
 /// Configuration for the engine core.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,14 +44,33 @@ pub struct EngineCoreConfig {
     #[serde(default = "default_block_size")]
     pub block_size: usize,
 
-    /// Maximum number of KV cache blocks
+    /// Maximum number of KV cache blocks per model (0 = size from free memory)
     #[serde(default = "default_max_blocks")]
     pub max_blocks: usize,
 
+    /// Element type of stored keys and values; int8 fits twice as many
+    /// blocks as float16 in the same memory
+    #[serde(default)]
+    pub kv_cache_dtype: KVCacheDtype,
+
+    /// Fraction of free memory a model's KV cache may use when `max_blocks`
+    /// is 0, shared with the caches of models already resident
+    #[serde(default = "default_gpu_memory_utilization")]
+    pub gpu_memory_utilization: f32,
+
     /// Scheduling policy
     #[serde(default)]
     pub scheduling_policy: SchedulingPolicy,
 
+    /// Fair-share weight per client ID (unlisted clients weigh 1.0)
+    #[serde(default)]
+    pub client_weights: HashMap<String, f64>,
+
+    /// Half-life of the per-client token usage tracked by fair-share
+    /// scheduling (milliseconds)
+    #[serde(default = "default_fair_share_half_life_ms")]
+    pub fair_share_half_life_ms: u64,
+
     /// Enable chunked prefill for long prompts
     #[serde(default = "default_chunked_prefill")]
     pub enable_chunked_prefill: bool,
@@ -61,10 +91,35 @@ pub struct EngineCoreConfig {
     #[serde(default = "default_streaming_chunk_size")]
     pub streaming_chunk_size: usize,
 
-    /// Enable Metal/MPS acceleration (macOS)
+    /// Cap on buffered output audio per request, in bytes (0 = unlimited)
+    #[serde(default = "default_max_output_buffer_bytes_per_request")]
+    pub max_output_buffer_bytes_per_request: usize,
+
+    /// What to do when an output buffer cap is reached
+    #[serde(default)]
+    pub output_overflow_policy: OverflowPolicy,
+
+    /// Chunks a streaming consumer may have queued before backpressure applies
+    #[serde(default = "default_stream_channel_capacity")]
+    pub stream_channel_capacity: usize,
+
+    /// What to do when a streaming consumer falls behind
+    #[serde(default)]
+    pub stream_backpressure: StreamBackpressure,
+
+    /// Enable Metal/MPS acceleration (macOS) when `device` is `auto`
     #[serde(default = "default_use_metal")]
     pub use_metal: bool,
 
+    /// Device for the executor and KV caches: auto, cpu, metal or cuda:N
+    #[serde(default)]
+    pub device: Device,
+
+    /// GPUs to split each model's layers over; overrides `device` when two
+    /// or more are listed
+    #[serde(default)]
+    pub devices: Vec<Device>,
+
     /// Number of CPU threads
     #[serde(default = "default_num_threads")]
     pub num_threads: usize,
@@ -73,9 +128,75 @@ pub struct EngineCoreConfig {
     #[serde(default = "default_enable_preemption")]
     pub enable_preemption: bool,
 
+    /// How long a model swap waits for running requests to finish before
+    /// restarting them on the new model (milliseconds)
+    #[serde(default = "default_swap_drain_timeout_ms")]
+    pub swap_drain_timeout_ms: u64,
+
+    /// Draft model proposing tokens for speculative decoding
+    #[serde(default)]
+    pub speculative_model: Option<ModelVariant>,
+
+    /// Tokens the draft model proposes per decode step (0 = disabled)
+    #[serde(default)]
+    pub num_speculative_tokens: usize,
+
+    /// Cap on requests waiting to be scheduled, across all models (0 = unlimited)
+    #[serde(default = "default_max_waiting_requests")]
+    pub max_waiting_requests: usize,
+
+    /// Cap on prompt tokens of waiting requests, across all models (0 = unlimited)
+    #[serde(default = "default_max_queued_tokens")]
+    pub max_queued_tokens: usize,
+
+    /// How long results of asynchronous jobs are kept after finishing (seconds)
+    #[serde(default = "default_result_ttl_secs")]
+    pub result_ttl_secs: u64,
+
+    /// How long an idle chat session is kept (seconds)
+    #[serde(default = "default_chat_session_ttl_secs")]
+    pub chat_session_ttl_secs: u64,
+
+    /// Most open chat sessions; the least recently active is closed to make
+    /// room (0 = unlimited)
+    #[serde(default = "default_max_chat_sessions")]
+    pub max_chat_sessions: usize,
+
+    /// Prompt tokens a chat turn may use before older turns are dropped or
+    /// summarized (0 = three quarters of `max_seq_len`, leaving the rest for
+    /// the reply)
+    #[serde(default)]
+    pub chat_context_tokens: usize,
+
+    /// How chat history past `chat_context_tokens` is shortened
+    #[serde(default)]
+    pub chat_context_policy: ContextPolicy,
+
+    /// Sliding-window attention: tokens of KV cache a sequence keeps
+    /// resident, evicting older blocks as it grows (0 = keep everything).
+    /// Lets long streaming sessions run without exhausting `max_blocks`.
+    #[serde(default)]
+    pub kv_window_tokens: usize,
+
+    /// Leading tokens kept outside the window as attention sinks
+    #[serde(default)]
+    pub kv_sink_tokens: usize,
+
     /// Python daemon socket paths
     #[serde(default)]
     pub daemon_config: DaemonConfig,
+
+    /// Cache of synthesized audio for repeated requests
+    #[serde(default)]
+    pub output_cache: OutputCacheConfig,
+
+    /// Dummy requests run after model loads
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+
+    /// Size limits checked by the request processor
+    #[serde(default)]
+    pub limits: InputLimits,
 }
 
 fn default_models_dir() -> PathBuf {
@@ -98,7 +219,10 @@ fn default_block_size() -> usize {
     16
 }
 fn default_max_blocks() -> usize {
-    1024
+    0
+}
+fn default_gpu_memory_utilization() -> f32 {
+    0.9
 }
 fn default_chunked_prefill() -> bool {
     true
@@ -115,6 +239,12 @@ fn default_num_codebooks() -> usize {
 fn default_streaming_chunk_size() -> usize {
     4800
 } // 200ms at 24kHz
+fn default_max_output_buffer_bytes_per_request() -> usize {
+    32 * 1024 * 1024
+}
+fn default_stream_channel_capacity() -> usize {
+    32
+}
 fn default_use_metal() -> bool {
     cfg!(target_os = "macos")
 }
@@ -127,6 +257,21 @@ fn default_num_threads() -> usize {
 fn default_enable_preemption() -> bool {
     true
 }
+fn default_swap_drain_timeout_ms() -> u64 {
+    30_000
+}
+fn default_fair_share_half_life_ms() -> u64 {
+    10_000
+}
+fn default_result_ttl_secs() -> u64 {
+    3600
+}
+fn default_chat_session_ttl_secs() -> u64 {
+    1800
+}
+fn default_max_chat_sessions() -> usize {
+    256
+}
 
 impl Default for EngineCoreConfig {
     fn default() -> Self {
@@ -138,16 +283,41 @@ impl Default for EngineCoreConfig {
             max_tokens_per_step: default_max_tokens_per_step(),
             block_size: default_block_size(),
             max_blocks: default_max_blocks(),
+            kv_cache_dtype: KVCacheDtype::default(),
+            gpu_memory_utilization: default_gpu_memory_utilization(),
             scheduling_policy: SchedulingPolicy::default(),
+            client_weights: HashMap::new(),
+            fair_share_half_life_ms: default_fair_share_half_life_ms(),
             enable_chunked_prefill: default_chunked_prefill(),
             chunked_prefill_threshold: default_chunked_prefill_threshold(),
             sample_rate: default_sample_rate(),
             num_codebooks: default_num_codebooks(),
             streaming_chunk_size: default_streaming_chunk_size(),
+            max_output_buffer_bytes_per_request: default_max_output_buffer_bytes_per_request(),
+            output_overflow_policy: OverflowPolicy::default(),
+            stream_channel_capacity: default_stream_channel_capacity(),
+            stream_backpressure: StreamBackpressure::default(),
             use_metal: default_use_metal(),
+            device: Device::default(),
+            devices: Vec::new(),
             num_threads: default_num_threads(),
             enable_preemption: default_enable_preemption(),
+            swap_drain_timeout_ms: default_swap_drain_timeout_ms(),
+            speculative_model: None,
+            num_speculative_tokens: 0,
+            max_waiting_requests: default_max_waiting_requests(),
+            max_queued_tokens: default_max_queued_tokens(),
+            result_ttl_secs: default_result_ttl_secs(),
+            chat_session_ttl_secs: default_chat_session_ttl_secs(),
+            max_chat_sessions: default_max_chat_sessions(),
+            chat_context_tokens: 0,
+            chat_context_policy: ContextPolicy::default(),
+            kv_window_tokens: 0,
+            kv_sink_tokens: 0,
             daemon_config: DaemonConfig::default(),
+            output_cache: OutputCacheConfig::default(),
+            warmup: WarmupConfig::default(),
+            limits: InputLimits::default(),
         }
     }
 }
@@ -163,15 +333,42 @@ impl EngineCoreConfig {
         }
     }
 
+    /// The device to run on, with `auto` resolved
+    pub fn compute_device(&self) -> Device {
+        self.device.resolve(self.use_metal)
+    }
+
+    /// Prompt tokens available to a chat turn
+    pub fn chat_context_window(&self) -> usize {
+        if self.chat_context_tokens > 0 {
+            self.chat_context_tokens
+        } else {
+            self.max_seq_len * 3 / 4
+        }
+    }
+
+    /// Draft tokens verified per decode step (0 without a draft model)
+    pub fn num_lookahead_tokens(&self) -> usize {
+        if self.speculative_model.is_some() {
+            self.num_speculative_tokens
+        } else {
+            0
+        }
+    }
+
     /// Calculate memory required for KV cache
     pub fn kv_cache_memory_bytes(&self) -> usize {
         // Approximate: 2 (K+V) * block_size * hidden_dim * num_layers * dtype_size
         // Using typical values for audio models
         let hidden_dim = 1024;
         let num_layers = 24;
-        let dtype_bytes = 2; // float16
 
-        self.max_blocks * self.block_size * hidden_dim * num_layers * 2 * dtype_bytes
+        self.max_blocks
+            * self.block_size
+            * hidden_dim
+            * num_layers
+            * 2
+            * self.kv_cache_dtype.bytes()
     }
 }
 
@@ -196,7 +393,7 @@ pub struct DaemonConfig {
 }
 
 fn default_tts_socket() -> PathBuf {
-    PathBuf::from("/tmp/izwi_tts_daemon.sock")
+    crate::config::BridgeConfig::default().socket_path("tts")
 }
 fn default_daemon_timeout() -> u64 {
     300
@@ -0,0 +1,933 @@
+//! Constrained text decoding.
+//!
+//! A [`Constraint`] limits the text a request may produce to a regular
+//! expression, a GBNF grammar or a JSON schema. It is compiled once per
+//! request and applied token by token in the sampler: [`TokenConstraint::mask`]
+//! zeroes the probability of every token that cannot continue a valid output,
+//! and [`TokenConstraint::advance`] moves past the token that was picked.
+//!
+//! Regular expressions and JSON schemas compile to a DFA over bytes. JSON is
+//! generated compactly (at most one space between tokens), with properties in
+//! schema order. Grammars are matched with a stack-based recognizer over
+//! characters, as in llama.cpp.
+
+use regex_automata::dfa::{dense, Automaton, StartKind};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::types::TokenId;
+use crate::error::{Error, Result};
+
+/// Largest DFA built for a pattern or schema
+const MAX_DFA_BYTES: usize = 32 * 1024 * 1024;
+/// Deepest nesting of schemas and grammar rule expansions followed
+const MAX_DEPTH: usize = 64;
+
+const WS: &str = "[ ]?";
+const STRING: &str = r#""(?:[^"\\\x00-\x1f]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*""#;
+const INTEGER: &str = "-?(?:0|[1-9][0-9]*)";
+const NUMBER: &str = r"-?(?:0|[1-9][0-9]*)(?:\.[0-9]+)?(?:[eE][+-]?[0-9]+)?";
+const BOOLEAN: &str = "(?:true|false)";
+const NULL: &str = "null";
+
+/// Limit on the text a request may generate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Constraint {
+    /// The whole output matches a regular expression
+    Regex { pattern: String },
+    /// The output derives from the `root` rule of a GBNF grammar
+    Grammar { gbnf: String },
+    /// The output is JSON valid against a schema
+    JsonSchema { schema: Value },
+}
+
+impl Constraint {
+    /// Compile the constraint, rejecting invalid patterns, grammars and schemas.
+    pub fn compile(&self) -> Result<CompiledConstraint> {
+        match self {
+            Self::Regex { pattern } => CompiledConstraint::regex(pattern),
+            Self::Grammar { gbnf } => Grammar::parse(gbnf).map(CompiledConstraint::Grammar),
+            Self::JsonSchema { schema } => CompiledConstraint::regex(&schema_to_regex(schema, 0)?),
+        }
+    }
+}
+
+/// A constraint ready to match text.
+#[derive(Debug)]
+pub enum CompiledConstraint {
+    Dfa {
+        dfa: Box<dense::DFA<Vec<u32>>>,
+        start: StateID,
+        /// States from which a complete match can still be reached (the DFA
+        /// reports matches one byte late, so past a match it is not yet dead)
+        live: HashSet<StateID>,
+    },
+    Grammar(Grammar),
+}
+
+/// How far into a constrained output a sequence is.
+#[derive(Debug, Clone)]
+enum MatchState {
+    Dfa(StateID),
+    Grammar {
+        stacks: Vec<Stack>,
+        /// Bytes of a character split across tokens
+        partial: Vec<u8>,
+    },
+}
+
+impl CompiledConstraint {
+    fn regex(pattern: &str) -> Result<Self> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            Error::InvalidInput(format!("Invalid output constraint: {}", e))
+        };
+        let dfa = dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .start_kind(StartKind::Anchored)
+                    .match_kind(MatchKind::All)
+                    .dfa_size_limit(Some(MAX_DFA_BYTES))
+                    .determinize_size_limit(Some(MAX_DFA_BYTES)),
+            )
+            .build(pattern)
+            .map_err(|e| invalid(&e))?;
+        let start = dfa
+            .start_state(&start::Config::new().anchored(Anchored::Yes))
+            .map_err(|e| invalid(&e))?;
+        let live = live_states(&dfa, start);
+        Ok(Self::Dfa {
+            dfa: Box::new(dfa),
+            start,
+            live,
+        })
+    }
+
+    /// Whether `text` is a complete valid output.
+    pub fn matches(&self, text: &str) -> bool {
+        self.feed(&self.start(), text.as_bytes())
+            .is_some_and(|state| self.is_complete(&state))
+    }
+
+    fn start(&self) -> MatchState {
+        match self {
+            Self::Dfa { start, .. } => MatchState::Dfa(*start),
+            Self::Grammar(grammar) => MatchState::Grammar {
+                stacks: grammar.start(),
+                partial: Vec::new(),
+            },
+        }
+    }
+
+    /// State after `bytes`, or `None` when they cannot continue the output.
+    fn feed(&self, state: &MatchState, bytes: &[u8]) -> Option<MatchState> {
+        match (self, state) {
+            (Self::Dfa { dfa, live, .. }, MatchState::Dfa(id)) => {
+                let mut id = *id;
+                for &byte in bytes {
+                    id = dfa.next_state(id, byte);
+                    if !live.contains(&id) {
+                        return None;
+                    }
+                }
+                Some(MatchState::Dfa(id))
+            }
+            (Self::Grammar(grammar), MatchState::Grammar { stacks, partial }) => {
+                let mut pending = partial.clone();
+                pending.extend_from_slice(bytes);
+                let (text, rest) = match std::str::from_utf8(&pending) {
+                    Ok(text) => (text, &[][..]),
+                    Err(e) if e.error_len().is_none() => {
+                        let (valid, rest) = pending.split_at(e.valid_up_to());
+                        (std::str::from_utf8(valid).ok()?, rest)
+                    }
+                    Err(_) => return None,
+                };
+                let mut stacks = stacks.clone();
+                for c in text.chars() {
+                    stacks = grammar.accept(&stacks, c);
+                    if stacks.is_empty() {
+                        return None;
+                    }
+                }
+                Some(MatchState::Grammar {
+                    stacks,
+                    partial: rest.to_vec(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn is_complete(&self, state: &MatchState) -> bool {
+        match (self, state) {
+            (Self::Dfa { dfa, .. }, MatchState::Dfa(id)) => {
+                dfa.is_match_state(dfa.next_eoi_state(*id))
+            }
+            (Self::Grammar(_), MatchState::Grammar { stacks, partial }) => {
+                partial.is_empty() && stacks.iter().any(Vec::is_empty)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// States reachable from `start` that can still lead to a complete match.
+fn live_states(dfa: &dense::DFA<Vec<u32>>, start: StateID) -> HashSet<StateID> {
+    let mut states = vec![start];
+    let mut index = HashMap::from([(start, 0)]);
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut i = 0;
+    while i < states.len() {
+        for byte in 0..=u8::MAX {
+            let next = dfa.next_state(states[i], byte);
+            if dfa.is_dead_state(next) || dfa.is_quit_state(next) {
+                continue;
+            }
+            let j = *index.entry(next).or_insert_with(|| {
+                states.push(next);
+                predecessors.push(Vec::new());
+                states.len() - 1
+            });
+            predecessors[j].push(i);
+        }
+        i += 1;
+    }
+
+    let mut live = vec![false; states.len()];
+    let mut queue: Vec<usize> = (0..states.len())
+        .filter(|&i| dfa.is_match_state(dfa.next_eoi_state(states[i])))
+        .collect();
+    while let Some(i) = queue.pop() {
+        if !std::mem::replace(&mut live[i], true) {
+            queue.extend(predecessors[i].iter().copied().filter(|&p| !live[p]));
+        }
+    }
+    states
+        .into_iter()
+        .zip(live)
+        .filter_map(|(id, live)| live.then_some(id))
+        .collect()
+}
+
+/// Constraint state of one sequence, masking its next-token probabilities.
+#[derive(Debug, Clone)]
+pub struct TokenConstraint {
+    compiled: Arc<CompiledConstraint>,
+    /// Text of every token, by ID
+    vocab: Arc<Vec<Vec<u8>>>,
+    /// Token ending the output, allowed once it is complete
+    eos_token: Option<TokenId>,
+    state: MatchState,
+}
+
+impl TokenConstraint {
+    /// Start matching `compiled` with tokens decoded through `vocab`.
+    pub fn new(compiled: Arc<CompiledConstraint>, vocab: Arc<Vec<Vec<u8>>>) -> Self {
+        let state = compiled.start();
+        Self {
+            compiled,
+            vocab,
+            eos_token: None,
+            state,
+        }
+    }
+
+    /// Allow `token` to end the output once it is complete.
+    pub fn with_eos_token(mut self, token: TokenId) -> Self {
+        self.eos_token = Some(token);
+        self
+    }
+
+    /// Whether the output so far is a complete valid output.
+    pub fn is_complete(&self) -> bool {
+        self.compiled.is_complete(&self.state)
+    }
+
+    /// Whether `token` may come next.
+    pub fn allows(&self, token: TokenId) -> bool {
+        if Some(token) == self.eos_token {
+            return self.is_complete();
+        }
+        match self.vocab.get(token as usize) {
+            Some(bytes) if !bytes.is_empty() => self.compiled.feed(&self.state, bytes).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Zero the probability of disallowed tokens and renormalize the rest.
+    pub fn mask(&self, probs: &mut [f32]) -> Result<()> {
+        for (token, p) in probs.iter_mut().enumerate() {
+            if *p > 0.0 && !self.allows(token as TokenId) {
+                *p = 0.0;
+            }
+        }
+        let total: f32 = probs.iter().sum();
+        if total <= 0.0 {
+            return Err(Error::InferenceError(
+                "No token can continue the constrained output".into(),
+            ));
+        }
+        probs.iter_mut().for_each(|p| *p /= total);
+        Ok(())
+    }
+
+    /// Move past `token`.
+    pub fn advance(&mut self, token: TokenId) -> Result<()> {
+        if Some(token) == self.eos_token && self.is_complete() {
+            return Ok(());
+        }
+        self.state = self
+            .vocab
+            .get(token as usize)
+            .and_then(|bytes| self.compiled.feed(&self.state, bytes))
+            .ok_or_else(|| {
+                Error::InferenceError(format!("Token {} violates the output constraint", token))
+            })?;
+        Ok(())
+    }
+}
+
+/// Grammar symbol: a character class or a rule reference.
+#[derive(Debug, Clone, PartialEq)]
+enum Element {
+    /// One character inside (or, negated, outside) the ranges
+    Char {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Rule(usize),
+}
+
+impl Element {
+    fn literal(c: char) -> Self {
+        Self::Char {
+            ranges: vec![(c, c)],
+            negated: false,
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Char { ranges, negated } => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+            Self::Rule(_) => false,
+        }
+    }
+}
+
+/// Position in a grammar: (rule, alternative, element)
+type Position = (usize, usize, usize);
+/// Positions still to complete, innermost last
+type Stack = Vec<Position>;
+
+/// A context-free grammar in GBNF.
+#[derive(Debug)]
+pub struct Grammar {
+    /// Alternatives of each rule, each a sequence of elements
+    rules: Vec<Vec<Vec<Element>>>,
+    root: usize,
+}
+
+impl Grammar {
+    /// Parse GBNF (`name ::= alternatives`, with literals, character classes,
+    /// groups, `*`, `+`, `?` and `#` comments). Matching starts at `root`.
+    pub fn parse(source: &str) -> Result<Self> {
+        GrammarParser::new(source).parse()
+    }
+
+    fn start(&self) -> Vec<Stack> {
+        let mut stacks = Vec::new();
+        for alt in 0..self.rules[self.root].len() {
+            self.expand(vec![(self.root, alt, 0)], &mut stacks, 0);
+        }
+        dedup(stacks)
+    }
+
+    /// Advance every stack whose next character class matches `c`.
+    fn accept(&self, stacks: &[Stack], c: char) -> Vec<Stack> {
+        let mut next = Vec::new();
+        for stack in stacks {
+            let Some(&(rule, alt, index)) = stack.last() else {
+                continue;
+            };
+            if self.rules[rule][alt][index].matches(c) {
+                let mut stack = stack.clone();
+                stack.pop();
+                stack.push((rule, alt, index + 1));
+                self.expand(stack, &mut next, 0);
+            }
+        }
+        dedup(next)
+    }
+
+    /// Resolve rule references and finished sequences until the stack waits
+    /// on a character (or is empty: the input so far is complete).
+    fn expand(&self, mut stack: Stack, out: &mut Vec<Stack>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let Some(&(rule, alt, index)) = stack.last() else {
+            out.push(stack);
+            return;
+        };
+        let sequence = &self.rules[rule][alt];
+        match sequence.get(index) {
+            None => {
+                stack.pop();
+                self.expand(stack, out, depth + 1);
+            }
+            Some(Element::Char { .. }) => out.push(stack),
+            Some(&Element::Rule(next)) => {
+                stack.pop();
+                if index + 1 < sequence.len() {
+                    stack.push((rule, alt, index + 1));
+                }
+                for next_alt in 0..self.rules[next].len() {
+                    let mut stack = stack.clone();
+                    stack.push((next, next_alt, 0));
+                    self.expand(stack, out, depth + 1);
+                }
+            }
+        }
+    }
+}
+
+fn dedup(mut stacks: Vec<Stack>) -> Vec<Stack> {
+    stacks.sort_unstable();
+    stacks.dedup();
+    stacks
+}
+
+struct GrammarParser {
+    chars: Vec<char>,
+    pos: usize,
+    names: HashMap<String, usize>,
+    rules: Vec<Option<Vec<Vec<Element>>>>,
+}
+
+impl GrammarParser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            names: HashMap::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> Error {
+        Error::InvalidInput(format!("Invalid grammar at {}: {}", self.pos, message))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse(mut self) -> Result<Grammar> {
+        loop {
+            self.skip_space();
+            if self.peek().is_none() {
+                break;
+            }
+            let name = self.name()?;
+            self.skip_space();
+            if !self.eat("::=") {
+                return Err(self.error(format!("expected ::= after {}", name)));
+            }
+            let id = self.rule_id(&name);
+            if self.rules[id].is_some() {
+                return Err(self.error(format!("rule {} is defined twice", name)));
+            }
+            let alternatives = self.alternatives(false)?;
+            self.rules[id] = Some(alternatives);
+        }
+
+        let root = *self
+            .names
+            .get("root")
+            .ok_or_else(|| self.error("no root rule"))?;
+        let mut rules = Vec::with_capacity(self.rules.len());
+        for (id, rule) in self.rules.into_iter().enumerate() {
+            let name = self.names.iter().find(|(_, &i)| i == id).map(|(n, _)| n);
+            rules.push(rule.ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Invalid grammar: rule {} is not defined",
+                    name.map_or("?", String::as_str)
+                ))
+            })?);
+        }
+        Ok(Grammar { rules, root })
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let matches = token
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += token.chars().count();
+        }
+        matches
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("expected a rule name"));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn rule_id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.names.get(name) {
+            return id;
+        }
+        self.rules.push(None);
+        self.names.insert(name.to_string(), self.rules.len() - 1);
+        self.rules.len() - 1
+    }
+
+    fn new_rule(&mut self, alternatives: Vec<Vec<Element>>) -> usize {
+        self.rules.push(Some(alternatives));
+        self.rules.len() - 1
+    }
+
+    /// Whether the next thing is the start of another rule (`name ::=`).
+    fn at_rule_start(&self) -> bool {
+        let mut pos = self.pos;
+        while self
+            .chars
+            .get(pos)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        {
+            pos += 1;
+        }
+        if pos == self.pos {
+            return false;
+        }
+        while self.chars.get(pos).is_some_and(|c| c.is_whitespace()) {
+            pos += 1;
+        }
+        self.chars[pos.min(self.chars.len())..].starts_with(&[':', ':', '='])
+    }
+
+    fn alternatives(&mut self, nested: bool) -> Result<Vec<Vec<Element>>> {
+        let mut alternatives = vec![Vec::new()];
+        loop {
+            self.skip_space();
+            let Some(c) = self.peek() else {
+                break;
+            };
+            if !nested && self.at_rule_start() {
+                break;
+            }
+            let sequence = alternatives.last_mut().expect("one alternative");
+            let start = sequence.len();
+            match c {
+                '|' => {
+                    self.pos += 1;
+                    alternatives.push(Vec::new());
+                    continue;
+                }
+                ')' if nested => break,
+                '"' => {
+                    self.pos += 1;
+                    let mut literal = Vec::new();
+                    loop {
+                        match self.peek() {
+                            None => return Err(self.error("unterminated string")),
+                            Some('"') => break,
+                            Some(_) => literal.push(Element::literal(self.char_escaped()?)),
+                        }
+                    }
+                    self.pos += 1;
+                    alternatives
+                        .last_mut()
+                        .expect("one alternative")
+                        .extend(literal);
+                }
+                '[' => {
+                    let class = self.class()?;
+                    alternatives
+                        .last_mut()
+                        .expect("one alternative")
+                        .push(class);
+                }
+                '.' => {
+                    self.pos += 1;
+                    sequence.push(Element::Char {
+                        ranges: Vec::new(),
+                        negated: true,
+                    });
+                }
+                '(' => {
+                    self.pos += 1;
+                    let group = self.alternatives(true)?;
+                    if !self.eat(")") {
+                        return Err(self.error("expected )"));
+                    }
+                    let id = self.new_rule(group);
+                    alternatives
+                        .last_mut()
+                        .expect("one alternative")
+                        .push(Element::Rule(id));
+                }
+                c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => {
+                    let name = self.name()?;
+                    let id = self.rule_id(&name);
+                    alternatives
+                        .last_mut()
+                        .expect("one alternative")
+                        .push(Element::Rule(id));
+                }
+                other => return Err(self.error(format!("unexpected {:?}", other))),
+            }
+
+            // A quantifier applies to the item just parsed
+            if let Some(quantifier @ ('*' | '+' | '?')) = self.peek() {
+                self.pos += 1;
+                let sequence = alternatives.last_mut().expect("one alternative");
+                let item: Vec<Element> = sequence.drain(start..).collect();
+                let id = self.rules.len();
+                let repeat = |mut item: Vec<Element>| {
+                    item.push(Element::Rule(id));
+                    item
+                };
+                let rule = match quantifier {
+                    '*' => vec![repeat(item), Vec::new()],
+                    '+' => vec![repeat(item.clone()), item],
+                    _ => vec![item, Vec::new()],
+                };
+                let id = self.new_rule(rule);
+                alternatives
+                    .last_mut()
+                    .expect("one alternative")
+                    .push(Element::Rule(id));
+            }
+        }
+        Ok(alternatives)
+    }
+
+    fn class(&mut self) -> Result<Element> {
+        self.pos += 1;
+        let negated = self.eat("^");
+        let mut ranges = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated character class")),
+                Some(']') => break,
+                Some(_) => {
+                    let lo = self.char_escaped()?;
+                    let hi =
+                        if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                            self.pos += 1;
+                            self.char_escaped()?
+                        } else {
+                            lo
+                        };
+                    ranges.push((lo, hi));
+                }
+            }
+        }
+        self.pos += 1;
+        Ok(Element::Char { ranges, negated })
+    }
+
+    fn char_escaped(&mut self) -> Result<char> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c);
+        }
+        let escape = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        let hex_digits = match escape {
+            'n' => return Ok('\n'),
+            't' => return Ok('\t'),
+            'r' => return Ok('\r'),
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            other => return Ok(other),
+        };
+        let end = self.pos + hex_digits;
+        let digits: String = self
+            .chars
+            .get(self.pos..end)
+            .unwrap_or_default()
+            .iter()
+            .collect();
+        self.pos = end;
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("invalid escape \\{}{}", escape, digits)))
+    }
+}
+
+/// Regular expression for JSON valid against `schema` (`type`, `properties`,
+/// `required`, `items`, `enum`, `const`, `anyOf` and `oneOf`).
+fn schema_to_regex(schema: &Value, depth: usize) -> Result<String> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidInput(
+            "JSON schema is nested too deeply".into(),
+        ));
+    }
+    if let Some(value) = schema.get("const") {
+        return Ok(escape(&value.to_string()));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Ok(alternation(values.iter().map(|v| escape(&v.to_string()))));
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(key).and_then(Value::as_array) {
+            let options = options
+                .iter()
+                .map(|option| schema_to_regex(option, depth + 1))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(alternation(options));
+        }
+    }
+    if schema.get("$ref").is_some() {
+        return Err(Error::InvalidInput(
+            "JSON schema references ($ref) are not supported".into(),
+        ));
+    }
+
+    match schema.get("type") {
+        None => Ok(scalar()),
+        Some(Value::String(kind)) => type_to_regex(kind, schema, depth),
+        Some(Value::Array(kinds)) => {
+            let options = kinds
+                .iter()
+                .map(|kind| match kind.as_str() {
+                    Some(kind) => type_to_regex(kind, schema, depth),
+                    None => Err(Error::InvalidInput(format!("Invalid JSON type {}", kind))),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(alternation(options))
+        }
+        Some(other) => Err(Error::InvalidInput(format!("Invalid JSON type {}", other))),
+    }
+}
+
+fn type_to_regex(kind: &str, schema: &Value, depth: usize) -> Result<String> {
+    Ok(match kind {
+        "string" => STRING.to_string(),
+        "integer" => INTEGER.to_string(),
+        "number" => NUMBER.to_string(),
+        "boolean" => BOOLEAN.to_string(),
+        "null" => NULL.to_string(),
+        "array" => {
+            let item = match schema.get("items") {
+                Some(items) => schema_to_regex(items, depth + 1)?,
+                None => scalar(),
+            };
+            format!(r"\[{WS}(?:{item}(?:{WS},{WS}{item})*)?{WS}\]")
+        }
+        "object" => match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) if !properties.is_empty() => {
+                let required: Vec<&str> = schema
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                let members = properties
+                    .iter()
+                    .map(|(name, property)| {
+                        let value = schema_to_regex(property, depth + 1)?;
+                        let member = format!(
+                            "{}{WS}:{WS}{}",
+                            escape(&Value::from(name.as_str()).to_string()),
+                            value
+                        );
+                        Ok((member, required.contains(&name.as_str())))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                format!(r"\{{{WS}{}{WS}\}}", members_regex(&members))
+            }
+            // Free-form objects hold scalar values
+            _ => {
+                let member = format!("{STRING}{WS}:{WS}{}", scalar());
+                format!(r"\{{{WS}(?:{member}(?:{WS},{WS}{member})*)?{WS}\}}")
+            }
+        },
+        other => return Err(Error::InvalidInput(format!("Unknown JSON type {}", other))),
+    })
+}
+
+/// Object members in order, optional ones skippable, separated by commas.
+fn members_regex(members: &[(String, bool)]) -> String {
+    // `after` continues once a member was written, `first` before any was
+    let (mut after, mut first) = (String::new(), String::new());
+    for (member, required) in members.iter().rev() {
+        let next_first = if *required {
+            format!("{member}{after}")
+        } else {
+            format!("(?:{member}{after}|{first})")
+        };
+        after = if *required {
+            format!("{WS},{WS}{member}{after}")
+        } else {
+            format!("(?:{WS},{WS}{member})?{after}")
+        };
+        first = next_first;
+    }
+    first
+}
+
+fn scalar() -> String {
+    alternation([STRING, NUMBER, BOOLEAN, NULL].map(String::from))
+}
+
+fn alternation(options: impl IntoIterator<Item = String>) -> String {
+    format!("(?:{})", options.into_iter().collect::<Vec<_>>().join("|"))
+}
+
+fn escape(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn compile(constraint: Constraint) -> Arc<CompiledConstraint> {
+        Arc::new(constraint.compile().unwrap())
+    }
+
+    #[test]
+    fn test_regex_masks_tokens() {
+        let compiled = compile(Constraint::Regex {
+            pattern: "(yes|no)!".into(),
+        });
+        let vocab: Vec<Vec<u8>> = ["y", "es", "no", "!", "maybe", ""]
+            .iter()
+            .map(|t| t.as_bytes().to_vec())
+            .collect();
+        let mut constraint = TokenConstraint::new(compiled, Arc::new(vocab)).with_eos_token(5);
+
+        let mut probs = vec![0.2; 6];
+        constraint.mask(&mut probs).unwrap();
+        assert_eq!(probs, [0.5, 0.0, 0.5, 0.0, 0.0, 0.0]);
+
+        for token in [0, 1, 3] {
+            constraint.advance(token).unwrap();
+        }
+        assert!(constraint.is_complete());
+        let mut probs = vec![0.2; 6];
+        constraint.mask(&mut probs).unwrap();
+        assert_eq!(probs, [0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert!(constraint.advance(4).is_err());
+    }
+
+    #[test]
+    fn test_json_schema_constraint() {
+        let compiled = Constraint::JsonSchema {
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "city": {"type": "string"},
+                    "days": {"type": "integer"},
+                    "unit": {"enum": ["c", "f"]}
+                },
+                "required": ["city"]
+            }),
+        }
+        .compile()
+        .unwrap();
+        for valid in [
+            r#"{"city": "Oslo"}"#,
+            r#"{"city":"Oslo","days":3}"#,
+            r#"{"city": "Oslo", "days": 3, "unit": "f"}"#,
+        ] {
+            assert!(compiled.matches(valid), "{} rejected", valid);
+        }
+        for invalid in [
+            r#"{}"#,
+            r#"{"days": 3}"#,
+            r#"{"city": "Oslo", "days": 3.5}"#,
+            r#"{"city": "Oslo", "unit": "k"}"#,
+            r#"{"city": "Oslo",}"#,
+        ] {
+            assert!(!compiled.matches(invalid), "{} accepted", invalid);
+        }
+        assert!(Constraint::JsonSchema {
+            schema: json!({"$ref": "#/defs/x"})
+        }
+        .compile()
+        .is_err());
+    }
+
+    #[test]
+    fn test_grammar_constraint() {
+        let compiled = Constraint::Grammar {
+            gbnf: r#"
+                # Arithmetic with nesting, which no regex can match
+                root ::= expr
+                expr ::= term ("+" term)*
+                term ::= [0-9]+ | "(" expr ")"
+            "#
+            .into(),
+        }
+        .compile()
+        .unwrap();
+        assert!(compiled.matches("1+(2+34)"));
+        assert!(compiled.matches("((7))"));
+        assert!(!compiled.matches("(1+2"));
+        assert!(!compiled.matches("1+"));
+
+        // Characters split across tokens are matched once complete
+        let vocab = vec![vec![0xC3], vec![0xA9], b"e".to_vec()];
+        let accent = compile(Constraint::Grammar {
+            gbnf: "root ::= [é]".into(),
+        });
+        let mut constraint = TokenConstraint::new(accent, Arc::new(vocab));
+        assert!(!constraint.allows(2));
+        constraint.advance(0).unwrap();
+        assert!(!constraint.is_complete());
+        constraint.advance(1).unwrap();
+        assert!(constraint.is_complete());
+
+        for invalid in ["expr ::= \"a\"", "root ::= missing", "root ::= \"a"] {
+            assert!(Grammar::parse(invalid).is_err(), "{} parsed", invalid);
+        }
+    }
+}
@@ -5,102 +5,375 @@
 //! - Model execution
 //! - KV cache management
 //! - Output processing
+//!
+//! Each resident model is a lane with its own scheduler, KV cache budget
+//! and executor. Requests are routed to a lane by their `model` field, or to
+//! the default model when it is unset.
 
 use std::collections::HashMap;
-use std::time::Instant;
-use tracing::{debug, info};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
+use super::candidates::candidate_sequence_id;
+use super::clock::{self, SharedClock};
 use super::config::EngineCoreConfig;
-use super::executor::{UnifiedExecutor, WorkerConfig};
-use super::kv_cache::{KVCacheConfig, KVCacheManager};
-use super::output::OutputProcessor;
-use super::request::{EngineCoreRequest, RequestStatus};
-use super::scheduler::{Scheduler, SchedulerConfig};
-use super::types::{EngineOutput, RequestId, SequenceId};
+use super::executor::{ExecutorOutput, UnifiedExecutor, WorkerConfig, DEFAULT_MODEL};
+use super::kv_cache::{KVCacheConfig, KVCacheManager, KVCacheStats};
+use super::latency::{LatencyPhase, LatencyReport, LatencyTracker};
+use super::memory;
+use super::output::{OutputProcessor, ResultStore, StopChecker};
+use super::output_cache::{CacheKey, OutputCache};
+use super::pipeline::PipelinePlan;
+use super::profiler::{StepProfile, StepProfiler};
+use super::request::{AuditEntry, AuditEvent, EngineCoreRequest, RequestStatus};
+use super::scheduler::{ScheduledRequest, Scheduler, SchedulerConfig};
+use super::session;
+use super::types::{
+    EngineOutput, EngineStats, ModelStats, OutputTimings, Priority, RequestId, RequestProgress,
+    SequenceId,
+};
+use crate::audio::OutputMemoryTracker;
+use crate::config::default_max_output_buffer_bytes;
 use crate::error::{Error, Result};
+use crate::model::ModelVariant;
+
+/// Requests scheduled together on one model. The batch holds what the
+/// executor needs, so it runs without the engine core borrowed.
+pub(crate) struct StepBatch {
+    model: ModelVariant,
+    executor: UnifiedExecutor,
+    requests: Vec<Arc<EngineCoreRequest>>,
+    scheduled: Vec<ScheduledRequest>,
+    profile: StepProfile,
+    clock: SharedClock,
+    schedule_start: Instant,
+    step_start: Instant,
+    /// Time from scheduling to the end of the forward pass
+    forward: Duration,
+}
+
+impl StepBatch {
+    /// Phase 2 of a step: run the forward pass
+    pub(crate) async fn execute(&mut self) -> Result<Vec<ExecutorOutput>> {
+        let requests: Vec<&EngineCoreRequest> = self.requests.iter().map(Arc::as_ref).collect();
+        let outputs = self.executor.execute(&requests, &self.scheduled).await;
+        self.forward = self.clock.elapsed_since(self.step_start);
+        outputs
+    }
+
+    /// Requests in the batch
+    pub(crate) fn request_ids(&self) -> impl Iterator<Item = &RequestId> {
+        self.requests.iter().map(|request| &request.id)
+    }
+}
+
+/// KV blocks per model when free memory cannot be detected
+const FALLBACK_MAX_BLOCKS: usize = 1024;
+
+/// A resident model with its own scheduler, KV cache and executor.
+struct ModelLane {
+    scheduler: Scheduler,
+    kv_cache: KVCacheManager,
+    executor: UnifiedExecutor,
+    /// Whether the executor has been initialized
+    initialized: bool,
+}
+
+impl ModelLane {
+    /// Create a lane with `max_blocks` KV blocks, or as many as fit in the
+    /// configured share of free memory minus `reserved_bytes` when 0.
+    fn new(
+        config: &EngineCoreConfig,
+        clock: &SharedClock,
+        max_blocks: usize,
+        reserved_bytes: usize,
+        executor: UnifiedExecutor,
+    ) -> Self {
+        let scheduler_config = SchedulerConfig::from(config);
+        let mut kv_config = KVCacheConfig {
+            num_layers: 24,
+            num_heads: 16,
+            head_dim: 64,
+            block_size: config.block_size,
+            max_blocks,
+            dtype: config.kv_cache_dtype,
+        };
+        let plan = PipelinePlan::for_config(config, kv_config.num_layers);
+        if max_blocks == 0 {
+            kv_config.max_blocks = budget_max_blocks(config, &plan, &kv_config, reserved_bytes);
+        }
+        info!(
+            "KV cache capacity: {} blocks of {} tokens ({:.2} GiB)",
+            kv_config.max_blocks,
+            kv_config.block_size,
+            kv_config.total_memory_bytes() as f64 / (1024.0 * 1024.0 * 1024.0)
+        );
+        if plan.is_pipelined() {
+            for stage in plan.stages() {
+                info!(
+                    "Pipeline stage on {}: layers {}..{}",
+                    stage.device, stage.layers.start, stage.layers.end
+                );
+            }
+        }
+        Self {
+            scheduler: Scheduler::new(scheduler_config).with_clock(clock.clone()),
+            kv_cache: KVCacheManager::new(kv_config).with_pipeline(plan),
+            executor,
+            initialized: false,
+        }
+    }
+
+    /// Share the request's prompt blocks with its other best-of-N takes.
+    ///
+    /// Re-forking on every prefill chunk keeps the takes in step with the
+    /// prompt's block table.
+    fn fork_candidates(&mut self, request: &EngineCoreRequest) {
+        for index in 1..request.params.n_candidates {
+            let take = candidate_sequence_id(&request.id, index);
+            self.kv_cache.free(&take);
+            self.kv_cache.fork(&request.id, &take);
+        }
+    }
+
+    /// Keep a finished chat turn's blocks as its session's cached history,
+    /// replacing the previous turn's.
+    fn keep_session_prefix(&mut self, request: &EngineCoreRequest) {
+        if let Some(session_id) = &request.session_id {
+            let key = session::prefix_key(session_id);
+            self.kv_cache.free(&key);
+            self.kv_cache.fork(&request.id, &key);
+        }
+    }
+
+    /// Free the blocks held by the request's other takes.
+    fn release_candidates(&mut self, request: &EngineCoreRequest) {
+        for index in 1..request.params.n_candidates {
+            self.kv_cache
+                .free(&candidate_sequence_id(&request.id, index));
+        }
+    }
+}
+
+/// KV blocks fitting in `gpu_memory_utilization` of the free memory, after
+/// the `reserved_bytes` held by other resident models' caches.
+///
+/// A pipelined model is limited by its tightest stage: each stage holds its
+/// share of every block, and of the reserved bytes, on its own device.
+fn budget_max_blocks(
+    config: &EngineCoreConfig,
+    plan: &PipelinePlan,
+    kv_config: &KVCacheConfig,
+    reserved_bytes: usize,
+) -> usize {
+    let fraction = config.gpu_memory_utilization.clamp(0.0, 1.0) as f64;
+    let block_bytes = kv_config.block_memory_bytes() as u64;
+    let mut max_blocks: Option<usize> = None;
+    for stage in plan.stages() {
+        let Some(available) = memory::available_memory_bytes(stage.device) else {
+            warn!(
+                "Could not detect free memory on {}; using {} KV cache blocks",
+                stage.device, FALLBACK_MAX_BLOCKS
+            );
+            return FALLBACK_MAX_BLOCKS;
+        };
+        let budget = (available as f64 * fraction) as u64;
+        let reserved = plan.stage_share(stage, reserved_bytes as u64);
+        let stage_block_bytes = plan.stage_share(stage, block_bytes).max(1);
+        let blocks = (budget.saturating_sub(reserved) / stage_block_bytes) as usize;
+        info!(
+            "{:.2} GiB free on {}, {:.0}% for KV caches",
+            available as f64 / (1024.0 * 1024.0 * 1024.0),
+            stage.device,
+            fraction * 100.0
+        );
+        max_blocks = Some(max_blocks.map_or(blocks, |max| max.min(blocks)));
+    }
+    let blocks = max_blocks.unwrap_or(0);
+    if blocks == 0 {
+        warn!("No memory left for the KV cache; scheduling will stall until requests fit");
+    }
+    blocks
+}
 
 /// The engine core - manages the inference loop.
 pub struct EngineCore {
     /// Configuration
     config: EngineCoreConfig,
-    /// Request scheduler
-    scheduler: Scheduler,
-    /// KV cache manager
-    kv_cache: KVCacheManager,
-    /// Model executor
-    executor: UnifiedExecutor,
+    /// Resident models
+    lanes: HashMap<ModelVariant, ModelLane>,
+    /// Model serving requests that don't name one
+    default_model: ModelVariant,
+    /// Lane each active request was routed to
+    request_models: HashMap<RequestId, ModelVariant>,
     /// Output processor
     output_processor: OutputProcessor,
-    /// Active requests (by ID)
-    requests: HashMap<RequestId, EngineCoreRequest>,
+    /// Active requests (by ID), shared with the batches executing them
+    requests: HashMap<RequestId, Arc<EngineCoreRequest>>,
     /// Request start times (for timing)
     request_start_times: HashMap<RequestId, Instant>,
     /// Sequence ID counter
     next_sequence_id: SequenceId,
-    /// Whether the engine has been initialized
-    initialized: bool,
+    /// Time source for arrival and generation timing
+    clock: SharedClock,
+    /// Per-request latency traces
+    latency: Arc<LatencyTracker>,
+    /// Recent step timings
+    profiler: Arc<StepProfiler>,
 }
 
 impl EngineCore {
     /// Create a new engine core.
     pub fn new(config: EngineCoreConfig) -> Result<Self> {
-        info!("Creating engine core");
-
-        // Create scheduler
-        let scheduler_config = SchedulerConfig::from(&config);
-        let scheduler = Scheduler::new(scheduler_config);
+        Self::with_clock(config, clock::system_clock())
+    }
 
-        // Create KV cache manager
-        let kv_config = KVCacheConfig {
-            num_layers: 24,
-            num_heads: 16,
-            head_dim: 64,
-            block_size: config.block_size,
-            max_blocks: config.max_blocks,
-            dtype_bytes: 2,
-        };
-        let kv_cache = KVCacheManager::new(kv_config);
+    /// Create an engine core reading time from `clock`.
+    pub fn with_clock(config: EngineCoreConfig, clock: SharedClock) -> Result<Self> {
+        info!("Creating engine core");
+        match config.speculative_model {
+            Some(draft) if config.num_speculative_tokens > 0 => info!(
+                "Speculative decoding: {} proposes {} tokens per step",
+                draft, config.num_speculative_tokens
+            ),
+            Some(draft) => warn!(
+                "Draft model {} set but num_speculative_tokens is 0; speculative decoding disabled",
+                draft
+            ),
+            None => {}
+        }
 
-        // Create executor
+        // Create the default model lane
         let worker_config = WorkerConfig::from(&config);
         let executor = UnifiedExecutor::new_python(worker_config);
+        let lane = ModelLane::new(&config, &clock, config.max_blocks, 0, executor);
 
         // Create output processor
-        let output_processor =
-            OutputProcessor::new(config.sample_rate).with_chunk_size(config.streaming_chunk_size);
+        let mut output_processor = OutputProcessor::new(config.sample_rate)
+            .with_clock(clock.clone())
+            .with_chunk_size(config.streaming_chunk_size)
+            .with_result_ttl(Duration::from_secs(config.result_ttl_secs))
+            .with_memory_limits(
+                config.max_output_buffer_bytes_per_request,
+                config.output_overflow_policy,
+            )
+            .with_memory_tracker(Arc::new(OutputMemoryTracker::new(
+                default_max_output_buffer_bytes(),
+            )))
+            .with_backpressure(config.stream_backpressure);
+        if config.output_cache.enabled {
+            let cache = OutputCache::open(config.output_cache.clone())?;
+            output_processor = output_processor.with_cache(Arc::new(cache));
+        }
 
         Ok(Self {
             config,
-            scheduler,
-            kv_cache,
-            executor,
+            lanes: HashMap::from([(DEFAULT_MODEL, lane)]),
+            default_model: DEFAULT_MODEL,
+            request_models: HashMap::new(),
             output_processor,
             requests: HashMap::new(),
             request_start_times: HashMap::new(),
             next_sequence_id: 0,
-            initialized: false,
+            clock,
+            latency: Arc::new(LatencyTracker::default()),
+            profiler: Arc::new(StepProfiler::default()),
         })
     }
 
     /// Initialize the engine core.
     pub async fn initialize(&mut self) -> Result<()> {
-        if self.initialized {
+        if self.lanes.values().all(|lane| lane.initialized) {
             return Ok(());
         }
 
         info!("Initializing engine core");
 
-        // Initialize executor (starts daemon processes)
-        self.executor.initialize().await?;
+        // Initialize executors (starts daemon processes)
+        for lane in self.lanes.values_mut().filter(|lane| !lane.initialized) {
+            lane.executor.initialize().await?;
+            lane.initialized = true;
+        }
 
-        self.initialized = true;
         info!("Engine core initialized");
 
         Ok(())
     }
 
+    /// Make `variant` resident next to the current models with its own
+    /// executor (already initialized) and a budget of `max_blocks` KV blocks
+    /// (0 = sized from the free memory not held by other models).
+    pub fn add_model(
+        &mut self,
+        variant: ModelVariant,
+        executor: UnifiedExecutor,
+        max_blocks: usize,
+    ) -> Result<()> {
+        if self.lanes.contains_key(&variant) {
+            return Err(Error::InvalidInput(format!(
+                "Model {} is already loaded",
+                variant
+            )));
+        }
+        let reserved = self
+            .lanes
+            .values()
+            .map(|lane| lane.kv_cache.config().total_memory_bytes())
+            .sum();
+        let mut lane = ModelLane::new(&self.config, &self.clock, max_blocks, reserved, executor);
+        lane.initialized = true;
+        info!(
+            "Model {} resident with {} KV blocks",
+            variant,
+            lane.kv_cache.config().max_blocks
+        );
+        self.lanes.insert(variant, lane);
+        Ok(())
+    }
+
+    /// Drop a resident model with no requests left, returning its executor.
+    pub fn remove_model(&mut self, variant: ModelVariant) -> Result<UnifiedExecutor> {
+        if variant == self.default_model {
+            return Err(Error::InvalidInput(format!(
+                "Model {} is the default model; swap it instead",
+                variant
+            )));
+        }
+        let lane = self
+            .lanes
+            .get(&variant)
+            .ok_or_else(|| Error::ModelNotFound(format!("Model {} is not loaded", variant)))?;
+        if lane.scheduler.has_pending_work() {
+            return Err(Error::InvalidInput(format!(
+                "Model {} still has requests in flight",
+                variant
+            )));
+        }
+        let lane = self.lanes.remove(&variant).expect("lane checked above");
+        Ok(lane.executor)
+    }
+
+    /// Resident models, in catalog order.
+    pub fn loaded_models(&self) -> Vec<ModelVariant> {
+        ModelVariant::all()
+            .iter()
+            .copied()
+            .filter(|v| self.lanes.contains_key(v))
+            .collect()
+    }
+
+    /// Model serving requests that don't name one.
+    pub fn default_model(&self) -> ModelVariant {
+        self.default_model
+    }
+
+    fn lane_of(&self, request_id: &RequestId) -> Option<&ModelLane> {
+        self.lanes.get(self.request_models.get(request_id)?)
+    }
+
     /// Add a request to the engine.
-    pub fn add_request(&mut self, request: EngineCoreRequest) -> Result<()> {
+    pub fn add_request(&mut self, mut request: EngineCoreRequest) -> Result<()> {
         let request_id = request.id.clone();
 
         if self.requests.contains_key(&request_id) {
@@ -110,19 +383,98 @@ impl EngineCore {
             )));
         }
 
-        // Add to scheduler
-        self.scheduler.add_request(&request);
+        self.check_admission(&request)?;
+
+        let model = request.model.unwrap_or(self.default_model);
+        let lane = self
+            .lanes
+            .get_mut(&model)
+            .ok_or_else(|| Error::ModelNotLoaded(format!("Model {} is not loaded", model)))?;
+
+        // A prompt larger than the whole cache would wait forever
+        let kv_config = lane.kv_cache.config();
+        let prompt_blocks = kv_config.blocks_for_tokens(request.num_prompt_tokens());
+        if prompt_blocks > kv_config.max_blocks {
+            return Err(Error::KvCacheExhausted(format!(
+                "prompt needs {} KV blocks but {} has {}",
+                prompt_blocks, model, kv_config.max_blocks
+            )));
+        }
+
+        let now = self.clock.now();
+        request.arrival_time = Some(now);
+
+        // Add to the model's scheduler
+        lane.scheduler.add_request(&request);
+        request.record(
+            AuditEvent::Queued {
+                priority: request.priority,
+            },
+            now,
+        );
+
+        if let Some(cache) = self.output_processor.cache() {
+            if request.cache_control.writes() && request.text.is_some() {
+                cache.expect(&request_id, CacheKey::for_request(&request, None));
+            }
+        }
+
+        if let Some(tx) = request.streaming_tx.clone() {
+            self.output_processor
+                .start_streaming(request_id.clone(), 0, tx);
+        }
 
         // Track request
-        self.requests.insert(request_id.clone(), request);
-        self.request_start_times
-            .insert(request_id.clone(), Instant::now());
+        self.request_models.insert(request_id.clone(), model);
+        self.requests.insert(request_id.clone(), Arc::new(request));
+        self.request_start_times.insert(request_id.clone(), now);
+        self.latency.start(&request_id, now);
 
         debug!("Added request {} to engine core", request_id);
 
         Ok(())
     }
 
+    /// Reject the request if it would push the waiting queue past its caps.
+    fn check_admission(&self, request: &EngineCoreRequest) -> Result<()> {
+        let waiting = self.pending_request_count();
+        let max_waiting = self.config.max_waiting_requests;
+        if max_waiting > 0 && waiting >= max_waiting {
+            return Err(self.overloaded(
+                format!(
+                    "{} requests already waiting (limit {})",
+                    waiting, max_waiting
+                ),
+                waiting,
+            ));
+        }
+
+        // A single oversized prompt is still admitted into an empty queue
+        let queued_tokens = self.queued_token_count();
+        let max_tokens = self.config.max_queued_tokens;
+        let tokens = request.num_prompt_tokens();
+        if max_tokens > 0 && waiting > 0 && queued_tokens + tokens > max_tokens {
+            return Err(self.overloaded(
+                format!(
+                    "{} prompt tokens already queued, request adds {} (limit {})",
+                    queued_tokens, tokens, max_tokens
+                ),
+                waiting,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Build an overload error with a Retry-After hint based on how long
+    /// recent steps took to drain a batch.
+    fn overloaded(&self, reason: String, waiting: usize) -> Error {
+        let summary = self.profiler.snapshot(Some(100)).summary;
+        let batches = waiting.div_ceil(self.config.max_batch_size.max(1)) as f64;
+        warn!("Rejecting request: {}", reason);
+        Error::overloaded(reason, batches * summary.mean_step_ms / 1000.0)
+    }
+
     /// Execute one step of the inference loop.
     ///
     /// The step consists of:
@@ -130,16 +482,66 @@ impl EngineCore {
     /// 2. Execute - run forward pass
     /// 3. Process - handle outputs, check stop conditions
     pub async fn step(&mut self) -> Result<Vec<EngineOutput>> {
+        let mut outputs = Vec::new();
+        for mut batch in self.schedule_step().await? {
+            let executed = batch.execute().await;
+            outputs.extend(self.finish_batch(batch, executed).await?);
+        }
+        Ok(outputs)
+    }
+
+    /// Schedule the next step of every model. The returned batches can be
+    /// executed without access to the core, then handed to
+    /// [`Self::finish_batch`].
+    pub(crate) async fn schedule_step(&mut self) -> Result<Vec<StepBatch>> {
         // Ensure initialized
-        if !self.initialized {
-            self.initialize().await?;
+        self.initialize().await?;
+
+        let stalled = self.output_processor.flush_streams();
+        for lane in self.lanes.values_mut() {
+            lane.scheduler.set_stalled(stalled.clone());
+        }
+
+        let mut batches = Vec::new();
+        for variant in self.loaded_models() {
+            // Take the lane out so it can be borrowed alongside the rest of self
+            let Some(mut lane) = self.lanes.remove(&variant) else {
+                continue;
+            };
+            let batch = self.schedule_lane(variant, &mut lane);
+            self.lanes.insert(variant, lane);
+            batches.extend(batch);
         }
+        Ok(batches)
+    }
 
+    /// Schedule one step for a single model; `None` when it has no work.
+    fn schedule_lane(&mut self, model: ModelVariant, lane: &mut ModelLane) -> Option<StepBatch> {
         // Phase 1: Schedule
-        let schedule_result = self.scheduler.schedule(&mut self.kv_cache);
+        let schedule_start = self.clock.now();
+        let schedule_result = lane.scheduler.schedule(&mut lane.kv_cache);
+        let profile = StepProfile {
+            schedule_ms: self.clock.elapsed_since(schedule_start).as_secs_f64() * 1000.0,
+            prefill_requests: schedule_result.prefill_requests.len(),
+            decode_requests: schedule_result.decode_requests.len(),
+            prefill_tokens: schedule_result
+                .prefill_requests
+                .iter()
+                .map(|s| s.num_tokens)
+                .sum(),
+            decode_tokens: schedule_result
+                .decode_requests
+                .iter()
+                .map(|s| s.num_tokens)
+                .sum(),
+            ..Default::default()
+        };
+        for (request_id, reason) in &schedule_result.deferred {
+            self.latency.delayed(request_id, *reason);
+        }
 
         if !schedule_result.has_work() {
-            return Ok(Vec::new());
+            return None;
         }
 
         debug!(
@@ -149,63 +551,185 @@ impl EngineCore {
         );
 
         // Collect requests for execution
-        let all_scheduled: Vec<_> = schedule_result
+        let scheduled: Vec<ScheduledRequest> = schedule_result
             .prefill_requests
-            .iter()
-            .chain(schedule_result.decode_requests.iter())
+            .into_iter()
+            .chain(schedule_result.decode_requests)
             .collect();
 
-        let request_refs: Vec<&EngineCoreRequest> = all_scheduled
+        let requests: Vec<Arc<EngineCoreRequest>> = scheduled
             .iter()
-            .filter_map(|s| self.requests.get(&s.request_id))
+            .filter_map(|s| self.requests.get(&s.request_id).cloned())
             .collect();
 
-        if request_refs.is_empty() {
-            return Ok(Vec::new());
+        if requests.is_empty() {
+            return None;
+        }
+
+        // Best-of-N takes share the prompt's KV blocks until they diverge
+        for scheduled in scheduled.iter().filter(|s| s.is_prefill) {
+            if let Some(request) = self.requests.get(&scheduled.request_id) {
+                lane.fork_candidates(request);
+            }
+        }
+
+        let step_start = self.clock.now();
+        let results = self.output_processor.results();
+        for scheduled in &scheduled {
+            self.latency.scheduled(&scheduled.request_id, step_start);
+            results.start(&scheduled.request_id);
         }
+        Some(StepBatch {
+            model,
+            executor: lane.executor.clone(),
+            requests,
+            scheduled,
+            profile,
+            clock: self.clock.clone(),
+            schedule_start,
+            step_start,
+            forward: Duration::ZERO,
+        })
+    }
+
+    /// Apply the outputs of an executed batch. Requests that left the
+    /// model while it ran are skipped; when execution failed, the batch's
+    /// requests are aborted and the error returned.
+    pub(crate) async fn finish_batch(
+        &mut self,
+        batch: StepBatch,
+        executed: Result<Vec<ExecutorOutput>>,
+    ) -> Result<Vec<EngineOutput>> {
+        let executor_outputs = match executed {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                for request_id in batch.request_ids() {
+                    self.abort_request(request_id);
+                }
+                return Err(e);
+            }
+        };
+        // The model was unloaded or swapped out meanwhile
+        let model = batch.model;
+        let Some(mut lane) = self.lanes.remove(&model) else {
+            return Ok(Vec::new());
+        };
+        let outputs = self
+            .process_outputs(batch, &mut lane, executor_outputs)
+            .await;
+        self.lanes.insert(model, lane);
+        Ok(outputs)
+    }
 
-        // Phase 2: Execute
-        let scheduled_refs: Vec<_> = all_scheduled.iter().map(|s| (*s).clone()).collect();
-        let executor_outputs = self
-            .executor
-            .execute(&request_refs, &scheduled_refs)
-            .await?;
+    /// Phase 3 of a step: hand out the executor's outputs and update the
+    /// scheduler.
+    async fn process_outputs(
+        &mut self,
+        batch: StepBatch,
+        lane: &mut ModelLane,
+        executor_outputs: Vec<ExecutorOutput>,
+    ) -> Vec<EngineOutput> {
+        let StepBatch {
+            model,
+            scheduled,
+            mut profile,
+            schedule_start,
+            forward: step_time,
+            ..
+        } = batch;
+        profile.set_forward(step_time);
+        for scheduled in &scheduled {
+            let phase = if scheduled.is_prefill {
+                LatencyPhase::Prefill
+            } else {
+                LatencyPhase::Decode
+            };
+            self.latency.record(&scheduled.request_id, phase, step_time);
+        }
 
-        // Phase 3: Process outputs
         let mut outputs = Vec::new();
+        let mut disconnected = Vec::new();
 
-        for exec_output in executor_outputs {
+        for mut exec_output in executor_outputs {
             let request_id = exec_output.request_id.clone();
 
+            // Aborted, or moved to another model, while the batch ran
+            if self.request_models.get(&request_id) != Some(&model) {
+                continue;
+            }
+
+            // Stop sequences and the audio length limit end requests early
+            if let Some(request) = self.requests.get(&request_id) {
+                let generated = lane
+                    .scheduler
+                    .get_running_info(&request_id)
+                    .map_or(0, |(_, generated)| generated);
+                StopChecker::for_params(&request.params, self.config.max_seq_len)
+                    .apply(&mut exec_output, generated);
+            }
+
             // Get timing info
             let generation_time = self
                 .request_start_times
                 .get(&request_id)
-                .map(|t| t.elapsed())
+                .map(|t| self.clock.elapsed_since(*t))
                 .unwrap_or_default();
 
             // Get sequence ID from scheduler
-            let sequence_id = self
+            let sequence_id = lane
                 .scheduler
                 .get_running_info(&request_id)
                 .map(|(_, _)| self.next_sequence_id)
                 .unwrap_or(0);
 
             // Process output
-            let engine_output =
+            let codec_start = self.clock.now();
+            let mut engine_output =
                 self.output_processor
                     .process(exec_output.clone(), sequence_id, generation_time);
+            let codec_time = self.clock.elapsed_since(codec_start);
+            profile.audio_decode_ms += codec_time.as_secs_f64() * 1000.0;
+            self.latency
+                .record(&request_id, LatencyPhase::Codec, codec_time);
+
+            if self.output_processor.is_streaming(&request_id) {
+                let samples = engine_output.audio.samples.clone();
+                if !self
+                    .output_processor
+                    .add_streaming_samples(&request_id, samples)
+                    .await
+                {
+                    disconnected.push(request_id.clone());
+                } else if exec_output.finished {
+                    self.output_processor
+                        .finish_streaming(&request_id, engine_output.text.clone())
+                        .await;
+                }
+            }
 
             // Update scheduler state
             if exec_output.finished {
-                self.scheduler
-                    .finish_request(&request_id, &mut self.kv_cache);
-                self.requests.remove(&request_id);
+                if exec_output.error.is_none() {
+                    if let Some(request) = self.requests.get(&request_id) {
+                        lane.keep_session_prefix(request);
+                    }
+                }
+                lane.scheduler
+                    .finish_request(&request_id, &mut lane.kv_cache);
+                if let Some(request) = self.requests.remove(&request_id) {
+                    lane.release_candidates(&request);
+                }
+                self.request_models.remove(&request_id);
                 self.request_start_times.remove(&request_id);
+                let now = self.clock.now();
+                self.latency.finish(&request_id, now);
+                if let Some(report) = self.latency.report(&request_id, now) {
+                    engine_output.timings = OutputTimings::from(&report);
+                }
                 debug!("Finished request {}", request_id);
             } else {
                 // Update for next step
-                self.scheduler.update_after_step(
+                lane.scheduler.update_after_step(
                     &request_id,
                     exec_output.tokens_processed,
                     exec_output.tokens_generated,
@@ -216,12 +740,47 @@ impl EngineCore {
             outputs.push(engine_output);
         }
 
-        Ok(outputs)
+        profile.total_ms = self.clock.elapsed_since(schedule_start).as_secs_f64() * 1000.0;
+        self.profiler.record(model, profile);
+
+        // Stop generating for streams nobody is listening to
+        for request_id in disconnected {
+            self.output_processor.cancel_streaming(&request_id);
+            if lane
+                .scheduler
+                .abort_request(&request_id, &mut lane.kv_cache)
+            {
+                if let Some(request) = self.requests.remove(&request_id) {
+                    lane.release_candidates(&request);
+                }
+                self.request_models.remove(&request_id);
+                self.request_start_times.remove(&request_id);
+                self.latency.finish(&request_id, self.clock.now());
+                self.output_processor
+                    .results()
+                    .fail(&request_id, "stream closed");
+                debug!("Stream {} closed; request aborted", request_id);
+            }
+        }
+
+        outputs
+    }
+
+    /// Free the KV cache held for a chat session's history.
+    pub fn release_session(&mut self, session_id: &str) {
+        let key = session::prefix_key(session_id);
+        for lane in self.lanes.values_mut() {
+            lane.kv_cache.free(&key);
+        }
     }
 
     /// Check if there's pending work.
     pub fn has_pending_work(&self) -> bool {
-        self.scheduler.has_pending_work()
+        self.output_processor.has_draining_streams()
+            || self
+                .lanes
+                .values()
+                .any(|lane| lane.scheduler.has_pending_work())
     }
 
     /// Check if a request exists.
@@ -231,14 +790,44 @@ impl EngineCore {
 
     /// Get request status.
     pub fn get_request_status(&self, request_id: &RequestId) -> Option<RequestStatus> {
-        self.scheduler.get_status(request_id)
+        self.lane_of(request_id)?.scheduler.get_status(request_id)
     }
 
     /// Abort a request.
     pub fn abort_request(&mut self, request_id: &RequestId) -> bool {
-        if self.scheduler.abort_request(request_id, &mut self.kv_cache) {
-            self.requests.remove(request_id);
+        self.abort_with_reason(request_id, "aborted")
+    }
+
+    /// Abort every request the core holds, waiting, running or streaming,
+    /// and fail their jobs with `reason`. Returns the aborted requests.
+    pub fn abort_all(&mut self, reason: &str) -> Vec<RequestId> {
+        let request_ids: Vec<RequestId> = self.requests.keys().cloned().collect();
+        request_ids
+            .into_iter()
+            .filter(|id| self.abort_with_reason(id, reason))
+            .collect()
+    }
+
+    fn abort_with_reason(&mut self, request_id: &RequestId, reason: &str) -> bool {
+        let Some(lane) = self
+            .request_models
+            .get(request_id)
+            .and_then(|model| self.lanes.get_mut(model))
+        else {
+            return false;
+        };
+        if lane.scheduler.abort_request(request_id, &mut lane.kv_cache) {
+            if let Some(request) = self.requests.remove(request_id) {
+                lane.release_candidates(&request);
+            }
+            self.request_models.remove(request_id);
             self.request_start_times.remove(request_id);
+            self.latency.finish(request_id, self.clock.now());
+            self.output_processor.cancel_streaming(request_id);
+            self.output_processor.results().fail(request_id, reason);
+            if let Some(cache) = self.output_processor.cache() {
+                cache.forget(request_id);
+            }
             debug!("Aborted request {}", request_id);
             true
         } else {
@@ -246,19 +835,239 @@ impl EngineCore {
         }
     }
 
+    /// Change the priority of a waiting request, returning the old one.
+    pub fn set_request_priority(
+        &mut self,
+        request_id: &RequestId,
+        priority: Priority,
+        reason: Option<String>,
+    ) -> Result<Priority> {
+        let request = self
+            .requests
+            .get_mut(request_id)
+            .map(Arc::make_mut)
+            .ok_or_else(|| Error::RequestNotFound(request_id.clone()))?;
+        let lane = self
+            .request_models
+            .get(request_id)
+            .and_then(|model| self.lanes.get_mut(model))
+            .ok_or_else(|| Error::RequestNotFound(request_id.clone()))?;
+
+        let previous = lane
+            .scheduler
+            .update_priority(request_id, priority)
+            .ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Request {} is no longer waiting and cannot be reprioritized",
+                    request_id
+                ))
+            })?;
+
+        request.priority = priority;
+        request.record(
+            AuditEvent::PriorityChanged {
+                from: previous,
+                to: priority,
+                reason,
+            },
+            self.clock.now(),
+        );
+        info!(
+            "Request {} priority changed from {:?} to {:?}",
+            request_id, previous, priority
+        );
+
+        Ok(previous)
+    }
+
+    /// Get status and audit trail of a request.
+    pub fn request_audit(
+        &self,
+        request_id: &RequestId,
+    ) -> Option<(RequestStatus, Vec<AuditEntry>)> {
+        let request = self.requests.get(request_id)?;
+        let status = self.get_request_status(request_id)?;
+        Some((status, request.audit.clone()))
+    }
+
+    /// Stop or resume starting waiting requests on the default model.
+    pub fn set_admission_paused(&mut self, paused: bool) {
+        if let Some(lane) = self.lanes.get_mut(&self.default_model) {
+            lane.scheduler.set_admission_paused(paused);
+        }
+    }
+
+    /// Number of requests running on the default model.
+    pub fn default_running_count(&self) -> usize {
+        self.lanes
+            .get(&self.default_model)
+            .map(|lane| lane.scheduler.running_count())
+            .unwrap_or(0)
+    }
+
+    /// Replace the default model with `model`, served by an already
+    /// initialized executor. Requests still running are sent back to the
+    /// waiting queue to restart on the new model; their IDs are returned.
+    pub fn replace_default_model(
+        &mut self,
+        model: ModelVariant,
+        executor: UnifiedExecutor,
+    ) -> Result<Vec<RequestId>> {
+        let previous = self.default_model;
+        if model != previous && self.lanes.contains_key(&model) {
+            return Err(Error::InvalidInput(format!(
+                "Model {} is already loaded; send requests to it directly",
+                model
+            )));
+        }
+        let mut lane = self
+            .lanes
+            .remove(&previous)
+            .ok_or_else(|| Error::ModelNotFound(previous.to_string()))?;
+
+        let migrated = lane.scheduler.requeue_running(&mut lane.kv_cache);
+        let now = self.clock.now();
+        for request_id in &migrated {
+            if let Some(request) = self.requests.get_mut(request_id) {
+                Arc::make_mut(request).record(
+                    AuditEvent::Migrated {
+                        model: model.dir_name().to_string(),
+                    },
+                    now,
+                );
+            }
+        }
+
+        // Both executors talk to the same daemon, so the old one is dropped
+        // without shutting it down
+        lane.executor = executor;
+        lane.initialized = true;
+        self.lanes.insert(model, lane);
+        for lane_model in self.request_models.values_mut() {
+            if *lane_model == previous {
+                *lane_model = model;
+            }
+        }
+        self.default_model = model;
+        Ok(migrated)
+    }
+
+    /// Shared latency tracker, also fed by the serving layer.
+    pub fn latency_tracker(&self) -> Arc<LatencyTracker> {
+        self.latency.clone()
+    }
+
+    /// Step profiler shared with the engine.
+    pub fn step_profiler(&self) -> Arc<StepProfiler> {
+        self.profiler.clone()
+    }
+
+    /// Results of asynchronous jobs, shared with the engine.
+    pub fn result_store(&self) -> Arc<ResultStore> {
+        self.output_processor.results()
+    }
+
+    /// Output cache shared with the engine, when enabled.
+    pub fn output_cache(&self) -> Option<Arc<OutputCache>> {
+        self.output_processor.cache()
+    }
+
+    /// Latency breakdown of a running or recently finished request.
+    pub fn latency_report(&self, request_id: &RequestId) -> Option<LatencyReport> {
+        self.latency.report(request_id, self.clock.now())
+    }
+
     /// Get number of pending (waiting) requests.
     pub fn pending_request_count(&self) -> usize {
-        self.scheduler.waiting_count()
+        self.lanes
+            .values()
+            .map(|lane| lane.scheduler.waiting_count())
+            .sum()
+    }
+
+    /// Prompt tokens of the requests waiting across all models.
+    pub fn queued_token_count(&self) -> usize {
+        self.lanes
+            .values()
+            .map(|lane| lane.scheduler.waiting_tokens())
+            .sum()
     }
 
     /// Get number of running requests.
     pub fn running_request_count(&self) -> usize {
-        self.scheduler.running_count()
+        self.lanes
+            .values()
+            .map(|lane| lane.scheduler.running_count())
+            .sum()
+    }
+
+    /// Account buffered output audio against `memory`, whose cap is shared
+    /// with everything else tracked by it.
+    pub fn with_output_memory(mut self, memory: Arc<OutputMemoryTracker>) -> Self {
+        self.output_processor.set_memory_tracker(memory);
+        self
     }
 
-    /// Get KV cache statistics.
-    pub fn kv_cache_stats(&self) -> super::kv_cache::KVCacheStats {
-        self.kv_cache.stats()
+    /// Get output buffer memory statistics.
+    pub fn output_memory_stats(&self) -> crate::audio::OutputMemoryStats {
+        self.output_processor.memory_stats()
+    }
+
+    /// Queue, KV cache and per-request progress of every resident model.
+    pub fn stats(&self) -> EngineStats {
+        let models = self
+            .loaded_models()
+            .into_iter()
+            .filter_map(|model| Some((model, self.lanes.get(&model)?)))
+            .map(|(model, lane)| {
+                let requests = lane
+                    .scheduler
+                    .running_request_ids()
+                    .into_iter()
+                    .filter_map(|id| {
+                        let (processed, generated) = lane.scheduler.get_running_info(&id)?;
+                        let request = self.requests.get(&id)?;
+                        Some(RequestProgress {
+                            client_id: request.client_id.clone(),
+                            priority: request.priority,
+                            tokens_processed: processed,
+                            tokens_generated: generated,
+                            max_tokens: request.params.max_tokens,
+                            elapsed_ms: self.request_start_times.get(&id).map_or(0.0, |t| {
+                                self.clock.elapsed_since(*t).as_secs_f64() * 1000.0
+                            }),
+                            request_id: id,
+                        })
+                    })
+                    .collect();
+                let kv_cache = lane.kv_cache.stats();
+                ModelStats {
+                    model,
+                    waiting: lane.scheduler.waiting_count(),
+                    running: lane.scheduler.running_count(),
+                    queued_tokens: lane.scheduler.waiting_tokens(),
+                    kv_utilization: kv_cache.utilization(),
+                    kv_cache,
+                    requests,
+                }
+            })
+            .collect();
+        EngineStats {
+            default_model: self.default_model,
+            models,
+            output_buffer_bytes: self.output_memory_stats().used_bytes,
+        }
+    }
+
+    /// Get KV cache statistics of the default model.
+    pub fn kv_cache_stats(&self) -> KVCacheStats {
+        self.kv_cache_stats_for(self.default_model)
+            .expect("default model is always resident")
+    }
+
+    /// Get KV cache statistics of a resident model.
+    pub fn kv_cache_stats_for(&self, variant: ModelVariant) -> Option<KVCacheStats> {
+        self.lanes.get(&variant).map(|lane| lane.kv_cache.stats())
     }
 
     /// Get configuration.
@@ -276,10 +1085,12 @@ impl EngineCore {
             self.abort_request(&id);
         }
 
-        // Shutdown executor
-        self.executor.shutdown().await?;
+        // Shutdown executors
+        for lane in self.lanes.values_mut() {
+            lane.executor.shutdown().await?;
+            lane.initialized = false;
+        }
 
-        self.initialized = false;
         info!("Engine core shutdown complete");
 
         Ok(())
@@ -289,7 +1100,7 @@ impl EngineCore {
 impl Drop for EngineCore {
     fn drop(&mut self) {
         // Note: We can't do async cleanup in drop, so we just log
-        if self.initialized {
+        if self.lanes.values().any(|lane| lane.initialized) {
             debug!("EngineCore dropped while still initialized");
         }
     }
@@ -298,6 +1109,7 @@ impl Drop for EngineCore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::MIN_RETRY_AFTER_SECS;
 
     #[test]
     fn test_engine_core_creation() {
@@ -316,4 +1128,143 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(core.pending_request_count(), 1);
     }
+
+    #[test]
+    fn test_stats_report_queue_and_progress() {
+        let mut core = EngineCore::new(EngineCoreConfig::default()).unwrap();
+        core.add_request(EngineCoreRequest::tts("Hello, world!").with_client_id("acme"))
+            .unwrap();
+
+        let stats = core.stats();
+        assert_eq!(stats.models.len(), 1);
+        assert_eq!(stats.models[0].waiting, 1);
+        assert!(stats.models[0].queued_tokens > 0);
+        assert!(stats.models[0].requests.is_empty());
+
+        let lane = core.lanes.get_mut(&core.default_model).unwrap();
+        lane.scheduler.schedule(&mut lane.kv_cache);
+        let stats = core.stats();
+        let model = &stats.models[0];
+        assert_eq!((model.waiting, model.running), (0, 1));
+        assert!(model.kv_cache.allocated_blocks > 0);
+        assert!(model.kv_utilization > 0.0);
+        assert_eq!(model.requests[0].client_id.as_deref(), Some("acme"));
+        assert_eq!(model.requests[0].tokens_generated, 0);
+    }
+
+    #[test]
+    fn test_admission_limits() {
+        let config = EngineCoreConfig {
+            max_waiting_requests: 2,
+            ..Default::default()
+        };
+        let mut core = EngineCore::new(config).unwrap();
+        core.add_request(EngineCoreRequest::tts("one")).unwrap();
+        core.add_request(EngineCoreRequest::tts("two")).unwrap();
+        match core.add_request(EngineCoreRequest::tts("three")) {
+            Err(Error::Overloaded {
+                retry_after_secs, ..
+            }) => assert!(retry_after_secs >= MIN_RETRY_AFTER_SECS),
+            other => panic!("expected Overloaded, got {:?}", other),
+        }
+
+        // The token cap still admits one request into an empty queue
+        let config = EngineCoreConfig {
+            max_queued_tokens: 1,
+            ..Default::default()
+        };
+        let mut core = EngineCore::new(config).unwrap();
+        core.add_request(EngineCoreRequest::tts("a long first prompt"))
+            .unwrap();
+        assert!(matches!(
+            core.add_request(EngineCoreRequest::tts("second")),
+            Err(Error::Overloaded { .. })
+        ));
+        assert_eq!(core.pending_request_count(), 1);
+    }
+
+    #[test]
+    fn test_audit_timestamps_use_clock() {
+        use crate::engine::clock::MockClock;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let clock = Arc::new(MockClock::new());
+        let mut core = EngineCore::with_clock(EngineCoreConfig::default(), clock.clone()).unwrap();
+
+        let request = EngineCoreRequest::tts("Hello, world!");
+        let id = request.id.clone();
+        core.add_request(request).unwrap();
+
+        clock.advance(Duration::from_millis(250));
+        core.set_request_priority(&id, Priority::High, None)
+            .unwrap();
+
+        let (_, audit) = core.request_audit(&id).unwrap();
+        let elapsed: Vec<u64> = audit.iter().map(|e| e.elapsed_ms).collect();
+        assert_eq!(elapsed, vec![0, 250]);
+    }
+
+    #[test]
+    fn test_abort_all_closes_streams() {
+        let mut core = EngineCore::new(EngineCoreConfig::default()).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let mut streaming = EngineCoreRequest::tts("Hello").with_streaming(true);
+        streaming.streaming_tx = Some(tx);
+        core.add_request(streaming).unwrap();
+        core.add_request(EngineCoreRequest::tts("world")).unwrap();
+
+        let aborted = core.abort_all("daemon unavailable");
+        assert_eq!(aborted.len(), 2);
+        assert_eq!(core.pending_request_count(), 0);
+        // The stream ends once the core drops its sender
+        assert!(rx.try_recv().is_err());
+        assert!(rx.is_closed());
+    }
+
+    #[test]
+    fn test_prompt_larger_than_kv_cache_rejected() {
+        let mut core = EngineCore::new(EngineCoreConfig::default()).unwrap();
+        let lfm2 = ModelVariant::Lfm2Audio15B;
+        let executor = UnifiedExecutor::new_python(WorkerConfig::default());
+        core.add_model(lfm2, executor, 1).unwrap();
+
+        let mut request = EngineCoreRequest::tts("Hello").with_model(lfm2);
+        request.prompt_tokens = vec![0; 64];
+        let err = core.add_request(request).unwrap_err();
+        assert!(matches!(err, Error::KvCacheExhausted(_)));
+        assert_eq!(err.code().as_str(), "kv_cache_oom");
+        assert_eq!(err.code().http_status(), 503);
+        assert_eq!(core.pending_request_count(), 0);
+    }
+
+    #[test]
+    fn test_requests_routed_to_resident_models() {
+        let mut core = EngineCore::new(EngineCoreConfig::default()).unwrap();
+        let lfm2 = ModelVariant::Lfm2Audio15B;
+
+        let request = EngineCoreRequest::tts("Hello").with_model(lfm2);
+        assert!(matches!(
+            core.add_request(request),
+            Err(Error::ModelNotLoaded(_))
+        ));
+
+        let executor = UnifiedExecutor::new_python(WorkerConfig::default());
+        core.add_model(lfm2, executor, 64).unwrap();
+        assert_eq!(core.loaded_models().len(), 2);
+        assert_eq!(core.kv_cache_stats_for(lfm2).unwrap().total_blocks, 64);
+
+        let request = EngineCoreRequest::tts("Hello").with_model(lfm2);
+        let id = request.id.clone();
+        core.add_request(request).unwrap();
+        core.add_request(EngineCoreRequest::tts("Default")).unwrap();
+        assert_eq!(core.pending_request_count(), 2);
+        assert_eq!(core.get_request_status(&id), Some(RequestStatus::Waiting));
+
+        // A model with queued work cannot be unloaded
+        assert!(core.remove_model(lfm2).is_err());
+        core.abort_request(&id);
+        assert!(core.remove_model(lfm2).is_ok());
+        assert!(core.remove_model(core.default_model()).is_err());
+    }
 }
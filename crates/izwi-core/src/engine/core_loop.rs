@@ -0,0 +1,219 @@
+//! Dedicated task driving the engine core.
+//!
+//! Callers send commands over a channel instead of stepping the core
+//! themselves, so scheduling cadence does not depend on how many HTTP
+//! handlers are waiting on requests.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, RwLock};
+use tracing::{debug, info, warn};
+
+use super::core::EngineCore;
+use super::profiler::{ProfileSnapshot, StepProfiler};
+use super::request::EngineCoreRequest;
+use super::types::{EngineMetrics, EngineOutput, RequestId};
+use crate::error::{Error, Result};
+
+/// Pause before stepping again when nothing could be scheduled (KV cache
+/// full, streams paused)
+const STALLED_STEP_DELAY: Duration = Duration::from_millis(1);
+
+/// Work handed to the core task
+pub(crate) enum EngineCommand {
+    /// Queue a preprocessed request; `finished` receives its final output
+    Add {
+        request: Box<EngineCoreRequest>,
+        added: oneshot::Sender<Result<()>>,
+        finished: Option<oneshot::Sender<Result<EngineOutput>>>,
+    },
+    Abort {
+        request_id: RequestId,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Timings of the last `limit` steps, taken between steps
+    Profile {
+        limit: Option<usize>,
+        reply: oneshot::Sender<ProfileSnapshot>,
+    },
+    ResetProfile {
+        reply: oneshot::Sender<()>,
+    },
+}
+
+/// State owned by the core task
+pub(crate) struct CoreLoop {
+    core: Arc<RwLock<EngineCore>>,
+    metrics: Arc<RwLock<EngineMetrics>>,
+    profiler: Arc<StepProfiler>,
+    commands: mpsc::UnboundedReceiver<EngineCommand>,
+    /// Callers waiting for a request's final output
+    waiters: HashMap<RequestId, oneshot::Sender<Result<EngineOutput>>>,
+}
+
+impl CoreLoop {
+    pub(crate) fn new(
+        core: Arc<RwLock<EngineCore>>,
+        metrics: Arc<RwLock<EngineMetrics>>,
+        profiler: Arc<StepProfiler>,
+        commands: mpsc::UnboundedReceiver<EngineCommand>,
+    ) -> Self {
+        Self {
+            core,
+            metrics,
+            profiler,
+            commands,
+            waiters: HashMap::new(),
+        }
+    }
+
+    /// Serve commands and step until every sender is gone and no work is
+    /// left.
+    pub(crate) async fn run(mut self) {
+        info!("Engine core loop started");
+        loop {
+            if !self.core.read().await.has_pending_work() {
+                // Idle: sleep until there is something to do
+                match self.commands.recv().await {
+                    Some(command) => self.handle(command).await,
+                    None => break,
+                }
+            }
+            while let Ok(command) = self.commands.try_recv() {
+                self.handle(command).await;
+            }
+            if !self.core.read().await.has_pending_work() {
+                continue;
+            }
+            match self.step().await {
+                Ok(0) => tokio::time::sleep(STALLED_STEP_DELAY).await,
+                Ok(_) => {}
+                Err(e) => {
+                    // Nothing could be scheduled, so no request can run.
+                    // Aborting closes the streams of those nobody waits on.
+                    warn!("Engine step error: {}", e);
+                    let aborted = self.core.write().await.abort_all(&e.to_string());
+                    self.fail_waiters(&aborted, &e);
+                    tokio::time::sleep(STALLED_STEP_DELAY).await;
+                }
+            }
+        }
+        info!("Engine core loop stopped");
+    }
+
+    async fn handle(&mut self, command: EngineCommand) {
+        match command {
+            EngineCommand::Add {
+                request,
+                added,
+                finished,
+            } => {
+                let request_id = request.id.clone();
+                let result = self.core.write().await.add_request(*request);
+                if result.is_ok() {
+                    if let Some(finished) = finished {
+                        self.waiters.insert(request_id, finished);
+                    }
+                }
+                let _ = added.send(result);
+            }
+            EngineCommand::Abort { request_id, reply } => {
+                let aborted = self.core.write().await.abort_request(&request_id);
+                if let Some(waiter) = self.waiters.remove(&request_id) {
+                    let _ = waiter.send(Err(Error::InferenceError(format!(
+                        "Request {} was aborted",
+                        request_id
+                    ))));
+                }
+                let _ = reply.send(aborted);
+            }
+            EngineCommand::Profile { limit, reply } => {
+                let _ = reply.send(self.profiler.snapshot(limit));
+            }
+            EngineCommand::ResetProfile { reply } => {
+                self.profiler.clear();
+                let _ = reply.send(());
+            }
+        }
+    }
+
+    /// Run one step and hand finished outputs to their waiters. Returns the
+    /// number of outputs produced.
+    ///
+    /// The core is locked to schedule and to apply each batch's outputs,
+    /// but not while the model runs, so callers reading engine state do
+    /// not wait for the forward pass. A batch that fails to execute fails
+    /// only its own requests.
+    async fn step(&mut self) -> Result<usize> {
+        let batches = self.core.write().await.schedule_step().await?;
+        let mut outputs = Vec::new();
+        for mut batch in batches {
+            let executed = batch.execute().await;
+            let request_ids: Vec<RequestId> = batch.request_ids().cloned().collect();
+            let finished = self.core.write().await.finish_batch(batch, executed).await;
+            match finished {
+                Ok(batch_outputs) => outputs.extend(batch_outputs),
+                Err(e) => {
+                    warn!("Engine step error: {}", e);
+                    self.fail_waiters(&request_ids, &e);
+                }
+            }
+        }
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.total_steps += 1;
+            metrics.requests_processed += outputs.len() as u64;
+        }
+
+        let produced = outputs.len();
+        for output in outputs {
+            if !output.is_finished {
+                continue;
+            }
+            if let Some(waiter) = self.waiters.remove(&output.request_id) {
+                let _ = waiter.send(Ok(output));
+            }
+        }
+        self.release_waiters().await;
+        Ok(produced)
+    }
+
+    /// Drop waiters whose request left the core without a final output, and
+    /// abort requests whose caller stopped waiting.
+    async fn release_waiters(&mut self) {
+        if self.waiters.is_empty() {
+            return;
+        }
+        let mut core = self.core.write().await;
+        let done: Vec<RequestId> = self
+            .waiters
+            .iter()
+            .filter(|(id, waiter)| waiter.is_closed() || !core.has_request(id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in done {
+            let Some(waiter) = self.waiters.remove(&id) else {
+                continue;
+            };
+            if waiter.is_closed() {
+                debug!("Aborting request {} nobody is waiting for", id);
+                core.abort_request(&id);
+            } else {
+                let _ = waiter.send(Err(Error::InferenceError(format!(
+                    "Request {} was removed unexpectedly",
+                    id
+                ))));
+            }
+        }
+    }
+
+    /// Fail the waiters of requests a step error aborted
+    fn fail_waiters(&mut self, request_ids: &[RequestId], error: &Error) {
+        for id in request_ids {
+            if let Some(waiter) = self.waiters.remove(id) {
+                let _ = waiter.send(Err(Error::InferenceError(error.to_string())));
+            }
+        }
+    }
+}
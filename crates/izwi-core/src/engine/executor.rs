@@ -3,17 +3,27 @@
 //! The executor abstracts the actual model inference, allowing for different
 //! backends (Python bridge, native Rust, etc.) while providing a unified interface.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
+use super::candidates::{self, Candidate};
 use super::config::EngineCoreConfig;
 use super::request::EngineCoreRequest;
 use super::scheduler::ScheduledRequest;
-use super::types::{AudioOutput, ModelType, TaskType};
+use super::types::{
+    AudioOutput, CandidateScoring, FinishReason, ModelType, TaskType, TokenLogprob,
+};
+use crate::audio::{truncate_at_silence, AudioEncoder, AudioFormat, SilenceConfig};
 use crate::error::{Error, Result};
+use crate::inference::asr_bridge::AsrBridge;
 use crate::inference::python_bridge::PythonBridge;
+use crate::inference::word_error_rate;
+use crate::model::ModelVariant;
+
+/// Model served until another one is swapped in
+pub(crate) const DEFAULT_MODEL: ModelVariant = ModelVariant::Qwen3Tts12Hz06BCustomVoice;
 
 /// Configuration for the model executor.
 #[derive(Debug, Clone)]
@@ -22,7 +32,9 @@ pub struct WorkerConfig {
     pub model_type: ModelType,
     /// Path to models directory
     pub models_dir: PathBuf,
-    /// Device to use (cpu, mps, cuda)
+    /// Directory of the model weights to serve
+    pub model_path: PathBuf,
+    /// Device to use (cpu, mps, cuda:N)
     pub device: String,
     /// Data type (float32, float16, bfloat16)
     pub dtype: String,
@@ -32,12 +44,14 @@ pub struct WorkerConfig {
 
 impl Default for WorkerConfig {
     fn default() -> Self {
+        let models_dir = dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("izwi")
+            .join("models");
         Self {
             model_type: ModelType::Qwen3TTS,
-            models_dir: dirs::data_local_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join("izwi")
-                .join("models"),
+            model_path: models_dir.join(DEFAULT_MODEL.dir_name()),
+            models_dir,
             device: if cfg!(target_os = "macos") {
                 "mps".to_string()
             } else {
@@ -54,11 +68,8 @@ impl From<&EngineCoreConfig> for WorkerConfig {
         Self {
             model_type: config.model_type,
             models_dir: config.models_dir.clone(),
-            device: if config.use_metal {
-                "mps".to_string()
-            } else {
-                "cpu".to_string()
-            },
+            model_path: config.models_dir.join(DEFAULT_MODEL.dir_name()),
+            device: config.compute_device().torch_name(),
             dtype: "float32".to_string(),
             num_threads: config.num_threads,
         }
@@ -80,8 +91,14 @@ pub struct ExecutorOutput {
     pub tokens_generated: usize,
     /// Whether generation is complete
     pub finished: bool,
+    /// Why generation ended early, when not by the model stopping
+    pub finish_reason: Option<FinishReason>,
     /// Error if any
     pub error: Option<String>,
+    /// All best-of-N takes, best first (when requested)
+    pub candidates: Vec<Candidate>,
+    /// Log probabilities of the text tokens (when requested)
+    pub logprobs: Vec<TokenLogprob>,
 }
 
 impl ExecutorOutput {
@@ -93,7 +110,10 @@ impl ExecutorOutput {
             tokens_processed: 0,
             tokens_generated: 0,
             finished: true,
+            finish_reason: None,
             error: Some(error.into()),
+            candidates: Vec::new(),
+            logprobs: Vec::new(),
         }
     }
 }
@@ -113,6 +133,11 @@ pub trait ModelExecutor: Send + Sync {
     /// Initialize the executor (load models, etc.)
     fn initialize(&mut self) -> Result<()>;
 
+    /// Load model weights ahead of the first request.
+    fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Shutdown the executor.
     fn shutdown(&mut self) -> Result<()>;
 }
@@ -121,6 +146,8 @@ pub trait ModelExecutor: Send + Sync {
 pub struct PythonExecutor {
     config: WorkerConfig,
     tts_bridge: Arc<PythonBridge>,
+    /// Transcribes takes for WER scoring
+    asr_bridge: Arc<AsrBridge>,
     initialized: bool,
     /// Maximum concurrent requests to execute
     max_concurrent: usize,
@@ -132,6 +159,7 @@ impl PythonExecutor {
         Self {
             config,
             tts_bridge: Arc::new(PythonBridge::new()),
+            asr_bridge: Arc::new(AsrBridge::new()),
             initialized: false,
             max_concurrent: 4, // Limit concurrent requests to avoid overwhelming the daemon
         }
@@ -142,45 +170,6 @@ impl PythonExecutor {
         self.max_concurrent = max.max(1);
         self
     }
-
-    /// Execute a single TTS request via Qwen3-TTS.
-    fn execute_qwen_tts(&self, request: &EngineCoreRequest) -> Result<ExecutorOutput> {
-        let text = request
-            .text
-            .as_ref()
-            .ok_or_else(|| Error::InvalidInput("TTS requires text".into()))?;
-
-        // Get model path - for now use a default
-        let model_path = self
-            .config
-            .models_dir
-            .join("Qwen3-TTS-12Hz-0.6B-CustomVoice");
-
-        let speaker = request.params.speaker.as_deref();
-        let voice_desc = request.voice_description.as_deref();
-        let ref_audio = request.reference_audio.clone();
-        let ref_text = request.reference_text.clone();
-
-        let (samples, sample_rate) = self.tts_bridge.generate_with_clone(
-            &model_path,
-            text,
-            speaker,
-            Some("Auto"),
-            voice_desc,
-            ref_audio,
-            ref_text,
-        )?;
-
-        Ok(ExecutorOutput {
-            request_id: request.id.clone(),
-            audio: Some(AudioOutput::new(samples, sample_rate)),
-            text: None,
-            tokens_processed: 0,
-            tokens_generated: 0,
-            finished: true,
-            error: None,
-        })
-    }
 }
 
 impl ModelExecutor for PythonExecutor {
@@ -199,25 +188,12 @@ impl ModelExecutor for PythonExecutor {
 
         // For a single request, execute directly without async overhead
         if requests.len() == 1 {
-            let request = requests[0];
-            let result = match (&request.model_type, &request.task_type) {
-                (ModelType::Qwen3TTS, TaskType::TTS) => self.execute_qwen_tts(request),
-                _ => Err(Error::InferenceError(format!(
-                    "Unsupported model/task combination: {:?}/{:?}",
-                    request.model_type, request.task_type
-                ))),
-            };
-
-            return match result {
-                Ok(output) => Ok(vec![output]),
-                Err(e) => {
-                    warn!("Execution error for request {}: {}", request.id, e);
-                    Ok(vec![ExecutorOutput::error(
-                        request.id.clone(),
-                        e.to_string(),
-                    )])
-                }
-            };
+            return Ok(vec![execute_single_task(
+                &self.tts_bridge,
+                &self.asr_bridge,
+                &self.config.model_path,
+                ExecutionTask::from(requests[0]),
+            )]);
         }
 
         // For multiple requests, execute concurrently in batches
@@ -228,27 +204,16 @@ impl ModelExecutor for PythonExecutor {
         );
 
         // Clone data needed for execution
-        let request_data: Vec<_> = requests
-            .iter()
-            .map(|r| ExecutionTask {
-                id: r.id.clone(),
-                model_type: r.model_type,
-                task_type: r.task_type,
-                text: r.text.clone(),
-                speaker: r.params.speaker.clone(),
-                voice_description: r.voice_description.clone(),
-                reference_audio: r.reference_audio.clone(),
-                reference_text: r.reference_text.clone(),
-            })
-            .collect();
+        let request_data: Vec<_> = requests.iter().map(|r| ExecutionTask::from(*r)).collect();
 
         let bridge = self.tts_bridge.clone();
-        let models_dir = self.config.models_dir.clone();
+        let asr_bridge = self.asr_bridge.clone();
+        let model_path = self.config.model_path.clone();
 
         // Execute all tasks concurrently using rayon for CPU-bound work
         let outputs: Vec<ExecutorOutput> = request_data
             .into_iter()
-            .map(|task| execute_single_task(&bridge, &models_dir, task))
+            .map(|task| execute_single_task(&bridge, &asr_bridge, &model_path, task))
             .collect();
 
         Ok(outputs)
@@ -258,6 +223,12 @@ impl ModelExecutor for PythonExecutor {
         self.initialized
     }
 
+    fn warm_up(&self) -> Result<()> {
+        info!("Preloading model from {:?}", self.config.model_path);
+        self.tts_bridge
+            .preload_model_blocking(&self.config.model_path)
+    }
+
     fn initialize(&mut self) -> Result<()> {
         info!(
             "Initializing Python executor (max_concurrent: {})",
@@ -295,12 +266,36 @@ struct ExecutionTask {
     voice_description: Option<String>,
     reference_audio: Option<String>,
     reference_text: Option<String>,
+    n_candidates: usize,
+    candidate_scoring: CandidateScoring,
+    return_candidates: bool,
+    stop_on_silence_ms: u32,
+}
+
+impl From<&EngineCoreRequest> for ExecutionTask {
+    fn from(request: &EngineCoreRequest) -> Self {
+        Self {
+            id: request.id.clone(),
+            model_type: request.model_type,
+            task_type: request.task_type,
+            text: request.text.clone(),
+            speaker: request.params.speaker.clone(),
+            voice_description: request.voice_description.clone(),
+            reference_audio: request.reference_audio.clone(),
+            reference_text: request.reference_text.clone(),
+            n_candidates: request.params.n_candidates,
+            candidate_scoring: request.params.candidate_scoring,
+            return_candidates: request.params.return_candidates,
+            stop_on_silence_ms: request.params.stop_on_silence_ms,
+        }
+    }
 }
 
 /// Execute a single task using the Python bridge.
 fn execute_single_task(
     bridge: &PythonBridge,
-    models_dir: &PathBuf,
+    asr_bridge: &AsrBridge,
+    model_path: &Path,
     task: ExecutionTask,
 ) -> ExecutorOutput {
     match (task.model_type, task.task_type) {
@@ -312,25 +307,24 @@ fn execute_single_task(
                 }
             };
 
-            let model_path = models_dir.join("Qwen3-TTS-12Hz-0.6B-CustomVoice");
-
-            match bridge.generate_with_clone(
-                &model_path,
-                text,
-                task.speaker.as_deref(),
-                Some("Auto"),
-                task.voice_description.as_deref(),
-                task.reference_audio.clone(),
-                task.reference_text.clone(),
-            ) {
-                Ok((samples, sample_rate)) => ExecutorOutput {
+            match generate_candidates(bridge, asr_bridge, model_path, &task, text) {
+                Ok(candidates) => ExecutorOutput {
+                    audio: Some(candidates[0].audio.clone()),
+                    finish_reason: candidates[0]
+                        .stopped_on_silence
+                        .then_some(FinishReason::Silence),
+                    candidates: if task.return_candidates {
+                        candidates
+                    } else {
+                        Vec::new()
+                    },
                     request_id: task.id,
-                    audio: Some(AudioOutput::new(samples, sample_rate)),
                     text: None,
                     tokens_processed: 0,
                     tokens_generated: 0,
                     finished: true,
                     error: None,
+                    logprobs: Vec::new(),
                 },
                 Err(e) => {
                     warn!("TTS execution error for {}: {}", task.id, e);
@@ -348,7 +342,76 @@ fn execute_single_task(
     }
 }
 
+/// Synthesize the task's takes and rank them best first.
+fn generate_candidates(
+    bridge: &PythonBridge,
+    asr_bridge: &AsrBridge,
+    model_path: &Path,
+    task: &ExecutionTask,
+    text: &str,
+) -> Result<Vec<Candidate>> {
+    let takes = task.n_candidates.max(1);
+    let mut candidates = Vec::with_capacity(takes);
+    for index in 0..takes {
+        let (mut samples, sample_rate) = bridge.generate_with_clone_blocking(
+            model_path,
+            text,
+            task.speaker.as_deref(),
+            Some("Auto"),
+            task.voice_description.as_deref(),
+            task.reference_audio.clone(),
+            task.reference_text.clone(),
+        )?;
+        // The daemon returns whole takes, so dead air is cut afterwards
+        let stopped_on_silence = task.stop_on_silence_ms > 0
+            && truncate_at_silence(
+                &mut samples,
+                sample_rate,
+                task.stop_on_silence_ms,
+                &SilenceConfig::default(),
+            );
+        let mut candidate = Candidate::new(index, AudioOutput::new(samples, sample_rate));
+        candidate.stopped_on_silence = stopped_on_silence;
+        candidates.push(candidate);
+    }
+    if takes == 1 {
+        return Ok(candidates);
+    }
+
+    if task.candidate_scoring == CandidateScoring::Wer {
+        for candidate in &mut candidates {
+            let transcript = transcribe(asr_bridge, &candidate.audio)?;
+            candidate.wer = Some(word_error_rate(text, &transcript));
+            candidate.transcript = Some(transcript);
+        }
+    }
+    candidates::rank(&mut candidates, task.candidate_scoring);
+    debug!(
+        "Request {}: take {} of {} chosen by {:?}",
+        task.id, candidates[0].index, takes, task.candidate_scoring
+    );
+    Ok(candidates)
+}
+
+/// Transcribe a take through the ASR daemon.
+fn transcribe(asr_bridge: &AsrBridge, audio: &AudioOutput) -> Result<String> {
+    use base64::Engine;
+
+    asr_bridge.ensure_daemon_running()?;
+    let wav = AudioEncoder::new(audio.sample_rate, 1).encode(&audio.samples, AudioFormat::Wav)?;
+    let audio_b64 = base64::engine::general_purpose::STANDARD.encode(wav);
+    let response = asr_bridge.transcribe_blocking(&audio_b64, None, None)?;
+    if let Some(err) = response.error {
+        return Err(Error::InferenceError(format!(
+            "Candidate transcription failed: {}",
+            err
+        )));
+    }
+    Ok(response.transcription.unwrap_or_default())
+}
+
 /// Unified executor that wraps a model executor implementation.
+#[derive(Clone)]
 pub struct UnifiedExecutor {
     inner: Arc<RwLock<Box<dyn ModelExecutor>>>,
 }
@@ -383,6 +446,12 @@ impl UnifiedExecutor {
         executor.initialize()
     }
 
+    /// Load model weights.
+    pub async fn warm_up(&self) -> Result<()> {
+        let executor = self.inner.read().await;
+        executor.warm_up()
+    }
+
     /// Shutdown.
     pub async fn shutdown(&self) -> Result<()> {
         let mut executor = self.inner.write().await;
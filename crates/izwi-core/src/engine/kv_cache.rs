@@ -5,12 +5,64 @@
 //! - Block-based memory allocation
 //! - Efficient free block tracking with doubly-linked list
 //! - Sequence-to-block mapping
+//! - Copy-on-write forking of shared prompt blocks
 //! - Memory usage tracking
+//! - Per-device partitions for pipeline-parallel models
+//! - Sliding-window eviction of a sequence's oldest blocks
 
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
 use tracing::debug;
 
+use super::clock::{self, SharedClock};
+use super::pipeline::{PipelinePlan, PipelineStage};
 use super::types::{BlockId, RequestId};
+use crate::error::{Error, Result};
+
+/// Element type of stored keys and values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KVCacheDtype {
+    #[default]
+    Float16,
+    Bfloat16,
+    Float32,
+    /// 8-bit integers with one scale per block, half the size of float16
+    Int8,
+}
+
+impl KVCacheDtype {
+    /// Bytes per stored element
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Float16 | Self::Bfloat16 => 2,
+            Self::Float32 => 4,
+            Self::Int8 => 1,
+        }
+    }
+
+    pub fn is_quantized(self) -> bool {
+        self == Self::Int8
+    }
+}
+
+impl FromStr for KVCacheDtype {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "float16" => Ok(Self::Float16),
+            "bfloat16" => Ok(Self::Bfloat16),
+            "float32" => Ok(Self::Float32),
+            "int8" => Ok(Self::Int8),
+            other => Err(Error::ConfigError(format!(
+                "engine.kv_cache_dtype must be float16, bfloat16, float32 or int8, got '{}'",
+                other
+            ))),
+        }
+    }
+}
 
 /// Configuration for the KV cache.
 #[derive(Debug, Clone)]
@@ -25,8 +77,8 @@ pub struct KVCacheConfig {
     pub block_size: usize,
     /// Maximum number of blocks to allocate
     pub max_blocks: usize,
-    /// Data type size in bytes (2 for float16, 4 for float32)
-    pub dtype_bytes: usize,
+    /// Element type of stored keys and values
+    pub dtype: KVCacheDtype,
 }
 
 impl Default for KVCacheConfig {
@@ -37,7 +89,7 @@ impl Default for KVCacheConfig {
             head_dim: 64,
             block_size: 16,
             max_blocks: 1024,
-            dtype_bytes: 2, // float16
+            dtype: KVCacheDtype::Float16,
         }
     }
 }
@@ -46,7 +98,18 @@ impl KVCacheConfig {
     /// Calculate memory per block in bytes.
     pub fn block_memory_bytes(&self) -> usize {
         // 2 (K+V) * block_size * num_heads * head_dim * dtype_bytes * num_layers
-        2 * self.block_size * self.num_heads * self.head_dim * self.dtype_bytes * self.num_layers
+        let elements = 2
+            * self.block_size
+            * self.num_heads
+            * self.head_dim
+            * self.dtype.bytes()
+            * self.num_layers;
+        if self.dtype.is_quantized() {
+            // One f32 scale per block for each layer's keys and values
+            elements + 2 * self.num_layers * std::mem::size_of::<f32>()
+        } else {
+            elements
+        }
     }
 
     /// Calculate total memory for all blocks.
@@ -54,6 +117,11 @@ impl KVCacheConfig {
         self.block_memory_bytes() * self.max_blocks
     }
 
+    /// Number of blocks that fit in `budget_bytes`.
+    pub fn blocks_for_budget(&self, budget_bytes: u64) -> usize {
+        (budget_bytes / self.block_memory_bytes().max(1) as u64) as usize
+    }
+
     /// Calculate number of blocks needed for a sequence length.
     pub fn blocks_for_tokens(&self, num_tokens: usize) -> usize {
         (num_tokens + self.block_size - 1) / self.block_size
@@ -155,6 +223,13 @@ impl BlockAllocator {
         }
     }
 
+    /// Add a reference to an allocated block so it is shared.
+    pub fn retain(&mut self, block_id: BlockId) {
+        if let Some(block) = self.blocks.get_mut(block_id) {
+            block.ref_count += 1;
+        }
+    }
+
     /// Free multiple blocks.
     pub fn free_blocks(&mut self, block_ids: &[BlockId]) {
         for &id in block_ids {
@@ -198,11 +273,15 @@ pub struct KVCacheManager {
     config: KVCacheConfig,
     /// Block allocator
     allocator: BlockAllocator,
+    /// Devices holding each block's layers when the model is pipelined
+    plan: Option<PipelinePlan>,
     /// Mapping from request ID to allocated block IDs
     request_blocks: HashMap<RequestId, Vec<BlockId>>,
     /// Block table: maps (request_id, block_index) to physical block ID
     /// This enables non-contiguous block allocation
     block_table: HashMap<RequestId, Vec<BlockId>>,
+    /// Blocks evicted from each sequence's sliding window
+    evicted: HashMap<RequestId, usize>,
 }
 
 impl KVCacheManager {
@@ -213,11 +292,27 @@ impl KVCacheManager {
         Self {
             config,
             allocator,
+            plan: None,
             request_blocks: HashMap::new(),
             block_table: HashMap::new(),
+            evicted: HashMap::new(),
         }
     }
 
+    /// Partition every block's layers over the stages of `plan`.
+    ///
+    /// Blocks stay logical: allocating one reserves its slice on every
+    /// stage, so the stats report usage per device.
+    pub fn with_pipeline(mut self, plan: PipelinePlan) -> Self {
+        self.plan = plan.is_pipelined().then_some(plan);
+        self
+    }
+
+    /// Pipeline stages the cache is partitioned over, if any
+    pub fn pipeline(&self) -> Option<&PipelinePlan> {
+        self.plan.as_ref()
+    }
+
     /// Check if n blocks can be allocated.
     pub fn can_allocate(&self, n: usize) -> bool {
         self.allocator.can_allocate(n)
@@ -264,6 +359,115 @@ impl KVCacheManager {
             self.allocator.free_blocks(&block_ids);
         }
         self.block_table.remove(request_id);
+        self.evicted.remove(request_id);
+    }
+
+    /// Free up to `count` of the request's oldest blocks after its first
+    /// `keep_front` (attention sinks), for sliding-window attention. The
+    /// last block, still being written, is always kept.
+    ///
+    /// The remaining blocks keep their order; [`evicted_blocks`] counts the
+    /// gap so token positions can be mapped to them. Returns the number of
+    /// blocks freed.
+    ///
+    /// [`evicted_blocks`]: Self::evicted_blocks
+    pub fn evict(&mut self, request_id: &RequestId, keep_front: usize, count: usize) -> usize {
+        let Some(blocks) = self.request_blocks.get_mut(request_id) else {
+            return 0;
+        };
+        let count = count.min(blocks.len().saturating_sub(keep_front + 1));
+        if count == 0 {
+            return 0;
+        }
+        let evicted: Vec<BlockId> = blocks.drain(keep_front..keep_front + count).collect();
+        if let Some(table) = self.block_table.get_mut(request_id) {
+            table.drain(keep_front..keep_front + count);
+        }
+        self.allocator.free_blocks(&evicted);
+        *self.evicted.entry(request_id.clone()).or_insert(0) += count;
+        debug!(
+            "Evicted {} blocks from the window of request {}",
+            count, request_id
+        );
+        count
+    }
+
+    /// Blocks evicted from the request's sliding window so far
+    pub fn evicted_blocks(&self, request_id: &RequestId) -> usize {
+        self.evicted.get(request_id).copied().unwrap_or(0)
+    }
+
+    /// Fork `parent` into `child`, sharing all of the parent's blocks.
+    ///
+    /// No blocks are copied: each shared block gains a reference and is
+    /// only copied once one of the sequences writes to it (see
+    /// [`copy_on_write`](Self::copy_on_write)). Returns false if the parent
+    /// has no blocks or the child already has some.
+    pub fn fork(&mut self, parent: &RequestId, child: &RequestId) -> bool {
+        if self.request_blocks.contains_key(child) {
+            return false;
+        }
+        let Some(block_ids) = self.request_blocks.get(parent).cloned() else {
+            return false;
+        };
+        for &id in &block_ids {
+            self.allocator.retain(id);
+        }
+        debug!(
+            "Forked request {} into {} sharing {} blocks",
+            parent,
+            child,
+            block_ids.len()
+        );
+        self.block_table.insert(child.clone(), block_ids.clone());
+        self.request_blocks.insert(child.clone(), block_ids);
+        if let Some(&evicted) = self.evicted.get(parent) {
+            self.evicted.insert(child.clone(), evicted);
+        }
+        true
+    }
+
+    /// Make the request's `block_index`-th block writable.
+    ///
+    /// A block shared with other sequences is replaced in this request's
+    /// block table by a fresh copy; the caller must copy the KV data from
+    /// `src` to `dst`. Returns `None` if the request has no such block or no
+    /// free block is left for the copy.
+    pub fn copy_on_write(
+        &mut self,
+        request_id: &RequestId,
+        block_index: usize,
+    ) -> Option<BlockWrite> {
+        let src = *self.request_blocks.get(request_id)?.get(block_index)?;
+        let block = self.allocator.get_block(src)?;
+        if block.ref_count <= 1 {
+            return Some(BlockWrite::InPlace(src));
+        }
+        let num_tokens = block.num_tokens;
+
+        let dst = self.allocator.allocate(1)?[0];
+        self.allocator.blocks[dst].num_tokens = num_tokens;
+        self.allocator.free(src);
+        for table in [&mut self.request_blocks, &mut self.block_table] {
+            if let Some(slot) = table
+                .get_mut(request_id)
+                .and_then(|blocks| blocks.get_mut(block_index))
+            {
+                *slot = dst;
+            }
+        }
+        debug!(
+            "Copied shared block {} to {} for request {}",
+            src, dst, request_id
+        );
+        Some(BlockWrite::Copied { src, dst })
+    }
+
+    /// Number of sequences referencing a block.
+    pub fn ref_count(&self, block_id: BlockId) -> usize {
+        self.allocator
+            .get_block(block_id)
+            .map_or(0, |block| block.ref_count)
     }
 
     /// Get blocks allocated to a request.
@@ -290,13 +494,27 @@ impl KVCacheManager {
 
     /// Get statistics.
     pub fn stats(&self) -> KVCacheStats {
+        let memory_used_bytes = self.allocator.memory_used_bytes();
+        let memory_capacity_bytes = self.allocator.memory_capacity_bytes();
+        let stages = self.plan.as_ref().map_or_else(Vec::new, |plan| {
+            plan.stages()
+                .iter()
+                .map(|stage| KVStageStats {
+                    stage: stage.clone(),
+                    memory_used_bytes: plan.stage_share(stage, memory_used_bytes as u64) as usize,
+                    memory_capacity_bytes: plan.stage_share(stage, memory_capacity_bytes as u64)
+                        as usize,
+                })
+                .collect()
+        });
         KVCacheStats {
             total_blocks: self.config.max_blocks,
             allocated_blocks: self.allocator.num_allocated(),
             free_blocks: self.allocator.num_free(),
             num_sequences: self.request_blocks.len(),
-            memory_used_bytes: self.allocator.memory_used_bytes(),
-            memory_capacity_bytes: self.allocator.memory_capacity_bytes(),
+            memory_used_bytes,
+            memory_capacity_bytes,
+            stages,
         }
     }
 
@@ -306,8 +524,17 @@ impl KVCacheManager {
     }
 }
 
+/// Block to write to after [`KVCacheManager::copy_on_write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockWrite {
+    /// The block was not shared and can be written directly
+    InPlace(BlockId),
+    /// The shared block `src` was copied to `dst`, now owned by the request
+    Copied { src: BlockId, dst: BlockId },
+}
+
 /// KV cache statistics.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct KVCacheStats {
     pub total_blocks: usize,
     pub allocated_blocks: usize,
@@ -315,6 +542,18 @@ pub struct KVCacheStats {
     pub num_sequences: usize,
     pub memory_used_bytes: usize,
     pub memory_capacity_bytes: usize,
+    /// Memory per device when the model is pipelined
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<KVStageStats>,
+}
+
+/// KV cache memory held on one pipeline stage's device.
+#[derive(Debug, Clone, Serialize)]
+pub struct KVStageStats {
+    #[serde(flatten)]
+    pub stage: PipelineStage,
+    pub memory_used_bytes: usize,
+    pub memory_capacity_bytes: usize,
 }
 
 impl KVCacheStats {
@@ -391,8 +630,8 @@ pub struct StreamingSequence {
 }
 
 impl StreamingSequence {
-    /// Create a new streaming sequence.
-    pub fn new(request_id: String) -> Self {
+    /// Create a new streaming sequence started at `now`.
+    pub fn new(request_id: String, now: std::time::Instant) -> Self {
         Self {
             request_id,
             token_count: 0,
@@ -400,7 +639,7 @@ impl StreamingSequence {
             window_start: 0,
             block_ids: Vec::new(),
             in_prefill: true,
-            last_update: std::time::Instant::now(),
+            last_update: now,
         }
     }
 
@@ -433,6 +672,8 @@ pub struct StreamingKVCacheManager {
     /// Statistics
     total_tokens_processed: u64,
     total_evictions: u64,
+    /// Time source for sequence updates
+    clock: SharedClock,
 }
 
 impl StreamingKVCacheManager {
@@ -447,16 +688,23 @@ impl StreamingKVCacheManager {
             block_table: HashMap::new(),
             total_tokens_processed: 0,
             total_evictions: 0,
+            clock: clock::system_clock(),
         }
     }
 
+    /// Use `clock` for sequence update times.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Start a new streaming sequence.
     pub fn start_sequence(&mut self, request_id: &str) -> bool {
         if self.sequences.contains_key(request_id) {
             return false;
         }
 
-        let sequence = StreamingSequence::new(request_id.to_string());
+        let sequence = StreamingSequence::new(request_id.to_string(), self.clock.now());
         self.sequences.insert(request_id.to_string(), sequence);
         self.block_table.insert(request_id.to_string(), Vec::new());
         true
@@ -470,11 +718,12 @@ impl StreamingKVCacheManager {
     /// - Eviction of old tokens
     pub fn append_tokens(&mut self, request_id: &str, num_tokens: usize) -> Option<Vec<BlockId>> {
         let config = self.config.clone();
+        let now = self.clock.now();
 
         // Check if sequence exists and if we need eviction
         let needs_eviction = {
             let sequence = self.sequences.get_mut(request_id)?;
-            sequence.last_update = std::time::Instant::now();
+            sequence.last_update = now;
             sequence.needs_window_slide(config.sliding_window_tokens) && config.enable_eviction
         };
 
@@ -647,6 +896,7 @@ impl StreamingKVCacheManager {
                 num_sequences: self.sequences.len(),
                 memory_used_bytes: self.allocator.memory_used_bytes(),
                 memory_capacity_bytes: self.allocator.memory_capacity_bytes(),
+                stages: Vec::new(),
             },
             total_tokens_processed: self.total_tokens_processed,
             total_evictions: self.total_evictions,
@@ -725,4 +975,107 @@ mod tests {
         assert_eq!(stats.allocated_blocks, 3);
         assert_eq!(stats.num_sequences, 1);
     }
+
+    #[test]
+    fn test_fork_copy_on_write() {
+        let config = KVCacheConfig {
+            max_blocks: 8,
+            ..Default::default()
+        };
+        let mut manager = KVCacheManager::new(config);
+        let (parent, child) = ("parent".to_string(), "child".to_string());
+
+        let shared = manager.allocate(&parent, 3);
+        assert!(manager.fork(&parent, &child));
+        assert!(!manager.fork(&parent, &child));
+        assert_eq!(manager.get_block_table(&child), Some(shared.as_slice()));
+        assert_eq!(manager.ref_count(shared[2]), 2);
+        assert_eq!(manager.stats().allocated_blocks, 3);
+
+        // Only the diverging block is copied
+        let write = manager.copy_on_write(&child, 2).unwrap();
+        let BlockWrite::Copied { src, dst } = write else {
+            panic!("shared block written in place");
+        };
+        assert_eq!(src, shared[2]);
+        assert_eq!(manager.get_block_table(&child).unwrap()[..2], shared[..2]);
+        assert_eq!(manager.get_block_table(&child).unwrap()[2], dst);
+        assert_eq!(manager.ref_count(src), 1);
+        assert_eq!(
+            manager.copy_on_write(&parent, 2),
+            Some(BlockWrite::InPlace(src))
+        );
+        assert_eq!(manager.stats().allocated_blocks, 4);
+
+        // Shared blocks survive until the last sequence frees them
+        manager.free(&parent);
+        assert_eq!(manager.stats().allocated_blocks, 3);
+        manager.free(&child);
+        assert_eq!(manager.stats().allocated_blocks, 0);
+    }
+
+    #[test]
+    fn test_blocks_for_budget() {
+        let config = KVCacheConfig::default();
+        let block = config.block_memory_bytes() as u64;
+        assert_eq!(config.blocks_for_budget(block * 10 + block / 2), 10);
+        assert_eq!(config.blocks_for_budget(block - 1), 0);
+    }
+
+    #[test]
+    fn test_pipeline_stage_stats() {
+        use crate::device::Device;
+
+        let config = KVCacheConfig {
+            max_blocks: 8,
+            ..Default::default()
+        };
+        let block = config.block_memory_bytes();
+        let plan = PipelinePlan::split(&[Device::Cuda(0), Device::Cuda(1)], &[3, 1], 24);
+        let mut manager = KVCacheManager::new(config).with_pipeline(plan);
+        manager.allocate(&"req".to_string(), 4);
+
+        let stats = manager.stats();
+        assert_eq!(stats.stages.len(), 2);
+        assert_eq!(stats.stages[0].stage.layers, 0..18);
+        assert_eq!(stats.stages[0].memory_used_bytes, block * 4 * 18 / 24);
+        assert_eq!(stats.stages[1].memory_capacity_bytes, block * 8 * 6 / 24);
+
+        let single = KVCacheManager::new(KVCacheConfig::default())
+            .with_pipeline(PipelinePlan::single(Device::Cpu, 24));
+        assert!(single.pipeline().is_none());
+        assert!(single.stats().stages.is_empty());
+    }
+
+    #[test]
+    fn test_sliding_window_eviction() {
+        let config = KVCacheConfig {
+            max_blocks: 8,
+            ..Default::default()
+        };
+        let mut manager = KVCacheManager::new(config);
+        let (request, session) = ("req".to_string(), "session".to_string());
+        let blocks = manager.allocate(&request, 6);
+
+        // One sink block stays, the last block is never evicted
+        assert_eq!(manager.evict(&request, 1, 2), 2);
+        assert_eq!(
+            manager.get_blocks(&request).unwrap(),
+            &[blocks[0], blocks[3], blocks[4], blocks[5]]
+        );
+        assert_eq!(manager.evict(&request, 1, 10), 2);
+        assert_eq!(
+            manager.get_blocks(&request).unwrap(),
+            &[blocks[0], blocks[5]]
+        );
+        assert_eq!(manager.evicted_blocks(&request), 4);
+        assert_eq!(manager.stats().free_blocks, 6);
+
+        // Forks inherit the gap; freeing forgets it
+        manager.fork(&request, &session);
+        assert_eq!(manager.evicted_blocks(&session), 4);
+        manager.free(&request);
+        assert_eq!(manager.evicted_blocks(&request), 0);
+        assert_eq!(manager.evict(&"missing".to_string(), 0, 1), 0);
+    }
 }
@@ -0,0 +1,373 @@
+//! Per-request latency breakdowns.
+//!
+//! The engine core and the serving layer record where a request spent its
+//! time (queueing, prefill, decode steps, codec, encoding, network flushes)
+//! and which scheduler limits held it back. Traces of recent requests are
+//! kept so a slow request can be explained after it finished.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::types::RequestId;
+
+/// Traces kept by default, including in-flight requests
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Stage of request processing being timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyPhase {
+    /// Prompt processing (summed over chunked prefill steps)
+    Prefill,
+    /// One decode step or streamed chunk
+    Decode,
+    /// Turning model output into audio
+    Codec,
+    /// Encoding audio into the response format
+    Encode,
+    /// Handing one response chunk to the network
+    Flush,
+}
+
+/// Scheduler limit that kept a request from running in a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelayReason {
+    /// Step token budget was used up
+    TokenBudget,
+    /// Batch was already full
+    BatchSize,
+    /// Not enough free KV cache blocks
+    KvCache,
+    /// Admission paused while a model swap drains
+    ModelSwap,
+    /// Streaming consumer has not caught up
+    Backpressure,
+}
+
+impl fmt::Display for DelayReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TokenBudget => "token budget",
+            Self::BatchSize => "batch size",
+            Self::KvCache => "KV cache",
+            Self::ModelSwap => "model swap",
+            Self::Backpressure => "stream backpressure",
+        })
+    }
+}
+
+/// Timings collected for one request.
+#[derive(Debug, Clone)]
+struct LatencyTrace {
+    arrival: Instant,
+    scheduled_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    prefill: Duration,
+    decode_chunks: Vec<Duration>,
+    codec: Duration,
+    encode: Duration,
+    flushes: Vec<Duration>,
+    delays: BTreeMap<DelayReason, u32>,
+}
+
+impl LatencyTrace {
+    fn new(arrival: Instant) -> Self {
+        Self {
+            arrival,
+            scheduled_at: None,
+            finished_at: None,
+            prefill: Duration::ZERO,
+            decode_chunks: Vec::new(),
+            codec: Duration::ZERO,
+            encode: Duration::ZERO,
+            flushes: Vec::new(),
+            delays: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrackerInner {
+    traces: HashMap<RequestId, LatencyTrace>,
+    order: VecDeque<RequestId>,
+}
+
+/// Bounded store of latency traces keyed by request ID.
+///
+/// The oldest traces are dropped once the capacity is reached.
+#[derive(Debug)]
+pub struct LatencyTracker {
+    capacity: usize,
+    inner: Mutex<TrackerInner>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl LatencyTracker {
+    /// Create a tracker keeping at most `capacity` traces
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(TrackerInner::default()),
+        }
+    }
+
+    fn with_trace(&self, request_id: &str, f: impl FnOnce(&mut LatencyTrace)) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(trace) = inner.traces.get_mut(request_id) {
+            f(trace);
+        }
+    }
+
+    /// Start tracing a request that arrived at `arrival`
+    pub fn start(&self, request_id: &str, arrival: Instant) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.traces.contains_key(request_id) {
+            return;
+        }
+        while inner.order.len() >= self.capacity {
+            match inner.order.pop_front() {
+                Some(oldest) => {
+                    inner.traces.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        inner.order.push_back(request_id.to_string());
+        inner
+            .traces
+            .insert(request_id.to_string(), LatencyTrace::new(arrival));
+    }
+
+    /// Mark the end of queueing (first time only)
+    pub fn scheduled(&self, request_id: &str, now: Instant) {
+        self.with_trace(request_id, |trace| {
+            trace.scheduled_at.get_or_insert(now);
+        });
+    }
+
+    /// Record time spent in a phase
+    pub fn record(&self, request_id: &str, phase: LatencyPhase, duration: Duration) {
+        self.with_trace(request_id, |trace| match phase {
+            LatencyPhase::Prefill => trace.prefill += duration,
+            LatencyPhase::Decode => trace.decode_chunks.push(duration),
+            LatencyPhase::Codec => trace.codec += duration,
+            LatencyPhase::Encode => trace.encode += duration,
+            LatencyPhase::Flush => trace.flushes.push(duration),
+        });
+    }
+
+    /// Record that a limit kept the request out of a step
+    pub fn delayed(&self, request_id: &str, reason: DelayReason) {
+        self.with_trace(request_id, |trace| {
+            *trace.delays.entry(reason).or_default() += 1;
+        });
+    }
+
+    /// Mark the request as done
+    pub fn finish(&self, request_id: &str, now: Instant) {
+        self.with_trace(request_id, |trace| {
+            trace.finished_at.get_or_insert(now);
+        });
+    }
+
+    /// Build the report for a request, measuring in-flight requests up to `now`
+    pub fn report(&self, request_id: &str, now: Instant) -> Option<LatencyReport> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let trace = inner.traces.get(request_id)?;
+        Some(LatencyReport::new(request_id, trace, now))
+    }
+}
+
+/// Steps a request was held back by one limit.
+#[derive(Debug, Clone, Serialize)]
+pub struct DelayCount {
+    pub reason: DelayReason,
+    pub steps: u32,
+}
+
+/// Where a request spent its time, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    pub request_id: RequestId,
+    pub finished: bool,
+    pub total_ms: f64,
+    pub queue_ms: f64,
+    pub prefill_ms: f64,
+    pub decode_ms: f64,
+    pub decode_chunk_ms: Vec<f64>,
+    pub codec_ms: f64,
+    pub encode_ms: f64,
+    pub flush_ms: f64,
+    pub flush_chunk_ms: Vec<f64>,
+    /// Scheduler limits that delayed the request
+    pub delayed_by: Vec<DelayCount>,
+    /// Phase with the largest share of the total
+    pub dominant_phase: &'static str,
+    /// One-line human-readable explanation
+    pub summary: String,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl LatencyReport {
+    fn new(request_id: &str, trace: &LatencyTrace, now: Instant) -> Self {
+        let end = trace.finished_at.unwrap_or(now);
+        let queue_end = trace.scheduled_at.unwrap_or(end);
+        let decode_chunk_ms: Vec<f64> = trace.decode_chunks.iter().copied().map(ms).collect();
+        let flush_chunk_ms: Vec<f64> = trace.flushes.iter().copied().map(ms).collect();
+
+        let mut report = Self {
+            request_id: request_id.to_string(),
+            finished: trace.finished_at.is_some(),
+            total_ms: ms(end.saturating_duration_since(trace.arrival)),
+            queue_ms: ms(queue_end.saturating_duration_since(trace.arrival)),
+            prefill_ms: ms(trace.prefill),
+            decode_ms: decode_chunk_ms.iter().sum(),
+            decode_chunk_ms,
+            codec_ms: ms(trace.codec),
+            encode_ms: ms(trace.encode),
+            flush_ms: flush_chunk_ms.iter().sum(),
+            flush_chunk_ms,
+            delayed_by: trace
+                .delays
+                .iter()
+                .map(|(&reason, &steps)| DelayCount { reason, steps })
+                .collect(),
+            dominant_phase: "queue",
+            summary: String::new(),
+        };
+        report.dominant_phase = report
+            .phases()
+            .into_iter()
+            .fold(
+                ("queue", f64::MIN),
+                |best, p| if p.1 > best.1 { p } else { best },
+            )
+            .0;
+        report.summary = report.summarize();
+        report
+    }
+
+    fn phases(&self) -> [(&'static str, f64); 6] {
+        [
+            ("queue", self.queue_ms),
+            ("prefill", self.prefill_ms),
+            ("decode", self.decode_ms),
+            ("codec", self.codec_ms),
+            ("encode", self.encode_ms),
+            ("flush", self.flush_ms),
+        ]
+    }
+
+    fn summarize(&self) -> String {
+        let share = |value: f64| {
+            if self.total_ms > 0.0 {
+                value / self.total_ms * 100.0
+            } else {
+                0.0
+            }
+        };
+        let dominant = self
+            .phases()
+            .iter()
+            .find(|(name, _)| *name == self.dominant_phase)
+            .map(|(_, value)| *value)
+            .unwrap_or_default();
+
+        let mut summary = format!(
+            "{:.1} ms total, mostly {} ({:.0}%)",
+            self.total_ms,
+            self.dominant_phase,
+            share(dominant)
+        );
+        if let Some(worst) = self.delayed_by.iter().max_by_key(|d| d.steps) {
+            summary.push_str(&format!(
+                "; held back {} step(s) by {}",
+                worst.steps, worst.reason
+            ));
+        }
+        if !self.finished {
+            summary.push_str(" (still running)");
+        }
+        summary
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Request {}: {}", self.request_id, self.summary)?;
+        writeln!(f, "  queue    {:>10.1} ms", self.queue_ms)?;
+        writeln!(f, "  prefill  {:>10.1} ms", self.prefill_ms)?;
+        let slowest = self.decode_chunk_ms.iter().copied().fold(0.0, f64::max);
+        writeln!(
+            f,
+            "  decode   {:>10.1} ms over {} chunk(s), slowest {:.1} ms",
+            self.decode_ms,
+            self.decode_chunk_ms.len(),
+            slowest
+        )?;
+        writeln!(f, "  codec    {:>10.1} ms", self.codec_ms)?;
+        writeln!(f, "  encode   {:>10.1} ms", self.encode_ms)?;
+        writeln!(
+            f,
+            "  flush    {:>10.1} ms over {} write(s)",
+            self.flush_ms,
+            self.flush_chunk_ms.len()
+        )?;
+        for delay in &self.delayed_by {
+            writeln!(f, "  delayed  {} step(s) by {}", delay.steps, delay.reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::clock::{Clock, MockClock};
+
+    #[test]
+    fn test_report_breakdown() {
+        let tracker = LatencyTracker::default();
+        let t0 = MockClock::new().now();
+        tracker.start("r1", t0);
+        tracker.delayed("r1", DelayReason::KvCache);
+        tracker.delayed("r1", DelayReason::KvCache);
+        tracker.scheduled("r1", t0 + Duration::from_millis(300));
+        tracker.record("r1", LatencyPhase::Prefill, Duration::from_millis(50));
+        tracker.record("r1", LatencyPhase::Decode, Duration::from_millis(20));
+        tracker.record("r1", LatencyPhase::Decode, Duration::from_millis(30));
+        tracker.finish("r1", t0 + Duration::from_millis(400));
+
+        let report = tracker.report("r1", t0).unwrap();
+        assert!(report.finished);
+        assert_eq!(report.total_ms.round(), 400.0);
+        assert_eq!(report.queue_ms.round(), 300.0);
+        assert_eq!(report.decode_ms.round(), 50.0);
+        assert_eq!(report.dominant_phase, "queue");
+        assert_eq!(report.delayed_by[0].steps, 2);
+        assert!(report.summary.contains("KV cache"));
+        assert!(report.to_string().contains("2 chunk(s)"));
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let tracker = LatencyTracker::new(2);
+        let now = MockClock::new().now();
+        for id in ["a", "b", "c"] {
+            tracker.start(id, now);
+        }
+        assert!(tracker.report("a", now).is_none());
+        assert!(tracker.report("c", now).is_some());
+    }
+}
@@ -0,0 +1,74 @@
+//! Detection of the memory available for KV caches.
+//!
+//! When `max_blocks` is left at 0 each resident model sizes its KV cache
+//! from the free memory of its device reported here, scaled by
+//! `gpu_memory_utilization`.
+
+use crate::device::{cuda, Device};
+
+/// Free memory on `device` in bytes, or `None` when it cannot be detected.
+///
+/// Uses the free memory of the CUDA device or the Metal device's remaining
+/// working set on GPUs, and `MemAvailable` from `/proc/meminfo` on Linux
+/// for the CPU or when the GPU cannot be queried.
+pub fn available_memory_bytes(device: Device) -> Option<u64> {
+    match device {
+        Device::Cuda(ordinal) => cuda::free_memory(ordinal),
+        Device::Metal => metal_available_bytes(),
+        _ => None,
+    }
+    .or_else(system_available_bytes)
+}
+
+#[cfg(target_os = "macos")]
+fn metal_available_bytes() -> Option<u64> {
+    let device = metal::Device::system_default()?;
+    Some(
+        device
+            .recommended_max_working_set_size()
+            .saturating_sub(device.current_allocated_size()),
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn metal_available_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn system_available_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_meminfo(&meminfo)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_available_bytes() -> Option<u64> {
+    None
+}
+
+/// `MemAvailable` in bytes from the contents of `/proc/meminfo`
+#[cfg(any(target_os = "linux", test))]
+fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let kb = line
+            .strip_prefix("MemAvailable:")?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal:       16318412 kB\nMemFree:         1220440 kB\nMemAvailable:    8388608 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(8 * 1024 * 1024 * 1024));
+        assert_eq!(parse_meminfo("MemTotal: 1 kB\n"), None);
+    }
+}
@@ -14,6 +14,8 @@ use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
+use super::clock::{self, SharedClock};
+
 /// Global metrics collector for the engine.
 #[derive(Debug)]
 pub struct MetricsCollector {
@@ -35,11 +37,18 @@ pub struct MetricsCollector {
     start_time: Instant,
     /// Maximum samples to keep
     max_samples: usize,
+    /// Time source for uptime and request timers
+    clock: SharedClock,
 }
 
 impl MetricsCollector {
     /// Create a new metrics collector.
     pub fn new() -> Self {
+        Self::with_clock(clock::system_clock())
+    }
+
+    /// Create a metrics collector reading time from `clock`.
+    pub fn with_clock(clock: SharedClock) -> Self {
         Self {
             latency_samples: RwLock::new(VecDeque::with_capacity(1000)),
             rtf_samples: RwLock::new(VecDeque::with_capacity(1000)),
@@ -48,8 +57,9 @@ impl MetricsCollector {
             total_tokens: AtomicU64::new(0),
             total_audio_duration_us: AtomicU64::new(0),
             total_processing_time_us: AtomicU64::new(0),
-            start_time: Instant::now(),
+            start_time: clock.now(),
             max_samples: 1000,
+            clock,
         }
     }
 
@@ -122,8 +132,10 @@ impl MetricsCollector {
         let total_audio_us = self.total_audio_duration_us.load(Ordering::Relaxed);
         let total_processing_us = self.total_processing_time_us.load(Ordering::Relaxed);
 
+        let uptime_secs = self.clock.elapsed_since(self.start_time).as_secs_f64();
+
         MetricsSnapshot {
-            uptime_secs: self.start_time.elapsed().as_secs_f64(),
+            uptime_secs,
             total_requests,
             total_tokens,
             total_audio_duration_secs: total_audio_us as f64 / 1_000_000.0,
@@ -134,8 +146,8 @@ impl MetricsCollector {
             p99_latency_ms: compute_percentile(&latency_samples, 0.99),
             avg_rtf: compute_mean(&rtf_samples),
             avg_tokens_per_sec: compute_mean(&throughput_samples),
-            requests_per_sec: if self.start_time.elapsed().as_secs_f64() > 0.0 {
-                total_requests as f64 / self.start_time.elapsed().as_secs_f64()
+            requests_per_sec: if uptime_secs > 0.0 {
+                total_requests as f64 / uptime_secs
             } else {
                 0.0
             },
@@ -220,20 +232,20 @@ impl RequestTimer {
     /// Start a new request timer.
     pub fn start(metrics: Arc<MetricsCollector>) -> Self {
         Self {
-            start: Instant::now(),
+            start: metrics.clock.now(),
             metrics,
         }
     }
 
     /// Stop the timer and record metrics.
     pub async fn stop(self, tokens_generated: u64, audio_duration: Duration) {
-        let latency = self.start.elapsed();
+        let latency = self.metrics.clock.elapsed_since(self.start);
         self.metrics.record_request(latency, tokens_generated, audio_duration).await;
     }
 
     /// Get elapsed time without stopping.
     pub fn elapsed(&self) -> Duration {
-        self.start.elapsed()
+        self.metrics.clock.elapsed_since(self.start)
     }
 }
 
@@ -365,4 +377,24 @@ mod tests {
         assert!((compute_percentile(&samples, 0.50) - 50.0).abs() < 2.0);
         assert!((compute_percentile(&samples, 0.90) - 90.0).abs() < 2.0);
     }
+
+    #[tokio::test]
+    async fn test_timer_and_uptime_use_clock() {
+        use crate::engine::clock::MockClock;
+
+        let clock = Arc::new(MockClock::new());
+        let collector = Arc::new(MetricsCollector::with_clock(clock.clone()));
+
+        let timer = RequestTimer::start(collector.clone());
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(timer.elapsed(), Duration::from_millis(500));
+        timer.stop(10, Duration::from_secs(1)).await;
+
+        clock.advance(Duration::from_millis(1500));
+        let snapshot = collector.snapshot().await;
+        assert!((snapshot.uptime_secs - 2.0).abs() < 1e-9);
+        assert!((snapshot.avg_latency_ms - 500.0).abs() < 1e-9);
+        assert!((snapshot.avg_rtf - 0.5).abs() < 1e-9);
+        assert!((snapshot.requests_per_sec - 0.5).abs() < 1e-9);
+    }
 }
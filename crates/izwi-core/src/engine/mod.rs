@@ -24,70 +24,180 @@
 //! └─────────────────────────────────────────────────────────────────┘
 //! ```
 
+mod candidates;
+pub mod clock;
 mod config;
+pub mod constrained;
 mod core;
+mod core_loop;
 mod executor;
 mod kv_cache;
+pub mod latency;
+pub mod memory;
 pub mod metrics;
 mod output;
+mod output_cache;
+mod paged_attention;
+mod pipeline;
+pub mod profiler;
 mod request;
+pub mod sampler;
 mod scheduler;
+mod session;
 pub mod signal_frontend;
+pub mod speculative;
+pub mod tools;
 mod types;
+pub mod validation;
 
+pub use candidates::{Candidate, MAX_CANDIDATES};
+pub use clock::{Clock, MockClock, SharedClock, SystemClock};
 pub use config::EngineCoreConfig;
+pub use constrained::{CompiledConstraint, Constraint, TokenConstraint};
 pub use core::EngineCore;
-pub use executor::{ExecutorOutput, ModelExecutor, WorkerConfig};
-pub use kv_cache::{BlockAllocator, KVCacheConfig as KVConfig, KVCacheManager};
+pub use executor::{ExecutorOutput, ModelExecutor, UnifiedExecutor, WorkerConfig};
+pub use kv_cache::{
+    BlockAllocator, BlockWrite, KVCacheConfig as KVConfig, KVCacheDtype, KVCacheManager,
+    KVCacheStats, KVStageStats,
+};
+pub use latency::{DelayReason, LatencyPhase, LatencyReport, LatencyTracker};
 pub use metrics::{BenchmarkResult, MetricsCollector, MetricsSnapshot};
-pub use output::{OutputProcessor, StreamingOutput};
-pub use request::{EngineCoreRequest, RequestProcessor, RequestStatus};
+pub use output::{
+    JobResult, JobStatus, OutputProcessor, ResultStore, SealedResult, StoredResult,
+    StreamBackpressure, StreamingOutput,
+};
+pub use output_cache::{CacheControl, CacheKey, CacheStats, OutputCache};
+pub use paged_attention::PagedAttention;
+pub use pipeline::{PipelinePlan, PipelineStage};
+pub use profiler::{ProfileSnapshot, ProfileSummary, StepProfile, StepProfiler};
+pub use request::{AuditEntry, AuditEvent, EngineCoreRequest, RequestProcessor, RequestStatus};
+pub use sampler::{LogitsContext, LogitsProcessor, SamplerPipeline, MAX_TOP_LOGPROBS};
 pub use scheduler::{ScheduleResult, Scheduler, SchedulerConfig, SchedulingPolicy};
+pub use session::{
+    ChatInput, ChatRole, ChatSession, ChatTurn, ContextPolicy, ContextTruncation, SessionId,
+    SessionStore,
+};
+pub use speculative::{SpeculativeDecoder, SpeculativeStats, SpeculativeStep, TokenModel};
+pub use tools::{ToolCall, ToolResult, ToolSpec};
 pub use types::{
-    AudioOutput, EngineMetrics, EngineOutput, GenerationParams, RequestId, SequenceId,
+    AudioOutput, AudioPayload, CandidateScoring, EngineMetrics, EngineOutput, EngineStats,
+    GenerationParams, ModelStats, OutputResponse, OutputTimings, Priority, RequestId,
+    RequestProgress, SequenceId, StopReason, SwapReport, TaskType, TokenLogprob, TopLogprob,
+    WarmupPass, WarmupReport,
 };
 
-use crate::error::Result;
+use crate::audio::OutputMemoryTracker;
+use crate::error::{Error, Result};
+use crate::model::ModelVariant;
+use core_loop::{CoreLoop, EngineCommand};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
 use tracing::{debug, info, warn};
+use types::FinishReason;
+
+/// Text of warmup requests
+const WARMUP_TEXT: &str = "Warming up.";
+
+/// Tokens generated per warmup request, enough to run prefill, decode and
+/// audio decoding
+const WARMUP_MAX_TOKENS: usize = 16;
 
 /// Main inference engine - the primary interface for audio generation.
 ///
 /// The engine orchestrates all components and provides both synchronous
-/// and asynchronous interfaces for inference.
+/// and asynchronous interfaces for inference. Requests are stepped by a
+/// dedicated core task, started on first use, which callers feed over a
+/// command channel.
 pub struct Engine {
     /// Engine core handles the actual inference loop
     core: Arc<RwLock<EngineCore>>,
+    /// Commands for the core task
+    commands: mpsc::UnboundedSender<EngineCommand>,
+    /// Core task, until it is started
+    core_loop: std::sync::Mutex<Option<CoreLoop>>,
     /// Request processor validates and preprocesses inputs
     request_processor: RequestProcessor,
     /// Output processor formats results for clients
     output_processor: OutputProcessor,
     /// Configuration
     config: EngineCoreConfig,
-    /// Whether the engine is running
-    running: std::sync::atomic::AtomicBool,
     /// Metrics collector
     metrics: Arc<RwLock<EngineMetrics>>,
+    /// Latency traces shared with the engine core
+    latency: Arc<LatencyTracker>,
+    /// Asynchronous job results shared with the engine core
+    results: Arc<ResultStore>,
+    /// Output cache shared with the engine core, when enabled
+    cache: Option<Arc<OutputCache>>,
+    /// Open chat sessions
+    sessions: Arc<SessionStore>,
+    /// Serializes model swaps
+    swap_lock: Mutex<()>,
+    /// Time source for warmup and swap timings
+    clock: SharedClock,
 }
 
 impl Engine {
     /// Create a new inference engine with the given configuration.
     pub fn new(config: EngineCoreConfig) -> Result<Self> {
+        Self::with_clock(config, clock::system_clock())
+    }
+
+    /// Create an engine reading time from `clock`.
+    pub fn with_clock(config: EngineCoreConfig, clock: SharedClock) -> Result<Self> {
+        let core = EngineCore::with_clock(config.clone(), clock.clone())?;
+        Ok(Self::from_core(core, config, clock))
+    }
+
+    /// Create an engine whose buffered output audio counts against
+    /// `memory`, so one global cap covers it and the inference engine
+    /// sharing the tracker.
+    pub fn with_output_memory(
+        config: EngineCoreConfig,
+        memory: Arc<OutputMemoryTracker>,
+    ) -> Result<Self> {
+        let clock = clock::system_clock();
+        let core =
+            EngineCore::with_clock(config.clone(), clock.clone())?.with_output_memory(memory);
+        Ok(Self::from_core(core, config, clock))
+    }
+
+    fn from_core(core: EngineCore, config: EngineCoreConfig, clock: SharedClock) -> Self {
         info!("Initializing inference engine");
 
-        let core = EngineCore::new(config.clone())?;
+        let sessions = SessionStore::new(
+            Duration::from_secs(config.chat_session_ttl_secs),
+            config.max_chat_sessions,
+        )
+        .with_clock(clock.clone());
+        let latency = core.latency_tracker();
+        let profiler = core.step_profiler();
+        let results = core.result_store();
+        let cache = core.output_cache();
         let request_processor = RequestProcessor::new(config.clone());
         let output_processor = OutputProcessor::new(config.sample_rate);
 
-        Ok(Self {
-            core: Arc::new(RwLock::new(core)),
+        let core = Arc::new(RwLock::new(core));
+        let metrics = Arc::new(RwLock::new(EngineMetrics::default()));
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let core_loop = CoreLoop::new(core.clone(), metrics.clone(), profiler, commands_rx);
+
+        Self {
+            core,
+            commands,
+            core_loop: std::sync::Mutex::new(Some(core_loop)),
             request_processor,
             output_processor,
             config,
-            running: std::sync::atomic::AtomicBool::new(false),
-            metrics: Arc::new(RwLock::new(EngineMetrics::default())),
-        })
+            metrics,
+            latency,
+            results,
+            cache,
+            sessions: Arc::new(sessions),
+            swap_lock: Mutex::new(()),
+            clock,
+        }
     }
 
     /// Add a request to the engine for processing.
@@ -95,43 +205,55 @@ impl Engine {
     /// The request will be validated, preprocessed, and added to the scheduler's
     /// waiting queue. Returns a request ID that can be used to track the request.
     pub async fn add_request(&self, request: EngineCoreRequest) -> Result<RequestId> {
+        self.enqueue(request, None).await
+    }
+
+    /// Validate a request and hand it to the core task. `finished` receives
+    /// its final output.
+    async fn enqueue(
+        &self,
+        request: EngineCoreRequest,
+        finished: Option<oneshot::Sender<Result<EngineOutput>>>,
+    ) -> Result<RequestId> {
         // Validate and preprocess
         let processed = self.request_processor.process(request)?;
         let request_id = processed.id.clone();
 
-        // Add to engine core
-        let mut core = self.core.write().await;
-        core.add_request(processed)?;
+        let (added, reply) = oneshot::channel();
+        self.send(EngineCommand::Add {
+            request: Box::new(processed),
+            added,
+            finished,
+        })?;
+        reply.await.map_err(|_| core_loop_stopped())??;
 
         debug!("Added request {} to engine", request_id);
         Ok(request_id)
     }
 
+    /// Send a command to the core task, starting it on first use.
+    fn send(&self, command: EngineCommand) -> Result<()> {
+        if let Some(core_loop) = self
+            .core_loop
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            tokio::spawn(core_loop.run());
+        }
+        self.commands.send(command).map_err(|_| core_loop_stopped())
+    }
+
     /// Generate audio synchronously (blocking until complete).
     ///
     /// This is a convenience method that adds a request and waits for completion.
     pub async fn generate(&self, request: EngineCoreRequest) -> Result<EngineOutput> {
-        let request_id = self.add_request(request).await?;
-
-        // Run steps until this request completes
-        loop {
-            let outputs = self.step().await?;
-
-            for output in outputs {
-                if output.request_id == request_id && output.is_finished {
-                    return Ok(output);
-                }
-            }
-
-            // Check if request is still in the system
-            let core = self.core.read().await;
-            if !core.has_request(&request_id) {
-                return Err(crate::error::Error::InferenceError(format!(
-                    "Request {} was removed unexpectedly",
-                    request_id
-                )));
-            }
+        if let Some(audio) = self.cached_audio(&request) {
+            return Ok(EngineOutput::cached(request.id, audio));
         }
+        let (finished, output) = oneshot::channel();
+        self.enqueue(request, Some(finished)).await?;
+        output.await.map_err(|_| core_loop_stopped())?
     }
 
     /// Generate audio with streaming output.
@@ -141,7 +263,7 @@ impl Engine {
         &self,
         request: EngineCoreRequest,
     ) -> Result<(RequestId, mpsc::Receiver<StreamingOutput>)> {
-        let (tx, rx) = mpsc::channel(32);
+        let (tx, rx) = mpsc::channel(self.config().stream_channel_capacity.max(1));
         let request_id = request.id.clone();
 
         // Add request with streaming callback
@@ -153,84 +275,514 @@ impl Engine {
         Ok((request_id, rx))
     }
 
-    /// Execute one step of the inference loop.
-    ///
-    /// This is the core loop that:
-    /// 1. Schedules requests (decides what to process this step)
-    /// 2. Runs forward pass on scheduled requests
-    /// 3. Processes outputs (sampling, stop conditions)
+    /// Get engine metrics.
+    pub async fn metrics(&self) -> EngineMetrics {
+        let mut metrics = self.metrics.read().await.clone();
+        let memory = self.core.read().await.output_memory_stats();
+        metrics.output_buffer_bytes = memory.used_bytes;
+        metrics.output_buffer_peak_bytes = memory.peak_bytes;
+        metrics
+    }
+
+    /// KV cache usage of the default model
+    pub async fn kv_cache_stats(&self) -> KVCacheStats {
+        self.core.read().await.kv_cache_stats()
+    }
+
+    /// Queue, KV cache and per-request progress of every resident model
+    pub async fn stats(&self) -> EngineStats {
+        self.core.read().await.stats()
+    }
+
+    /// Get current configuration.
+    pub fn config(&self) -> &EngineCoreConfig {
+        &self.config
+    }
+
+    /// Abort a specific request.
+    pub async fn abort_request(&self, request_id: &RequestId) -> Result<bool> {
+        let (reply, aborted) = oneshot::channel();
+        self.send(EngineCommand::Abort {
+            request_id: request_id.clone(),
+            reply,
+        })?;
+        aborted.await.map_err(|_| core_loop_stopped())
+    }
+
+    /// Change the priority of a waiting request.
     ///
-    /// Returns outputs for any completed or streaming requests.
-    pub async fn step(&self) -> Result<Vec<EngineOutput>> {
+    /// Returns the previous priority. Fails if the request is unknown or has
+    /// already been scheduled.
+    pub async fn set_request_priority(
+        &self,
+        request_id: &RequestId,
+        priority: Priority,
+        reason: Option<String>,
+    ) -> Result<Priority> {
         let mut core = self.core.write().await;
-        let outputs = core.step().await?;
+        core.set_request_priority(request_id, priority, reason)
+    }
 
-        // Update metrics
-        {
-            let mut metrics = self.metrics.write().await;
-            metrics.total_steps += 1;
-            metrics.requests_processed += outputs.len() as u64;
-        }
+    /// Get the status and audit trail of a request.
+    pub async fn request_audit(
+        &self,
+        request_id: &RequestId,
+    ) -> Option<(RequestStatus, Vec<AuditEntry>)> {
+        let core = self.core.read().await;
+        core.request_audit(request_id)
+    }
+
+    /// Explain where a running or recently finished request spent its time.
+    pub async fn latency_report(&self, request_id: &RequestId) -> Option<LatencyReport> {
+        let core = self.core.read().await;
+        core.latency_report(request_id)
+    }
 
-        Ok(outputs)
+    /// Latency tracker for recording serving-side phases (encoding, flushes).
+    pub fn latency_tracker(&self) -> Arc<LatencyTracker> {
+        self.latency.clone()
     }
 
-    /// Run the engine continuously, processing requests as they arrive.
+    /// Submit a request as an asynchronous job.
     ///
-    /// This should be called in a separate task. It will run until `stop()` is called.
-    pub async fn run(&self) -> Result<()> {
-        use std::sync::atomic::Ordering;
-
-        self.running.store(true, Ordering::SeqCst);
-        info!("Engine started");
-
-        while self.running.load(Ordering::SeqCst) {
-            // Check if there are requests to process
-            let has_work = {
-                let core = self.core.read().await;
-                core.has_pending_work()
-            };
-
-            if has_work {
-                if let Err(e) = self.step().await {
-                    warn!("Engine step error: {}", e);
+    /// Its audio is kept in the result store once it finishes, for polling
+    /// with [`Engine::result_store`].
+    pub async fn submit(&self, request: EngineCoreRequest) -> Result<RequestId> {
+        let request_id = request.id.clone();
+        self.results
+            .submit(&request_id, request.client_id.as_deref(), None);
+        if let Some(audio) = self.cached_audio(&request) {
+            self.results.complete(&request_id, audio);
+            return Ok(request_id);
+        }
+        match self.add_request(request).await {
+            Ok(id) => Ok(id),
+            Err(e) => {
+                self.results.fail(&request_id, e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Store of asynchronous job results.
+    pub fn result_store(&self) -> Arc<ResultStore> {
+        self.results.clone()
+    }
+
+    /// Output cache, when enabled.
+    pub fn output_cache(&self) -> Option<Arc<OutputCache>> {
+        self.cache.clone()
+    }
+
+    /// Run `processor` on the logits of every later request it applies to,
+    /// after those registered before it. Replaces any processor registered
+    /// under the same name.
+    pub fn register_logits_processor(&self, processor: Arc<dyn LogitsProcessor>) {
+        info!("Registered logits processor {}", processor.name());
+        self.request_processor.register_logits_processor(processor);
+    }
+
+    /// Unregister the logits processor named `name`. Returns false if there
+    /// was none.
+    pub fn remove_logits_processor(&self, name: &str) -> bool {
+        self.request_processor.remove_logits_processor(name)
+    }
+
+    /// Names of the registered logits processors, in the order they run.
+    pub fn logits_processors(&self) -> Vec<String> {
+        self.request_processor.logits_processor_names()
+    }
+
+    /// Open a chat session answered by `model` (the default model when unset),
+    /// which may call `tools`.
+    pub async fn create_session(
+        &self,
+        model: Option<ModelVariant>,
+        system_prompt: Option<String>,
+        tools: Vec<ToolSpec>,
+    ) -> Result<ChatSession> {
+        tools::validate_tools(&tools)?;
+        self.expire_sessions().await;
+        let (session, evicted) = self.sessions.create(model, system_prompt, tools);
+        let mut core = self.core.write().await;
+        for id in evicted {
+            debug!("Closed chat session {} to make room", id);
+            core.release_session(&id);
+        }
+        Ok(session)
+    }
+
+    /// Current state of a chat session.
+    pub fn session(&self, session_id: &str) -> Option<ChatSession> {
+        self.sessions.get(session_id)
+    }
+
+    /// Run the next turn of a chat session on user text and/or audio, and the
+    /// results of the tool calls the assistant last made. Both sides of the
+    /// exchange are added to the history, whose KV cache is kept for the next
+    /// turn. Tool calls in the reply are in the last turn. History past the
+    /// context window is shortened first, as reported in the session's
+    /// `truncation`.
+    pub async fn chat_turn(
+        &self,
+        session_id: &str,
+        input: ChatInput,
+    ) -> Result<(ChatSession, EngineOutput)> {
+        let ChatInput {
+            text,
+            audio,
+            tool_results,
+            constraint,
+            stop,
+            max_audio_seconds,
+            logprobs,
+            priority,
+        } = input;
+        let text = text.filter(|t| !t.trim().is_empty());
+        if text.is_none() && audio.is_none() && tool_results.is_empty() {
+            return Err(Error::InvalidInput(
+                "A chat turn needs text, audio or tool results".into(),
+            ));
+        }
+        self.expire_sessions().await;
+        let session = self.sessions.begin_turn(session_id)?;
+        let mut turns = match tool_result_turns(&session, tool_results) {
+            Ok(turns) => turns,
+            Err(e) => {
+                self.sessions.abort_turn(session_id);
+                return Err(e);
+            }
+        };
+        if text.is_some() || audio.is_some() {
+            turns.push(ChatTurn::new(ChatRole::User, text, audio));
+        }
+        let session = match self.sessions.fit_context(
+            session_id,
+            &turns,
+            self.config.chat_context_window(),
+            self.config.chat_context_policy,
+        ) {
+            Ok(session) => session,
+            Err(e) => {
+                self.sessions.abort_turn(session_id);
+                return Err(e);
+            }
+        };
+        if let Some(truncation) = &session.truncation {
+            debug!(
+                "Dropped {} turns of chat session {} to fit the context window",
+                truncation.dropped_turns, session_id
+            );
+            // The cached history is no longer a prefix of the prompt
+            self.core.write().await.release_session(session_id);
+        }
+        let mut request = EngineCoreRequest::chat(&session, &turns).with_priority(priority);
+        request.params.constraint = constraint;
+        request.params.stop_sequences = stop;
+        request.params.max_audio_seconds = max_audio_seconds;
+        request.params.logprobs = logprobs;
+        let result = match self.request_processor.process(request) {
+            Ok(request) => {
+                let prompt_tokens = request.num_prompt_tokens();
+                let constraint = request.constraint.clone();
+                self.generate(request)
+                    .await
+                    .map(|output| (output, prompt_tokens, constraint))
+            }
+            Err(e) => Err(e),
+        };
+        let result = result.and_then(|(mut output, prompt_tokens, constraint)| {
+            if output.finish_reason == Some(FinishReason::Error) {
+                return Ok((output, prompt_tokens, Vec::new()));
+            }
+            // Executors decoding out of process cannot mask tokens, so the
+            // finished reply is checked as well
+            if let (Some(constraint), Some(text)) = (&constraint, &output.text) {
+                if !constraint.matches(text) {
+                    return Err(Error::InferenceError(
+                        "Chat reply does not satisfy the output constraint".into(),
+                    ));
                 }
-            } else {
-                // No work, sleep briefly to avoid busy-waiting
-                tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+            }
+            if session.tools.is_empty() {
+                return Ok((output, prompt_tokens, Vec::new()));
+            }
+            let (spoken, calls) = tools::parse_tool_calls(
+                output.text.as_deref().unwrap_or_default(),
+                &session.tools,
+            )?;
+            output.text = (!spoken.is_empty()).then_some(spoken);
+            Ok((output, prompt_tokens, calls))
+        });
+        match result {
+            Ok((output, prompt_tokens, calls))
+                if output.finish_reason != Some(FinishReason::Error) =>
+            {
+                turns.push(
+                    ChatTurn::new(ChatRole::Assistant, output.text.clone(), None)
+                        .with_tool_calls(calls),
+                );
+                self.sessions.finish_turn(session_id, turns, prompt_tokens);
+                let Some(session) = self.sessions.get(session_id) else {
+                    // Closed during the turn: drop the history cached for it
+                    self.core.write().await.release_session(session_id);
+                    return Err(Error::SessionNotFound(session_id.to_string()));
+                };
+                Ok((session, output))
+            }
+            Ok((output, _, _)) => {
+                self.sessions.abort_turn(session_id);
+                Err(Error::InferenceError(format!(
+                    "Chat turn {} failed",
+                    output.request_id
+                )))
+            }
+            Err(e) => {
+                self.sessions.abort_turn(session_id);
+                Err(e)
             }
         }
+    }
 
-        info!("Engine stopped");
-        Ok(())
+    /// Close a chat session and free its cached history. Returns false if it
+    /// was not open.
+    pub async fn delete_session(&self, session_id: &str) -> bool {
+        if !self.sessions.remove(session_id) {
+            return false;
+        }
+        self.core.write().await.release_session(session_id);
+        true
     }
 
-    /// Stop the engine.
-    pub fn stop(&self) {
-        use std::sync::atomic::Ordering;
-        self.running.store(false, Ordering::SeqCst);
+    /// Close sessions idle past their TTL.
+    async fn expire_sessions(&self) {
+        let expired = self.sessions.expire();
+        if expired.is_empty() {
+            return;
+        }
+        let mut core = self.core.write().await;
+        for id in expired {
+            debug!("Chat session {} expired", id);
+            core.release_session(&id);
+        }
     }
 
-    /// Check if the engine is running.
-    pub fn is_running(&self) -> bool {
-        use std::sync::atomic::Ordering;
-        self.running.load(Ordering::SeqCst)
+    /// Audio cached for an identical earlier request, if the request allows
+    fn cached_audio(&self, request: &EngineCoreRequest) -> Option<AudioOutput> {
+        let cache = self.cache.as_ref()?;
+        if !request.cache_control.reads() || request.text.is_none() {
+            return None;
+        }
+        let audio = cache.get(&CacheKey::for_request(request, None))?;
+        debug!("Serving request {} from the output cache", request.id);
+        Some(audio.as_ref().clone())
     }
 
-    /// Get engine metrics.
-    pub async fn metrics(&self) -> EngineMetrics {
-        self.metrics.read().await.clone()
+    /// Timings of the last `limit` engine steps (all kept steps when `None`).
+    pub async fn profile_snapshot(&self, limit: Option<usize>) -> Result<ProfileSnapshot> {
+        let (reply, snapshot) = oneshot::channel();
+        self.send(EngineCommand::Profile { limit, reply })?;
+        snapshot.await.map_err(|_| core_loop_stopped())
     }
 
-    /// Get current configuration.
-    pub fn config(&self) -> &EngineCoreConfig {
-        &self.config
+    /// Forget recorded step timings.
+    pub async fn reset_profile(&self) -> Result<()> {
+        let (reply, done) = oneshot::channel();
+        self.send(EngineCommand::ResetProfile { reply })?;
+        done.await.map_err(|_| core_loop_stopped())
     }
 
-    /// Abort a specific request.
-    pub async fn abort_request(&self, request_id: &RequestId) -> Result<bool> {
+    /// Model serving requests that don't name one.
+    pub async fn current_model(&self) -> ModelVariant {
+        self.core.read().await.default_model()
+    }
+
+    /// Models currently resident.
+    pub async fn loaded_models(&self) -> Vec<ModelVariant> {
+        self.core.read().await.loaded_models()
+    }
+
+    /// Start an executor for `variant` and load its weights.
+    async fn start_executor(&self, variant: ModelVariant) -> Result<UnifiedExecutor> {
+        if variant.is_tokenizer() {
+            return Err(Error::InvalidInput(format!(
+                "{} is a codec and cannot serve requests",
+                variant
+            )));
+        }
+        let model_path = self.config.models_dir.join(variant.dir_name());
+        if !model_path.exists() {
+            return Err(Error::ModelNotFound(format!(
+                "Model {} not downloaded. Please download it first.",
+                variant
+            )));
+        }
+
+        let mut worker_config = WorkerConfig::from(&self.config);
+        worker_config.model_path = model_path;
+        let executor = UnifiedExecutor::new_python(worker_config);
+        executor.initialize().await?;
+        executor.warm_up().await?;
+        Ok(executor)
+    }
+
+    /// Load `variant` next to the resident models so requests naming it are
+    /// routed there. It gets its own KV cache of `max_blocks` blocks
+    /// (`max_blocks` from the config when `None`).
+    pub async fn load_model(&self, variant: ModelVariant, max_blocks: Option<usize>) -> Result<()> {
+        if self.core.read().await.loaded_models().contains(&variant) {
+            return Err(Error::InvalidInput(format!(
+                "Model {} is already loaded",
+                variant
+            )));
+        }
+        info!("Loading model {}", variant);
+        let executor = self.start_executor(variant).await?;
+        let max_blocks = max_blocks.unwrap_or(self.config.max_blocks);
+        self.core
+            .write()
+            .await
+            .add_model(variant, executor, max_blocks)?;
+        if self.config.warmup.enabled {
+            self.warmup_model(variant).await?;
+        }
+        Ok(())
+    }
+
+    /// Run short dummy requests through every resident model at each
+    /// configured batch size, so compilation and allocation happen now
+    /// rather than on the first real request.
+    pub async fn warmup(&self) -> Result<WarmupReport> {
+        let start = self.clock.now();
+        let mut passes = Vec::new();
+        for variant in self.loaded_models().await {
+            passes.extend(self.warmup_model(variant).await?);
+        }
+        Ok(WarmupReport {
+            passes,
+            total_ms: self.clock.elapsed_since(start).as_secs_f64() * 1000.0,
+        })
+    }
+
+    async fn warmup_model(&self, variant: ModelVariant) -> Result<Vec<WarmupPass>> {
+        let mut passes = Vec::new();
+        for batch_size in warmup_batch_sizes(&self.config) {
+            let start = self.clock.now();
+            let mut pending = Vec::with_capacity(batch_size);
+            for _ in 0..batch_size {
+                let request = EngineCoreRequest::tts(WARMUP_TEXT)
+                    .with_model(variant)
+                    .with_params(GenerationParams {
+                        max_tokens: WARMUP_MAX_TOKENS,
+                        ..Default::default()
+                    })
+                    .with_cache_control(CacheControl::NoStore);
+                let (finished, output) = oneshot::channel();
+                self.enqueue(request, Some(finished)).await?;
+                pending.push(output);
+            }
+
+            for output in pending {
+                output.await.map_err(|_| core_loop_stopped())??;
+            }
+
+            let elapsed_ms = self.clock.elapsed_since(start).as_secs_f64() * 1000.0;
+            info!(
+                "Warmed up {} at batch size {} in {:.0} ms",
+                variant, batch_size, elapsed_ms
+            );
+            passes.push(WarmupPass {
+                model: variant,
+                batch_size,
+                elapsed_ms,
+            });
+        }
+        Ok(passes)
+    }
+
+    /// Remove a resident model that has no requests in flight.
+    pub async fn unload_model(&self, variant: ModelVariant) -> Result<()> {
         let mut core = self.core.write().await;
-        Ok(core.abort_request(request_id))
+        // The daemon is shared with other models, so it keeps running
+        core.remove_model(variant)?;
+        info!("Unloaded model {}", variant);
+        Ok(())
+    }
+
+    /// Switch the default model without dropping in-flight requests.
+    ///
+    /// The new weights are loaded while the current model keeps serving.
+    /// Waiting requests are then held back until running ones finish, for at
+    /// most `swap_drain_timeout_ms`; anything still running after that is
+    /// restarted on the new model. Streams stay open throughout.
+    pub async fn swap_model(&self, variant: ModelVariant) -> Result<SwapReport> {
+        let _guard = self.swap_lock.lock().await;
+        if variant.is_asr() {
+            return Err(Error::InvalidInput(format!(
+                "{} cannot serve TTS requests",
+                variant
+            )));
+        }
+
+        {
+            let core = self.core.read().await;
+            if variant != core.default_model() && core.loaded_models().contains(&variant) {
+                return Err(Error::InvalidInput(format!(
+                    "Model {} is already loaded; send requests to it directly",
+                    variant
+                )));
+            }
+        }
+
+        info!("Swapping default model to {}", variant);
+        let load_start = self.clock.now();
+        let executor = self.start_executor(variant).await?;
+        let load_ms = self.clock.elapsed_since(load_start).as_secs_f64() * 1000.0;
+
+        // Let running requests finish on the current model
+        let drain_start = self.clock.now();
+        let drain_timeout = Duration::from_millis(self.config.swap_drain_timeout_ms);
+        let (previous, running_before) = {
+            let mut core = self.core.write().await;
+            core.set_admission_paused(true);
+            (core.default_model(), core.default_running_count())
+        };
+        while self.clock.elapsed_since(drain_start) < drain_timeout
+            && self.core.read().await.default_running_count() > 0
+        {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let drain_ms = self.clock.elapsed_since(drain_start).as_secs_f64() * 1000.0;
+
+        let migrated = {
+            let mut core = self.core.write().await;
+            let result = core.replace_default_model(variant, executor);
+            core.set_admission_paused(false);
+            result?
+        };
+
+        if !migrated.is_empty() {
+            warn!(
+                "Restarted {} request(s) on {} after the drain timeout",
+                migrated.len(),
+                variant
+            );
+        }
+        info!("Now serving {} (was {})", variant, previous);
+        if self.config.warmup.enabled {
+            if let Err(e) = self.warmup_model(variant).await {
+                warn!("Warmup of {} failed: {}", variant, e);
+            }
+        }
+
+        Ok(SwapReport {
+            model: variant,
+            previous,
+            load_ms,
+            drain_ms,
+            drained: running_before.saturating_sub(migrated.len()),
+            migrated,
+        })
     }
 
     /// Get the number of pending requests.
@@ -246,6 +798,48 @@ impl Engine {
     }
 }
 
+fn core_loop_stopped() -> Error {
+    Error::InferenceError("Engine core loop has stopped".into())
+}
+
+/// Warmup batch sizes in increasing order, capped at the maximum batch size
+fn warmup_batch_sizes(config: &EngineCoreConfig) -> Vec<usize> {
+    let mut sizes: Vec<usize> = config
+        .warmup
+        .batch_sizes
+        .iter()
+        .map(|&size| size.clamp(1, config.max_batch_size.max(1)))
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// History turns for tool results, which must answer the session's pending
+/// tool calls
+fn tool_result_turns(session: &ChatSession, results: Vec<ToolResult>) -> Result<Vec<ChatTurn>> {
+    let pending = session.pending_tool_calls();
+    let mut turns: Vec<ChatTurn> = Vec::with_capacity(results.len());
+    for result in results {
+        if !pending.iter().any(|call| call.id == result.tool_call_id)
+            || turns
+                .iter()
+                .any(|turn| turn.tool_call_id.as_ref() == Some(&result.tool_call_id))
+        {
+            return Err(Error::InvalidInput(format!(
+                "No pending tool call {} in session {}",
+                result.tool_call_id, session.id
+            )));
+        }
+        let content = match result.content {
+            serde_json::Value::String(text) => text,
+            other => other.to_string(),
+        };
+        turns.push(ChatTurn::tool_result(result.tool_call_id, content));
+    }
+    Ok(turns)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,4 +850,65 @@ mod tests {
         let engine = Engine::new(config);
         assert!(engine.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_swap_requires_downloaded_model() {
+        let config = EngineCoreConfig {
+            models_dir: std::env::temp_dir().join("izwi-missing-models"),
+            ..Default::default()
+        };
+        let engine = Engine::new(config).unwrap();
+        let previous = engine.current_model().await;
+
+        let result = engine.swap_model(ModelVariant::Qwen3Tts12Hz17BBase).await;
+        assert!(matches!(result, Err(Error::ModelNotFound(_))));
+        let result = engine.swap_model(ModelVariant::Qwen3Asr06B).await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert_eq!(engine.current_model().await, previous);
+    }
+
+    #[tokio::test]
+    async fn test_core_loop_serves_commands() {
+        let engine = Engine::new(EngineCoreConfig::default()).unwrap();
+        assert!(!engine.abort_request(&"missing".to_string()).await.unwrap());
+        engine.reset_profile().await.unwrap();
+        let profile = engine.profile_snapshot(None).await.unwrap();
+        assert!(profile.steps.is_empty());
+
+        // Without a worker the step fails, which reaches the waiting caller
+        let request = EngineCoreRequest::tts("Hello").with_cache_control(CacheControl::NoStore);
+        let result = tokio::time::timeout(Duration::from_secs(5), engine.generate(request)).await;
+        assert!(result.expect("generate should not hang").is_err());
+    }
+
+    #[test]
+    fn test_output_response_schema() {
+        let mut output = EngineOutput::cached("req-1".to_string(), AudioOutput::empty(24000));
+        output.finish_reason = Some(FinishReason::MaxTokens);
+        output.text = Some("Hi".to_string());
+
+        let text_only = serde_json::to_value(OutputResponse::from_output(&output)).unwrap();
+        assert_eq!(text_only["request_id"], "req-1");
+        assert_eq!(text_only["finish_reason"], "length");
+        assert_eq!(text_only["text"], "Hi");
+        assert!(text_only.get("audio").is_none());
+        assert!(text_only.get("sample_rate").is_none());
+
+        let response = OutputResponse::from_output(&output).with_audio(b"abc", "wav", 8000, 1.5);
+        let json = serde_json::to_value(response).unwrap();
+        assert_eq!(json["audio"], "YWJj");
+        assert_eq!(json["format"], "wav");
+        assert_eq!(json["sample_rate"], 8000);
+        assert_eq!(json["timings"]["queue_ms"], 0.0);
+    }
+
+    #[test]
+    fn test_warmup_batch_sizes_are_capped() {
+        let mut config = EngineCoreConfig {
+            max_batch_size: 8,
+            ..Default::default()
+        };
+        config.warmup.batch_sizes = vec![4, 0, 16, 1, 4];
+        assert_eq!(warmup_batch_sizes(&config), [1, 4, 8]);
+    }
 }
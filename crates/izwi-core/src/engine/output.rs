@@ -1,16 +1,27 @@
 //! Output processing for the inference engine.
 //!
 //! Handles conversion of raw model outputs to user-facing results,
-//! including streaming chunked output and stop condition detection.
+//! including streaming chunked output, stop condition detection, and the
+//! store of asynchronous job results.
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::debug;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::{debug, warn};
 
+use super::clock::{self, SharedClock};
 use super::executor::ExecutorOutput;
+use super::output_cache::OutputCache;
 use super::types::{
-    AudioOutput, EngineOutput, FinishReason, RequestId, SequenceId, TokenStats,
+    AudioOutput, EngineOutput, FinishReason, GenerationParams, OutputTimings, RequestId,
+    SequenceId, TokenStats,
+};
+use crate::audio::{
+    reserve_samples, OutputMemoryStats, OutputMemoryTracker, OverflowPolicy, SampleBuffer,
+    SpooledAudio, BYTES_PER_SAMPLE, TOKEN_RATE_HZ,
 };
 
 /// Streaming output chunk.
@@ -21,7 +32,7 @@ pub struct StreamingOutput {
     /// Sequence number of this chunk
     pub sequence: usize,
     /// Audio samples in this chunk
-    pub samples: Vec<f32>,
+    pub samples: SampleBuffer,
     /// Sample rate
     pub sample_rate: u32,
     /// Whether this is the final chunk
@@ -37,13 +48,13 @@ impl StreamingOutput {
     pub fn new(
         request_id: RequestId,
         sequence: usize,
-        samples: Vec<f32>,
+        samples: impl Into<SampleBuffer>,
         sample_rate: u32,
     ) -> Self {
         Self {
             request_id,
             sequence,
-            samples,
+            samples: samples.into(),
             sample_rate,
             is_final: false,
             text: None,
@@ -55,13 +66,13 @@ impl StreamingOutput {
     pub fn final_chunk(
         request_id: RequestId,
         sequence: usize,
-        samples: Vec<f32>,
+        samples: impl Into<SampleBuffer>,
         sample_rate: u32,
     ) -> Self {
         Self {
             request_id,
             sequence,
-            samples,
+            samples: samples.into(),
             sample_rate,
             is_final: true,
             text: None,
@@ -90,6 +101,217 @@ pub struct StreamingStats {
     pub rtf: f32,
 }
 
+/// What a streaming session does when its consumer can't keep up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamBackpressure {
+    /// Wait for room in the channel, holding up the engine step
+    #[default]
+    Block,
+    /// Hold at most a channel's worth of undelivered chunks, discarding the oldest
+    DropOldest,
+    /// Stop decoding the request until its consumer catches up
+    Pause,
+}
+
+/// Status of an asynchronously submitted job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Output of a finished job published to output storage
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    /// Object key the audio was stored under
+    pub key: String,
+    pub duration_secs: f32,
+}
+
+/// Output of a finished tenant job, kept encrypted in the output store under
+/// the job's request ID
+#[derive(Debug, Clone, Copy)]
+pub struct SealedResult {
+    pub duration_secs: f32,
+}
+
+/// State of an asynchronous job, with its audio once completed.
+///
+/// Long-form jobs finish with `spooled` set instead of `audio`; their WAV
+/// file is deleted when the result expires. Jobs whose audio went to output
+/// storage finish with only `stored` set, and tenant jobs with only
+/// `sealed`.
+#[derive(Debug, Clone)]
+pub struct JobResult {
+    pub request_id: RequestId,
+    pub status: JobStatus,
+    /// API key that submitted the job
+    pub client_id: Option<String>,
+    /// Tenant the job ran for
+    pub tenant: Option<String>,
+    pub audio: Option<Arc<AudioOutput>>,
+    pub spooled: Option<Arc<SpooledAudio>>,
+    pub stored: Option<StoredResult>,
+    pub sealed: Option<SealedResult>,
+    pub error: Option<String>,
+    pub submitted_at: Instant,
+    pub finished_at: Option<Instant>,
+}
+
+/// Results of asynchronous jobs, kept for a TTL after they finish.
+///
+/// Only submitted jobs are tracked; finished jobs expire `ttl` after
+/// completion and are purged lazily on access.
+pub struct ResultStore {
+    ttl: Duration,
+    clock: SharedClock,
+    jobs: Mutex<HashMap<RequestId, JobResult>>,
+}
+
+impl ResultStore {
+    /// Create a store keeping finished results for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            clock: clock::system_clock(),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use `clock` for submission times and expiry.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<RequestId, JobResult>> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let now = self.clock.now();
+        let ttl = self.ttl;
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished| now.saturating_duration_since(finished) < ttl)
+        });
+        jobs
+    }
+
+    /// Register a queued job submitted by `client_id` for `tenant`.
+    pub fn submit(&self, request_id: &RequestId, client_id: Option<&str>, tenant: Option<&str>) {
+        let now = self.clock.now();
+        self.jobs().insert(
+            request_id.clone(),
+            JobResult {
+                request_id: request_id.clone(),
+                status: JobStatus::Queued,
+                client_id: client_id.map(String::from),
+                tenant: tenant.map(String::from),
+                audio: None,
+                spooled: None,
+                stored: None,
+                sealed: None,
+                error: None,
+                submitted_at: now,
+                finished_at: None,
+            },
+        );
+    }
+
+    /// Whether `request_id` is a tracked job.
+    pub fn contains(&self, request_id: &RequestId) -> bool {
+        self.jobs().contains_key(request_id)
+    }
+
+    /// Mark a queued job as running.
+    pub fn start(&self, request_id: &RequestId) {
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            if job.status == JobStatus::Queued {
+                job.status = JobStatus::Running;
+            }
+        }
+    }
+
+    /// Store the audio of a finished job.
+    pub fn complete(&self, request_id: &RequestId, audio: AudioOutput) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            job.status = JobStatus::Completed;
+            job.audio = Some(Arc::new(audio));
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Store the spool file of a finished long-form job.
+    pub fn complete_spooled(&self, request_id: &RequestId, audio: SpooledAudio) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            job.status = JobStatus::Completed;
+            job.spooled = Some(Arc::new(audio));
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Record where a finished job's audio was stored.
+    pub fn complete_stored(&self, request_id: &RequestId, stored: StoredResult) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            job.status = JobStatus::Completed;
+            job.stored = Some(stored);
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Record that a finished tenant job's audio is in the output store.
+    pub fn complete_sealed(&self, request_id: &RequestId, sealed: SealedResult) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            job.status = JobStatus::Completed;
+            job.sealed = Some(sealed);
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Record a job failure.
+    pub fn fail(&self, request_id: &RequestId, error: impl Into<String>) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(request_id) {
+            job.status = JobStatus::Failed;
+            job.error = Some(error.into());
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Current state of a job, `None` if unknown or expired.
+    pub fn get(&self, request_id: &RequestId) -> Option<JobResult> {
+        self.jobs().get(request_id).cloned()
+    }
+
+    /// Current state of a job submitted by `client_id` for `tenant`; others'
+    /// jobs are not found.
+    pub fn get_owned(
+        &self,
+        request_id: &RequestId,
+        client_id: Option<&str>,
+        tenant: Option<&str>,
+    ) -> Option<JobResult> {
+        self.get(request_id)
+            .filter(|job| job.client_id.as_deref() == client_id && job.tenant.as_deref() == tenant)
+    }
+
+    /// Number of tracked jobs.
+    pub fn len(&self) -> usize {
+        self.jobs().len()
+    }
+
+    /// Whether no jobs are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// Output processor - converts raw outputs to user-facing results.
 pub struct OutputProcessor {
     /// Sample rate for audio output
@@ -98,6 +320,22 @@ pub struct OutputProcessor {
     streaming_chunk_size: usize,
     /// Active streaming sessions
     streaming_sessions: HashMap<RequestId, StreamingSession>,
+    /// Global accounting of buffered session audio
+    memory: Arc<OutputMemoryTracker>,
+    /// Per-session buffer cap in bytes (0 = unlimited)
+    max_session_bytes: usize,
+    /// Behaviour when a buffer cap is reached
+    overflow_policy: OverflowPolicy,
+    /// Behaviour when a stream's channel is full
+    backpressure: StreamBackpressure,
+    /// Finished sessions whose last chunks are still waiting for room
+    draining: Vec<StreamingSession>,
+    /// Time source for session timing
+    clock: SharedClock,
+    /// Results of asynchronous jobs
+    results: Arc<ResultStore>,
+    /// Cache of finished audio, when enabled
+    cache: Option<Arc<OutputCache>>,
 }
 
 /// State for an active streaming session.
@@ -109,6 +347,47 @@ struct StreamingSession {
     chunks_sent: usize,
     total_samples_sent: usize,
     tx: mpsc::Sender<StreamingOutput>,
+    /// Chunks waiting for room in the channel
+    pending: VecDeque<StreamingOutput>,
+    /// Chunks discarded under `StreamBackpressure::DropOldest`
+    dropped_chunks: usize,
+}
+
+impl StreamingSession {
+    /// Move pending chunks into the channel while it has room. Returns false
+    /// once the consumer is gone.
+    fn flush(&mut self) -> bool {
+        while let Some(output) = self.pending.pop_front() {
+            match self.tx.try_send(output) {
+                Ok(()) => {}
+                Err(TrySendError::Full(output)) => {
+                    self.pending.push_front(output);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => return false,
+            }
+        }
+        true
+    }
+
+    /// Hand `output` to the consumer under `policy`. Returns false once the
+    /// consumer is gone.
+    async fn deliver(&mut self, output: StreamingOutput, policy: StreamBackpressure) -> bool {
+        if policy == StreamBackpressure::Block {
+            return self.tx.send(output).await.is_ok();
+        }
+        self.pending.push_back(output);
+        if !self.flush() {
+            return false;
+        }
+        if policy == StreamBackpressure::DropOldest {
+            while self.pending.len() > self.tx.max_capacity() {
+                self.pending.pop_front();
+                self.dropped_chunks += 1;
+            }
+        }
+        true
+    }
 }
 
 impl OutputProcessor {
@@ -118,9 +397,76 @@ impl OutputProcessor {
             sample_rate,
             streaming_chunk_size: 4800, // 200ms at 24kHz
             streaming_sessions: HashMap::new(),
+            memory: Arc::new(OutputMemoryTracker::default()),
+            max_session_bytes: 0,
+            overflow_policy: OverflowPolicy::default(),
+            backpressure: StreamBackpressure::default(),
+            draining: Vec::new(),
+            clock: clock::system_clock(),
+            results: Arc::new(ResultStore::new(Duration::from_secs(3600))),
+            cache: None,
         }
     }
 
+    /// Use `clock` for session timing.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.results = Arc::new(ResultStore::new(self.results.ttl).with_clock(clock.clone()));
+        self.clock = clock;
+        self
+    }
+
+    /// Keep finished job results for `ttl`.
+    pub fn with_result_ttl(mut self, ttl: Duration) -> Self {
+        self.results = Arc::new(ResultStore::new(ttl).with_clock(self.clock.clone()));
+        self
+    }
+
+    /// Store of asynchronous job results.
+    pub fn results(&self) -> Arc<ResultStore> {
+        self.results.clone()
+    }
+
+    /// Cache the audio of finished requests.
+    pub fn with_cache(mut self, cache: Arc<OutputCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Output cache, when enabled.
+    pub fn cache(&self) -> Option<Arc<OutputCache>> {
+        self.cache.clone()
+    }
+
+    /// Set the per-session buffer cap.
+    pub fn with_memory_limits(mut self, max_session_bytes: usize, policy: OverflowPolicy) -> Self {
+        self.max_session_bytes = max_session_bytes;
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Account buffered session audio against `memory`, which holds the
+    /// global cap.
+    pub fn with_memory_tracker(mut self, memory: Arc<OutputMemoryTracker>) -> Self {
+        self.set_memory_tracker(memory);
+        self
+    }
+
+    /// Account later session audio against `memory`.
+    pub fn set_memory_tracker(&mut self, memory: Arc<OutputMemoryTracker>) {
+        self.memory = memory;
+    }
+
+    /// Set what streams do when their consumer falls behind.
+    pub fn with_backpressure(mut self, policy: StreamBackpressure) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    /// Current output buffer memory usage.
+    pub fn memory_stats(&self) -> OutputMemoryStats {
+        self.memory.stats()
+    }
+
     /// Set streaming chunk size.
     pub fn with_chunk_size(mut self, size: usize) -> Self {
         self.streaming_chunk_size = size;
@@ -137,15 +483,36 @@ impl OutputProcessor {
         let finish_reason = if executor_output.error.is_some() {
             Some(FinishReason::Error)
         } else if executor_output.finished {
-            Some(FinishReason::StopToken)
+            executor_output
+                .finish_reason
+                .or(Some(FinishReason::StopToken))
         } else {
             None
         };
 
-        let audio = executor_output.audio.unwrap_or_else(|| AudioOutput::empty(self.sample_rate));
+        let audio = executor_output
+            .audio
+            .unwrap_or_else(|| AudioOutput::empty(self.sample_rate));
+        let done = executor_output.finished || executor_output.error.is_some();
+        if done && self.results.contains(&executor_output.request_id) {
+            match &executor_output.error {
+                Some(error) => self
+                    .results
+                    .fail(&executor_output.request_id, error.clone()),
+                None => self
+                    .results
+                    .complete(&executor_output.request_id, audio.clone()),
+            }
+        }
+        if let Some(cache) = self.cache.as_ref().filter(|_| done) {
+            match &executor_output.error {
+                Some(_) => cache.forget(&executor_output.request_id),
+                None => cache.fulfil(&executor_output.request_id, &audio),
+            }
+        }
         let num_tokens = executor_output.tokens_generated.max(
             // Estimate tokens from audio length if not provided
-            (audio.samples.len() / 256).max(1)
+            (audio.samples.len() / 256).max(1),
         );
 
         let token_stats = TokenStats {
@@ -170,6 +537,9 @@ impl OutputProcessor {
             is_finished: executor_output.finished,
             finish_reason,
             token_stats,
+            timings: OutputTimings::default(),
+            candidates: executor_output.candidates,
+            logprobs: executor_output.logprobs,
         }
     }
 
@@ -183,11 +553,13 @@ impl OutputProcessor {
         let session = StreamingSession {
             request_id: request_id.clone(),
             sequence_id,
-            start_time: Instant::now(),
+            start_time: self.clock.now(),
             samples_buffer: Vec::new(),
             chunks_sent: 0,
             total_samples_sent: 0,
             tx,
+            pending: VecDeque::new(),
+            dropped_chunks: 0,
         };
         self.streaming_sessions.insert(request_id, session);
     }
@@ -203,38 +575,66 @@ impl OutputProcessor {
             None => return false,
         };
 
-        session.samples_buffer.extend(samples);
+        let buffer = &mut session.samples_buffer;
+        let keep = match reserve_samples(
+            buffer.len(),
+            samples.len(),
+            self.max_session_bytes,
+            Some(&self.memory),
+            self.overflow_policy,
+            |count| {
+                buffer.drain(..count);
+            },
+        ) {
+            Ok(keep) => keep,
+            Err(reason) => {
+                warn!("Cancelling stream {}: {}", request_id, reason);
+                self.cancel_streaming(request_id);
+                return false;
+            }
+        };
+        session
+            .samples_buffer
+            .extend_from_slice(&samples[samples.len() - keep..]);
 
-        // Send chunks when buffer is large enough
-        while session.samples_buffer.len() >= self.streaming_chunk_size {
-            let chunk_samples: Vec<f32> = session.samples_buffer
-                .drain(..self.streaming_chunk_size)
-                .collect();
+        // Send chunks when buffer is large enough; the whole chunks are split
+        // off at once and sent as slices of one shared buffer
+        let chunk_size = self.streaming_chunk_size;
+        let ready = session.samples_buffer.len() / chunk_size * chunk_size;
+        if ready == 0 {
+            return true;
+        }
+        let rest = session.samples_buffer.split_off(ready);
+        let ready = SampleBuffer::from(std::mem::replace(&mut session.samples_buffer, rest));
+        for start in (0..ready.len()).step_by(chunk_size) {
+            let chunk_samples = ready.slice(start..start + chunk_size);
+            self.memory.release(chunk_samples.len() * BYTES_PER_SAMPLE);
 
             let stats = StreamingStats {
                 total_samples: session.total_samples_sent + chunk_samples.len(),
-                total_duration_secs: (session.total_samples_sent + chunk_samples.len()) as f32 
+                total_duration_secs: (session.total_samples_sent + chunk_samples.len()) as f32
                     / self.sample_rate as f32,
                 chunks_sent: session.chunks_sent + 1,
-                elapsed_secs: session.start_time.elapsed().as_secs_f32(),
-                rtf: session.start_time.elapsed().as_secs_f32() 
-                    / ((session.total_samples_sent + chunk_samples.len()) as f32 / self.sample_rate as f32),
+                elapsed_secs: self.clock.elapsed_since(session.start_time).as_secs_f32(),
+                rtf: self.clock.elapsed_since(session.start_time).as_secs_f32()
+                    / ((session.total_samples_sent + chunk_samples.len()) as f32
+                        / self.sample_rate as f32),
             };
 
+            session.total_samples_sent += chunk_samples.len();
             let output = StreamingOutput {
                 request_id: session.request_id.clone(),
                 sequence: session.chunks_sent,
-                samples: chunk_samples.clone(),
+                samples: chunk_samples,
                 sample_rate: self.sample_rate,
                 is_final: false,
                 text: None,
                 stats: Some(stats),
             };
 
-            session.total_samples_sent += chunk_samples.len();
             session.chunks_sent += 1;
 
-            if session.tx.send(output).await.is_err() {
+            if !session.deliver(output, self.backpressure).await {
                 debug!("Streaming channel closed for {}", request_id);
                 return false;
             }
@@ -249,19 +649,21 @@ impl OutputProcessor {
         request_id: &RequestId,
         text: Option<String>,
     ) -> Option<StreamingStats> {
-        let session = self.streaming_sessions.remove(request_id)?;
+        let mut session = self.streaming_sessions.remove(request_id)?;
 
         // Send remaining samples as final chunk
-        let remaining_samples = session.samples_buffer;
+        let remaining_samples = std::mem::take(&mut session.samples_buffer);
+        self.memory
+            .release(remaining_samples.len() * BYTES_PER_SAMPLE);
         let total_samples = session.total_samples_sent + remaining_samples.len();
 
         let stats = StreamingStats {
             total_samples,
             total_duration_secs: total_samples as f32 / self.sample_rate as f32,
             chunks_sent: session.chunks_sent + 1,
-            elapsed_secs: session.start_time.elapsed().as_secs_f32(),
+            elapsed_secs: self.clock.elapsed_since(session.start_time).as_secs_f32(),
             rtf: if total_samples > 0 {
-                session.start_time.elapsed().as_secs_f32() 
+                self.clock.elapsed_since(session.start_time).as_secs_f32()
                     / (total_samples as f32 / self.sample_rate as f32)
             } else {
                 0.0
@@ -269,23 +671,58 @@ impl OutputProcessor {
         };
 
         let output = StreamingOutput {
-            request_id: session.request_id,
+            request_id: session.request_id.clone(),
             sequence: session.chunks_sent,
-            samples: remaining_samples,
+            samples: remaining_samples.into(),
             sample_rate: self.sample_rate,
             is_final: true,
             text,
             stats: Some(stats.clone()),
         };
 
-        let _ = session.tx.send(output).await;
+        if session.dropped_chunks > 0 {
+            warn!(
+                "Stream {} dropped {} chunks its consumer could not keep up with",
+                request_id, session.dropped_chunks
+            );
+        }
+        if session.deliver(output, self.backpressure).await && !session.pending.is_empty() {
+            self.draining.push(session);
+        }
 
         Some(stats)
     }
 
     /// Cancel a streaming session.
     pub fn cancel_streaming(&mut self, request_id: &RequestId) {
-        self.streaming_sessions.remove(request_id);
+        if let Some(session) = self.streaming_sessions.remove(request_id) {
+            self.memory
+                .release(session.samples_buffer.len() * BYTES_PER_SAMPLE);
+        }
+    }
+
+    /// Retry delivery of chunks held back by full channels. Returns the
+    /// streams still backed up under `StreamBackpressure::Pause`, whose
+    /// requests should not be decoded this step.
+    pub fn flush_streams(&mut self) -> HashSet<RequestId> {
+        self.draining
+            .retain_mut(|session| session.flush() && !session.pending.is_empty());
+        let mut stalled = HashSet::new();
+        for (request_id, session) in &mut self.streaming_sessions {
+            // A closed channel is noticed on the next delivery
+            if session.flush()
+                && !session.pending.is_empty()
+                && self.backpressure == StreamBackpressure::Pause
+            {
+                stalled.insert(request_id.clone());
+            }
+        }
+        stalled
+    }
+
+    /// Whether finished streams still hold undelivered chunks.
+    pub fn has_draining_streams(&self) -> bool {
+        !self.draining.is_empty()
     }
 
     /// Check if a streaming session is active.
@@ -307,6 +744,10 @@ pub struct StopChecker {
     max_tokens: usize,
     /// Maximum sequence length
     max_seq_len: usize,
+    /// Text that ends generation (excluded from the output)
+    stop_sequences: Vec<String>,
+    /// Longest audio to return, in seconds
+    max_audio_seconds: Option<f32>,
 }
 
 impl StopChecker {
@@ -316,9 +757,37 @@ impl StopChecker {
             stop_token_ids,
             max_tokens,
             max_seq_len,
+            stop_sequences: Vec::new(),
+            max_audio_seconds: None,
         }
     }
 
+    /// Stop checker for a request's generation parameters.
+    pub fn for_params(params: &GenerationParams, max_seq_len: usize) -> Self {
+        Self::new(
+            params.stop_token_ids.clone(),
+            params.max_tokens,
+            max_seq_len,
+        )
+        .with_stop_sequences(params.stop_sequences.clone())
+        .with_max_audio_seconds(params.max_audio_seconds)
+    }
+
+    /// Set text that ends generation.
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.stop_sequences = stop_sequences
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect();
+        self
+    }
+
+    /// Set the longest audio to return.
+    pub fn with_max_audio_seconds(mut self, max_audio_seconds: Option<f32>) -> Self {
+        self.max_audio_seconds = max_audio_seconds;
+        self
+    }
+
     /// Check if generation should stop.
     pub fn should_stop(
         &self,
@@ -345,6 +814,55 @@ impl StopChecker {
 
         None
     }
+
+    /// Cut `text` before the earliest stop sequence, if it contains one.
+    pub fn truncate_text(&self, text: &mut String) -> bool {
+        match self
+            .stop_sequences
+            .iter()
+            .filter_map(|s| text.find(s))
+            .min()
+        {
+            Some(at) => {
+                text.truncate(at);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply the stop conditions to one step of executor output, given the
+    /// tokens generated in earlier steps. Output past a stop sequence or
+    /// the audio length limit is dropped and the request finished.
+    pub fn apply(&self, output: &mut ExecutorOutput, generated_before: usize) {
+        if output.error.is_some() {
+            return;
+        }
+        let mut reason = None;
+        if let Some(text) = output.text.as_mut() {
+            if self.truncate_text(text) {
+                reason = Some(FinishReason::StopSequence);
+            }
+        }
+        if let (Some(secs), Some(audio)) = (self.max_audio_seconds, output.audio.as_mut()) {
+            let rate = audio.sample_rate as f32;
+            let before = (generated_before as f32 * rate / TOKEN_RATE_HZ) as usize;
+            let allowed = ((secs * rate) as usize).saturating_sub(before);
+            if audio.samples.len() > allowed {
+                audio.samples.truncate(allowed);
+                audio.duration_secs = allowed as f32 / rate;
+                reason = reason.or(Some(FinishReason::MaxTokens));
+            }
+        }
+        let generated = generated_before + output.tokens_generated;
+        if output.tokens_generated > 0 && generated >= self.max_tokens {
+            reason = reason.or(Some(FinishReason::MaxTokens));
+        }
+        if reason.is_some() {
+            output.finished = true;
+            output.finish_reason = reason;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -357,29 +875,172 @@ mod tests {
         assert_eq!(processor.sample_rate, 24000);
     }
 
+    #[test]
+    fn test_result_store_lifecycle_and_ttl() {
+        let clock = Arc::new(clock::MockClock::new());
+        let store = ResultStore::new(Duration::from_secs(60)).with_clock(clock.clone());
+        let (done, failed) = ("done".to_string(), "failed".to_string());
+        store.submit(&done, Some("acme-key"), Some("acme"));
+        store.submit(&failed, None, None);
+        assert_eq!(store.get(&done).unwrap().status, JobStatus::Queued);
+
+        store.start(&done);
+        assert_eq!(store.get(&done).unwrap().status, JobStatus::Running);
+        store.complete(&done, AudioOutput::new(vec![0.0; 240], 24000));
+        store.fail(&failed, "boom");
+        let job = store.get(&done).unwrap();
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.audio.unwrap().samples.len(), 240);
+        assert_eq!(store.get(&failed).unwrap().error.as_deref(), Some("boom"));
+
+        // Jobs are only found by the key and tenant that submitted them
+        assert!(store
+            .get_owned(&done, Some("acme-key"), Some("acme"))
+            .is_some());
+        assert!(store
+            .get_owned(&done, Some("other-key"), Some("acme"))
+            .is_none());
+        assert!(store.get_owned(&done, Some("acme-key"), None).is_none());
+
+        // Unknown jobs are not tracked
+        store.complete(&"other".to_string(), AudioOutput::empty(24000));
+        assert!(store.get(&"other".to_string()).is_none());
+
+        // Spooled outputs are stored as files, deleted once they expire
+        let dir = std::env::temp_dir().join(format!("izwi-results-{}", uuid::Uuid::new_v4()));
+        let long = "long".to_string();
+        store.submit(&long, None, None);
+        let mut spool = crate::audio::AudioSpool::create(&dir, &long, 24000).unwrap();
+        spool.write(&[0.0; 240]).unwrap();
+        store.complete_spooled(&long, spool.finish().unwrap());
+        let path = store.get(&long).unwrap().spooled.unwrap().path.clone();
+        assert!(path.exists());
+
+        // Finished results expire after the TTL
+        clock.advance(Duration::from_secs(61));
+        assert!(store.is_empty());
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_memory_limits() {
+        let mut processor = OutputProcessor::new(24000)
+            .with_chunk_size(1000)
+            .with_memory_limits(800, OverflowPolicy::Reject);
+        let (tx, mut rx) = mpsc::channel(8);
+        let id = "req".to_string();
+        processor.start_streaming(id.clone(), 0, tx);
+
+        // 150 samples fit within the 200-sample cap
+        assert!(processor.add_streaming_samples(&id, vec![0.0; 150]).await);
+        assert_eq!(processor.memory_stats().used_bytes, 600);
+
+        // Exceeding the cap cancels the stream and frees its buffer
+        assert!(!processor.add_streaming_samples(&id, vec![0.0; 100]).await);
+        assert!(!processor.is_streaming(&id));
+        assert_eq!(processor.memory_stats().used_bytes, 0);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_backpressure_policies() {
+        let id = "req".to_string();
+        let stream = |policy| {
+            let mut processor = OutputProcessor::new(24000)
+                .with_chunk_size(100)
+                .with_backpressure(policy);
+            let (tx, rx) = mpsc::channel(2);
+            processor.start_streaming(id.clone(), 0, tx);
+            (processor, rx)
+        };
+
+        // A stalled consumer keeps the newest chunks without blocking the step
+        let (mut processor, mut rx) = stream(StreamBackpressure::DropOldest);
+        assert!(processor.add_streaming_samples(&id, vec![0.0; 600]).await);
+        processor.finish_streaming(&id, None).await;
+        assert!(processor.has_draining_streams());
+        let received: Vec<usize> = std::iter::from_fn(|| {
+            let chunk = rx.try_recv().ok();
+            processor.flush_streams();
+            chunk.map(|c| c.sequence)
+        })
+        .collect();
+        assert_eq!(received, [0, 1, 5, 6]);
+        assert!(!processor.has_draining_streams());
+
+        // Pausing reports the stream as stalled until its consumer reads
+        let (mut processor, mut rx) = stream(StreamBackpressure::Pause);
+        assert!(processor.add_streaming_samples(&id, vec![0.0; 300]).await);
+        assert!(processor.flush_streams().contains(&id));
+        rx.recv().await.unwrap();
+        assert!(processor.flush_streams().is_empty());
+
+        // A closed channel ends the stream
+        drop(rx);
+        assert!(!processor.add_streaming_samples(&id, vec![0.0; 100]).await);
+    }
+
     #[test]
     fn test_stop_checker() {
         let checker = StopChecker::new(vec![151673], 100, 1000);
-        
+
         // Should not stop
         assert!(checker.should_stop(50, 100, Some(12345)).is_none());
-        
+
         // Should stop - max tokens
-        assert_eq!(checker.should_stop(100, 150, None), Some(FinishReason::MaxTokens));
-        
+        assert_eq!(
+            checker.should_stop(100, 150, None),
+            Some(FinishReason::MaxTokens)
+        );
+
         // Should stop - stop token
-        assert_eq!(checker.should_stop(50, 100, Some(151673)), Some(FinishReason::StopToken));
+        assert_eq!(
+            checker.should_stop(50, 100, Some(151673)),
+            Some(FinishReason::StopToken)
+        );
+    }
+
+    #[test]
+    fn test_stop_checker_sequences_and_audio_length() {
+        let output = |text: &str, samples: usize| ExecutorOutput {
+            request_id: "req".to_string(),
+            audio: Some(AudioOutput::new(vec![0.1; samples], 24000)),
+            text: Some(text.to_string()),
+            tokens_processed: 0,
+            tokens_generated: 0,
+            finished: false,
+            finish_reason: None,
+            error: None,
+            candidates: Vec::new(),
+            logprobs: Vec::new(),
+        };
+        let checker = StopChecker::new(Vec::new(), 2048, 4096)
+            .with_stop_sequences(vec!["\n\n".to_string(), "END".to_string()])
+            .with_max_audio_seconds(Some(1.0));
+
+        let mut within = output("Hello there", 12000);
+        checker.apply(&mut within, 0);
+        assert!(!within.finished);
+
+        let mut stopped = output("Hello END and\n\nmore", 12000);
+        checker.apply(&mut stopped, 0);
+        assert!(stopped.finished);
+        assert_eq!(stopped.text.as_deref(), Some("Hello "));
+        assert_eq!(stopped.finish_reason, Some(FinishReason::StopSequence));
+
+        // 10 tokens were already generated (0.8s at 12.5Hz), leaving 0.2s
+        let mut long = output("", 24000);
+        checker.apply(&mut long, 10);
+        assert!(long.finished);
+        assert_eq!(long.audio.as_ref().unwrap().samples.len(), 4800);
+        assert_eq!(long.finish_reason, Some(FinishReason::MaxTokens));
     }
 
     #[test]
     fn test_streaming_output() {
-        let chunk = StreamingOutput::new(
-            "test-req".to_string(),
-            0,
-            vec![0.0; 4800],
-            24000,
-        );
-        
+        let chunk = StreamingOutput::new("test-req".to_string(), 0, vec![0.0; 4800], 24000);
+
         assert_eq!(chunk.duration_secs(), 0.2); // 4800 samples at 24kHz = 200ms
     }
 }
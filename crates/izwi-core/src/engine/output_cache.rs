@@ -0,0 +1,545 @@
+//! Content-addressed cache of synthesized audio.
+//!
+//! Identical TTS requests (same text, voice, model and generation
+//! parameters) are answered with the audio synthesized the first time.
+//! Entries live in an in-memory LRU bounded by bytes and, optionally, in a
+//! disk tier that survives restarts; disk hits are promoted into memory.
+//! Audio made for a tenant is keyed by the tenant, kept in memory only
+//! (the disk tier is not encrypted), and dropped when the tenant is purged.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+
+use super::request::EngineCoreRequest;
+use super::types::{AudioOutput, RequestId};
+use crate::config::OutputCacheConfig;
+use crate::error::Result;
+use crate::inference::GenerationRequest;
+
+/// Extension of audio files in the disk tier
+const DISK_EXTENSION: &str = "f32";
+
+/// How a request may use the output cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheControl {
+    /// Serve from the cache and store new audio
+    #[default]
+    Default,
+    /// Always synthesize, but store the result
+    NoCache,
+    /// Neither read from nor write to the cache
+    NoStore,
+}
+
+impl CacheControl {
+    /// Whether a cached result may be returned
+    pub fn reads(self) -> bool {
+        self == Self::Default
+    }
+
+    /// Whether the result may be stored
+    pub fn writes(self) -> bool {
+        self != Self::NoStore
+    }
+}
+
+/// Hash of everything that determines the synthesized audio, and the
+/// tenant it was made for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    digest: String,
+    tenant: Option<String>,
+}
+
+impl CacheKey {
+    /// Key over the tenant, text, voice, model and remaining generation
+    /// inputs
+    pub fn new(
+        tenant: Option<&str>,
+        text: &str,
+        voice: Option<&str>,
+        model: Option<&str>,
+        params: &impl Serialize,
+    ) -> Self {
+        let inputs = serde_json::json!([tenant, text, voice, model, params]);
+        let digest = Sha256::digest(serde_json::to_vec(&inputs).unwrap_or_default());
+        Self {
+            digest: format!("{:x}", digest),
+            tenant: tenant.map(str::to_string),
+        }
+    }
+
+    /// Key for an engine request
+    pub fn for_request(request: &EngineCoreRequest, tenant: Option<&str>) -> Self {
+        Self::derive(request, tenant, &serde_json::Value::Null)
+    }
+
+    /// Key for a request to the inference engine: that of the engine
+    /// request it becomes, plus the text processing and settings the engine
+    /// request does not carry. `extra` holds inputs from outside the request
+    /// that change the audio served, such as the lexicon in use.
+    pub fn for_generation(
+        request: &GenerationRequest,
+        tenant: Option<&str>,
+        extra: &impl Serialize,
+    ) -> Self {
+        let inputs = serde_json::json!({
+            "config": request.config,
+            "language": request.language,
+            "pronunciations": request.pronunciations,
+            "profanity": request.profanity,
+            "spell_out": request.spell_out,
+            "extra": extra,
+        });
+        Self::derive(&EngineCoreRequest::from(request.clone()), tenant, &inputs)
+    }
+
+    fn derive(request: &EngineCoreRequest, tenant: Option<&str>, inputs: &impl Serialize) -> Self {
+        let voice = request
+            .params
+            .speaker
+            .as_deref()
+            .or(request.params.voice.as_deref());
+        let model = request.model.map(|m| m.to_string());
+        Self::new(
+            tenant,
+            request.text.as_deref().unwrap_or_default(),
+            voice,
+            model.as_deref(),
+            &serde_json::json!({
+                "model_type": request.model_type,
+                "params": request.params,
+                "reference_audio": request.reference_audio,
+                "reference_text": request.reference_text,
+                "voice_description": request.voice_description,
+                "inputs": inputs,
+            }),
+        )
+    }
+
+    /// Untenanted key of a file in the disk tier
+    fn from_disk(digest: String) -> Self {
+        Self {
+            digest,
+            tenant: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.digest
+    }
+
+    /// Tenant the audio was made for
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+}
+
+/// Cache counters.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    /// Lookups answered from memory
+    pub hits: u64,
+    /// Lookups answered from the disk tier
+    pub disk_hits: u64,
+    pub misses: u64,
+    pub insertions: u64,
+    /// Entries evicted from memory
+    pub evictions: u64,
+    /// Entries in memory
+    pub entries: usize,
+    pub memory_bytes: usize,
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from either tier
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits + self.disk_hits;
+        let total = hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    }
+}
+
+struct MemoryEntry {
+    audio: Arc<AudioOutput>,
+    /// Recency tick, key into `CacheState::recency`
+    tick: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    memory: HashMap<CacheKey, MemoryEntry>,
+    /// Memory entries by last use, least recent first
+    recency: BTreeMap<u64, CacheKey>,
+    next_tick: u64,
+    /// Disk entries and their file sizes
+    disk: HashMap<CacheKey, u64>,
+    /// Disk entries by insertion, oldest first
+    disk_order: BTreeMap<u64, CacheKey>,
+    /// Requests whose audio should be stored once they finish
+    pending: HashMap<RequestId, CacheKey>,
+    stats: CacheStats,
+}
+
+impl CacheState {
+    fn tick(&mut self) -> u64 {
+        self.next_tick += 1;
+        self.next_tick
+    }
+}
+
+/// Output cache with a memory tier and an optional disk tier.
+pub struct OutputCache {
+    config: OutputCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl std::fmt::Debug for OutputCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputCache")
+            .field("config", &self.config)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl OutputCache {
+    /// Open the cache, indexing audio already in the disk tier.
+    pub fn open(config: OutputCacheConfig) -> Result<Self> {
+        let mut state = CacheState::default();
+        if let Some(dir) = &config.disk_dir {
+            fs::create_dir_all(dir)?;
+            let mut files = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some(DISK_EXTENSION) {
+                    continue;
+                }
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                let metadata = entry.metadata()?;
+                files.push((metadata.modified().ok(), stem.to_string(), metadata.len()));
+            }
+            files.sort();
+            for (_, key, size) in files {
+                let tick = state.tick();
+                state
+                    .disk_order
+                    .insert(tick, CacheKey::from_disk(key.clone()));
+                state.disk.insert(CacheKey::from_disk(key), size);
+                state.stats.disk_bytes += size;
+            }
+            state.stats.disk_entries = state.disk.len();
+            info!(
+                "Output cache disk tier at {:?} ({} entries)",
+                dir, state.stats.disk_entries
+            );
+        }
+        Ok(Self {
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// File of `key` in the disk tier; `None` without one, and for tenant
+    /// audio, which is never written to disk in the clear
+    fn disk_path(&self, key: &CacheKey) -> Option<PathBuf> {
+        if key.tenant.is_some() {
+            return None;
+        }
+        self.config
+            .disk_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.{}", key.as_str(), DISK_EXTENSION)))
+    }
+
+    /// Cached audio for `key`, counting a hit or miss.
+    pub fn get(&self, key: &CacheKey) -> Option<Arc<AudioOutput>> {
+        {
+            let mut state = self.lock();
+            let tick = state.tick();
+            if let Some(entry) = state.memory.get_mut(key) {
+                let old = std::mem::replace(&mut entry.tick, tick);
+                let audio = entry.audio.clone();
+                state.recency.remove(&old);
+                state.recency.insert(tick, key.clone());
+                state.stats.hits += 1;
+                return Some(audio);
+            }
+            if !state.disk.contains_key(key) {
+                state.stats.misses += 1;
+                return None;
+            }
+        }
+
+        // Read outside the lock; a file removed meanwhile is a miss
+        let audio = self
+            .disk_path(key)
+            .and_then(|path| match read_audio(&path) {
+                Ok(audio) => Some(Arc::new(audio)),
+                Err(e) => {
+                    warn!("Dropping unreadable cache file {:?}: {}", path, e);
+                    None
+                }
+            });
+        let mut state = self.lock();
+        match audio {
+            Some(audio) => {
+                state.stats.disk_hits += 1;
+                self.insert_memory(&mut state, key.clone(), audio.clone());
+                Some(audio)
+            }
+            None => {
+                if let Some(size) = state.disk.remove(key) {
+                    state.disk_order.retain(|_, k| k != key);
+                    state.stats.disk_bytes -= size;
+                    state.stats.disk_entries = state.disk.len();
+                }
+                state.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store audio under `key` in both tiers.
+    pub fn put(&self, key: CacheKey, audio: AudioOutput) {
+        let audio = Arc::new(audio);
+        let written = match self.disk_path(&key) {
+            Some(path) => match write_audio(&path, &audio) {
+                Ok(size) => Some(size),
+                Err(e) => {
+                    warn!("Failed to write cache file {:?}: {}", path, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut state = self.lock();
+        state.stats.insertions += 1;
+        if let Some(size) = written {
+            self.insert_disk(&mut state, key.clone(), size);
+        }
+        self.insert_memory(&mut state, key, audio);
+    }
+
+    fn insert_memory(&self, state: &mut CacheState, key: CacheKey, audio: Arc<AudioOutput>) {
+        let size = audio_bytes(&audio);
+        let limit = self.config.max_memory_bytes;
+        if size > limit {
+            return;
+        }
+        let tick = state.tick();
+        if let Some(old) = state
+            .memory
+            .insert(key.clone(), MemoryEntry { audio, tick })
+        {
+            state.recency.remove(&old.tick);
+            state.stats.memory_bytes -= audio_bytes(&old.audio);
+        }
+        state.recency.insert(tick, key);
+        state.stats.memory_bytes += size;
+
+        while state.stats.memory_bytes > limit {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = state.memory.remove(&oldest) {
+                state.stats.memory_bytes -= audio_bytes(&entry.audio);
+                state.stats.evictions += 1;
+            }
+        }
+        state.stats.entries = state.memory.len();
+    }
+
+    fn insert_disk(&self, state: &mut CacheState, key: CacheKey, size: u64) {
+        if let Some(old) = state.disk.insert(key.clone(), size) {
+            state.disk_order.retain(|_, k| k != &key);
+            state.stats.disk_bytes -= old;
+        }
+        let tick = state.tick();
+        state.disk_order.insert(tick, key);
+        state.stats.disk_bytes += size;
+
+        let limit = self.config.max_disk_bytes as u64;
+        while limit > 0 && state.stats.disk_bytes > limit {
+            let Some((_, oldest)) = state.disk_order.pop_first() else {
+                break;
+            };
+            if let Some(size) = state.disk.remove(&oldest) {
+                state.stats.disk_bytes -= size;
+                if let Some(path) = self.disk_path(&oldest) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        state.stats.disk_entries = state.disk.len();
+    }
+
+    /// Remember to store the audio of `request_id` under `key` when it
+    /// finishes.
+    pub fn expect(&self, request_id: &RequestId, key: CacheKey) {
+        self.lock().pending.insert(request_id.clone(), key);
+    }
+
+    /// Store the finished audio of an expected request
+    pub fn fulfil(&self, request_id: &RequestId, audio: &AudioOutput) {
+        let key = self.lock().pending.remove(request_id);
+        if let Some(key) = key {
+            debug!("Caching output of {} as {}", request_id, key.as_str());
+            self.put(key, audio.clone());
+        }
+    }
+
+    /// Stop expecting a request that ended without usable audio
+    pub fn forget(&self, request_id: &RequestId) {
+        self.lock().pending.remove(request_id);
+    }
+
+    /// Drop every entry made for `tenant`, returning how many there were
+    pub fn purge_tenant(&self, tenant: &str) -> usize {
+        let mut state = self.lock();
+        let purged: Vec<CacheKey> = state
+            .memory
+            .keys()
+            .filter(|key| key.tenant() == Some(tenant))
+            .cloned()
+            .collect();
+        for key in &purged {
+            if let Some(entry) = state.memory.remove(key) {
+                state.recency.remove(&entry.tick);
+                state.stats.memory_bytes -= audio_bytes(&entry.audio);
+            }
+        }
+        state.pending.retain(|_, key| key.tenant() != Some(tenant));
+        state.stats.entries = state.memory.len();
+        purged.len()
+    }
+
+    /// Current counters and sizes
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats.clone()
+    }
+}
+
+fn audio_bytes(audio: &AudioOutput) -> usize {
+    audio.samples.len() * std::mem::size_of::<f32>()
+}
+
+/// Write the sample rate and little-endian f32 samples, replacing any
+/// existing file atomically; returns the file size
+fn write_audio(path: &Path, audio: &AudioOutput) -> Result<u64> {
+    let mut bytes = Vec::with_capacity(4 + audio_bytes(audio));
+    bytes.extend_from_slice(&audio.sample_rate.to_le_bytes());
+    for sample in &audio.samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, &bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(bytes.len() as u64)
+}
+
+fn read_audio(path: &Path) -> Result<AudioOutput> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < 4 || (bytes.len() - 4) % 4 != 0 {
+        return Err(crate::error::Error::AudioError(format!(
+            "Truncated cache file of {} bytes",
+            bytes.len()
+        )));
+    }
+    let sample_rate = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let samples = bytes[4..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Ok(AudioOutput::new(samples, sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio(len: usize) -> AudioOutput {
+        AudioOutput::new(vec![0.25; len], 24000)
+    }
+
+    #[test]
+    fn test_memory_lru_and_disk_tier() {
+        let dir = std::env::temp_dir().join(format!("izwi-cache-{}", uuid::Uuid::new_v4()));
+        let config = OutputCacheConfig {
+            enabled: true,
+            // Room for two 100-sample entries
+            max_memory_bytes: 800,
+            disk_dir: Some(dir.clone()),
+            max_disk_bytes: 0,
+        };
+        let cache = OutputCache::open(config.clone()).unwrap();
+        let key =
+            |text: &str| CacheKey::new(None, text, Some("Vivian"), None, &serde_json::json!({}));
+
+        assert!(cache.get(&key("a")).is_none());
+        cache.put(key("a"), audio(100));
+        cache.put(key("b"), audio(100));
+        assert!(cache.get(&key("a")).is_some());
+        // "b" is least recently used and leaves memory, but not the disk
+        cache.put(key("c"), audio(100));
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.evictions), (2, 1));
+        assert_eq!(cache.get(&key("b")).unwrap().samples.len(), 100);
+        assert_eq!(cache.stats().disk_hits, 1);
+
+        // The disk tier survives a restart
+        let reopened = OutputCache::open(config).unwrap();
+        assert_eq!(reopened.stats().disk_entries, 3);
+        assert_eq!(reopened.get(&key("c")).unwrap().sample_rate, 24000);
+        assert_ne!(key("a"), CacheKey::new(None, "a", Some("Ryan"), None, &()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tenant_entries() {
+        let dir = std::env::temp_dir().join(format!("izwi-cache-{}", uuid::Uuid::new_v4()));
+        let config = OutputCacheConfig {
+            enabled: true,
+            max_memory_bytes: 4000,
+            disk_dir: Some(dir.clone()),
+            max_disk_bytes: 0,
+        };
+        let cache = OutputCache::open(config).unwrap();
+        let request = GenerationRequest::new("Hello");
+        let key = |tenant| CacheKey::for_generation(&request, tenant, &());
+        assert_ne!(key(Some("acme")), key(Some("globex")));
+        assert_ne!(key(Some("acme")), key(None));
+
+        cache.put(key(Some("acme")), audio(100));
+        cache.put(key(Some("globex")), audio(100));
+        cache.put(key(None), audio(100));
+        // Tenant audio stays off the unencrypted disk tier
+        assert_eq!(cache.stats().disk_entries, 1);
+
+        assert_eq!(cache.purge_tenant("acme"), 1);
+        assert!(cache.get(&key(Some("acme"))).is_none());
+        assert!(cache.get(&key(Some("globex"))).is_some());
+        assert_eq!(cache.stats().entries, 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,865 @@
+//! Paged attention over the KV cache's block table.
+//!
+//! Keys and values are stored per layer in the blocks handed out by the
+//! [`KVCacheManager`](super::KVCacheManager), laid out
+//! `[block][slot][kv_head][head_dim]`. A decode step gathers a sequence's
+//! keys and values through its block table, so no sequence ever needs
+//! contiguous KV tensors and blocks shared by forked sequences are read in
+//! place. Query heads may outnumber KV heads (grouped-query attention).
+//!
+//! The CPU kernel keeps a running softmax so each token is visited once. On
+//! Apple Silicon the blocks live in shared Metal buffers: the host writes new
+//! tokens straight into them and a compute kernel reads them without copies.
+//!
+//! With the `int8` dtype each block of a layer's keys (and of its values)
+//! is stored as 8-bit integers sharing one scale. A token larger than the
+//! block has seen so far widens the scale and requantizes the block's
+//! earlier tokens; the kernels dequantize as they read.
+//!
+//! This module provides the storage and kernels only. The engine's sole
+//! executor, `PythonExecutor`, runs the forward pass in the Python daemons,
+//! which keep their own KV tensors, so nothing writes tokens into
+//! [`PagedAttention`] or calls [`PagedAttention::forward`] yet. A native
+//! executor would own one per model, write each step's keys and values at
+//! the slots the scheduler assigned, and replay every [`BlockWrite`]
+//! returned by
+//! [`KVCacheManager::copy_on_write`](super::KVCacheManager::copy_on_write)
+//! through [`PagedAttention::apply_block_write`] before attending.
+
+use tracing::{info, warn};
+
+use super::kv_cache::{BlockWrite, KVCacheConfig, KVCacheDtype};
+use super::types::BlockId;
+use crate::device::Device;
+use crate::error::{Error, Result};
+
+/// Least factor an int8 block's scale grows by, so that each rounding
+/// requantization of its earlier tokens covers a good step in range
+const SCALE_GROWTH: f32 = 1.25;
+
+/// Largest head dimension the GPU kernel accumulates in registers
+#[cfg(target_os = "macos")]
+const MAX_HEAD_DIM: usize = 256;
+
+/// Block-paged key/value storage with an attention kernel that reads it.
+pub struct PagedAttention {
+    num_heads: usize,
+    num_kv_heads: usize,
+    head_dim: usize,
+    block_size: usize,
+    num_blocks: usize,
+    scale: f32,
+    /// Keys and values of every layer
+    keys: Vec<Storage>,
+    values: Vec<Storage>,
+    #[cfg(target_os = "macos")]
+    metal: Option<metal_backend::MetalAttention>,
+}
+
+impl PagedAttention {
+    /// Storage for every block of `config`, attended by `num_heads` query
+    /// heads. `config.num_heads` is the number of KV heads and must divide
+    /// `num_heads`.
+    ///
+    /// The Metal kernel is used when `device` is Metal and one is present;
+    /// every other device attends on the CPU.
+    pub fn new(config: &KVCacheConfig, num_heads: usize, device: Device) -> Result<Self> {
+        let num_kv_heads = config.num_heads;
+        if num_kv_heads == 0 || !num_heads.is_multiple_of(num_kv_heads) {
+            return Err(Error::InvalidInput(format!(
+                "{} query heads cannot share {} KV heads",
+                num_heads, num_kv_heads
+            )));
+        }
+        #[cfg(target_os = "macos")]
+        let metal = match device {
+            Device::Metal if config.head_dim <= MAX_HEAD_DIM => {
+                match metal_backend::MetalAttention::new() {
+                    Ok(metal) => metal,
+                    Err(e) => {
+                        warn!("Metal paged attention unavailable, using CPU: {}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        #[cfg(not(target_os = "macos"))]
+        if device == Device::Metal {
+            warn!("Metal paged attention needs macOS, using CPU");
+        }
+
+        let mut attention = Self {
+            num_heads,
+            num_kv_heads,
+            head_dim: config.head_dim,
+            block_size: config.block_size,
+            num_blocks: config.max_blocks,
+            scale: 1.0 / (config.head_dim as f32).sqrt(),
+            keys: Vec::new(),
+            values: Vec::new(),
+            #[cfg(target_os = "macos")]
+            metal,
+        };
+        for _ in 0..config.num_layers {
+            let keys = attention.storage(config.dtype);
+            let values = attention.storage(config.dtype);
+            attention.keys.push(keys);
+            attention.values.push(values);
+        }
+        info!(
+            "Paged attention running on {} with {:?} storage",
+            attention.backend_name(),
+            config.dtype
+        );
+        Ok(attention)
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        #[cfg(target_os = "macos")]
+        if self.metal.is_some() {
+            return "metal";
+        }
+        "cpu"
+    }
+
+    /// Zeroed storage for one layer's keys or values
+    fn storage(&self, dtype: KVCacheDtype) -> Storage {
+        let len = self.num_blocks * self.block_len();
+        if dtype.is_quantized() {
+            Storage::Int8 {
+                data: self.buffer(len),
+                scales: self.buffer(self.num_blocks),
+            }
+        } else {
+            Storage::Float(self.buffer(len))
+        }
+    }
+
+    /// Zeroed buffer, in shared memory when the Metal kernel reads it
+    fn buffer<T: Copy + Default>(&self, len: usize) -> Buffer<T> {
+        #[cfg(target_os = "macos")]
+        if let Some(metal) = &self.metal {
+            return Buffer::Metal(metal.buffer(len));
+        }
+        Buffer::Host(vec![T::default(); len])
+    }
+
+    /// Elements held by one token of one layer's keys or values
+    fn token_len(&self) -> usize {
+        self.num_kv_heads * self.head_dim
+    }
+
+    /// Elements held by one block of one layer's keys or values
+    fn block_len(&self) -> usize {
+        self.block_size * self.token_len()
+    }
+
+    /// Offset of token `position` through `block_table` in a layer's storage
+    fn slot_offset(&self, block_table: &[BlockId], position: usize) -> Result<usize> {
+        let block = block_table
+            .get(position / self.block_size)
+            .copied()
+            .filter(|&block| block < self.num_blocks)
+            .ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Token {} is outside the sequence's {} KV blocks",
+                    position,
+                    block_table.len()
+                ))
+            })?;
+        Ok((block * self.block_size + position % self.block_size) * self.token_len())
+    }
+
+    fn check_layer(&self, layer: usize) -> Result<()> {
+        if layer >= self.keys.len() {
+            return Err(Error::InvalidInput(format!(
+                "Layer {} is outside the KV cache's {} layers",
+                layer,
+                self.keys.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Store the key and value of token `position` of a sequence for
+    /// `layer`. Both are `[kv_head][head_dim]`.
+    pub fn write(
+        &mut self,
+        layer: usize,
+        block_table: &[BlockId],
+        position: usize,
+        key: &[f32],
+        value: &[f32],
+    ) -> Result<()> {
+        self.check_layer(layer)?;
+        let len = self.token_len();
+        if key.len() != len || value.len() != len {
+            return Err(Error::InvalidInput(format!(
+                "Expected {} floats per key and value, got {} and {}",
+                len,
+                key.len(),
+                value.len()
+            )));
+        }
+        let offset = self.slot_offset(block_table, position)?;
+        let block_len = self.block_len();
+        self.keys[layer].write(offset, key, block_len);
+        self.values[layer].write(offset, value, block_len);
+        Ok(())
+    }
+
+    /// Mirror a [`KVCacheManager::copy_on_write`](super::KVCacheManager::copy_on_write)
+    /// by copying the shared block into its new owner's block.
+    pub fn apply_block_write(&mut self, write: BlockWrite) {
+        let BlockWrite::Copied { src, dst } = write else {
+            return;
+        };
+        let block_len = self.block_len();
+        for storage in self.keys.iter_mut().chain(self.values.iter_mut()) {
+            storage.copy_block(src, dst, block_len);
+        }
+    }
+
+    /// Attend `query` (`[head][head_dim]`) over the first `context_len`
+    /// tokens of a sequence, returning `[head][head_dim]`.
+    pub fn forward(
+        &self,
+        layer: usize,
+        query: &[f32],
+        block_table: &[BlockId],
+        context_len: usize,
+    ) -> Result<Vec<f32>> {
+        self.check_layer(layer)?;
+        if query.len() != self.num_heads * self.head_dim {
+            return Err(Error::InvalidInput(format!(
+                "Expected {} query floats, got {}",
+                self.num_heads * self.head_dim,
+                query.len()
+            )));
+        }
+        if context_len > 0 {
+            self.slot_offset(block_table, context_len - 1)?;
+        }
+        if let Some(&block) = block_table.iter().find(|&&block| block >= self.num_blocks) {
+            return Err(Error::InvalidInput(format!(
+                "Block {} is outside the KV cache's {} blocks",
+                block, self.num_blocks
+            )));
+        }
+
+        #[cfg(target_os = "macos")]
+        if let Some(metal) = &self.metal {
+            return metal.forward(
+                self,
+                &self.keys[layer],
+                &self.values[layer],
+                query,
+                block_table,
+                context_len,
+            );
+        }
+        Ok(self.forward_cpu(layer, query, block_table, context_len))
+    }
+
+    fn forward_cpu(
+        &self,
+        layer: usize,
+        query: &[f32],
+        block_table: &[BlockId],
+        context_len: usize,
+    ) -> Vec<f32> {
+        let (keys, values) = (&self.keys[layer], &self.values[layer]);
+        let (mut key_scratch, mut value_scratch) =
+            (vec![0.0f32; self.head_dim], vec![0.0f32; self.head_dim]);
+        let group = self.num_heads / self.num_kv_heads;
+        let mut output = vec![0.0f32; query.len()];
+
+        for head in 0..self.num_heads {
+            let q = &query[head * self.head_dim..(head + 1) * self.head_dim];
+            let out = &mut output[head * self.head_dim..(head + 1) * self.head_dim];
+            let head_offset = (head / group) * self.head_dim;
+            let mut max_score = f32::NEG_INFINITY;
+            let mut denom = 0.0f32;
+
+            for position in 0..context_len {
+                let block = block_table[position / self.block_size];
+                let offset = (block * self.block_size + position % self.block_size)
+                    * self.token_len()
+                    + head_offset;
+                let k = keys.read(block, offset, &mut key_scratch);
+                let v = values.read(block, offset, &mut value_scratch);
+                let score = q.iter().zip(k).map(|(a, b)| a * b).sum::<f32>() * self.scale;
+
+                // Rescale what has been accumulated when the maximum grows
+                let new_max = max_score.max(score);
+                let correction = (max_score - new_max).exp();
+                let weight = (score - new_max).exp();
+                denom = denom * correction + weight;
+                for (acc, &x) in out.iter_mut().zip(v) {
+                    *acc = *acc * correction + weight * x;
+                }
+                max_score = new_max;
+            }
+            if denom > 0.0 {
+                out.iter_mut().for_each(|acc| *acc /= denom);
+            }
+        }
+        output
+    }
+}
+
+/// Host memory or, on Apple Silicon, a buffer shared with the GPU
+enum Buffer<T> {
+    Host(Vec<T>),
+    #[cfg(target_os = "macos")]
+    Metal(metal_backend::SharedBuffer<T>),
+}
+
+impl<T> Buffer<T> {
+    fn as_slice(&self) -> &[T] {
+        match self {
+            Self::Host(data) => data,
+            #[cfg(target_os = "macos")]
+            Self::Metal(buffer) => buffer.as_slice(),
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        match self {
+            Self::Host(data) => data,
+            #[cfg(target_os = "macos")]
+            Self::Metal(buffer) => buffer.as_mut_slice(),
+        }
+    }
+}
+
+/// One layer's keys or values
+enum Storage {
+    Float(Buffer<f32>),
+    /// `value = scales[block] * data`
+    Int8 {
+        data: Buffer<i8>,
+        scales: Buffer<f32>,
+    },
+}
+
+impl Storage {
+    /// Store `token` at `offset`, in the block of `block_len` elements
+    /// containing it
+    fn write(&mut self, offset: usize, token: &[f32], block_len: usize) {
+        let range = offset..offset + token.len();
+        match self {
+            Self::Float(data) => data.as_mut_slice()[range].copy_from_slice(token),
+            Self::Int8 { data, scales } => {
+                let block = offset / block_len;
+                let data = data.as_mut_slice();
+                let scale = &mut scales.as_mut_slice()[block];
+                let needed = token.iter().fold(0.0f32, |max, x| max.max(x.abs())) / 127.0;
+                if needed > *scale {
+                    // Requantize the block's earlier tokens to the wider scale
+                    let widened = if *scale > 0.0 {
+                        let widened = needed.max(*scale * SCALE_GROWTH);
+                        let ratio = *scale / widened;
+                        for q in &mut data[block * block_len..(block + 1) * block_len] {
+                            *q = (*q as f32 * ratio).round() as i8;
+                        }
+                        widened
+                    } else {
+                        needed
+                    };
+                    *scale = widened;
+                }
+                let inverse = if *scale > 0.0 { 1.0 / *scale } else { 0.0 };
+                for (q, x) in data[range].iter_mut().zip(token) {
+                    *q = (x * inverse).round().clamp(-127.0, 127.0) as i8;
+                }
+            }
+        }
+    }
+
+    fn copy_block(&mut self, src: usize, dst: usize, block_len: usize) {
+        let range = src * block_len..(src + 1) * block_len;
+        match self {
+            Self::Float(data) => data.as_mut_slice().copy_within(range, dst * block_len),
+            Self::Int8 { data, scales } => {
+                data.as_mut_slice().copy_within(range, dst * block_len);
+                let scales = scales.as_mut_slice();
+                scales[dst] = scales[src];
+            }
+        }
+    }
+
+    /// The `scratch.len()` elements at `offset` of `block` as floats,
+    /// dequantized into `scratch` when stored as integers
+    fn read<'a>(&'a self, block: usize, offset: usize, scratch: &'a mut [f32]) -> &'a [f32] {
+        let range = offset..offset + scratch.len();
+        match self {
+            Self::Float(data) => &data.as_slice()[range],
+            Self::Int8 { data, scales } => {
+                let scale = scales.as_slice()[block];
+                for (x, &q) in scratch.iter_mut().zip(&data.as_slice()[range]) {
+                    *x = q as f32 * scale;
+                }
+                scratch
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod metal_backend {
+    use metal::{
+        Buffer, CommandQueue, CompileOptions, ComputePipelineState, Device, MTLResourceOptions,
+        MTLSize,
+    };
+    use std::ffi::c_void;
+    use std::marker::PhantomData;
+    use std::sync::Mutex;
+
+    use super::{PagedAttention, Storage, MAX_HEAD_DIM};
+    use crate::engine::types::BlockId;
+    use crate::error::{Error, Result};
+
+    const KERNEL_SOURCE: &str = r#"
+#include <metal_stdlib>
+using namespace metal;
+
+constant uint MAX_HEAD_DIM = 256;
+
+// dims: num_heads, num_kv_heads, head_dim, block_size.
+// Float caches ignore the scales and read elements as stored.
+template <typename T, bool QUANTIZED>
+inline void attend(
+    device const float* query,
+    device const T* key_cache,
+    device const T* value_cache,
+    device const float* key_scales,
+    device const float* value_scales,
+    device const uint* block_table,
+    device float* output,
+    uint4 dims,
+    uint context_len,
+    float scale,
+    uint head)
+{
+    if (head >= dims.x) {
+        return;
+    }
+    uint num_kv_heads = dims.y;
+    uint head_dim = dims.z;
+    uint block_size = dims.w;
+    uint kv_head = head / (dims.x / num_kv_heads);
+    device const float* q = query + head * head_dim;
+
+    float acc[MAX_HEAD_DIM];
+    for (uint d = 0; d < head_dim; d++) {
+        acc[d] = 0.0;
+    }
+    float max_score = -INFINITY;
+    float denom = 0.0;
+    for (uint t = 0; t < context_len; t++) {
+        uint block = block_table[t / block_size];
+        uint offset = ((block * block_size + t % block_size) * num_kv_heads + kv_head) * head_dim;
+        float key_scale = QUANTIZED ? key_scales[block] : 1.0;
+        float value_scale = QUANTIZED ? value_scales[block] : 1.0;
+        float score = 0.0;
+        for (uint d = 0; d < head_dim; d++) {
+            score += q[d] * float(key_cache[offset + d]);
+        }
+        score *= scale * key_scale;
+        float new_max = max(max_score, score);
+        float correction = exp(max_score - new_max);
+        float weight = exp(score - new_max);
+        denom = denom * correction + weight;
+        for (uint d = 0; d < head_dim; d++) {
+            acc[d] = acc[d] * correction + weight * value_scale * float(value_cache[offset + d]);
+        }
+        max_score = new_max;
+    }
+    device float* out = output + head * head_dim;
+    for (uint d = 0; d < head_dim; d++) {
+        out[d] = denom > 0.0 ? acc[d] / denom : 0.0;
+    }
+}
+
+kernel void paged_attention(
+    device const float* query [[buffer(0)]],
+    device const float* key_cache [[buffer(1)]],
+    device const float* value_cache [[buffer(2)]],
+    device const uint* block_table [[buffer(3)]],
+    device float* output [[buffer(4)]],
+    constant uint4& dims [[buffer(5)]],
+    constant uint& context_len [[buffer(6)]],
+    constant float& scale [[buffer(7)]],
+    uint head [[thread_position_in_grid]])
+{
+    attend<float, false>(query, key_cache, value_cache, query, query, block_table, output,
+                         dims, context_len, scale, head);
+}
+
+kernel void paged_attention_int8(
+    device const float* query [[buffer(0)]],
+    device const char* key_cache [[buffer(1)]],
+    device const char* value_cache [[buffer(2)]],
+    device const uint* block_table [[buffer(3)]],
+    device float* output [[buffer(4)]],
+    constant uint4& dims [[buffer(5)]],
+    constant uint& context_len [[buffer(6)]],
+    constant float& scale [[buffer(7)]],
+    device const float* key_scales [[buffer(8)]],
+    device const float* value_scales [[buffer(9)]],
+    uint head [[thread_position_in_grid]])
+{
+    attend<char, true>(query, key_cache, value_cache, key_scales, value_scales, block_table,
+                       output, dims, context_len, scale, head);
+}
+"#;
+
+    /// Buffer in unified memory, readable and writable from the host
+    pub struct SharedBuffer<T> {
+        buffer: Buffer,
+        len: usize,
+        element: PhantomData<T>,
+    }
+
+    impl<T> SharedBuffer<T> {
+        pub fn as_slice(&self) -> &[T] {
+            // Safety: the shared buffer holds `len` elements and is only
+            // written through `&mut self` or by kernels that have completed
+            unsafe { std::slice::from_raw_parts(self.buffer.contents() as *const T, self.len) }
+        }
+
+        pub fn as_mut_slice(&mut self) -> &mut [T] {
+            // Safety: as above, with exclusive access through `&mut self`
+            unsafe { std::slice::from_raw_parts_mut(self.buffer.contents() as *mut T, self.len) }
+        }
+    }
+
+    pub struct MetalAttention {
+        device: Device,
+        queue: Mutex<CommandQueue>,
+        float_pipeline: ComputePipelineState,
+        int8_pipeline: ComputePipelineState,
+    }
+
+    // Metal objects are reference counted and safe to use across threads;
+    // command submission is serialized through the queue mutex.
+    unsafe impl Send for MetalAttention {}
+    unsafe impl Sync for MetalAttention {}
+    unsafe impl<T: Send> Send for SharedBuffer<T> {}
+    unsafe impl<T: Sync> Sync for SharedBuffer<T> {}
+
+    fn buffer<T>(device: &Device, data: &[T]) -> Buffer {
+        device.new_buffer_with_data(
+            data.as_ptr() as *const c_void,
+            std::mem::size_of_val(data).max(1) as u64,
+            MTLResourceOptions::StorageModeShared,
+        )
+    }
+
+    impl MetalAttention {
+        /// `None` when the machine has no Metal device
+        pub fn new() -> Result<Option<Self>> {
+            let Some(device) = Device::system_default() else {
+                return Ok(None);
+            };
+            let library = device
+                .new_library_with_source(KERNEL_SOURCE, &CompileOptions::new())
+                .map_err(Error::InferenceError)?;
+            let pipeline = |name: &str| {
+                let function = library
+                    .get_function(name, None)
+                    .map_err(Error::InferenceError)?;
+                device
+                    .new_compute_pipeline_state_with_function(&function)
+                    .map_err(Error::InferenceError)
+            };
+            let float_pipeline = pipeline("paged_attention")?;
+            let int8_pipeline = pipeline("paged_attention_int8")?;
+            let queue = Mutex::new(device.new_command_queue());
+            Ok(Some(Self {
+                device,
+                queue,
+                float_pipeline,
+                int8_pipeline,
+            }))
+        }
+
+        /// Zeroed shared buffer of `len` elements
+        pub fn buffer<T>(&self, len: usize) -> SharedBuffer<T> {
+            let bytes = len * std::mem::size_of::<T>();
+            let buffer = self
+                .device
+                .new_buffer(bytes.max(1) as u64, MTLResourceOptions::StorageModeShared);
+            // Safety: the buffer holds `bytes` bytes; zero is a valid f32 and i8
+            unsafe { std::ptr::write_bytes(buffer.contents() as *mut u8, 0, bytes) };
+            SharedBuffer {
+                buffer,
+                len,
+                element: PhantomData,
+            }
+        }
+
+        pub fn forward(
+            &self,
+            attention: &PagedAttention,
+            keys: &Storage,
+            values: &Storage,
+            query: &[f32],
+            block_table: &[BlockId],
+            context_len: usize,
+        ) -> Result<Vec<f32>> {
+            use super::Buffer::Metal;
+
+            // Only the int8 kernel takes per-block scales
+            let (pipeline, key_cache, value_cache, scales) = match (keys, values) {
+                (Storage::Float(Metal(k)), Storage::Float(Metal(v))) => {
+                    (&self.float_pipeline, &k.buffer, &v.buffer, None)
+                }
+                (
+                    Storage::Int8 {
+                        data: Metal(k),
+                        scales: Metal(ks),
+                    },
+                    Storage::Int8 {
+                        data: Metal(v),
+                        scales: Metal(vs),
+                    },
+                ) => (
+                    &self.int8_pipeline,
+                    &k.buffer,
+                    &v.buffer,
+                    Some((&ks.buffer, &vs.buffer)),
+                ),
+                _ => {
+                    return Err(Error::InferenceError(
+                        "Paged KV storage is not in Metal buffers".into(),
+                    ))
+                }
+            };
+            debug_assert!(attention.head_dim <= MAX_HEAD_DIM);
+            let out_len = query.len();
+            let block_table: Vec<u32> = block_table.iter().map(|&b| b as u32).collect();
+            let query_buffer = buffer(&self.device, query);
+            let table_buffer = buffer(&self.device, &block_table);
+            let output_buffer = self.device.new_buffer(
+                (out_len * std::mem::size_of::<f32>()) as u64,
+                MTLResourceOptions::StorageModeShared,
+            );
+            let dims: [u32; 4] = [
+                attention.num_heads as u32,
+                attention.num_kv_heads as u32,
+                attention.head_dim as u32,
+                attention.block_size as u32,
+            ];
+            let context_len = context_len as u32;
+
+            let queue = self.queue.lock().unwrap();
+            let command_buffer = queue.new_command_buffer();
+            let encoder = command_buffer.new_compute_command_encoder();
+            encoder.set_compute_pipeline_state(pipeline);
+            encoder.set_buffer(0, Some(&query_buffer), 0);
+            encoder.set_buffer(1, Some(key_cache), 0);
+            encoder.set_buffer(2, Some(value_cache), 0);
+            encoder.set_buffer(3, Some(&table_buffer), 0);
+            encoder.set_buffer(4, Some(&output_buffer), 0);
+            encoder.set_bytes(
+                5,
+                std::mem::size_of_val(&dims) as u64,
+                dims.as_ptr() as *const c_void,
+            );
+            encoder.set_bytes(
+                6,
+                std::mem::size_of::<u32>() as u64,
+                &context_len as *const u32 as *const c_void,
+            );
+            encoder.set_bytes(
+                7,
+                std::mem::size_of::<f32>() as u64,
+                &attention.scale as *const f32 as *const c_void,
+            );
+            if let Some((key_scales, value_scales)) = scales {
+                encoder.set_buffer(8, Some(key_scales), 0);
+                encoder.set_buffer(9, Some(value_scales), 0);
+            }
+            let width = pipeline
+                .thread_execution_width()
+                .min(attention.num_heads as u64);
+            encoder.dispatch_threads(
+                MTLSize::new(attention.num_heads as u64, 1, 1),
+                MTLSize::new(width.max(1), 1, 1),
+            );
+            encoder.end_encoding();
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+
+            // Safety: the shared buffer holds `out_len` floats written by the kernel
+            let output = unsafe {
+                std::slice::from_raw_parts(output_buffer.contents() as *const f32, out_len)
+            };
+            Ok(output.to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Softmax attention over contiguous `[token][kv_head][head_dim]` tensors
+    fn reference(
+        query: &[f32],
+        keys: &[Vec<f32>],
+        values: &[Vec<f32>],
+        num_heads: usize,
+        num_kv_heads: usize,
+        head_dim: usize,
+    ) -> Vec<f32> {
+        let scale = 1.0 / (head_dim as f32).sqrt();
+        let mut output = vec![0.0; num_heads * head_dim];
+        for head in 0..num_heads {
+            let kv = (head / (num_heads / num_kv_heads)) * head_dim;
+            let q = &query[head * head_dim..(head + 1) * head_dim];
+            let scores: Vec<f32> = keys
+                .iter()
+                .map(|k| {
+                    q.iter()
+                        .zip(&k[kv..kv + head_dim])
+                        .map(|(a, b)| a * b)
+                        .sum::<f32>()
+                        * scale
+                })
+                .collect();
+            let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+            let weights: Vec<f32> = scores.iter().map(|s| (s - max).exp()).collect();
+            let total: f32 = weights.iter().sum();
+            for (weight, v) in weights.iter().zip(values) {
+                for d in 0..head_dim {
+                    output[head * head_dim + d] += weight / total * v[kv + d];
+                }
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_paged_attention_matches_contiguous() {
+        let config = KVCacheConfig {
+            num_layers: 2,
+            num_heads: 2,
+            head_dim: 8,
+            block_size: 4,
+            max_blocks: 8,
+            dtype: KVCacheDtype::Float32,
+        };
+        let num_heads = 4;
+        let mut attention = PagedAttention::new(&config, num_heads, Device::Cpu).unwrap();
+        assert_eq!(attention.backend_name(), "cpu");
+
+        // Scattered, out-of-order blocks as the allocator hands them out
+        let block_table = [5, 2, 7];
+        let context_len = 10;
+        let token = |seed: usize| -> Vec<f32> {
+            (0..16)
+                .map(|i| ((seed * 16 + i) as f32 * 0.13).sin())
+                .collect()
+        };
+        let keys: Vec<Vec<f32>> = (0..context_len).map(token).collect();
+        let values: Vec<Vec<f32>> = (0..context_len).map(|t| token(t + 100)).collect();
+        for t in 0..context_len {
+            attention
+                .write(1, &block_table, t, &keys[t], &values[t])
+                .unwrap();
+        }
+        let query: Vec<f32> = (0..num_heads * 8)
+            .map(|i| (i as f32 * 0.29).cos())
+            .collect();
+
+        let output = attention
+            .forward(1, &query, &block_table, context_len)
+            .unwrap();
+        let expected = reference(&query, &keys, &values, num_heads, 2, 8);
+        for (a, b) in output.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+        }
+
+        // A copy-on-write fork reads the same context from its own block
+        attention.apply_block_write(BlockWrite::Copied { src: 7, dst: 0 });
+        let forked = attention
+            .forward(1, &query, &[5, 2, 0], context_len)
+            .unwrap();
+        assert_eq!(forked, output);
+
+        assert!(attention
+            .forward(1, &query, &block_table[..2], context_len)
+            .is_err());
+        assert!(attention
+            .write(2, &block_table, 0, &keys[0], &values[0])
+            .is_err());
+        assert!(PagedAttention::new(&config, 3, Device::Cpu).is_err());
+    }
+
+    /// Round to the nearest float16 value (normal range)
+    fn to_f16(x: f32) -> f32 {
+        let bits = x.to_bits();
+        f32::from_bits((bits + 0x0fff + ((bits >> 13) & 1)) & !0x1fff)
+    }
+
+    #[test]
+    fn test_int8_accuracy_against_float16() {
+        let config = KVCacheConfig {
+            num_layers: 1,
+            num_heads: 2,
+            head_dim: 64,
+            block_size: 16,
+            max_blocks: 8,
+            dtype: KVCacheDtype::Float16,
+        };
+        let int8_config = KVCacheConfig {
+            dtype: KVCacheDtype::Int8,
+            ..config.clone()
+        };
+        assert_eq!(
+            config.block_memory_bytes(),
+            2 * (int8_config.block_memory_bytes() - 2 * 4)
+        );
+        let mut float16 = PagedAttention::new(&config, 4, Device::Cpu).unwrap();
+        let mut int8 = PagedAttention::new(&int8_config, 4, Device::Cpu).unwrap();
+
+        // Growing magnitudes force blocks to be requantized as tokens arrive
+        let block_table = [3, 6, 1, 4];
+        let context_len = 50;
+        let token = |seed: usize, gain: f32| -> Vec<f32> {
+            (0..128)
+                .map(|i| gain * ((seed * 128 + i) as f32 * 0.071).sin())
+                .collect()
+        };
+        for t in 0..context_len {
+            let gain = 0.5 + t as f32 * 0.05;
+            let (key, value) = (token(t, gain), token(t + 1000, gain));
+            let (key16, value16): (Vec<f32>, Vec<f32>) = key
+                .iter()
+                .zip(&value)
+                .map(|(&k, &v)| (to_f16(k), to_f16(v)))
+                .unzip();
+            float16.write(0, &block_table, t, &key16, &value16).unwrap();
+            int8.write(0, &block_table, t, &key, &value).unwrap();
+        }
+        int8.apply_block_write(BlockWrite::Copied { src: 6, dst: 0 });
+
+        let query: Vec<f32> = (0..4 * 64).map(|i| (i as f32 * 0.37).cos()).collect();
+        let expected = float16
+            .forward(0, &query, &block_table, context_len)
+            .unwrap();
+        for table in [block_table, [3, 0, 1, 4]] {
+            let output = int8.forward(0, &query, &table, context_len).unwrap();
+            let max_error = output
+                .iter()
+                .zip(&expected)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0f32, f32::max);
+            assert!(max_error < 0.01, "int8 attention off by {}", max_error);
+        }
+    }
+}
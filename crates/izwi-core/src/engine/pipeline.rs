@@ -0,0 +1,170 @@
+//! Layer-wise pipeline parallelism across several GPUs.
+//!
+//! A model too large for one device is split into contiguous layer ranges,
+//! one stage per entry of `devices`, sized in proportion to each device's
+//! free memory. Every KV block keeps the keys and values of a stage's layers
+//! on that stage's device, so the number of blocks a model lane can hold is
+//! bounded by its tightest stage.
+
+use serde::Serialize;
+use std::ops::Range;
+
+use super::config::EngineCoreConfig;
+use super::memory;
+use crate::device::Device;
+
+/// A contiguous range of layers placed on one device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PipelineStage {
+    pub device: Device,
+    pub layers: Range<usize>,
+}
+
+impl PipelineStage {
+    /// Number of layers in the stage
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+/// Placement of a model's layers over one or more devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelinePlan {
+    stages: Vec<PipelineStage>,
+    num_layers: usize,
+}
+
+impl PipelinePlan {
+    /// Every layer on one device
+    pub fn single(device: Device, num_layers: usize) -> Self {
+        Self {
+            stages: vec![PipelineStage {
+                device,
+                layers: 0..num_layers,
+            }],
+            num_layers,
+        }
+    }
+
+    /// Split `num_layers` over `devices` in proportion to `weights`, giving
+    /// every stage at least one layer.
+    ///
+    /// Devices beyond the number of layers get no stage. Equal weights are
+    /// used when they are all zero.
+    pub fn split(devices: &[Device], weights: &[u64], num_layers: usize) -> Self {
+        let count = devices.len().min(num_layers).max(1);
+        let devices = &devices[..count.min(devices.len())];
+        if devices.len() < 2 {
+            let device = devices.first().copied().unwrap_or_default();
+            return Self::single(device, num_layers);
+        }
+
+        let mut weights: Vec<u128> = (0..count)
+            .map(|i| weights.get(i).copied().unwrap_or(0) as u128)
+            .collect();
+        if weights.iter().all(|&w| w == 0) {
+            weights.iter_mut().for_each(|w| *w = 1);
+        }
+        let total: u128 = weights.iter().sum();
+        let spare = (num_layers - count) as u128;
+
+        // Spare layers handed out before stage `i`, rounded from the running
+        // weight so the stages always add up to `num_layers`
+        let mut running = 0u128;
+        let mut extra_before = 0usize;
+        let mut start = 0usize;
+        let stages = devices
+            .iter()
+            .zip(&weights)
+            .map(|(&device, &weight)| {
+                running += weight;
+                let extra_through = ((spare * running + total / 2) / total) as usize;
+                let len = 1 + extra_through - extra_before;
+                extra_before = extra_through;
+                let layers = start..start + len;
+                start += len;
+                PipelineStage { device, layers }
+            })
+            .collect();
+        Self { stages, num_layers }
+    }
+
+    /// Plan for the configured `devices`, weighted by their free memory, or
+    /// the single compute device when fewer than two are listed.
+    pub fn for_config(config: &EngineCoreConfig, num_layers: usize) -> Self {
+        if config.devices.len() < 2 {
+            return Self::single(config.compute_device(), num_layers);
+        }
+        let weights: Vec<u64> = config
+            .devices
+            .iter()
+            .map(|&device| memory::available_memory_bytes(device).unwrap_or(0))
+            .collect();
+        Self::split(&config.devices, &weights, num_layers)
+    }
+
+    pub fn stages(&self) -> &[PipelineStage] {
+        &self.stages
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.num_layers
+    }
+
+    /// Whether the model spans more than one device
+    pub fn is_pipelined(&self) -> bool {
+        self.stages.len() > 1
+    }
+
+    /// Share of `bytes` sized over all layers that falls on `stage`
+    pub fn stage_share(&self, stage: &PipelineStage, bytes: u64) -> u64 {
+        if self.num_layers == 0 {
+            return 0;
+        }
+        (bytes as u128 * stage.num_layers() as u128 / self.num_layers as u128) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lengths(plan: &PipelinePlan) -> Vec<usize> {
+        plan.stages()
+            .iter()
+            .map(PipelineStage::num_layers)
+            .collect()
+    }
+
+    #[test]
+    fn test_split_by_free_memory() {
+        let gpus = [Device::Cuda(0), Device::Cuda(1)];
+
+        let even = PipelinePlan::split(&gpus, &[8, 8], 24);
+        assert_eq!(lengths(&even), vec![12, 12]);
+        assert_eq!(even.stages()[1].layers, 12..24);
+        assert!(even.is_pipelined());
+
+        let uneven = PipelinePlan::split(&gpus, &[24, 8], 24);
+        assert_eq!(lengths(&uneven), vec![18, 6]);
+
+        // Every stage keeps a layer however little memory its device has
+        let starved = PipelinePlan::split(&gpus, &[100, 0], 24);
+        assert_eq!(lengths(&starved), vec![23, 1]);
+
+        let unknown = PipelinePlan::split(&gpus, &[0, 0], 5);
+        assert_eq!(lengths(&unknown).iter().sum::<usize>(), 5);
+
+        let crowded = PipelinePlan::split(
+            &[Device::Cuda(0), Device::Cuda(1), Device::Cuda(2)],
+            &[1, 1, 1],
+            2,
+        );
+        assert_eq!(lengths(&crowded), vec![1, 1]);
+
+        let single = PipelinePlan::split(&gpus[..1], &[1], 24);
+        assert!(!single.is_pipelined());
+        assert_eq!(single.stage_share(&single.stages()[0], 100), 100);
+        assert_eq!(uneven.stage_share(&uneven.stages()[1], 100), 25);
+    }
+}
@@ -0,0 +1,226 @@
+//! Step-level profiler for the continuous batching loop.
+//!
+//! Every engine step that runs work records where its time went
+//! (scheduling, prefill and decode forward passes, sampling, audio decode)
+//! together with its batch composition. The most recent steps are kept in a
+//! ring buffer and summarized on demand.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::model::ModelVariant;
+
+/// Steps kept by default
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Timings and batch composition of one engine step.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StepProfile {
+    /// Step number, counted across all models
+    pub step: u64,
+    /// Model that ran the step
+    pub model: String,
+    pub schedule_ms: f64,
+    /// Forward time for prefill requests (mixed steps are split by token share)
+    pub prefill_forward_ms: f64,
+    /// Forward time for decode requests (mixed steps are split by token share)
+    pub decode_forward_ms: f64,
+    /// Sampling time (0 when the executor samples inside its forward pass)
+    pub sampling_ms: f64,
+    /// Turning executor output into audio
+    pub audio_decode_ms: f64,
+    pub total_ms: f64,
+    pub prefill_requests: usize,
+    pub decode_requests: usize,
+    pub prefill_tokens: usize,
+    pub decode_tokens: usize,
+    pub tokens_per_second: f64,
+}
+
+impl StepProfile {
+    /// Requests run in the step
+    pub fn batch_size(&self) -> usize {
+        self.prefill_requests + self.decode_requests
+    }
+
+    /// Split the forward time between prefill and decode by token share
+    pub(crate) fn set_forward(&mut self, forward: Duration) {
+        let forward_ms = ms(forward);
+        let tokens = (self.prefill_tokens + self.decode_tokens).max(1) as f64;
+        self.prefill_forward_ms = forward_ms * self.prefill_tokens as f64 / tokens;
+        self.decode_forward_ms = forward_ms - self.prefill_forward_ms;
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Averages over the profiled steps.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileSummary {
+    pub steps: usize,
+    pub mean_batch_size: f64,
+    pub max_batch_size: usize,
+    pub mean_step_ms: f64,
+    pub p50_step_ms: f64,
+    pub p99_step_ms: f64,
+    pub mean_schedule_ms: f64,
+    pub mean_prefill_forward_ms: f64,
+    pub mean_decode_forward_ms: f64,
+    pub mean_sampling_ms: f64,
+    pub mean_audio_decode_ms: f64,
+    /// Tokens processed per second of step time
+    pub tokens_per_second: f64,
+}
+
+impl ProfileSummary {
+    fn new(steps: &[StepProfile]) -> Self {
+        if steps.is_empty() {
+            return Self::default();
+        }
+        let n = steps.len() as f64;
+        let mean = |f: fn(&StepProfile) -> f64| steps.iter().map(f).sum::<f64>() / n;
+
+        let mut totals: Vec<f64> = steps.iter().map(|s| s.total_ms).collect();
+        totals.sort_by(f64::total_cmp);
+        let percentile = |p: f64| totals[((totals.len() - 1) as f64 * p).round() as usize];
+
+        let tokens: usize = steps
+            .iter()
+            .map(|s| s.prefill_tokens + s.decode_tokens)
+            .sum();
+        let total_ms: f64 = totals.iter().sum();
+
+        Self {
+            steps: steps.len(),
+            mean_batch_size: mean(|s| s.batch_size() as f64),
+            max_batch_size: steps.iter().map(StepProfile::batch_size).max().unwrap_or(0),
+            mean_step_ms: total_ms / n,
+            p50_step_ms: percentile(0.5),
+            p99_step_ms: percentile(0.99),
+            mean_schedule_ms: mean(|s| s.schedule_ms),
+            mean_prefill_forward_ms: mean(|s| s.prefill_forward_ms),
+            mean_decode_forward_ms: mean(|s| s.decode_forward_ms),
+            mean_sampling_ms: mean(|s| s.sampling_ms),
+            mean_audio_decode_ms: mean(|s| s.audio_decode_ms),
+            tokens_per_second: if total_ms > 0.0 {
+                tokens as f64 / (total_ms / 1000.0)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Recent steps and their summary, oldest step first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSnapshot {
+    pub summary: ProfileSummary,
+    pub steps: Vec<StepProfile>,
+}
+
+#[derive(Debug, Default)]
+struct ProfilerInner {
+    steps: VecDeque<StepProfile>,
+    next_step: u64,
+}
+
+/// Ring buffer of step profiles.
+#[derive(Debug)]
+pub struct StepProfiler {
+    capacity: usize,
+    inner: Mutex<ProfilerInner>,
+}
+
+impl Default for StepProfiler {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl StepProfiler {
+    /// Create a profiler keeping the last `capacity` steps
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(ProfilerInner::default()),
+        }
+    }
+
+    /// Record a step, numbering it and computing its throughput
+    pub fn record(&self, model: ModelVariant, mut profile: StepProfile) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        profile.step = inner.next_step;
+        profile.model = model.to_string();
+        profile.tokens_per_second = if profile.total_ms > 0.0 {
+            (profile.prefill_tokens + profile.decode_tokens) as f64 / (profile.total_ms / 1000.0)
+        } else {
+            0.0
+        };
+        inner.next_step += 1;
+        if inner.steps.len() >= self.capacity {
+            inner.steps.pop_front();
+        }
+        inner.steps.push_back(profile);
+    }
+
+    /// The last `limit` steps (all kept steps when `None`) and their summary
+    pub fn snapshot(&self, limit: Option<usize>) -> ProfileSnapshot {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let skip = limit.map_or(0, |limit| inner.steps.len().saturating_sub(limit));
+        let steps: Vec<StepProfile> = inner.steps.iter().skip(skip).cloned().collect();
+        ProfileSnapshot {
+            summary: ProfileSummary::new(&steps),
+            steps,
+        }
+    }
+
+    /// Drop all recorded steps
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.steps.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(prefill_tokens: usize, decode_requests: usize, total_ms: f64) -> StepProfile {
+        let mut profile = StepProfile {
+            prefill_requests: usize::from(prefill_tokens > 0),
+            decode_requests,
+            prefill_tokens,
+            decode_tokens: decode_requests,
+            total_ms,
+            ..Default::default()
+        };
+        profile.set_forward(Duration::from_millis(total_ms as u64));
+        profile
+    }
+
+    #[test]
+    fn test_ring_buffer_and_summary() {
+        let profiler = StepProfiler::new(2);
+        let model = ModelVariant::Qwen3Tts12Hz06BCustomVoice;
+        profiler.record(model, step(30, 2, 40.0));
+        profiler.record(model, step(0, 4, 10.0));
+        profiler.record(model, step(0, 6, 20.0));
+
+        let snapshot = profiler.snapshot(None);
+        let numbers: Vec<u64> = snapshot.steps.iter().map(|s| s.step).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(snapshot.summary.mean_batch_size, 5.0);
+        assert_eq!(snapshot.summary.max_batch_size, 6);
+        assert_eq!(snapshot.summary.tokens_per_second, 10.0 / 0.03);
+        assert_eq!(profiler.snapshot(Some(1)).steps[0].step, 2);
+
+        // Mixed steps split forward time by token share
+        let mixed = step(30, 10, 40.0);
+        assert_eq!(mixed.prefill_forward_ms, 30.0);
+        assert_eq!(mixed.decode_forward_ms, 10.0);
+    }
+}
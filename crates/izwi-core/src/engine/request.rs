@@ -1,14 +1,23 @@
 //! Request types and processing for the inference engine.
 
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
 use std::time::Instant;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use super::candidates::MAX_CANDIDATES;
 use super::config::EngineCoreConfig;
+use super::constrained::CompiledConstraint;
 use super::output::StreamingOutput;
+use super::output_cache::CacheControl;
+use super::sampler::{LogitsProcessor, MAX_TOP_LOGPROBS};
+use super::session::{ChatSession, ChatTurn, SessionId};
 use super::types::{GenerationParams, ModelType, Priority, RequestId, TaskType, TokenId};
+use super::validation;
 use crate::error::{Error, Result};
+use crate::inference::GenerationRequest;
+use crate::model::ModelVariant;
 
 /// Status of a request in the engine.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,6 +34,32 @@ pub enum RequestStatus {
     Failed,
 }
 
+/// Event recorded in a request's audit trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Request entered the waiting queue
+    Queued { priority: Priority },
+    /// Priority was changed while waiting
+    PriorityChanged {
+        from: Priority,
+        to: Priority,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Restarted from the beginning on a newly swapped-in model
+    Migrated { model: String },
+}
+
+/// Timestamped entry in a request's audit trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the request arrived
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
 /// A request to the engine core.
 #[derive(Debug, Clone)]
 pub struct EngineCoreRequest {
@@ -34,6 +69,8 @@ pub struct EngineCoreRequest {
     pub task_type: TaskType,
     /// Model type to use
     pub model_type: ModelType,
+    /// Resident model to route to (the default model when unset)
+    pub model: Option<ModelVariant>,
     /// Input text (for TTS)
     pub text: Option<String>,
     /// Input audio (base64 encoded, for ASR/chat)
@@ -48,12 +85,26 @@ pub struct EngineCoreRequest {
     pub params: GenerationParams,
     /// Request priority
     pub priority: Priority,
-    /// Arrival timestamp
-    pub arrival_time: Instant,
+    /// Client identity (API key or tenant) used for fair-share scheduling
+    pub client_id: Option<String>,
+    /// When the engine queued the request, read from its clock
+    pub arrival_time: Option<Instant>,
     /// Prompt token IDs (set by processor)
     pub prompt_tokens: Vec<TokenId>,
     /// Enable streaming output
     pub streaming: bool,
+    /// How the request may use the output cache
+    pub cache_control: CacheControl,
+    /// Lifecycle changes made to this request
+    pub audit: Vec<AuditEntry>,
+    /// Chat session the request is a turn of
+    pub session_id: Option<SessionId>,
+    /// Leading prompt tokens already in the session's KV cache
+    pub prefix_tokens: usize,
+    /// Compiled `params.constraint` (set by processor)
+    pub constraint: Option<Arc<CompiledConstraint>>,
+    /// Registered logits processors that apply (set by processor)
+    pub logits_processors: Vec<Arc<dyn LogitsProcessor>>,
     /// Channel for streaming output (internal use)
     #[allow(dead_code)]
     pub(crate) streaming_tx: Option<mpsc::Sender<StreamingOutput>>,
@@ -66,6 +117,7 @@ impl EngineCoreRequest {
             id: Uuid::new_v4().to_string(),
             task_type: TaskType::TTS,
             model_type: ModelType::Qwen3TTS,
+            model: None,
             text: Some(text.into()),
             audio_input: None,
             reference_audio: None,
@@ -73,9 +125,16 @@ impl EngineCoreRequest {
             voice_description: None,
             params: GenerationParams::default(),
             priority: Priority::Normal,
-            arrival_time: Instant::now(),
+            client_id: None,
+            arrival_time: None,
             prompt_tokens: Vec::new(),
             streaming: false,
+            cache_control: CacheControl::default(),
+            audit: Vec::new(),
+            session_id: None,
+            prefix_tokens: 0,
+            constraint: None,
+            logits_processors: Vec::new(),
             streaming_tx: None,
         }
     }
@@ -86,6 +145,7 @@ impl EngineCoreRequest {
             id: Uuid::new_v4().to_string(),
             task_type: TaskType::ASR,
             model_type: ModelType::Qwen3TTS,
+            model: None,
             text: None,
             audio_input: Some(audio_base64.into()),
             reference_audio: None,
@@ -93,19 +153,45 @@ impl EngineCoreRequest {
             voice_description: None,
             params: GenerationParams::default(),
             priority: Priority::Normal,
-            arrival_time: Instant::now(),
+            client_id: None,
+            arrival_time: None,
             prompt_tokens: Vec::new(),
             streaming: false,
+            cache_control: CacheControl::default(),
+            audit: Vec::new(),
+            session_id: None,
+            prefix_tokens: 0,
+            constraint: None,
+            logits_processors: Vec::new(),
             streaming_tx: None,
         }
     }
 
+    /// Create the next turn of a chat session from the turns it adds (tool
+    /// results and/or the user's text and audio).
+    pub fn chat(session: &ChatSession, next: &[ChatTurn]) -> Self {
+        let mut request = Self::tts(session.prompt(next));
+        request.task_type = TaskType::Chat;
+        request.model = session.model;
+        request.audio_input = next.iter().rev().find_map(|turn| turn.audio.clone());
+        request.session_id = Some(session.id.clone());
+        request.prefix_tokens = session.cached_tokens;
+        request.cache_control = CacheControl::NoStore;
+        request
+    }
+
     /// Set model type.
     pub fn with_model_type(mut self, model_type: ModelType) -> Self {
         self.model_type = model_type;
         self
     }
 
+    /// Route the request to a specific resident model.
+    pub fn with_model(mut self, model: ModelVariant) -> Self {
+        self.model = Some(model);
+        self
+    }
+
     /// Set generation parameters.
     pub fn with_params(mut self, params: GenerationParams) -> Self {
         self.params = params;
@@ -118,6 +204,12 @@ impl EngineCoreRequest {
         self
     }
 
+    /// Attribute the request to a client for fair-share scheduling.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
     /// Enable streaming.
     pub fn with_streaming(mut self, streaming: bool) -> Self {
         self.streaming = streaming;
@@ -143,35 +235,122 @@ impl EngineCoreRequest {
         self
     }
 
+    /// Set how the request may use the output cache.
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = cache_control;
+        self
+    }
+
     /// Get number of prompt tokens.
     pub fn num_prompt_tokens(&self) -> usize {
         if !self.prompt_tokens.is_empty() {
             self.prompt_tokens.len()
         } else {
             // Estimate from text length (rough approximation)
-            self.text.as_ref().map(|t| t.len() / 4).unwrap_or(0).max(1)
+            self.text
+                .as_deref()
+                .map(crate::usage::estimate_prompt_tokens)
+                .unwrap_or(1)
         }
     }
 
-    /// Time since request arrival.
-    pub fn waiting_time(&self) -> std::time::Duration {
-        self.arrival_time.elapsed()
+    /// Time from arrival until `now` (zero before the request is queued).
+    pub fn waiting_time(&self, now: Instant) -> std::time::Duration {
+        self.arrival_time.map_or_else(Default::default, |arrival| {
+            now.saturating_duration_since(arrival)
+        })
+    }
+
+    /// Append an event to the audit trail, timestamped at `now`.
+    pub fn record(&mut self, event: AuditEvent, now: Instant) {
+        self.audit.push(AuditEntry {
+            elapsed_ms: self.waiting_time(now).as_millis() as u64,
+            event,
+        });
+    }
+}
+
+impl From<GenerationRequest> for EngineCoreRequest {
+    /// Carry a server-side TTS request, including its client identity and
+    /// priority, into the engine.
+    fn from(request: GenerationRequest) -> Self {
+        let instruction = request.instruction();
+        let config = request.config;
+        let mut core = Self::tts(request.text);
+        core.id = request.id;
+        core.model = request.model;
+        core.reference_audio = request.reference_audio;
+        core.reference_text = request.reference_text;
+        core.voice_description = instruction;
+        core.client_id = request.client_id;
+        core.priority = request.priority;
+        core.streaming = config.streaming;
+        core.params.temperature = config.temperature;
+        core.params.top_p = config.top_p;
+        core.params.top_k = config.top_k;
+        core.params.repetition_penalty = config.repetition_penalty;
+        core.params.max_tokens = config.max_tokens;
+        core.params.speaker = config.speaker;
+        core.params.speed = config.speed;
+        core.params.stop_on_silence_ms = config.stop_on_silence_ms;
+        core.params.max_audio_seconds = config.max_audio_seconds;
+        core
     }
 }
 
 /// Request processor - validates and preprocesses requests.
 pub struct RequestProcessor {
     config: EngineCoreConfig,
+    /// Logits processors attached to the requests they apply to
+    logits_processors: RwLock<Vec<Arc<dyn LogitsProcessor>>>,
 }
 
 impl RequestProcessor {
     /// Create a new request processor.
     pub fn new(config: EngineCoreConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            logits_processors: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Attach `processor` to later requests it applies to, replacing any
+    /// processor registered under the same name.
+    pub fn register_logits_processor(&self, processor: Arc<dyn LogitsProcessor>) {
+        let mut processors = self
+            .logits_processors
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        processors.retain(|p| p.name() != processor.name());
+        processors.push(processor);
+    }
+
+    /// Stop attaching the processor registered as `name`. Returns false if
+    /// there was none.
+    pub fn remove_logits_processor(&self, name: &str) -> bool {
+        let mut processors = self
+            .logits_processors
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        let before = processors.len();
+        processors.retain(|p| p.name() != name);
+        processors.len() != before
+    }
+
+    /// Names of the registered logits processors, in the order they run.
+    pub fn logits_processor_names(&self) -> Vec<String> {
+        self.logits_processors
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect()
     }
 
     /// Process and validate a request.
     pub fn process(&self, mut request: EngineCoreRequest) -> Result<EngineCoreRequest> {
+        self.validate_input(&mut request)?;
+
         // Validate request based on task type
         match request.task_type {
             TaskType::TTS => {
@@ -188,10 +367,48 @@ impl RequestProcessor {
                     ));
                 }
             }
+            TaskType::Chat => {
+                if request.session_id.is_none() {
+                    return Err(Error::InvalidInput(
+                        "Chat request requires a session".into(),
+                    ));
+                }
+            }
+        }
+
+        if let Some(model) = request.model {
+            Self::validate_model(model, request.task_type)?;
         }
 
         // Validate and clamp parameters
         self.validate_params(&mut request.params)?;
+        if request.params.n_candidates > 1 && (request.streaming || request.streaming_tx.is_some())
+        {
+            return Err(Error::InvalidInput(
+                "Best-of-N generation cannot be streamed".into(),
+            ));
+        }
+        if let Some(constraint) = &request.params.constraint {
+            if request.task_type == TaskType::TTS {
+                return Err(Error::InvalidInput(
+                    "Output constraints only apply to text outputs (ASR and chat)".into(),
+                ));
+            }
+            request.constraint = Some(Arc::new(constraint.compile()?));
+        }
+        if request.params.logprobs.is_some() && request.task_type == TaskType::TTS {
+            return Err(Error::InvalidInput(
+                "Log probabilities only apply to text outputs (ASR and chat)".into(),
+            ));
+        }
+        request.logits_processors = self
+            .logits_processors
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|p| p.applies_to(&request))
+            .cloned()
+            .collect();
 
         // Set model type from config if not specified
         if request.model_type == ModelType::default() {
@@ -202,13 +419,60 @@ impl RequestProcessor {
         if let Some(text) = &request.text {
             // For now, use a simple approximation. In production, this would use
             // the actual tokenizer for the model.
-            let estimated_tokens = (text.len() / 4).max(1);
+            let estimated_tokens = crate::usage::estimate_prompt_tokens(text);
             request.prompt_tokens = (0..estimated_tokens as u32).collect();
         }
 
         Ok(request)
     }
 
+    /// Normalize text fields and check text and audio against the input
+    /// limits.
+    fn validate_input(&self, request: &mut EngineCoreRequest) -> Result<()> {
+        let limits = &self.config.limits;
+        // A chat prompt carries the whole session, so only its turns were
+        // checked as they were added
+        if request.task_type != TaskType::Chat {
+            if let Some(text) = request.text.as_mut() {
+                *text = validation::check_text(text, "text", limits)?;
+            }
+        }
+        for (field, value) in [
+            ("reference_text", &mut request.reference_text),
+            ("voice_description", &mut request.voice_description),
+        ] {
+            if let Some(text) = value.as_mut() {
+                *text = validation::check_text(text, field, limits)?;
+            }
+        }
+        for (field, value) in [
+            ("audio_input", &request.audio_input),
+            ("reference_audio", &request.reference_audio),
+        ] {
+            if let Some(audio) = value {
+                validation::check_audio(audio, field, limits)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the requested model can perform the task.
+    fn validate_model(model: ModelVariant, task_type: TaskType) -> Result<()> {
+        let supported = match task_type {
+            TaskType::TTS => !model.is_asr() && !model.is_tokenizer(),
+            TaskType::ASR => model.is_asr() || model.is_lfm2(),
+            TaskType::Chat => model.is_lfm2(),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(format!(
+                "Model {} does not support {:?} requests",
+                model, task_type
+            )))
+        }
+    }
+
     /// Validate and clamp generation parameters.
     fn validate_params(&self, params: &mut GenerationParams) -> Result<()> {
         // Clamp temperature
@@ -223,6 +487,18 @@ impl RequestProcessor {
         }
         params.max_tokens = params.max_tokens.min(self.config.max_seq_len);
 
+        // An audio length limit becomes a token budget
+        if let Some(secs) = params.max_audio_seconds {
+            if !secs.is_finite() || secs <= 0.0 {
+                return Err(Error::InvalidInput(
+                    "max_audio_seconds must be positive".to_string(),
+                ));
+            }
+        }
+        if let Some(budget) = params.audio_token_budget() {
+            params.max_tokens = params.max_tokens.min(budget);
+        }
+
         // Clamp speed
         params.speed = params.speed.clamp(0.5, 2.0);
 
@@ -231,6 +507,22 @@ impl RequestProcessor {
             params.repetition_penalty = 1.0;
         }
 
+        // Validate best-of-N takes
+        params.n_candidates = params.n_candidates.max(1);
+        if params.n_candidates > MAX_CANDIDATES {
+            return Err(Error::InvalidInput(format!(
+                "n_candidates must be at most {}",
+                MAX_CANDIDATES
+            )));
+        }
+
+        if params.logprobs.is_some_and(|top| top > MAX_TOP_LOGPROBS) {
+            return Err(Error::InvalidInput(format!(
+                "logprobs must be at most {}",
+                MAX_TOP_LOGPROBS
+            )));
+        }
+
         Ok(())
     }
 }
@@ -267,6 +559,12 @@ impl RequestBuilder {
         self
     }
 
+    /// Route to a specific resident model.
+    pub fn model_variant(mut self, model: ModelVariant) -> Self {
+        self.request.model = Some(model);
+        self
+    }
+
     /// Set the voice.
     pub fn voice(mut self, voice: impl Into<String>) -> Self {
         self.request.params.voice = Some(voice.into());
@@ -334,6 +632,12 @@ impl RequestBuilder {
         self
     }
 
+    /// Set the client identity (API key or tenant).
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.request.client_id = Some(client_id.into());
+        self
+    }
+
     /// Enable streaming.
     pub fn streaming(mut self) -> Self {
         self.request.streaming = true;
@@ -383,6 +687,21 @@ mod tests {
         assert_eq!(request.params.max_tokens, 1024);
     }
 
+    #[test]
+    fn test_from_generation_request() {
+        let request = GenerationRequest::new("Hello")
+            .with_speaker("vivian")
+            .with_client_id("acme")
+            .with_priority(Priority::High);
+        let id = request.id.clone();
+
+        let core = EngineCoreRequest::from(request);
+        assert_eq!(core.id, id);
+        assert_eq!(core.client_id.as_deref(), Some("acme"));
+        assert_eq!(core.priority, Priority::High);
+        assert_eq!(core.params.speaker.as_deref(), Some("vivian"));
+    }
+
     #[test]
     fn test_request_processor() {
         let config = EngineCoreConfig::default();
@@ -391,5 +710,59 @@ mod tests {
         let request = EngineCoreRequest::tts("Test");
         let processed = processor.process(request);
         assert!(processed.is_ok());
+
+        let request = EngineCoreRequest::tts("Test").with_model(ModelVariant::Lfm2Audio15B);
+        assert!(processor.process(request).is_ok());
+        let request = EngineCoreRequest::tts("Test").with_model(ModelVariant::Qwen3Asr06B);
+        assert!(processor.process(request).is_err());
+
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.n_candidates = 0;
+        assert_eq!(processor.process(request).unwrap().params.n_candidates, 1);
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.n_candidates = MAX_CANDIDATES + 1;
+        assert!(processor.process(request).is_err());
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.n_candidates = 3;
+        request.streaming = true;
+        assert!(processor.process(request).is_err());
+    }
+
+    #[test]
+    fn test_request_processor_checks_input() {
+        let mut config = EngineCoreConfig::default();
+        config.limits.max_text_chars = 8;
+        let processor = RequestProcessor::new(config);
+
+        let request = EngineCoreRequest::tts("\u{FEFF}Hi\r\nthere");
+        let processed = processor.process(request).unwrap();
+        assert_eq!(processed.text.as_deref(), Some("Hi\nthere"));
+
+        let request = EngineCoreRequest::tts("Far too long");
+        let err = processor.process(request).unwrap_err();
+        assert_eq!(err.code(), crate::error::ErrorCode::TextTooLong);
+
+        let request = EngineCoreRequest::asr("bm90IGF1ZGlv!");
+        let err = processor.process(request).unwrap_err();
+        assert_eq!(err.code(), crate::error::ErrorCode::InvalidBase64);
+    }
+
+    #[test]
+    fn test_max_audio_seconds_caps_tokens() {
+        let processor = RequestProcessor::new(EngineCoreConfig::default());
+
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.max_audio_seconds = Some(4.0);
+        // 12.5 audio tokens per second
+        assert_eq!(processor.process(request).unwrap().params.max_tokens, 50);
+
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.max_tokens = 10;
+        request.params.max_audio_seconds = Some(4.0);
+        assert_eq!(processor.process(request).unwrap().params.max_tokens, 10);
+
+        let mut request = EngineCoreRequest::tts("Test");
+        request.params.max_audio_seconds = Some(0.0);
+        assert!(processor.process(request).is_err());
     }
 }
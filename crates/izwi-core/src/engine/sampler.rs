@@ -0,0 +1,531 @@
+//! Token selection and the logits processor plugin API.
+//!
+//! Downstream crates customize how tokens are picked by implementing
+//! [`LogitsProcessor`] and registering it with
+//! [`Engine::register_logits_processor`](super::Engine::register_logits_processor).
+//! Every request it applies to gets it in `logits_processors`; executors that
+//! decode token by token run them, in registration order, through a
+//! [`SamplerPipeline`] before each token is chosen. The request's output
+//! constraint, if any, is applied last so processors cannot undo it.
+//!
+//! When a request asks for `logprobs`, the pipeline also keeps the log
+//! probability of each picked token and its most likely alternatives. They
+//! are taken from the processed logits, before temperature and truncation,
+//! so they describe the model's distribution rather than the sampler's.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use super::constrained::TokenConstraint;
+use super::request::EngineCoreRequest;
+use super::types::{GenerationParams, TokenId, TokenLogprob, TopLogprob};
+use crate::error::{Error, Result};
+
+/// Most alternatives a request may ask for per token
+pub const MAX_TOP_LOGPROBS: usize = 20;
+
+/// What a processor sees of the sequence being decoded.
+#[derive(Debug, Clone, Copy)]
+pub struct LogitsContext<'a> {
+    pub request_id: &'a str,
+    /// Prompt tokens followed by the tokens generated so far
+    pub tokens: &'a [TokenId],
+    pub num_prompt_tokens: usize,
+}
+
+impl LogitsContext<'_> {
+    /// Tokens generated so far.
+    pub fn generated(&self) -> &[TokenId] {
+        &self.tokens[self.num_prompt_tokens.min(self.tokens.len())..]
+    }
+}
+
+/// Adjusts next-token logits before a token is picked.
+pub trait LogitsProcessor: Send + Sync {
+    /// Name the processor is registered under.
+    fn name(&self) -> &str;
+
+    /// Modify `logits` (one per vocabulary entry) in place. Set a logit to
+    /// negative infinity to ban a token.
+    fn process(&self, context: &LogitsContext<'_>, logits: &mut [f32]) -> Result<()>;
+
+    /// Whether the processor runs for `request`; all requests by default.
+    fn applies_to(&self, _request: &EngineCoreRequest) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for dyn LogitsProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Never pick the given tokens.
+#[derive(Debug, Clone)]
+pub struct BanTokens(pub HashSet<TokenId>);
+
+impl LogitsProcessor for BanTokens {
+    fn name(&self) -> &str {
+        "ban_tokens"
+    }
+
+    fn process(&self, _context: &LogitsContext<'_>, logits: &mut [f32]) -> Result<()> {
+        for &token in &self.0 {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Add a fixed bias to the logits of some tokens.
+#[derive(Debug, Clone)]
+pub struct LogitBias(pub HashMap<TokenId, f32>);
+
+impl LogitsProcessor for LogitBias {
+    fn name(&self) -> &str {
+        "logit_bias"
+    }
+
+    fn process(&self, _context: &LogitsContext<'_>, logits: &mut [f32]) -> Result<()> {
+        for (&token, &bias) in &self.0 {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit += bias;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Penalize tokens already generated in the last `window` tokens (CTRL-style:
+/// positive logits are divided by `penalty`, negative ones multiplied).
+#[derive(Debug, Clone)]
+pub struct RepetitionPenalty {
+    pub penalty: f32,
+    /// Generated tokens looked back over (0 = all)
+    pub window: usize,
+}
+
+impl LogitsProcessor for RepetitionPenalty {
+    fn name(&self) -> &str {
+        "repetition_penalty"
+    }
+
+    fn process(&self, context: &LogitsContext<'_>, logits: &mut [f32]) -> Result<()> {
+        let generated = context.generated();
+        let recent = match self.window {
+            0 => generated,
+            window => &generated[generated.len().saturating_sub(window)..],
+        };
+        let seen: HashSet<TokenId> = recent.iter().copied().collect();
+        for token in seen {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit = if *logit > 0.0 {
+                    *logit / self.penalty
+                } else {
+                    *logit * self.penalty
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Statistical text watermark (Kirchenbauer et al., 2023): the previous token
+/// seeds a pseudo-random "green" share `gamma` of the vocabulary, whose logits
+/// are raised by `delta`. Watermarked text holds more green tokens than
+/// chance, which [`GreenListWatermark::green_fraction`] measures.
+#[derive(Debug, Clone)]
+pub struct GreenListWatermark {
+    pub key: u64,
+    pub gamma: f32,
+    pub delta: f32,
+}
+
+impl GreenListWatermark {
+    fn is_green(&self, previous: TokenId, token: TokenId) -> bool {
+        let mut x = self.key ^ (u64::from(previous) << 32) ^ u64::from(token);
+        // SplitMix64 finalizer
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^= x >> 31;
+        (x as f64 / u64::MAX as f64) < f64::from(self.gamma)
+    }
+
+    /// Share of `tokens` (after the first) that are green.
+    pub fn green_fraction(&self, tokens: &[TokenId]) -> f32 {
+        if tokens.len() < 2 {
+            return 0.0;
+        }
+        let green = tokens
+            .windows(2)
+            .filter(|pair| self.is_green(pair[0], pair[1]))
+            .count();
+        green as f32 / (tokens.len() - 1) as f32
+    }
+}
+
+impl LogitsProcessor for GreenListWatermark {
+    fn name(&self) -> &str {
+        "watermark"
+    }
+
+    fn process(&self, context: &LogitsContext<'_>, logits: &mut [f32]) -> Result<()> {
+        let Some(&previous) = context.tokens.last() else {
+            return Ok(());
+        };
+        for (token, logit) in logits.iter_mut().enumerate() {
+            if self.is_green(previous, token as TokenId) {
+                *logit += self.delta;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Per-request token selection: logits processors, then the output
+/// constraint, then temperature, top-k and top-p sampling.
+#[derive(Debug, Clone, Default)]
+pub struct SamplerPipeline {
+    request_id: String,
+    num_prompt_tokens: usize,
+    processors: Vec<Arc<dyn LogitsProcessor>>,
+    constraint: Option<TokenConstraint>,
+    /// Alternatives kept per token, when log probabilities are kept
+    top_logprobs: Option<usize>,
+    logprobs: Vec<TokenLogprob>,
+    /// Decodes tokens for `logprobs`
+    vocab: Option<Arc<Vec<Vec<u8>>>>,
+}
+
+impl SamplerPipeline {
+    /// An empty pipeline, which leaves logits untouched.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pipeline of `request`'s logits processors. Its output constraint is
+    /// applied with tokens decoded through `vocab`, ended by `eos_token`, and
+    /// log probabilities are kept if it asks for them.
+    pub fn for_request(
+        request: &EngineCoreRequest,
+        vocab: Arc<Vec<Vec<u8>>>,
+        eos_token: Option<TokenId>,
+    ) -> Self {
+        let constraint = request.constraint.clone().map(|compiled| {
+            let constraint = TokenConstraint::new(compiled, vocab.clone());
+            match eos_token {
+                Some(token) => constraint.with_eos_token(token),
+                None => constraint,
+            }
+        });
+        Self {
+            request_id: request.id.clone(),
+            num_prompt_tokens: request.prompt_tokens.len(),
+            processors: request.logits_processors.clone(),
+            constraint,
+            top_logprobs: request.params.logprobs,
+            logprobs: Vec::new(),
+            vocab: request.params.logprobs.is_some().then_some(vocab),
+        }
+    }
+
+    /// Add a processor after the existing ones.
+    pub fn with_processor(mut self, processor: Arc<dyn LogitsProcessor>) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    /// Only pick tokens `constraint` allows.
+    pub fn with_constraint(mut self, constraint: TokenConstraint) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    /// Keep the log probability of each sampled token, with its `top` most
+    /// likely alternatives.
+    pub fn with_logprobs(mut self, top: usize) -> Self {
+        self.top_logprobs = Some(top);
+        self
+    }
+
+    /// Log probabilities of the tokens sampled so far, if kept.
+    pub fn logprobs(&self) -> &[TokenLogprob] {
+        &self.logprobs
+    }
+
+    /// Take the log probabilities kept so far.
+    pub fn take_logprobs(&mut self) -> Vec<TokenLogprob> {
+        std::mem::take(&mut self.logprobs)
+    }
+
+    /// Constraint state after the tokens accepted so far.
+    pub fn constraint(&self) -> Option<&TokenConstraint> {
+        self.constraint.as_ref()
+    }
+
+    /// Whether the pipeline changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty() && self.constraint.is_none()
+    }
+
+    fn context<'a>(&'a self, tokens: &'a [TokenId]) -> LogitsContext<'a> {
+        LogitsContext {
+            request_id: &self.request_id,
+            tokens,
+            num_prompt_tokens: self.num_prompt_tokens,
+        }
+    }
+
+    /// Run the processors and the constraint over the logits of the token
+    /// following `tokens`.
+    pub fn process(&self, tokens: &[TokenId], logits: &mut [f32]) -> Result<()> {
+        let context = self.context(tokens);
+        for processor in &self.processors {
+            processor.process(&context, logits)?;
+        }
+        if let Some(constraint) = &self.constraint {
+            for (token, logit) in logits.iter_mut().enumerate() {
+                if *logit > f32::NEG_INFINITY && !constraint.allows(token as TokenId) {
+                    *logit = f32::NEG_INFINITY;
+                }
+            }
+        }
+        if logits.iter().all(|&logit| logit == f32::NEG_INFINITY) {
+            return Err(Error::InferenceError(format!(
+                "Every token was banned for request {}",
+                self.request_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Like [`process`](Self::process) for a probability distribution,
+    /// which is renormalized.
+    pub fn process_probs(&self, tokens: &[TokenId], probs: &mut [f32]) -> Result<()> {
+        if self.processors.is_empty() {
+            return match &self.constraint {
+                Some(constraint) => constraint.mask(probs),
+                None => Ok(()),
+            };
+        }
+        probs.iter_mut().for_each(|p| *p = p.ln());
+        self.process(tokens, probs)?;
+        softmax(probs);
+        Ok(())
+    }
+
+    /// Pick the token following `tokens` from its logits, using the
+    /// temperature, top-k and top-p of `params` (`uniform` in `[0, 1)`).
+    pub fn sample(
+        &mut self,
+        tokens: &[TokenId],
+        logits: &mut [f32],
+        params: &GenerationParams,
+        uniform: f32,
+    ) -> Result<TokenId> {
+        self.process(tokens, logits)?;
+        let log_probs = self.top_logprobs.map(|_| log_softmax(logits));
+        let token = if params.temperature <= 0.0 {
+            argmax(logits)
+        } else {
+            logits.iter_mut().for_each(|l| *l /= params.temperature);
+            softmax(logits);
+            truncate(logits, params.top_k, params.top_p);
+            draw(logits, uniform).unwrap_or_else(|| argmax(logits))
+        };
+        if let (Some(top), Some(log_probs)) = (self.top_logprobs, log_probs) {
+            let logprob = self.token_logprob(token, &log_probs, top);
+            self.logprobs.push(logprob);
+        }
+        self.accept(token)?;
+        Ok(token)
+    }
+
+    fn token_logprob(&self, token: TokenId, log_probs: &[f32], top: usize) -> TokenLogprob {
+        let text = |token: TokenId| {
+            let bytes = self.vocab.as_ref()?.get(token as usize)?;
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        };
+        let mut order: Vec<usize> = (0..log_probs.len())
+            .filter(|&i| log_probs[i] > f32::NEG_INFINITY)
+            .collect();
+        let by_logprob = |a: &usize, b: &usize| log_probs[*b].total_cmp(&log_probs[*a]);
+        if top < order.len() {
+            order.select_nth_unstable_by(top, by_logprob);
+            order.truncate(top);
+        }
+        order.sort_unstable_by(by_logprob);
+        TokenLogprob {
+            token,
+            text: text(token),
+            logprob: log_probs[token as usize],
+            top_logprobs: order
+                .into_iter()
+                .map(|i| TopLogprob {
+                    token: i as TokenId,
+                    text: text(i as TokenId),
+                    logprob: log_probs[i],
+                })
+                .collect(),
+        }
+    }
+
+    /// Record that `token` was picked.
+    pub fn accept(&mut self, token: TokenId) -> Result<()> {
+        match &mut self.constraint {
+            Some(constraint) => constraint.advance(token),
+            None => Ok(()),
+        }
+    }
+}
+
+fn softmax(values: &mut [f32]) {
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    values.iter_mut().for_each(|v| *v = (*v - max).exp());
+    let total: f32 = values.iter().sum();
+    if total > 0.0 {
+        values.iter_mut().for_each(|v| *v /= total);
+    }
+}
+
+/// Log-softmax of `logits`, as a new vector
+fn log_softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let total: f32 = logits.iter().map(|l| (l - max).exp()).sum();
+    let log_total = max + total.ln();
+    logits.iter().map(|l| l - log_total).collect()
+}
+
+/// Keep the `top_k` most likely tokens (0 = all) within `top_p` cumulative
+/// probability, zeroing the rest.
+fn truncate(probs: &mut [f32], top_k: usize, top_p: f32) {
+    let mut order: Vec<usize> = (0..probs.len()).collect();
+    order.sort_unstable_by(|&a, &b| probs[b].total_cmp(&probs[a]));
+    let mut cumulative = 0.0;
+    for (rank, &token) in order.iter().enumerate() {
+        if (top_k > 0 && rank >= top_k) || (rank > 0 && cumulative >= top_p) {
+            probs[token] = 0.0;
+        } else {
+            cumulative += probs[token];
+        }
+    }
+}
+
+fn argmax(values: &[f32]) -> TokenId {
+    values
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i as TokenId)
+}
+
+/// Draw from unnormalized weights with `u` in `[0, 1)`; `None` if all are zero.
+fn draw(weights: &[f32], u: f32) -> Option<TokenId> {
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut threshold = u * total;
+    for (i, &w) in weights.iter().enumerate() {
+        if threshold < w {
+            return Some(i as TokenId);
+        }
+        threshold -= w;
+    }
+    weights.iter().rposition(|&w| w > 0.0).map(|i| i as TokenId)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_runs_processors_in_order() {
+        let mut pipeline = SamplerPipeline::new()
+            .with_processor(Arc::new(BanTokens([0].into())))
+            .with_processor(Arc::new(LogitBias([(2, 5.0)].into())))
+            .with_processor(Arc::new(RepetitionPenalty {
+                penalty: 100.0,
+                window: 0,
+            }));
+        let greedy = GenerationParams {
+            temperature: 0.0,
+            ..Default::default()
+        };
+
+        // Token 0 is banned and token 2 boosted past token 1
+        let mut logits = vec![9.0, 4.0, 0.0];
+        assert_eq!(pipeline.sample(&[], &mut logits, &greedy, 0.5).unwrap(), 2);
+        // Once generated, token 2 is penalized below token 1
+        let mut logits = vec![9.0, 4.0, 0.0];
+        assert_eq!(pipeline.sample(&[2], &mut logits, &greedy, 0.5).unwrap(), 1);
+
+        let mut logits = vec![1.0, f32::NEG_INFINITY, f32::NEG_INFINITY];
+        assert!(pipeline.process(&[], &mut logits).is_err());
+    }
+
+    #[test]
+    fn test_logprobs_kept_when_requested() {
+        let params = GenerationParams {
+            temperature: 0.0,
+            ..Default::default()
+        };
+        let mut plain = SamplerPipeline::new();
+        let mut logits = vec![1.0, 2.0];
+        plain.sample(&[], &mut logits, &params, 0.5).unwrap();
+        assert!(plain.logprobs().is_empty());
+
+        let mut pipeline = SamplerPipeline::new()
+            .with_processor(Arc::new(BanTokens([3].into())))
+            .with_logprobs(2);
+        let mut logits = vec![2.0f32.ln(), 1.0f32.ln(), 1.0f32.ln(), 9.0];
+        assert_eq!(pipeline.sample(&[], &mut logits, &params, 0.5).unwrap(), 0);
+        let mut logits = vec![0.0, 0.0, 0.0, 0.0];
+        pipeline.sample(&[0], &mut logits, &params, 0.5).unwrap();
+
+        let logprobs = pipeline.take_logprobs();
+        assert_eq!(logprobs.len(), 2);
+        // Banned tokens are left out of the distribution
+        assert!((logprobs[0].logprob - 0.5f32.ln()).abs() < 1e-5);
+        let top: Vec<TokenId> = logprobs[0].top_logprobs.iter().map(|t| t.token).collect();
+        assert_eq!(top[0], 0);
+        assert_eq!(top.len(), 2);
+        assert!((logprobs[1].top_logprobs[1].logprob - (1.0f32 / 3.0).ln()).abs() < 1e-5);
+        assert!(pipeline.logprobs().is_empty());
+    }
+
+    #[test]
+    fn test_top_k_and_top_p_truncate() {
+        let mut probs = vec![0.5, 0.3, 0.15, 0.05];
+        truncate(&mut probs, 0, 0.8);
+        assert_eq!(probs, [0.5, 0.3, 0.0, 0.0]);
+        let mut probs = vec![0.1, 0.6, 0.3];
+        truncate(&mut probs, 1, 1.0);
+        assert_eq!(probs, [0.0, 0.6, 0.0]);
+    }
+
+    #[test]
+    fn test_watermark_biases_green_tokens() {
+        let watermark = GreenListWatermark {
+            key: 42,
+            gamma: 0.5,
+            delta: 100.0,
+        };
+        let mut pipeline = SamplerPipeline::new().with_processor(Arc::new(watermark.clone()));
+        let params = GenerationParams::default();
+        let mut tokens = vec![1];
+        for step in 0..64 {
+            let mut logits = vec![0.0; 50];
+            let u = (step as f32 * 0.618).fract();
+            let token = pipeline.sample(&tokens, &mut logits, &params, u).unwrap();
+            tokens.push(token);
+        }
+        assert!(watermark.green_fraction(&tokens) > 0.95);
+        let plain: Vec<TokenId> = (0..64).map(|i| (i * 7) % 50).collect();
+        assert!(watermark.green_fraction(&plain) < 0.8);
+    }
+}
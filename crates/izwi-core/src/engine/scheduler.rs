@@ -1,4 +1,5 @@
-//! Request scheduler with support for FCFS and priority-based scheduling.
+//! Request scheduler with support for FCFS, priority-based and fair-share
+//! scheduling.
 //!
 //! The scheduler manages request queues and decides which requests to process
 //! in each engine step. It handles:
@@ -9,13 +10,16 @@
 
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap, VecDeque};
-use std::time::Instant;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use tracing::debug;
 
+use super::clock::{self, SharedClock};
 use super::config::EngineCoreConfig;
 use super::kv_cache::KVCacheManager;
+use super::latency::DelayReason;
 use super::request::{EngineCoreRequest, RequestStatus};
+use super::session;
 use super::types::{BlockId, Priority, RequestId, SequenceId};
 
 /// Scheduling policy for the engine.
@@ -23,9 +27,15 @@ use super::types::{BlockId, Priority, RequestId, SequenceId};
 pub enum SchedulingPolicy {
     /// First-come, first-served (default)
     #[default]
+    #[serde(alias = "fcfs")]
     FCFS,
     /// Priority-based scheduling (higher priority first)
+    #[serde(alias = "priority")]
     Priority,
+    /// Clients with the least recent token usage (relative to their weight)
+    /// go first, so one client cannot monopolize the batch
+    #[serde(alias = "fair_share")]
+    FairShare,
 }
 
 /// Configuration for the scheduler.
@@ -45,6 +55,17 @@ pub struct SchedulerConfig {
     pub enable_preemption: bool,
     /// Enable VAD-triggered preemption (for audio interruption handling)
     pub enable_vad_preemption: bool,
+    /// Draft tokens verified per decode step (speculative decoding)
+    pub num_lookahead_tokens: usize,
+    /// Fair-share weight per client ID (unlisted clients weigh 1.0)
+    pub client_weights: HashMap<String, f64>,
+    /// Half-life of the per-client token usage tracked for fair-share scheduling
+    pub fair_share_half_life: Duration,
+    /// Tokens of KV cache each sequence keeps resident besides its sinks
+    /// (0 = keep everything); older blocks are evicted as it grows
+    pub window_tokens: usize,
+    /// Leading tokens kept past the window as attention sinks
+    pub sink_tokens: usize,
 }
 
 /// Preemption reason - why a request was preempted.
@@ -81,6 +102,11 @@ impl Default for SchedulerConfig {
             chunked_prefill_threshold: 256,
             enable_preemption: true,
             enable_vad_preemption: true,
+            num_lookahead_tokens: 0,
+            client_weights: HashMap::new(),
+            fair_share_half_life: Duration::from_secs(10),
+            window_tokens: 0,
+            sink_tokens: 0,
         }
     }
 }
@@ -95,6 +121,11 @@ impl From<&EngineCoreConfig> for SchedulerConfig {
             chunked_prefill_threshold: config.chunked_prefill_threshold,
             enable_preemption: config.enable_preemption,
             enable_vad_preemption: true, // Default to enabled for audio apps
+            num_lookahead_tokens: config.num_lookahead_tokens(),
+            client_weights: config.client_weights.clone(),
+            fair_share_half_life: Duration::from_millis(config.fair_share_half_life_ms),
+            window_tokens: config.kv_window_tokens,
+            sink_tokens: config.kv_sink_tokens,
         }
     }
 }
@@ -144,6 +175,8 @@ pub struct ScheduleResult {
     pub total_tokens: usize,
     /// Number of blocks allocated
     pub blocks_allocated: usize,
+    /// Requests left out of this step and the limit that excluded them
+    pub deferred: Vec<(RequestId, DelayReason)>,
 }
 
 impl ScheduleResult {
@@ -154,6 +187,7 @@ impl ScheduleResult {
             preempted_requests: Vec::new(),
             total_tokens: 0,
             blocks_allocated: 0,
+            deferred: Vec::new(),
         }
     }
 
@@ -184,12 +218,17 @@ pub struct ScheduledRequest {
     pub sequence_id: SequenceId,
     /// Number of tokens to process this step
     pub num_tokens: usize,
+    /// Draft tokens to propose and verify this step (included in `num_tokens`)
+    pub num_lookahead_tokens: usize,
     /// Whether this is a prefill (first pass) or decode (continuation)
     pub is_prefill: bool,
     /// KV cache blocks allocated to this request
     pub block_ids: Vec<BlockId>,
     /// Number of tokens already computed (for chunked prefill)
     pub num_computed_tokens: usize,
+    /// Blocks evicted from the sliding window; `block_ids` skip them after
+    /// the sink blocks
+    pub num_evicted_blocks: usize,
 }
 
 /// Request scheduler.
@@ -205,6 +244,32 @@ pub struct Scheduler {
     requests: HashMap<RequestId, RequestMetadata>,
     /// Next sequence ID
     next_sequence_id: SequenceId,
+    /// Time source for arrival and waiting times
+    clock: SharedClock,
+    /// Whether new requests are held in the waiting queue
+    admission_paused: bool,
+    /// Running requests not decoded until their stream drains
+    stalled: HashSet<RequestId>,
+    /// Recent token usage per client (fair-share mode)
+    client_usage: HashMap<String, ClientUsage>,
+}
+
+/// Exponentially decaying token count of one client.
+#[derive(Debug, Clone, Copy)]
+struct ClientUsage {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl ClientUsage {
+    fn decayed(&self, half_life: Duration, now: Instant) -> f64 {
+        let half_life = half_life.as_secs_f64();
+        if half_life <= 0.0 {
+            return self.tokens;
+        }
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens * 0.5f64.powf(elapsed / half_life)
+    }
 }
 
 /// Metadata for a request in the scheduler.
@@ -216,6 +281,10 @@ struct RequestMetadata {
     arrival_time: Instant,
     total_prompt_tokens: usize,
     max_tokens: usize,
+    /// Client identity (empty for anonymous requests)
+    client: String,
+    /// KV cache owner and length of a cached prompt prefix (chat history)
+    prefix: Option<(RequestId, usize)>,
 }
 
 /// State for a running request.
@@ -245,34 +314,51 @@ impl Scheduler {
             running: HashMap::new(),
             requests: HashMap::new(),
             next_sequence_id: 0,
+            clock: clock::system_clock(),
+            admission_paused: false,
+            stalled: HashSet::new(),
+            client_usage: HashMap::new(),
         }
     }
 
+    /// Use `clock` as the time source.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Add a request to the waiting queue.
     pub fn add_request(&mut self, request: &EngineCoreRequest) {
         let sequence_id = self.next_sequence_id;
         self.next_sequence_id += 1;
 
+        let arrival_time = self.clock.now();
         let metadata = RequestMetadata {
             request_id: request.id.clone(),
             sequence_id,
             priority: request.priority,
-            arrival_time: Instant::now(),
+            arrival_time,
             total_prompt_tokens: request.num_prompt_tokens(),
             max_tokens: request.params.max_tokens,
+            client: request.client_id.clone().unwrap_or_default(),
+            prefix: request
+                .session_id
+                .as_deref()
+                .filter(|_| request.prefix_tokens > 0)
+                .map(|id| (session::prefix_key(id), request.prefix_tokens)),
         };
 
         self.requests.insert(request.id.clone(), metadata);
 
         match self.config.policy {
-            SchedulingPolicy::FCFS => {
+            SchedulingPolicy::FCFS | SchedulingPolicy::FairShare => {
                 self.waiting_fcfs.push_back(request.id.clone());
             }
             SchedulingPolicy::Priority => {
                 self.waiting_priority.push(PriorityRequest {
                     request_id: request.id.clone(),
                     priority: request.priority,
-                    arrival_time: Instant::now(),
+                    arrival_time,
                 });
             }
         }
@@ -285,48 +371,88 @@ impl Scheduler {
         );
     }
 
+    /// Stop or resume starting waiting requests; running ones keep decoding.
+    pub fn set_admission_paused(&mut self, paused: bool) {
+        self.admission_paused = paused;
+    }
+
+    /// Hold back decoding of `request_ids` (replacing the previous set), for
+    /// streams whose consumer is behind.
+    pub fn set_stalled(&mut self, request_ids: HashSet<RequestId>) {
+        self.stalled = request_ids;
+    }
+
     /// Schedule requests for the next step.
     pub fn schedule(&mut self, kv_cache: &mut KVCacheManager) -> ScheduleResult {
         let mut result = ScheduleResult::empty();
         let mut remaining_budget = self.config.max_tokens_per_step;
         let mut remaining_batch = self.config.max_batch_size;
+        let now = self.clock.now();
+        let fair_share = self.config.policy == SchedulingPolicy::FairShare;
 
         // Phase 1: Schedule decode requests (already running)
         // First collect candidates to avoid borrow checker issues
-        let block_size = 16; // Default block size
-        let decode_candidates: Vec<_> = self
+        let mut decode_candidates: Vec<_> = self
             .running
             .iter()
             .filter(|(_, r)| r.prefill_complete)
             .map(|(id, r)| {
-                let num_tokens = 1;
-                let total_tokens = r.num_tokens_processed + num_tokens;
-                let blocks_needed = (total_tokens + block_size - 1) / block_size;
-                let additional_blocks = if blocks_needed > r.block_ids.len() {
-                    blocks_needed - r.block_ids.len()
-                } else {
-                    0
-                };
+                // One token plus any draft tokens, but no more than are left
+                let remaining_tokens = self
+                    .requests
+                    .get(id)
+                    .map(|m| m.max_tokens.saturating_sub(r.num_tokens_generated))
+                    .unwrap_or(1);
+                let wanted_tokens =
+                    (1 + self.config.num_lookahead_tokens).min(remaining_tokens.max(1));
                 (
                     id.clone(),
                     r.sequence_id,
                     r.priority,
                     r.block_ids.clone(),
                     r.num_tokens_processed,
-                    additional_blocks,
+                    wanted_tokens,
                 )
             })
             .collect();
+        if fair_share {
+            decode_candidates.sort_by(|a, b| {
+                self.request_usage(&a.0, now)
+                    .total_cmp(&self.request_usage(&b.0, now))
+            });
+        }
 
         // Now process decode candidates with potential preemption
-        for (request_id, sequence_id, priority, block_ids, num_computed, additional_blocks) in
+        for (request_id, sequence_id, priority, mut block_ids, num_computed, wanted_tokens) in
             decode_candidates
         {
-            if remaining_batch == 0 || remaining_budget == 0 {
-                break;
+            if self.stalled.contains(&request_id) {
+                result
+                    .deferred
+                    .push((request_id, DelayReason::Backpressure));
+                continue;
+            }
+            if remaining_batch == 0 {
+                result.deferred.push((request_id, DelayReason::BatchSize));
+                continue;
+            }
+            if remaining_budget == 0 {
+                result.deferred.push((request_id, DelayReason::TokenBudget));
+                continue;
             }
 
-            let num_tokens = 1;
+            let num_tokens = wanted_tokens.min(remaining_budget);
+            let needed_blocks = self.blocks_needed_for_tokens(num_computed + num_tokens);
+            let mut evicted_blocks = kv_cache.evicted_blocks(&request_id);
+            let mut additional_blocks =
+                needed_blocks.saturating_sub(block_ids.len() + evicted_blocks);
+            if self.slide_window(&request_id, additional_blocks, kv_cache) > 0 {
+                block_ids = kv_cache
+                    .get_blocks(&request_id)
+                    .map_or_else(Vec::new, <[BlockId]>::to_vec);
+                evicted_blocks = kv_cache.evicted_blocks(&request_id);
+                additional_blocks = needed_blocks.saturating_sub(block_ids.len() + evicted_blocks);
+            }
 
             // Check if we need to allocate more blocks
             if additional_blocks > 0 {
@@ -340,39 +466,57 @@ impl Scheduler {
                             // Re-check if we can allocate now
                             if !kv_cache.can_allocate(additional_blocks) {
                                 debug!("Still cannot allocate after preemption for {}", request_id);
+                                result.deferred.push((request_id, DelayReason::KvCache));
                                 continue;
                             }
                         } else {
                             debug!("No suitable requests to preempt for {}", request_id);
+                            result.deferred.push((request_id, DelayReason::KvCache));
                             continue;
                         }
                     } else {
+                        result.deferred.push((request_id, DelayReason::KvCache));
                         continue;
                     }
                 }
+
+                // Slots for the new token and any draft tokens
+                let new_blocks = kv_cache.extend(&request_id, additional_blocks);
+                result.blocks_allocated += new_blocks.len();
+                if let Some(running) = self.running.get_mut(&request_id) {
+                    running.block_ids.extend(new_blocks.iter().copied());
+                }
+                block_ids.extend(new_blocks);
             }
 
             result.decode_requests.push(ScheduledRequest {
                 request_id: request_id.clone(),
                 sequence_id,
                 num_tokens,
+                num_lookahead_tokens: num_tokens - 1,
                 is_prefill: false,
                 block_ids,
                 num_computed_tokens: num_computed,
+                num_evicted_blocks: evicted_blocks,
             });
 
+            if fair_share {
+                self.charge(&request_id, num_tokens, now);
+            }
             remaining_budget = remaining_budget.saturating_sub(num_tokens);
             remaining_batch -= 1;
             result.total_tokens += num_tokens;
         }
 
         // Phase 2: Schedule prefill requests (from waiting queue)
-        while remaining_batch > 0 && remaining_budget > 0 {
+        let mut kv_blocked = false;
+        while !self.admission_paused && remaining_batch > 0 && remaining_budget > 0 {
             let next_request_id = match self.config.policy {
                 SchedulingPolicy::FCFS => self.waiting_fcfs.front().cloned(),
                 SchedulingPolicy::Priority => {
                     self.waiting_priority.peek().map(|r| r.request_id.clone())
                 }
+                SchedulingPolicy::FairShare => self.next_fair_share(now),
             };
 
             let request_id = match next_request_id {
@@ -383,19 +527,40 @@ impl Scheduler {
             let metadata = match self.requests.get(&request_id) {
                 Some(m) => m.clone(),
                 None => {
-                    self.pop_from_waiting();
+                    self.pop_from_waiting(&request_id);
                     continue;
                 }
             };
 
             // Check if already running (shouldn't happen, but safety check)
             if self.running.contains_key(&request_id) {
-                self.pop_from_waiting();
+                self.pop_from_waiting(&request_id);
                 continue;
             }
 
+            // A cached prefix is shared rather than prefilled again; at least
+            // one prompt token is always computed. Blocks the prefix evicted
+            // from its window still count as cached.
+            let (prefix_blocks, evicted_blocks, cached_tokens) = metadata
+                .prefix
+                .as_ref()
+                .and_then(|(owner, tokens)| {
+                    let blocks = kv_cache.get_blocks(owner)?.len();
+                    Some((blocks, kv_cache.evicted_blocks(owner), *tokens))
+                })
+                .map(|(blocks, evicted, tokens)| {
+                    let tokens = tokens
+                        .min((blocks + evicted) * kv_cache.config().block_size)
+                        .min(metadata.total_prompt_tokens.saturating_sub(1));
+                    (blocks, evicted, tokens)
+                })
+                .unwrap_or((0, 0, 0));
+            // The partly filled last block is copied before it is written to
+            let copied_blocks =
+                usize::from(cached_tokens > 0 && cached_tokens % kv_cache.config().block_size != 0);
+
             // Calculate tokens for this prefill
-            let mut num_tokens = metadata.total_prompt_tokens;
+            let mut num_tokens = metadata.total_prompt_tokens - cached_tokens;
 
             // Apply chunked prefill if enabled and prompt is long
             if self.config.enable_chunked_prefill
@@ -408,7 +573,10 @@ impl Scheduler {
             num_tokens = num_tokens.min(remaining_budget);
 
             // Allocate KV cache blocks
-            let blocks_needed = self.blocks_needed_for_tokens(num_tokens);
+            let blocks_needed = self
+                .blocks_needed_for_tokens(cached_tokens + num_tokens)
+                .saturating_sub(prefix_blocks + evicted_blocks)
+                + copied_blocks;
             if !kv_cache.can_allocate(blocks_needed) {
                 // Can't fit this request, try preemption or skip
                 if self.config.enable_preemption {
@@ -422,28 +590,42 @@ impl Scheduler {
                                 "Still cannot allocate after preemption for prefill {}",
                                 request_id
                             );
+                            kv_blocked = true;
                             break;
                         }
                     } else {
                         debug!("No suitable requests to preempt for prefill {}", request_id);
+                        kv_blocked = true;
                         break;
                     }
                 } else {
+                    kv_blocked = true;
                     break;
                 }
             }
 
-            let block_ids = kv_cache.allocate(&request_id, blocks_needed);
-            result.blocks_allocated += block_ids.len();
+            if cached_tokens > 0 {
+                if let Some((owner, _)) = &metadata.prefix {
+                    kv_cache.fork(owner, &request_id);
+                    if copied_blocks > 0 {
+                        kv_cache.copy_on_write(&request_id, prefix_blocks - 1);
+                    }
+                }
+            }
+            let new_blocks = kv_cache.allocate(&request_id, blocks_needed - copied_blocks);
+            result.blocks_allocated += new_blocks.len() + copied_blocks;
+            let block_ids = kv_cache
+                .get_blocks(&request_id)
+                .map_or(new_blocks, |blocks| blocks.to_vec());
 
             // Create running state
             let running = RunningRequest {
                 request_id: request_id.clone(),
                 sequence_id: metadata.sequence_id,
-                num_tokens_processed: 0,
+                num_tokens_processed: cached_tokens,
                 num_tokens_generated: 0,
                 block_ids: block_ids.clone(),
-                prefill_complete: num_tokens >= metadata.total_prompt_tokens,
+                prefill_complete: cached_tokens + num_tokens >= metadata.total_prompt_tokens,
                 priority: metadata.priority,
             };
 
@@ -451,19 +633,48 @@ impl Scheduler {
                 request_id: request_id.clone(),
                 sequence_id: metadata.sequence_id,
                 num_tokens,
+                num_lookahead_tokens: 0,
                 is_prefill: true,
                 block_ids,
-                num_computed_tokens: 0,
+                num_computed_tokens: cached_tokens,
+                num_evicted_blocks: if cached_tokens > 0 { evicted_blocks } else { 0 },
             });
 
+            self.pop_from_waiting(&request_id);
+            if fair_share {
+                self.charge(&request_id, num_tokens, now);
+            }
             self.running.insert(request_id, running);
-            self.pop_from_waiting();
 
             remaining_budget = remaining_budget.saturating_sub(num_tokens);
             remaining_batch -= 1;
             result.total_tokens += num_tokens;
         }
 
+        // Everything still waiting was held back by whichever limit ended the loop
+        let reason = if self.admission_paused {
+            DelayReason::ModelSwap
+        } else if kv_blocked {
+            DelayReason::KvCache
+        } else if remaining_batch == 0 {
+            DelayReason::BatchSize
+        } else {
+            DelayReason::TokenBudget
+        };
+        let waiting: Vec<RequestId> = match self.config.policy {
+            SchedulingPolicy::FCFS | SchedulingPolicy::FairShare => {
+                self.waiting_fcfs.iter().cloned().collect()
+            }
+            SchedulingPolicy::Priority => self
+                .waiting_priority
+                .iter()
+                .map(|r| r.request_id.clone())
+                .collect(),
+        };
+        result
+            .deferred
+            .extend(waiting.into_iter().map(|id| (id, reason)));
+
         result
     }
 
@@ -513,12 +724,44 @@ impl Scheduler {
         // Remove from running
         if let Some(running) = self.running.remove(request_id) {
             kv_cache.free(&running.request_id);
-            self.requests.remove(request_id);
-            return true;
         }
 
-        self.requests.remove(request_id);
-        false
+        self.requests.remove(request_id).is_some()
+    }
+
+    /// Change the priority of a waiting request.
+    ///
+    /// Under the priority policy the request is re-queued in place, keeping
+    /// its original arrival time. Returns the previous priority, or `None`
+    /// if the request is not waiting.
+    pub fn update_priority(
+        &mut self,
+        request_id: &RequestId,
+        priority: Priority,
+    ) -> Option<Priority> {
+        if self.running.contains_key(request_id) {
+            return None;
+        }
+        let metadata = self.requests.get_mut(request_id)?;
+        let previous = metadata.priority;
+        metadata.priority = priority;
+
+        if self.config.policy == SchedulingPolicy::Priority {
+            let arrival_time = metadata.arrival_time;
+            self.waiting_priority
+                .retain(|r| &r.request_id != request_id);
+            self.waiting_priority.push(PriorityRequest {
+                request_id: request_id.clone(),
+                priority,
+                arrival_time,
+            });
+        }
+
+        debug!(
+            "Updated priority of {} from {:?} to {:?}",
+            request_id, previous, priority
+        );
+        Some(previous)
     }
 
     /// Check if a request exists in the scheduler.
@@ -537,14 +780,30 @@ impl Scheduler {
         }
     }
 
+    /// Time a request has spent in the scheduler since arrival.
+    pub fn waiting_time(&self, request_id: &RequestId) -> Option<Duration> {
+        self.requests
+            .get(request_id)
+            .map(|m| self.clock.elapsed_since(m.arrival_time))
+    }
+
     /// Get number of waiting requests.
     pub fn waiting_count(&self) -> usize {
         match self.config.policy {
-            SchedulingPolicy::FCFS => self.waiting_fcfs.len(),
+            SchedulingPolicy::FCFS | SchedulingPolicy::FairShare => self.waiting_fcfs.len(),
             SchedulingPolicy::Priority => self.waiting_priority.len(),
         }
     }
 
+    /// Prompt tokens of the requests still waiting.
+    pub fn waiting_tokens(&self) -> usize {
+        self.requests
+            .iter()
+            .filter(|(id, _)| !self.running.contains_key(*id))
+            .map(|(_, metadata)| metadata.total_prompt_tokens)
+            .sum()
+    }
+
     /// Get number of running requests.
     pub fn running_count(&self) -> usize {
         self.running.len()
@@ -555,6 +814,13 @@ impl Scheduler {
         self.waiting_count() > 0 || self.running_count() > 0
     }
 
+    /// IDs of the running requests, in order.
+    pub fn running_request_ids(&self) -> Vec<RequestId> {
+        let mut ids: Vec<_> = self.running.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
     /// Get running request info.
     pub fn get_running_info(&self, request_id: &RequestId) -> Option<(usize, usize)> {
         self.running
@@ -562,9 +828,18 @@ impl Scheduler {
             .map(|r| (r.num_tokens_processed, r.num_tokens_generated))
     }
 
+    /// Recent token usage of a client, decayed to now (fair-share mode).
+    pub fn client_usage(&self, client_id: &str) -> f64 {
+        let now = self.clock.now();
+        self.client_usage
+            .get(client_id)
+            .map_or(0.0, |usage| self.decayed(usage, now))
+    }
+
     // Helper methods
 
-    fn pop_from_waiting(&mut self) {
+    /// Remove the request just picked from the head of the waiting queue.
+    fn pop_from_waiting(&mut self, request_id: &RequestId) {
         match self.config.policy {
             SchedulingPolicy::FCFS => {
                 self.waiting_fcfs.pop_front();
@@ -572,7 +847,98 @@ impl Scheduler {
             SchedulingPolicy::Priority => {
                 self.waiting_priority.pop();
             }
+            SchedulingPolicy::FairShare => {
+                if let Some(pos) = self.waiting_fcfs.iter().position(|id| id == request_id) {
+                    self.waiting_fcfs.remove(pos);
+                }
+            }
+        }
+    }
+
+    /// Oldest waiting request of the client with the lowest weighted usage.
+    fn next_fair_share(&self, now: Instant) -> Option<RequestId> {
+        // `min_by` keeps the first of equal elements, so ties go to arrival order
+        self.waiting_fcfs
+            .iter()
+            .map(|id| (id, self.request_usage(id, now)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Usage of the request's client divided by the client's weight.
+    fn request_usage(&self, request_id: &RequestId, now: Instant) -> f64 {
+        let Some(client) = self.requests.get(request_id).map(|m| m.client.as_str()) else {
+            return 0.0;
+        };
+        let weight = self
+            .config
+            .client_weights
+            .get(client)
+            .copied()
+            .unwrap_or(1.0)
+            .max(f64::EPSILON);
+        let usage = self
+            .client_usage
+            .get(client)
+            .map_or(0.0, |usage| self.decayed(usage, now));
+        usage / weight
+    }
+
+    fn decayed(&self, usage: &ClientUsage, now: Instant) -> f64 {
+        usage.decayed(self.config.fair_share_half_life, now)
+    }
+
+    /// Add tokens scheduled for a request to its client's usage.
+    fn charge(&mut self, request_id: &RequestId, tokens: usize, now: Instant) {
+        let Some(client) = self.requests.get(request_id).map(|m| m.client.clone()) else {
+            return;
+        };
+        let tokens = self
+            .client_usage
+            .get(&client)
+            .map_or(0.0, |usage| self.decayed(usage, now))
+            + tokens as f64;
+        self.client_usage.insert(
+            client,
+            ClientUsage {
+                tokens,
+                updated: now,
+            },
+        );
+
+        // Forget clients whose usage has decayed away
+        let half_life = self.config.fair_share_half_life;
+        self.client_usage
+            .retain(|_, usage| usage.decayed(half_life, now) >= 0.5);
+    }
+
+    /// With a sliding window, evict the request's oldest blocks after its
+    /// sinks so that no more than the window stays resident once
+    /// `additional_blocks` are added. Returns the number of blocks freed.
+    fn slide_window(
+        &mut self,
+        request_id: &RequestId,
+        additional_blocks: usize,
+        kv_cache: &mut KVCacheManager,
+    ) -> usize {
+        if self.config.window_tokens == 0 {
+            return 0;
+        }
+        let Some(resident) = kv_cache.get_blocks(request_id).map(<[BlockId]>::len) else {
+            return 0;
+        };
+        let sink_blocks = kv_cache.blocks_for_tokens(self.config.sink_tokens);
+        let window_blocks = kv_cache.blocks_for_tokens(self.config.window_tokens);
+        let excess = (resident + additional_blocks).saturating_sub(sink_blocks + window_blocks);
+        let freed = kv_cache.evict(request_id, sink_blocks, excess);
+        if freed > 0 {
+            if let Some(running) = self.running.get_mut(request_id) {
+                running.block_ids = kv_cache
+                    .get_blocks(request_id)
+                    .map_or_else(Vec::new, <[BlockId]>::to_vec);
+            }
         }
+        freed
     }
 
     fn blocks_needed_for_tokens(&self, num_tokens: usize) -> usize {
@@ -621,28 +987,10 @@ impl Scheduler {
             }
 
             // Remove from running and free blocks
-            if let Some(running) = self.running.remove(&request_id) {
-                kv_cache.free(&request_id);
+            if self.requeue(&request_id, kv_cache) {
                 blocks_freed += num_blocks;
                 preempted.push(request_id.clone());
 
-                // Re-add to waiting queue for later processing
-                if let Some(metadata) = self.requests.get(&request_id) {
-                    match self.config.policy {
-                        SchedulingPolicy::FCFS => {
-                            // Add to front of queue (will be processed soon)
-                            self.waiting_fcfs.push_front(request_id.clone());
-                        }
-                        SchedulingPolicy::Priority => {
-                            self.waiting_priority.push(PriorityRequest {
-                                request_id: request_id.clone(),
-                                priority: running.priority,
-                                arrival_time: metadata.arrival_time,
-                            });
-                        }
-                    }
-                }
-
                 debug!(
                     "Preempted request {} (freed {} blocks, total freed: {})",
                     request_id, num_blocks, blocks_freed
@@ -666,6 +1014,44 @@ impl Scheduler {
 
         preempted
     }
+
+    /// Move every running request back to the waiting queue, freeing its
+    /// KV cache blocks. Returns the requeued IDs, oldest first.
+    pub fn requeue_running(&mut self, kv_cache: &mut KVCacheManager) -> Vec<RequestId> {
+        let mut ids: Vec<_> = self.running.keys().cloned().collect();
+        ids.sort_by_key(|id| self.requests.get(id).map(|m| m.arrival_time));
+
+        // Pushed to the front newest first so the oldest ends up leading
+        for id in ids.iter().rev() {
+            self.requeue(id, kv_cache);
+        }
+        ids
+    }
+
+    /// Return a running request to the waiting queue so it starts over.
+    fn requeue(&mut self, request_id: &RequestId, kv_cache: &mut KVCacheManager) -> bool {
+        let Some(running) = self.running.remove(request_id) else {
+            return false;
+        };
+        kv_cache.free(request_id);
+
+        if let Some(metadata) = self.requests.get(request_id) {
+            match self.config.policy {
+                SchedulingPolicy::FCFS | SchedulingPolicy::FairShare => {
+                    // Add to front of queue (will be processed soon)
+                    self.waiting_fcfs.push_front(request_id.clone());
+                }
+                SchedulingPolicy::Priority => {
+                    self.waiting_priority.push(PriorityRequest {
+                        request_id: request_id.clone(),
+                        priority: running.priority,
+                        arrival_time: metadata.arrival_time,
+                    });
+                }
+            }
+        }
+        true
+    }
 }
 
 #[cfg(test)]
@@ -679,4 +1065,238 @@ mod tests {
         assert_eq!(scheduler.waiting_count(), 0);
         assert_eq!(scheduler.running_count(), 0);
     }
+
+    #[test]
+    fn test_priority_bump_reorders_waiting_queue() {
+        use crate::engine::kv_cache::KVCacheConfig;
+
+        let config = SchedulerConfig {
+            max_batch_size: 1,
+            policy: SchedulingPolicy::Priority,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config);
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+
+        let first = EngineCoreRequest::tts("first request");
+        let second = EngineCoreRequest::tts("second request");
+        scheduler.add_request(&first);
+        scheduler.add_request(&second);
+
+        assert_eq!(
+            scheduler.update_priority(&second.id, Priority::High),
+            Some(Priority::Normal)
+        );
+        assert_eq!(scheduler.waiting_count(), 2);
+
+        let result = scheduler.schedule(&mut kv_cache);
+        assert_eq!(result.prefill_requests[0].request_id, second.id);
+        assert_eq!(
+            result.deferred,
+            vec![(first.id.clone(), DelayReason::BatchSize)]
+        );
+
+        // Running requests can no longer be reprioritized
+        assert_eq!(scheduler.update_priority(&second.id, Priority::Low), None);
+    }
+
+    #[test]
+    fn test_high_priority_request_jumps_queue() {
+        use crate::engine::kv_cache::KVCacheConfig;
+
+        let config = SchedulerConfig {
+            max_batch_size: 1,
+            policy: SchedulingPolicy::Priority,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config);
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+
+        let normal = EngineCoreRequest::tts("normal request");
+        let low = EngineCoreRequest::tts("low request").with_priority(Priority::Low);
+        let high = EngineCoreRequest::tts("high request").with_priority(Priority::High);
+        let later = EngineCoreRequest::tts("later normal request");
+        for request in [&normal, &low, &high, &later] {
+            scheduler.add_request(request);
+        }
+
+        // Highest priority first, then arrival order within a priority
+        let mut order = Vec::new();
+        while let Some(scheduled) = scheduler.schedule(&mut kv_cache).prefill_requests.first() {
+            order.push(scheduled.request_id.clone());
+            scheduler.finish_request(&scheduled.request_id, &mut kv_cache);
+        }
+        assert_eq!(order, vec![high.id, normal.id, later.id, low.id]);
+    }
+
+    #[test]
+    fn test_prefill_reuses_cached_session_prefix() {
+        use crate::engine::kv_cache::KVCacheConfig;
+
+        let mut scheduler = Scheduler::new(SchedulerConfig::default());
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+        let key = session::prefix_key("chat");
+        let prefix = kv_cache.allocate(&key, 3);
+
+        // 40 of 100 prompt tokens are cached in 3 blocks, the last partly filled
+        let mut request = EngineCoreRequest::tts("next turn");
+        request.prompt_tokens = (0..100).collect();
+        request.session_id = Some("chat".to_string());
+        request.prefix_tokens = 40;
+        scheduler.add_request(&request);
+
+        let result = scheduler.schedule(&mut kv_cache);
+        let prefill = &result.prefill_requests[0];
+        assert_eq!((prefill.num_tokens, prefill.num_computed_tokens), (60, 40));
+        assert_eq!(prefill.block_ids.len(), 7);
+        assert_eq!(prefill.block_ids[..2], prefix[..2]);
+        assert_ne!(prefill.block_ids[2], prefix[2]);
+        assert_eq!(kv_cache.ref_count(prefix[0]), 2);
+        assert_eq!(kv_cache.ref_count(prefix[2]), 1);
+        assert_eq!(result.blocks_allocated, 5);
+    }
+
+    #[test]
+    fn test_sliding_window_bounds_resident_blocks() {
+        use crate::engine::kv_cache::KVCacheConfig;
+
+        let config = SchedulerConfig {
+            window_tokens: 32,
+            sink_tokens: 16,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config);
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+
+        let mut request = EngineCoreRequest::tts("long generation");
+        request.prompt_tokens = (0..40).collect();
+        request.params.max_tokens = 200;
+        scheduler.add_request(&request);
+
+        let prefill = scheduler.schedule(&mut kv_cache);
+        let sink = prefill.prefill_requests[0].block_ids[0];
+        scheduler.update_after_step(&request.id, 40, 0, Vec::new());
+
+        for _ in 0..100 {
+            let result = scheduler.schedule(&mut kv_cache);
+            let decode = &result.decode_requests[0];
+            assert!(decode.block_ids.len() <= 3);
+            assert_eq!(decode.block_ids[0], sink);
+            assert_eq!(
+                decode.block_ids.len() + decode.num_evicted_blocks,
+                (decode.num_computed_tokens + decode.num_tokens).div_ceil(16)
+            );
+            scheduler.update_after_step(&request.id, 1, 1, Vec::new());
+        }
+        assert_eq!(kv_cache.evicted_blocks(&request.id), 6);
+
+        scheduler.finish_request(&request.id, &mut kv_cache);
+        assert_eq!(kv_cache.evicted_blocks(&request.id), 0);
+    }
+
+    #[test]
+    fn test_fair_share_interleaves_clients() {
+        use crate::engine::clock::MockClock;
+        use crate::engine::kv_cache::KVCacheConfig;
+        use std::sync::Arc;
+
+        let clock = Arc::new(MockClock::new());
+        let config = SchedulerConfig {
+            max_batch_size: 2,
+            policy: SchedulingPolicy::FairShare,
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(config).with_clock(clock.clone());
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+
+        let busy: Vec<_> = (0..3)
+            .map(|i| EngineCoreRequest::tts(format!("busy request {i}")).with_client_id("busy"))
+            .collect();
+        let quiet = EngineCoreRequest::tts("quiet request").with_client_id("quiet");
+        for request in busy.iter().chain([&quiet]) {
+            scheduler.add_request(request);
+        }
+
+        // FCFS would run two "busy" requests; fair share lets "quiet" in
+        let result = scheduler.schedule(&mut kv_cache);
+        let ids: Vec<_> = result
+            .prefill_requests
+            .iter()
+            .map(|r| r.request_id.clone())
+            .collect();
+        assert_eq!(ids, vec![busy[0].id.clone(), quiet.id.clone()]);
+        assert_eq!(scheduler.waiting_count(), 2);
+
+        let usage = scheduler.client_usage("busy");
+        assert!(usage > 0.0);
+        clock.advance(Duration::from_secs(10));
+        assert!((scheduler.client_usage("busy") - usage / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_waiting_time_uses_clock() {
+        use crate::engine::clock::MockClock;
+        use std::sync::Arc;
+
+        let clock = Arc::new(MockClock::new());
+        let mut scheduler = Scheduler::new(SchedulerConfig::default()).with_clock(clock.clone());
+
+        let request = EngineCoreRequest::tts("hello");
+        scheduler.add_request(&request);
+        assert_eq!(scheduler.waiting_time(&request.id), Some(Duration::ZERO));
+
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(
+            scheduler.waiting_time(&request.id),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(scheduler.waiting_time(&"missing".to_string()), None);
+    }
+
+    #[test]
+    fn test_paused_admission_and_requeue() {
+        use crate::engine::clock::MockClock;
+        use crate::engine::kv_cache::KVCacheConfig;
+        use std::sync::Arc;
+
+        let clock = Arc::new(MockClock::new());
+        let mut scheduler = Scheduler::new(SchedulerConfig::default()).with_clock(clock.clone());
+        let mut kv_cache = KVCacheManager::new(KVCacheConfig::default());
+
+        let requests: Vec<_> = ["first", "second", "third"]
+            .into_iter()
+            .map(EngineCoreRequest::tts)
+            .collect();
+        for request in &requests[..2] {
+            scheduler.add_request(request);
+            clock.advance(Duration::from_millis(1));
+        }
+        assert_eq!(scheduler.schedule(&mut kv_cache).prefill_requests.len(), 2);
+
+        scheduler.set_admission_paused(true);
+        scheduler.add_request(&requests[2]);
+        let result = scheduler.schedule(&mut kv_cache);
+        assert!(result.prefill_requests.is_empty());
+        assert_eq!(
+            result.deferred,
+            vec![(requests[2].id.clone(), DelayReason::ModelSwap)]
+        );
+
+        let requeued = scheduler.requeue_running(&mut kv_cache);
+        assert_eq!(
+            requeued,
+            vec![requests[0].id.clone(), requests[1].id.clone()]
+        );
+        assert_eq!(scheduler.running_count(), 0);
+
+        scheduler.set_admission_paused(false);
+        let order: Vec<_> = scheduler
+            .schedule(&mut kv_cache)
+            .prefill_requests
+            .into_iter()
+            .map(|r| r.request_id)
+            .collect();
+        let expected: Vec<_> = requests.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(order, expected);
+    }
 }
@@ -0,0 +1,626 @@
+//! Stateful chat sessions.
+//!
+//! A session keeps the conversation so far (the text and audio of every
+//! turn) so clients only send what is new. The KV cache of the history is
+//! kept between turns under the session's prefix key and forked into the
+//! next turn's request, so only the new part of the prompt is prefilled.
+//! Sessions opened with tools list them to the model, which may answer with
+//! tool calls; their results come back as `tool` turns (see [`super::tools`]).
+//!
+//! When the prompt for a turn would outgrow the context window, the oldest
+//! turns are dropped or folded into a short recap (see [`ContextPolicy`]).
+//! System turns and the tool list are always kept. Shortening the history
+//! changes the prompt prefix, so the cached history is prefilled again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::clock::{self, SharedClock};
+use super::constrained::Constraint;
+use super::tools::{self, ToolCall, ToolResult, ToolSpec};
+use super::types::{Priority, RequestId};
+use crate::error::{Error, Result};
+use crate::model::ModelVariant;
+use crate::usage::estimate_prompt_tokens;
+
+/// Start of the system turn recapping dropped turns
+const RECAP_PREFIX: &str = "Earlier in this conversation:";
+
+/// Characters of each dropped turn kept in the recap
+const RECAP_TURN_CHARS: usize = 80;
+
+/// Dropped turns listed in the recap, most recent kept
+const MAX_RECAP_TURNS: usize = 16;
+
+/// Chat session identifier.
+pub type SessionId = String;
+
+/// Speaker of a chat turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    /// Result of a tool call
+    Tool,
+}
+
+impl ChatRole {
+    fn label(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+        }
+    }
+}
+
+/// One message of a conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatTurn {
+    pub role: ChatRole,
+    pub text: Option<String>,
+    /// Audio of the turn (base64 WAV), left out of API responses
+    #[serde(skip)]
+    pub audio: Option<String>,
+    /// Tools the assistant called in this turn
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Call a `tool` turn answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatTurn {
+    pub fn new(role: ChatRole, text: Option<String>, audio: Option<String>) -> Self {
+        Self {
+            role,
+            text,
+            audio,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    /// Assistant turn making tool calls.
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    /// Result of the tool call `tool_call_id`.
+    pub fn tool_result(tool_call_id: String, content: String) -> Self {
+        let mut turn = Self::new(ChatRole::Tool, Some(content), None);
+        turn.tool_call_id = Some(tool_call_id);
+        turn
+    }
+}
+
+/// How a session's history is shortened once it outgrows the context window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPolicy {
+    /// Drop the oldest turns
+    #[default]
+    DropOldest,
+    /// Replace the oldest turns with a system turn recapping them
+    Summarize,
+}
+
+/// How the history was shortened for a turn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextTruncation {
+    pub policy: ContextPolicy,
+    /// History turns removed
+    pub dropped_turns: usize,
+    /// Estimated prompt tokens before and after shortening
+    pub prompt_tokens_before: usize,
+    pub prompt_tokens_after: usize,
+}
+
+/// What the client sends for the next turn of a session.
+#[derive(Debug, Clone, Default)]
+pub struct ChatInput {
+    pub text: Option<String>,
+    /// Spoken input (base64 WAV)
+    pub audio: Option<String>,
+    /// Results of the tool calls the assistant last made
+    pub tool_results: Vec<ToolResult>,
+    /// Limit on the text of the reply
+    pub constraint: Option<Constraint>,
+    /// Text that ends the reply
+    pub stop: Vec<String>,
+    /// Longest spoken reply, in seconds
+    pub max_audio_seconds: Option<f32>,
+    /// Return the log probability of each reply token, with this many
+    /// alternatives
+    pub logprobs: Option<usize>,
+    /// Scheduling priority of the turn
+    pub priority: Priority,
+}
+
+/// A conversation kept across turns.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatSession {
+    pub id: SessionId,
+    /// Model answering the turns (the default model when unset)
+    pub model: Option<ModelVariant>,
+    pub turns: Vec<ChatTurn>,
+    /// Tools the model may call, fixed for the session's lifetime
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+    /// Prompt tokens of the history held in the KV cache for the next turn
+    pub cached_tokens: usize,
+    /// How the history was shortened for the latest turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<ContextTruncation>,
+    #[serde(skip)]
+    last_active: Instant,
+    /// Whether a turn is being generated
+    #[serde(skip)]
+    busy: bool,
+}
+
+impl ChatSession {
+    /// Prompt for the next turn: the tool list, the history and then `next`,
+    /// one `role: text` line per turn. The history always renders the same
+    /// way, so it stays a prefix of every later prompt.
+    pub fn prompt(&self, next: &[ChatTurn]) -> String {
+        self.render(&self.turns, next)
+    }
+
+    /// Prompt for `next` after `history` in place of the session's turns
+    fn render(&self, history: &[ChatTurn], next: &[ChatTurn]) -> String {
+        let mut prompt = String::new();
+        if !self.tools.is_empty() {
+            prompt.push_str(ChatRole::System.label());
+            prompt.push_str(": ");
+            prompt.push_str(&tools::render_tool_list(&self.tools));
+            prompt.push('\n');
+        }
+        for turn in history.iter().chain(next) {
+            prompt.push_str(turn.role.label());
+            prompt.push_str(": ");
+            prompt.push_str(turn.text.as_deref().unwrap_or_default());
+            if !turn.tool_calls.is_empty() {
+                prompt.push_str(&tools::render_tool_calls(&turn.tool_calls));
+            }
+            prompt.push('\n');
+        }
+        prompt.push_str(ChatRole::Assistant.label());
+        prompt.push(':');
+        prompt
+    }
+
+    /// Shorten the history so the prompt for `next` fits in `max_tokens`,
+    /// keeping system turns. Returns `None` when it already fits, and an
+    /// error when `next` does not fit even without history.
+    pub fn fit_context(
+        &mut self,
+        next: &[ChatTurn],
+        max_tokens: usize,
+        policy: ContextPolicy,
+    ) -> Result<Option<ContextTruncation>> {
+        let before = estimate_prompt_tokens(&self.prompt(next));
+        if before <= max_tokens {
+            return Ok(None);
+        }
+
+        // Tool results in `next` must follow the assistant turn that called
+        // them, so it and anything after it stay
+        let tail = if next.iter().any(|turn| turn.role == ChatRole::Tool) {
+            self.turns
+                .iter()
+                .rposition(|turn| turn.role == ChatRole::Assistant)
+                .map_or(0, |last| self.turns.len() - last)
+        } else {
+            0
+        };
+        // An earlier recap is folded into the new one
+        let (mut dropped, mut kept): (Vec<ChatTurn>, Vec<ChatTurn>) =
+            self.turns.iter().cloned().partition(is_recap);
+
+        // Recap lines kept, cut from the oldest once no turn is left to drop
+        let mut recap_turns = MAX_RECAP_TURNS;
+        let (history, after) = loop {
+            let history = with_recap(&kept, &dropped, policy, recap_turns);
+            let tokens = estimate_prompt_tokens(&self.render(&history, next));
+            if tokens <= max_tokens {
+                break (history, tokens);
+            }
+            let droppable = kept.len() - tail;
+            let Some(oldest) = kept[..droppable]
+                .iter()
+                .position(|turn| turn.role != ChatRole::System)
+            else {
+                if policy == ContextPolicy::Summarize && recap_turns > 0 {
+                    recap_turns -= 1;
+                    continue;
+                }
+                return Err(Error::InvalidInput(format!(
+                    "Chat turn needs about {} prompt tokens, more than the {} token context window",
+                    tokens, max_tokens
+                )));
+            };
+            dropped.push(kept.remove(oldest));
+            // Results of the dropped turn's tool calls go with it
+            while oldest < kept.len() - tail && kept[oldest].role == ChatRole::Tool {
+                dropped.push(kept.remove(oldest));
+            }
+        };
+
+        self.turns = history;
+        self.cached_tokens = 0;
+        Ok(Some(ContextTruncation {
+            policy,
+            dropped_turns: dropped.iter().filter(|turn| !is_recap(turn)).count(),
+            prompt_tokens_before: before,
+            prompt_tokens_after: after,
+        }))
+    }
+
+    /// Tool calls of the last assistant turn that have no result yet.
+    pub fn pending_tool_calls(&self) -> Vec<&ToolCall> {
+        let Some(last) = self
+            .turns
+            .iter()
+            .rposition(|turn| turn.role == ChatRole::Assistant)
+        else {
+            return Vec::new();
+        };
+        let answered: Vec<&str> = self.turns[last + 1..]
+            .iter()
+            .filter_map(|turn| turn.tool_call_id.as_deref())
+            .collect();
+        self.turns[last]
+            .tool_calls
+            .iter()
+            .filter(|call| !answered.contains(&call.id.as_str()))
+            .collect()
+    }
+}
+
+/// Whether `turn` is a recap of dropped turns
+fn is_recap(turn: &ChatTurn) -> bool {
+    turn.role == ChatRole::System
+        && turn
+            .text
+            .as_deref()
+            .is_some_and(|text| text.starts_with(RECAP_PREFIX))
+}
+
+/// `kept` with a recap of `dropped` after its leading system turns when
+/// summarizing
+fn with_recap(
+    kept: &[ChatTurn],
+    dropped: &[ChatTurn],
+    policy: ContextPolicy,
+    max_lines: usize,
+) -> Vec<ChatTurn> {
+    let mut history = kept.to_vec();
+    if policy == ContextPolicy::Summarize {
+        if let Some(recap) = recap(dropped, max_lines) {
+            let at = history
+                .iter()
+                .position(|turn| turn.role != ChatRole::System)
+                .unwrap_or(history.len());
+            history.insert(at, recap);
+        }
+    }
+    history
+}
+
+/// System turn listing the start of the last `max_lines` dropped turns,
+/// oldest first
+fn recap(dropped: &[ChatTurn], max_lines: usize) -> Option<ChatTurn> {
+    let mut lines: Vec<String> = Vec::new();
+    for turn in dropped {
+        let Some(text) = turn.text.as_deref() else {
+            continue;
+        };
+        if is_recap(turn) {
+            lines.extend(
+                text[RECAP_PREFIX.len()..]
+                    .split(" | ")
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(String::from),
+            );
+        } else if !text.trim().is_empty() {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let clipped: String = text.chars().take(RECAP_TURN_CHARS).collect();
+            let ellipsis = if clipped.len() < text.len() {
+                "…"
+            } else {
+                ""
+            };
+            lines.push(format!("{}: {}{}", turn.role.label(), clipped, ellipsis));
+        }
+    }
+    let start = lines.len().saturating_sub(max_lines);
+    if start == lines.len() {
+        return None;
+    }
+    let text = format!("{} {}", RECAP_PREFIX, lines[start..].join(" | "));
+    Some(ChatTurn::new(ChatRole::System, Some(text), None))
+}
+
+/// Key the session's cached history is held under in the KV cache.
+pub fn prefix_key(session_id: &str) -> RequestId {
+    format!("session:{}", session_id)
+}
+
+/// Open chat sessions, dropped after sitting idle for a TTL.
+pub struct SessionStore {
+    ttl: Duration,
+    /// Most sessions kept (0 = unlimited)
+    max_sessions: usize,
+    clock: SharedClock,
+    sessions: Mutex<HashMap<SessionId, ChatSession>>,
+}
+
+impl SessionStore {
+    /// Create a store expiring sessions idle for `ttl`.
+    pub fn new(ttl: Duration, max_sessions: usize) -> Self {
+        Self {
+            ttl,
+            max_sessions,
+            clock: clock::system_clock(),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use `clock` as the time source.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<SessionId, ChatSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Open a session. Returns it with the IDs of sessions evicted to stay
+    /// within the cap (least recently active idle ones first).
+    pub fn create(
+        &self,
+        model: Option<ModelVariant>,
+        system_prompt: Option<String>,
+        tools: Vec<ToolSpec>,
+    ) -> (ChatSession, Vec<SessionId>) {
+        let mut sessions = self.sessions();
+        let mut evicted = Vec::new();
+        if self.max_sessions > 0 {
+            while sessions.len() >= self.max_sessions {
+                let Some(oldest) = sessions
+                    .values()
+                    .filter(|s| !s.busy)
+                    .min_by_key(|s| s.last_active)
+                    .map(|s| s.id.clone())
+                else {
+                    break;
+                };
+                sessions.remove(&oldest);
+                evicted.push(oldest);
+            }
+        }
+
+        let session = ChatSession {
+            id: Uuid::new_v4().to_string(),
+            model,
+            turns: system_prompt
+                .map(|text| ChatTurn::new(ChatRole::System, Some(text), None))
+                .into_iter()
+                .collect(),
+            tools,
+            cached_tokens: 0,
+            truncation: None,
+            last_active: self.clock.now(),
+            busy: false,
+        };
+        sessions.insert(session.id.clone(), session.clone());
+        (session, evicted)
+    }
+
+    /// Current state of a session.
+    pub fn get(&self, id: &str) -> Option<ChatSession> {
+        self.sessions().get(id).cloned()
+    }
+
+    /// Claim the session for a new turn.
+    pub fn begin_turn(&self, id: &str) -> Result<ChatSession> {
+        let now = self.clock.now();
+        let mut sessions = self.sessions();
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| Error::SessionNotFound(id.to_string()))?;
+        if session.busy {
+            return Err(Error::SessionBusy(id.to_string()));
+        }
+        session.busy = true;
+        session.last_active = now;
+        Ok(session.clone())
+    }
+
+    /// Shorten the history of a session claimed for a turn so the prompt for
+    /// `next` fits in `max_tokens` (see [`ChatSession::fit_context`]).
+    /// Returns the session as the turn should see it.
+    pub fn fit_context(
+        &self,
+        id: &str,
+        next: &[ChatTurn],
+        max_tokens: usize,
+        policy: ContextPolicy,
+    ) -> Result<ChatSession> {
+        let mut sessions = self.sessions();
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| Error::SessionNotFound(id.to_string()))?;
+        session.truncation = session.fit_context(next, max_tokens, policy)?;
+        Ok(session.clone())
+    }
+
+    /// Record a finished turn's messages and the history now cached.
+    pub fn finish_turn(&self, id: &str, turns: Vec<ChatTurn>, cached_tokens: usize) {
+        let now = self.clock.now();
+        if let Some(session) = self.sessions().get_mut(id) {
+            session.turns.extend(turns);
+            session.cached_tokens = cached_tokens;
+            session.busy = false;
+            session.last_active = now;
+        }
+    }
+
+    /// Release the session after a failed turn, leaving its history as is.
+    pub fn abort_turn(&self, id: &str) {
+        if let Some(session) = self.sessions().get_mut(id) {
+            session.busy = false;
+        }
+    }
+
+    /// Close a session. Returns false if it was not open.
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions().remove(id).is_some()
+    }
+
+    /// Drop sessions idle past the TTL, returning their IDs.
+    pub fn expire(&self) -> Vec<SessionId> {
+        let now = self.clock.now();
+        let mut sessions = self.sessions();
+        let expired: Vec<SessionId> = sessions
+            .values()
+            .filter(|s| !s.busy && now.saturating_duration_since(s.last_active) >= self.ttl)
+            .map(|s| s.id.clone())
+            .collect();
+        for id in &expired {
+            sessions.remove(id);
+        }
+        expired
+    }
+
+    /// Number of open sessions.
+    pub fn len(&self) -> usize {
+        self.sessions().len()
+    }
+
+    /// Whether no session is open.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_session_lifecycle() {
+        let clock = Arc::new(clock::MockClock::new());
+        let store = SessionStore::new(Duration::from_secs(60), 2).with_clock(clock.clone());
+        let (session, evicted) = store.create(None, Some("Be brief.".into()), Vec::new());
+        assert!(evicted.is_empty());
+        let id = session.id.clone();
+
+        let first = store.begin_turn(&id).unwrap();
+        assert!(matches!(store.begin_turn(&id), Err(Error::SessionBusy(_))));
+        let hi = [ChatTurn::new(ChatRole::User, Some("Hi".into()), None)];
+        assert_eq!(first.prompt(&hi), "system: Be brief.\nuser: Hi\nassistant:");
+        store.finish_turn(
+            &id,
+            vec![
+                ChatTurn::new(ChatRole::User, Some("Hi".into()), None),
+                ChatTurn::new(ChatRole::Assistant, Some("Hello!".into()), None),
+            ],
+            5,
+        );
+
+        // The previous prompt is a prefix of the next one
+        let second = store.begin_turn(&id).unwrap();
+        assert_eq!(second.cached_tokens, 5);
+        let bye = [ChatTurn::new(ChatRole::User, Some("Bye".into()), None)];
+        assert!(second.prompt(&bye).starts_with(first.prompt(&hi).as_str()));
+        store.abort_turn(&id);
+
+        // The cap evicts the least recently active session
+        clock.advance(Duration::from_secs(10));
+        let (other, _) = store.create(None, None, Vec::new());
+        let (_, evicted) = store.create(None, None, Vec::new());
+        assert_eq!(evicted, [id]);
+
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(store.expire().len(), 2);
+        assert!(store.get(&other.id).is_none());
+    }
+
+    #[test]
+    fn test_fit_context_keeps_system_turns() {
+        let store = SessionStore::new(Duration::from_secs(60), 0);
+        let (session, _) = store.create(None, Some("Be brief.".into()), Vec::new());
+        let id = session.id.clone();
+        store.begin_turn(&id).unwrap();
+        let long = "word ".repeat(40);
+        let history: Vec<ChatTurn> = (0..6)
+            .map(|i| {
+                let role = if i % 2 == 0 {
+                    ChatRole::User
+                } else {
+                    ChatRole::Assistant
+                };
+                ChatTurn::new(role, Some(format!("{} {}", i, long)), None)
+            })
+            .collect();
+        store.finish_turn(&id, history, 300);
+
+        let next = [ChatTurn::new(ChatRole::User, Some("Bye".into()), None)];
+        let mut session = store.begin_turn(&id).unwrap();
+        assert!(session
+            .clone()
+            .fit_context(&next, 1000, ContextPolicy::DropOldest)
+            .unwrap()
+            .is_none());
+
+        // Dropping keeps the system prompt and the most recent turns
+        let mut dropped = session.clone();
+        let truncation = dropped
+            .fit_context(&next, 120, ContextPolicy::DropOldest)
+            .unwrap()
+            .unwrap();
+        assert!(truncation.prompt_tokens_after <= 120);
+        assert_eq!(dropped.turns[0].role, ChatRole::System);
+        assert_eq!(dropped.turns.len(), 7 - truncation.dropped_turns);
+        assert!(dropped
+            .turns
+            .last()
+            .unwrap()
+            .text
+            .as_deref()
+            .unwrap()
+            .starts_with("5 "));
+        assert_eq!(dropped.cached_tokens, 0);
+
+        // Summarizing replaces the dropped turns with one recap
+        let truncation = session
+            .fit_context(&next, 120, ContextPolicy::Summarize)
+            .unwrap()
+            .unwrap();
+        assert!(truncation.prompt_tokens_after <= 120);
+        assert_eq!(session.turns[0].text.as_deref(), Some("Be brief."));
+        assert!(is_recap(&session.turns[1]));
+        // Lines of the oldest turns are cut when the recap is too long
+        let recap = session.turns[1].text.as_deref().unwrap();
+        assert!(recap.contains("assistant: 5 word"));
+        assert!(!recap.contains("user: 0 word"));
+
+        // Nothing left to drop
+        assert!(matches!(
+            session.fit_context(&next, 5, ContextPolicy::DropOldest),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
@@ -29,6 +29,8 @@
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+use super::clock::{self, SharedClock};
+
 /// Configuration for the signal frontend.
 #[derive(Debug, Clone)]
 pub struct SignalFrontendConfig {
@@ -121,22 +123,33 @@ pub struct VoiceActivityDetector {
     energy_threshold: f32,
     /// Smoothed speech probability
     smoothed_prob: f32,
+    /// Time source for state durations
+    clock: SharedClock,
 }
 
 impl VoiceActivityDetector {
     /// Create a new VAD instance.
     pub fn new(config: SignalFrontendConfig) -> Self {
+        let clock = clock::system_clock();
         Self {
             config,
             state: VadState::Silence,
-            state_start: Instant::now(),
+            state_start: clock.now(),
             speech_frames: 0,
             silence_frames: 0,
             energy_threshold: 0.01,
             smoothed_prob: 0.0,
+            clock,
         }
     }
 
+    /// Use `clock` for state durations.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.state_start = clock.now();
+        self.clock = clock;
+        self
+    }
+
     /// Process an audio frame and return VAD result.
     pub fn process(&mut self, samples: &[f32], is_ai_speaking: bool) -> VadResult {
         // Calculate frame energy (RMS)
@@ -162,7 +175,7 @@ impl VoiceActivityDetector {
                     let speech_duration = self.speech_frames as u32 * frame_duration_ms;
                     if speech_duration >= self.config.min_speech_duration_ms {
                         self.state = VadState::Speech;
-                        self.state_start = Instant::now();
+                        self.state_start = self.clock.now();
                         self.silence_frames = 0;
                     }
                 } else {
@@ -175,7 +188,7 @@ impl VoiceActivityDetector {
                     let silence_duration = self.silence_frames as u32 * frame_duration_ms;
                     if silence_duration >= self.config.silence_end_duration_ms {
                         self.state = VadState::SpeechEnding;
-                        self.state_start = Instant::now();
+                        self.state_start = self.clock.now();
                     }
                 } else {
                     self.silence_frames = 0;
@@ -184,11 +197,11 @@ impl VoiceActivityDetector {
             VadState::SpeechEnding => {
                 if is_speech {
                     self.state = VadState::Speech;
-                    self.state_start = Instant::now();
+                    self.state_start = self.clock.now();
                     self.silence_frames = 0;
                 } else {
                     self.state = VadState::Silence;
-                    self.state_start = Instant::now();
+                    self.state_start = self.clock.now();
                     self.speech_frames = 0;
                 }
             }
@@ -197,7 +210,7 @@ impl VoiceActivityDetector {
         VadResult {
             state: self.state,
             speech_probability: self.smoothed_prob,
-            state_duration: self.state_start.elapsed(),
+            state_duration: self.clock.elapsed_since(self.state_start),
             is_interruption: is_speech && is_ai_speaking,
         }
     }
@@ -214,7 +227,7 @@ impl VoiceActivityDetector {
     /// Reset VAD state.
     pub fn reset(&mut self) {
         self.state = VadState::Silence;
-        self.state_start = Instant::now();
+        self.state_start = self.clock.now();
         self.speech_frames = 0;
         self.silence_frames = 0;
         self.smoothed_prob = 0.0;
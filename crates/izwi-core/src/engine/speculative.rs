@@ -0,0 +1,397 @@
+//! Speculative decoding with a draft model.
+//!
+//! A small draft model proposes `k` tokens one at a time; the target model
+//! then scores all of them in a single batched pass. Proposals are accepted
+//! by rejection sampling (Leviathan et al., 2023), so the output follows the
+//! target model's distribution exactly while each step yields between 1 and
+//! `k + 1` tokens.
+//!
+//! The scheduler reserves KV slots for the draft tokens of each decode step
+//! (`ScheduledRequest::num_lookahead_tokens`). Executors that decode token
+//! by token drive a [`SpeculativeDecoder`] for those steps; executors that
+//! synthesize whole utterances per call leave the slots unused.
+//!
+//! A request's [`SamplerPipeline`] (logits processors and output constraint)
+//! is applied to both models' distributions before proposals are drawn and
+//! verified.
+
+use serde::Serialize;
+
+use super::constrained::TokenConstraint;
+use super::sampler::SamplerPipeline;
+use super::types::TokenId;
+use crate::error::{Error, Result};
+
+/// Token-level model used by the speculative decoder.
+pub trait TokenModel {
+    /// Next-token probabilities after `context`.
+    fn next_token_probs(&mut self, context: &[TokenId]) -> Result<Vec<f32>>;
+
+    /// Probabilities after `context` and after each prefix of `proposed`
+    /// (`proposed.len() + 1` distributions).
+    ///
+    /// Target models should override this with a single batched pass; the
+    /// default scores one position at a time.
+    fn score_proposal(
+        &mut self,
+        context: &[TokenId],
+        proposed: &[TokenId],
+    ) -> Result<Vec<Vec<f32>>> {
+        let mut sequence = context.to_vec();
+        let mut probs = Vec::with_capacity(proposed.len() + 1);
+        probs.push(self.next_token_probs(&sequence)?);
+        for &token in proposed {
+            sequence.push(token);
+            probs.push(self.next_token_probs(&sequence)?);
+        }
+        Ok(probs)
+    }
+}
+
+/// Tokens produced by one speculative step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeculativeStep {
+    /// Accepted draft tokens followed by one token from the target model
+    pub tokens: Vec<TokenId>,
+    /// Draft tokens proposed
+    pub num_proposed: usize,
+    /// Draft tokens accepted
+    pub num_accepted: usize,
+}
+
+/// Running acceptance counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct SpeculativeStats {
+    pub steps: u64,
+    pub proposed: u64,
+    pub accepted: u64,
+    pub generated: u64,
+}
+
+impl SpeculativeStats {
+    /// Add the outcome of one step
+    pub fn record(&mut self, step: &SpeculativeStep) {
+        self.steps += 1;
+        self.proposed += step.num_proposed as u64;
+        self.accepted += step.num_accepted as u64;
+        self.generated += step.tokens.len() as u64;
+    }
+
+    /// Fraction of draft tokens accepted (0.0 - 1.0)
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.proposed == 0 {
+            return 0.0;
+        }
+        self.accepted as f64 / self.proposed as f64
+    }
+
+    /// Average tokens produced per target model pass
+    pub fn tokens_per_step(&self) -> f64 {
+        if self.steps == 0 {
+            return 0.0;
+        }
+        self.generated as f64 / self.steps as f64
+    }
+}
+
+/// Draft-and-verify decoder over a draft and a target model.
+pub struct SpeculativeDecoder<D, T> {
+    draft: D,
+    target: T,
+    /// Pick the most likely token instead of sampling
+    greedy: bool,
+    /// Logits processors and constraint, at the tokens generated so far
+    sampler: SamplerPipeline,
+    stats: SpeculativeStats,
+}
+
+impl<D: TokenModel, T: TokenModel> SpeculativeDecoder<D, T> {
+    /// Create a sampling decoder.
+    pub fn new(draft: D, target: T) -> Self {
+        Self {
+            draft,
+            target,
+            greedy: false,
+            sampler: SamplerPipeline::new(),
+            stats: SpeculativeStats::default(),
+        }
+    }
+
+    /// Shape both models' distributions with `sampler`.
+    pub fn with_sampler(mut self, sampler: SamplerPipeline) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Only generate text that `constraint` allows.
+    pub fn with_constraint(mut self, constraint: TokenConstraint) -> Self {
+        self.sampler = self.sampler.with_constraint(constraint);
+        self
+    }
+
+    /// Constraint state after the tokens generated so far.
+    pub fn constraint(&self) -> Option<&TokenConstraint> {
+        self.sampler.constraint()
+    }
+
+    /// Decode greedily (temperature 0): draft tokens are accepted while they
+    /// match the target model's most likely token.
+    pub fn with_greedy(mut self, greedy: bool) -> Self {
+        self.greedy = greedy;
+        self
+    }
+
+    /// Counters over all steps so far.
+    pub fn stats(&self) -> SpeculativeStats {
+        self.stats
+    }
+
+    /// Propose `k` draft tokens after `context` and verify them.
+    ///
+    /// `uniform` supplies random numbers in `[0, 1)`.
+    pub fn step(
+        &mut self,
+        context: &[TokenId],
+        k: usize,
+        uniform: &mut impl FnMut() -> f32,
+    ) -> Result<SpeculativeStep> {
+        // Draft k tokens autoregressively
+        let mut sequence = context.to_vec();
+        let mut proposed = Vec::with_capacity(k);
+        let mut draft_probs = Vec::with_capacity(k);
+        // Sampler state before each proposal, and after the last one
+        let mut sampler = self.sampler.clone();
+        let mut samplers = Vec::with_capacity(k + 1);
+        for _ in 0..k {
+            let mut probs = self.draft.next_token_probs(&sequence)?;
+            sampler.process_probs(&sequence, &mut probs)?;
+            let token = self.choose(&probs, uniform);
+            samplers.push(sampler.clone());
+            sampler.accept(token)?;
+            sequence.push(token);
+            proposed.push(token);
+            draft_probs.push(probs);
+        }
+
+        samplers.push(sampler);
+
+        // Score every proposal with one target pass
+        let mut target_probs = self.target.score_proposal(context, &proposed)?;
+        if target_probs.len() != k + 1 {
+            return Err(Error::InferenceError(format!(
+                "Target model returned {} distributions for {} draft tokens",
+                target_probs.len(),
+                k
+            )));
+        }
+        for (i, (probs, sampler)) in target_probs.iter_mut().zip(&samplers).enumerate() {
+            sampler.process_probs(&sequence[..context.len() + i], probs)?;
+        }
+
+        let mut tokens = Vec::with_capacity(k + 1);
+        for (i, &token) in proposed.iter().enumerate() {
+            let (p, q) = (&target_probs[i], &draft_probs[i]);
+            if p.len() != q.len() {
+                return Err(Error::InferenceError(format!(
+                    "Draft and target vocabularies differ ({} vs {})",
+                    q.len(),
+                    p.len()
+                )));
+            }
+            if self.accepts(token, p, q, uniform) {
+                tokens.push(token);
+                continue;
+            }
+
+            // First rejection: resample from where the target exceeds the draft
+            let replacement = if self.greedy {
+                argmax(p)
+            } else {
+                let residual: Vec<f32> = p.iter().zip(q).map(|(p, q)| (p - q).max(0.0)).collect();
+                sample(&residual, uniform()).unwrap_or_else(|| argmax(p))
+            };
+            tokens.push(replacement);
+            return self.finish(tokens, k, i);
+        }
+
+        // Every draft token accepted: take a bonus token from the target
+        let bonus = self.choose(&target_probs[k], uniform);
+        tokens.push(bonus);
+        self.finish(tokens, k, k)
+    }
+
+    fn accepts(
+        &self,
+        token: TokenId,
+        p: &[f32],
+        q: &[f32],
+        uniform: &mut impl FnMut() -> f32,
+    ) -> bool {
+        if self.greedy {
+            return argmax(p) == token;
+        }
+        let (px, qx) = (p[token as usize], q[token as usize]);
+        qx > 0.0 && uniform() < (px / qx).min(1.0)
+    }
+
+    fn choose(&self, probs: &[f32], uniform: &mut impl FnMut() -> f32) -> TokenId {
+        if self.greedy {
+            argmax(probs)
+        } else {
+            sample(probs, uniform()).unwrap_or_else(|| argmax(probs))
+        }
+    }
+
+    fn finish(
+        &mut self,
+        tokens: Vec<TokenId>,
+        proposed: usize,
+        accepted: usize,
+    ) -> Result<SpeculativeStep> {
+        for &token in &tokens {
+            self.sampler.accept(token)?;
+        }
+        let step = SpeculativeStep {
+            tokens,
+            num_proposed: proposed,
+            num_accepted: accepted,
+        };
+        self.stats.record(&step);
+        Ok(step)
+    }
+}
+
+fn argmax(probs: &[f32]) -> TokenId {
+    probs
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i as TokenId)
+}
+
+/// Sample from unnormalized weights with `u` in `[0, 1)`; `None` if all are zero.
+fn sample(weights: &[f32], u: f32) -> Option<TokenId> {
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut threshold = u * total;
+    for (i, &w) in weights.iter().enumerate() {
+        if threshold < w {
+            return Some(i as TokenId);
+        }
+        threshold -= w;
+    }
+    weights.iter().rposition(|&w| w > 0.0).map(|i| i as TokenId)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOCAB: usize = 8;
+
+    /// Predicts `last + 1`, except after `diverge_at` where it predicts 7.
+    struct Counter {
+        diverge_at: Option<TokenId>,
+    }
+
+    impl TokenModel for Counter {
+        fn next_token_probs(&mut self, context: &[TokenId]) -> Result<Vec<f32>> {
+            let last = *context.last().unwrap();
+            let next = if Some(last) == self.diverge_at {
+                7
+            } else {
+                (last + 1) % VOCAB as TokenId
+            };
+            let mut probs = vec![0.0; VOCAB];
+            probs[next as usize] = 1.0;
+            Ok(probs)
+        }
+    }
+
+    #[test]
+    fn test_greedy_accepts_until_divergence() {
+        let draft = Counter { diverge_at: None };
+        let target = Counter {
+            diverge_at: Some(3),
+        };
+        let mut decoder = SpeculativeDecoder::new(draft, target).with_greedy(true);
+
+        let step = decoder.step(&[0], 4, &mut || 0.5).unwrap();
+        assert_eq!(step.tokens, vec![1, 2, 3, 7]);
+        assert_eq!(step.num_accepted, 3);
+
+        let step = decoder.step(&[3], 2, &mut || 0.5).unwrap();
+        assert_eq!(step.tokens, vec![7]);
+        assert_eq!(step.num_accepted, 0);
+
+        // Fully accepted proposals earn a bonus token
+        let step = decoder.step(&[4], 3, &mut || 0.5).unwrap();
+        assert_eq!(step.tokens, vec![5, 6, 7, 0]);
+
+        let stats = decoder.stats();
+        assert_eq!(stats.proposed, 9);
+        assert_eq!(stats.accepted, 6);
+        assert_eq!(stats.generated, 9);
+    }
+
+    #[test]
+    fn test_sampling_rejects_impossible_tokens() {
+        let draft = Counter { diverge_at: None };
+        let target = Counter {
+            diverge_at: Some(0),
+        };
+        let mut decoder = SpeculativeDecoder::new(draft, target);
+
+        // The target never emits 1 after 0, so the draft is rejected at once
+        let step = decoder.step(&[0], 3, &mut || 0.25).unwrap();
+        assert_eq!(step.tokens, vec![7]);
+        assert_eq!(step.num_accepted, 0);
+    }
+
+    struct Uniform;
+
+    impl TokenModel for Uniform {
+        fn next_token_probs(&mut self, _context: &[TokenId]) -> Result<Vec<f32>> {
+            Ok(vec![1.0 / VOCAB as f32; VOCAB])
+        }
+    }
+
+    #[test]
+    fn test_constraint_masks_both_models() {
+        use super::super::constrained::Constraint;
+        use std::sync::Arc;
+
+        // Token 0 ends the output; the others are the digits 1-7
+        let vocab: Vec<Vec<u8>> = (0..VOCAB)
+            .map(|t| {
+                if t == 0 {
+                    Vec::new()
+                } else {
+                    t.to_string().into_bytes()
+                }
+            })
+            .collect();
+        let compiled = Constraint::Regex {
+            pattern: "[246]{3}".into(),
+        }
+        .compile()
+        .unwrap();
+        let constraint =
+            TokenConstraint::new(Arc::new(compiled), Arc::new(vocab)).with_eos_token(0);
+        let mut decoder = SpeculativeDecoder::new(Uniform, Uniform)
+            .with_greedy(true)
+            .with_constraint(constraint);
+
+        let step = decoder.step(&[0], 1, &mut || 0.5).unwrap();
+        assert_eq!(step.tokens.len(), 2);
+        assert!(step.tokens.iter().all(|t| [2, 4, 6].contains(t)));
+        assert!(!decoder.constraint().unwrap().is_complete());
+
+        // Once complete, only the end token is allowed
+        let step = decoder.step(&[0], 1, &mut || 0.5).unwrap();
+        assert_eq!(step.tokens[1], 0);
+        assert!(decoder.constraint().unwrap().is_complete());
+    }
+}
@@ -0,0 +1,281 @@
+//! Tool (function) calling in chat sessions.
+//!
+//! Tools are listed to the model in LFM2's format, and the model answers
+//! with calls on its text channel between `<|tool_call_start|>` and
+//! `<|tool_call_end|>` as a JSON array of `{"name", "arguments"}` objects.
+//! Calls are checked against the tool's JSON schema before they reach the
+//! client; results come back as `tool` turns.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+pub const TOOL_LIST_START: &str = "<|tool_list_start|>";
+pub const TOOL_LIST_END: &str = "<|tool_list_end|>";
+pub const TOOL_CALL_START: &str = "<|tool_call_start|>";
+pub const TOOL_CALL_END: &str = "<|tool_call_end|>";
+
+/// A function the model may call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON schema of the arguments object
+    #[serde(default = "empty_object_schema")]
+    pub parameters: Value,
+}
+
+fn empty_object_schema() -> Value {
+    serde_json::json!({"type": "object", "properties": {}})
+}
+
+/// A call the model made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// What a tool call returned, sent with the next turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    pub content: Value,
+}
+
+/// Check a session's tool list: unique non-empty names and object schemas.
+pub fn validate_tools(tools: &[ToolSpec]) -> Result<()> {
+    for (index, tool) in tools.iter().enumerate() {
+        if tool.name.trim().is_empty() {
+            return Err(Error::InvalidInput(format!("Tool {} has no name", index)));
+        }
+        if tools[..index].iter().any(|t| t.name == tool.name) {
+            return Err(Error::InvalidInput(format!(
+                "Tool {} is listed twice",
+                tool.name
+            )));
+        }
+        if tool.parameters.get("type").and_then(Value::as_str) != Some("object") {
+            return Err(Error::InvalidInput(format!(
+                "Parameters of tool {} must be an object schema",
+                tool.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The tool list as shown to the model.
+pub fn render_tool_list(tools: &[ToolSpec]) -> String {
+    let list: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            serde_json::json!({
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            })
+        })
+        .collect();
+    format!(
+        "List of tools: {}{}{}",
+        TOOL_LIST_START,
+        Value::Array(list),
+        TOOL_LIST_END
+    )
+}
+
+/// Calls as the model writes them, to replay them in the history.
+pub fn render_tool_calls(calls: &[ToolCall]) -> String {
+    let calls: Vec<Value> = calls
+        .iter()
+        .map(|call| serde_json::json!({"name": call.name, "arguments": call.arguments}))
+        .collect();
+    format!(
+        "{}{}{}",
+        TOOL_CALL_START,
+        Value::Array(calls),
+        TOOL_CALL_END
+    )
+}
+
+/// Split model text into what is spoken and the tool calls it makes. Calls
+/// to unknown tools or with arguments outside the tool's schema are errors.
+pub fn parse_tool_calls(text: &str, tools: &[ToolSpec]) -> Result<(String, Vec<ToolCall>)> {
+    let mut spoken = String::new();
+    let mut calls = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(TOOL_CALL_START) {
+        spoken.push_str(&rest[..start]);
+        let body = &rest[start + TOOL_CALL_START.len()..];
+        let end = body.find(TOOL_CALL_END).ok_or_else(|| {
+            Error::InferenceError("Model output has an unterminated tool call".into())
+        })?;
+        let parsed: Value = serde_json::from_str(body[..end].trim()).map_err(|e| {
+            Error::InferenceError(format!("Model emitted a malformed tool call: {}", e))
+        })?;
+        let entries = match parsed {
+            Value::Array(entries) => entries,
+            single => vec![single],
+        };
+        for entry in entries {
+            calls.push(check_call(entry, tools)?);
+        }
+        rest = &body[end + TOOL_CALL_END.len()..];
+    }
+    spoken.push_str(rest);
+    Ok((spoken.trim().to_string(), calls))
+}
+
+fn check_call(entry: Value, tools: &[ToolSpec]) -> Result<ToolCall> {
+    let name = entry
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::InferenceError("Model emitted a tool call without a name".into()))?;
+    let tool = tools
+        .iter()
+        .find(|tool| tool.name == name)
+        .ok_or_else(|| Error::InferenceError(format!("Model called unknown tool {}", name)))?;
+    let arguments = entry
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| Value::Object(Default::default()));
+    validate_schema(&arguments, &tool.parameters, "arguments")
+        .map_err(|e| Error::InferenceError(format!("Model called {} with invalid {}", name, e)))?;
+    Ok(ToolCall {
+        id: format!("call_{}", &Uuid::new_v4().simple().to_string()[..12]),
+        name: name.to_string(),
+        arguments,
+    })
+}
+
+/// Check `value` against the common subset of JSON schema: `type`, `enum`,
+/// `properties`, `required`, `additionalProperties: false` and `items`.
+pub fn validate_schema(
+    value: &Value,
+    schema: &Value,
+    path: &str,
+) -> std::result::Result<(), String> {
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: {} is not one of {:?}", path, value, allowed));
+        }
+    }
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{}: expected {}, got {}", path, expected, value));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                return Err(format!("{}: missing {}", path, name));
+            }
+        }
+        for (name, field) in object {
+            let field_path = format!("{}.{}", path, name);
+            match properties.and_then(|p| p.get(name)) {
+                Some(field_schema) => validate_schema(field, field_schema, &field_path)?,
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err(format!("{}: not allowed", field_path));
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate_schema(item, item_schema, &format!("{}[{}]", path, index))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn weather_tool() -> ToolSpec {
+        ToolSpec {
+            name: "get_weather".into(),
+            description: "Current weather in a city".into(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "city": {"type": "string"},
+                    "unit": {"type": "string", "enum": ["celsius", "fahrenheit"]}
+                },
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let tools = [weather_tool()];
+        let text = format!(
+            "Let me check.{}[{{\"name\": \"get_weather\", \"arguments\": {{\"city\": \"Paris\"}}}}]{}",
+            TOOL_CALL_START, TOOL_CALL_END
+        );
+        let (spoken, calls) = parse_tool_calls(&text, &tools).unwrap();
+        assert_eq!(spoken, "Let me check.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].arguments, json!({"city": "Paris"}));
+        assert!(render_tool_calls(&calls).starts_with(TOOL_CALL_START));
+
+        let (spoken, calls) = parse_tool_calls("No tools needed.", &tools).unwrap();
+        assert_eq!((spoken.as_str(), calls.len()), ("No tools needed.", 0));
+    }
+
+    #[test]
+    fn test_tool_calls_must_match_schema() {
+        let tools = [weather_tool()];
+        let call = |arguments: Value| {
+            let text = format!(
+                "{}{}{}",
+                TOOL_CALL_START,
+                json!({"name": "get_weather", "arguments": arguments}),
+                TOOL_CALL_END
+            );
+            parse_tool_calls(&text, &tools)
+        };
+        assert!(call(json!({"city": "Oslo", "unit": "celsius"})).is_ok());
+        for bad in [
+            json!({}),
+            json!({"city": 7}),
+            json!({"city": "Oslo", "unit": "kelvin"}),
+            json!({"city": "Oslo", "when": "now"}),
+        ] {
+            assert!(call(bad.clone()).is_err(), "{} accepted", bad);
+        }
+        let unknown = format!(
+            "{}{{\"name\": \"launch\"}}{}",
+            TOOL_CALL_START, TOOL_CALL_END
+        );
+        assert!(parse_tool_calls(&unknown, &tools).is_err());
+    }
+}
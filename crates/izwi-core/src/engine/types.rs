@@ -3,6 +3,13 @@
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
+use super::candidates::Candidate;
+use super::constrained::Constraint;
+use super::kv_cache::KVCacheStats;
+use super::latency::LatencyReport;
+use crate::audio::TOKEN_RATE_HZ;
+use crate::model::ModelVariant;
+
 /// Unique identifier for a request.
 pub type RequestId = String;
 
@@ -59,12 +66,53 @@ pub struct GenerationParams {
     pub speed: f32,
 
     /// Stop sequences (generation stops when any of these are produced)
-    #[serde(default)]
+    #[serde(default, alias = "stop")]
     pub stop_sequences: Vec<String>,
 
     /// Stop token IDs
     #[serde(default)]
     pub stop_token_ids: Vec<TokenId>,
+
+    /// Stop after this much silence following speech, in ms (0 = off)
+    #[serde(default)]
+    pub stop_on_silence_ms: u32,
+
+    /// Longest audio to generate, in seconds; caps `max_tokens` at the
+    /// codec's token rate
+    #[serde(default)]
+    pub max_audio_seconds: Option<f32>,
+
+    /// Number of takes to generate, keeping the best (best-of-N)
+    #[serde(default = "default_n_candidates")]
+    pub n_candidates: usize,
+
+    /// How takes are ranked when `n_candidates > 1`
+    #[serde(default)]
+    pub candidate_scoring: CandidateScoring,
+
+    /// Return every take, best first, alongside the chosen one
+    #[serde(default)]
+    pub return_candidates: bool,
+
+    /// Limit on the text output (ASR and chat)
+    #[serde(default)]
+    pub constraint: Option<Constraint>,
+
+    /// Return the log probability of each text token (ASR and chat), with
+    /// this many most likely alternatives per token
+    #[serde(default)]
+    pub logprobs: Option<usize>,
+}
+
+/// How best-of-N takes are ranked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateScoring {
+    /// Mean token log-probability reported by the executor
+    #[default]
+    LogProb,
+    /// Word error rate of an ASR transcript against the input text
+    Wer,
 }
 
 fn default_temperature() -> f32 {
@@ -82,6 +130,9 @@ fn default_max_tokens() -> usize {
 fn default_speed() -> f32 {
     1.0
 }
+fn default_n_candidates() -> usize {
+    1
+}
 
 impl Default for GenerationParams {
     fn default() -> Self {
@@ -98,10 +149,25 @@ impl Default for GenerationParams {
             speed: default_speed(),
             stop_sequences: Vec::new(),
             stop_token_ids: Vec::new(),
+            stop_on_silence_ms: 0,
+            max_audio_seconds: None,
+            n_candidates: default_n_candidates(),
+            candidate_scoring: CandidateScoring::default(),
+            return_candidates: false,
+            constraint: None,
+            logprobs: None,
         }
     }
 }
 
+impl GenerationParams {
+    /// Audio tokens that fit in `max_audio_seconds` at the codec's token rate
+    pub fn audio_token_budget(&self) -> Option<usize> {
+        self.max_audio_seconds
+            .map(|secs| ((secs * TOKEN_RATE_HZ) as usize).max(1))
+    }
+}
+
 /// Audio output from generation.
 #[derive(Debug, Clone)]
 pub struct AudioOutput {
@@ -160,9 +226,33 @@ pub struct EngineOutput {
     pub finish_reason: Option<FinishReason>,
     /// Token statistics
     pub token_stats: TokenStats,
+    /// Where the request spent its time (filled in once finished)
+    pub timings: OutputTimings,
+    /// All best-of-N takes, best first (when requested)
+    pub candidates: Vec<Candidate>,
+    /// Log probabilities of the text tokens (when requested)
+    pub logprobs: Vec<TokenLogprob>,
 }
 
 impl EngineOutput {
+    /// Finished output answering a request from the output cache
+    pub fn cached(request_id: RequestId, audio: AudioOutput) -> Self {
+        Self {
+            request_id,
+            sequence_id: 0,
+            audio,
+            text: None,
+            num_tokens: 0,
+            generation_time: Duration::ZERO,
+            is_finished: true,
+            finish_reason: Some(FinishReason::Cached),
+            token_stats: TokenStats::default(),
+            timings: OutputTimings::default(),
+            candidates: Vec::new(),
+            logprobs: Vec::new(),
+        }
+    }
+
     /// Calculate real-time factor (RTF)
     /// RTF < 1.0 means faster than real-time
     pub fn rtf(&self) -> f32 {
@@ -174,6 +264,181 @@ impl EngineOutput {
     }
 }
 
+/// Log probability of a generated text token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: TokenId,
+    /// Decoded token, when the vocabulary is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Natural log of the token's probability
+    pub logprob: f32,
+    /// Most likely tokens at this position, most likely first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// An alternative considered for a text token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: TokenId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub logprob: f32,
+}
+
+/// Where a finished request spent its time, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputTimings {
+    /// Waiting to be scheduled
+    pub queue_ms: f64,
+    pub prefill_ms: f64,
+    pub decode_ms: f64,
+    /// Arrival to finish
+    pub total_ms: f64,
+}
+
+impl From<&LatencyReport> for OutputTimings {
+    fn from(report: &LatencyReport) -> Self {
+        Self {
+            queue_ms: report.queue_ms,
+            prefill_ms: report.prefill_ms,
+            decode_ms: report.decode_ms,
+            total_ms: report.total_ms,
+        }
+    }
+}
+
+/// Why an output ended, as reported to API clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopReason {
+    /// The model, a stop sequence or silence ended it (or it was cached)
+    Stop,
+    /// A token or audio length limit was reached
+    Length,
+    Abort,
+    Error,
+}
+
+impl From<FinishReason> for StopReason {
+    fn from(reason: FinishReason) -> Self {
+        match reason {
+            FinishReason::MaxTokens => Self::Length,
+            FinishReason::StopToken
+            | FinishReason::StopSequence
+            | FinishReason::Silence
+            | FinishReason::Cached => Self::Stop,
+            FinishReason::Aborted => Self::Abort,
+            FinishReason::Error => Self::Error,
+        }
+    }
+}
+
+/// Encoded audio of an output.
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioPayload {
+    /// Encoded audio (base64)
+    #[serde(rename = "audio")]
+    pub data: String,
+    pub format: String,
+    pub sample_rate: u32,
+    pub duration_secs: f32,
+}
+
+/// Response body of a finished generation.
+///
+/// Every endpoint that generates audio or text returns these fields
+/// (flattened next to its own), so clients read outputs the same way
+/// whichever route produced them.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputResponse {
+    pub request_id: RequestId,
+    /// Absent for text-only outputs
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub prompt_tokens: usize,
+    pub generated_tokens: usize,
+    pub finish_reason: StopReason,
+    pub timings: OutputTimings,
+    /// Log probabilities of the text tokens (when requested)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logprobs: Vec<TokenLogprob>,
+}
+
+impl OutputResponse {
+    /// Response for a request that finished normally, with nothing attached
+    pub fn new(request_id: impl Into<RequestId>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            audio: None,
+            text: None,
+            prompt_tokens: 0,
+            generated_tokens: 0,
+            finish_reason: StopReason::Stop,
+            timings: OutputTimings::default(),
+            logprobs: Vec::new(),
+        }
+    }
+
+    /// Response for a finished engine output, without its audio payload
+    pub fn from_output(output: &EngineOutput) -> Self {
+        Self {
+            text: output.text.clone(),
+            prompt_tokens: output.token_stats.prompt_tokens,
+            generated_tokens: output.num_tokens,
+            finish_reason: output
+                .finish_reason
+                .map(StopReason::from)
+                .unwrap_or(StopReason::Stop),
+            timings: output.timings,
+            logprobs: output.logprobs.clone(),
+            ..Self::new(output.request_id.clone())
+        }
+    }
+
+    /// Attach audio encoded as `format` at `sample_rate`
+    pub fn with_audio(
+        mut self,
+        bytes: &[u8],
+        format: impl Into<String>,
+        sample_rate: u32,
+        duration_secs: f32,
+    ) -> Self {
+        use base64::Engine;
+        self.audio = Some(AudioPayload {
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            format: format.into(),
+            sample_rate,
+            duration_secs,
+        });
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn with_tokens(mut self, prompt_tokens: usize, generated_tokens: usize) -> Self {
+        self.prompt_tokens = prompt_tokens;
+        self.generated_tokens = generated_tokens;
+        self
+    }
+
+    pub fn with_timings(mut self, timings: OutputTimings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    pub fn with_logprobs(mut self, logprobs: Vec<TokenLogprob>) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+}
+
 /// Reason for finishing generation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FinishReason {
@@ -183,10 +448,14 @@ pub enum FinishReason {
     StopToken,
     /// Generated stop sequence
     StopSequence,
+    /// Audio fell silent for `stop_on_silence_ms`
+    Silence,
     /// Request was aborted
     Aborted,
     /// Error during generation
     Error,
+    /// Served from the output cache
+    Cached,
 }
 
 /// Token generation statistics.
@@ -240,21 +509,26 @@ pub struct EngineMetrics {
     pub kv_cache_blocks_allocated: usize,
     /// Number of KV cache blocks free
     pub kv_cache_blocks_free: usize,
+    /// Decoded audio currently held in output buffers (bytes)
+    pub output_buffer_bytes: usize,
+    /// Peak decoded audio held in output buffers (bytes)
+    pub output_buffer_peak_bytes: usize,
     /// Timestamp of last update
     #[serde(skip)]
     pub last_updated: Option<Instant>,
 }
 
 impl EngineMetrics {
-    pub fn new() -> Self {
+    /// Empty metrics, last updated at `now`
+    pub fn new(now: Instant) -> Self {
         Self {
-            last_updated: Some(Instant::now()),
+            last_updated: Some(now),
             ..Default::default()
         }
     }
 
-    /// Update metrics with a completed request
-    pub fn record_completion(&mut self, output: &EngineOutput) {
+    /// Update metrics with a request completed at `now`
+    pub fn record_completion(&mut self, output: &EngineOutput, now: Instant) {
         self.tokens_generated += output.num_tokens as u64;
         self.audio_seconds_generated += output.audio.duration_secs as f64;
 
@@ -269,12 +543,87 @@ impl EngineMetrics {
             self.avg_rtf = output.rtf();
         }
 
-        self.last_updated = Some(Instant::now());
+        self.last_updated = Some(now);
     }
 }
 
+/// Outcome of swapping the served model.
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapReport {
+    /// Model now being served
+    pub model: ModelVariant,
+    /// Model served before the swap
+    pub previous: ModelVariant,
+    /// Time spent loading the new weights (ms)
+    pub load_ms: f64,
+    /// Time spent waiting for running requests (ms)
+    pub drain_ms: f64,
+    /// Requests that finished on the previous model while draining
+    pub drained: usize,
+    /// Requests restarted on the new model
+    pub migrated: Vec<RequestId>,
+}
+
+/// Outcome of warming up the resident models.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WarmupReport {
+    /// One pass per model and batch size
+    pub passes: Vec<WarmupPass>,
+    /// Wall time of the whole warmup (ms)
+    pub total_ms: f64,
+}
+
+/// One warmup pass of concurrent dummy requests.
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupPass {
+    pub model: ModelVariant,
+    pub batch_size: usize,
+    /// Time until every request of the pass finished (ms)
+    pub elapsed_ms: f64,
+}
+
+/// Queue and KV cache state of the engine, for operator dashboards.
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineStats {
+    /// Model serving requests that don't name one
+    pub default_model: ModelVariant,
+    /// One entry per resident model
+    pub models: Vec<ModelStats>,
+    /// Bytes of audio buffered for streams and results
+    pub output_buffer_bytes: usize,
+}
+
+/// Queue and KV cache state of one resident model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStats {
+    pub model: ModelVariant,
+    pub waiting: usize,
+    pub running: usize,
+    /// Prompt tokens of the waiting requests
+    pub queued_tokens: usize,
+    pub kv_cache: KVCacheStats,
+    /// Share of KV blocks allocated (0.0 - 1.0)
+    pub kv_utilization: f64,
+    /// Progress of the running requests
+    pub requests: Vec<RequestProgress>,
+}
+
+/// Progress of a running request.
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestProgress {
+    pub request_id: RequestId,
+    pub client_id: Option<String>,
+    pub priority: Priority,
+    pub tokens_processed: usize,
+    pub tokens_generated: usize,
+    pub max_tokens: usize,
+    /// Time since the request was added (ms)
+    pub elapsed_ms: f64,
+}
+
 /// Priority level for requests.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Priority {
     /// Low priority (background tasks)
     Low = 0,
@@ -292,6 +641,18 @@ impl Default for Priority {
     }
 }
 
+impl Priority {
+    /// Name used in requests and configs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+}
+
 /// Model type being used for inference.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ModelType {
@@ -312,6 +673,8 @@ pub enum TaskType {
     TTS,
     /// Automatic speech recognition
     ASR,
+    /// Spoken conversation turn
+    Chat,
 }
 
 impl Default for TaskType {
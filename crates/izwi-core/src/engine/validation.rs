@@ -0,0 +1,280 @@
+//! Input checks run before requests reach the scheduler or the daemons.
+//!
+//! Text is normalized (NFC, `\n` line endings, no control or zero-width
+//! characters) and bounded in length. Base64 audio is checked for a sane
+//! encoding and a bounded size, and WAV uploads are checked for duration
+//! and sample rate from their header alone, without decoding the samples.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::InputLimits;
+use crate::error::{Error, ErrorCode, Result};
+
+/// Base64 characters decoded to read a WAV header
+const HEADER_BASE64_CHARS: usize = 4096;
+
+fn rejected(code: ErrorCode, message: String) -> Error {
+    Error::InputRejected { code, message }
+}
+
+/// Normalize `text` and check it against `limits`. `field` names the
+/// request field in error messages.
+pub fn check_text(text: &str, field: &str, limits: &InputLimits) -> Result<String> {
+    if text.contains('\0') {
+        return Err(rejected(
+            ErrorCode::InvalidText,
+            format!("{} contains a NUL character", field),
+        ));
+    }
+    let text = normalize_text(text);
+    let chars = text.chars().count();
+    if chars > limits.max_text_chars {
+        return Err(rejected(
+            ErrorCode::TextTooLong,
+            format!(
+                "{} is {} characters long; the limit is {}",
+                field, chars, limits.max_text_chars
+            ),
+        ));
+    }
+    Ok(text)
+}
+
+/// NFC form with `\n` line endings, without control characters other than
+/// newlines and tabs, and without zero-width characters and byte order marks
+pub fn normalize_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .nfc()
+        .map(|c| if c == '\r' { '\n' } else { c })
+        .filter(|&c| {
+            !matches!(
+                c,
+                '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+            ) && (!c.is_control() || c == '\n' || c == '\t')
+        })
+        .collect()
+}
+
+/// What could be learned about an upload without decoding it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSummary {
+    /// Decoded size in bytes
+    pub bytes: usize,
+    /// Sample rate, from a WAV header
+    pub sample_rate: Option<u32>,
+    /// Duration in seconds, from a WAV header
+    pub duration_secs: Option<f64>,
+}
+
+/// Check base64 audio against `limits`. `field` names the request field in
+/// error messages.
+pub fn check_audio(audio_base64: &str, field: &str, limits: &InputLimits) -> Result<AudioSummary> {
+    if audio_base64.starts_with("data:") {
+        return Err(rejected(
+            ErrorCode::InvalidBase64,
+            format!("{} must be plain base64, not a data URL", field),
+        ));
+    }
+    let bytes = decoded_len(audio_base64).ok_or_else(|| {
+        rejected(
+            ErrorCode::InvalidBase64,
+            format!("{} is not valid base64", field),
+        )
+    })?;
+    if bytes == 0 {
+        return Err(rejected(
+            ErrorCode::InvalidAudio,
+            format!("{} is empty", field),
+        ));
+    }
+    if bytes > limits.max_audio_bytes {
+        return Err(rejected(
+            ErrorCode::AudioTooLarge,
+            format!(
+                "{} is {} bytes; the limit is {}",
+                field, bytes, limits.max_audio_bytes
+            ),
+        ));
+    }
+
+    let header = wav_header(audio_base64, bytes);
+    let summary = AudioSummary {
+        bytes,
+        sample_rate: header.map(|h| h.sample_rate),
+        duration_secs: header.map(|h| h.duration_secs),
+    };
+    if let Some(rate) = summary.sample_rate {
+        check_sample_rate(rate, field, limits)?;
+    }
+    if let Some(secs) = summary.duration_secs {
+        if secs > limits.max_audio_seconds {
+            return Err(rejected(
+                ErrorCode::AudioTooLong,
+                format!(
+                    "{} is {:.1}s long; the limit is {}s",
+                    field, secs, limits.max_audio_seconds
+                ),
+            ));
+        }
+    }
+    Ok(summary)
+}
+
+/// Check a sample rate against the allowed list
+pub fn check_sample_rate(sample_rate: u32, field: &str, limits: &InputLimits) -> Result<()> {
+    if limits.sample_rates.is_empty() || limits.sample_rates.contains(&sample_rate) {
+        return Ok(());
+    }
+    Err(rejected(
+        ErrorCode::UnsupportedSampleRate,
+        format!(
+            "{} has a sample rate of {} Hz; supported rates are {:?}",
+            field, sample_rate, limits.sample_rates
+        ),
+    ))
+}
+
+/// Decoded length of standard, padded base64, or `None` if malformed
+fn decoded_len(encoded: &str) -> Option<usize> {
+    let bytes = encoded.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    let padding = bytes.iter().rev().take_while(|&&b| b == b'=').count();
+    if padding > 2 {
+        return None;
+    }
+    let body = &bytes[..bytes.len() - padding];
+    if !body
+        .iter()
+        .all(|b| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'/')
+    {
+        return None;
+    }
+    Some(bytes.len() / 4 * 3 - padding)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WavHeader {
+    sample_rate: u32,
+    duration_secs: f64,
+}
+
+/// Read the format of a WAV upload from the start of its base64 form.
+/// Returns `None` for other formats.
+fn wav_header(encoded: &str, total_bytes: usize) -> Option<WavHeader> {
+    use base64::Engine;
+    let prefix = &encoded[..encoded.len().min(HEADER_BASE64_CHARS)];
+    let head = base64::engine::general_purpose::STANDARD
+        .decode(prefix)
+        .ok()?;
+    if head.len() < 12 || &head[0..4] != b"RIFF" || &head[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let u32_at = |at: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(head.get(at..at + 4)?.try_into().ok()?))
+    };
+    let mut format = None;
+    let mut at = 12;
+    while at + 8 <= head.len() {
+        let id = &head[at..at + 4];
+        let size = u32_at(at + 4)? as usize;
+        let body = at + 8;
+        match id {
+            b"fmt " => {
+                let sample_rate = u32_at(body + 4)?;
+                let byte_rate = u32_at(body + 8)?;
+                format = Some((sample_rate, byte_rate));
+            }
+            b"data" => {
+                let (sample_rate, byte_rate) = format?;
+                // Streamed WAVs leave the size unset
+                let data = size.min(total_bytes.saturating_sub(body));
+                let duration_secs = if byte_rate > 0 {
+                    data as f64 / byte_rate as f64
+                } else {
+                    0.0
+                };
+                return Some(WavHeader {
+                    sample_rate,
+                    duration_secs,
+                });
+            }
+            _ => {}
+        }
+        at = body + size + size % 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioEncoder, AudioFormat};
+    use base64::Engine;
+
+    fn wav(sample_rate: u32, secs: f32) -> String {
+        let samples = vec![0.1f32; (sample_rate as f32 * secs) as usize];
+        let bytes = AudioEncoder::new(sample_rate, 1)
+            .encode(&samples, AudioFormat::Wav)
+            .unwrap();
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    fn code(result: Result<impl std::fmt::Debug>) -> ErrorCode {
+        result.unwrap_err().code()
+    }
+
+    #[test]
+    fn test_text_and_audio_checks() {
+        let limits = InputLimits {
+            max_text_chars: 10,
+            max_audio_seconds: 2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_text("Cafe\u{301}\r\nok\u{200B}", "text", &limits).unwrap(),
+            "Café\nok"
+        );
+        assert_eq!(
+            code(check_text("Much too long", "text", &limits)),
+            ErrorCode::TextTooLong
+        );
+        assert_eq!(
+            code(check_text("a\0b", "text", &limits)),
+            ErrorCode::InvalidText
+        );
+
+        let summary = check_audio(&wav(16000, 1.0), "audio", &limits).unwrap();
+        assert_eq!(summary.sample_rate, Some(16000));
+        assert!((summary.duration_secs.unwrap() - 1.0).abs() < 0.01);
+        assert_eq!(
+            code(check_audio(&wav(16000, 3.0), "audio", &limits)),
+            ErrorCode::AudioTooLong
+        );
+        assert_eq!(
+            code(check_audio(&wav(12345, 0.5), "audio", &limits)),
+            ErrorCode::UnsupportedSampleRate
+        );
+        assert_eq!(
+            code(check_audio("not base64!", "audio", &limits)),
+            ErrorCode::InvalidBase64
+        );
+        let small = InputLimits {
+            max_audio_bytes: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            code(check_audio(&wav(16000, 1.0), "audio", &small)),
+            ErrorCode::AudioTooLarge
+        );
+        // Other formats are only checked for size
+        let mp3 = base64::engine::general_purpose::STANDARD.encode(b"ID3\x04\0\0\0\0\0\0");
+        assert_eq!(
+            check_audio(&mp3, "audio", &limits).unwrap().sample_rate,
+            None
+        );
+    }
+}
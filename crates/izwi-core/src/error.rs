@@ -1,5 +1,6 @@
 //! Error types for the Izwi TTS engine
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,12 +11,44 @@ pub enum Error {
     #[error("Model not found: {0}")]
     ModelNotFound(String),
 
+    #[error("Request not found: {0}")]
+    RequestNotFound(String),
+
+    #[error("Chat session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("Chat session {0} already has a turn in progress")]
+    SessionBusy(String),
+
+    #[error("Upload not found: {0}")]
+    UploadNotFound(String),
+
+    #[error("Model not loaded: {0}")]
+    ModelNotLoaded(String),
+
     #[error("Model loading failed: {0}")]
     ModelLoadError(String),
 
     #[error("Download failed: {0}")]
     DownloadError(String),
 
+    #[error("HuggingFace Hub access denied: {0}")]
+    HfAuthError(String),
+
+    #[error("Not enough disk space in {path}: {needed} bytes needed, {available} available")]
+    InsufficientDiskSpace {
+        path: String,
+        needed: u64,
+        available: u64,
+    },
+
+    #[error("Checksum mismatch for {file}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
     #[error("Tokenization error: {0}")]
     TokenizationError(String),
 
@@ -25,9 +58,41 @@ pub enum Error {
     #[error("Audio encoding error: {0}")]
     AudioError(String),
 
+    #[error("Invalid audio: {0}")]
+    InvalidAudio(String),
+
+    /// Input refused by the validation layer, with the specific reason
+    #[error("Invalid input: {message}")]
+    InputRejected { code: ErrorCode, message: String },
+
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("Output buffer overflow: {0}")]
+    BufferOverflow(String),
+
+    #[error("Engine overloaded: {reason}")]
+    Overloaded {
+        reason: String,
+        /// Suggested wait before retrying
+        retry_after_secs: u64,
+    },
+
+    #[error("KV cache exhausted: {0}")]
+    KvCacheExhausted(String),
+
+    #[error("Daemon unavailable: {0}")]
+    DaemonUnavailable(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Encryption error: {0}")]
+    EncryptionError(String),
+
+    #[error("Output storage error: {0}")]
+    StorageError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -52,6 +117,133 @@ pub enum Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Stable, machine-readable error code reported to API clients.
+///
+/// Codes are part of the public API: add new ones rather than renaming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidInput,
+    InvalidAudio,
+    InvalidText,
+    InvalidBase64,
+    TextTooLong,
+    AudioTooLarge,
+    AudioTooLong,
+    UnsupportedSampleRate,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    ModelNotLoaded,
+    #[serde(rename = "kv_cache_oom")]
+    KvCacheOom,
+    DaemonUnavailable,
+    Overloaded,
+    Timeout,
+    InsufficientStorage,
+    Unavailable,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "invalid_input",
+            ErrorCode::InvalidAudio => "invalid_audio",
+            ErrorCode::InvalidText => "invalid_text",
+            ErrorCode::InvalidBase64 => "invalid_base64",
+            ErrorCode::TextTooLong => "text_too_long",
+            ErrorCode::AudioTooLarge => "audio_too_large",
+            ErrorCode::AudioTooLong => "audio_too_long",
+            ErrorCode::UnsupportedSampleRate => "unsupported_sample_rate",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::ModelNotLoaded => "model_not_loaded",
+            ErrorCode::KvCacheOom => "kv_cache_oom",
+            ErrorCode::DaemonUnavailable => "daemon_unavailable",
+            ErrorCode::Overloaded => "overloaded",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::InsufficientStorage => "insufficient_storage",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// HTTP status code this error is reported with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::InvalidInput
+            | ErrorCode::InvalidAudio
+            | ErrorCode::InvalidText
+            | ErrorCode::InvalidBase64
+            | ErrorCode::TextTooLong
+            | ErrorCode::AudioTooLarge
+            | ErrorCode::AudioTooLong
+            | ErrorCode::UnsupportedSampleRate => 400,
+            ErrorCode::Unauthorized => 401,
+            ErrorCode::Forbidden => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Conflict => 409,
+            ErrorCode::Overloaded => 429,
+            ErrorCode::Internal => 500,
+            ErrorCode::ModelNotLoaded
+            | ErrorCode::KvCacheOom
+            | ErrorCode::DaemonUnavailable
+            | ErrorCode::Unavailable => 503,
+            ErrorCode::Timeout => 504,
+            ErrorCode::InsufficientStorage => 507,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Bounds on the Retry-After hint returned when the engine is full (seconds)
+pub(crate) const MIN_RETRY_AFTER_SECS: u64 = 1;
+pub(crate) const MAX_RETRY_AFTER_SECS: u64 = 60;
+
+impl Error {
+    /// Overload error suggesting a retry once `wait_secs` have passed,
+    /// within the Retry-After bounds
+    pub(crate) fn overloaded(reason: String, wait_secs: f64) -> Self {
+        Error::Overloaded {
+            reason,
+            retry_after_secs: (wait_secs.ceil() as u64)
+                .clamp(MIN_RETRY_AFTER_SECS, MAX_RETRY_AFTER_SECS),
+        }
+    }
+
+    /// Machine-readable code for this error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::InvalidInput(_) | Error::ConfigError(_) => ErrorCode::InvalidInput,
+            Error::InvalidAudio(_) => ErrorCode::InvalidAudio,
+            Error::InputRejected { code, .. } => *code,
+            Error::HfAuthError(_) => ErrorCode::Forbidden,
+            Error::ModelNotFound(_)
+            | Error::RequestNotFound(_)
+            | Error::SessionNotFound(_)
+            | Error::UploadNotFound(_) => ErrorCode::NotFound,
+            Error::SessionBusy(_) => ErrorCode::Conflict,
+            Error::ModelNotLoaded(_) => ErrorCode::ModelNotLoaded,
+            Error::KvCacheExhausted(_) => ErrorCode::KvCacheOom,
+            Error::DaemonUnavailable(_) => ErrorCode::DaemonUnavailable,
+            Error::Overloaded { .. } => ErrorCode::Overloaded,
+            Error::Timeout(_) => ErrorCode::Timeout,
+            Error::InsufficientDiskSpace { .. } => ErrorCode::InsufficientStorage,
+            Error::BufferOverflow(_) => ErrorCode::Unavailable,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
 impl From<hf_hub::api::sync::ApiError> for Error {
     fn from(e: hf_hub::api::sync::ApiError) -> Self {
         Error::HfHubError(e.to_string())
@@ -0,0 +1,148 @@
+//! Admission control for generation requests
+//!
+//! The inference engine hands requests straight to the daemons, so there is
+//! no scheduler queue to bound. Instead each generation holds a permit for
+//! as long as it runs, and a request is turned away with a Retry-After hint
+//! once the permits in use reach the configured caps, the same caps the
+//! engine core applies to its waiting queue.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::error::{Error, Result};
+
+/// Weight of the latest request in the running mean duration
+const DURATION_SMOOTHING: f64 = 0.2;
+
+/// Caps on the generations running at once
+#[derive(Debug)]
+pub struct AdmissionControl {
+    /// Requests in flight (0 = unlimited)
+    max_requests: usize,
+    /// Prompt tokens of requests in flight (0 = unlimited)
+    max_tokens: usize,
+    state: Mutex<AdmissionState>,
+}
+
+#[derive(Debug, Default)]
+struct AdmissionState {
+    requests: usize,
+    tokens: usize,
+    /// Running mean of how long a request held its permit (seconds)
+    mean_duration_secs: f64,
+}
+
+impl AdmissionControl {
+    pub fn new(max_requests: usize, max_tokens: usize) -> Self {
+        Self {
+            max_requests,
+            max_tokens,
+            state: Mutex::new(AdmissionState::default()),
+        }
+    }
+
+    /// Admit a request with `tokens` prompt tokens, or reject it if it would
+    /// push the requests in flight past the caps. The request counts until
+    /// the permit is dropped.
+    pub fn admit(self: &Arc<Self>, tokens: usize) -> Result<AdmissionPermit> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if self.max_requests > 0 && state.requests >= self.max_requests {
+            return Err(self.overloaded(
+                format!(
+                    "{} requests already in flight (limit {})",
+                    state.requests, self.max_requests
+                ),
+                &state,
+            ));
+        }
+
+        // A single oversized prompt is still admitted into an idle engine
+        if self.max_tokens > 0 && state.requests > 0 && state.tokens + tokens > self.max_tokens {
+            return Err(self.overloaded(
+                format!(
+                    "{} prompt tokens already in flight, request adds {} (limit {})",
+                    state.tokens, tokens, self.max_tokens
+                ),
+                &state,
+            ));
+        }
+
+        state.requests += 1;
+        state.tokens += tokens;
+        Ok(AdmissionPermit {
+            control: self.clone(),
+            tokens,
+            start: Instant::now(),
+        })
+    }
+
+    /// Requests in flight
+    pub fn in_flight(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .requests
+    }
+
+    /// Build an overload error hinting a retry once a typical request
+    /// has finished
+    fn overloaded(&self, reason: String, state: &AdmissionState) -> Error {
+        warn!("Rejecting request: {}", reason);
+        Error::overloaded(reason, state.mean_duration_secs)
+    }
+
+    fn release(&self, tokens: usize, held: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.requests = state.requests.saturating_sub(1);
+        state.tokens = state.tokens.saturating_sub(tokens);
+        let held = held.as_secs_f64();
+        state.mean_duration_secs = if state.mean_duration_secs == 0.0 {
+            held
+        } else {
+            state.mean_duration_secs + DURATION_SMOOTHING * (held - state.mean_duration_secs)
+        };
+    }
+}
+
+/// A request's place among those in flight, given up when dropped
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    control: Arc<AdmissionControl>,
+    tokens: usize,
+    start: Instant,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.control.release(self.tokens, self.start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MIN_RETRY_AFTER_SECS;
+
+    #[test]
+    fn test_admission_caps() {
+        let control = Arc::new(AdmissionControl::new(2, 0));
+        let first = control.admit(10).unwrap();
+        let _second = control.admit(10).unwrap();
+        match control.admit(10) {
+            Err(Error::Overloaded {
+                retry_after_secs, ..
+            }) => assert!(retry_after_secs >= MIN_RETRY_AFTER_SECS),
+            other => panic!("expected Overloaded, got {:?}", other),
+        }
+        drop(first);
+        assert_eq!(control.in_flight(), 1);
+        assert!(control.admit(10).is_ok());
+
+        // The token cap still admits one request into an idle engine
+        let control = Arc::new(AdmissionControl::new(0, 5));
+        let _long = control.admit(50).unwrap();
+        assert!(matches!(control.admit(1), Err(Error::Overloaded { .. })));
+    }
+}
@@ -2,18 +2,90 @@
 //! Connects to a persistent Python daemon for ASR model inference
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, info};
 
+use super::daemon_client::{DaemonClient, RetryPolicy};
+use super::supervisor::{DaemonSpec, DaemonStatus, DaemonSupervisor};
+use crate::config::BridgeConfig;
 use crate::error::{Error, Result};
 
-/// Default socket path for the ASR daemon
-const DEFAULT_SOCKET_PATH: &str = "/tmp/izwi_qwen3_asr_daemon.sock";
+/// What the ASR daemon produces from the audio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsrTask {
+    /// Text in the spoken language
+    #[default]
+    Transcribe,
+    /// Text in the spoken language plus an English translation
+    Translate,
+}
+
+/// Sample rate the ASR models take input at; other audio is resampled
+pub const ASR_SAMPLE_RATE: u32 = 16_000;
+
+/// Most hotwords accepted per request
+pub const MAX_HOTWORDS: usize = 200;
+
+/// Largest accepted hotword boost
+pub const MAX_HOTWORD_BOOST: f32 = 10.0;
+
+/// A term ASR decoding should favor, such as a product name or jargon.
+/// Deserializes from a bare string or `{"word", "boost"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "HotwordSpec")]
+pub struct Hotword {
+    pub word: String,
+    /// Relative weight; 1.0 unless given
+    pub boost: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HotwordSpec {
+    Word(String),
+    Weighted {
+        word: String,
+        #[serde(default = "default_boost")]
+        boost: f32,
+    },
+}
+
+fn default_boost() -> f32 {
+    1.0
+}
+
+impl From<HotwordSpec> for Hotword {
+    fn from(spec: HotwordSpec) -> Self {
+        match spec {
+            HotwordSpec::Word(word) => Self { word, boost: 1.0 },
+            HotwordSpec::Weighted { word, boost } => Self { word, boost },
+        }
+    }
+}
+
+/// Check a hotword list before it is sent to the daemon
+pub fn validate_hotwords(hotwords: &[Hotword]) -> Result<()> {
+    if hotwords.len() > MAX_HOTWORDS {
+        return Err(Error::InvalidInput(format!(
+            "At most {} hotwords are allowed, got {}",
+            MAX_HOTWORDS,
+            hotwords.len()
+        )));
+    }
+    for hotword in hotwords {
+        if hotword.word.trim().is_empty() {
+            return Err(Error::InvalidInput("Hotwords must not be empty".into()));
+        }
+        if !(hotword.boost > 0.0 && hotword.boost <= MAX_HOTWORD_BOOST) {
+            return Err(Error::InvalidInput(format!(
+                "Boost for hotword '{}' must be in (0, {}]",
+                hotword.word, MAX_HOTWORD_BOOST
+            )));
+        }
+    }
+    Ok(())
+}
 
 /// Request to ASR daemon
 #[derive(Debug, Serialize)]
@@ -25,6 +97,12 @@ pub struct AsrRequest {
     pub model_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<AsrTask>,
+    /// Terms to bias decoding toward. Boosts travel with the words so a
+    /// decoder that supports per-token bias can apply them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hotwords: Vec<Hotword>,
 }
 
 impl Default for AsrRequest {
@@ -34,6 +112,8 @@ impl Default for AsrRequest {
             audio_base64: None,
             model_id: None,
             language: None,
+            task: None,
+            hotwords: Vec::new(),
         }
     }
 }
@@ -43,6 +123,9 @@ impl Default for AsrRequest {
 pub struct AsrResponse {
     pub transcription: Option<String>,
     pub language: Option<String>,
+    /// English translation, for [`AsrTask::Translate`]
+    #[serde(default)]
+    pub translation: Option<String>,
     pub error: Option<String>,
     pub status: Option<String>,
     pub device: Option<String>,
@@ -51,214 +134,194 @@ pub struct AsrResponse {
 
 /// Qwen3-ASR bridge for calling the ASR daemon
 pub struct AsrBridge {
-    socket_path: PathBuf,
-    daemon_script_path: PathBuf,
-    python_cmd: String,
-    daemon_process: Mutex<Option<Child>>,
+    supervisor: Arc<DaemonSupervisor>,
+    client: DaemonClient,
 }
 
 impl AsrBridge {
     /// Create a new ASR bridge
     pub fn new() -> Self {
-        let base_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self::with_config(&BridgeConfig::default())
+    }
+
+    /// Create a bridge using the configured paths and interpreter
+    pub fn with_config(config: &BridgeConfig) -> Self {
+        let transport = config.transport("asr");
+        let spec = DaemonSpec::new(
+            "asr",
+            config.script_path("qwen3_asr_daemon.py"),
+            transport.clone(),
+        )
+        .with_python_cmd(config.python_executable().to_string_lossy())
+        .with_startup_timeout(config.startup_timeout())
+        .with_io_timeouts(Duration::from_secs(120), Duration::from_secs(30));
+        let client = DaemonClient::new("asr", transport)
+            .with_timeout(Duration::from_secs(120))
+            .with_retry_policy(RetryPolicy::from(config));
 
         Self {
-            socket_path: PathBuf::from(DEFAULT_SOCKET_PATH),
-            daemon_script_path: base_dir.join("scripts/qwen3_asr_daemon.py"),
-            python_cmd: "python3".to_string(),
-            daemon_process: Mutex::new(None),
+            supervisor: Arc::new(DaemonSupervisor::new(spec)),
+            client,
         }
     }
 
-    /// Check if the daemon is running
-    fn is_daemon_running(&self) -> bool {
-        self.socket_path.exists() && self.connect_to_daemon().is_ok()
-    }
-
     /// Start the daemon if not running
     pub fn ensure_daemon_running(&self) -> Result<()> {
-        if self.is_daemon_running() {
-            debug!("ASR daemon already running");
-            return Ok(());
-        }
-
-        info!("Starting ASR daemon...");
-
-        let child = Command::new(&self.python_cmd)
-            .arg(&self.daemon_script_path)
-            .arg("--socket")
-            .arg(&self.socket_path)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| Error::InferenceError(format!("Failed to start ASR daemon: {}", e)))?;
-
-        // Store the child process
-        {
-            let mut guard = self.daemon_process.lock().unwrap();
-            *guard = Some(child);
-        }
-
-        // Wait for daemon to be ready (up to 10 seconds)
-        for i in 0..100 {
-            std::thread::sleep(Duration::from_millis(100));
-            if self.socket_path.exists() {
-                if let Ok(mut stream) = self.connect_to_daemon() {
-                    // Send a check command to verify it's responding
-                    let request = AsrRequest {
-                        command: "check".to_string(),
-                        ..Default::default()
-                    };
-                    if self.send_request(&mut stream, &request).is_ok() {
-                        info!("ASR daemon started successfully");
-                        return Ok(());
-                    }
-                }
-            }
-            if i % 20 == 0 {
-                debug!("Waiting for ASR daemon to start... ({}/10s)", i / 10);
-            }
-        }
-
-        Err(Error::InferenceError(
-            "ASR daemon failed to start within 10 seconds".to_string(),
-        ))
+        self.supervisor.ensure_running()
     }
 
     /// Stop the daemon
     pub fn stop_daemon(&self) -> Result<()> {
-        if !self.is_daemon_running() {
-            return Ok(());
-        }
-
-        info!("Stopping ASR daemon...");
-
-        // Send shutdown command
-        let request = AsrRequest {
-            command: "shutdown".to_string(),
-            ..Default::default()
-        };
-
-        if let Ok(mut stream) = self.connect_to_daemon() {
-            let _ = self.send_request(&mut stream, &request);
-        }
-
-        // Wait for socket to be removed
-        for _ in 0..50 {
-            std::thread::sleep(Duration::from_millis(100));
-            if !self.socket_path.exists() {
-                info!("ASR daemon stopped");
-                return Ok(());
-            }
-        }
+        self.client.clear();
+        self.supervisor.stop()
+    }
 
-        // Force kill if still running
-        let mut guard = self.daemon_process.lock().unwrap();
-        if let Some(mut child) = guard.take() {
-            let _ = child.kill();
-            let _ = child.wait();
+    /// Health of the ASR daemon
+    pub fn health(&self) -> DaemonStatus {
+        DaemonStatus {
+            circuit: self.client.circuit_state(),
+            ..self.supervisor.status()
         }
+    }
 
-        Ok(())
+    /// Pooled async connections to the daemon, for raw requests
+    pub fn client(&self) -> &DaemonClient {
+        &self.client
     }
 
     /// Get daemon status
-    pub fn get_status(&self) -> Result<AsrResponse> {
+    pub async fn get_status(&self) -> Result<AsrResponse> {
         let request = AsrRequest {
             command: "status".to_string(),
             ..Default::default()
         };
-        self.call_daemon(&request)
+        self.call(&request).await
     }
 
     /// Transcribe audio to text
-    pub fn transcribe(
+    pub async fn transcribe(
         &self,
         audio_base64: &str,
         model_id: Option<&str>,
         language: Option<&str>,
     ) -> Result<AsrResponse> {
-        let request = AsrRequest {
-            command: "transcribe".to_string(),
-            audio_base64: Some(audio_base64.to_string()),
-            model_id: model_id.map(String::from),
-            language: language.map(String::from),
-        };
-        self.call_daemon(&request)
+        self.call(&transcribe_request(
+            audio_base64,
+            model_id,
+            language,
+            AsrTask::Transcribe,
+        ))
+        .await
     }
 
-    /// Connect to the daemon socket
-    fn connect_to_daemon(&self) -> Result<UnixStream> {
-        UnixStream::connect(&self.socket_path)
-            .map_err(|e| Error::InferenceError(format!("Failed to connect to ASR daemon: {}", e)))
+    /// Transcribe audio and translate the transcript to English
+    pub async fn translate(
+        &self,
+        audio_base64: &str,
+        model_id: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<AsrResponse> {
+        self.call(&transcribe_request(
+            audio_base64,
+            model_id,
+            language,
+            AsrTask::Translate,
+        ))
+        .await
     }
 
-    /// Send a request to the daemon and receive response
-    fn send_request(&self, stream: &mut UnixStream, request: &AsrRequest) -> Result<AsrResponse> {
-        // Set timeouts
-        stream
-            .set_read_timeout(Some(Duration::from_secs(120)))
-            .ok();
-        stream
-            .set_write_timeout(Some(Duration::from_secs(30)))
-            .ok();
-
-        // Serialize request
-        let request_json = serde_json::to_vec(request)
-            .map_err(|e| Error::InferenceError(format!("Failed to serialize request: {}", e)))?;
-
-        // Send length prefix (4 bytes, big-endian)
-        let length = (request_json.len() as u32).to_be_bytes();
-        stream
-            .write_all(&length)
-            .map_err(|e| Error::InferenceError(format!("Failed to write length: {}", e)))?;
-
-        // Send request
-        stream
-            .write_all(&request_json)
-            .map_err(|e| Error::InferenceError(format!("Failed to write request: {}", e)))?;
-
-        // Read response length
-        let mut length_buf = [0u8; 4];
-        stream
-            .read_exact(&mut length_buf)
-            .map_err(|e| Error::InferenceError(format!("Failed to read response length: {}", e)))?;
-        let response_length = u32::from_be_bytes(length_buf) as usize;
-
-        // Read response
-        let mut response_buf = vec![0u8; response_length];
-        stream
-            .read_exact(&mut response_buf)
-            .map_err(|e| Error::InferenceError(format!("Failed to read response: {}", e)))?;
-
-        // Deserialize response
-        let response: AsrResponse = serde_json::from_slice(&response_buf)
-            .map_err(|e| Error::InferenceError(format!("Failed to parse response: {}", e)))?;
-
-        // Check for errors in response
-        if let Some(error) = &response.error {
-            return Err(Error::InferenceError(error.clone()));
+    /// Blocking variant of [`transcribe`](Self::transcribe) for synchronous
+    /// executors
+    pub fn transcribe_blocking(
+        &self,
+        audio_base64: &str,
+        model_id: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<AsrResponse> {
+        self.ensure_daemon_running()?;
+        let response: AsrResponse = self.supervisor.call(&transcribe_request(
+            audio_base64,
+            model_id,
+            language,
+            AsrTask::Transcribe,
+        ))?;
+        check_response(response)
+    }
+
+    /// Call daemon with request, starting it off the async runtime first
+    /// unless a pooled connection shows it is already up
+    async fn call(&self, request: &AsrRequest) -> Result<AsrResponse> {
+        if !self.client.has_idle() {
+            let supervisor = self.supervisor.clone();
+            tokio::task::spawn_blocking(move || supervisor.ensure_running())
+                .await
+                .map_err(|e| Error::InferenceError(format!("Daemon start task failed: {}", e)))??;
         }
+        check_response(self.client.call(request).await?)
+    }
+}
 
-        Ok(response)
+impl Default for AsrBridge {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Call daemon with request
-    fn call_daemon(&self, request: &AsrRequest) -> Result<AsrResponse> {
-        // Ensure daemon is running
-        self.ensure_daemon_running()?;
+fn transcribe_request(
+    audio_base64: &str,
+    model_id: Option<&str>,
+    language: Option<&str>,
+    task: AsrTask,
+) -> AsrRequest {
+    AsrRequest {
+        command: "transcribe".to_string(),
+        audio_base64: Some(audio_base64.to_string()),
+        model_id: model_id.map(String::from),
+        language: language.map(String::from),
+        task: Some(task),
+        hotwords: Vec::new(),
+    }
+}
 
-        // Connect and send request
-        let mut stream = self.connect_to_daemon()?;
-        self.send_request(&mut stream, request)
+fn check_response(response: AsrResponse) -> Result<AsrResponse> {
+    if let Some(error) = &response.error {
+        return Err(Error::InferenceError(error.clone()));
     }
+    Ok(response)
 }
 
-impl Drop for AsrBridge {
-    fn drop(&mut self) {
-        // Note: We don't stop the daemon on drop anymore
-        // The daemon persists for better performance across requests
-        // Use stop_daemon() explicitly if needed
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotwords_parse_and_validate() {
+        let hotwords: Vec<Hotword> =
+            serde_json::from_str(r#"["Izwi", {"word": "Qwen3", "boost": 2.5}, {"word": "MLX"}]"#)
+                .unwrap();
+        assert_eq!(hotwords[0].boost, 1.0);
+        assert_eq!(hotwords[1].boost, 2.5);
+        assert_eq!(hotwords[2].word, "MLX");
+        assert!(validate_hotwords(&hotwords).is_ok());
+
+        let request = AsrRequest {
+            command: "transcribe".to_string(),
+            hotwords: hotwords.clone(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["hotwords"][1]["boost"], 2.5);
+        assert!(serde_json::to_value(AsrRequest::default()).unwrap()["hotwords"].is_null());
+
+        let zero = vec![Hotword {
+            word: "Izwi".into(),
+            boost: 0.0,
+        }];
+        assert!(validate_hotwords(&zero).is_err());
+        let blank = vec![Hotword {
+            word: " ".into(),
+            boost: 1.0,
+        }];
+        assert!(validate_hotwords(&blank).is_err());
     }
 }
@@ -0,0 +1,576 @@
+//! Async client for the Python inference daemons.
+//!
+//! Requests go over pooled async connections of the daemon's transport
+//! (see [`super::transport`]), so waiting on a daemon never blocks a runtime
+//! thread. Each connection carries one request at a time; concurrency comes
+//! from the pool, whose size caps the requests in flight per daemon. Every
+//! request is tagged with a `request_id` that the daemon echoes back, so a
+//! response can never be matched to the wrong request.
+//!
+//! A response is one or more frames. Streaming commands send frames marked
+//! `"final": false` followed by one final frame; a frame without the flag is
+//! final, so plain request/response commands are a one-frame stream.
+//!
+//! Dropping a pending call cancels it: its connection is closed instead of
+//! being returned to the pool, so a late response cannot leak into a later
+//! request.
+//!
+//! Calls that cannot reach the daemon, e.g. while it restarts, are retried
+//! with exponential backoff. After a run of failed calls a circuit breaker
+//! opens and calls fail at once until a cooldown has passed, when the next
+//! call is let through to probe the daemon again.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, info, warn};
+
+use super::transport::{Connection, SharedTransport};
+use crate::config::BridgeConfig;
+use crate::error::{Error, Result};
+
+/// Largest response accepted from a daemon
+const MAX_MESSAGE_BYTES: usize = 512 * 1024 * 1024;
+
+/// Retry and circuit-breaker settings for daemon calls.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts after the first for calls that could not reach the daemon
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled per retry
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failed calls that open the breaker (0 disables it)
+    pub failure_threshold: u32,
+    /// How long an open breaker fails calls before letting one through
+    pub cooldown: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from(&BridgeConfig::default())
+    }
+}
+
+impl From<&BridgeConfig> for RetryPolicy {
+    fn from(config: &BridgeConfig) -> Self {
+        Self {
+            max_retries: config.retries,
+            initial_backoff: Duration::from_millis(config.retry_backoff_ms),
+            max_backoff: Duration::from_secs(5),
+            failure_threshold: config.breaker_threshold,
+            cooldown: Duration::from_secs(config.breaker_cooldown_secs),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based)
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32 << retry.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// State of a client's circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls fail at once until the cooldown has passed
+    Open,
+    /// Cooldown over; the next call decides whether the breaker closes
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    /// Consecutive failed calls
+    failures: u32,
+    /// When the breaker last opened
+    opened_at: Option<Instant>,
+}
+
+/// Pooled, request-tagged connections to one daemon.
+#[derive(Debug)]
+pub struct DaemonClient {
+    name: String,
+    transport: SharedTransport,
+    /// Connections waiting for their next request
+    idle: Mutex<Vec<Connection>>,
+    /// One permit per connection allowed in flight
+    slots: Semaphore,
+    /// Time allowed for sending a request and for each response frame
+    timeout: Duration,
+    next_id: AtomicU64,
+    retry: RetryPolicy,
+    breaker: Mutex<Breaker>,
+}
+
+impl DaemonClient {
+    /// Client allowing 4 requests in flight with a 5 minute timeout.
+    pub fn new(name: impl Into<String>, transport: SharedTransport) -> Self {
+        Self {
+            name: name.into(),
+            transport,
+            idle: Mutex::new(Vec::new()),
+            slots: Semaphore::new(4),
+            timeout: Duration::from_secs(300),
+            next_id: AtomicU64::new(1),
+            retry: RetryPolicy::default(),
+            breaker: Mutex::new(Breaker::default()),
+        }
+    }
+
+    /// Allow up to `max` requests in flight (and pooled connections).
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.slots = Semaphore::new(max.max(1));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Where the daemon listens
+    pub fn transport(&self) -> &SharedTransport {
+        &self.transport
+    }
+
+    /// Whether a pooled connection is ready for use
+    pub fn has_idle(&self) -> bool {
+        !self.lock_idle().is_empty()
+    }
+
+    /// Close all pooled connections, e.g. after the daemon restarted
+    pub fn clear(&self) {
+        self.lock_idle().clear();
+    }
+
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<Connection>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_breaker(&self) -> std::sync::MutexGuard<'_, Breaker> {
+        self.breaker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current state of the circuit breaker
+    pub fn circuit_state(&self) -> CircuitState {
+        match self.lock_breaker().opened_at {
+            None => CircuitState::Closed,
+            Some(at) if at.elapsed() < self.retry.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Fail fast while the breaker is open
+    fn admit(&self) -> Result<()> {
+        let breaker = self.lock_breaker();
+        match breaker.opened_at {
+            Some(at) if at.elapsed() < self.retry.cooldown => {
+                Err(Error::DaemonUnavailable(format!(
+                    "{} daemon failed {} calls in a row; not retrying for {}s",
+                    self.name,
+                    breaker.failures,
+                    (self.retry.cooldown - at.elapsed()).as_secs().max(1)
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Track the outcome of one attempt, opening or closing the breaker
+    fn record<T>(&self, result: &Result<T>) {
+        let mut breaker = self.lock_breaker();
+        match result {
+            Ok(_) => {
+                if breaker.opened_at.take().is_some() {
+                    info!("{} daemon answered again, closing circuit", self.name);
+                }
+                breaker.failures = 0;
+            }
+            Err(e) if is_daemon_failure(e) => {
+                breaker.failures += 1;
+                let threshold = self.retry.failure_threshold;
+                if threshold > 0 && breaker.failures >= threshold {
+                    if breaker.opened_at.is_none() {
+                        warn!(
+                            "{} daemon failed {} calls in a row, opening circuit for {:?}",
+                            self.name, breaker.failures, self.retry.cooldown
+                        );
+                    }
+                    breaker.opened_at = Some(Instant::now());
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Run `attempt` until it succeeds, fails for a reason other than the
+    /// daemon being unreachable, or runs out of retries
+    async fn with_retries<V, F, Fut>(&self, mut attempt: F) -> Result<V>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        let mut retries = 0;
+        loop {
+            self.admit()?;
+            let result = attempt().await;
+            self.record(&result);
+            match result {
+                Err(e) if is_retryable(&e) && retries < self.retry.max_retries => {
+                    retries += 1;
+                    let delay = self.retry.backoff(retries);
+                    debug!(
+                        "{} daemon call failed ({}), retry {} in {:?}",
+                        self.name, e, retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        self.transport.connect().await.map_err(|e| {
+            Error::DaemonUnavailable(format!("Failed to connect to {} daemon: {}", self.name, e))
+        })
+    }
+
+    /// Whether the daemon answers a check command. Pings are not retried.
+    pub async fn ping(&self) -> bool {
+        if self.admit().is_err() {
+            return false;
+        }
+        let result = self
+            .call_once::<_, Value>(&serde_json::json!({"command": "check"}))
+            .await;
+        self.record(&result);
+        result.is_ok()
+    }
+
+    /// Send one request and wait for its response.
+    ///
+    /// A pooled connection that turns out to be dead (the daemon restarted)
+    /// is retried once on a fresh connection, and a daemon that cannot be
+    /// reached at all per the retry policy.
+    pub async fn call<T: Serialize, R: DeserializeOwned>(&self, request: &T) -> Result<R> {
+        self.with_retries(|| self.call_once(request)).await
+    }
+
+    async fn call_once<T: Serialize, R: DeserializeOwned>(&self, request: &T) -> Result<R> {
+        let _slot = self.acquire().await?;
+        let (id, body) = self.tag(request)?;
+        let (stream, response) = self.start(&body, id).await?;
+        self.lock_idle().push(stream);
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Send a request whose response arrives as a sequence of frames.
+    ///
+    /// The connection stays reserved for the stream until its final frame
+    /// has been read; dropping the stream early closes the connection. Only
+    /// starting the stream is retried.
+    pub async fn call_stream<T: Serialize>(&self, request: &T) -> Result<DaemonStream<'_>> {
+        self.with_retries(|| async {
+            let slot = self.acquire().await?;
+            let (id, body) = self.tag(request)?;
+            let (stream, first) = self.start(&body, id).await?;
+            Ok(DaemonStream {
+                client: self,
+                _slot: slot,
+                stream: Some(stream),
+                pending: Some(first),
+                id,
+            })
+        })
+        .await
+    }
+
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>> {
+        self.slots
+            .acquire()
+            .await
+            .map_err(|_| Error::InferenceError(format!("{} client is closed", self.name)))
+    }
+
+    /// Serialize a request tagged with a fresh request ID
+    fn tag<T: Serialize>(&self, request: &T) -> Result<(u64, Vec<u8>)> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut message = serde_json::to_value(request)?;
+        match &mut message {
+            Value::Object(fields) => {
+                fields.insert("request_id".to_string(), id.into());
+            }
+            _ => {
+                return Err(Error::InferenceError(
+                    "Daemon requests must be JSON objects".to_string(),
+                ))
+            }
+        }
+        Ok((id, serde_json::to_vec(&message)?))
+    }
+
+    /// Send a request and read its first frame, retrying once on a fresh
+    /// connection when a pooled one turns out to be dead
+    async fn start(&self, body: &[u8], id: u64) -> Result<(Connection, Value)> {
+        let pooled = self.lock_idle().pop();
+        match pooled {
+            Some(stream) => match self.send(stream, body, id).await {
+                Err(Error::IoError(e)) => {
+                    debug!(
+                        "Pooled {} connection failed ({}), reconnecting",
+                        self.name, e
+                    );
+                    self.send(self.connect().await?, body, id).await
+                }
+                result => result,
+            },
+            None => self.send(self.connect().await?, body, id).await,
+        }
+    }
+
+    /// Write one message on `stream` and read the first response frame
+    async fn send(
+        &self,
+        mut stream: Connection,
+        body: &[u8],
+        id: u64,
+    ) -> Result<(Connection, Value)> {
+        self.timed(write_message(&mut stream, body)).await?;
+        let response = self.receive(&mut stream, id).await?;
+        Ok((stream, response))
+    }
+
+    /// Read the next frame for request `id`
+    async fn receive(&self, stream: &mut Connection, id: u64) -> Result<Value> {
+        let response = self.timed(read_message(stream)).await?;
+        match response.get("request_id").and_then(Value::as_u64) {
+            // Daemons that predate request IDs don't echo them
+            Some(echoed) if echoed != id => Err(Error::InferenceError(format!(
+                "{} daemon answered request {} while {} was pending",
+                self.name, echoed, id
+            ))),
+            _ => Ok(response),
+        }
+    }
+
+    /// Run one read or write within the client timeout
+    async fn timed<T>(&self, io: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        tokio::time::timeout(self.timeout, io).await.map_err(|_| {
+            Error::Timeout(format!(
+                "{} daemon did not answer within {}s",
+                self.name,
+                self.timeout.as_secs()
+            ))
+        })?
+    }
+}
+
+/// Frames of one streamed daemon response.
+#[derive(Debug)]
+pub struct DaemonStream<'a> {
+    client: &'a DaemonClient,
+    _slot: SemaphorePermit<'a>,
+    /// Connection, until the final frame returns it to the pool
+    stream: Option<Connection>,
+    /// First frame, read while starting the request
+    pending: Option<Value>,
+    id: u64,
+}
+
+impl DaemonStream<'_> {
+    /// Next frame, or `None` once the final frame has been returned
+    pub async fn next_frame(&mut self) -> Result<Option<Value>> {
+        let frame = match self.pending.take() {
+            Some(frame) => frame,
+            None => {
+                let Some(stream) = self.stream.as_mut() else {
+                    return Ok(None);
+                };
+                match self.client.receive(stream, self.id).await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        self.stream = None;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        if is_final(&frame) {
+            if let Some(stream) = self.stream.take() {
+                self.client.lock_idle().push(stream);
+            }
+        }
+        Ok(Some(frame))
+    }
+}
+
+/// Whether `error` means the daemon could not be reached, so the call may
+/// succeed once it is back
+fn is_retryable(error: &Error) -> bool {
+    matches!(error, Error::DaemonUnavailable(_) | Error::IoError(_))
+}
+
+/// Whether `error` counts against the daemon for the circuit breaker
+fn is_daemon_failure(error: &Error) -> bool {
+    is_retryable(error) || matches!(error, Error::Timeout(_))
+}
+
+/// Whether a frame ends its response; frames without the flag do
+pub fn is_final(frame: &Value) -> bool {
+    frame.get("final").and_then(Value::as_bool).unwrap_or(true)
+}
+
+/// Write one length-prefixed message
+async fn write_message(stream: &mut (impl AsyncWrite + Unpin), body: &[u8]) -> Result<()> {
+    stream.write_all(&(body.len() as u32).to_be_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Read one length-prefixed message
+async fn read_message(stream: &mut (impl AsyncRead + Unpin)) -> Result<Value> {
+    let length = stream.read_u32().await? as usize;
+    if length > MAX_MESSAGE_BYTES {
+        return Err(Error::InferenceError(format!(
+            "Daemon response of {} bytes exceeds the {} byte limit",
+            length, MAX_MESSAGE_BYTES
+        )));
+    }
+    let mut response = vec![0u8; length];
+    stream.read_exact(&mut response).await?;
+    serde_json::from_slice(&response).map_err(|e| {
+        Error::InferenceError(format!(
+            "Failed to parse daemon response: {} - {}",
+            e,
+            String::from_utf8_lossy(&response)
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::transport::UnixTransport;
+    use std::sync::Arc;
+    use tokio::net::UnixListener;
+
+    /// Echo daemon answering each request with its command and ID, split into
+    /// `frames` frames when asked
+    async fn serve(listener: UnixListener) {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                while let Ok(length) = stream.read_u32().await {
+                    let mut body = vec![0u8; length as usize];
+                    stream.read_exact(&mut body).await.unwrap();
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+                    let frames = request["frames"].as_u64().unwrap_or(1);
+                    for index in 0..frames {
+                        let response = serde_json::to_vec(&serde_json::json!({
+                            "status": request["command"],
+                            "index": index,
+                            "final": index + 1 == frames,
+                            "request_id": request["request_id"],
+                        }))
+                        .unwrap();
+                        stream.write_u32(response.len() as u32).await.unwrap();
+                        stream.write_all(&response).await.unwrap();
+                    }
+                }
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_reuse_pooled_connections() {
+        let socket =
+            std::env::temp_dir().join(format!("izwi-client-{}.sock", uuid::Uuid::new_v4()));
+        tokio::spawn(serve(UnixListener::bind(&socket).unwrap()));
+
+        let client = DaemonClient::new("test", Arc::new(UnixTransport::new(&socket)))
+            .with_max_connections(2);
+        let calls = (0..6).map(|i| {
+            let client = &client;
+            async move {
+                let response: Value = client
+                    .call(&serde_json::json!({"command": format!("c{}", i)}))
+                    .await
+                    .unwrap();
+                assert_eq!(response["status"], format!("c{}", i));
+            }
+        });
+        futures::future::join_all(calls).await;
+        assert!(client.lock_idle().len() <= 2);
+        assert!(client.ping().await);
+
+        let mut frames = client
+            .call_stream(&serde_json::json!({"command": "stream", "frames": 3}))
+            .await
+            .unwrap();
+        let mut indices = Vec::new();
+        while let Some(frame) = frames.next_frame().await.unwrap() {
+            indices.push(frame["index"].as_u64().unwrap());
+        }
+        assert_eq!(indices, vec![0, 1, 2]);
+        drop(frames);
+        // The connection is reusable once the final frame was read
+        assert!(client.ping().await);
+
+        std::fs::remove_file(&socket).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_and_recovers() {
+        let socket =
+            std::env::temp_dir().join(format!("izwi-client-{}.sock", uuid::Uuid::new_v4()));
+        let client = DaemonClient::new("test", Arc::new(UnixTransport::new(&socket)))
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+                failure_threshold: 2,
+                cooldown: Duration::from_millis(200),
+            });
+        let check = serde_json::json!({"command": "check"});
+
+        // Nothing listens yet: the call and its retry fail, opening the breaker
+        let err = client.call::<_, Value>(&check).await.unwrap_err();
+        assert!(matches!(err, Error::DaemonUnavailable(_)));
+        assert_eq!(client.circuit_state(), CircuitState::Open);
+
+        // Calls fail fast while open, even once the daemon is back
+        tokio::spawn(serve(UnixListener::bind(&socket).unwrap()));
+        let err = client.call::<_, Value>(&check).await.unwrap_err();
+        assert!(err.to_string().contains("not retrying"));
+        assert!(!client.ping().await);
+
+        // After the cooldown a call is let through and closes the breaker
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.circuit_state(), CircuitState::HalfOpen);
+        assert!(client.ping().await);
+        assert_eq!(client.circuit_state(), CircuitState::Closed);
+
+        std::fs::remove_file(&socket).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+//! Multi-speaker dialogue synthesis from a script
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::audio::{normalize_loudness, trim_silence, LoudnessConfig, SilenceConfig};
+use crate::error::{Error, Result};
+use crate::model::ModelVariant;
+
+/// One line of a dialogue script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueLine {
+    /// Script speaker name, mapped to a voice through `DialogueRequest::voices`
+    pub speaker: String,
+    pub text: String,
+    /// Silence after this line (defaults to the request's `pause_ms`)
+    #[serde(default)]
+    pub pause_ms: Option<u32>,
+}
+
+/// A dialogue script to render into a single output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueRequest {
+    pub lines: Vec<DialogueLine>,
+    /// Script speaker -> voice; unmapped speakers are used as voice names
+    #[serde(default)]
+    pub voices: HashMap<String, String>,
+    /// Silence between lines that don't set their own, in milliseconds
+    #[serde(default)]
+    pub pause_ms: Option<u32>,
+    /// Loudness every line is matched to, in LUFS
+    #[serde(default)]
+    pub target_lufs: Option<f32>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub model: Option<ModelVariant>,
+}
+
+impl DialogueRequest {
+    /// Check the script before any line is synthesized
+    pub fn validate(&self, max_lines: usize) -> Result<()> {
+        if self.lines.is_empty() {
+            return Err(Error::InvalidInput("Dialogue has no lines".to_string()));
+        }
+        if max_lines > 0 && self.lines.len() > max_lines {
+            return Err(Error::InvalidInput(format!(
+                "Dialogue has {} lines, the limit is {}",
+                self.lines.len(),
+                max_lines
+            )));
+        }
+        if let Some((i, _)) = self
+            .lines
+            .iter()
+            .enumerate()
+            .find(|(_, line)| line.text.trim().is_empty() || line.speaker.trim().is_empty())
+        {
+            return Err(Error::InvalidInput(format!(
+                "Dialogue line {} needs a speaker and text",
+                i
+            )));
+        }
+        Ok(())
+    }
+
+    /// Voice a script speaker is rendered with
+    pub fn voice_for<'a>(&'a self, speaker: &'a str) -> &'a str {
+        self.voices
+            .get(speaker)
+            .map(String::as_str)
+            .unwrap_or(speaker)
+    }
+}
+
+/// Where a line landed in the stitched output
+#[derive(Debug, Clone, Serialize)]
+pub struct LineTiming {
+    pub index: usize,
+    pub speaker: String,
+    pub voice: String,
+    pub start_secs: f32,
+    pub end_secs: f32,
+}
+
+/// Stitched dialogue audio and per-line timings
+#[derive(Debug, Clone)]
+pub struct DialogueResult {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub lines: Vec<LineTiming>,
+    pub total_time_ms: f32,
+    /// Non-fatal notices collected from the lines
+    pub warnings: Vec<String>,
+}
+
+impl DialogueResult {
+    pub fn duration_secs(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+}
+
+/// A synthesized line waiting to be stitched
+#[derive(Debug, Clone)]
+pub struct RenderedLine {
+    pub speaker: String,
+    pub voice: String,
+    pub samples: Vec<f32>,
+    pub pause_ms: u32,
+}
+
+/// Trim, loudness-match and join rendered lines with their pauses.
+///
+/// The pause after the last line is dropped.
+pub fn stitch_lines(
+    lines: Vec<RenderedLine>,
+    sample_rate: u32,
+    loudness: &LoudnessConfig,
+) -> (Vec<f32>, Vec<LineTiming>) {
+    let silence = SilenceConfig::default();
+    let count = lines.len();
+    let mut output = Vec::new();
+    let mut timings = Vec::with_capacity(count);
+
+    for (index, line) in lines.into_iter().enumerate() {
+        let mut samples = trim_silence(&line.samples, sample_rate, &silence);
+        normalize_loudness(&mut samples, sample_rate, loudness);
+
+        let start = output.len();
+        output.extend_from_slice(&samples);
+        timings.push(LineTiming {
+            index,
+            speaker: line.speaker,
+            voice: line.voice,
+            start_secs: start as f32 / sample_rate as f32,
+            end_secs: output.len() as f32 / sample_rate as f32,
+        });
+
+        if index + 1 < count {
+            let pause = (sample_rate as usize * line.pause_ms as usize) / 1000;
+            output.resize(output.len() + pause, 0.0);
+        }
+    }
+    (output, timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize, amplitude: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (i as f32 * 0.1).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_stitch_lines_with_pauses_and_matched_loudness() {
+        let rate = 16000;
+        let line = |speaker: &str, samples, pause_ms| RenderedLine {
+            speaker: speaker.to_string(),
+            voice: speaker.to_string(),
+            samples,
+            pause_ms,
+        };
+        let lines = vec![
+            line("A", tone(16000, 0.5), 500),
+            line("B", tone(16000, 0.05), 250),
+        ];
+        let (samples, timings) = stitch_lines(lines, rate, &LoudnessConfig::default());
+
+        assert_eq!(samples.len(), 40000);
+        assert_eq!(timings[0].start_secs, 0.0);
+        assert_eq!(timings[0].end_secs, 1.0);
+        assert_eq!(timings[1].start_secs, 1.5);
+        assert_eq!(timings[1].end_secs, 2.5);
+
+        let peak = |s: &[f32]| s.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        let ratio = peak(&samples[..16000]) / peak(&samples[24000..]);
+        assert!((ratio - 1.0).abs() < 0.1, "ratio {}", ratio);
+
+        let script = DialogueRequest {
+            lines: vec![],
+            voices: HashMap::new(),
+            pause_ms: None,
+            target_lufs: None,
+            language: None,
+            model: None,
+        };
+        assert!(script.validate(0).is_err());
+    }
+}
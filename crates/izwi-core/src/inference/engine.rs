@@ -1,54 +1,151 @@
 //! Main inference engine for Qwen3-TTS
 
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio::sync::mpsc;
 use tracing::{info, warn};
 
-use crate::audio::{AudioChunkBuffer, AudioCodec, AudioEncoder, StreamingConfig};
+use crate::audio::{
+    conform_wav, decode_wav, truncate_at_silence, AudioChunkBuffer, AudioCodec, AudioEncoder,
+    AudioFormat, AudioSpool, LoudnessConfig, OutputMemoryStats, OutputMemoryTracker, OutputStore,
+    Resampler, SilenceConfig, SilenceStop, SpooledAudio, StreamingConfig, TOKEN_RATE_HZ,
+};
 use crate::config::EngineConfig;
+use crate::engine::validation;
 use crate::error::{Error, Result};
-use crate::inference::asr_bridge::{AsrBridge, AsrResponse};
+use crate::inference::admission::AdmissionControl;
+use crate::inference::asr_bridge::{AsrBridge, AsrResponse, AsrTask, ASR_SAMPLE_RATE};
+use crate::inference::daemon_client::DaemonClient;
+use crate::inference::dialogue::{stitch_lines, DialogueRequest, DialogueResult, RenderedLine};
 use crate::inference::generation::{
     AudioChunk, GenerationConfig, GenerationRequest, GenerationResult,
 };
 use crate::inference::kv_cache::{KVCache, KVCacheConfig};
-use crate::inference::python_bridge::PythonBridge;
-use crate::model::{ModelInfo, ModelManager, ModelVariant};
+use crate::inference::presets::GenerationPreset;
+use crate::inference::python_bridge::{generate_request, PythonBridge};
+use crate::inference::style;
+use crate::inference::supervisor::DaemonStatus;
+use crate::inference::verify::{word_error_rate, VerificationResult, VerifyConfig};
+use crate::journal::RequestJournal;
+use crate::language::{detect_language, normalize_language, resolve_language, AUTO_LANGUAGE};
+use crate::lexicon::Lexicon;
+use crate::model::{ModelInfo, ModelManager, ModelVariant, Quantization, QuantizeReport};
+use crate::tenant::TenantKeyring;
+use crate::text::{spell, ProfanityFilter, Segmenters};
 use crate::tokenizer::Tokenizer;
+use crate::usage::estimate_prompt_tokens;
+use crate::voice::{ResolvedVoice, VoiceRegistry, VoiceStore};
 
 /// Main TTS inference engine
+///
+/// State that changes after startup (loaded models, tokenizer, codec
+/// weights, lexicon) is locked per component, so the engine is shared
+/// behind an `Arc` and model management never blocks generation.
 pub struct InferenceEngine {
     config: EngineConfig,
     model_manager: Arc<ModelManager>,
-    tokenizer: Option<Tokenizer>,
-    codec: AudioCodec,
+    /// Tokenizer of the last loaded model
+    tokenizer: RwLock<Option<Arc<Tokenizer>>>,
+    /// Replaced when codec weights are loaded
+    codec: RwLock<Arc<AudioCodec>>,
     _kv_cache: KVCache,
     streaming_config: StreamingConfig,
+    output_memory: Arc<OutputMemoryTracker>,
+    output_store: Arc<OutputStore>,
+    admission: Arc<AdmissionControl>,
+    voice_registry: VoiceRegistry,
+    /// Replaced when the lexicon file is reloaded
+    lexicon: RwLock<Arc<Lexicon>>,
+    profanity: Arc<ProfanityFilter>,
+    segmenters: Arc<Segmenters>,
+    keyring: Arc<TenantKeyring>,
+    voice_store: Arc<VoiceStore>,
+    journal: Option<Arc<RequestJournal>>,
     python_bridge: PythonBridge,
     asr_bridge: AsrBridge,
-    loaded_model_path: Option<std::path::PathBuf>,
+    models: RwLock<LoadedModels>,
+}
+
+/// Models loaded for generation
+#[derive(Debug, Default)]
+struct LoadedModels {
+    /// Weights directory of each model
+    paths: HashMap<ModelVariant, PathBuf>,
+    /// Model used when a request does not name one
+    default: Option<ModelVariant>,
 }
 
 impl InferenceEngine {
     /// Create a new inference engine
     pub fn new(config: EngineConfig) -> Result<Self> {
         let model_manager = Arc::new(ModelManager::new(config.clone())?);
-        let codec = AudioCodec::new();
+        let codec = AudioCodec::new()
+            .with_device(config.compute_device())
+            .with_layout_cache(config.layout_cache);
         let kv_cache = KVCache::new(KVCacheConfig::default());
+        let output_memory = Arc::new(OutputMemoryTracker::new(config.max_output_buffer_bytes));
+        let keyring = Arc::new(TenantKeyring::from_config(&config.encryption)?);
+        let output_store = Arc::new(
+            OutputStore::new(config.max_stored_output_bytes).with_keyring(keyring.clone()),
+        );
+        let voice_store = Arc::new(VoiceStore::new(keyring.clone()));
+        let admission = Arc::new(AdmissionControl::new(
+            config.max_waiting_requests,
+            config.max_queued_tokens,
+        ));
+        let voice_registry = VoiceRegistry::with_aliases(&config.voice_aliases)?;
+        let lexicon = Lexicon::load(&config.lexicon_path())?;
+        if !lexicon.is_empty() {
+            info!("Loaded {} pronunciation lexicon entries", lexicon.len());
+        }
+        let profanity = Arc::new(ProfanityFilter::load(&config.profanity_path())?);
+        let segmenters = Arc::new(Segmenters::new(&config.segmentation));
+        let journal = config
+            .journal_path
+            .as_ref()
+            .map(|path| RequestJournal::open(path, config.journal_max_entries).map(Arc::new))
+            .transpose()?;
+
+        let python_bridge = PythonBridge::with_devices(&config.bridge, &config.devices);
+        let asr_bridge = AsrBridge::with_config(&config.bridge);
 
         Ok(Self {
             config,
             model_manager,
-            tokenizer: None,
-            codec,
+            tokenizer: RwLock::new(None),
+            codec: RwLock::new(Arc::new(codec)),
             _kv_cache: kv_cache,
             streaming_config: StreamingConfig::default(),
-            python_bridge: PythonBridge::new(),
-            asr_bridge: AsrBridge::new(),
-            loaded_model_path: None,
+            output_memory,
+            output_store,
+            admission,
+            voice_registry,
+            lexicon: RwLock::new(Arc::new(lexicon)),
+            profanity,
+            segmenters,
+            keyring,
+            voice_store,
+            journal,
+            python_bridge,
+            asr_bridge,
+            models: RwLock::new(LoadedModels::default()),
         })
     }
 
+    fn models(&self) -> RwLockReadGuard<'_, LoadedModels> {
+        self.models.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn models_mut(&self) -> RwLockWriteGuard<'_, LoadedModels> {
+        self.models.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current codec, kept for the whole of a generation
+    fn codec(&self) -> Arc<AudioCodec> {
+        self.codec.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
     /// Get reference to model manager
     pub fn model_manager(&self) -> &Arc<ModelManager> {
         &self.model_manager
@@ -65,8 +162,37 @@ impl InferenceEngine {
         Ok(())
     }
 
+    /// Download a model with a caller-supplied HuggingFace token
+    pub async fn download_model_with_token(
+        &self,
+        variant: ModelVariant,
+        token: Option<String>,
+    ) -> Result<()> {
+        self.model_manager
+            .download_model_with_token(variant, token)
+            .await?;
+        Ok(())
+    }
+
+    /// Write a quantized copy of a downloaded model
+    pub async fn quantize_model(
+        &self,
+        variant: ModelVariant,
+        quant: Quantization,
+    ) -> Result<QuantizeReport> {
+        self.model_manager.quantize_model(variant, quant).await
+    }
+
+    /// Load a model for inference using the weights stored at `quant`
+    pub async fn load_model_with(&self, variant: ModelVariant, quant: Quantization) -> Result<()> {
+        self.model_manager
+            .select_quantization(variant, quant)
+            .await?;
+        self.load_model(variant).await
+    }
+
     /// Load a model for inference
-    pub async fn load_model(&mut self, variant: ModelVariant) -> Result<()> {
+    pub async fn load_model(&self, variant: ModelVariant) -> Result<()> {
         // Ensure model is downloaded
         if !self.model_manager.is_ready(variant).await {
             let info = self.model_manager.get_model_info(variant).await;
@@ -96,7 +222,8 @@ impl InferenceEngine {
             match Tokenizer::from_path(&path) {
                 Ok(tokenizer) => {
                     info!("Loaded tokenizer from {:?}", path);
-                    self.tokenizer = Some(tokenizer);
+                    *self.tokenizer.write().unwrap_or_else(|e| e.into_inner()) =
+                        Some(Arc::new(tokenizer));
                 }
                 Err(e) => {
                     warn!("Failed to load tokenizer: {}. TTS generation may not work until tokenizer files are available.", e);
@@ -112,7 +239,12 @@ impl InferenceEngine {
                 .await
                 .and_then(|i| i.local_path)
             {
-                self.codec.load_weights(&path)?;
+                // Requests in flight keep the codec they started with
+                let mut codec = AudioCodec::new()
+                    .with_device(self.config.compute_device())
+                    .with_layout_cache(self.config.layout_cache);
+                codec.load_weights(&path)?;
+                *self.codec.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(codec);
             }
         }
 
@@ -123,35 +255,100 @@ impl InferenceEngine {
             .await
             .and_then(|i| i.local_path)
         {
-            self.loaded_model_path = Some(path);
+            if !variant.is_tokenizer() {
+                let mut models = self.models_mut();
+                models.paths.insert(variant, path);
+                models.default = Some(variant);
+            }
         }
 
         Ok(())
     }
 
+    /// Unload a model and stop routing requests to it
+    pub async fn unload_model(&self, variant: ModelVariant) -> Result<()> {
+        self.model_manager.unload_model(variant).await?;
+        let mut models = self.models_mut();
+        models.paths.remove(&variant);
+        if models.default == Some(variant) {
+            models.default = models.ordered().first().copied();
+        }
+        Ok(())
+    }
+
+    /// Models currently loaded for generation
+    pub fn loaded_models(&self) -> Vec<ModelVariant> {
+        self.models().ordered()
+    }
+
+    /// Weights directory serving `model`, or the default model when unset
+    fn model_path_for(&self, model: Option<ModelVariant>) -> Result<PathBuf> {
+        let models = self.models();
+        match model {
+            Some(variant) => {
+                models.paths.get(&variant).cloned().ok_or_else(|| {
+                    Error::ModelNotLoaded(format!("Model {} is not loaded", variant))
+                })
+            }
+            None => models
+                .default
+                .and_then(|v| models.paths.get(&v))
+                .cloned()
+                .ok_or_else(|| Error::ModelNotLoaded("No model loaded".to_string())),
+        }
+    }
+
     /// Generate audio from text (non-streaming)
-    pub async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+    pub async fn generate(&self, mut request: GenerationRequest) -> Result<GenerationResult> {
         let start_time = std::time::Instant::now();
+        self.validate_input(&mut request)?;
+        let _permit = self
+            .admission
+            .admit(estimate_prompt_tokens(&request.text))?;
+        let voice = self.resolve_speaker(&mut request)?;
+        let language = Self::resolve_language(&mut request);
+        self.filter_profanity(&mut request)?;
+        self.apply_pronunciations(&mut request)?;
+        Self::apply_spell_out(&mut request);
+        self.validate_style(&request)?;
 
         // Get model path
-        let model_path = self
-            .loaded_model_path
-            .as_ref()
-            .ok_or_else(|| Error::InferenceError("No model loaded".to_string()))?;
+        let model_path = self.model_path_for(request.model)?;
 
-        info!("Generating TTS for: {}", request.text);
+        info!(
+            "Generating TTS for: {}",
+            self.config.privacy.redact(&request.text)
+        );
 
         // Use Python bridge for actual inference
-        // voice_description is passed as instruct for VoiceDesign models
-        let (samples, sample_rate) = self.python_bridge.generate_with_clone(
-            model_path,
-            &request.text,
-            request.config.speaker.as_deref(),
-            Some("Auto"),                         // language
-            request.voice_description.as_deref(), // instruct (used for voice design)
-            request.reference_audio,
-            request.reference_text,
-        )?;
+        let instruction = request.instruction();
+        let (mut samples, sample_rate) = self
+            .python_bridge
+            .generate_with_clone(
+                &model_path,
+                &request.text,
+                request.config.speaker.as_deref(),
+                Some(&language),
+                instruction.as_deref(),
+                request.reference_audio,
+                request.reference_text,
+            )
+            .await?;
+        // The daemon returns the whole take, so dead air is cut afterwards
+        let stop_ms = request.config.stop_on_silence_ms;
+        if stop_ms > 0
+            && truncate_at_silence(
+                &mut samples,
+                sample_rate,
+                stop_ms,
+                &SilenceConfig::default(),
+            )
+        {
+            info!("Cut generation at {}ms of silence", stop_ms);
+        }
+        if let Some(limit) = audio_sample_limit(&request.config, sample_rate) {
+            samples.truncate(limit);
+        }
 
         let total_time_ms = start_time.elapsed().as_secs_f32() * 1000.0;
         let num_samples = samples.len();
@@ -167,23 +364,352 @@ impl InferenceEngine {
             sample_rate,
             total_tokens: num_samples / 256, // approximate
             total_time_ms,
+            voice: voice.as_ref().map(|v| v.name.clone()),
+            language: (language != AUTO_LANGUAGE).then_some(language),
+            warnings: voice.and_then(|v| v.warning).into_iter().collect(),
+            verification: None,
         })
     }
 
+    /// Synthesize a dialogue script line by line and stitch it into one output
+    pub async fn generate_dialogue(&self, request: DialogueRequest) -> Result<DialogueResult> {
+        request.validate(self.config.max_dialogue_lines)?;
+        let start_time = std::time::Instant::now();
+        let default_pause_ms = request.pause_ms.unwrap_or(self.config.dialogue_pause_ms);
+
+        let mut rendered = Vec::with_capacity(request.lines.len());
+        let mut warnings = Vec::new();
+        let mut sample_rate = self.sample_rate();
+        for line in &request.lines {
+            let voice = request.voice_for(&line.speaker);
+            let mut line_request = GenerationRequest::new(line.text.clone()).with_speaker(voice);
+            line_request.model = request.model;
+            line_request.language = request.language.clone();
+
+            let result = self.generate(line_request).await?;
+            sample_rate = result.sample_rate;
+            warnings.extend(result.warnings);
+            rendered.push(RenderedLine {
+                speaker: line.speaker.clone(),
+                voice: result.voice.unwrap_or_else(|| voice.to_string()),
+                samples: result.samples,
+                pause_ms: line.pause_ms.unwrap_or(default_pause_ms),
+            });
+        }
+
+        let loudness = request
+            .target_lufs
+            .map(LoudnessConfig::with_target)
+            .unwrap_or_default();
+        let (samples, lines) = stitch_lines(rendered, sample_rate, &loudness);
+        info!(
+            "Rendered dialogue of {} lines in {:.1}s",
+            lines.len(),
+            start_time.elapsed().as_secs_f32()
+        );
+
+        Ok(DialogueResult {
+            samples,
+            sample_rate,
+            lines,
+            total_time_ms: start_time.elapsed().as_secs_f32() * 1000.0,
+            warnings,
+        })
+    }
+
+    /// Generate audio and check it by transcribing the output.
+    ///
+    /// If the word error rate exceeds the threshold, generation is retried
+    /// with a lower temperature. When all attempts fail, the attempt with the
+    /// lowest WER is returned with `verification.passed == false`.
+    pub async fn generate_verified(
+        &self,
+        request: GenerationRequest,
+        verify: &VerifyConfig,
+    ) -> Result<GenerationResult> {
+        let mut attempt_request = request.clone();
+        let mut best: Option<GenerationResult> = None;
+        let mut total_time_ms = 0.0;
+        let mut attempts = 0;
+
+        for attempt in 1..=verify.max_retries + 1 {
+            attempts = attempt;
+            let mut result = self.generate(attempt_request.clone()).await?;
+            total_time_ms += result.total_time_ms;
+
+            let transcript = self
+                .transcribe_samples(&result.samples, result.sample_rate, verify)
+                .await?;
+            let wer = word_error_rate(&request.text, &transcript);
+            let passed = wer <= verify.wer_threshold;
+            info!(
+                "Verification attempt {}: WER {:.3} (threshold {:.3})",
+                attempt, wer, verify.wer_threshold
+            );
+
+            result.verification = Some(VerificationResult {
+                wer,
+                transcript,
+                attempts,
+                passed,
+            });
+
+            let is_better = best
+                .as_ref()
+                .and_then(|b| b.verification.as_ref())
+                .map(|v| wer < v.wer)
+                .unwrap_or(true);
+            if is_better {
+                best = Some(result);
+            }
+            if passed {
+                break;
+            }
+
+            attempt_request.config.temperature =
+                (attempt_request.config.temperature * verify.temperature_decay).max(0.1);
+        }
+
+        let mut result = best.ok_or_else(|| {
+            Error::InferenceError("Verification produced no generation".to_string())
+        })?;
+        if let Some(verification) = result.verification.as_mut() {
+            verification.attempts = attempts;
+            if !verification.passed {
+                warn!(
+                    "Output failed verification after {} attempts (WER {:.3})",
+                    verification.attempts, verification.wer
+                );
+                result.warnings.push(format!(
+                    "Transcript verification failed (WER {:.2})",
+                    verification.wer
+                ));
+            }
+        }
+        result.total_time_ms = total_time_ms;
+        Ok(result)
+    }
+
+    /// Run generated samples through the ASR daemon
+    async fn transcribe_samples(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        verify: &VerifyConfig,
+    ) -> Result<String> {
+        use base64::Engine as _;
+
+        let wav = AudioEncoder::new(sample_rate, 1).encode(samples, AudioFormat::Wav)?;
+        let audio_b64 = base64::engine::general_purpose::STANDARD.encode(wav);
+        let response = self
+            .asr_transcribe(&audio_b64, verify.asr_model.as_deref(), None)
+            .await?;
+
+        if let Some(err) = response.error {
+            return Err(Error::InferenceError(format!(
+                "Verification transcription failed: {}",
+                err
+            )));
+        }
+        Ok(response.transcription.unwrap_or_default())
+    }
+
+    /// Rewrite the requested speaker to its canonical voice name
+    fn resolve_speaker(&self, request: &mut GenerationRequest) -> Result<Option<ResolvedVoice>> {
+        let Some(speaker) = request.config.speaker.as_deref() else {
+            return Ok(None);
+        };
+        let resolved = self.voice_registry.resolve(speaker)?;
+        if resolved.was_aliased() {
+            info!(
+                "Resolved voice '{}' -> '{}'",
+                resolved.requested, resolved.name
+            );
+        }
+        request.config.speaker = Some(resolved.name.clone());
+        Ok(Some(resolved))
+    }
+
+    /// Fill in the request language, detecting it from the text when unset
+    fn resolve_language(request: &mut GenerationRequest) -> String {
+        let language = resolve_language(request.language.as_deref(), &request.text);
+        if request.language.is_none() && language != AUTO_LANGUAGE {
+            info!("Detected language: {}", language);
+        }
+        request.language = Some(language.clone());
+        language
+    }
+
+    /// Refuse a style or instruction the request's model cannot follow
+    fn validate_style(&self, request: &GenerationRequest) -> Result<()> {
+        let Some(model) = request.model.or(self.models().default) else {
+            return Ok(());
+        };
+        style::validate(
+            model,
+            request.style,
+            request.instruct.as_deref(),
+            request.reference_audio.is_some(),
+        )
+    }
+
+    /// Apply the request's profanity filter to its text
+    /// Normalize the request's text fields and check them and its reference
+    /// audio against the input limits
+    fn validate_input(&self, request: &mut GenerationRequest) -> Result<()> {
+        let limits = &self.config.limits;
+        request.text = validation::check_text(&request.text, "text", limits)?;
+        for (field, value) in [
+            ("reference_text", &mut request.reference_text),
+            ("voice_description", &mut request.voice_description),
+            ("instruct", &mut request.instruct),
+        ] {
+            if let Some(text) = value.as_mut() {
+                *text = validation::check_text(text, field, limits)?;
+            }
+        }
+        if let Some(audio) = &request.reference_audio {
+            validation::check_audio(audio, "reference_audio", limits)?;
+        }
+        Ok(())
+    }
+
+    fn filter_profanity(&self, request: &mut GenerationRequest) -> Result<()> {
+        if let Some(mode) = request.profanity {
+            request.text = self.profanity.filter_speech(&request.text, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the request text using the lexicon and its own pronunciations
+    fn apply_pronunciations(&self, request: &mut GenerationRequest) -> Result<()> {
+        let lexicon = self.lexicon();
+        if lexicon.is_empty() && request.pronunciations.is_empty() {
+            return Ok(());
+        }
+        request.text = lexicon.apply_with(&request.text, &request.pronunciations)?;
+        request.pronunciations.clear();
+        Ok(())
+    }
+
+    /// Spell out codes in the request text, after the lexicon so its
+    /// entries win, using letter names for the resolved language
+    fn apply_spell_out(request: &mut GenerationRequest) {
+        let language = request.language.as_deref().unwrap_or(AUTO_LANGUAGE);
+        request.text = spell::spell_out(&request.text, request.spell_out, language);
+    }
+
+    /// Word list behind the TTS and ASR profanity filters
+    pub fn profanity(&self) -> &Arc<ProfanityFilter> {
+        &self.profanity
+    }
+
+    /// Sampling settings for a request to `model` (the default model when
+    /// unset) naming `preset`: the model's defaults with the preset over them
+    pub fn generation_config(
+        &self,
+        model: Option<ModelVariant>,
+        preset: Option<&str>,
+    ) -> Result<GenerationConfig> {
+        let mut config = GenerationConfig::default();
+        if let Some(model) = model.or(self.models().default) {
+            self.config.model_defaults(model).apply_to(&mut config);
+        }
+        if let Some(name) = preset {
+            let preset = self.config.preset(name).ok_or_else(|| {
+                let known: Vec<String> = self.presets().into_keys().collect();
+                Error::InvalidInput(format!(
+                    "Unknown preset '{}' (available: {})",
+                    name,
+                    known.join(", ")
+                ))
+            })?;
+            preset.apply_to(&mut config);
+        }
+        Ok(config)
+    }
+
+    /// Presets requests can name, configured and built in
+    pub fn presets(&self) -> BTreeMap<String, GenerationPreset> {
+        self.config.all_presets()
+    }
+
+    /// Sentence segmenters used to split text into subtitle cues; register
+    /// one to replace the built-in rules for a language
+    pub fn segmenters(&self) -> &Arc<Segmenters> {
+        &self.segmenters
+    }
+
+    /// Pronunciation lexicon applied to every request
+    pub fn lexicon(&self) -> Arc<Lexicon> {
+        self.lexicon
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Re-read the lexicon file, returning the number of entries
+    pub fn reload_lexicon(&self) -> Result<usize> {
+        let lexicon = Lexicon::load(&self.config.lexicon_path())?;
+        let entries = lexicon.len();
+        *self.lexicon.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(lexicon);
+        info!("Reloaded {} pronunciation lexicon entries", entries);
+        Ok(entries)
+    }
+
+    /// Request journal, when `journal_path` is configured
+    pub fn journal(&self) -> Option<&Arc<RequestJournal>> {
+        self.journal.as_ref()
+    }
+
+    /// Voice catalog and alias table
+    pub fn voice_registry(&self) -> &VoiceRegistry {
+        &self.voice_registry
+    }
+
     /// Generate audio with streaming output
     pub async fn generate_streaming(
         &self,
-        request: GenerationRequest,
+        mut request: GenerationRequest,
         chunk_tx: mpsc::Sender<AudioChunk>,
     ) -> Result<()> {
+        self.validate_input(&mut request)?;
+        // Held until the last chunk is sent
+        let _permit = self
+            .admission
+            .admit(estimate_prompt_tokens(&request.text))?;
+        self.resolve_speaker(&mut request)?;
+        Self::resolve_language(&mut request);
+        self.filter_profanity(&mut request)?;
+        self.apply_pronunciations(&mut request)?;
+        Self::apply_spell_out(&mut request);
+        self.validate_style(&request)?;
+
+        // Models served by the Python daemon stream its audio frames
+        if let Ok(model_path) = self.model_path_for(request.model) {
+            return self
+                .stream_from_daemon(&model_path, request, chunk_tx)
+                .await;
+        }
+
         let tokenizer = self
             .tokenizer
-            .as_ref()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
             .ok_or_else(|| Error::InferenceError("No tokenizer loaded".to_string()))?;
 
         // Tokenize input text
-        let prompt = tokenizer.format_tts_prompt(&request.text, request.config.speaker.as_deref());
+        let prompt = tokenizer.format_tts_prompt(
+            &request.text,
+            request.config.speaker.as_deref(),
+            request.language.as_deref(),
+        );
         let input_tokens = tokenizer.encode(&prompt)?;
+        let reference_tokens = match &request.reference_audio {
+            Some(audio) => self.encode_audio(audio)?,
+            None => Vec::new(),
+        };
 
         info!(
             "Starting streaming generation for {} input tokens",
@@ -191,17 +717,32 @@ impl InferenceEngine {
         );
 
         // Create streaming buffer
-        let mut buffer =
-            AudioChunkBuffer::new(self.streaming_config.clone(), self.codec.sample_rate());
+        let codec = self.codec();
+        let mut buffer = AudioChunkBuffer::new(self.streaming_config.clone(), codec.sample_rate())
+            .with_memory_tracker(self.output_memory.clone());
 
         let mut sequence = 0;
-        let mut audio_tokens: Vec<Vec<u32>> = vec![Vec::new(); self.codec.config().num_codebooks];
+        let mut audio_tokens: Vec<Vec<u32>> = vec![Vec::new(); codec.config().num_codebooks];
+        let mut decoder = codec.decoder_state();
+        let mut silence = silence_stop(&request.config, codec.sample_rate());
 
-        // Generate tokens incrementally
-        for _step in 0..request.config.max_tokens {
+        // Generate tokens incrementally, within the audio length limit
+        let max_steps = match request.config.max_audio_seconds {
+            Some(secs) => request
+                .config
+                .max_tokens
+                .min((secs * TOKEN_RATE_HZ) as usize),
+            None => request.config.max_tokens,
+        };
+        for _step in 0..max_steps {
             // Generate next audio token(s)
             let next_tokens = self
-                .generate_next_token(&input_tokens, &audio_tokens, &request.config)
+                .generate_next_token(
+                    &input_tokens,
+                    &reference_tokens,
+                    &audio_tokens,
+                    &request.config,
+                )
                 .await?;
 
             // Add to token buffer
@@ -219,11 +760,12 @@ impl InferenceEngine {
 
             // Decode and stream when buffer is ready
             if buffer.ready_to_stream() {
-                let chunk_tokens: Vec<Vec<u32>> =
-                    audio_tokens.iter().map(|cb| cb.clone()).collect();
-
-                let samples = self.codec.decode(&chunk_tokens)?;
-                buffer.push_samples(&samples);
+                let samples = codec.decode_chunk(&mut decoder, &audio_tokens)?;
+                buffer.push_samples(&samples)?;
+                if silence.as_mut().is_some_and(|s| s.push(&samples).is_some()) {
+                    info!("Stopping generation on silence");
+                    break;
+                }
 
                 while let Some(chunk_samples) = buffer.take_chunk() {
                     let chunk = AudioChunk::new(request.id.clone(), sequence, chunk_samples);
@@ -237,7 +779,9 @@ impl InferenceEngine {
             }
         }
 
-        // Send remaining samples
+        // Decode columns generated since the last chunk, then send what's left
+        let samples = codec.decode_chunk(&mut decoder, &audio_tokens)?;
+        buffer.push_samples(&samples)?;
         let remaining = buffer.take_remaining();
         if !remaining.is_empty() {
             let chunk = AudioChunk::final_chunk(request.id.clone(), sequence, remaining);
@@ -248,6 +792,103 @@ impl InferenceEngine {
         Ok(())
     }
 
+    /// Generate audio into a WAV file in the spool directory, holding one
+    /// streaming chunk in memory at a time. Used for long-form jobs whose
+    /// assembled output would not fit comfortably in RAM.
+    pub async fn generate_spooled(&self, request: GenerationRequest) -> Result<SpooledAudio> {
+        let mut spool =
+            AudioSpool::create(&self.config.spool_dir, &request.id, self.sample_rate())?;
+        let (chunk_tx, mut chunk_rx) = mpsc::channel(32);
+
+        let generation = self.generate_streaming(request, chunk_tx);
+        let write = async {
+            while let Some(chunk) = chunk_rx.recv().await {
+                // Dropping the receiver stops the generation
+                spool.write(&chunk.samples)?;
+            }
+            Ok::<_, Error>(())
+        };
+
+        let (generated, written) = tokio::join!(generation, write);
+        generated?;
+        written?;
+        spool.finish()
+    }
+
+    /// Stream daemon audio frames through a chunk buffer at the engine's
+    /// sample rate, ending with a final (possibly empty) chunk
+    async fn stream_from_daemon(
+        &self,
+        model_path: &Path,
+        request: GenerationRequest,
+        chunk_tx: mpsc::Sender<AudioChunk>,
+    ) -> Result<()> {
+        let daemon_request = generate_request(
+            model_path,
+            &request.text,
+            request.config.speaker.as_deref(),
+            request.language.as_deref(),
+            request.instruction().as_deref(),
+            request.reference_audio.clone(),
+            request.reference_text.clone(),
+        );
+        let mut frames = self
+            .python_bridge
+            .generate_stream(daemon_request, self.streaming_config.chunk_duration_ms)
+            .await?;
+
+        let sample_rate = self.codec().sample_rate();
+        let mut buffer = AudioChunkBuffer::new(self.streaming_config.clone(), sample_rate)
+            .with_memory_tracker(self.output_memory.clone());
+        let mut resampler: Option<Resampler> = None;
+        let mut sequence = 0;
+        let mut silence = silence_stop(&request.config, sample_rate);
+        let mut remaining = audio_sample_limit(&request.config, sample_rate);
+
+        while let Some(samples) = frames.next_samples().await? {
+            let resampler = match resampler.as_mut() {
+                Some(resampler) => resampler,
+                None => resampler.insert(Resampler::new(
+                    frames.sample_rate().unwrap_or(sample_rate),
+                    sample_rate,
+                )?),
+            };
+            let mut samples = resampler.process(&samples)?;
+            let at_limit = remaining.as_mut().is_some_and(|remaining| {
+                samples.truncate(*remaining);
+                *remaining -= samples.len();
+                *remaining == 0
+            });
+            buffer.push_samples(&samples)?;
+            if at_limit {
+                info!("Stopping generation at max_audio_seconds");
+                break;
+            }
+            // Dropping the frame stream ends the daemon's generation
+            if silence.as_mut().is_some_and(|s| s.push(&samples).is_some()) {
+                info!("Stopping generation on silence");
+                break;
+            }
+
+            while let Some(chunk_samples) = buffer.take_chunk() {
+                let chunk = AudioChunk::new(request.id.clone(), sequence, chunk_samples);
+                sequence += 1;
+                if chunk_tx.send(chunk).await.is_err() {
+                    warn!("Streaming channel closed");
+                    return Ok(());
+                }
+            }
+        }
+
+        if let Some(resampler) = resampler.as_mut() {
+            buffer.push_samples(&resampler.flush()?)?;
+        }
+        let chunk = AudioChunk::final_chunk(request.id.clone(), sequence, buffer.take_remaining());
+        let _ = chunk_tx.send(chunk).await;
+        info!("Streaming generation complete ({} chunks)", sequence + 1);
+        Ok(())
+    }
+
     /// Generate audio tokens from input tokens
     #[allow(dead_code)]
     async fn generate_audio_tokens(
@@ -257,7 +898,7 @@ impl InferenceEngine {
     ) -> Result<Vec<Vec<u32>>> {
         // Placeholder: Generate dummy tokens
         // In real implementation, this runs the transformer forward pass
-        let num_codebooks = self.codec.config().num_codebooks;
+        let num_codebooks = self.codec().config().num_codebooks;
         let num_tokens = config.max_tokens.min(256);
 
         let mut audio_tokens = Vec::with_capacity(num_codebooks);
@@ -275,12 +916,13 @@ impl InferenceEngine {
     async fn generate_next_token(
         &self,
         _input_tokens: &[u32],
+        _reference_tokens: &[Vec<u32>],
         _audio_tokens: &[Vec<u32>],
         _config: &GenerationConfig,
     ) -> Result<Vec<u32>> {
         // Placeholder: Generate single token per codebook
         // In real implementation, this runs incremental inference
-        let num_codebooks = self.codec.config().num_codebooks;
+        let num_codebooks = self.codec().config().num_codebooks;
         let tokens: Vec<u32> = (0..num_codebooks)
             .map(|_i| (rand_u32() % 4096) as u32)
             .collect();
@@ -291,6 +933,22 @@ impl InferenceEngine {
         Ok(tokens)
     }
 
+    /// Tokenize base64 WAV audio (voice-cloning references, audio prompts)
+    /// with the native codec encoder
+    pub fn encode_audio(&self, audio_base64: &str) -> Result<Vec<Vec<u32>>> {
+        use base64::Engine;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(audio_base64)
+            .map_err(|e| Error::InvalidAudio(e.to_string()))?;
+        let (samples, sample_rate) = decode_wav(&bytes)?;
+        self.codec().encode(&samples, sample_rate)
+    }
+
+    /// Whether audio can be tokenized without the Python daemon
+    pub fn has_native_encoder(&self) -> bool {
+        self.codec().has_encoder()
+    }
+
     /// Check if generation should end
     fn is_end_of_audio(&self, audio_tokens: &[Vec<u32>]) -> bool {
         // Check for end token or maximum length
@@ -309,12 +967,62 @@ impl InferenceEngine {
 
     /// Get codec sample rate
     pub fn sample_rate(&self) -> u32 {
-        self.codec.sample_rate()
+        self.codec().sample_rate()
+    }
+
+    /// Memory currently held in streaming output buffers
+    pub fn output_memory_stats(&self) -> OutputMemoryStats {
+        self.output_memory.stats()
+    }
+
+    /// Tracker of streaming output buffers, holding the global cap
+    pub fn output_memory(&self) -> Arc<OutputMemoryTracker> {
+        self.output_memory.clone()
+    }
+
+    /// Caps on the generations running at once
+    pub fn admission(&self) -> &Arc<AdmissionControl> {
+        &self.admission
+    }
+
+    /// Completed outputs kept for range extraction
+    pub fn output_store(&self) -> &Arc<OutputStore> {
+        &self.output_store
+    }
+
+    /// Cloned voices saved by clients
+    pub fn voice_store(&self) -> &Arc<VoiceStore> {
+        &self.voice_store
+    }
+
+    /// Rotate a tenant's key and re-encrypt its stored data, returning the new key version
+    pub fn rotate_tenant_key(&self, tenant: &str, key: Option<&str>) -> Result<u32> {
+        let version = self.keyring.rotate(tenant, key)?;
+        let outputs = self.output_store.reseal_tenant(tenant)?;
+        let voices = self.voice_store.reseal_tenant(tenant)?;
+        self.keyring.retire_old_keys(tenant);
+        info!(
+            "Re-encrypted {} outputs and {} voices for tenant '{}'",
+            outputs, voices, tenant
+        );
+        Ok(version)
+    }
+
+    /// Delete all stored data and keys of a tenant, returning (outputs, voices) removed
+    pub fn purge_tenant(&self, tenant: &str) -> (usize, usize) {
+        let outputs = self.output_store.purge_tenant(tenant);
+        let voices = self.voice_store.purge_tenant(tenant);
+        self.keyring.forget(tenant);
+        info!(
+            "Purged {} outputs and {} voices for tenant '{}'",
+            outputs, voices, tenant
+        );
+        (outputs, voices)
     }
 
     /// Create audio encoder
     pub fn audio_encoder(&self) -> AudioEncoder {
-        AudioEncoder::new(self.codec.sample_rate(), 1)
+        AudioEncoder::new(self.codec().sample_rate(), 1)
     }
 
     /// Ensure the TTS daemon is running
@@ -328,14 +1036,15 @@ impl InferenceEngine {
     }
 
     /// Get daemon status
-    pub fn get_daemon_status(&self) -> Result<super::python_bridge::PythonTTSResponse> {
-        self.python_bridge.get_status()
+    pub async fn get_daemon_status(&self) -> Result<super::python_bridge::PythonTTSResponse> {
+        self.python_bridge.get_status().await
     }
 
     /// Preload a model into the daemon cache
-    pub fn preload_model(&self, model_path: &str) -> Result<()> {
+    pub async fn preload_model(&self, model_path: &str) -> Result<()> {
         self.python_bridge
             .preload_model(std::path::Path::new(model_path))
+            .await
     }
 
     // ============ Qwen3-ASR Methods ============
@@ -351,18 +1060,84 @@ impl InferenceEngine {
     }
 
     /// Get ASR daemon status
-    pub fn get_asr_daemon_status(&self) -> Result<AsrResponse> {
-        self.asr_bridge.get_status()
+    pub async fn get_asr_daemon_status(&self) -> Result<AsrResponse> {
+        self.asr_bridge.get_status().await
     }
 
     /// Transcribe audio with Qwen3-ASR
-    pub fn asr_transcribe(
+    pub async fn asr_transcribe(
+        &self,
+        audio_base64: &str,
+        model_id: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<AsrResponse> {
+        self.asr_run(audio_base64, model_id, language, AsrTask::Transcribe)
+            .await
+    }
+
+    /// Transcribe audio with Qwen3-ASR and translate it to English
+    pub async fn asr_translate(
+        &self,
+        audio_base64: &str,
+        model_id: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<AsrResponse> {
+        self.asr_run(audio_base64, model_id, language, AsrTask::Translate)
+            .await
+    }
+
+    async fn asr_run(
         &self,
         audio_base64: &str,
         model_id: Option<&str>,
         language: Option<&str>,
+        task: AsrTask,
     ) -> Result<AsrResponse> {
-        self.asr_bridge.transcribe(audio_base64, model_id, language)
+        use base64::Engine;
+        validation::check_audio(audio_base64, "audio", &self.config.limits)?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(audio_base64)
+            .map_err(|e| Error::InvalidAudio(format!("Invalid base64 audio: {}", e)))?;
+        let conformed = conform_wav(&bytes, ASR_SAMPLE_RATE)?
+            .map(|wav| base64::engine::general_purpose::STANDARD.encode(wav));
+        let audio_base64 = conformed.as_deref().unwrap_or(audio_base64);
+        let language = language.map(normalize_language);
+        let language = language.as_deref();
+        let mut response = match task {
+            AsrTask::Transcribe => {
+                self.asr_bridge
+                    .transcribe(audio_base64, model_id, language)
+                    .await?
+            }
+            AsrTask::Translate => {
+                self.asr_bridge
+                    .translate(audio_base64, model_id, language)
+                    .await?
+            }
+        };
+        if response.language.is_none() {
+            response.language = response
+                .transcription
+                .as_deref()
+                .and_then(detect_language)
+                .map(|d| d.name.to_string());
+        }
+        Ok(response)
+    }
+
+    /// Pooled async connections to the TTS daemon
+    pub fn tts_client(&self) -> &DaemonClient {
+        self.python_bridge.client()
+    }
+
+    /// Pooled async connections to the ASR daemon
+    pub fn asr_client(&self) -> &DaemonClient {
+        self.asr_bridge.client()
+    }
+
+    /// Health of the TTS and ASR daemons
+    pub fn daemon_health(&self) -> Vec<DaemonStatus> {
+        vec![self.python_bridge.health(), self.asr_bridge.health()]
     }
 
     /// Stop all daemons (TTS, ASR)
@@ -373,6 +1148,35 @@ impl InferenceEngine {
     }
 }
 
+impl LoadedModels {
+    /// Loaded models in catalog order
+    fn ordered(&self) -> Vec<ModelVariant> {
+        ModelVariant::all()
+            .iter()
+            .copied()
+            .filter(|v| self.paths.contains_key(v))
+            .collect()
+    }
+}
+
+/// Most samples a request may return, when it set `max_audio_seconds`
+fn audio_sample_limit(config: &GenerationConfig, sample_rate: u32) -> Option<usize> {
+    config
+        .max_audio_seconds
+        .map(|secs| (secs.max(0.0) * sample_rate as f32) as usize)
+}
+
+/// Silence detector for a streamed request, when it asked to stop on silence
+fn silence_stop(config: &GenerationConfig, sample_rate: u32) -> Option<SilenceStop> {
+    (config.stop_on_silence_ms > 0).then(|| {
+        SilenceStop::new(
+            config.stop_on_silence_ms,
+            sample_rate,
+            &SilenceConfig::default(),
+        )
+    })
+}
+
 // Simple pseudo-random number generator for placeholder
 fn rand_u32() -> u32 {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -1,8 +1,17 @@
 //! Generation configuration and output types
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::style::{self, SpeechStyle};
+use super::verify::VerificationResult;
+use crate::audio::SampleBuffer;
+use crate::engine::Priority;
+use crate::lexicon::Pronunciation;
+use crate::model::ModelVariant;
+use crate::text::{SpeechFilter, SpellOut};
+
 /// Configuration for audio generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerationConfig {
@@ -37,6 +46,15 @@ pub struct GenerationConfig {
     /// Speed factor (1.0 = normal)
     #[serde(default = "default_speed")]
     pub speed: f32,
+
+    /// End generation after this much silence following speech, in
+    /// milliseconds (0 = run until the model stops or `max_tokens`)
+    #[serde(default)]
+    pub stop_on_silence_ms: u32,
+
+    /// Longest audio to generate, in seconds (no limit when unset)
+    #[serde(default)]
+    pub max_audio_seconds: Option<f32>,
 }
 
 fn default_temperature() -> f32 {
@@ -69,6 +87,8 @@ impl Default for GenerationConfig {
             streaming: default_streaming(),
             speaker: None,
             speed: default_speed(),
+            stop_on_silence_ms: 0,
+            max_audio_seconds: None,
         }
     }
 }
@@ -98,6 +118,42 @@ pub struct GenerationRequest {
     /// Voice description for voice design models
     #[serde(default)]
     pub voice_description: Option<String>,
+
+    /// Speaking style or emotion (instruction-following models only)
+    #[serde(default)]
+    pub style: Option<SpeechStyle>,
+
+    /// Free-text direction on how to speak, e.g. "slowly, with pauses"
+    #[serde(default)]
+    pub instruct: Option<String>,
+
+    /// Loaded model to generate with (the most recently loaded when unset)
+    #[serde(default)]
+    pub model: Option<ModelVariant>,
+
+    /// Client identity (API key name) the request is made for
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// Scheduling priority (bounded per API key by the server)
+    #[serde(default)]
+    pub priority: Priority,
+
+    /// Language of the text (detected from it when unset)
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// One-off pronunciations overriding the lexicon for this request
+    #[serde(default)]
+    pub pronunciations: HashMap<String, Pronunciation>,
+
+    /// Reject or bleep profanity in the text
+    #[serde(default)]
+    pub profanity: Option<SpeechFilter>,
+
+    /// Read codes like "A4-7Z" character by character
+    #[serde(default)]
+    pub spell_out: SpellOut,
 }
 
 fn generate_request_id() -> String {
@@ -113,9 +169,33 @@ impl GenerationRequest {
             reference_audio: None,
             reference_text: None,
             voice_description: None,
+            style: None,
+            instruct: None,
+            model: None,
+            client_id: None,
+            priority: Priority::Normal,
+            language: None,
+            pronunciations: HashMap::new(),
+            profanity: None,
+            spell_out: SpellOut::Off,
         }
     }
 
+    pub fn with_model(mut self, model: ModelVariant) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
     pub fn with_config(mut self, config: GenerationConfig) -> Self {
         self.config = config;
         self
@@ -125,6 +205,16 @@ impl GenerationRequest {
         self.config.speaker = Some(speaker.into());
         self
     }
+
+    /// Instruction prompt for the model, combining the voice description,
+    /// style and free-text instruction
+    pub fn instruction(&self) -> Option<String> {
+        style::compose(
+            self.voice_description.as_deref(),
+            self.style,
+            self.instruct.as_deref(),
+        )
+    }
 }
 
 /// A chunk of generated audio
@@ -136,8 +226,8 @@ pub struct AudioChunk {
     /// Chunk sequence number
     pub sequence: usize,
 
-    /// Audio samples (f32, mono)
-    pub samples: Vec<f32>,
+    /// Audio samples (f32, mono), shared rather than copied downstream
+    pub samples: SampleBuffer,
 
     /// Whether this is the final chunk
     pub is_final: bool,
@@ -147,21 +237,25 @@ pub struct AudioChunk {
 }
 
 impl AudioChunk {
-    pub fn new(request_id: String, sequence: usize, samples: Vec<f32>) -> Self {
+    pub fn new(request_id: String, sequence: usize, samples: impl Into<SampleBuffer>) -> Self {
         Self {
             request_id,
             sequence,
-            samples,
+            samples: samples.into(),
             is_final: false,
             stats: None,
         }
     }
 
-    pub fn final_chunk(request_id: String, sequence: usize, samples: Vec<f32>) -> Self {
+    pub fn final_chunk(
+        request_id: String,
+        sequence: usize,
+        samples: impl Into<SampleBuffer>,
+    ) -> Self {
         Self {
             request_id,
             sequence,
-            samples,
+            samples: samples.into(),
             is_final: true,
             stats: None,
         }
@@ -192,6 +286,14 @@ pub struct GenerationResult {
     pub sample_rate: u32,
     pub total_tokens: usize,
     pub total_time_ms: f32,
+    /// Voice actually used after alias resolution
+    pub voice: Option<String>,
+    /// Language requested from the model (unset when left to the model)
+    pub language: Option<String>,
+    /// Non-fatal notices for the client (e.g. deprecated voice names)
+    pub warnings: Vec<String>,
+    /// Transcription check, when verification was requested
+    pub verification: Option<VerificationResult>,
 }
 
 impl GenerationResult {
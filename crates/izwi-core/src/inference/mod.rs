@@ -1,13 +1,31 @@
 //! Inference engine for Qwen3-TTS and Qwen3-ASR
 
+mod admission;
 pub mod asr_bridge;
+pub mod daemon_client;
+mod dialogue;
 mod engine;
 mod generation;
 mod kv_cache;
+pub mod presets;
 pub mod python_bridge;
+pub mod style;
+pub mod supervisor;
+pub mod transport;
+mod verify;
 
-pub use asr_bridge::{AsrBridge, AsrResponse};
+pub use admission::{AdmissionControl, AdmissionPermit};
+pub use asr_bridge::{AsrBridge, AsrResponse, AsrTask, Hotword, ASR_SAMPLE_RATE};
+pub use daemon_client::{CircuitState, DaemonClient, DaemonStream, RetryPolicy};
+pub use dialogue::{
+    stitch_lines, DialogueLine, DialogueRequest, DialogueResult, LineTiming, RenderedLine,
+};
 pub use engine::InferenceEngine;
-pub use generation::{AudioChunk, GenerationConfig, GenerationRequest};
+pub use generation::{AudioChunk, GenerationConfig, GenerationRequest, GenerationResult};
 pub use kv_cache::KVCache;
-pub use python_bridge::PythonBridge;
+pub use presets::GenerationPreset;
+pub use python_bridge::{AudioFrames, PythonBridge};
+pub use style::SpeechStyle;
+pub use supervisor::{DaemonHealth, DaemonSpec, DaemonStatus, DaemonSupervisor};
+pub use transport::{SharedTransport, Transport, TransportKind};
+pub use verify::{word_error_rate, VerificationResult, VerifyConfig};
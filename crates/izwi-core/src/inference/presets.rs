@@ -0,0 +1,83 @@
+//! Named sampling presets and per-model generation defaults
+//!
+//! A request's sampling settings are built up in layers: the engine
+//! defaults, then the defaults configured for its model, then the preset it
+//! names, then the values it sets itself. Clients can ask for "quality"
+//! without knowing the temperatures and top-k a model was tuned with.
+
+use serde::{Deserialize, Serialize};
+
+use super::generation::GenerationConfig;
+
+/// Sampling settings laid over those beneath them; unset fields keep the
+/// value underneath
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+}
+
+impl GenerationPreset {
+    /// Set the fields this preset sets in `config`
+    pub fn apply_to(&self, config: &mut GenerationConfig) {
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = top_p;
+        }
+        if let Some(top_k) = self.top_k {
+            config.top_k = top_k;
+        }
+        if let Some(penalty) = self.repetition_penalty {
+            config.repetition_penalty = penalty;
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            config.max_tokens = max_tokens;
+        }
+    }
+}
+
+/// Presets available without configuration: `fast` samples near-greedily
+/// and settles quickly, `quality` samples more freely for more natural
+/// prosody, and `balanced` sits between them
+pub const BUILTIN_PRESETS: &[(&str, GenerationPreset)] = &[
+    (
+        "fast",
+        GenerationPreset {
+            temperature: Some(0.3),
+            top_p: Some(0.8),
+            top_k: Some(10),
+            repetition_penalty: Some(1.1),
+            max_tokens: None,
+        },
+    ),
+    (
+        "balanced",
+        GenerationPreset {
+            temperature: Some(0.7),
+            top_p: Some(0.9),
+            top_k: Some(50),
+            repetition_penalty: Some(1.1),
+            max_tokens: None,
+        },
+    ),
+    (
+        "quality",
+        GenerationPreset {
+            temperature: Some(0.9),
+            top_p: Some(1.0),
+            top_k: Some(50),
+            repetition_penalty: Some(1.05),
+            max_tokens: None,
+        },
+    ),
+];
@@ -2,19 +2,19 @@
 //! Connects to a persistent Python daemon for fast inference
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
+use super::daemon_client::{DaemonClient, DaemonStream, RetryPolicy};
+use super::supervisor::{DaemonSpec, DaemonStatus, DaemonSupervisor};
+use crate::config::BridgeConfig;
+use crate::device::Device;
 use crate::error::{Error, Result};
 
-/// Default socket path for the TTS daemon
-const DEFAULT_SOCKET_PATH: &str = "/tmp/izwi_tts_daemon.sock";
-
 /// Request to Python inference script
 #[derive(Debug, Serialize)]
 pub struct PythonTTSRequest {
@@ -35,6 +35,9 @@ pub struct PythonTTSRequest {
     pub ref_audio_base64: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ref_text: Option<String>,
+    /// Audio per frame when streaming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_ms: Option<u32>,
 }
 
 impl Default for PythonTTSRequest {
@@ -49,6 +52,7 @@ impl Default for PythonTTSRequest {
             use_voice_clone: None,
             ref_audio_base64: None,
             ref_text: None,
+            chunk_ms: None,
         }
     }
 }
@@ -68,294 +72,136 @@ pub struct PythonTTSResponse {
 /// Python TTS bridge for calling qwen_tts
 /// Now connects to a persistent daemon for better performance
 pub struct PythonBridge {
-    socket_path: PathBuf,
-    daemon_script_path: PathBuf,
+    supervisor: Arc<DaemonSupervisor>,
+    client: DaemonClient,
     fallback_script_path: PathBuf,
     python_cmd: String,
-    daemon_process: Mutex<Option<Child>>,
 }
 
 impl PythonBridge {
     /// Create a new Python bridge
     pub fn new() -> Self {
-        let base_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-
-        Self {
-            socket_path: PathBuf::from(DEFAULT_SOCKET_PATH),
-            daemon_script_path: base_dir.join("scripts/tts_daemon.py"),
-            fallback_script_path: base_dir.join("scripts/tts_inference.py"),
-            python_cmd: "python3".to_string(),
-            daemon_process: Mutex::new(None),
-        }
+        Self::with_config(&BridgeConfig::default())
     }
 
-    /// Check if the daemon is running
-    fn is_daemon_running(&self) -> bool {
-        self.socket_path.exists() && self.connect_to_daemon().is_ok()
+    /// Create a bridge using the configured paths and interpreter
+    pub fn with_config(config: &BridgeConfig) -> Self {
+        Self::with_devices(config, &[])
     }
 
-    /// Start the daemon if not running
-    pub fn ensure_daemon_running(&self) -> Result<()> {
-        if self.is_daemon_running() {
-            debug!("TTS daemon already running");
-            return Ok(());
-        }
-
-        info!("Starting TTS daemon...");
-
-        let child = Command::new(&self.python_cmd)
-            .arg(&self.daemon_script_path)
-            .arg("--socket")
-            .arg(&self.socket_path)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| Error::InferenceError(format!("Failed to start daemon: {}", e)))?;
-
-        // Store the child process
-        {
-            let mut guard = self.daemon_process.lock().unwrap();
-            *guard = Some(child);
-        }
+    /// Create a bridge whose daemon splits models over `devices`, layer by
+    /// layer, when two or more are given
+    pub fn with_devices(config: &BridgeConfig, devices: &[Device]) -> Self {
+        let transport = config.transport("tts");
+        let python_cmd = config.python_executable().to_string_lossy().into_owned();
+        // Voice cloning can take minutes, so allow 5 minutes for reads
+        let spec = DaemonSpec::new(
+            "tts",
+            config.script_path("tts_daemon.py"),
+            transport.clone(),
+        )
+        .with_python_cmd(python_cmd.clone())
+        .with_startup_timeout(config.startup_timeout())
+        .with_io_timeouts(Duration::from_secs(300), Duration::from_secs(60));
+        let spec = if devices.len() > 1 {
+            let devices: Vec<String> = devices.iter().map(|d| d.torch_name()).collect();
+            spec.with_args(["--devices".to_string(), devices.join(",")])
+        } else {
+            spec
+        };
+        let client = DaemonClient::new("tts", transport)
+            .with_timeout(Duration::from_secs(300))
+            .with_retry_policy(RetryPolicy::from(config));
 
-        // Wait for daemon to be ready (up to 10 seconds)
-        for i in 0..100 {
-            std::thread::sleep(Duration::from_millis(100));
-            if self.socket_path.exists() {
-                if let Ok(mut stream) = self.connect_to_daemon() {
-                    // Send a check command to verify it's responding
-                    let request = PythonTTSRequest {
-                        command: "check".to_string(),
-                        ..Default::default()
-                    };
-                    if self.send_request(&mut stream, &request).is_ok() {
-                        info!("TTS daemon started successfully");
-                        return Ok(());
-                    }
-                }
-            }
-            if i % 20 == 0 {
-                debug!("Waiting for daemon to start... ({}/10s)", i / 10);
-            }
+        Self {
+            supervisor: Arc::new(DaemonSupervisor::new(spec)),
+            client,
+            fallback_script_path: config.script_path("tts_inference.py"),
+            python_cmd,
         }
+    }
 
-        Err(Error::InferenceError(
-            "Daemon failed to start within 10 seconds".to_string(),
-        ))
+    /// Start the daemon if not running
+    pub fn ensure_daemon_running(&self) -> Result<()> {
+        self.supervisor.ensure_running()
     }
 
     /// Stop the daemon
     pub fn stop_daemon(&self) -> Result<()> {
-        if !self.is_daemon_running() {
-            return Ok(());
-        }
-
-        info!("Stopping TTS daemon...");
-
-        // Send shutdown command
-        if let Ok(mut stream) = self.connect_to_daemon() {
-            let request = PythonTTSRequest {
-                command: "shutdown".to_string(),
-                ..Default::default()
-            };
-            let _ = self.send_request(&mut stream, &request);
-        }
-
-        // Kill the process if we started it
-        {
-            let mut guard = self.daemon_process.lock().unwrap();
-            if let Some(mut child) = guard.take() {
-                let _ = child.kill();
-                let _ = child.wait();
-            }
-        }
+        self.client.clear();
+        self.supervisor.stop()
+    }
 
-        // Clean up socket file
-        if self.socket_path.exists() {
-            let _ = std::fs::remove_file(&self.socket_path);
+    /// Health of the TTS daemon
+    pub fn health(&self) -> DaemonStatus {
+        DaemonStatus {
+            circuit: self.client.circuit_state(),
+            ..self.supervisor.status()
         }
-
-        Ok(())
     }
 
-    /// Connect to the daemon socket
-    fn connect_to_daemon(&self) -> Result<UnixStream> {
-        let stream = UnixStream::connect(&self.socket_path)
-            .map_err(|e| Error::InferenceError(format!("Failed to connect to daemon: {}", e)))?;
-
-        // Set longer timeouts for voice cloning which can take minutes
-        // Use 5 minutes for read (generation can be slow) and 60s for write
-        stream.set_read_timeout(Some(Duration::from_secs(300))).ok();
-        stream.set_write_timeout(Some(Duration::from_secs(60))).ok();
-
-        // Ensure socket is in blocking mode
-        stream.set_nonblocking(false).ok();
-
-        Ok(stream)
+    /// Pooled async connections to the daemon
+    pub fn client(&self) -> &DaemonClient {
+        &self.client
     }
 
-    /// Read exactly n bytes with retry on EAGAIN/WouldBlock
-    fn read_exact_with_retry(
-        stream: &mut UnixStream,
-        buf: &mut [u8],
-        max_retries: u32,
-    ) -> std::io::Result<()> {
-        let mut total_read = 0;
-        let mut retries = 0;
-
-        while total_read < buf.len() {
-            match stream.read(&mut buf[total_read..]) {
-                Ok(0) => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::UnexpectedEof,
-                        "Connection closed",
-                    ));
-                }
-                Ok(n) => {
-                    total_read += n;
-                    retries = 0; // Reset retries on successful read
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    retries += 1;
-                    if retries > max_retries {
-                        return Err(e);
-                    }
-                    // Wait a bit before retrying
-                    std::thread::sleep(Duration::from_millis(100));
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
-                    // Retry immediately on interrupt
-                    continue;
-                }
-                Err(e) => return Err(e),
-            }
+    /// Start the daemon off the async runtime unless a pooled connection
+    /// shows it is already up
+    async fn ensure_daemon_running_async(&self) -> Result<()> {
+        if self.client.has_idle() {
+            return Ok(());
         }
-        Ok(())
-    }
-
-    /// Send request to daemon and receive response
-    fn send_request(
-        &self,
-        stream: &mut UnixStream,
-        request: &PythonTTSRequest,
-    ) -> Result<PythonTTSResponse> {
-        let request_json = serde_json::to_string(request)
-            .map_err(|e| Error::InferenceError(format!("Failed to serialize request: {}", e)))?;
-
-        // Send length-prefixed message
-        let data = request_json.as_bytes();
-        let length = (data.len() as u32).to_be_bytes();
-
-        stream
-            .write_all(&length)
-            .map_err(|e| Error::InferenceError(format!("Failed to write length: {}", e)))?;
-        stream
-            .write_all(data)
-            .map_err(|e| Error::InferenceError(format!("Failed to write request: {}", e)))?;
-        stream
-            .flush()
-            .map_err(|e| Error::InferenceError(format!("Failed to flush: {}", e)))?;
-
-        // Read length-prefixed response with retry logic for EAGAIN
-        // Allow up to 3000 retries (5 minutes at 100ms per retry)
-        let mut length_buf = [0u8; 4];
-        Self::read_exact_with_retry(stream, &mut length_buf, 3000)
-            .map_err(|e| Error::InferenceError(format!("Failed to read response length: {}", e)))?;
-        let response_len = u32::from_be_bytes(length_buf) as usize;
-
-        let mut response_buf = vec![0u8; response_len];
-        Self::read_exact_with_retry(stream, &mut response_buf, 3000)
-            .map_err(|e| Error::InferenceError(format!("Failed to read response body: {}", e)))?;
-
-        let response: PythonTTSResponse = serde_json::from_slice(&response_buf).map_err(|e| {
-            Error::InferenceError(format!(
-                "Failed to parse response: {} - {}",
-                e,
-                String::from_utf8_lossy(&response_buf)
-            ))
-        })?;
-
-        Ok(response)
+        let supervisor = self.supervisor.clone();
+        tokio::task::spawn_blocking(move || supervisor.ensure_running())
+            .await
+            .map_err(|e| Error::InferenceError(format!("Daemon start task failed: {}", e)))?
     }
 
     /// Call daemon with request, with fallback to direct Python call
-    fn call_daemon(&self, request: &PythonTTSRequest) -> Result<PythonTTSResponse> {
-        // Try to ensure daemon is running
-        if let Err(e) = self.ensure_daemon_running() {
-            warn!("Failed to start daemon, falling back to direct call: {}", e);
-            return self.call_python_direct(request);
-        }
-
-        // Connect and send request
-        match self.connect_to_daemon() {
-            Ok(mut stream) => match self.send_request(&mut stream, request) {
-                Ok(response) => Ok(response),
-                Err(e) => {
-                    warn!("Daemon request failed, falling back to direct call: {}", e);
-                    self.call_python_direct(request)
-                }
-            },
+    async fn call(&self, request: &PythonTTSRequest) -> Result<PythonTTSResponse> {
+        let result = match self.ensure_daemon_running_async().await {
+            Ok(()) => self.client.call(request).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(response) => Ok(response),
             Err(e) => {
-                warn!(
-                    "Failed to connect to daemon, falling back to direct call: {}",
-                    e
-                );
-                self.call_python_direct(request)
+                warn!("Daemon request failed, falling back to direct call: {}", e);
+                let request_json = serde_json::to_string(request)?;
+                let python_cmd = self.python_cmd.clone();
+                let script = self.fallback_script_path.clone();
+                tokio::task::spawn_blocking(move || {
+                    call_python_direct(&python_cmd, &script, &request_json)
+                })
+                .await
+                .map_err(|e| Error::InferenceError(format!("Python task failed: {}", e)))?
             }
         }
     }
 
-    /// Fallback: Call Python script directly (old method)
-    fn call_python_direct(&self, request: &PythonTTSRequest) -> Result<PythonTTSResponse> {
-        let request_json = serde_json::to_string(request)
-            .map_err(|e| Error::InferenceError(format!("Failed to serialize request: {}", e)))?;
-
-        let mut child = Command::new(&self.python_cmd)
-            .arg(&self.fallback_script_path)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| Error::InferenceError(format!("Failed to start Python: {}", e)))?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin
-                .write_all(request_json.as_bytes())
-                .map_err(|e| Error::InferenceError(format!("Failed to write to Python: {}", e)))?;
-        }
-
-        let output = child
-            .wait_with_output()
-            .map_err(|e| Error::InferenceError(format!("Python process failed: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::InferenceError(format!("Python error: {}", stderr)));
+    /// Blocking variant of [`call`](Self::call) for synchronous executors
+    fn call_blocking(&self, request: &PythonTTSRequest) -> Result<PythonTTSResponse> {
+        let result = self
+            .ensure_daemon_running()
+            .and_then(|()| self.supervisor.call(request));
+        match result {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                warn!("Daemon request failed, falling back to direct call: {}", e);
+                let request_json = serde_json::to_string(request)?;
+                call_python_direct(&self.python_cmd, &self.fallback_script_path, &request_json)
+            }
         }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let json_str = stdout
-            .lines()
-            .find(|line| line.trim().starts_with('{'))
-            .unwrap_or(&stdout);
-
-        serde_json::from_str(json_str).map_err(|e| {
-            Error::InferenceError(format!(
-                "Failed to parse Python response: {} - {}",
-                e, json_str
-            ))
-        })
     }
 
     /// Check if Python dependencies are available
-    pub fn check_dependencies(&self) -> Result<bool> {
+    pub async fn check_dependencies(&self) -> Result<bool> {
         let request = PythonTTSRequest {
             command: "check".to_string(),
             ..Default::default()
         };
 
-        match self.call_daemon(&request) {
+        match self.call(&request).await {
             Ok(response) => {
                 if response.status.as_deref() == Some("ok") {
                     if let Some(device) = response.device {
@@ -377,36 +223,26 @@ impl PythonBridge {
     }
 
     /// Get daemon status
-    pub fn get_status(&self) -> Result<PythonTTSResponse> {
+    pub async fn get_status(&self) -> Result<PythonTTSResponse> {
         let request = PythonTTSRequest {
             command: "status".to_string(),
             ..Default::default()
         };
-        self.call_daemon(&request)
+        self.call(&request).await
     }
 
     /// Preload a model into the daemon cache
-    pub fn preload_model(&self, model_path: &Path) -> Result<()> {
-        let request = PythonTTSRequest {
-            command: "preload".to_string(),
-            model_path: model_path.to_string_lossy().to_string(),
-            ..Default::default()
-        };
-
-        let response = self.call_daemon(&request)?;
-
-        if let Some(err) = response.error {
-            return Err(Error::InferenceError(format!(
-                "Failed to preload model: {}",
-                err
-            )));
-        }
+    pub async fn preload_model(&self, model_path: &Path) -> Result<()> {
+        check_preload(self.call(&preload_request(model_path)).await?)
+    }
 
-        Ok(())
+    /// Blocking variant of [`preload_model`](Self::preload_model)
+    pub fn preload_model_blocking(&self, model_path: &Path) -> Result<()> {
+        check_preload(self.call_blocking(&preload_request(model_path))?)
     }
 
     /// Generate TTS audio using Python
-    pub fn generate(
+    pub async fn generate(
         &self,
         model_path: &Path,
         text: &str,
@@ -415,10 +251,11 @@ impl PythonBridge {
         instruct: Option<&str>,
     ) -> Result<(Vec<f32>, u32)> {
         self.generate_with_clone(model_path, text, speaker, language, instruct, None, None)
+            .await
     }
 
     /// Generate TTS audio with voice cloning
-    pub fn generate_with_clone(
+    pub async fn generate_with_clone(
         &self,
         model_path: &Path,
         text: &str,
@@ -428,67 +265,238 @@ impl PythonBridge {
         ref_audio_base64: Option<String>,
         ref_text: Option<String>,
     ) -> Result<(Vec<f32>, u32)> {
-        info!("Generating TTS for text: {}", text);
-        info!(
-            "Voice clone params - ref_audio: {}, ref_text: {}",
-            ref_audio_base64.is_some(),
-            ref_text.is_some()
+        let request = generate_request(
+            model_path,
+            text,
+            speaker,
+            language,
+            instruct,
+            ref_audio_base64,
+            ref_text,
         );
+        decode_audio(self.call(&request).await?)
+    }
 
-        let use_voice_clone = ref_audio_base64.is_some() && ref_text.is_some();
-        info!("use_voice_clone: {}", use_voice_clone);
-
-        let request = PythonTTSRequest {
-            command: "generate".to_string(),
-            model_path: model_path.to_string_lossy().to_string(),
-            text: text.to_string(),
-            speaker: speaker.map(|s| s.to_string()),
-            language: language.map(|s| s.to_string()),
-            instruct: instruct.map(|s| s.to_string()),
-            use_voice_clone: Some(use_voice_clone),
+    /// Blocking variant of [`generate_with_clone`](Self::generate_with_clone)
+    #[allow(clippy::too_many_arguments)] // mirrors `generate_with_clone`
+    pub fn generate_with_clone_blocking(
+        &self,
+        model_path: &Path,
+        text: &str,
+        speaker: Option<&str>,
+        language: Option<&str>,
+        instruct: Option<&str>,
+        ref_audio_base64: Option<String>,
+        ref_text: Option<String>,
+    ) -> Result<(Vec<f32>, u32)> {
+        let request = generate_request(
+            model_path,
+            text,
+            speaker,
+            language,
+            instruct,
             ref_audio_base64,
             ref_text,
-        };
+        );
+        decode_audio(self.call_blocking(&request)?)
+    }
 
-        let response = self.call_daemon(&request)?;
+    /// Stream TTS audio as the daemon produces it, in frames of about
+    /// `chunk_ms` milliseconds.
+    ///
+    /// There is no direct-call fallback: streaming needs the daemon.
+    pub async fn generate_stream(
+        &self,
+        mut request: PythonTTSRequest,
+        chunk_ms: u32,
+    ) -> Result<AudioFrames<'_>> {
+        request.command = "generate_stream".to_string();
+        request.chunk_ms = Some(chunk_ms);
+        self.ensure_daemon_running_async().await?;
+        Ok(AudioFrames {
+            frames: self.client.call_stream(&request).await?,
+            sample_rate: None,
+        })
+    }
+}
+
+/// One frame of a streamed TTS response
+#[derive(Debug, Deserialize)]
+struct AudioFrame {
+    /// Little-endian 16-bit mono PCM
+    audio_pcm16_base64: Option<String>,
+    sample_rate: Option<u32>,
+    error: Option<String>,
+}
 
-        if let Some(err) = response.error {
-            return Err(Error::InferenceError(format!("Python TTS error: {}", err)));
+/// Audio streamed from the TTS daemon, frame by frame.
+#[derive(Debug)]
+pub struct AudioFrames<'a> {
+    frames: DaemonStream<'a>,
+    sample_rate: Option<u32>,
+}
+
+impl AudioFrames<'_> {
+    /// Samples of the next frame, or `None` once generation has finished
+    pub async fn next_samples(&mut self) -> Result<Option<Vec<f32>>> {
+        while let Some(frame) = self.frames.next_frame().await? {
+            let frame: AudioFrame = serde_json::from_value(frame)?;
+            if let Some(err) = frame.error {
+                return Err(Error::InferenceError(format!("Python TTS error: {}", err)));
+            }
+            if frame.sample_rate.is_some() {
+                self.sample_rate = frame.sample_rate;
+            }
+            if let Some(pcm) = frame.audio_pcm16_base64 {
+                return decode_pcm16(&pcm).map(Some);
+            }
         }
+        Ok(None)
+    }
 
-        let audio_b64 = response
-            .audio_base64
-            .ok_or_else(|| Error::InferenceError("No audio in response".to_string()))?;
+    /// Sample rate reported by the daemon, known after the first frame
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+}
 
-        let sample_rate = response.sample_rate.unwrap_or(24000);
+/// Decode base64 little-endian 16-bit PCM to f32 samples
+fn decode_pcm16(pcm_b64: &str) -> Result<Vec<f32>> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(pcm_b64)
+        .map_err(|e| Error::InferenceError(format!("Failed to decode audio: {}", e)))?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect())
+}
 
-        // Decode base64 to WAV bytes
-        use base64::Engine;
-        let wav_bytes = base64::engine::general_purpose::STANDARD
-            .decode(&audio_b64)
-            .map_err(|e| Error::InferenceError(format!("Failed to decode audio: {}", e)))?;
+impl Default for PythonBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        // Parse WAV and extract samples
-        let samples = parse_wav_samples(&wav_bytes)?;
+fn preload_request(model_path: &Path) -> PythonTTSRequest {
+    PythonTTSRequest {
+        command: "preload".to_string(),
+        model_path: model_path.to_string_lossy().to_string(),
+        ..Default::default()
+    }
+}
 
-        debug!("Generated {} samples at {} Hz", samples.len(), sample_rate);
+fn check_preload(response: PythonTTSResponse) -> Result<()> {
+    if let Some(err) = response.error {
+        return Err(Error::InferenceError(format!(
+            "Failed to preload model: {}",
+            err
+        )));
+    }
+    Ok(())
+}
 
-        Ok((samples, sample_rate))
+pub(crate) fn generate_request(
+    model_path: &Path,
+    text: &str,
+    speaker: Option<&str>,
+    language: Option<&str>,
+    instruct: Option<&str>,
+    ref_audio_base64: Option<String>,
+    ref_text: Option<String>,
+) -> PythonTTSRequest {
+    // The text itself is logged, redacted, by the engine
+    info!("Generating TTS for {} chars", text.chars().count());
+    info!(
+        "Voice clone params - ref_audio: {}, ref_text: {}",
+        ref_audio_base64.is_some(),
+        ref_text.is_some()
+    );
+
+    let use_voice_clone = ref_audio_base64.is_some() && ref_text.is_some();
+    info!("use_voice_clone: {}", use_voice_clone);
+
+    PythonTTSRequest {
+        command: "generate".to_string(),
+        model_path: model_path.to_string_lossy().to_string(),
+        text: text.to_string(),
+        speaker: speaker.map(|s| s.to_string()),
+        language: language.map(|s| s.to_string()),
+        instruct: instruct.map(|s| s.to_string()),
+        use_voice_clone: Some(use_voice_clone),
+        ref_audio_base64,
+        ref_text,
+        chunk_ms: None,
     }
 }
 
-impl Default for PythonBridge {
-    fn default() -> Self {
-        Self::new()
+/// Extract the generated samples from a daemon response
+fn decode_audio(response: PythonTTSResponse) -> Result<(Vec<f32>, u32)> {
+    if let Some(err) = response.error {
+        return Err(Error::InferenceError(format!("Python TTS error: {}", err)));
     }
+
+    let audio_b64 = response
+        .audio_base64
+        .ok_or_else(|| Error::InferenceError("No audio in response".to_string()))?;
+
+    let sample_rate = response.sample_rate.unwrap_or(24000);
+
+    // Decode base64 to WAV bytes
+    use base64::Engine;
+    let wav_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&audio_b64)
+        .map_err(|e| Error::InferenceError(format!("Failed to decode audio: {}", e)))?;
+
+    // Parse WAV and extract samples
+    let samples = parse_wav_samples(&wav_bytes)?;
+
+    debug!("Generated {} samples at {} Hz", samples.len(), sample_rate);
+
+    Ok((samples, sample_rate))
 }
 
-impl Drop for PythonBridge {
-    fn drop(&mut self) {
-        // Note: We don't stop the daemon on drop anymore
-        // The daemon persists for better performance across requests
-        // Use stop_daemon() explicitly if needed
+/// Fallback: Call Python script directly (old method)
+fn call_python_direct(
+    python_cmd: &str,
+    script: &Path,
+    request_json: &str,
+) -> Result<PythonTTSResponse> {
+    let mut child = Command::new(python_cmd)
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::InferenceError(format!("Failed to start Python: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(request_json.as_bytes())
+            .map_err(|e| Error::InferenceError(format!("Failed to write to Python: {}", e)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Error::InferenceError(format!("Python process failed: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::InferenceError(format!("Python error: {}", stderr)));
     }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json_str = stdout
+        .lines()
+        .find(|line| line.trim().starts_with('{'))
+        .unwrap_or(&stdout);
+
+    serde_json::from_str(json_str).map_err(|e| {
+        Error::InferenceError(format!(
+            "Failed to parse Python response: {} - {}",
+            e, json_str
+        ))
+    })
 }
 
 /// Parse WAV bytes and extract f32 samples
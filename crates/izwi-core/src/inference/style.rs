@@ -0,0 +1,210 @@
+//! Speaking style and free-text instructions for TTS requests.
+//!
+//! Instruction-following models (CustomVoice and VoiceDesign) take an
+//! `instruct` prompt describing how to speak. A named [`SpeechStyle`] expands
+//! to a fixed instruction, and is combined with any free-text instruction and
+//! the voice description into the one prompt the model sees.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::model::ModelVariant;
+
+/// Longest free-text instruction accepted, in characters
+pub const MAX_INSTRUCT_CHARS: usize = 500;
+
+/// Named speaking style or emotion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechStyle {
+    Cheerful,
+    Sad,
+    Angry,
+    Calm,
+    Excited,
+    Whisper,
+    Newscaster,
+    Storyteller,
+}
+
+impl SpeechStyle {
+    /// Every style, in the order they are listed to clients
+    pub fn all() -> &'static [SpeechStyle] {
+        &[
+            Self::Cheerful,
+            Self::Sad,
+            Self::Angry,
+            Self::Calm,
+            Self::Excited,
+            Self::Whisper,
+            Self::Newscaster,
+            Self::Storyteller,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cheerful => "cheerful",
+            Self::Sad => "sad",
+            Self::Angry => "angry",
+            Self::Calm => "calm",
+            Self::Excited => "excited",
+            Self::Whisper => "whisper",
+            Self::Newscaster => "newscaster",
+            Self::Storyteller => "storyteller",
+        }
+    }
+
+    /// Instruction given to the model for this style
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            Self::Cheerful => "Speak in a cheerful, upbeat tone.",
+            Self::Sad => "Speak in a sad, subdued tone.",
+            Self::Angry => "Speak in an angry, forceful tone.",
+            Self::Calm => "Speak in a calm, soothing tone.",
+            Self::Excited => "Speak with excitement and energy.",
+            Self::Whisper => "Whisper softly.",
+            Self::Newscaster => "Speak like a news anchor: clear, measured and neutral.",
+            Self::Storyteller => "Narrate like a storyteller, warm and expressive.",
+        }
+    }
+}
+
+impl std::fmt::Display for SpeechStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SpeechStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase();
+        Self::all()
+            .iter()
+            .copied()
+            .find(|style| style.as_str() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::all().iter().map(SpeechStyle::as_str).collect();
+                Error::InvalidInput(format!(
+                    "Unknown style '{}'; expected one of {}",
+                    s,
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Styles `model` can follow; empty for models without instruction prompts.
+pub fn supported_styles(model: ModelVariant) -> &'static [SpeechStyle] {
+    if supports_instruct(model) {
+        SpeechStyle::all()
+    } else {
+        &[]
+    }
+}
+
+/// Whether `model` takes an instruction prompt
+pub fn supports_instruct(model: ModelVariant) -> bool {
+    matches!(
+        model,
+        ModelVariant::Qwen3Tts12Hz06BCustomVoice
+            | ModelVariant::Qwen3Tts12Hz17BCustomVoice
+            | ModelVariant::Qwen3Tts12Hz17BVoiceDesign
+    )
+}
+
+/// Check that `model` can follow the requested style and instruction.
+/// Cloned voices ignore instructions, so both are refused with reference
+/// audio.
+pub fn validate(
+    model: ModelVariant,
+    style: Option<SpeechStyle>,
+    instruct: Option<&str>,
+    cloning: bool,
+) -> Result<()> {
+    if let Some(instruct) = instruct {
+        let chars = instruct.chars().count();
+        if chars > MAX_INSTRUCT_CHARS {
+            return Err(Error::InvalidInput(format!(
+                "instruct is {} characters long (limit {})",
+                chars, MAX_INSTRUCT_CHARS
+            )));
+        }
+    }
+    if style.is_none() && instruct.is_none() {
+        return Ok(());
+    }
+    if cloning {
+        return Err(Error::InvalidInput(
+            "style and instruct are not applied to cloned voices".to_string(),
+        ));
+    }
+    if !supports_instruct(model) {
+        return Err(Error::InvalidInput(format!(
+            "Model {} does not take style or instruct prompts; use a CustomVoice or VoiceDesign model",
+            model
+        )));
+    }
+    if let Some(style) = style {
+        if !supported_styles(model).contains(&style) {
+            return Err(Error::InvalidInput(format!(
+                "Model {} does not support the '{}' style",
+                model, style
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Prompt for the model: the voice description, then the style's
+/// instruction, then the free-text instruction
+pub fn compose(
+    voice_description: Option<&str>,
+    style: Option<SpeechStyle>,
+    instruct: Option<&str>,
+) -> Option<String> {
+    let parts: Vec<&str> = [voice_description, style.map(|s| s.instruction()), instruct]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_validation_and_prompt() {
+        assert_eq!(
+            "Cheerful".parse::<SpeechStyle>().unwrap(),
+            SpeechStyle::Cheerful
+        );
+        assert!("sarcastic".parse::<SpeechStyle>().is_err());
+
+        let custom = ModelVariant::Qwen3Tts12Hz06BCustomVoice;
+        let base = ModelVariant::Qwen3Tts12Hz06BBase;
+        assert!(validate(custom, Some(SpeechStyle::Whisper), Some("Slowly."), false).is_ok());
+        assert!(validate(base, Some(SpeechStyle::Whisper), None, false).is_err());
+        assert!(validate(base, None, None, false).is_ok());
+        assert!(validate(custom, None, Some("Slowly."), true).is_err());
+        let long = "a".repeat(MAX_INSTRUCT_CHARS + 1);
+        assert!(validate(custom, None, Some(&long), false).is_err());
+
+        assert_eq!(
+            compose(
+                Some("A deep male voice."),
+                Some(SpeechStyle::Calm),
+                Some("Pause after commas.")
+            )
+            .as_deref(),
+            Some("A deep male voice. Speak in a calm, soothing tone. Pause after commas.")
+        );
+        assert_eq!(compose(None, None, Some("  ")), None);
+    }
+}
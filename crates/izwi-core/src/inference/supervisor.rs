@@ -0,0 +1,531 @@
+//! Lifecycle management for the Python inference daemons.
+//!
+//! Each bridge owns a [`DaemonSupervisor`] that spawns its daemon on first
+//! use, checks that it answers on its socket, restarts it with exponential
+//! backoff after it dies or hangs, and shuts it down gracefully when the
+//! bridge is dropped. Daemons speak length-prefixed JSON over their
+//! transport: a 4-byte big-endian length followed by the message body.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use super::daemon_client::CircuitState;
+use super::transport::{BlockingStream, SharedTransport};
+use crate::error::{Error, Result};
+
+/// Interval between readiness checks while a daemon starts
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a stopping daemon gets to exit before it is killed
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// How to run and reach one daemon.
+#[derive(Debug, Clone)]
+pub struct DaemonSpec {
+    /// Name used in logs and status reports, e.g. `tts`
+    pub name: String,
+    pub python_cmd: String,
+    pub script_path: PathBuf,
+    /// Where the daemon listens
+    pub transport: SharedTransport,
+    /// Arguments passed after the listen arguments
+    pub args: Vec<OsString>,
+    /// How long a freshly spawned daemon has to answer a check
+    pub startup_timeout: Duration,
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    /// Delay before the first restart after a failure, doubled per failure
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Shut down a daemon this supervisor spawned when it is dropped
+    pub stop_on_drop: bool,
+}
+
+impl DaemonSpec {
+    /// Spec for a daemon run as `python3 <script>` plus the transport's
+    /// listen arguments, e.g. `--socket <path>`.
+    pub fn new(
+        name: impl Into<String>,
+        script_path: impl Into<PathBuf>,
+        transport: SharedTransport,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            python_cmd: "python3".to_string(),
+            script_path: script_path.into(),
+            transport,
+            args: Vec::new(),
+            startup_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(120),
+            write_timeout: Duration::from_secs(30),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stop_on_drop: true,
+        }
+    }
+
+    pub fn with_python_cmd(mut self, python_cmd: impl Into<String>) -> Self {
+        self.python_cmd = python_cmd.into();
+        self
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    pub fn with_io_timeouts(mut self, read: Duration, write: Duration) -> Self {
+        self.read_timeout = read;
+        self.write_timeout = write;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_stop_on_drop(mut self, stop_on_drop: bool) -> Self {
+        self.stop_on_drop = stop_on_drop;
+        self
+    }
+
+    /// Restart delay after `failures` consecutive failed starts
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 1u32 << failures.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Health of a supervised daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaemonHealth {
+    /// Not running and not expected to be
+    Stopped,
+    /// Answering on its endpoint
+    Healthy,
+    /// Failed to start, died or stopped answering
+    Unhealthy,
+}
+
+/// Health report for one daemon.
+#[derive(Debug, Clone, Serialize)]
+pub struct DaemonStatus {
+    pub name: String,
+    pub health: DaemonHealth,
+    /// Process ID when this supervisor spawned the daemon
+    pub pid: Option<u32>,
+    /// Where the daemon listens, e.g. `unix:/tmp/izwi-1234-tts.sock`
+    pub endpoint: String,
+    /// Times the daemon has been restarted
+    pub restarts: u32,
+    /// Consecutive failed starts
+    pub failures: u32,
+    pub last_error: Option<String>,
+    /// Seconds until the next start attempt is allowed
+    pub retry_in_secs: Option<u64>,
+    /// Circuit breaker of the bridge's client; open while calls fail fast
+    pub circuit: CircuitState,
+}
+
+#[derive(Debug)]
+struct SupervisorState {
+    /// Daemon process spawned by this supervisor
+    child: Option<Child>,
+    health: DaemonHealth,
+    /// Whether a daemon has been spawned before, so the next spawn is a restart
+    spawned: bool,
+    restarts: u32,
+    failures: u32,
+    /// Earliest time of the next start attempt after a failure
+    retry_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+/// Spawns, health-checks and restarts one Python daemon.
+#[derive(Debug)]
+pub struct DaemonSupervisor {
+    spec: DaemonSpec,
+    state: Mutex<SupervisorState>,
+}
+
+impl DaemonSupervisor {
+    pub fn new(spec: DaemonSpec) -> Self {
+        Self {
+            spec,
+            state: Mutex::new(SupervisorState {
+                child: None,
+                health: DaemonHealth::Stopped,
+                spawned: false,
+                restarts: 0,
+                failures: 0,
+                retry_at: None,
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn spec(&self) -> &DaemonSpec {
+        &self.spec
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SupervisorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Connect to the daemon
+    pub fn connect(&self) -> Result<Box<dyn BlockingStream>> {
+        self.spec
+            .transport
+            .connect_blocking(self.spec.read_timeout, self.spec.write_timeout)
+            .map_err(|e| {
+                Error::InferenceError(format!(
+                    "Failed to connect to {} daemon: {}",
+                    self.spec.name, e
+                ))
+            })
+    }
+
+    /// Send one request and read its response over a new connection.
+    ///
+    /// Connection failures mark the daemon unhealthy so the next
+    /// [`ensure_running`](Self::ensure_running) restarts it if needed.
+    pub fn call<T: Serialize, R: DeserializeOwned>(&self, request: &T) -> Result<R> {
+        let result = self
+            .connect()
+            .and_then(|mut stream| exchange(&mut stream, request));
+        if let Err(e) = &result {
+            let mut state = self.lock();
+            state.health = DaemonHealth::Unhealthy;
+            state.last_error = Some(e.to_string());
+        }
+        result
+    }
+
+    /// Whether the daemon answers a check command
+    fn ping(&self) -> bool {
+        self.connect()
+            .and_then(|mut stream| {
+                exchange::<_, serde_json::Value>(
+                    &mut stream,
+                    &serde_json::json!({"command": "check"}),
+                )
+            })
+            .is_ok()
+    }
+
+    /// Start the daemon unless it is already answering.
+    ///
+    /// A dead or hung daemon is replaced, but after a failed start no new
+    /// attempt is made until its backoff delay has passed.
+    pub fn ensure_running(&self) -> Result<()> {
+        let mut state = self.lock();
+        if self.ping() {
+            state.health = DaemonHealth::Healthy;
+            return Ok(());
+        }
+
+        // The daemon we spawned has died or stopped answering
+        if let Some(mut child) = state.child.take() {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    warn!("{} daemon exited unexpectedly ({})", self.spec.name, status);
+                    state.last_error = Some(format!("Daemon exited ({})", status));
+                }
+                _ => {
+                    warn!("{} daemon is not responding, restarting it", self.spec.name);
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if let Some(retry_at) = state.retry_at.filter(|at| *at > now) {
+            state.health = DaemonHealth::Unhealthy;
+            return Err(Error::InferenceError(format!(
+                "{} daemon failed to start, retrying in {}s",
+                self.spec.name,
+                retry_at.duration_since(now).as_secs().max(1)
+            )));
+        }
+
+        match self.spawn(&mut state) {
+            Ok(()) => {
+                state.health = DaemonHealth::Healthy;
+                state.failures = 0;
+                state.retry_at = None;
+                Ok(())
+            }
+            Err(e) => {
+                state.failures += 1;
+                let backoff = self.spec.backoff(state.failures);
+                warn!(
+                    "{} daemon failed to start (attempt {}), next attempt in {:?}: {}",
+                    self.spec.name, state.failures, backoff, e
+                );
+                state.health = DaemonHealth::Unhealthy;
+                state.retry_at = Some(Instant::now() + backoff);
+                state.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Spawn the daemon and wait until it answers
+    fn spawn(&self, state: &mut SupervisorState) -> Result<()> {
+        if state.spawned {
+            state.restarts += 1;
+            info!(
+                "Restarting {} daemon (restart {})",
+                self.spec.name, state.restarts
+            );
+        } else {
+            info!("Starting {} daemon...", self.spec.name);
+        }
+        state.spawned = true;
+
+        // A socket left behind by a dead daemon would make it look alive
+        self.spec.transport.cleanup();
+
+        let mut child = Command::new(&self.spec.python_cmd)
+            .arg(&self.spec.script_path)
+            .args(self.spec.transport.listen_args())
+            .args(&self.spec.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                Error::InferenceError(format!("Failed to start {} daemon: {}", self.spec.name, e))
+            })?;
+
+        let deadline = Instant::now() + self.spec.startup_timeout;
+        while Instant::now() < deadline {
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(Error::InferenceError(format!(
+                    "{} daemon exited during startup ({})",
+                    self.spec.name, status
+                )));
+            }
+            if self.ping() {
+                info!("{} daemon started (pid {})", self.spec.name, child.id());
+                state.child = Some(child);
+                return Ok(());
+            }
+            debug!("Waiting for {} daemon to start...", self.spec.name);
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        let _ = child.kill();
+        let _ = child.wait();
+        Err(Error::InferenceError(format!(
+            "{} daemon failed to start within {}s",
+            self.spec.name,
+            self.spec.startup_timeout.as_secs()
+        )))
+    }
+
+    /// Ask the daemon to shut down, killing it if it does not exit in time
+    pub fn stop(&self) -> Result<()> {
+        let mut state = self.lock();
+        self.shutdown(&mut state);
+        Ok(())
+    }
+
+    fn shutdown(&self, state: &mut SupervisorState) {
+        let reachable = self
+            .connect()
+            .and_then(|mut stream| {
+                exchange::<_, serde_json::Value>(
+                    &mut stream,
+                    &serde_json::json!({"command": "shutdown"}),
+                )
+            })
+            .is_ok();
+        if reachable || state.child.is_some() {
+            info!("Stopping {} daemon...", self.spec.name);
+        }
+
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        if let Some(mut child) = state.child.take() {
+            while Instant::now() < deadline && matches!(child.try_wait(), Ok(None)) {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            if matches!(child.try_wait(), Ok(None)) {
+                warn!("{} daemon did not exit in time, killing it", self.spec.name);
+                let _ = child.kill();
+            }
+            let _ = child.wait();
+        } else if reachable {
+            // Started elsewhere: wait for it to stop listening
+            while Instant::now() < deadline && self.spec.transport.is_listening() {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        if !self.ping() {
+            self.spec.transport.cleanup();
+        }
+        state.health = DaemonHealth::Stopped;
+        state.failures = 0;
+        state.retry_at = None;
+    }
+
+    /// Current health, checking the daemon answers
+    pub fn status(&self) -> DaemonStatus {
+        let alive = self.ping();
+        let mut state = self.lock();
+        if let Some(child) = state.child.as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                state.last_error = Some(format!("Daemon exited ({})", status));
+                state.child = None;
+            }
+        }
+        if alive {
+            state.health = DaemonHealth::Healthy;
+        } else if state.health == DaemonHealth::Healthy {
+            state.health = DaemonHealth::Unhealthy;
+        }
+
+        let now = Instant::now();
+        DaemonStatus {
+            name: self.spec.name.clone(),
+            health: state.health,
+            pid: state.child.as_ref().map(Child::id),
+            endpoint: self.spec.transport.to_string(),
+            restarts: state.restarts,
+            failures: state.failures,
+            last_error: state.last_error.clone(),
+            retry_in_secs: state
+                .retry_at
+                .filter(|at| *at > now)
+                .map(|at| at.duration_since(now).as_secs().max(1)),
+            circuit: CircuitState::Closed,
+        }
+    }
+}
+
+impl Drop for DaemonSupervisor {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        if self.spec.stop_on_drop && state.child.is_some() {
+            self.shutdown(&mut state);
+        }
+    }
+}
+
+/// Write one length-prefixed request and read the response
+fn exchange<T: Serialize, R: DeserializeOwned>(
+    stream: &mut Box<dyn BlockingStream>,
+    request: &T,
+) -> Result<R> {
+    let body = serde_json::to_vec(request)
+        .map_err(|e| Error::InferenceError(format!("Failed to serialize request: {}", e)))?;
+    stream
+        .write_all(&(body.len() as u32).to_be_bytes())
+        .and_then(|_| stream.write_all(&body))
+        .and_then(|_| stream.flush())
+        .map_err(|e| Error::InferenceError(format!("Failed to write request: {}", e)))?;
+
+    let mut length = [0u8; 4];
+    read_exact_with_retry(stream, &mut length)
+        .map_err(|e| Error::InferenceError(format!("Failed to read response length: {}", e)))?;
+    let mut response = vec![0u8; u32::from_be_bytes(length) as usize];
+    read_exact_with_retry(stream, &mut response)
+        .map_err(|e| Error::InferenceError(format!("Failed to read response body: {}", e)))?;
+
+    serde_json::from_slice(&response).map_err(|e| {
+        Error::InferenceError(format!(
+            "Failed to parse response: {} - {}",
+            e,
+            String::from_utf8_lossy(&response)
+        ))
+    })
+}
+
+/// Read exactly `buf.len()` bytes, retrying on EAGAIN/WouldBlock for up to
+/// 5 minutes
+fn read_exact_with_retry(stream: &mut impl Read, buf: &mut [u8]) -> std::io::Result<()> {
+    const MAX_RETRIES: u32 = 3000;
+    let mut total_read = 0;
+    let mut retries = 0;
+
+    while total_read < buf.len() {
+        match stream.read(&mut buf[total_read..]) {
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Connection closed",
+                ));
+            }
+            Ok(n) => {
+                total_read += n;
+                retries = 0;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                retries += 1;
+                if retries > MAX_RETRIES {
+                    return Err(e);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::transport::UnixTransport;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_failed_start_backs_off() {
+        let socket = std::env::temp_dir().join(format!("izwi-test-{}.sock", uuid::Uuid::new_v4()));
+        let spec = DaemonSpec::new("test", "missing.py", Arc::new(UnixTransport::new(&socket)))
+            .with_python_cmd("false")
+            .with_startup_timeout(Duration::from_secs(2))
+            .with_backoff(Duration::from_secs(30), Duration::from_secs(60));
+        assert_eq!(spec.backoff(1), Duration::from_secs(30));
+        assert_eq!(spec.backoff(3), Duration::from_secs(60));
+
+        let supervisor = DaemonSupervisor::new(spec);
+        assert_eq!(supervisor.status().health, DaemonHealth::Stopped);
+
+        // The process exits at once, so the start fails
+        assert!(supervisor.ensure_running().is_err());
+        let status = supervisor.status();
+        assert_eq!(status.health, DaemonHealth::Unhealthy);
+        assert_eq!(status.failures, 1);
+        assert!(status.retry_in_secs.is_some());
+
+        // Within the backoff window no new process is spawned
+        let err = supervisor.ensure_running().unwrap_err();
+        assert!(err.to_string().contains("retrying in"));
+        assert_eq!(supervisor.status().restarts, 0);
+    }
+}
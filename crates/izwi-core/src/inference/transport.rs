@@ -0,0 +1,317 @@
+//! Connections to the Python inference daemons.
+//!
+//! Daemons are reached over a [`Transport`]: a Unix socket, a TCP port on
+//! the loopback interface, or a Windows named pipe. All carry the same
+//! length-prefixed JSON messages, so the supervisor and clients only see
+//! byte streams. [`TransportKind::Auto`] picks Unix sockets on Unix and
+//! named pipes on Windows.
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// How long blocking TCP connects and listening probes may take
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Transport between the server and its daemons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportKind {
+    /// Unix sockets on Unix, named pipes on Windows
+    #[default]
+    Auto,
+    Unix,
+    /// TCP on the loopback interface
+    Tcp,
+    NamedPipe,
+}
+
+impl TransportKind {
+    /// The concrete transport used on this platform
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto if cfg!(unix) => Self::Unix,
+            Self::Auto if cfg!(windows) => Self::NamedPipe,
+            Self::Auto => Self::Tcp,
+            kind => kind,
+        }
+    }
+
+    /// Whether this platform can use the transport
+    pub fn is_supported(self) -> bool {
+        match self.resolve() {
+            Self::Unix => cfg!(unix),
+            Self::NamedPipe => cfg!(windows),
+            _ => true,
+        }
+    }
+}
+
+/// Blocking connection to a daemon.
+pub trait BlockingStream: Read + Write + Send + fmt::Debug {}
+
+impl<T: Read + Write + Send + fmt::Debug> BlockingStream for T {}
+
+/// Async connection to a daemon.
+pub trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + fmt::Debug> AsyncStream for T {}
+
+/// Pooled async connection, whatever the transport
+pub type Connection = Box<dyn AsyncStream>;
+
+/// Where a daemon listens and how to connect to it.
+///
+/// `Display` names the endpoint for logs and status reports.
+pub trait Transport: fmt::Debug + fmt::Display + Send + Sync {
+    /// Open a blocking connection with the given I/O timeouts
+    fn connect_blocking(
+        &self,
+        read_timeout: Duration,
+        write_timeout: Duration,
+    ) -> io::Result<Box<dyn BlockingStream>>;
+
+    /// Open an async connection
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>>;
+
+    /// Command-line arguments telling a daemon where to listen
+    fn listen_args(&self) -> Vec<OsString>;
+
+    /// Whether something still listens on the endpoint
+    fn is_listening(&self) -> bool {
+        self.connect_blocking(CONNECT_TIMEOUT, CONNECT_TIMEOUT)
+            .is_ok()
+    }
+
+    /// Remove what a dead daemon left behind, such as its socket file
+    fn cleanup(&self) {}
+}
+
+/// Transport shared by a daemon's supervisor and client
+pub type SharedTransport = Arc<dyn Transport>;
+
+/// Unix domain socket at a filesystem path.
+#[derive(Debug, Clone)]
+pub struct UnixTransport {
+    path: PathBuf,
+}
+
+impl UnixTransport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl fmt::Display for UnixTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unix:{}", self.path.display())
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixTransport {
+    fn connect_blocking(
+        &self,
+        read_timeout: Duration,
+        write_timeout: Duration,
+    ) -> io::Result<Box<dyn BlockingStream>> {
+        let stream = std::os::unix::net::UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(read_timeout))?;
+        stream.set_write_timeout(Some(write_timeout))?;
+        Ok(Box::new(stream))
+    }
+
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>> {
+        Box::pin(async move {
+            let stream = tokio::net::UnixStream::connect(&self.path).await?;
+            Ok(Box::new(stream) as Connection)
+        })
+    }
+
+    fn listen_args(&self) -> Vec<OsString> {
+        vec!["--socket".into(), self.path.clone().into()]
+    }
+
+    fn is_listening(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn cleanup(&self) {
+        if self.path.exists() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Transport for UnixTransport {
+    fn connect_blocking(&self, _: Duration, _: Duration) -> io::Result<Box<dyn BlockingStream>> {
+        Err(unsupported("Unix sockets"))
+    }
+
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>> {
+        Box::pin(async { Err(unsupported("Unix sockets")) })
+    }
+
+    fn listen_args(&self) -> Vec<OsString> {
+        vec!["--socket".into(), self.path.clone().into()]
+    }
+}
+
+/// TCP port, normally on the loopback interface.
+#[derive(Debug, Clone)]
+pub struct TcpTransport {
+    addr: SocketAddr,
+}
+
+impl TcpTransport {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+}
+
+impl fmt::Display for TcpTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tcp:{}", self.addr)
+    }
+}
+
+impl Transport for TcpTransport {
+    fn connect_blocking(
+        &self,
+        read_timeout: Duration,
+        write_timeout: Duration,
+    ) -> io::Result<Box<dyn BlockingStream>> {
+        let stream = TcpStream::connect_timeout(&self.addr, CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(read_timeout))?;
+        stream.set_write_timeout(Some(write_timeout))?;
+        stream.set_nodelay(true)?;
+        Ok(Box::new(stream))
+    }
+
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>> {
+        Box::pin(async move {
+            let stream = tokio::net::TcpStream::connect(self.addr).await?;
+            stream.set_nodelay(true)?;
+            Ok(Box::new(stream) as Connection)
+        })
+    }
+
+    fn listen_args(&self) -> Vec<OsString> {
+        vec!["--tcp".into(), self.addr.to_string().into()]
+    }
+}
+
+/// Windows named pipe, e.g. `\\.\pipe\izwi-1234-tts`.
+#[derive(Debug, Clone)]
+pub struct NamedPipeTransport {
+    name: String,
+}
+
+impl NamedPipeTransport {
+    /// Transport for the pipe `name`, with or without the `\\.\pipe\` prefix
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let name = if name.starts_with(r"\\") {
+            name
+        } else {
+            format!(r"\\.\pipe\{}", name)
+        };
+        Self { name }
+    }
+}
+
+impl fmt::Display for NamedPipeTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pipe:{}", self.name)
+    }
+}
+
+#[cfg(windows)]
+impl Transport for NamedPipeTransport {
+    /// Pipe handles opened as files have no I/O timeouts
+    fn connect_blocking(&self, _: Duration, _: Duration) -> io::Result<Box<dyn BlockingStream>> {
+        let pipe = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.name)?;
+        Ok(Box::new(pipe))
+    }
+
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>> {
+        Box::pin(async move {
+            let pipe = tokio::net::windows::named_pipe::ClientOptions::new().open(&self.name)?;
+            Ok(Box::new(pipe) as Connection)
+        })
+    }
+
+    fn listen_args(&self) -> Vec<OsString> {
+        vec!["--pipe".into(), self.name.clone().into()]
+    }
+}
+
+#[cfg(not(windows))]
+impl Transport for NamedPipeTransport {
+    fn connect_blocking(&self, _: Duration, _: Duration) -> io::Result<Box<dyn BlockingStream>> {
+        Err(unsupported("Named pipes"))
+    }
+
+    fn connect(&self) -> BoxFuture<'_, io::Result<Connection>> {
+        Box::pin(async { Err(unsupported("Named pipes")) })
+    }
+
+    fn listen_args(&self) -> Vec<OsString> {
+        vec!["--pipe".into(), self.name.clone().into()]
+    }
+}
+
+fn unsupported(transport: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} are not available on this platform", transport),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_auto_resolves_per_platform() {
+        let resolved = TransportKind::Auto.resolve();
+        assert_ne!(resolved, TransportKind::Auto);
+        assert!(resolved.is_supported());
+        assert!(TransportKind::Tcp.is_supported());
+        assert_eq!(
+            NamedPipeTransport::new("izwi-a-tts").to_string(),
+            r"pipe:\\.\pipe\izwi-a-tts"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_round_trip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let transport = TcpTransport::new(listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+        });
+
+        let mut stream = transport.connect().await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        assert_eq!(transport.listen_args()[0], "--tcp");
+    }
+}
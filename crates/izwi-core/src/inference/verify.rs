@@ -0,0 +1,147 @@
+//! Transcription-based verification of generated speech
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for the post-generation verification loop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyConfig {
+    /// Maximum acceptable word error rate (0.0 - 1.0)
+    #[serde(default = "default_wer_threshold")]
+    pub wer_threshold: f32,
+
+    /// Additional generation attempts after a failed check
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+
+    /// Factor applied to the temperature on each retry
+    #[serde(default = "default_temperature_decay")]
+    pub temperature_decay: f32,
+
+    /// ASR model used for verification (daemon default if unset)
+    #[serde(default)]
+    pub asr_model: Option<String>,
+}
+
+fn default_wer_threshold() -> f32 {
+    0.3
+}
+fn default_max_retries() -> usize {
+    2
+}
+fn default_temperature_decay() -> f32 {
+    0.7
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            wer_threshold: default_wer_threshold(),
+            max_retries: default_max_retries(),
+            temperature_decay: default_temperature_decay(),
+            asr_model: None,
+        }
+    }
+}
+
+/// Outcome of verifying a generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationResult {
+    /// Word error rate of the transcript against the input text
+    pub wer: f32,
+    /// ASR transcript of the returned audio
+    pub transcript: String,
+    /// Number of generations performed
+    pub attempts: usize,
+    /// Whether the WER is within the threshold
+    pub passed: bool,
+}
+
+/// Split text into comparison units.
+///
+/// Text is lowercased and stripped of punctuation. Scripts written without
+/// spaces (CJK) are compared per character, everything else per word.
+fn tokenize(text: &str) -> Vec<String> {
+    let normalized: String = text
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c.is_whitespace() || c == '\'' {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect();
+
+    let mut units = Vec::new();
+    for word in normalized.split_whitespace() {
+        if word.chars().any(is_cjk) {
+            units.extend(word.chars().map(|c| c.to_string()));
+        } else {
+            units.push(word.trim_matches('\'').to_string());
+        }
+    }
+    units.retain(|u| !u.is_empty());
+    units
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // Hiragana, Katakana
+        | 0x3400..=0x4DBF // CJK Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF // Hangul syllables
+        | 0xF900..=0xFAFF) // CJK Compatibility Ideographs
+}
+
+/// Word error rate between a reference and a hypothesis transcript.
+///
+/// Computed as Levenshtein distance over words divided by the reference
+/// length. An empty reference yields 0.0 for an empty hypothesis, else 1.0.
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f32 {
+    let reference = tokenize(reference);
+    let hypothesis = tokenize(hypothesis);
+
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+
+    let mut prev: Vec<usize> = (0..=hypothesis.len()).collect();
+    let mut curr = vec![0; hypothesis.len() + 1];
+    for (i, r) in reference.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, h) in hypothesis.iter().enumerate() {
+            let substitution = prev[j] + usize::from(r != h);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[hypothesis.len()] as f32 / reference.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wer_identical_ignores_case_and_punctuation() {
+        assert_eq!(word_error_rate("Hello, world!", "hello world"), 0.0);
+    }
+
+    #[test]
+    fn test_wer_counts_edits() {
+        // one substitution, one deletion over four words
+        let wer = word_error_rate("the quick brown fox", "the quack fox");
+        assert!((wer - 0.5).abs() < 1e-6);
+
+        assert_eq!(word_error_rate("", ""), 0.0);
+        assert_eq!(word_error_rate("", "noise"), 1.0);
+        assert_eq!(word_error_rate("hello", ""), 1.0);
+    }
+
+    #[test]
+    fn test_wer_cjk_per_character() {
+        let wer = word_error_rate("今天天气很好", "今天天气不好");
+        assert!((wer - 1.0 / 6.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,376 @@
+//! Write-ahead journal of accepted requests.
+//!
+//! Each accepted request is appended to a JSON-lines file before work
+//! starts, and again when it reaches a terminal status; the last line for a
+//! request wins. On restart, requests that were accepted but never finished
+//! are marked interrupted, so operators can see which jobs a crash lost and
+//! clients can still look up the final status of earlier requests.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::error::{Error, Result};
+
+/// Lifecycle status recorded for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalStatus {
+    /// Accepted and in progress
+    Accepted,
+    /// Completed successfully
+    Finished,
+    /// Ended with an error
+    Failed,
+    /// Dropped before completing, e.g. the client disconnected
+    Aborted,
+    /// In progress when the server stopped
+    Interrupted,
+}
+
+impl JournalStatus {
+    /// Whether the request can no longer change status
+    pub fn is_terminal(self) -> bool {
+        self != Self::Accepted
+    }
+}
+
+/// Latest journaled state of one request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub request_id: String,
+    pub status: JournalStatus,
+    /// Kind of work, e.g. `tts` or `tts_stream`
+    pub kind: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Unix time in milliseconds
+    pub accepted_at_ms: u64,
+    #[serde(default)]
+    pub finished_at_ms: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl JournalEntry {
+    /// New in-progress entry accepted now
+    pub fn accepted(request_id: impl Into<String>, kind: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            status: JournalStatus::Accepted,
+            kind: kind.into(),
+            model: None,
+            client_id: None,
+            accepted_at_ms: now_ms(),
+            finished_at_ms: None,
+            error: None,
+        }
+    }
+
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_client_id(mut self, client_id: Option<String>) -> Self {
+        self.client_id = client_id;
+        self
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    entries: HashMap<String, JournalEntry>,
+    /// Request IDs, oldest first
+    order: VecDeque<String>,
+    /// Lines in the file since it was last compacted
+    lines: usize,
+}
+
+impl JournalState {
+    fn insert(&mut self, entry: JournalEntry, max_entries: usize) {
+        if !self.entries.contains_key(&entry.request_id) {
+            self.order.push_back(entry.request_id.clone());
+        }
+        self.entries.insert(entry.request_id.clone(), entry);
+        while max_entries > 0 && self.order.len() > max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn ordered(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.order.iter().filter_map(|id| self.entries.get(id))
+    }
+}
+
+/// Append-only request journal backed by a file.
+#[derive(Debug)]
+pub struct RequestJournal {
+    path: PathBuf,
+    /// Entries kept in memory and on compaction (0 = unlimited)
+    max_entries: usize,
+    /// Open file and in-memory state, locked together
+    inner: Mutex<(File, JournalState)>,
+    /// Requests found unfinished when the journal was opened
+    interrupted: Vec<String>,
+}
+
+impl RequestJournal {
+    /// Open or create the journal at `path`, recovering its entries.
+    ///
+    /// Unfinished requests from the previous run are marked interrupted and
+    /// the file is compacted to one line per kept request.
+    pub fn open(path: impl AsRef<Path>, max_entries: usize) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut state = JournalState::default();
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<JournalEntry>(&line) {
+                    Ok(entry) => state.insert(entry, max_entries),
+                    // A crash mid-write can leave a torn last line
+                    Err(e) => warn!(
+                        "Skipping unreadable journal line {} in {:?}: {}",
+                        number + 1,
+                        path,
+                        e
+                    ),
+                }
+            }
+        }
+
+        let finished_at_ms = now_ms();
+        let mut interrupted = Vec::new();
+        for entry in state.entries.values_mut() {
+            if entry.status == JournalStatus::Accepted {
+                entry.status = JournalStatus::Interrupted;
+                entry.finished_at_ms = Some(finished_at_ms);
+                interrupted.push(entry.request_id.clone());
+            }
+        }
+        if !interrupted.is_empty() {
+            warn!(
+                "{} request(s) were in progress when the server last stopped",
+                interrupted.len()
+            );
+        }
+
+        let file = Self::compact(&path, &mut state)?;
+        info!(
+            "Request journal at {:?} ({} entries)",
+            path,
+            state.entries.len()
+        );
+
+        Ok(Self {
+            path,
+            max_entries,
+            inner: Mutex::new((file, state)),
+            interrupted,
+        })
+    }
+
+    /// Rewrite the journal with the current entries, replacing it
+    /// atomically, and reopen it for appending
+    fn compact(path: &Path, state: &mut JournalState) -> Result<File> {
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        for entry in state.ordered() {
+            serde_json::to_writer(&mut file, entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        state.lines = state.entries.len();
+        Ok(OpenOptions::new().append(true).open(path)?)
+    }
+
+    /// Journal file location
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the entry's latest state, compacting once the file holds
+    /// twice as many lines as kept entries
+    fn append(
+        &self,
+        file: &mut File,
+        state: &mut JournalState,
+        entry: &JournalEntry,
+    ) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+        state.lines += 1;
+        if self.max_entries > 0 && state.lines > 2 * self.max_entries {
+            *file = Self::compact(&self.path, state)?;
+        }
+        Ok(())
+    }
+
+    /// Durably record an accepted request before work on it starts.
+    pub fn accept(self: &Arc<Self>, entry: JournalEntry) -> Result<JournalTicket> {
+        let request_id = entry.request_id.clone();
+        {
+            let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+            let (file, state) = &mut *inner;
+            if state.entries.contains_key(&request_id) {
+                return Err(Error::InvalidInput(format!(
+                    "Request {} is already journaled",
+                    request_id
+                )));
+            }
+            state.insert(entry.clone(), self.max_entries);
+            if let Err(e) = self.append(file, state, &entry) {
+                state.entries.remove(&request_id);
+                state.order.retain(|id| id != &request_id);
+                return Err(e);
+            }
+        }
+        Ok(JournalTicket {
+            journal: self.clone(),
+            request_id,
+            done: false,
+        })
+    }
+
+    /// Record a terminal status; requests already terminal keep theirs.
+    pub fn complete(&self, request_id: &str, status: JournalStatus, error: Option<String>) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (file, state) = &mut *inner;
+        let Some(entry) = state.entries.get_mut(request_id) else {
+            return;
+        };
+        if entry.status.is_terminal() {
+            return;
+        }
+        entry.status = status;
+        entry.finished_at_ms = Some(now_ms());
+        entry.error = error;
+        let entry = entry.clone();
+        if let Err(e) = self.append(file, state, &entry) {
+            warn!("Failed to journal {:?} for {}: {}", status, request_id, e);
+        }
+    }
+
+    /// Latest state of a request
+    pub fn get(&self, request_id: &str) -> Option<JournalEntry> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.1.entries.get(request_id).cloned()
+    }
+
+    /// Requests the previous run left unfinished
+    pub fn interrupted(&self) -> Vec<JournalEntry> {
+        self.interrupted
+            .iter()
+            .filter_map(|id| self.get(id))
+            .collect()
+    }
+}
+
+/// Handle for a journaled request that records how it ended.
+///
+/// Dropping the ticket without finishing it records the request as aborted.
+#[derive(Debug)]
+pub struct JournalTicket {
+    journal: Arc<RequestJournal>,
+    request_id: String,
+    done: bool,
+}
+
+impl JournalTicket {
+    /// Record successful completion
+    pub fn finish(mut self) {
+        self.done = true;
+        self.journal
+            .complete(&self.request_id, JournalStatus::Finished, None);
+    }
+
+    /// Record failure with the error message
+    pub fn fail(mut self, error: impl std::fmt::Display) {
+        self.done = true;
+        self.journal.complete(
+            &self.request_id,
+            JournalStatus::Failed,
+            Some(error.to_string()),
+        );
+    }
+}
+
+impl Drop for JournalTicket {
+    fn drop(&mut self) {
+        if !self.done {
+            self.journal
+                .complete(&self.request_id, JournalStatus::Aborted, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_marks_unfinished_requests() {
+        let dir = std::env::temp_dir().join(format!("izwi-journal-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("requests.jsonl");
+
+        {
+            let journal = Arc::new(RequestJournal::open(&path, 0).unwrap());
+            journal
+                .accept(JournalEntry::accepted("done", "tts"))
+                .unwrap()
+                .finish();
+            journal
+                .accept(JournalEntry::accepted("failed", "tts"))
+                .unwrap()
+                .fail("boom");
+            let running = journal
+                .accept(JournalEntry::accepted("running", "tts"))
+                .unwrap();
+            // Simulate a crash: the ticket never records an outcome
+            std::mem::forget(running);
+        }
+
+        let journal = RequestJournal::open(&path, 0).unwrap();
+        assert_eq!(journal.get("done").unwrap().status, JournalStatus::Finished);
+        assert_eq!(
+            journal.get("failed").unwrap().error.as_deref(),
+            Some("boom")
+        );
+        let lost = journal.interrupted();
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].request_id, "running");
+        assert_eq!(lost[0].status, JournalStatus::Interrupted);
+
+        // Compaction leaves one line per request
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+//! Language identification for requests that don't name a language
+
+use serde::Serialize;
+use whatlang::Lang;
+
+/// Language value that leaves the choice to the model
+pub const AUTO_LANGUAGE: &str = "Auto";
+
+/// Minimum detector confidence for a detected language to be used
+const MIN_CONFIDENCE: f64 = 0.25;
+
+/// Languages the Qwen3 TTS and ASR models accept, by the name they expect
+const SUPPORTED_LANGUAGES: &[(Lang, &str)] = &[
+    (Lang::Cmn, "Chinese"),
+    (Lang::Eng, "English"),
+    (Lang::Jpn, "Japanese"),
+    (Lang::Kor, "Korean"),
+    (Lang::Deu, "German"),
+    (Lang::Fra, "French"),
+    (Lang::Rus, "Russian"),
+    (Lang::Por, "Portuguese"),
+    (Lang::Spa, "Spanish"),
+    (Lang::Ita, "Italian"),
+];
+
+/// Language found in a piece of text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectedLanguage {
+    /// Name passed to the models (e.g. "English")
+    pub name: &'static str,
+    /// ISO 639-3 code
+    pub code: &'static str,
+    pub confidence: f64,
+}
+
+/// Detect the language of `text`.
+///
+/// Returns `None` when the text is too short or ambiguous to tell, or the
+/// language is not one the models support.
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let info = whatlang::detect(text)?;
+    if info.confidence() < MIN_CONFIDENCE {
+        return None;
+    }
+    let (lang, name) = SUPPORTED_LANGUAGES
+        .iter()
+        .find(|(lang, _)| *lang == info.lang())?;
+    Some(DetectedLanguage {
+        name,
+        code: lang.code(),
+        confidence: info.confidence(),
+    })
+}
+
+/// Canonical model name for a client-supplied language ("en", "eng",
+/// "english" -> "English"); unknown values are returned unchanged
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim();
+    let lower = language.to_lowercase();
+    SUPPORTED_LANGUAGES
+        .iter()
+        .find(|(lang, name)| {
+            name.to_lowercase() == lower || lang.code() == lower || iso_639_1(*lang) == lower
+        })
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| language.to_string())
+}
+
+/// Language to request from the model: the client's choice when given,
+/// otherwise the language detected in `text`, otherwise [`AUTO_LANGUAGE`]
+pub fn resolve_language(requested: Option<&str>, text: &str) -> String {
+    match requested {
+        Some(language) if !language.trim().is_empty() => normalize_language(language),
+        _ => detect_language(text)
+            .map(|d| d.name.to_string())
+            .unwrap_or_else(|| AUTO_LANGUAGE.to_string()),
+    }
+}
+
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Cmn => "zh",
+        Lang::Eng => "en",
+        Lang::Jpn => "ja",
+        Lang::Kor => "ko",
+        Lang::Deu => "de",
+        Lang::Fra => "fr",
+        Lang::Rus => "ru",
+        Lang::Por => "pt",
+        Lang::Spa => "es",
+        Lang::Ita => "it",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_resolve_language() {
+        let english =
+            detect_language("The quick brown fox jumps over the lazy dog near the river.");
+        assert_eq!(english.map(|d| d.name), Some("English"));
+        let german = detect_language("Ich habe heute keine Zeit, weil ich arbeiten muss.");
+        assert_eq!(german.map(|d| d.name), Some("German"));
+        // Unsupported by the models
+        assert_eq!(
+            detect_language("Dit is een Nederlandse zin over het weer."),
+            None
+        );
+
+        assert_eq!(normalize_language("en"), "English");
+        assert_eq!(normalize_language("ZH"), "Chinese");
+        assert_eq!(normalize_language("Swahili"), "Swahili");
+
+        assert_eq!(resolve_language(Some("fra"), "Hello there"), "French");
+        assert_eq!(resolve_language(None, "?!"), AUTO_LANGUAGE);
+    }
+}
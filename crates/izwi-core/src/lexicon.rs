@@ -0,0 +1,244 @@
+//! Pronunciation lexicon applied to TTS input text
+//!
+//! The lexicon is a TOML file mapping words or phrases to a respelling or to
+//! IPA phonemes:
+//!
+//! ```toml
+//! Nguyen = "win"
+//! "Los Angeles" = "loss an-jel-us"
+//! Worcester = { phonemes = "ˈwʊstə" }
+//! ```
+//!
+//! Matches are whole-word and case-insensitive, longest entry first.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// File name of the lexicon inside the models directory
+pub const LEXICON_FILE: &str = "lexicon.toml";
+
+/// How a lexicon entry is spoken
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Pronunciation {
+    /// Plain-text respelling read by the model
+    Respelling(String),
+    /// IPA phonemes, passed inline between slashes
+    Phonemes { phonemes: String },
+}
+
+impl Pronunciation {
+    /// Text substituted for a matched word
+    pub fn render(&self) -> String {
+        match self {
+            Self::Respelling(text) => text.clone(),
+            Self::Phonemes { phonemes } => format!("/{}/", phonemes),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Respelling(text) => text.trim().is_empty(),
+            Self::Phonemes { phonemes } => phonemes.trim().is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Lowercased word or phrase
+    word: Vec<char>,
+    replacement: String,
+}
+
+/// Word and phrase pronunciations applied before synthesis
+#[derive(Debug, Clone, Default)]
+pub struct Lexicon {
+    entries: BTreeMap<String, Pronunciation>,
+    fingerprint: String,
+}
+
+impl Lexicon {
+    /// Create a lexicon from word -> pronunciation entries, validating them
+    pub fn from_entries(entries: HashMap<String, Pronunciation>) -> Result<Self> {
+        let mut lexicon = Self::default();
+        for (word, pronunciation) in entries {
+            lexicon.insert(&word, pronunciation)?;
+        }
+        lexicon.fingerprint = lexicon.compute_fingerprint();
+        Ok(lexicon)
+    }
+
+    /// Load the lexicon at `path`; a missing file yields an empty lexicon
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let entries: HashMap<String, Pronunciation> = toml::from_str(&content).map_err(|e| {
+            Error::ConfigError(format!("Invalid lexicon {}: {}", path.display(), e))
+        })?;
+        Self::from_entries(entries)
+    }
+
+    fn insert(&mut self, word: &str, pronunciation: Pronunciation) -> Result<()> {
+        let word = word.trim();
+        if word.is_empty() {
+            return Err(Error::InvalidInput(
+                "Pronunciation entry has an empty word".to_string(),
+            ));
+        }
+        if pronunciation.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Pronunciation of '{}' is empty",
+                word
+            )));
+        }
+        self.entries.insert(word.to_lowercase(), pronunciation);
+        Ok(())
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries keyed by lowercased word, in alphabetical order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Pronunciation)> {
+        self.entries.iter().map(|(word, p)| (word.as_str(), p))
+    }
+
+    /// Look up the pronunciation of a word or phrase
+    pub fn get(&self, word: &str) -> Option<&Pronunciation> {
+        self.entries.get(&word.trim().to_lowercase())
+    }
+
+    /// Hash of the entries, used to key cached audio
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    fn compute_fingerprint(&self) -> String {
+        if self.entries.is_empty() {
+            return String::new();
+        }
+        let json = serde_json::to_vec(&self.entries).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&json))
+    }
+
+    /// Rewrite `text` using the lexicon
+    pub fn apply(&self, text: &str) -> String {
+        self.apply_with(text, &HashMap::new())
+            .unwrap_or_else(|_| text.to_string())
+    }
+
+    /// Rewrite `text` using the lexicon and per-request overrides, which take
+    /// precedence over lexicon entries for the same word
+    pub fn apply_with(
+        &self,
+        text: &str,
+        overrides: &HashMap<String, Pronunciation>,
+    ) -> Result<String> {
+        let mut merged = self.entries.clone();
+        let mut request = Self::default();
+        for (word, pronunciation) in overrides {
+            request.insert(word, pronunciation.clone())?;
+        }
+        merged.extend(request.entries);
+        if merged.is_empty() {
+            return Ok(text.to_string());
+        }
+
+        let mut entries: Vec<Entry> = merged
+            .into_iter()
+            .map(|(word, pronunciation)| Entry {
+                word: word.chars().collect(),
+                replacement: pronunciation.render(),
+            })
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.word.len()));
+
+        Ok(replace_words(text, &entries))
+    }
+}
+
+/// Replace whole-word, case-insensitive matches of `entries` in `text`
+fn replace_words(text: &str, entries: &[Entry]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let at_boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+        let matched = at_boundary
+            .then(|| entries.iter().find(|e| matches_at(&chars, i, &e.word)))
+            .flatten();
+        match matched {
+            Some(entry) => {
+                out.push_str(&entry.replacement);
+                i += entry.word.len();
+            }
+            None => {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn matches_at(chars: &[char], start: usize, word: &[char]) -> bool {
+    let end = start + word.len();
+    if end > chars.len() {
+        return false;
+    }
+    let same = chars[start..end]
+        .iter()
+        .zip(word)
+        .all(|(c, w)| c.to_lowercase().eq(w.to_lowercase()));
+    same && chars.get(end).is_none_or(|c| !c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_lexicon_with_overrides() {
+        let lexicon: HashMap<String, Pronunciation> = toml::from_str(
+            r#"
+            Nguyen = "win"
+            "Los Angeles" = "loss an-jel-us"
+            Worcester = { phonemes = "wʊstə" }
+            "#,
+        )
+        .unwrap();
+        let lexicon = Lexicon::from_entries(lexicon).unwrap();
+        assert_eq!(lexicon.len(), 3);
+
+        assert_eq!(
+            lexicon.apply("Mr. NGUYEN's trip from los angeles to Worcester."),
+            "Mr. win's trip from loss an-jel-us to /wʊstə/."
+        );
+        // Whole words only
+        assert_eq!(lexicon.apply("Nguyenville"), "Nguyenville");
+
+        let overrides = HashMap::from([(
+            "nguyen".to_string(),
+            Pronunciation::Respelling("nwen".to_string()),
+        )]);
+        assert_eq!(lexicon.apply_with("Nguyen", &overrides).unwrap(), "nwen");
+
+        let empty = HashMap::from([("x".to_string(), Pronunciation::Respelling(" ".into()))]);
+        assert!(lexicon.apply_with("x", &empty).is_err());
+        assert_ne!(lexicon.fingerprint(), Lexicon::default().fingerprint());
+    }
+}
@@ -1,7 +1,7 @@
 //! Izwi Core - High-Performance Audio Inference Engine
 //!
 //! This crate provides a production-ready inference engine for audio models
-//! (Qwen3-TTS, LFM2-Audio) on Apple Silicon and CPU devices.
+//! (Qwen3-TTS, LFM2-Audio) on Apple Silicon, NVIDIA GPUs and CPU devices.
 //!
 //! # Architecture
 //!
@@ -24,22 +24,47 @@
 //! ```
 
 pub mod audio;
+pub mod audit;
 pub mod config;
+pub mod device;
 pub mod engine;
 pub mod error;
 pub mod inference;
+pub mod journal;
+pub mod language;
+pub mod lexicon;
+pub mod longform;
 pub mod model;
+pub mod storage;
+pub mod tenant;
+pub mod text;
 pub mod tokenizer;
+pub mod upload;
+pub mod usage;
+pub mod voice;
 
 // Re-export main types from the new engine module
 pub use engine::{
-    Engine, EngineCore, EngineCoreConfig, EngineCoreRequest, EngineMetrics, EngineOutput,
-    GenerationParams, KVCacheManager, ModelExecutor, OutputProcessor, RequestProcessor,
-    RequestStatus, Scheduler, SchedulerConfig, SchedulingPolicy, StreamingOutput,
+    CacheControl, CacheKey, CacheStats, Engine, EngineCore, EngineCoreConfig, EngineCoreRequest,
+    EngineMetrics, EngineOutput, GenerationParams, JobResult, JobStatus, KVCacheManager,
+    LogitsProcessor, ModelExecutor, OutputCache, OutputProcessor, RequestProcessor, RequestStatus,
+    ResultStore, SamplerPipeline, Scheduler, SchedulerConfig, SchedulingPolicy, StreamBackpressure,
+    StreamingOutput,
 };
 
 // Legacy re-exports for backward compatibility
-pub use config::EngineConfig;
-pub use error::{Error, Result};
-pub use inference::{AudioChunk, GenerationConfig, InferenceEngine};
+pub use audit::AuditLog;
+pub use config::{
+    ApiKeyConfig, AuditConfig, AuditPrivacy, AuthConfig, BridgeConfig, ConfigLoader, EngineConfig,
+    InputLimits, IzwiConfig, LongformConfig, OutputCacheConfig, PrivacyConfig, RedactionMode,
+    S3Config, SegmentationConfig, ServerConfig, StorageBackend, StorageConfig, UploadConfig,
+    UsageConfig,
+};
+pub use device::Device;
+pub use error::{Error, ErrorCode, Result};
+pub use inference::{AudioChunk, GenerationConfig, GenerationPreset, InferenceEngine};
+pub use longform::LongformJobs;
 pub use model::{ModelInfo, ModelManager, ModelVariant};
+pub use storage::{OutputStorage, SharedStorage};
+pub use upload::UploadStore;
+pub use usage::{Usage, UsageLedger, UsageReport};
@@ -0,0 +1,333 @@
+//! Long-form transcription jobs.
+//!
+//! A recording too long for one ASR pass is cut into segments at pauses
+//! (see [`crate::audio::plan_segments`]), the segments are transcribed in
+//! parallel and their transcripts joined in order. Segments cut mid-speech
+//! overlap, so the words at the end of one segment are often heard again at
+//! the start of the next; merging keeps them once. Jobs report how many
+//! segments are done while they run, and finished jobs are kept for `ttl`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::LongformConfig;
+use crate::engine::clock::{self, SharedClock};
+use crate::engine::JobStatus;
+use crate::error::{Error, Result};
+
+/// Most words compared when looking for text repeated across an overlap
+const MAX_OVERLAP_WORDS: usize = 16;
+
+/// Transcript of one segment
+#[derive(Debug, Clone, Serialize)]
+pub struct LongformSegment {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+}
+
+/// Transcript of a whole recording
+#[derive(Debug, Clone, Serialize)]
+pub struct LongformTranscript {
+    pub text: String,
+    /// English translation, for the `translate` task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+    pub language: Option<String>,
+    pub duration_secs: f64,
+    pub segments: Vec<LongformSegment>,
+}
+
+impl LongformTranscript {
+    /// Join the transcripts of consecutive segments. Where a segment
+    /// overlaps the one before, words repeated across the overlap are
+    /// dropped from it and it is taken to start where the previous ended.
+    pub fn merge(
+        mut segments: Vec<LongformSegment>,
+        language: Option<String>,
+        duration_secs: f64,
+    ) -> Self {
+        for i in 1..segments.len() {
+            let (before, after) = segments.split_at_mut(i);
+            let (prev, segment) = (&before[i - 1], &mut after[0]);
+            if segment.start_secs >= prev.end_secs {
+                continue;
+            }
+            segment.start_secs = prev.end_secs;
+            segment.text = drop_repeated(&prev.text, &segment.text);
+            if let (Some(prev), Some(translation)) = (&prev.translation, &segment.translation) {
+                segment.translation = Some(drop_repeated(prev, translation));
+            }
+        }
+
+        let join = |texts: Vec<&str>| {
+            texts
+                .into_iter()
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let text = join(segments.iter().map(|s| s.text.as_str()).collect());
+        let translation = segments.iter().any(|s| s.translation.is_some()).then(|| {
+            join(
+                segments
+                    .iter()
+                    .filter_map(|s| s.translation.as_deref())
+                    .collect(),
+            )
+        });
+        Self {
+            text,
+            translation,
+            language,
+            duration_secs,
+            segments,
+        }
+    }
+}
+
+/// Comparison form of a word: lowercase, without punctuation
+fn word_key(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// `next` without its leading words that repeat the last words of `prev`
+fn drop_repeated(prev: &str, next: &str) -> String {
+    let tail: Vec<String> = prev
+        .split_whitespace()
+        .rev()
+        .take(MAX_OVERLAP_WORDS)
+        .map(word_key)
+        .collect();
+    let head: Vec<String> = next
+        .split_whitespace()
+        .take(MAX_OVERLAP_WORDS)
+        .map(word_key)
+        .collect();
+    let repeated = (1..=tail.len().min(head.len()))
+        .rev()
+        .find(|&n| tail[..n].iter().rev().eq(&head[..n]))
+        .unwrap_or(0);
+    if repeated == 0 {
+        return next.to_string();
+    }
+    next.split_whitespace()
+        .skip(repeated)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Progress of a long-form job, with its transcript once completed
+#[derive(Debug, Clone, Serialize)]
+pub struct LongformStatus {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub segments_total: usize,
+    pub segments_done: usize,
+    /// Share of segments transcribed, from 0 to 1
+    pub progress: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<LongformTranscript>,
+}
+
+#[derive(Debug)]
+struct LongformJob {
+    owner: Option<String>,
+    status: JobStatus,
+    segments_total: usize,
+    segments_done: usize,
+    transcript: Option<LongformTranscript>,
+    error: Option<String>,
+    finished_at: Option<Instant>,
+}
+
+/// Long-form jobs, running or finished.
+///
+/// Jobs belong to the API key that started them. Finished jobs expire `ttl`
+/// after they end and are purged lazily on access.
+pub struct LongformJobs {
+    config: LongformConfig,
+    ttl: Duration,
+    clock: SharedClock,
+    jobs: Mutex<HashMap<String, LongformJob>>,
+}
+
+impl LongformJobs {
+    pub fn new(config: LongformConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_secs),
+            config,
+            clock: clock::system_clock(),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Segmentation and concurrency settings jobs run with
+    pub fn config(&self) -> &LongformConfig {
+        &self.config
+    }
+
+    /// Use `clock` for finish times and expiry.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, LongformJob>> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let now = self.clock.now();
+        let ttl = self.ttl;
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished| now.saturating_duration_since(finished) < ttl)
+        });
+        jobs
+    }
+
+    fn status(id: &str, job: &LongformJob) -> LongformStatus {
+        LongformStatus {
+            job_id: id.to_string(),
+            status: job.status,
+            segments_total: job.segments_total,
+            segments_done: job.segments_done,
+            progress: match job.segments_total {
+                0 => 1.0,
+                total => job.segments_done as f32 / total as f32,
+            },
+            error: job.error.clone(),
+            transcript: job.transcript.clone(),
+        }
+    }
+
+    /// Start tracking a job of `segments_total` segments owned by `owner`
+    pub fn create(&self, owner: Option<&str>, segments_total: usize) -> LongformStatus {
+        let id = Uuid::new_v4().to_string();
+        let job = LongformJob {
+            owner: owner.map(String::from),
+            status: JobStatus::Running,
+            segments_total,
+            segments_done: 0,
+            transcript: None,
+            error: None,
+            finished_at: None,
+        };
+        let status = Self::status(&id, &job);
+        self.jobs().insert(id, job);
+        status
+    }
+
+    /// Count one more segment as transcribed
+    pub fn advance(&self, id: &str) {
+        if let Some(job) = self.jobs().get_mut(id) {
+            job.segments_done = (job.segments_done + 1).min(job.segments_total);
+        }
+    }
+
+    /// Store the transcript of a finished job
+    pub fn complete(&self, id: &str, transcript: LongformTranscript) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(id) {
+            job.status = JobStatus::Completed;
+            job.segments_done = job.segments_total;
+            job.transcript = Some(transcript);
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Mark a job as failed
+    pub fn fail(&self, id: &str, error: impl Into<String>) {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs().get_mut(id) {
+            job.status = JobStatus::Failed;
+            job.error = Some(error.into());
+            job.finished_at = Some(now);
+        }
+    }
+
+    /// Progress of a job if it belongs to `owner`; others' jobs aren't found
+    pub fn get(&self, id: &str, owner: Option<&str>) -> Result<LongformStatus> {
+        self.jobs()
+            .get(id)
+            .filter(|job| job.owner.as_deref() == owner)
+            .map(|job| Self::status(id, job))
+            .ok_or_else(|| Error::RequestNotFound(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MockClock;
+    use std::sync::Arc;
+
+    fn segment(start_secs: f64, end_secs: f64, text: &str) -> LongformSegment {
+        LongformSegment {
+            start_secs,
+            end_secs,
+            text: text.to_string(),
+            translation: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_reconciles_overlaps() {
+        let transcript = LongformTranscript::merge(
+            vec![
+                segment(0.0, 30.0, "We met at the old"),
+                // Cut mid-speech: "the old" was heard by both segments
+                segment(29.0, 59.0, "The old harbour, near the"),
+                // Cut at a pause: a repeated word is genuine
+                segment(59.0, 70.0, "the end."),
+            ],
+            Some("English".into()),
+            70.0,
+        );
+        assert_eq!(
+            transcript.text,
+            "We met at the old harbour, near the the end."
+        );
+        assert_eq!(transcript.segments[1].text, "harbour, near the");
+        assert_eq!(transcript.segments[1].start_secs, 30.0);
+        assert_eq!(transcript.translation, None);
+    }
+
+    #[test]
+    fn test_job_progress() {
+        let clock = Arc::new(MockClock::new());
+        let config = LongformConfig {
+            ttl_secs: 60,
+            ..Default::default()
+        };
+        let jobs = LongformJobs::new(config).with_clock(clock.clone());
+        let id = jobs.create(Some("alice"), 4).job_id;
+
+        jobs.advance(&id);
+        let status = jobs.get(&id, Some("alice")).unwrap();
+        assert_eq!(
+            (status.status, status.segments_done),
+            (JobStatus::Running, 1)
+        );
+        assert_eq!(status.progress, 0.25);
+        assert!(jobs.get(&id, Some("bob")).is_err());
+
+        jobs.complete(&id, LongformTranscript::merge(Vec::new(), None, 0.0));
+        let status = jobs.get(&id, Some("alice")).unwrap();
+        assert_eq!(status.status, JobStatus::Completed);
+        assert_eq!(status.progress, 1.0);
+        assert!(status.transcript.is_some());
+
+        // Finished jobs expire
+        clock.advance(Duration::from_secs(61));
+        assert!(jobs.get(&id, Some("alice")).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Artifact selection for sparse model downloads
+
+use serde::{Deserialize, Serialize};
+
+/// Role of a file within a model repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Model and generation configuration
+    Config,
+    /// Text tokenizer files and chat templates
+    Tokenizer,
+    /// Model weights and shard indexes
+    Weights,
+    /// Audio codec / detokenizer used only for speech output
+    Codec,
+    /// Anything else (docs, images, training leftovers)
+    Other,
+}
+
+impl ArtifactKind {
+    /// Classify a repository-relative path
+    pub fn classify(path: &str) -> Self {
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if path.starts_with("speech_tokenizer/")
+            || (name.starts_with("tokenizer-") && name.ends_with(".safetensors"))
+        {
+            return Self::Codec;
+        }
+        if name.ends_with(".safetensors")
+            || name.ends_with(".safetensors.index.json")
+            || name.ends_with(".gguf")
+        {
+            return Self::Weights;
+        }
+        if name.starts_with("tokenizer")
+            || name.starts_with("chat_template")
+            || matches!(
+                name,
+                "vocab.json" | "merges.txt" | "special_tokens_map.json" | "added_tokens.json"
+            )
+        {
+            return Self::Tokenizer;
+        }
+        if name.ends_with("config.json") {
+            return Self::Config;
+        }
+        Self::Other
+    }
+}
+
+/// A file in a model repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoFile {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Size in bytes (estimated when the hub listing is unavailable)
+    pub size: u64,
+    /// SHA256 of the contents, known for LFS files in the hub listing
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Whether `size` is an estimate rather than the listed size
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+/// Include/exclude rules deciding which repository files are fetched.
+///
+/// Patterns are globs where `*` matches within a path segment and `**`
+/// matches across segments. Patterns without a `/` match the file name in
+/// any directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadFilter {
+    /// Files must match at least one of these
+    pub include: Vec<String>,
+    /// Files matching any of these are skipped
+    pub exclude: Vec<String>,
+    /// Artifact kinds skipped regardless of patterns
+    pub skip_kinds: Vec<ArtifactKind>,
+}
+
+impl Default for DownloadFilter {
+    fn default() -> Self {
+        Self {
+            include: [
+                "*.json",
+                "merges.txt",
+                "*.jinja",
+                "tokenizer.model",
+                "*.safetensors",
+            ]
+            .map(String::from)
+            .to_vec(),
+            exclude: ["optimizer*", "training_args*", "original/**"]
+                .map(String::from)
+                .to_vec(),
+            skip_kinds: Vec::new(),
+        }
+    }
+}
+
+impl DownloadFilter {
+    /// Also skip codec artifacts (e.g. when only transcription is needed)
+    pub fn without_codec(mut self) -> Self {
+        if !self.skip_kinds.contains(&ArtifactKind::Codec) {
+            self.skip_kinds.push(ArtifactKind::Codec);
+        }
+        self
+    }
+
+    /// Add an include pattern
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Add an exclude pattern
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Whether a repository path should be downloaded
+    pub fn allows(&self, path: &str) -> bool {
+        if self.skip_kinds.contains(&ArtifactKind::classify(path)) {
+            return false;
+        }
+        if self.exclude.iter().any(|p| path_matches(p, path)) {
+            return false;
+        }
+        self.include.iter().any(|p| path_matches(p, path))
+    }
+
+    /// Split a repository listing into files to fetch and files to skip
+    pub fn plan(&self, files: Vec<RepoFile>) -> DownloadPlan {
+        let (files, skipped) = files.into_iter().partition(|f| self.allows(&f.path));
+        DownloadPlan { files, skipped }
+    }
+}
+
+/// Files selected for download and those left out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    pub files: Vec<RepoFile>,
+    pub skipped: Vec<RepoFile>,
+}
+
+impl DownloadPlan {
+    /// Total bytes selected for download
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    /// Total bytes left out by the filter
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Match a repository path against a glob pattern
+fn path_matches(pattern: &str, path: &str) -> bool {
+    if pattern.contains('/') {
+        glob_match(pattern.as_bytes(), path.as_bytes())
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => match text {
+            [c, tail @ ..] => *c != b'/' && glob_match(rest, tail),
+            [] => false,
+        },
+        [c, rest @ ..] => match text {
+            [t, tail @ ..] => t == c && glob_match(rest, tail),
+            [] => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> RepoFile {
+        RepoFile {
+            path: path.to_string(),
+            size,
+            sha256: None,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(ArtifactKind::classify("config.json"), ArtifactKind::Config);
+        assert_eq!(
+            ArtifactKind::classify("vocab.json"),
+            ArtifactKind::Tokenizer
+        );
+        assert_eq!(
+            ArtifactKind::classify("model-00001-of-00002.safetensors"),
+            ArtifactKind::Weights
+        );
+        assert_eq!(
+            ArtifactKind::classify("speech_tokenizer/config.json"),
+            ArtifactKind::Codec
+        );
+        assert_eq!(
+            ArtifactKind::classify("tokenizer-e351c8d8-checkpoint125.safetensors"),
+            ArtifactKind::Codec
+        );
+        assert_eq!(ArtifactKind::classify("README.md"), ArtifactKind::Other);
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        assert!(path_matches("*.json", "speech_tokenizer/config.json"));
+        assert!(path_matches(
+            "speech_tokenizer/*",
+            "speech_tokenizer/model.safetensors"
+        ));
+        assert!(!path_matches(
+            "speech_tokenizer/*",
+            "speech_tokenizer/a/b.bin"
+        ));
+        assert!(path_matches("original/**", "original/a/b.pth"));
+        assert!(path_matches(
+            "model-?????-of-*.safetensors",
+            "model-00001-of-00002.safetensors"
+        ));
+    }
+
+    #[test]
+    fn test_default_plan_skips_unneeded_files() {
+        let listing = vec![
+            file("config.json", 1_000),
+            file("model.safetensors", 1_000_000),
+            file("pytorch_model.bin", 1_000_000),
+            file("optimizer.safetensors", 2_000_000),
+            file("README.md", 500),
+            file("speech_tokenizer/model.safetensors", 100_000),
+        ];
+
+        let plan = DownloadFilter::default().plan(listing.clone());
+        assert_eq!(plan.files.len(), 3);
+        assert_eq!(plan.skipped_bytes(), 3_000_500);
+
+        let plan = DownloadFilter::default().without_codec().plan(listing);
+        assert_eq!(plan.total_bytes(), 1_001_000);
+        assert_eq!(plan.skipped.len(), 4);
+    }
+}
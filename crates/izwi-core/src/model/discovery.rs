@@ -0,0 +1,228 @@
+//! Discovery of model folders present on disk
+//!
+//! Any folder holding a `config.json` or weight files is picked up, whether
+//! or not it is one of the built-in [`ModelVariant`]s. Capabilities are
+//! inferred from the architecture names in its config (or the GGUF
+//! `general.architecture` key when there is no config).
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::model::gguf::GgufFile;
+use crate::model::info::ModelVariant;
+
+/// What a discovered model can be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelCapability {
+    Tts,
+    Asr,
+    Chat,
+    /// Audio tokenizer / codec
+    Codec,
+}
+
+/// Storage format of a model's weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightsFormat {
+    Safetensors,
+    Gguf,
+}
+
+/// A model folder found on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredModel {
+    /// Folder name, used as the model id
+    pub id: String,
+    pub path: PathBuf,
+    /// Built-in variant the folder is a copy of, if any
+    pub variant: Option<ModelVariant>,
+    pub architecture: Option<String>,
+    pub format: Option<WeightsFormat>,
+    pub capabilities: Vec<ModelCapability>,
+    pub size_bytes: u64,
+}
+
+/// Scan `dirs` for model folders (one level deep); unreadable dirs are skipped
+pub fn discover_models(dirs: &[PathBuf]) -> Vec<DiscoveredModel> {
+    let mut models: Vec<DiscoveredModel> = Vec::new();
+    for dir in dirs {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                if dir.exists() {
+                    warn!("Cannot scan model directory {:?}: {}", dir, e);
+                }
+                continue;
+            }
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.is_dir() || models.iter().any(|m| m.path == path) {
+                continue;
+            }
+            if let Some(model) = inspect_model_dir(&path) {
+                debug!("Discovered model {} at {:?}", model.id, path);
+                models.push(model);
+            }
+        }
+    }
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
+
+/// Describe a model folder, or `None` when it holds no config or weights
+pub fn inspect_model_dir(path: &Path) -> Option<DiscoveredModel> {
+    let id = path.file_name()?.to_string_lossy().into_owned();
+    let files: Vec<PathBuf> = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect();
+    let has_ext = |ext: &str| {
+        files
+            .iter()
+            .any(|f| f.extension().is_some_and(|e| e == ext))
+    };
+    let format = if has_ext("safetensors") {
+        Some(WeightsFormat::Safetensors)
+    } else if has_ext("gguf") {
+        Some(WeightsFormat::Gguf)
+    } else {
+        None
+    };
+
+    let config: Option<serde_json::Value> = std::fs::read_to_string(path.join("config.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok());
+    if config.is_none() && format.is_none() {
+        return None;
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    if let Some(config) = &config {
+        if let Some(archs) = config.get("architectures").and_then(|a| a.as_array()) {
+            names.extend(archs.iter().filter_map(|a| a.as_str()).map(str::to_string));
+        }
+        if let Some(model_type) = config.get("model_type").and_then(|t| t.as_str()) {
+            names.push(model_type.to_string());
+        }
+    } else if format == Some(WeightsFormat::Gguf) {
+        let gguf = files
+            .iter()
+            .find(|f| f.extension().is_some_and(|e| e == "gguf"))?;
+        match GgufFile::open(gguf) {
+            Ok(gguf) => names.extend(gguf.architecture().map(str::to_string)),
+            Err(e) => warn!("Cannot read {:?}: {}", gguf, e),
+        }
+    }
+
+    let has_chat_template = files.iter().any(|f| {
+        f.file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with("chat_template"))
+    });
+    let has_talker = config
+        .as_ref()
+        .is_some_and(|c| c.get("talker_config").is_some());
+    let capabilities = infer_capabilities(&names, has_talker, has_chat_template);
+
+    Some(DiscoveredModel {
+        variant: ModelVariant::all()
+            .iter()
+            .copied()
+            .find(|v| v.dir_name() == id),
+        architecture: names.first().cloned(),
+        size_bytes: files
+            .iter()
+            .filter_map(|f| f.metadata().ok())
+            .map(|m| m.len())
+            .sum(),
+        id,
+        path: path.to_path_buf(),
+        format,
+        capabilities,
+    })
+}
+
+/// Capabilities implied by architecture / model type names
+fn infer_capabilities(
+    names: &[String],
+    has_talker: bool,
+    has_chat_template: bool,
+) -> Vec<ModelCapability> {
+    let names: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    let any = |needles: &[&str]| {
+        names
+            .iter()
+            .any(|name| needles.iter().any(|needle| name.contains(needle)))
+    };
+
+    if any(&["tokenizer", "codec"]) {
+        return vec![ModelCapability::Codec];
+    }
+    // Audio LMs (LFM2-Audio) listen, speak and chat
+    if any(&["lfm2_audio", "lfm2audio"]) || (any(&["lfm2"]) && any(&["audio"])) {
+        return vec![
+            ModelCapability::Tts,
+            ModelCapability::Asr,
+            ModelCapability::Chat,
+        ];
+    }
+
+    let mut capabilities = Vec::new();
+    if has_talker || any(&["tts", "texttospeech", "text_to_speech"]) {
+        capabilities.push(ModelCapability::Tts);
+    }
+    if any(&["asr", "whisper", "speechtotext", "speech_to_text", "ctc"]) {
+        capabilities.push(ModelCapability::Asr);
+    }
+    if capabilities.is_empty() && (has_chat_template || any(&["causallm"])) {
+        capabilities.push(ModelCapability::Chat);
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_model_folders() {
+        let root = std::env::temp_dir().join(format!("izwi-discover-{}", std::process::id()));
+        let write = |dir: &str, file: &str, content: &str| {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join(file), content).unwrap();
+        };
+        write(
+            "my-asr",
+            "config.json",
+            r#"{"architectures": ["Qwen3ASRForConditionalGeneration"]}"#,
+        );
+        write(
+            "my-llm",
+            "config.json",
+            r#"{"architectures": ["Qwen3ForCausalLM"], "model_type": "qwen3"}"#,
+        );
+        write("my-llm", "model.safetensors", "");
+        write(
+            "Qwen3-TTS-12Hz-0.6B-Base",
+            "config.json",
+            r#"{"model_type": "qwen3_tts", "talker_config": {}}"#,
+        );
+        write("notes", "README.md", "not a model");
+
+        let models = discover_models(&[root.clone(), root.join("missing")]);
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["Qwen3-TTS-12Hz-0.6B-Base", "my-asr", "my-llm"]);
+
+        assert_eq!(models[0].variant, Some(ModelVariant::Qwen3Tts12Hz06BBase));
+        assert_eq!(models[0].capabilities, [ModelCapability::Tts]);
+        assert_eq!(models[1].capabilities, [ModelCapability::Asr]);
+        assert_eq!(models[1].format, None);
+        assert_eq!(models[2].capabilities, [ModelCapability::Chat]);
+        assert_eq!(models[2].format, Some(WeightsFormat::Safetensors));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,21 +1,50 @@
 //! Model downloading from HuggingFace Hub
 
-use hf_hub::api::sync::Api;
+use hf_hub::api::sync::{Api, ApiBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::blocking::Client;
-use std::fs::File;
-use std::io::Write;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{AUTHORIZATION, RANGE};
+use reqwest::{Proxy, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
+use crate::engine::TaskType;
 use crate::error::{Error, Result};
+use crate::model::artifacts::{DownloadFilter, DownloadPlan, RepoFile};
 use crate::model::info::ModelVariant;
+use crate::model::quant::Quantization;
 
 const HF_BASE_URL: &str = "https://huggingface.co";
 
+/// Environment variables checked for a HuggingFace token, in order
+const HF_TOKEN_VARS: [&str; 2] = ["HF_TOKEN", "HUGGING_FACE_HUB_TOKEN"];
+
+/// Suffix of files still being downloaded
+const PARTIAL_SUFFIX: &str = ".incomplete";
+
+/// Attempts per file before giving up; each retry resumes where the last stopped
+const MAX_ATTEMPTS: usize = 3;
+
+/// Bytes read from the network per write (and per bandwidth reservation)
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Marker touched in a model directory whenever the model is used
+const LAST_USED_FILE: &str = ".last_used";
+
+/// How often aggregated progress is reported while files are in flight
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Progress update for model downloads
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DownloadProgress {
     pub variant: ModelVariant,
     pub downloaded_bytes: u64,
@@ -24,6 +53,96 @@ pub struct DownloadProgress {
     pub current_file: Option<String>,
     pub files_completed: usize,
     pub files_total: usize,
+    /// Files left out by the download filter
+    pub files_skipped: usize,
+    /// Bytes left out by the download filter
+    pub skipped_bytes: u64,
+    /// Bytes whose SHA256 matched the hub manifest
+    pub verified_bytes: u64,
+    /// Average transfer speed since the download started
+    pub bytes_per_sec: u64,
+    /// Estimated seconds until done, once a speed is known
+    pub eta_secs: Option<u64>,
+}
+
+/// Outcome of checking a downloaded model against the hub manifest
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RepairReport {
+    /// Files whose checksum matched
+    pub verified: Vec<String>,
+    /// Files without a published checksum that were present
+    pub unchecked: Vec<String>,
+    /// Missing or corrupt files that were re-downloaded
+    pub repaired: Vec<String>,
+    /// Files that could not be repaired
+    pub failed: Vec<String>,
+}
+
+impl RepairReport {
+    /// Whether every file is now present and, where possible, verified
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Entry in the Hub repository tree listing
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    lfs: Option<LfsPointer>,
+}
+
+/// LFS metadata of a tree entry; `oid` is the SHA256 of the contents
+#[derive(Debug, Deserialize)]
+struct LfsPointer {
+    oid: String,
+}
+
+/// Caps the combined transfer rate of all download workers.
+///
+/// Each read reserves its bytes; once reservations run ahead of the allowed
+/// rate, the reader sleeps until the budget catches up.
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    bytes_per_sec: u64,
+    /// Unspent budget (negative while in debt) and when it was last refilled
+    budget: Mutex<(f64, Instant)>,
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter allowing `bytes_per_sec` (at least 1)
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            budget: Mutex::new((0.0, Instant::now())),
+        }
+    }
+
+    /// Block until `bytes` may be transferred
+    pub fn acquire(&self, bytes: u64) {
+        let rate = self.bytes_per_sec as f64;
+        let wait = {
+            let mut budget = self.budget.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            // Allow at most one second of burst after an idle period
+            let refilled = budget.0 + now.duration_since(budget.1).as_secs_f64() * rate;
+            budget.0 = refilled.min(rate) - bytes as f64;
+            budget.1 = now;
+            if budget.0 < 0.0 {
+                Duration::from_secs_f64(-budget.0 / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
 }
 
 /// Model downloader for HuggingFace Hub
@@ -31,6 +150,11 @@ pub struct ModelDownloader {
     pub api: Api,
     pub models_dir: PathBuf,
     http_client: Client,
+    filters: HashMap<ModelVariant, DownloadFilter>,
+    concurrency: usize,
+    limiter: Option<Arc<BandwidthLimiter>>,
+    /// HuggingFace access token for private and gated repositories
+    token: Option<String>,
 }
 
 impl ModelDownloader {
@@ -39,31 +163,107 @@ impl ModelDownloader {
         // Ensure models directory exists
         std::fs::create_dir_all(&models_dir)?;
 
-        let api = Api::new().map_err(|e| Error::HfHubError(e.to_string()))?;
-        let http_client = Client::builder()
-            .timeout(std::time::Duration::from_secs(3600)) // 1 hour timeout for large files
+        let token = hf_token_from_env();
+        let api = ApiBuilder::new()
+            .with_token(token.clone())
             .build()
-            .map_err(|e| Error::HfHubError(format!("Failed to create HTTP client: {}", e)))?;
+            .map_err(|e| Error::HfHubError(e.to_string()))?;
 
         Ok(Self {
             api,
             models_dir,
-            http_client,
+            http_client: build_client(None)?,
+            filters: HashMap::new(),
+            concurrency: 1,
+            limiter: None,
+            token,
         })
     }
 
-    /// Download a file directly from HuggingFace using HTTP
-    fn download_file_http(&self, repo_id: &str, filename: &str, dest: &Path) -> Result<()> {
-        let url = format!("{}/{}/resolve/main/{}", HF_BASE_URL, repo_id, filename);
-        debug!("Downloading from URL: {}", url);
+    /// Authenticate with `token` instead of the one found in the environment
+    pub fn with_token(mut self, token: Option<String>) -> Result<Self> {
+        if token.is_some() {
+            self.api = ApiBuilder::new()
+                .with_token(token.clone())
+                .build()
+                .map_err(|e| Error::HfHubError(e.to_string()))?;
+            self.token = token;
+        }
+        Ok(self)
+    }
 
-        let response = self
+    /// Send requests through an HTTP(S) proxy such as `http://proxy:3128`.
+    ///
+    /// Without one, the standard `HTTPS_PROXY` / `NO_PROXY` variables apply.
+    pub fn with_proxy(mut self, proxy: Option<&str>) -> Result<Self> {
+        if proxy.is_some() {
+            self.http_client = build_client(proxy)?;
+        }
+        Ok(self)
+    }
+
+    /// Whether requests carry an access token
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// GET request to the Hub, authenticated when a token is set
+    fn hub_get(&self, url: &str) -> RequestBuilder {
+        let request = self
             .http_client
-            .get(&url)
-            .header("User-Agent", "izwi-audio/0.1.0")
+            .get(url)
+            .header("User-Agent", "izwi-audio/0.1.0");
+        match &self.token {
+            Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    /// Fetch up to `concurrency` files of a model at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Cap the combined download speed in bytes per second (0 = unlimited)
+    pub fn with_bandwidth_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.limiter = (bytes_per_sec > 0).then(|| Arc::new(BandwidthLimiter::new(bytes_per_sec)));
+        self
+    }
+
+    /// Override the download filter for a model variant
+    pub fn with_filter(mut self, variant: ModelVariant, filter: DownloadFilter) -> Self {
+        self.filters.insert(variant, filter);
+        self
+    }
+
+    /// Download filter in effect for a model variant
+    pub fn filter_for(&self, variant: ModelVariant) -> DownloadFilter {
+        self.filters.get(&variant).cloned().unwrap_or_else(|| {
+            if variant.is_gguf() {
+                DownloadFilter::default().include("*Q8_0.gguf")
+            } else {
+                DownloadFilter::default()
+            }
+        })
+    }
+
+    /// List files in a HuggingFace repository with their sizes
+    fn list_repo_files(&self, repo_id: &str) -> Result<Vec<RepoFile>> {
+        let url = format!(
+            "{}/api/models/{}/tree/main?recursive=true",
+            HF_BASE_URL, repo_id
+        );
+        debug!("Listing repository: {}", url);
+
+        let response = self
+            .hub_get(&url)
             .send()
             .map_err(|e| Error::HfHubError(format!("HTTP request failed: {}", e)))?;
 
+        if let Some(e) = auth_error(response.status(), repo_id, self.has_token()) {
+            return Err(e);
+        }
         if !response.status().is_success() {
             return Err(Error::HfHubError(format!(
                 "HTTP {} for {}",
@@ -72,28 +272,273 @@ impl ModelDownloader {
             )));
         }
 
-        // Create parent directories
+        let entries: Vec<TreeEntry> = response
+            .json()
+            .map_err(|e| Error::HfHubError(format!("Invalid repository listing: {}", e)))?;
+
+        Ok(entries
+            .into_iter()
+            .filter(|e| e.kind == "file")
+            .map(|e| RepoFile {
+                path: e.path,
+                size: e.size,
+                sha256: e.lfs.map(|lfs| lfs.oid),
+                estimated: false,
+            })
+            .collect())
+    }
+
+    /// Work out which files of a model repository to fetch.
+    ///
+    /// Uses the Hub listing when reachable and falls back to the known file
+    /// layout with estimated sizes otherwise. Fails when the Hub refuses
+    /// access, since every file would be refused too.
+    pub fn plan_download(
+        &self,
+        variant: ModelVariant,
+        filter: &DownloadFilter,
+    ) -> Result<DownloadPlan> {
+        let listing = match self.list_repo_files(variant.repo_id()) {
+            Ok(files) => files,
+            Err(e @ Error::HfAuthError(_)) => return Err(e),
+            Err(e) => {
+                warn!(
+                    "Could not list {}, using known file layout: {}",
+                    variant.repo_id(),
+                    e
+                );
+                self.get_model_files(variant)
+                    .into_iter()
+                    .zip(self.get_file_sizes(variant))
+                    .map(|(path, size)| RepoFile {
+                        path,
+                        size,
+                        sha256: None,
+                        estimated: true,
+                    })
+                    .collect()
+            }
+        };
+        Ok(filter.plan(listing))
+    }
+
+    /// Download a file directly from HuggingFace using HTTP.
+    ///
+    /// Data is written to an `.incomplete` file next to `dest`, so an
+    /// interrupted download resumes with a range request on the next attempt.
+    /// The finished file is checked against the manifest checksum before it
+    /// is moved into place. Bytes on disk for the file are tallied in `received`.
+    fn download_file_http(
+        &self,
+        repo_id: &str,
+        entry: &RepoFile,
+        dest: &Path,
+        received: &AtomicU64,
+    ) -> Result<()> {
+        let partial = partial_path(dest);
         if let Some(parent) = dest.parent() {
             std::fs::create_dir_all(parent)?;
         }
+        let resumed = std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+        received.fetch_add(resumed, Ordering::Relaxed);
+
+        let mut attempt = 1;
+        while let Err(e) = self.fetch_resumable(repo_id, &entry.path, &partial, received) {
+            if attempt == MAX_ATTEMPTS {
+                return Err(e);
+            }
+            warn!(
+                "Attempt {}/{} for {} failed, resuming: {}",
+                attempt, MAX_ATTEMPTS, entry.path, e
+            );
+            attempt += 1;
+        }
+
+        if let Err(e) = verify_file(&partial, entry) {
+            // A corrupt partial file cannot be resumed
+            let len = std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+            received.fetch_sub(len, Ordering::Relaxed);
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+        std::fs::rename(&partial, dest)?;
+        Ok(())
+    }
+
+    /// Append the rest of a file to `partial`, starting from its current length
+    fn fetch_resumable(
+        &self,
+        repo_id: &str,
+        filename: &str,
+        partial: &Path,
+        received: &AtomicU64,
+    ) -> Result<()> {
+        let url = format!("{}/{}/resolve/main/{}", HF_BASE_URL, repo_id, filename);
+        let offset = std::fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+
+        let mut request = self.hub_get(&url);
+        if offset > 0 {
+            debug!("Resuming {} from byte {}", filename, offset);
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        } else {
+            debug!("Downloading from URL: {}", url);
+        }
 
-        // Write to file
-        let bytes = response
-            .bytes()
-            .map_err(|e| Error::HfHubError(format!("Failed to read response: {}", e)))?;
+        let mut response = request
+            .send()
+            .map_err(|e| Error::HfHubError(format!("HTTP request failed: {}", e)))?;
 
-        let mut file = File::create(dest)?;
-        file.write_all(&bytes)?;
+        let mut file = match response.status() {
+            StatusCode::PARTIAL_CONTENT => OpenOptions::new().append(true).open(partial)?,
+            // Nothing left to fetch
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+            // Range ignored, start over
+            status if status.is_success() => {
+                received.fetch_sub(offset, Ordering::Relaxed);
+                File::create(partial)?
+            }
+            status => {
+                return Err(auth_error(status, repo_id, self.has_token())
+                    .unwrap_or_else(|| Error::HfHubError(format!("HTTP {} for {}", status, url))));
+            }
+        };
 
-        debug!("Downloaded {} bytes to {:?}", bytes.len(), dest);
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut written = 0u64;
+        loop {
+            let n = response
+                .read(&mut buf)
+                .map_err(|e| Error::HfHubError(format!("Failed to read response: {}", e)))?;
+            if n == 0 {
+                break;
+            }
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire(n as u64);
+            }
+            file.write_all(&buf[..n])?;
+            written += n as u64;
+            received.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        debug!("Downloaded {} bytes to {:?}", written, partial);
         Ok(())
     }
 
+    /// Whether an existing local file still matches the manifest size.
+    /// Estimated sizes only require the file to exist.
+    fn is_present(dest: &Path, entry: &RepoFile) -> bool {
+        match std::fs::metadata(dest) {
+            Ok(meta) => entry.estimated || meta.len() == entry.size,
+            Err(_) => false,
+        }
+    }
+
+    /// Check every file of a downloaded model and re-fetch the bad ones.
+    ///
+    /// Files with a published SHA256 are hashed; missing or mismatching
+    /// files are deleted and downloaded again. Other files are only checked
+    /// for presence.
+    pub fn repair(&self, variant: ModelVariant) -> Result<RepairReport> {
+        let repo_id = variant.repo_id();
+        let local_dir = self.model_path(variant);
+        let plan = self.plan_download(variant, &self.filter_for(variant))?;
+        let mut report = RepairReport::default();
+
+        for entry in &plan.files {
+            let dest = local_dir.join(&entry.path);
+            if dest.exists() {
+                match (&entry.sha256, verify_file(&dest, entry)) {
+                    (None, _) => {
+                        report.unchecked.push(entry.path.clone());
+                        continue;
+                    }
+                    (Some(_), Ok(())) => {
+                        report.verified.push(entry.path.clone());
+                        continue;
+                    }
+                    (Some(_), Err(e)) => {
+                        warn!("{}", e);
+                        std::fs::remove_file(&dest)?;
+                    }
+                }
+            }
+
+            info!("Re-downloading {}", entry.path);
+            match self.download_file_http(repo_id, entry, &dest, &AtomicU64::new(0)) {
+                Ok(()) => report.repaired.push(entry.path.clone()),
+                Err(e) => {
+                    warn!("Failed to repair {}: {}", entry.path, e);
+                    report.failed.push(entry.path.clone());
+                }
+            }
+        }
+
+        info!(
+            "Repair of {}: {} verified, {} repaired, {} failed",
+            variant,
+            report.verified.len(),
+            report.repaired.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
     /// Get the local path for a model variant
     pub fn model_path(&self, variant: ModelVariant) -> PathBuf {
         self.models_dir.join(variant.dir_name())
     }
 
+    /// Get the local path for a model variant stored at `quant`
+    pub fn quantized_path(&self, variant: ModelVariant, quant: Quantization) -> PathBuf {
+        self.models_dir.join(variant.local_dir_name(quant))
+    }
+
+    /// Fail early when the models directory can't hold the rest of `plan`
+    pub fn check_disk_space(&self, plan: &DownloadPlan, local_dir: &Path) -> Result<()> {
+        let needed = bytes_needed(plan, local_dir);
+        let Some(available) = available_space(&self.models_dir) else {
+            return Ok(());
+        };
+        if needed > available {
+            return Err(Error::InsufficientDiskSpace {
+                path: self.models_dir.display().to_string(),
+                needed,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// Bytes on disk for every copy of a model, quantized ones included
+    pub fn disk_usage(&self, variant: ModelVariant) -> u64 {
+        [Quantization::None, Quantization::Q8, Quantization::Q4]
+            .into_iter()
+            .map(|quant| self.quantized_path(variant, quant))
+            .filter(|path| path.is_dir())
+            .filter_map(|path| Self::dir_size(&path).ok())
+            .sum()
+    }
+
+    /// When any copy of a model was last used (or written), if it is on disk
+    pub fn last_used(&self, variant: ModelVariant) -> Option<SystemTime> {
+        [Quantization::None, Quantization::Q8, Quantization::Q4]
+            .into_iter()
+            .map(|quant| self.quantized_path(variant, quant))
+            .filter_map(|path| {
+                std::fs::metadata(path.join(LAST_USED_FILE))
+                    .or_else(|_| std::fs::metadata(&path))
+                    .and_then(|meta| meta.modified())
+                    .ok()
+            })
+            .max()
+    }
+
+    /// Record that the model copy in `path` was just used
+    pub fn mark_used(&self, path: &Path) {
+        if let Err(e) = File::create(path.join(LAST_USED_FILE)) {
+            debug!("Cannot record use of {:?}: {}", path, e);
+        }
+    }
+
     /// Check if a model is already downloaded
     pub fn is_downloaded(&self, variant: ModelVariant) -> bool {
         let path = self.model_path(variant);
@@ -102,6 +547,16 @@ impl ModelDownloader {
         }
 
         // Check for essential files based on model type
+        if variant.is_gguf() {
+            return std::fs::read_dir(&path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .any(|e| e.path().extension().is_some_and(|ext| ext == "gguf"))
+                })
+                .unwrap_or(false);
+        }
+
         if variant.is_lfm2() {
             // LFM2-Audio requires model.safetensors, config.json, and tokenizer files
             let has_model = path.join("model.safetensors").exists();
@@ -142,6 +597,26 @@ impl ModelDownloader {
 
     /// Download a model from HuggingFace Hub
     pub fn download(&self, variant: ModelVariant) -> Result<PathBuf> {
+        self.download_filtered(variant, &self.filter_for(variant))
+    }
+
+    /// Download only the artifacts needed for the given tasks.
+    ///
+    /// Codec weights are skipped unless speech synthesis is requested.
+    pub fn download_for_tasks(&self, variant: ModelVariant, tasks: &[TaskType]) -> Result<PathBuf> {
+        let mut filter = self.filter_for(variant);
+        if !tasks.contains(&TaskType::TTS) && !variant.is_tokenizer() {
+            filter = filter.without_codec();
+        }
+        self.download_filtered(variant, &filter)
+    }
+
+    /// Download the files of a model allowed by `filter`
+    pub fn download_filtered(
+        &self,
+        variant: ModelVariant,
+        filter: &DownloadFilter,
+    ) -> Result<PathBuf> {
         let repo_id = variant.repo_id();
         let local_dir = self.model_path(variant);
 
@@ -157,32 +632,24 @@ impl ModelDownloader {
         );
         pb.set_message(format!("Downloading {}", variant.display_name()));
 
-        // List and download all files
-        let files = self.get_model_files(variant);
-
-        for file in &files {
-            pb.set_message(format!("Downloading: {}", file));
-            debug!("Downloading file: {}", file);
-
-            let dest = local_dir.join(file);
+        let plan = self.plan_download(variant, filter)?;
+        self.check_disk_space(&plan, &local_dir)?;
+        info!(
+            "Fetching {} files ({} bytes), skipping {} files ({} bytes)",
+            plan.files.len(),
+            plan.total_bytes(),
+            plan.skipped.len(),
+            plan.skipped_bytes()
+        );
 
-            // Skip if already downloaded
-            if dest.exists() {
-                debug!("File already exists: {:?}", dest);
-                continue;
+        self.fetch_plan(variant, &plan, &local_dir, &|progress| {
+            if let Some(file) = &progress.current_file {
+                pb.set_message(format!(
+                    "Downloading: {} ({:.1}%)",
+                    file, progress.progress_percent
+                ));
             }
-
-            // Use direct HTTP download (more reliable than hf-hub for some repos)
-            match self.download_file_http(repo_id, file, &dest) {
-                Ok(()) => {
-                    debug!("Downloaded: {} -> {:?}", file, dest);
-                }
-                Err(e) => {
-                    warn!("Failed to download {}: {}", file, e);
-                    // Some files might be optional, continue
-                }
-            }
-        }
+        });
 
         pb.finish_with_message(format!("Downloaded {}", variant.display_name()));
 
@@ -203,70 +670,143 @@ impl ModelDownloader {
 
         info!("Downloading {} to {:?}", repo_id, local_dir);
 
-        let files = self.get_model_files(variant);
-        let total_files = files.len();
+        // Planning and fetching block, so keep them off the async runtime
+        let downloader = self.clone();
+        let dir = local_dir.clone();
+        let tx = progress_tx.clone();
+        let mut last = tokio::task::spawn_blocking(move || {
+            let plan = downloader.plan_download(variant, &downloader.filter_for(variant))?;
+            downloader.check_disk_space(&plan, &dir)?;
+            // Intermediate updates are dropped rather than stalling the workers
+            // when the receiver falls behind
+            Ok::<_, Error>(downloader.fetch_plan(variant, &plan, &dir, &|progress| {
+                let _ = tx.try_send(progress);
+            }))
+        })
+        .await
+        .map_err(|e| Error::DownloadError(e.to_string()))??;
+
+        // Send completion
+        last.downloaded_bytes = last.total_bytes;
+        last.progress_percent = 100.0;
+        last.current_file = None;
+        last.eta_secs = Some(0);
+        let _ = progress_tx.send(last).await;
 
-        let file_sizes = self.get_file_sizes(variant);
-        let total_bytes: u64 = file_sizes.iter().sum();
-        let mut downloaded_bytes: u64 = 0;
+        info!("Model downloaded to {:?}", local_dir);
+        Ok(local_dir)
+    }
 
-        for (idx, file) in files.iter().enumerate() {
-            let file_size = file_sizes.get(idx).copied().unwrap_or(0);
-            let progress = DownloadProgress {
+    /// Download the files of a plan on up to `concurrency` worker threads.
+    ///
+    /// Files already present are skipped. Aggregated progress is passed to
+    /// `on_progress` periodically and the final state is returned.
+    fn fetch_plan(
+        &self,
+        variant: ModelVariant,
+        plan: &DownloadPlan,
+        local_dir: &Path,
+        on_progress: &(dyn Fn(DownloadProgress) + Sync),
+    ) -> DownloadProgress {
+        let repo_id = variant.repo_id();
+        let (present, pending): (Vec<&RepoFile>, Vec<&RepoFile>) = plan
+            .files
+            .iter()
+            .partition(|entry| Self::is_present(&local_dir.join(&entry.path), entry));
+        for entry in &present {
+            debug!("File already exists: {}", entry.path);
+        }
+
+        let received = AtomicU64::new(present.iter().map(|f| f.size).sum());
+        // Bytes already on disk, including partial files, don't count towards speed
+        let initial_bytes = plan
+            .total_bytes()
+            .saturating_sub(bytes_needed(plan, local_dir));
+        let started = Instant::now();
+        let verified = AtomicU64::new(0);
+        let completed = AtomicUsize::new(present.len());
+        let next = AtomicUsize::new(0);
+        let current = Mutex::new(None::<String>);
+
+        let total_bytes = plan.total_bytes();
+        let files_total = plan.files.len();
+        let snapshot = || {
+            let downloaded_bytes = received.load(Ordering::Relaxed);
+            let files_completed = completed.load(Ordering::Relaxed);
+            let (bytes_per_sec, eta_secs) = transfer_rate(
+                downloaded_bytes.saturating_sub(initial_bytes),
+                started.elapsed(),
+                total_bytes.saturating_sub(downloaded_bytes),
+            );
+            DownloadProgress {
                 variant,
                 downloaded_bytes,
                 total_bytes,
                 progress_percent: if total_bytes > 0 {
-                    (downloaded_bytes as f32 / total_bytes as f32) * 100.0
+                    (downloaded_bytes as f32 / total_bytes as f32 * 100.0).min(100.0)
+                } else if files_total > 0 {
+                    files_completed as f32 / files_total as f32 * 100.0
                 } else {
-                    (idx as f32 / total_files as f32) * 100.0
+                    100.0
                 },
-                current_file: Some(file.clone()),
-                files_completed: idx,
-                files_total: total_files,
-            };
-            let _ = progress_tx.send(progress).await;
+                current_file: current.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                files_completed,
+                files_total,
+                files_skipped: plan.skipped.len(),
+                skipped_bytes: plan.skipped_bytes(),
+                verified_bytes: verified.load(Ordering::Relaxed),
+                bytes_per_sec,
+                eta_secs,
+            }
+        };
 
-            let dest = local_dir.join(file);
+        let workers = self.concurrency.min(pending.len());
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        while let Some(entry) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            *current.lock().unwrap_or_else(|e| e.into_inner()) =
+                                Some(entry.path.clone());
+                            let dest = local_dir.join(&entry.path);
 
-            // Skip if already downloaded
-            if dest.exists() {
-                debug!("File already exists: {:?}", dest);
-                downloaded_bytes += file_size;
-                continue;
-            }
+                            // Use direct HTTP download (more reliable than hf-hub for some repos)
+                            match self.download_file_http(repo_id, entry, &dest, &received) {
+                                Ok(()) => {
+                                    debug!("Downloaded: {} -> {:?}", entry.path, dest);
+                                    if entry.sha256.is_some() {
+                                        verified.fetch_add(entry.size, Ordering::Relaxed);
+                                    }
+                                }
+                                // Some files might be optional, continue
+                                Err(e) => warn!("Failed to download {}: {}", entry.path, e),
+                            }
+                            completed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    })
+                })
+                .collect();
 
-            // Use direct HTTP download (more reliable than hf-hub for some repos)
-            match self.download_file_http(repo_id, file, &dest) {
-                Ok(()) => {
-                    debug!("Downloaded: {} -> {:?}", file, dest);
-                    downloaded_bytes += file_size;
-                }
-                Err(e) => {
-                    warn!("Failed to download {}: {}", file, e);
-                }
+            while !handles.iter().all(|h| h.is_finished()) {
+                on_progress(snapshot());
+                std::thread::sleep(PROGRESS_INTERVAL);
             }
-        }
+        });
 
-        // Send completion
-        let progress = DownloadProgress {
-            variant,
-            downloaded_bytes: total_bytes,
-            total_bytes,
-            progress_percent: 100.0,
-            current_file: None,
-            files_completed: total_files,
-            files_total: total_files,
-        };
-        let _ = progress_tx.send(progress).await;
-
-        info!("Model downloaded to {:?}", local_dir);
-        Ok(local_dir)
+        let progress = snapshot();
+        on_progress(progress.clone());
+        progress
     }
 
     /// Get list of files to download for a model variant
     /// Based on actual repo structure on HuggingFace
     fn get_model_files(&self, variant: ModelVariant) -> Vec<String> {
+        // GGUF repos ship one file per quantization; take Q8_0
+        if variant.is_gguf() {
+            let name = variant.dir_name().trim_end_matches("-GGUF");
+            return vec![format!("{}-Q8_0.gguf", name)];
+        }
+
         // LFM2-Audio has a different file structure
         if variant.is_lfm2() {
             return vec![
@@ -363,7 +903,9 @@ impl ModelDownloader {
             .iter()
             .map(|file| {
                 // Estimate file sizes based on filename patterns
-                if file.contains("model.safetensors") && !file.contains("index") {
+                if file.ends_with(".gguf") {
+                    variant.estimated_size()
+                } else if file.contains("model.safetensors") && !file.contains("index") {
                     if file.contains("00001") || file.contains("00002") {
                         // Sharded model files ~2GB each
                         2_000_000_000
@@ -422,3 +964,266 @@ impl ModelDownloader {
         Ok(size)
     }
 }
+
+// Make downloader cloneable for async tasks
+impl Clone for ModelDownloader {
+    fn clone(&self) -> Self {
+        Self {
+            api: self.api.clone(),
+            models_dir: self.models_dir.clone(),
+            http_client: self.http_client.clone(),
+            filters: self.filters.clone(),
+            concurrency: self.concurrency,
+            // Clones share the bandwidth budget
+            limiter: self.limiter.clone(),
+            token: self.token.clone(),
+        }
+    }
+}
+
+/// Bytes still to fetch for `plan`, net of files present and partial downloads
+fn bytes_needed(plan: &DownloadPlan, local_dir: &Path) -> u64 {
+    plan.files
+        .iter()
+        .map(|entry| {
+            let dest = local_dir.join(&entry.path);
+            if ModelDownloader::is_present(&dest, entry) {
+                return 0;
+            }
+            let partial = std::fs::metadata(partial_path(&dest))
+                .map(|m| m.len())
+                .unwrap_or(0);
+            entry.size.saturating_sub(partial)
+        })
+        .sum()
+}
+
+/// Average speed and the time left at that speed
+fn transfer_rate(transferred: u64, elapsed: Duration, remaining: u64) -> (u64, Option<u64>) {
+    let secs = elapsed.as_secs_f64();
+    if transferred == 0 || secs <= 0.0 {
+        return (0, None);
+    }
+    let rate = transferred as f64 / secs;
+    (rate as u64, Some((remaining as f64 / rate).ceil() as u64))
+}
+
+/// Free space available to this process on the filesystem holding `path`
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // statvfs field widths differ between platforms
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(u64::from(stat.f_bavail) * u64::from(stat.f_frsize))
+}
+
+/// Free space is not checked on this platform
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// HTTP client for Hub downloads, optionally through `proxy`
+fn build_client(proxy: Option<&str>) -> Result<Client> {
+    let mut builder = Client::builder().timeout(Duration::from_secs(3600)); // 1 hour timeout for large files
+    if let Some(proxy) = proxy {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| Error::ConfigError(format!("Invalid download proxy {}: {}", proxy, e)))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::HfHubError(format!("Failed to create HTTP client: {}", e)))
+}
+
+/// HuggingFace token from `HF_TOKEN`, `HUGGING_FACE_HUB_TOKEN`, or the token
+/// file written by `huggingface-cli login`
+pub fn hf_token_from_env() -> Option<String> {
+    let non_empty = |token: String| {
+        let token = token.trim().to_string();
+        (!token.is_empty()).then_some(token)
+    };
+    if let Some(token) = HF_TOKEN_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok().and_then(non_empty))
+    {
+        return Some(token);
+    }
+
+    let path = std::env::var_os("HF_TOKEN_PATH")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HF_HOME").map(|home| PathBuf::from(home).join("token")))
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache/huggingface/token")))?;
+    std::fs::read_to_string(path).ok().and_then(non_empty)
+}
+
+/// Explain a Hub refusal, or `None` when `status` isn't one
+fn auth_error(status: StatusCode, repo_id: &str, has_token: bool) -> Option<Error> {
+    let message = match status {
+        StatusCode::UNAUTHORIZED if has_token => format!(
+            "the HuggingFace token was rejected for {}; check that it is valid and not expired",
+            repo_id
+        ),
+        StatusCode::UNAUTHORIZED => format!(
+            "{} is private or gated and needs a HuggingFace token; set HF_TOKEN, \
+             `hf_token` in the engine config, or the X-HF-Token request header",
+            repo_id
+        ),
+        StatusCode::FORBIDDEN => format!(
+            "{} is gated; accept its terms at {}/{} with the account that owns the \
+             token, then retry",
+            repo_id, HF_BASE_URL, repo_id
+        ),
+        _ => return None,
+    };
+    Some(Error::HfAuthError(message))
+}
+
+/// Path of the in-progress download for `dest`
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Hex-encoded SHA256 of a file
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Check a file against the manifest checksum, if one is published
+fn verify_file(path: &Path, entry: &RepoFile) -> Result<()> {
+    let Some(expected) = &entry.sha256 else {
+        return Ok(());
+    };
+    let actual = sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(Error::ChecksumMismatch {
+            file: entry.path.clone(),
+            expected: expected.clone(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_file_checksum() {
+        let path = std::env::temp_dir().join(format!("izwi-verify-{}.bin", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+
+        let mut entry = RepoFile {
+            path: "model.safetensors".to_string(),
+            size: 3,
+            sha256: Some(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+            ),
+            estimated: false,
+        };
+        assert!(verify_file(&path, &entry).is_ok());
+        assert!(ModelDownloader::is_present(&path, &entry));
+
+        entry.sha256 = Some("00".repeat(32));
+        assert!(matches!(
+            verify_file(&path, &entry),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+
+        entry.size = 4;
+        assert!(!ModelDownloader::is_present(&path, &entry));
+
+        // Sizes from the listing are checked for small files without a hash too
+        entry.sha256 = None;
+        assert!(!ModelDownloader::is_present(&path, &entry));
+        entry.estimated = true;
+        assert!(ModelDownloader::is_present(&path, &entry));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            partial_path(Path::new("m/model.safetensors")),
+            PathBuf::from("m/model.safetensors.incomplete")
+        );
+    }
+
+    #[test]
+    fn test_bandwidth_limiter_paces_reads() {
+        let limiter = BandwidthLimiter::new(20_000);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire(1_000);
+        }
+        // 4 KB at 20 KB/s
+        assert!(start.elapsed() >= Duration::from_millis(190));
+    }
+
+    #[test]
+    fn test_auth_errors_explain_how_to_get_access() {
+        let repo = "acme/private-tts";
+        let missing = auth_error(StatusCode::UNAUTHORIZED, repo, false).unwrap();
+        assert!(missing.to_string().contains("HF_TOKEN"), "{}", missing);
+
+        let gated = auth_error(StatusCode::FORBIDDEN, repo, true).unwrap();
+        assert!(matches!(gated, Error::HfAuthError(_)));
+        assert!(gated
+            .to_string()
+            .contains("https://huggingface.co/acme/private-tts"));
+
+        assert!(auth_error(StatusCode::NOT_FOUND, repo, true).is_none());
+    }
+
+    #[test]
+    fn test_bytes_needed_counts_missing_and_partial_files() {
+        let dir = std::env::temp_dir().join(format!("izwi-preflight-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.json"), b"{}").unwrap();
+        std::fs::write(dir.join("model.safetensors.incomplete"), vec![0u8; 300]).unwrap();
+
+        let file = |path: &str, size| RepoFile {
+            path: path.to_string(),
+            size,
+            sha256: Some("x".to_string()),
+            estimated: false,
+        };
+        let plan = DownloadFilter::default().plan(vec![
+            file("config.json", 2),
+            file("model.safetensors", 1000),
+            file("vocab.json", 50),
+        ]);
+        assert_eq!(bytes_needed(&plan, &dir), 700 + 50);
+        assert!(available_space(&dir).is_some_and(|free| free > 0) || cfg!(not(unix)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transfer_rate_and_eta() {
+        assert_eq!(
+            transfer_rate(2_000_000, Duration::from_secs(2), 5_000_000),
+            (1_000_000, Some(5))
+        );
+        assert_eq!(transfer_rate(0, Duration::from_secs(3), 100), (0, None));
+    }
+}
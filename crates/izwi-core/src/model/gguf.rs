@@ -0,0 +1,597 @@
+//! GGUF model files (llama.cpp format)
+//!
+//! Tensors are mapped onto the representation used for safetensors models:
+//! llama.cpp names (`blk.0.attn_q.weight`) become Hugging Face names
+//! (`model.layers.0.self_attn.q_proj.weight`), dimensions are reversed to
+//! row-major order, and Q8_0 / Q4_0 blocks are repacked into the
+//! `.qweight` / `.scales` pairs of [`crate::model::quant`], whose groups of
+//! [`GROUP_SIZE`] values line up with GGUF blocks. Block formats without an
+//! internal equivalent are dequantized to f32 at load.
+
+use memmap2::Mmap;
+use safetensors::Dtype;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::model::quant::{Quantization, GROUP_SIZE, QWEIGHT_SUFFIX, SCALES_SUFFIX};
+use crate::model::weights::{f16_to_f32, Storage, TensorData};
+
+const MAGIC: &[u8; 4] = b"GGUF";
+
+/// Data alignment when the file does not set `general.alignment`
+const DEFAULT_ALIGNMENT: usize = 32;
+
+/// Tensor element types (`ggml_type`) understood by the loader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GgmlType {
+    F32,
+    F16,
+    Q4_0,
+    Q4_1,
+    Q8_0,
+    BF16,
+    Other(u32),
+}
+
+impl GgmlType {
+    fn from_id(id: u32) -> Self {
+        match id {
+            0 => Self::F32,
+            1 => Self::F16,
+            2 => Self::Q4_0,
+            3 => Self::Q4_1,
+            8 => Self::Q8_0,
+            30 => Self::BF16,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Elements per block and bytes per block
+    fn block(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::F32 => Some((1, 4)),
+            Self::F16 | Self::BF16 => Some((1, 2)),
+            Self::Q4_0 => Some((GROUP_SIZE, 2 + GROUP_SIZE / 2)),
+            Self::Q4_1 => Some((GROUP_SIZE, 4 + GROUP_SIZE / 2)),
+            Self::Q8_0 => Some((GROUP_SIZE, 2 + GROUP_SIZE)),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Internal quantization a tensor of this type can be stored as
+    fn quantization(&self) -> Option<Quantization> {
+        match self {
+            Self::Q8_0 => Some(Quantization::Q8),
+            Self::Q4_0 => Some(Quantization::Q4),
+            _ => None,
+        }
+    }
+}
+
+/// A metadata value
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+}
+
+impl GgufValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::UInt(v) => Some(*v),
+            Self::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TensorInfo {
+    name: String,
+    /// GGUF order: innermost dimension first
+    dims: Vec<usize>,
+    ty: GgmlType,
+    offset: usize,
+}
+
+/// A parsed GGUF file with its tensor data mapped
+pub struct GgufFile {
+    pub version: u32,
+    pub metadata: HashMap<String, GgufValue>,
+    tensors: Vec<TensorInfo>,
+    storage: Arc<Storage>,
+    data_start: usize,
+}
+
+impl GgufFile {
+    /// Map and parse a GGUF file
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // Safety: model files are not expected to change while mapped
+        let storage = Arc::new(Storage::Mapped(unsafe { Mmap::map(&file)? }));
+        Self::parse(storage).map_err(|e| match e {
+            Error::ModelLoadError(msg) => {
+                Error::ModelLoadError(format!("{}: {}", path.display(), msg))
+            }
+            other => other,
+        })
+    }
+
+    fn parse(storage: Arc<Storage>) -> Result<Self> {
+        let mut reader = Reader::new(storage.bytes());
+        if reader.bytes(4)? != MAGIC {
+            return Err(Error::ModelLoadError("Not a GGUF file".to_string()));
+        }
+        let version = reader.u32()?;
+        if !(2..=3).contains(&version) {
+            return Err(Error::ModelLoadError(format!(
+                "Unsupported GGUF version {}",
+                version
+            )));
+        }
+        let tensor_count = reader.u64()?;
+        let kv_count = reader.u64()?;
+
+        let mut metadata = HashMap::new();
+        for _ in 0..kv_count {
+            let key = reader.string()?;
+            let ty = reader.u32()?;
+            metadata.insert(key, reader.value(ty)?);
+        }
+
+        let mut tensors = Vec::new();
+        for _ in 0..tensor_count {
+            let name = reader.string()?;
+            let n_dims = reader.u32()?;
+            let dims = (0..n_dims)
+                .map(|_| reader.u64().map(|d| d as usize))
+                .collect::<Result<Vec<_>>>()?;
+            let ty = GgmlType::from_id(reader.u32()?);
+            let offset = reader.u64()? as usize;
+            tensors.push(TensorInfo {
+                name,
+                dims,
+                ty,
+                offset,
+            });
+        }
+
+        let alignment = metadata
+            .get("general.alignment")
+            .and_then(GgufValue::as_u64)
+            .map_or(DEFAULT_ALIGNMENT, |a| a as usize)
+            .max(1);
+        let data_start = reader.pos.div_ceil(alignment) * alignment;
+
+        Ok(Self {
+            version,
+            metadata,
+            tensors,
+            storage,
+            data_start,
+        })
+    }
+
+    /// Model architecture (`general.architecture`), e.g. "qwen3"
+    pub fn architecture(&self) -> Option<&str> {
+        self.metadata
+            .get("general.architecture")
+            .and_then(GgufValue::as_str)
+    }
+
+    /// Internal quantization of the file: the most common block format with
+    /// an internal equivalent (`None` for float-only files)
+    pub fn quantization(&self) -> Quantization {
+        let count = |quant| {
+            self.tensors
+                .iter()
+                .filter(|t| t.ty.quantization() == Some(quant))
+                .count()
+        };
+        let (q8, q4) = (count(Quantization::Q8), count(Quantization::Q4));
+        match (q8, q4) {
+            (0, 0) => Quantization::None,
+            (q8, q4) if q8 >= q4 => Quantization::Q8,
+            _ => Quantization::Q4,
+        }
+    }
+
+    /// Convert the tensors to internal names and storage, keeping blocks of
+    /// `quantization` quantized and dequantizing other block formats
+    pub fn tensors(&self, quantization: Quantization) -> Result<HashMap<String, TensorData>> {
+        let mut result = HashMap::new();
+        for info in &self.tensors {
+            for tensor in self.convert(info, quantization)? {
+                result.insert(tensor.name.clone(), tensor);
+            }
+        }
+        Ok(result)
+    }
+
+    fn convert(&self, info: &TensorInfo, quantization: Quantization) -> Result<Vec<TensorData>> {
+        let name = hf_tensor_name(&info.name);
+        let shape: Vec<usize> = info.dims.iter().rev().copied().collect();
+        let len: usize = info.dims.iter().product();
+        let (block_len, block_bytes) = info.ty.block().ok_or_else(|| {
+            Error::ModelLoadError(format!(
+                "Tensor {} uses unsupported GGUF type {:?}; requantize to Q8_0, Q4_0 or F16",
+                info.name, info.ty
+            ))
+        })?;
+        if !len.is_multiple_of(block_len) {
+            return Err(Error::ModelLoadError(format!(
+                "Tensor {} has {} values, not a whole number of blocks",
+                info.name, len
+            )));
+        }
+        let start = self.data_start + info.offset;
+        let range = start..start + len / block_len * block_bytes;
+        if range.end > self.storage.bytes().len() {
+            return Err(Error::ModelLoadError(format!(
+                "Tensor {} extends past the end of the file",
+                info.name
+            )));
+        }
+
+        let mapped = |dtype| {
+            TensorData::new(
+                name.clone(),
+                shape.clone(),
+                dtype,
+                self.storage.clone(),
+                range.clone(),
+            )
+        };
+        let data = &self.storage.bytes()[range.clone()];
+        let tensors = match info.ty {
+            GgmlType::F32 => vec![mapped(Dtype::F32)],
+            GgmlType::F16 => vec![mapped(Dtype::F16)],
+            GgmlType::BF16 => vec![mapped(Dtype::BF16)],
+            ty if ty.quantization() == Some(quantization) => {
+                let (packed, scales) = repack(ty, data);
+                let mut packed_shape = shape.clone();
+                if ty == GgmlType::Q4_0 {
+                    if let Some(last) = packed_shape.last_mut() {
+                        *last /= 2;
+                    }
+                }
+                vec![
+                    TensorData::owned(
+                        format!("{}{}", name, QWEIGHT_SUFFIX),
+                        packed_shape,
+                        Dtype::U8,
+                        packed,
+                    ),
+                    TensorData::owned(
+                        format!("{}{}", name, SCALES_SUFFIX),
+                        vec![scales.len()],
+                        Dtype::F32,
+                        scales.iter().flat_map(|s| s.to_le_bytes()).collect(),
+                    ),
+                ]
+            }
+            ty => {
+                let values = dequantize(ty, data);
+                vec![TensorData::owned(
+                    name,
+                    shape,
+                    Dtype::F32,
+                    values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                )]
+            }
+        };
+        Ok(tensors)
+    }
+}
+
+/// Map a llama.cpp tensor name to the Hugging Face name; unknown names are
+/// kept as they are
+pub fn hf_tensor_name(name: &str) -> String {
+    const GLOBAL: &[(&str, &str)] = &[
+        ("token_embd", "model.embed_tokens"),
+        ("output_norm", "model.norm"),
+        ("output", "lm_head"),
+    ];
+    const BLOCK: &[(&str, &str)] = &[
+        ("attn_q", "self_attn.q_proj"),
+        ("attn_k", "self_attn.k_proj"),
+        ("attn_v", "self_attn.v_proj"),
+        ("attn_output", "self_attn.o_proj"),
+        ("attn_q_norm", "self_attn.q_norm"),
+        ("attn_k_norm", "self_attn.k_norm"),
+        ("attn_norm", "input_layernorm"),
+        ("ffn_norm", "post_attention_layernorm"),
+        ("ffn_gate", "mlp.gate_proj"),
+        ("ffn_up", "mlp.up_proj"),
+        ("ffn_down", "mlp.down_proj"),
+    ];
+
+    let (stem, suffix) = name.rsplit_once('.').unwrap_or((name, ""));
+    let join = |stem: String| {
+        if suffix.is_empty() {
+            stem
+        } else {
+            format!("{}.{}", stem, suffix)
+        }
+    };
+    if let Some((_, hf)) = GLOBAL.iter().find(|(gguf, _)| *gguf == stem) {
+        return join(hf.to_string());
+    }
+    if let Some(rest) = stem.strip_prefix("blk.") {
+        if let Some((layer, part)) = rest.split_once('.') {
+            if let Some((_, hf)) = BLOCK.iter().find(|(gguf, _)| *gguf == part) {
+                return join(format!("model.layers.{}.{}", layer, hf));
+            }
+        }
+    }
+    name.to_string()
+}
+
+fn f16_scale(block: &[u8]) -> f32 {
+    f16_to_f32(u16::from_le_bytes([block[0], block[1]]))
+}
+
+/// Q8_0 / Q4_0 blocks as internal packed levels and per-group scales
+fn repack(ty: GgmlType, data: &[u8]) -> (Vec<u8>, Vec<f32>) {
+    let (_, block_bytes) = ty.block().unwrap_or((GROUP_SIZE, 1));
+    let blocks = data.chunks_exact(block_bytes);
+    let mut scales = Vec::with_capacity(blocks.len());
+    let mut packed = Vec::with_capacity(data.len());
+    for block in blocks {
+        scales.push(f16_scale(block));
+        let qs = &block[2..];
+        match ty {
+            GgmlType::Q8_0 => packed.extend_from_slice(qs),
+            // GGUF keeps values j and j + 16 in one byte; internally
+            // consecutive values share a byte, low nibble first
+            _ => {
+                let nibble = |i: usize| {
+                    let byte = qs[i % (GROUP_SIZE / 2)];
+                    if i < GROUP_SIZE / 2 {
+                        byte & 0x0f
+                    } else {
+                        byte >> 4
+                    }
+                };
+                packed.extend((0..GROUP_SIZE / 2).map(|i| nibble(2 * i) | nibble(2 * i + 1) << 4));
+            }
+        }
+    }
+    (packed, scales)
+}
+
+/// Expand quantized blocks to f32
+fn dequantize(ty: GgmlType, data: &[u8]) -> Vec<f32> {
+    let (block_len, block_bytes) = ty.block().unwrap_or((GROUP_SIZE, 1));
+    let mut values = Vec::with_capacity(data.len() / block_bytes * block_len);
+    for block in data.chunks_exact(block_bytes) {
+        let d = f16_scale(block);
+        match ty {
+            GgmlType::Q8_0 => values.extend(block[2..].iter().map(|&q| q as i8 as f32 * d)),
+            GgmlType::Q4_0 => {
+                let qs = &block[2..];
+                values.extend(qs.iter().map(|q| ((q & 0x0f) as f32 - 8.0) * d));
+                values.extend(qs.iter().map(|q| ((q >> 4) as f32 - 8.0) * d));
+            }
+            GgmlType::Q4_1 => {
+                let m = f16_to_f32(u16::from_le_bytes([block[2], block[3]]));
+                let qs = &block[4..];
+                values.extend(qs.iter().map(|q| (q & 0x0f) as f32 * d + m));
+                values.extend(qs.iter().map(|q| (q >> 4) as f32 * d + m));
+            }
+            _ => {}
+        }
+    }
+    values
+}
+
+/// Little-endian cursor over the file header
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| Error::ModelLoadError("Truncated GGUF header".to_string()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.bytes(N)?);
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u64()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::ModelLoadError("GGUF string is not UTF-8".to_string()))
+    }
+
+    fn value(&mut self, ty: u32) -> Result<GgufValue> {
+        Ok(match ty {
+            0 => GgufValue::UInt(self.array::<1>()?[0] as u64),
+            1 => GgufValue::Int(self.array::<1>()?[0] as i8 as i64),
+            2 => GgufValue::UInt(u16::from_le_bytes(self.array()?) as u64),
+            3 => GgufValue::Int(i16::from_le_bytes(self.array()?) as i64),
+            4 => GgufValue::UInt(self.u32()? as u64),
+            5 => GgufValue::Int(i32::from_le_bytes(self.array()?) as i64),
+            6 => GgufValue::Float(f32::from_le_bytes(self.array()?) as f64),
+            7 => GgufValue::Bool(self.array::<1>()?[0] != 0),
+            8 => GgufValue::String(self.string()?),
+            9 => {
+                let item_ty = self.u32()?;
+                let len = self.u64()?;
+                let items = (0..len)
+                    .map(|_| self.value(item_ty))
+                    .collect::<Result<Vec<_>>>()?;
+                GgufValue::Array(items)
+            }
+            10 => GgufValue::UInt(self.u64()?),
+            11 => GgufValue::Int(i64::from_le_bytes(self.array()?)),
+            12 => GgufValue::Float(f64::from_le_bytes(self.array()?)),
+            other => {
+                return Err(Error::ModelLoadError(format!(
+                    "Unknown GGUF metadata type {}",
+                    other
+                )))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a GGUF v3 file with the given tensors `(name, dims, type, bytes)`
+    fn write_gguf(path: &Path, tensors: &[(&str, Vec<u64>, u32, Vec<u8>)]) {
+        let string = |s: &str| {
+            let mut out = (s.len() as u64).to_le_bytes().to_vec();
+            out.extend_from_slice(s.as_bytes());
+            out
+        };
+        let mut file = MAGIC.to_vec();
+        file.extend(3u32.to_le_bytes());
+        file.extend((tensors.len() as u64).to_le_bytes());
+        file.extend(1u64.to_le_bytes());
+        file.extend(string("general.architecture"));
+        file.extend(8u32.to_le_bytes());
+        file.extend(string("qwen3"));
+
+        let mut offset = 0;
+        let mut data = Vec::new();
+        for (name, dims, ty, bytes) in tensors {
+            file.extend(string(name));
+            file.extend((dims.len() as u32).to_le_bytes());
+            for d in dims {
+                file.extend(d.to_le_bytes());
+            }
+            file.extend(ty.to_le_bytes());
+            file.extend((offset as u64).to_le_bytes());
+            data.extend_from_slice(bytes);
+            data.resize(
+                data.len().div_ceil(DEFAULT_ALIGNMENT) * DEFAULT_ALIGNMENT,
+                0,
+            );
+            offset = data.len();
+        }
+        file.resize(
+            file.len().div_ceil(DEFAULT_ALIGNMENT) * DEFAULT_ALIGNMENT,
+            0,
+        );
+        file.extend(data);
+        std::fs::write(path, file).unwrap();
+    }
+
+    #[test]
+    fn test_load_gguf_tensors() {
+        let dir = std::env::temp_dir().join(format!("izwi-gguf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gguf");
+
+        let embd: Vec<u8> = (0..6).flat_map(|i| (i as f32).to_le_bytes()).collect();
+        // Two Q8_0 blocks with scale 0.5 (f16 0x3800)
+        let mut q8 = Vec::new();
+        for _ in 0..2 {
+            q8.extend(0x3800u16.to_le_bytes());
+            q8.extend((0..32).map(|i| (i as i8 - 16) as u8));
+        }
+        // One Q4_0 block with scale 1.0: values j -> j % 16 - 8
+        let mut q4 = 0x3c00u16.to_le_bytes().to_vec();
+        q4.extend((0..16u8).map(|j| j | ((15 - j) << 4)));
+        write_gguf(
+            &path,
+            &[
+                ("token_embd.weight", vec![2, 3], 0, embd),
+                ("blk.0.attn_q.weight", vec![32, 2], 8, q8),
+                ("blk.0.ffn_up.weight", vec![32, 1], 2, q4.clone()),
+            ],
+        );
+
+        let gguf = GgufFile::open(&path).unwrap();
+        assert_eq!(gguf.architecture(), Some("qwen3"));
+        assert_eq!(gguf.quantization(), Quantization::Q8);
+
+        let tensors = gguf.tensors(Quantization::Q8).unwrap();
+        let embd = &tensors["model.embed_tokens.weight"];
+        assert_eq!(embd.shape, vec![3, 2]);
+        assert_eq!(embd.to_f32().unwrap(), vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let qweight = &tensors["model.layers.0.self_attn.q_proj.weight.qweight"];
+        assert_eq!(qweight.shape, vec![2, 32]);
+        assert_eq!(
+            tensors["model.layers.0.self_attn.q_proj.weight.scales"]
+                .to_f32()
+                .unwrap(),
+            vec![0.5, 0.5]
+        );
+
+        // Q4_0 in a Q8 file is dequantized
+        let up = tensors["model.layers.0.mlp.up_proj.weight"]
+            .to_f32()
+            .unwrap();
+        assert_eq!(&up[..3], &[-8.0, -7.0, -6.0]);
+        assert_eq!(up[16], 7.0);
+
+        // ... and repacked when the model is Q4
+        let (packed, scales) = repack(GgmlType::Q4_0, &q4);
+        let levels =
+            crate::model::quant::dequantize(Quantization::Q4, &packed, &scales, 32).unwrap();
+        assert_eq!(levels, up);
+
+        let weights = crate::model::ModelWeights::load(&dir).unwrap();
+        assert_eq!(weights.quantization, Quantization::Q8);
+        assert_eq!(weights.config.model_type.as_deref(), Some("qwen3"));
+        assert_eq!(
+            weights.shape("model.layers.0.self_attn.q_proj.weight"),
+            Some(vec![2, 32])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hf_tensor_name() {
+        assert_eq!(
+            hf_tensor_name("blk.12.attn_output.weight"),
+            "model.layers.12.self_attn.o_proj.weight"
+        );
+        assert_eq!(hf_tensor_name("output_norm.weight"), "model.norm.weight");
+        assert_eq!(hf_tensor_name("output.weight"), "lm_head.weight");
+        assert_eq!(hf_tensor_name("audio.enc.weight"), "audio.enc.weight");
+    }
+}
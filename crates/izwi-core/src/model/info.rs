@@ -3,6 +3,10 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use super::quant::Quantization;
+use crate::inference::presets::GenerationPreset;
+use crate::inference::style::{self, SpeechStyle};
+
 /// Available TTS model variants
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ModelVariant {
@@ -33,6 +37,12 @@ pub enum ModelVariant {
     /// Qwen3-ASR 1.7B model
     #[serde(rename = "Qwen3-ASR-1.7B")]
     Qwen3Asr17B,
+    /// 0.6B parameter base model as Q8_0 GGUF
+    #[serde(rename = "Qwen3-TTS-12Hz-0.6B-Base-GGUF")]
+    Qwen3Tts12Hz06BBaseGguf,
+    /// LFM2-Audio 1.5B as Q8_0 GGUF
+    #[serde(rename = "LFM2-Audio-1.5B-GGUF")]
+    Lfm2Audio15BGguf,
 }
 
 impl ModelVariant {
@@ -48,6 +58,8 @@ impl ModelVariant {
             Self::Lfm2Audio15B => "LiquidAI/LFM2-Audio-1.5B",
             Self::Qwen3Asr06B => "Qwen/Qwen3-ASR-0.6B",
             Self::Qwen3Asr17B => "Qwen/Qwen3-ASR-1.7B",
+            Self::Qwen3Tts12Hz06BBaseGguf => "Qwen/Qwen3-TTS-12Hz-0.6B-Base-GGUF",
+            Self::Lfm2Audio15BGguf => "LiquidAI/LFM2-Audio-1.5B-GGUF",
         }
     }
 
@@ -63,6 +75,8 @@ impl ModelVariant {
             Self::Lfm2Audio15B => "LFM2-Audio 1.5B",
             Self::Qwen3Asr06B => "Qwen3-ASR 0.6B",
             Self::Qwen3Asr17B => "Qwen3-ASR 1.7B",
+            Self::Qwen3Tts12Hz06BBaseGguf => "Qwen3-TTS 0.6B Base (GGUF)",
+            Self::Lfm2Audio15BGguf => "LFM2-Audio 1.5B (GGUF)",
         }
     }
 
@@ -78,6 +92,8 @@ impl ModelVariant {
             Self::Lfm2Audio15B => "LFM2-Audio-1.5B",
             Self::Qwen3Asr06B => "Qwen3-ASR-0.6B",
             Self::Qwen3Asr17B => "Qwen3-ASR-1.7B",
+            Self::Qwen3Tts12Hz06BBaseGguf => "Qwen3-TTS-12Hz-0.6B-Base-GGUF",
+            Self::Lfm2Audio15BGguf => "LFM2-Audio-1.5B-GGUF",
         }
     }
 
@@ -93,6 +109,8 @@ impl ModelVariant {
             Self::Lfm2Audio15B => 3_000_000_000,        // ~3GB
             Self::Qwen3Asr06B => 1_900_000_000,         // ~1.9GB
             Self::Qwen3Asr17B => 4_700_000_000,         // ~4.7GB
+            Self::Qwen3Tts12Hz06BBaseGguf => 700_000_000, // ~0.7GB at Q8_0
+            Self::Lfm2Audio15BGguf => 1_700_000_000,    // ~1.7GB at Q8_0
         }
     }
 
@@ -107,9 +125,25 @@ impl ModelVariant {
             Self::Lfm2Audio15B => 6.0,
             Self::Qwen3Asr06B => 2.5,
             Self::Qwen3Asr17B => 6.0,
+            Self::Qwen3Tts12Hz06BBaseGguf => 2.0,
+            Self::Lfm2Audio15BGguf => 4.5,
+        }
+    }
+
+    /// Local directory name for weights stored at `quant`
+    pub fn local_dir_name(&self, quant: Quantization) -> String {
+        match quant {
+            Quantization::None => self.dir_name().to_string(),
+            quant => format!("{}-{}", self.dir_name(), quant),
         }
     }
 
+    /// Memory required for inference with weights stored at `quant`
+    pub fn memory_required_gb_with(&self, quant: Quantization) -> f32 {
+        let weights_gb = self.estimated_size() as f32 / 1e9;
+        self.memory_required_gb() - weights_gb * (1.0 - quant.size_factor())
+    }
+
     /// Whether this is a tokenizer/codec model
     pub fn is_tokenizer(&self) -> bool {
         matches!(self, Self::Qwen3TtsTokenizer12Hz)
@@ -117,7 +151,12 @@ impl ModelVariant {
 
     /// Whether this is an LFM2-Audio model
     pub fn is_lfm2(&self) -> bool {
-        matches!(self, Self::Lfm2Audio15B)
+        matches!(self, Self::Lfm2Audio15B | Self::Lfm2Audio15BGguf)
+    }
+
+    /// Whether the weights are distributed as GGUF
+    pub fn is_gguf(&self) -> bool {
+        matches!(self, Self::Qwen3Tts12Hz06BBaseGguf | Self::Lfm2Audio15BGguf)
     }
 
     /// Whether this is a Qwen3-ASR model
@@ -137,6 +176,8 @@ impl ModelVariant {
             Self::Lfm2Audio15B,
             Self::Qwen3Asr06B,
             Self::Qwen3Asr17B,
+            Self::Qwen3Tts12Hz06BBaseGguf,
+            Self::Lfm2Audio15BGguf,
         ]
     }
 }
@@ -174,6 +215,18 @@ pub struct ModelInfo {
     pub size_bytes: Option<u64>,
     pub download_progress: Option<f32>,
     pub error_message: Option<String>,
+    /// Precision of the weights at `local_path`
+    #[serde(default)]
+    pub quantization: Quantization,
+    /// Quantized copies available on disk
+    #[serde(default)]
+    pub quantized_variants: Vec<Quantization>,
+    /// Speaking styles the model can follow
+    #[serde(default)]
+    pub styles: Vec<SpeechStyle>,
+    /// Sampling settings requests to this model start from
+    #[serde(default)]
+    pub generation_defaults: GenerationPreset,
 }
 
 impl ModelInfo {
@@ -185,6 +238,10 @@ impl ModelInfo {
             size_bytes: None,
             download_progress: None,
             error_message: None,
+            quantization: Quantization::None,
+            quantized_variants: Vec::new(),
+            styles: style::supported_styles(variant).to_vec(),
+            generation_defaults: GenerationPreset::default(),
         }
     }
 
@@ -0,0 +1,376 @@
+//! Weight layout cache
+//!
+//! Published weights are laid out for training frameworks, not for the
+//! kernels that run them here. [`LayoutOp`]s rewrite the affected tensors
+//! once (fusing attention projections, reordering conv weights for the CPU
+//! kernel) and the results are written next to the model under
+//! [`LAYOUT_DIR`]. Later loads map the cached tensors instead of redoing
+//! the work.
+//!
+//! The cache holds only rewritten tensors, plus the names of the source
+//! tensors they replace. It is keyed by the ops and a fingerprint of the
+//! source files (names, sizes, modification times and safetensors headers),
+//! so replacing the weights invalidates it.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info, warn};
+
+use crate::error::{Error, Result};
+use crate::model::weights::{ModelWeights, TensorData};
+
+/// Directory of cached layouts, inside the model directory
+pub const LAYOUT_DIR: &str = ".izwi-layout";
+
+/// Suffix of a conv weight reordered by [`LayoutOp::PackConv`]
+pub const PACKED_SUFFIX: &str = ".packed";
+
+/// Bumped when an op's output changes, so old caches are rebuilt
+const LAYOUT_VERSION: u32 = 1;
+
+/// Sanity bound on a safetensors header
+const MAX_HEADER_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Metadata key holding the cache key
+const KEY_METADATA: &str = "izwi.layout.key";
+
+/// Metadata key listing the source tensors replaced by the cache
+const REPLACES_METADATA: &str = "izwi.layout.replaces";
+
+/// A rewrite applied to a model's tensors at load time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutOp {
+    /// Concatenate the `q_proj`, `k_proj` and `v_proj` weights (and biases)
+    /// of each attention block into one `qkv_proj`, so the three
+    /// projections run as one matrix product
+    FuseQkv,
+    /// Store conv weights `[out, in, kernel]` as `f32` `[out, kernel, in]`
+    /// under `<name>.packed`, the layout of the CPU conv kernel. The
+    /// original tensors are kept.
+    PackConv,
+}
+
+impl LayoutOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FuseQkv => "fuse_qkv",
+            Self::PackConv => "pack_conv",
+        }
+    }
+}
+
+/// Where the cached layout of `source` (a weights file or a model
+/// directory) is kept
+pub fn cache_path(source: &Path) -> PathBuf {
+    if source.is_dir() {
+        source.join(LAYOUT_DIR).join("model.safetensors")
+    } else {
+        let dir = source.parent().unwrap_or(Path::new("."));
+        let name = source.file_name().unwrap_or_default();
+        dir.join(LAYOUT_DIR).join(name)
+    }
+}
+
+/// Rewrite `tensors`, loaded from `sources`, with `ops`. The cache at
+/// `cache` is used when it matches the sources and rebuilt otherwise; a
+/// cache that cannot be written (e.g. a read-only model directory) only
+/// costs the rewrite on the next load.
+pub fn apply(
+    tensors: &mut HashMap<String, TensorData>,
+    sources: &[PathBuf],
+    cache: &Path,
+    ops: &[LayoutOp],
+) -> Result<()> {
+    if ops.is_empty() {
+        return Ok(());
+    }
+    let key = cache_key(sources, ops)?;
+    if let Some((cached, replaces)) = read_cache(cache, &key) {
+        debug!("Using cached weight layout {:?}", cache);
+        for name in &replaces {
+            tensors.remove(name);
+        }
+        tensors.extend(cached);
+        return Ok(());
+    }
+
+    let (rewritten, replaces) = rewrite(tensors, ops)?;
+    info!(
+        "Rewrote {} tensors for layout {}",
+        rewritten.len(),
+        ops_id(ops)
+    );
+    if let Err(e) = write_cache(cache, &key, &rewritten, &replaces) {
+        warn!("Could not cache weight layout at {:?}: {}", cache, e);
+    }
+    for name in &replaces {
+        tensors.remove(name);
+    }
+    tensors.extend(rewritten.into_iter().map(|t| (t.name.clone(), t)));
+    Ok(())
+}
+
+fn ops_id(ops: &[LayoutOp]) -> String {
+    ops.iter()
+        .map(LayoutOp::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Hash of the layout version, the ops and the source files
+fn cache_key(sources: &[PathBuf], ops: &[LayoutOp]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(LAYOUT_VERSION.to_le_bytes());
+    hasher.update(ops_id(ops).as_bytes());
+    for source in sources {
+        let meta = std::fs::metadata(source)?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        hasher.update(source.file_name().unwrap_or_default().as_encoded_bytes());
+        hasher.update(meta.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+        // The header lists every tensor's name, dtype, shape and offsets
+        hasher.update(read_header(source)?);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The JSON header of a safetensors file, without reading its tensors
+fn read_header(path: &Path) -> Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut size = [0u8; 8];
+    file.read_exact(&mut size)?;
+    let size = u64::from_le_bytes(size);
+    if size > MAX_HEADER_BYTES {
+        return Err(Error::ModelLoadError(format!(
+            "Safetensors header of {:?} is too large",
+            path
+        )));
+    }
+    let mut header = Vec::with_capacity(size as usize);
+    file.take(size).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Cached tensors and the source tensors they replace, if the cache at
+/// `path` was built for `key`
+fn read_cache(path: &Path, key: &str) -> Option<(HashMap<String, TensorData>, Vec<String>)> {
+    let header: serde_json::Value = serde_json::from_slice(&read_header(path).ok()?).ok()?;
+    let metadata = header.get("__metadata__")?;
+    if metadata.get(KEY_METADATA)?.as_str()? != key {
+        return None;
+    }
+    let replaces = metadata
+        .get(REPLACES_METADATA)
+        .and_then(|names| names.as_str())
+        .unwrap_or_default()
+        .split(',')
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect();
+    let (tensors, _) = ModelWeights::load_safetensors(path).ok()?;
+    Some((tensors, replaces))
+}
+
+fn write_cache(path: &Path, key: &str, tensors: &[TensorData], replaces: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let metadata = HashMap::from([
+        (KEY_METADATA.to_string(), key.to_string()),
+        (REPLACES_METADATA.to_string(), replaces.join(",")),
+    ]);
+    let views = tensors
+        .iter()
+        .map(|t| Ok((t.name.clone(), t.view()?)))
+        .collect::<Result<Vec<_>>>()?;
+    // Written aside and renamed, so a crash never leaves a partial cache
+    let partial = path.with_extension("partial");
+    safetensors::serialize_to_file(views, &Some(metadata), &partial)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Apply `ops`, returning the new tensors and the names they replace
+fn rewrite(
+    tensors: &HashMap<String, TensorData>,
+    ops: &[LayoutOp],
+) -> Result<(Vec<TensorData>, Vec<String>)> {
+    let mut rewritten = Vec::new();
+    let mut replaces = Vec::new();
+    for op in ops {
+        match op {
+            LayoutOp::FuseQkv => fuse_qkv(tensors, &mut rewritten, &mut replaces),
+            LayoutOp::PackConv => pack_conv(tensors, &mut rewritten)?,
+        }
+    }
+    rewritten.sort_by(|a, b| a.name.cmp(&b.name));
+    replaces.sort();
+    Ok((rewritten, replaces))
+}
+
+fn fuse_qkv(
+    tensors: &HashMap<String, TensorData>,
+    rewritten: &mut Vec<TensorData>,
+    replaces: &mut Vec<String>,
+) {
+    for (name, q) in tensors {
+        let Some((prefix, suffix)) = name.split_once("q_proj") else {
+            continue;
+        };
+        if !matches!(suffix, ".weight" | ".bias") {
+            continue;
+        }
+        let k_name = format!("{}k_proj{}", prefix, suffix);
+        let v_name = format!("{}v_proj{}", prefix, suffix);
+        let (Some(k), Some(v)) = (tensors.get(&k_name), tensors.get(&v_name)) else {
+            continue;
+        };
+        let compatible =
+            |t: &TensorData| t.raw_dtype() == q.raw_dtype() && t.shape.get(1..) == q.shape.get(1..);
+        if q.shape.is_empty() || !compatible(k) || !compatible(v) {
+            continue;
+        }
+
+        let mut shape = q.shape.clone();
+        shape[0] = q.shape[0] + k.shape[0] + v.shape[0];
+        let bytes = [q.data(), k.data(), v.data()].concat();
+        rewritten.push(TensorData::owned(
+            format!("{}qkv_proj{}", prefix, suffix),
+            shape,
+            q.raw_dtype(),
+            bytes,
+        ));
+        replaces.extend([name.clone(), k_name, v_name]);
+    }
+}
+
+fn pack_conv(tensors: &HashMap<String, TensorData>, rewritten: &mut Vec<TensorData>) -> Result<()> {
+    for (name, weight) in tensors {
+        let [out_channels, in_channels, kernel_size] = weight.shape[..] else {
+            continue;
+        };
+        if !name.ends_with(".weight") || !weight.dtype.is_float() {
+            continue;
+        }
+        let packed = pack_conv_weight(&weight.to_f32()?, out_channels, in_channels, kernel_size);
+        rewritten.push(TensorData::owned(
+            format!("{}{}", name, PACKED_SUFFIX),
+            vec![out_channels, kernel_size, in_channels],
+            safetensors::Dtype::F32,
+            packed.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reorder a PyTorch `Conv1d` weight from `[out][in][kernel]` to
+/// `[out][kernel][in]`
+pub fn pack_conv_weight(
+    weight: &[f32],
+    out_channels: usize,
+    in_channels: usize,
+    kernel_size: usize,
+) -> Vec<f32> {
+    let mut packed = vec![0.0; weight.len()];
+    for o in 0..out_channels {
+        for i in 0..in_channels {
+            for k in 0..kernel_size {
+                packed[(o * kernel_size + k) * in_channels + i] =
+                    weight[(o * in_channels + i) * kernel_size + k];
+            }
+        }
+    }
+    packed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::weights::LoadOptions;
+    use safetensors::tensor::TensorView;
+    use safetensors::Dtype;
+
+    fn bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn write_model(dir: &Path, k_rows: usize) {
+        let q = bytes(&[1.0; 6]);
+        let k = bytes(&vec![2.0; k_rows * 3]);
+        let v = bytes(&[3.0; 3]);
+        let conv = bytes(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0]);
+        let tensors = vec![
+            (
+                "attn.q_proj.weight",
+                TensorView::new(Dtype::F32, vec![2, 3], &q).unwrap(),
+            ),
+            (
+                "attn.k_proj.weight",
+                TensorView::new(Dtype::F32, vec![k_rows, 3], &k).unwrap(),
+            ),
+            (
+                "attn.v_proj.weight",
+                TensorView::new(Dtype::F32, vec![1, 3], &v).unwrap(),
+            ),
+            (
+                "conv.weight",
+                TensorView::new(Dtype::F32, vec![2, 3, 2], &conv).unwrap(),
+            ),
+        ];
+        safetensors::serialize_to_file(tensors, &None, &dir.join("model.safetensors")).unwrap();
+    }
+
+    #[test]
+    fn test_layout_cached_and_invalidated() {
+        let dir = std::env::temp_dir().join(format!("izwi-layout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_model(&dir, 1);
+        let options = LoadOptions {
+            layout: vec![LayoutOp::FuseQkv, LayoutOp::PackConv],
+            ..Default::default()
+        };
+
+        let weights = ModelWeights::load_with(&dir, &options, &|_| {}).unwrap();
+        let qkv = weights.get("attn.qkv_proj.weight").unwrap();
+        assert_eq!(qkv.shape, vec![4, 3]);
+        assert_eq!(qkv.to_f32().unwrap()[6..], [2.0, 2.0, 2.0, 3.0, 3.0, 3.0]);
+        assert!(weights.get("attn.q_proj.weight").is_none());
+        let packed = weights.get("conv.weight.packed").unwrap();
+        assert_eq!(packed.shape, vec![2, 2, 3]);
+        assert_eq!(
+            packed.to_f32().unwrap(),
+            pack_conv_weight(&weights.get_f32("conv.weight").unwrap(), 2, 3, 2)
+        );
+
+        // The second load maps the cache instead of rewriting
+        let cache = cache_path(&dir);
+        let built = std::fs::metadata(&cache).unwrap().modified().unwrap();
+        let weights = ModelWeights::load_with(&dir, &options, &|_| {}).unwrap();
+        assert_eq!(
+            std::fs::metadata(&cache).unwrap().modified().unwrap(),
+            built
+        );
+        assert_eq!(
+            weights.get("attn.qkv_proj.weight").unwrap().shape,
+            vec![4, 3]
+        );
+
+        // New weights invalidate it
+        write_model(&dir, 2);
+        let weights = ModelWeights::load_with(&dir, &options, &|_| {}).unwrap();
+        assert_eq!(
+            weights.get("attn.qkv_proj.weight").unwrap().shape,
+            vec![5, 3]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
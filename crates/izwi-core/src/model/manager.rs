@@ -3,36 +3,51 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::{mpsc, RwLock};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::config::EngineConfig;
 use crate::error::{Error, Result};
-use crate::model::download::{DownloadProgress, ModelDownloader};
+use crate::model::discovery::{self, DiscoveredModel};
+use crate::model::download::{DownloadProgress, ModelDownloader, RepairReport};
 use crate::model::info::{ModelInfo, ModelStatus, ModelVariant};
-use crate::model::weights::ModelWeights;
+use crate::model::layout::LayoutOp;
+use crate::model::quant::{self, Quantization, QuantizeReport};
+use crate::model::weights::{LoadOptions, LoadProgress, ModelWeights};
 
 /// Manages model downloading, loading, and lifecycle
 pub struct ModelManager {
-    _config: EngineConfig,
+    config: EngineConfig,
     downloader: ModelDownloader,
     models: RwLock<HashMap<ModelVariant, ModelState>>,
+    /// Model folders found on disk, built-in or not
+    discovered: RwLock<Vec<DiscoveredModel>>,
+    /// Progress of the weight loads under way, updated from loading threads
+    loading: Arc<std::sync::Mutex<HashMap<ModelVariant, LoadProgress>>>,
 }
 
 struct ModelState {
     info: ModelInfo,
     weights: Option<Arc<ModelWeights>>,
+    /// Last download or load, for cache eviction
+    last_used: SystemTime,
 }
 
 impl ModelManager {
     /// Create a new model manager
     pub fn new(config: EngineConfig) -> Result<Self> {
-        let downloader = ModelDownloader::new(config.models_dir.clone())?;
+        let downloader = ModelDownloader::new(config.models_dir.clone())?
+            .with_concurrency(config.download_concurrency)
+            .with_bandwidth_limit(config.download_bandwidth_limit)
+            .with_token(config.hf_token.clone())?
+            .with_proxy(config.download_proxy.as_deref())?;
 
         // Initialize model states
         let mut models = HashMap::new();
         for variant in ModelVariant::all() {
             let mut info = ModelInfo::new(*variant);
+            info.generation_defaults = config.model_defaults(*variant);
 
             // Check if already downloaded
             if downloader.is_downloaded(*variant) {
@@ -41,22 +56,66 @@ impl ModelManager {
                 info.size_bytes = downloader.get_cached_size(*variant);
             }
 
+            info.quantized_variants = [Quantization::Q8, Quantization::Q4]
+                .into_iter()
+                .filter(|q| downloader.quantized_path(*variant, *q).is_dir())
+                .collect();
+            // Fall back to a quantized copy when the original was removed
+            if info.local_path.is_none() {
+                if let Some(&quant) = info.quantized_variants.first() {
+                    info.status = ModelStatus::Downloaded;
+                    info.local_path = Some(downloader.quantized_path(*variant, quant));
+                    info.quantization = quant;
+                }
+            }
+
             models.insert(
                 *variant,
                 ModelState {
                     info,
                     weights: None,
+                    last_used: downloader
+                        .last_used(*variant)
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
                 },
             );
         }
 
+        let discovered = discovery::discover_models(&Self::scan_dirs(&config));
+        adopt_discovered(&mut models, &discovered);
+        info!("Discovered {} local model folders", discovered.len());
+
         Ok(Self {
-            _config: config,
+            config,
             downloader,
             models: RwLock::new(models),
+            discovered: RwLock::new(discovered),
+            loading: Arc::default(),
         })
     }
 
+    fn scan_dirs(config: &EngineConfig) -> Vec<PathBuf> {
+        std::iter::once(config.models_dir.clone())
+            .chain(config.extra_model_dirs.iter().cloned())
+            .collect()
+    }
+
+    /// Model folders found in the models dir and `extra_model_dirs`
+    pub async fn discovered_models(&self) -> Vec<DiscoveredModel> {
+        self.discovered.read().await.clone()
+    }
+
+    /// Scan the model directories again, e.g. after copying a model in
+    pub async fn rescan(&self) -> Vec<DiscoveredModel> {
+        let dirs = Self::scan_dirs(&self.config);
+        let discovered = tokio::task::spawn_blocking(move || discovery::discover_models(&dirs))
+            .await
+            .unwrap_or_default();
+        adopt_discovered(&mut *self.models.write().await, &discovered);
+        *self.discovered.write().await = discovered.clone();
+        discovered
+    }
+
     /// Get list of all available models with their status
     pub async fn list_models(&self) -> Vec<ModelInfo> {
         let models = self.models.read().await;
@@ -71,67 +130,87 @@ impl ModelManager {
 
     /// Download a model from HuggingFace
     pub async fn download_model(&self, variant: ModelVariant) -> Result<PathBuf> {
-        // Update status to downloading
-        {
-            let mut models = self.models.write().await;
-            if let Some(state) = models.get_mut(&variant) {
-                state.info.status = ModelStatus::Downloading;
-                state.info.download_progress = Some(0.0);
-            }
-        }
-
-        // Perform download
-        let result = tokio::task::spawn_blocking({
-            let downloader = self.downloader.clone();
-            move || downloader.download(variant)
-        })
-        .await
-        .map_err(|e| Error::DownloadError(e.to_string()))??;
+        self.download_model_with_token(variant, None).await
+    }
 
-        // Update status
-        {
-            let mut models = self.models.write().await;
-            if let Some(state) = models.get_mut(&variant) {
-                state.info.status = ModelStatus::Downloaded;
-                state.info.local_path = Some(result.clone());
-                state.info.download_progress = Some(100.0);
-                state.info.size_bytes = self.downloader.get_cached_size(variant);
-            }
-        }
+    /// Download a model, authenticating with `token` when given
+    pub async fn download_model_with_token(
+        &self,
+        variant: ModelVariant,
+        token: Option<String>,
+    ) -> Result<PathBuf> {
+        let downloader = self.downloader.clone().with_token(token)?;
+        self.start_download(variant).await;
 
-        Ok(result)
+        let result = tokio::task::spawn_blocking(move || downloader.download(variant))
+            .await
+            .map_err(|e| Error::DownloadError(e.to_string()))
+            .and_then(|result| result);
+        self.finish_download(variant, result).await
     }
 
-    /// Download a model with progress reporting
+    /// Download a model with progress reporting, authenticating with `token`
+    /// when given
     pub async fn download_model_with_progress(
         &self,
         variant: ModelVariant,
+        token: Option<String>,
         progress_tx: mpsc::Sender<DownloadProgress>,
     ) -> Result<PathBuf> {
-        // Update status
-        {
-            let mut models = self.models.write().await;
-            if let Some(state) = models.get_mut(&variant) {
-                state.info.status = ModelStatus::Downloading;
-            }
-        }
+        let downloader = self.downloader.clone().with_token(token)?;
+        self.start_download(variant).await;
 
-        let result = self
-            .downloader
+        let result = downloader
             .download_with_progress(variant, progress_tx)
-            .await?;
+            .await;
+        self.finish_download(variant, result).await
+    }
+
+    async fn start_download(&self, variant: ModelVariant) {
+        let mut models = self.models.write().await;
+        if let Some(state) = models.get_mut(&variant) {
+            state.info.status = ModelStatus::Downloading;
+            state.info.download_progress = Some(0.0);
+        }
+    }
+
+    /// Record the outcome of a download and enforce the cache limit
+    async fn finish_download(
+        &self,
+        variant: ModelVariant,
+        result: Result<PathBuf>,
+    ) -> Result<PathBuf> {
+        let path = match result {
+            Ok(path) => path,
+            Err(e) => {
+                let mut models = self.models.write().await;
+                if let Some(state) = models.get_mut(&variant) {
+                    state.info.status = if state.info.local_path.is_some() {
+                        ModelStatus::Downloaded
+                    } else {
+                        ModelStatus::NotDownloaded
+                    };
+                    state.info.download_progress = None;
+                }
+                return Err(e);
+            }
+        };
 
-        // Update status
         {
             let mut models = self.models.write().await;
             if let Some(state) = models.get_mut(&variant) {
                 state.info.status = ModelStatus::Downloaded;
-                state.info.local_path = Some(result.clone());
+                state.info.local_path = Some(path.clone());
+                state.info.quantization = Quantization::None;
+                state.info.download_progress = Some(100.0);
                 state.info.size_bytes = self.downloader.get_cached_size(variant);
+                state.last_used = SystemTime::now();
             }
         }
+        self.downloader.mark_used(&path);
+        self.evict_for_limit(variant).await;
 
-        Ok(result)
+        Ok(path)
     }
 
     /// Load a model into memory
@@ -164,11 +243,32 @@ impl ModelManager {
         }
 
         info!("Loading model {} from {:?}", variant, model_path);
+        self.downloader.mark_used(&model_path);
 
         // Load weights (blocking operation)
-        let weights = tokio::task::spawn_blocking(move || ModelWeights::load(&model_path))
-            .await
-            .map_err(|e| Error::ModelLoadError(e.to_string()))??;
+        let options = LoadOptions {
+            prefetch: self.config.prefetch_weights,
+            layout: if self.config.layout_cache {
+                vec![LayoutOp::FuseQkv]
+            } else {
+                Vec::new()
+            },
+        };
+        let loading = self.loading.clone();
+        let weights = tokio::task::spawn_blocking(move || {
+            let report = |progress| {
+                let mut loading = loading.lock().unwrap_or_else(|e| e.into_inner());
+                loading.insert(variant, progress);
+            };
+            let weights = ModelWeights::load_with(&model_path, &options, &report);
+            loading
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&variant);
+            weights
+        })
+        .await
+        .map_err(|e| Error::ModelLoadError(e.to_string()))??;
 
         let weights = Arc::new(weights);
 
@@ -178,6 +278,7 @@ impl ModelManager {
             if let Some(state) = models.get_mut(&variant) {
                 state.info.status = ModelStatus::Ready;
                 state.weights = Some(weights.clone());
+                state.last_used = SystemTime::now();
             }
         }
 
@@ -185,6 +286,84 @@ impl ModelManager {
         Ok(weights)
     }
 
+    /// Write a quantized copy of a downloaded model under the models directory
+    pub async fn quantize_model(
+        &self,
+        variant: ModelVariant,
+        quant: Quantization,
+    ) -> Result<QuantizeReport> {
+        if !self.downloader.is_downloaded(variant) {
+            return Err(Error::ModelNotFound(format!(
+                "Model {} not downloaded. Please download it first.",
+                variant
+            )));
+        }
+
+        let src = self.downloader.model_path(variant);
+        let dst = self.downloader.quantized_path(variant, quant);
+        info!("Quantizing model {} to {}", variant, quant);
+        let report =
+            tokio::task::spawn_blocking(move || quant::quantize_model_dir(&src, &dst, quant))
+                .await
+                .map_err(|e| Error::ModelLoadError(e.to_string()))??;
+
+        {
+            let mut models = self.models.write().await;
+            if let Some(state) = models.get_mut(&variant) {
+                if !state.info.quantized_variants.contains(&quant) {
+                    state.info.quantized_variants.push(quant);
+                }
+                // Weights loaded from the replaced copy are stale
+                if state.info.quantization == quant {
+                    state.weights = None;
+                    state.info.status = ModelStatus::Downloaded;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Choose which copy of a model the next load uses
+    pub async fn select_quantization(
+        &self,
+        variant: ModelVariant,
+        quant: Quantization,
+    ) -> Result<()> {
+        let mut models = self.models.write().await;
+        let state = models
+            .get_mut(&variant)
+            .ok_or_else(|| Error::ModelNotFound(variant.to_string()))?;
+        if state.info.quantization == quant && state.info.local_path.is_some() {
+            return Ok(());
+        }
+
+        let available = match quant {
+            Quantization::None => self.downloader.is_downloaded(variant),
+            quant => state.info.quantized_variants.contains(&quant),
+        };
+        if !available {
+            return Err(Error::ModelNotFound(format!(
+                "No {} copy of model {}. Quantize it first.",
+                quant, variant
+            )));
+        }
+
+        state.weights = None;
+        state.info.status = ModelStatus::Downloaded;
+        state.info.local_path = Some(self.downloader.quantized_path(variant, quant));
+        state.info.quantization = quant;
+        Ok(())
+    }
+
+    /// Progress of the models whose weights are being loaded
+    pub fn load_progress(&self) -> Vec<(ModelVariant, LoadProgress)> {
+        let loading = self.loading.lock().unwrap_or_else(|e| e.into_inner());
+        let mut progress: Vec<_> = loading.iter().map(|(v, p)| (*v, *p)).collect();
+        progress.sort_by_key(|(variant, _)| variant.to_string());
+        progress
+    }
+
     /// Unload a model from memory
     pub async fn unload_model(&self, variant: ModelVariant) -> Result<()> {
         let mut models = self.models.write().await;
@@ -214,14 +393,86 @@ impl ModelManager {
             .unwrap_or(false)
     }
 
+    /// Verify downloaded files against the hub manifest and re-fetch corrupt ones
+    pub async fn repair_model(&self, variant: ModelVariant) -> Result<RepairReport> {
+        let report = tokio::task::spawn_blocking({
+            let downloader = self.downloader.clone();
+            move || downloader.repair(variant)
+        })
+        .await
+        .map_err(|e| Error::DownloadError(e.to_string()))??;
+
+        {
+            let mut models = self.models.write().await;
+            if let Some(state) = models.get_mut(&variant) {
+                if report.is_complete() && state.info.status == ModelStatus::NotDownloaded {
+                    state.info.status = ModelStatus::Downloaded;
+                    state.info.local_path = Some(self.downloader.model_path(variant));
+                }
+                state.info.size_bytes = self.downloader.get_cached_size(variant);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Delete the least recently used downloaded models until the cache fits
+    /// `model_cache_limit`. Loaded models and those being loaded or
+    /// downloaded are never evicted.
+    pub async fn enforce_cache_limit(&self) -> Result<Vec<ModelVariant>> {
+        self.evict_except(None).await
+    }
+
+    async fn evict_except(&self, keep: Option<ModelVariant>) -> Result<Vec<ModelVariant>> {
+        let limit = self.config.model_cache_limit;
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let entries: Vec<CacheEntry> = {
+            let models = self.models.read().await;
+            models
+                .iter()
+                .map(|(variant, state)| CacheEntry {
+                    variant: *variant,
+                    size_bytes: self.downloader.disk_usage(*variant),
+                    last_used: state.last_used,
+                    pinned: keep == Some(*variant)
+                        || state.weights.is_some()
+                        || matches!(
+                            state.info.status,
+                            ModelStatus::Loading | ModelStatus::Downloading | ModelStatus::Ready
+                        ),
+                })
+                .filter(|entry| entry.size_bytes > 0)
+                .collect()
+        };
+
+        let evicted = select_evictions(entries, limit);
+        for variant in &evicted {
+            info!("Evicting least recently used model {}", variant);
+            self.delete_model(*variant).await?;
+        }
+        Ok(evicted)
+    }
+
+    /// Enforce the cache limit after `variant` was downloaded, keeping it
+    async fn evict_for_limit(&self, variant: ModelVariant) {
+        if let Err(e) = self.evict_except(Some(variant)).await {
+            warn!("Model cache eviction failed: {}", e);
+        }
+    }
+
     /// Delete downloaded model files
     pub async fn delete_model(&self, variant: ModelVariant) -> Result<()> {
         // Unload first
         self.unload_model(variant).await?;
 
-        let model_path = self.downloader.model_path(variant);
-        if model_path.exists() {
-            std::fs::remove_dir_all(&model_path)?;
+        for quant in [Quantization::None, Quantization::Q8, Quantization::Q4] {
+            let model_path = self.downloader.quantized_path(variant, quant);
+            if model_path.exists() {
+                std::fs::remove_dir_all(&model_path)?;
+            }
         }
 
         // Update status
@@ -229,6 +480,7 @@ impl ModelManager {
             let mut models = self.models.write().await;
             if let Some(state) = models.get_mut(&variant) {
                 state.info = ModelInfo::new(variant);
+                state.info.generation_defaults = self.config.model_defaults(variant);
             }
         }
 
@@ -236,9 +488,79 @@ impl ModelManager {
     }
 }
 
-// Make downloader cloneable for async tasks
-impl Clone for ModelDownloader {
-    fn clone(&self) -> Self {
-        ModelDownloader::new(self.models_dir.clone()).unwrap()
+/// A downloaded model as seen by cache eviction
+struct CacheEntry {
+    variant: ModelVariant,
+    size_bytes: u64,
+    last_used: SystemTime,
+    /// In use, never evicted
+    pinned: bool,
+}
+
+/// Models to delete, least recently used first, to bring the total under
+/// `limit`. Pinned models count towards the total but are skipped.
+fn select_evictions(mut entries: Vec<CacheEntry>, limit: u64) -> Vec<ModelVariant> {
+    let mut total: u64 = entries.iter().map(|e| e.size_bytes).sum();
+    entries.sort_by_key(|e| e.last_used);
+    let mut evicted = Vec::new();
+    for entry in entries.iter().filter(|e| !e.pinned) {
+        if total <= limit {
+            break;
+        }
+        total = total.saturating_sub(entry.size_bytes);
+        evicted.push(entry.variant);
+    }
+    evicted
+}
+
+/// Point built-in variants that aren't downloaded at matching folders found
+/// elsewhere, e.g. a copy in one of the extra model dirs
+fn adopt_discovered(
+    models: &mut HashMap<ModelVariant, ModelState>,
+    discovered: &[DiscoveredModel],
+) {
+    for model in discovered {
+        let Some(state) = model.variant.and_then(|v| models.get_mut(&v)) else {
+            continue;
+        };
+        if state.info.local_path.is_none() && model.format.is_some() {
+            state.info.status = ModelStatus::Downloaded;
+            state.info.local_path = Some(model.path.clone());
+            state.info.size_bytes = Some(model.size_bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_evicts_least_recently_used_unpinned_models() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let entry = |variant, size_bytes, secs, pinned| CacheEntry {
+            variant,
+            size_bytes,
+            last_used: at(secs),
+            pinned,
+        };
+        let entries = vec![
+            entry(ModelVariant::Qwen3Tts12Hz06BBase, 400, 30, false),
+            // Oldest, but loaded
+            entry(ModelVariant::Qwen3Tts12Hz17BBase, 500, 10, true),
+            entry(ModelVariant::Qwen3TtsTokenizer12Hz, 100, 20, false),
+            entry(ModelVariant::Lfm2Audio15B, 300, 40, false),
+        ];
+
+        // 1300 bytes against an 800 byte cap: drop the tokenizer, then the 0.6B
+        assert_eq!(
+            select_evictions(entries, 800),
+            [
+                ModelVariant::Qwen3TtsTokenizer12Hz,
+                ModelVariant::Qwen3Tts12Hz06BBase
+            ]
+        );
+        assert!(select_evictions(Vec::new(), 1).is_empty());
     }
 }
@@ -1,11 +1,20 @@
 //! Model management for Qwen3-TTS
 
+mod artifacts;
+mod discovery;
 mod download;
+pub mod gguf;
 mod info;
+pub mod layout;
 mod manager;
+pub mod quant;
 pub mod weights;
 
-pub use download::ModelDownloader;
+pub use artifacts::{ArtifactKind, DownloadFilter, DownloadPlan, RepoFile};
+pub use discovery::{DiscoveredModel, ModelCapability, WeightsFormat};
+pub use download::{DownloadProgress, ModelDownloader, RepairReport};
 pub use info::{ModelInfo, ModelStatus, ModelVariant};
+pub use layout::LayoutOp;
 pub use manager::ModelManager;
-pub use weights::ModelWeights;
+pub use quant::{Quantization, QuantizeReport};
+pub use weights::{LoadOptions, LoadProgress, ModelWeights};
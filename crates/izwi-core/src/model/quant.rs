@@ -0,0 +1,497 @@
+//! Quantized weight formats
+//!
+//! Quantized tensors are stored in safetensors files as a pair of entries:
+//! `<name>.qweight` holding the packed integers and `<name>.scales` holding
+//! one `f32` scale per group of [`GROUP_SIZE`] consecutive values. The
+//! scheme is symmetric, like GGUF's Q8_0 and Q4_0 blocks.
+//!
+//! [`quantize_model_dir`] produces such files from a downloaded
+//! full-precision model.
+
+use safetensors::tensor::TensorView;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info};
+
+use crate::error::{Error, Result};
+use crate::model::weights::{ModelWeights, TensorData, TensorDtype};
+
+/// Values sharing one scale
+pub const GROUP_SIZE: usize = 32;
+
+/// Suffix of the packed integer tensor
+pub const QWEIGHT_SUFFIX: &str = ".qweight";
+
+/// Suffix of the per-group scale tensor
+pub const SCALES_SUFFIX: &str = ".scales";
+
+/// Safetensors metadata key naming the quantization of a file
+pub const METADATA_KEY: &str = "quantization";
+
+/// Precision of stored model weights
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantization {
+    /// Weights as published (fp16/bf16/fp32)
+    #[default]
+    None,
+    /// 8-bit integers
+    Q8,
+    /// 4-bit integers, two per byte
+    Q4,
+}
+
+impl Quantization {
+    /// Bits per stored weight
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::None => 16,
+            Self::Q8 => 8,
+            Self::Q4 => 4,
+        }
+    }
+
+    /// Name used in configs, file metadata and directory names
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Q8 => "q8",
+            Self::Q4 => "q4",
+        }
+    }
+
+    /// Approximate weight size relative to fp16, including scales
+    pub fn size_factor(&self) -> f32 {
+        match self {
+            Self::None => 1.0,
+            Self::Q8 => (8.0 + 32.0 / GROUP_SIZE as f32) / 16.0,
+            Self::Q4 => (4.0 + 32.0 / GROUP_SIZE as f32) / 16.0,
+        }
+    }
+
+    /// Largest magnitude of a quantized value
+    fn max_level(&self) -> f32 {
+        match self {
+            Self::None => 1.0,
+            Self::Q8 => 127.0,
+            Self::Q4 => 7.0,
+        }
+    }
+
+    /// Bytes of packed data holding `len` values
+    pub fn packed_len(&self, len: usize) -> usize {
+        match self {
+            Self::None => len * 2,
+            Self::Q8 => len,
+            Self::Q4 => len.div_ceil(2),
+        }
+    }
+}
+
+impl fmt::Display for Quantization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Quantization {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "fp16" | "f16" => Ok(Self::None),
+            "q8" | "int8" | "q8_0" => Ok(Self::Q8),
+            "q4" | "int4" | "q4_0" => Ok(Self::Q4),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown quantization '{}': use none, q8 or q4",
+                s
+            ))),
+        }
+    }
+}
+
+/// Quantize values, returning the packed integers and per-group scales
+pub fn quantize(quant: Quantization, values: &[f32]) -> (Vec<u8>, Vec<f32>) {
+    let max_level = quant.max_level();
+    let mut packed = vec![0u8; quant.packed_len(values.len())];
+    let mut scales = Vec::with_capacity(values.len().div_ceil(GROUP_SIZE));
+
+    for (g, group) in values.chunks(GROUP_SIZE).enumerate() {
+        let max = group.iter().fold(0f32, |m, v| m.max(v.abs()));
+        let scale = if max > 0.0 { max / max_level } else { 1.0 };
+        scales.push(scale);
+
+        for (j, &v) in group.iter().enumerate() {
+            let i = g * GROUP_SIZE + j;
+            let level = (v / scale).round().clamp(-max_level, max_level) as i8;
+            match quant {
+                Quantization::Q8 => packed[i] = level as u8,
+                Quantization::Q4 => {
+                    let nibble = (level + 8) as u8;
+                    packed[i / 2] |= if i.is_multiple_of(2) {
+                        nibble
+                    } else {
+                        nibble << 4
+                    };
+                }
+                Quantization::None => {}
+            }
+        }
+    }
+
+    (packed, scales)
+}
+
+/// Integer level of element `i` in packed data
+#[inline]
+fn level(quant: Quantization, packed: &[u8], i: usize) -> f32 {
+    match quant {
+        Quantization::Q8 => packed[i] as i8 as f32,
+        Quantization::Q4 => {
+            let byte = packed[i / 2];
+            let nibble = if i.is_multiple_of(2) {
+                byte & 0x0f
+            } else {
+                byte >> 4
+            };
+            nibble as f32 - 8.0
+        }
+        Quantization::None => 0.0,
+    }
+}
+
+/// Expand `len` quantized values back to `f32`
+pub fn dequantize(
+    quant: Quantization,
+    packed: &[u8],
+    scales: &[f32],
+    len: usize,
+) -> Result<Vec<f32>> {
+    if quant == Quantization::None {
+        return Err(Error::InvalidInput("Tensor is not quantized".into()));
+    }
+    if packed.len() < quant.packed_len(len) || scales.len() < len.div_ceil(GROUP_SIZE) {
+        return Err(Error::ModelLoadError(format!(
+            "Quantized tensor too short for {} values",
+            len
+        )));
+    }
+    Ok((0..len)
+        .map(|i| level(quant, packed, i) * scales[i / GROUP_SIZE])
+        .collect())
+}
+
+/// A row-major quantized matrix used without dequantizing it first
+#[derive(Debug, Clone)]
+pub struct QuantizedMatrix<'a> {
+    pub quant: Quantization,
+    pub rows: usize,
+    pub cols: usize,
+    packed: &'a [u8],
+    scales: Vec<f32>,
+}
+
+impl<'a> QuantizedMatrix<'a> {
+    /// Wrap packed data and scales for a `rows x cols` matrix
+    pub fn new(
+        quant: Quantization,
+        rows: usize,
+        cols: usize,
+        packed: &'a [u8],
+        scales: Vec<f32>,
+    ) -> Result<Self> {
+        let len = rows * cols;
+        if quant == Quantization::None
+            || packed.len() < quant.packed_len(len)
+            || scales.len() < len.div_ceil(GROUP_SIZE)
+        {
+            return Err(Error::ModelLoadError(format!(
+                "Invalid {} matrix of {}x{}",
+                quant, rows, cols
+            )));
+        }
+        Ok(Self {
+            quant,
+            rows,
+            cols,
+            packed,
+            scales,
+        })
+    }
+
+    /// Compute `self * x`, accumulating each group before applying its scale
+    pub fn matvec(&self, x: &[f32]) -> Result<Vec<f32>> {
+        if x.len() != self.cols {
+            return Err(Error::InvalidInput(format!(
+                "Vector of length {} does not match {} columns",
+                x.len(),
+                self.cols
+            )));
+        }
+
+        let mut out = Vec::with_capacity(self.rows);
+        for row in 0..self.rows {
+            let start = row * self.cols;
+            let end = start + self.cols;
+            let mut sum = 0.0;
+            let mut i = start;
+            while i < end {
+                let group = i / GROUP_SIZE;
+                let group_end = ((group + 1) * GROUP_SIZE).min(end);
+                let mut acc = 0.0;
+                for k in i..group_end {
+                    acc += level(self.quant, self.packed, k) * x[k - start];
+                }
+                sum += acc * self.scales[group];
+                i = group_end;
+            }
+            out.push(sum);
+        }
+        Ok(out)
+    }
+}
+
+/// Summary of a model conversion
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuantizeReport {
+    pub quantization: Quantization,
+    /// Tensors stored as packed integers
+    pub quantized_tensors: usize,
+    /// Tensors copied at their original precision
+    pub kept_tensors: usize,
+    /// Size of the source weights
+    pub source_bytes: u64,
+    /// Size of the converted weights
+    pub output_bytes: u64,
+}
+
+/// Float matrices (or higher) large enough to fill a group; Q4 also needs an
+/// even last dimension so packing keeps rows byte-aligned
+fn should_quantize(quant: Quantization, tensor: &TensorData) -> bool {
+    matches!(
+        tensor.dtype,
+        TensorDtype::Float32 | TensorDtype::Float16 | TensorDtype::BFloat16
+    ) && tensor.shape.len() >= 2
+        && tensor.shape.iter().product::<usize>() >= GROUP_SIZE
+        && (quant != Quantization::Q4 || tensor.shape.last().is_some_and(|d| d.is_multiple_of(2)))
+}
+
+/// Write a quantized copy of the model in `src` to `dst`
+///
+/// Top-level safetensors files are converted; other files and subdirectories
+/// (configs, tokenizers, codec weights) are copied unchanged. The copy is
+/// staged next to `dst` and renamed into place once complete.
+pub fn quantize_model_dir(src: &Path, dst: &Path, quant: Quantization) -> Result<QuantizeReport> {
+    if quant == Quantization::None {
+        return Err(Error::InvalidInput(
+            "Target quantization must be q8 or q4".to_string(),
+        ));
+    }
+
+    let mut staging = dst.as_os_str().to_owned();
+    staging.push(".incomplete");
+    let staging = std::path::PathBuf::from(staging);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    let mut report = QuantizeReport {
+        quantization: quant,
+        ..Default::default()
+    };
+    let result = (|| {
+        for entry in std::fs::read_dir(src)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let target = staging.join(name);
+            if path.is_dir() {
+                copy_dir(&path, &target)?;
+            } else if path.extension().is_some_and(|e| e == "safetensors") {
+                quantize_file(&path, &target, quant, &mut report)?;
+            } else if !path.to_string_lossy().ends_with(".incomplete") {
+                std::fs::copy(&path, &target)?;
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    if dst.exists() {
+        std::fs::remove_dir_all(dst)?;
+    }
+    std::fs::rename(&staging, dst)?;
+
+    info!(
+        "Quantized {:?} to {}: {} tensors converted, {} kept, {} -> {} bytes",
+        src,
+        quant,
+        report.quantized_tensors,
+        report.kept_tensors,
+        report.source_bytes,
+        report.output_bytes
+    );
+    Ok(report)
+}
+
+/// Convert one safetensors file
+fn quantize_file(
+    src: &Path,
+    dst: &Path,
+    quant: Quantization,
+    report: &mut QuantizeReport,
+) -> Result<()> {
+    let (tensors, existing) = ModelWeights::load_safetensors(src)?;
+    if existing != Quantization::None {
+        return Err(Error::InvalidInput(format!(
+            "{:?} is already quantized ({})",
+            src, existing
+        )));
+    }
+    debug!("Quantizing {} tensors from {:?}", tensors.len(), src);
+    report.source_bytes += std::fs::metadata(src)?.len();
+
+    // Converted buffers must outlive the views handed to the serializer
+    let mut converted = HashMap::new();
+    for (name, tensor) in &tensors {
+        if !should_quantize(quant, tensor) {
+            report.kept_tensors += 1;
+            continue;
+        }
+        let (packed, scales) = quantize(quant, &tensor.to_f32()?);
+        let scales: Vec<u8> = scales.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut shape = tensor.shape.clone();
+        if quant == Quantization::Q4 {
+            if let Some(last) = shape.last_mut() {
+                *last /= 2;
+            }
+        }
+        converted.insert(name.clone(), (shape, packed, scales));
+        report.quantized_tensors += 1;
+    }
+
+    let mut views = Vec::with_capacity(tensors.len() + converted.len());
+    for (name, tensor) in &tensors {
+        match converted.get(name) {
+            Some((shape, packed, scales)) => {
+                let scale_count = scales.len() / 4;
+                views.push((
+                    format!("{}{}", name, QWEIGHT_SUFFIX),
+                    TensorView::new(safetensors::Dtype::U8, shape.clone(), packed)?,
+                ));
+                views.push((
+                    format!("{}{}", name, SCALES_SUFFIX),
+                    TensorView::new(safetensors::Dtype::F32, vec![scale_count], scales)?,
+                ));
+            }
+            None => views.push((name.clone(), tensor.view()?)),
+        }
+    }
+
+    let metadata = HashMap::from([(METADATA_KEY.to_string(), quant.to_string())]);
+    safetensors::serialize_to_file(views, &Some(metadata), dst)?;
+    report.output_bytes += std::fs::metadata(dst)?.len();
+    Ok(())
+}
+
+/// Recursively copy a directory
+fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if path.is_dir() {
+            copy_dir(&path, &dst.join(name))?;
+        } else {
+            std::fs::copy(&path, dst.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_error_is_bounded() {
+        let values: Vec<f32> = (0..100).map(|i| (i as f32 * 0.37).sin()).collect();
+        for (quant, tolerance) in [(Quantization::Q8, 0.01), (Quantization::Q4, 0.08)] {
+            let (packed, scales) = quantize(quant, &values);
+            assert_eq!(packed.len(), quant.packed_len(values.len()));
+            let restored = dequantize(quant, &packed, &scales, values.len()).unwrap();
+            for (a, b) in values.iter().zip(&restored) {
+                assert!((a - b).abs() <= tolerance, "{} vs {} ({})", a, b, quant);
+            }
+        }
+        assert_eq!("int4".parse::<Quantization>().unwrap(), Quantization::Q4);
+    }
+
+    #[test]
+    fn test_matvec_matches_dequantized() {
+        let (rows, cols) = (3, 40);
+        let weights: Vec<f32> = (0..rows * cols).map(|i| (i as f32 * 0.11).cos()).collect();
+        let x: Vec<f32> = (0..cols).map(|i| i as f32 / cols as f32).collect();
+
+        let (packed, scales) = quantize(Quantization::Q4, &weights);
+        let dense = dequantize(Quantization::Q4, &packed, &scales, weights.len()).unwrap();
+        let matrix = QuantizedMatrix::new(Quantization::Q4, rows, cols, &packed, scales).unwrap();
+
+        let out = matrix.matvec(&x).unwrap();
+        for (r, value) in out.iter().enumerate() {
+            let expected: f32 = (0..cols).map(|c| dense[r * cols + c] * x[c]).sum();
+            assert!((value - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_quantize_model_dir() {
+        let root = std::env::temp_dir().join(format!("izwi-quantize-{}", std::process::id()));
+        let (src, dst) = (root.join("model"), root.join("model-q8"));
+        std::fs::create_dir_all(src.join("speech_tokenizer")).unwrap();
+        std::fs::write(src.join("config.json"), "{}").unwrap();
+        std::fs::write(src.join("speech_tokenizer/config.json"), "{}").unwrap();
+
+        let weight: Vec<u8> = (0..64)
+            .flat_map(|i| (i as f32 / 32.0 - 1.0).to_le_bytes())
+            .collect();
+        let bias: Vec<u8> = [0.5f32; 2].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let tensors = vec![
+            (
+                "proj.weight",
+                TensorView::new(safetensors::Dtype::F32, vec![2, 32], &weight).unwrap(),
+            ),
+            (
+                "proj.bias",
+                TensorView::new(safetensors::Dtype::F32, vec![2], &bias).unwrap(),
+            ),
+        ];
+        safetensors::serialize_to_file(tensors, &None, &src.join("model.safetensors")).unwrap();
+
+        let report = quantize_model_dir(&src, &dst, Quantization::Q8).unwrap();
+        assert_eq!((report.quantized_tensors, report.kept_tensors), (1, 1));
+        assert!(dst.join("config.json").exists());
+        assert!(dst.join("speech_tokenizer/config.json").exists());
+
+        let weights = ModelWeights::load(&dst).unwrap();
+        assert_eq!(weights.quantization, Quantization::Q8);
+        assert!(weights.is_quantized("proj.weight"));
+        assert_eq!(weights.shape("proj.weight").unwrap(), vec![2, 32]);
+        assert_eq!(weights.get_f32("proj.bias").unwrap(), vec![0.5, 0.5]);
+
+        // Quantizing an already quantized copy is rejected
+        assert!(quantize_model_dir(&dst, &root.join("again"), Quantization::Q4).is_err());
+        assert!(!root.join("again.incomplete").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
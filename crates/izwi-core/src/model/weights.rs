@@ -1,20 +1,172 @@
-//! Model weight loading from safetensors
+//! Model weight loading from safetensors and GGUF
+//!
+//! Files are memory-mapped rather than read into memory, so tensor bytes are
+//! only paged in when accessed and never held twice. GGUF tensors that need
+//! repacking are the exception and are held in owned buffers.
+//!
+//! Shards are indexed in parallel. A prefetching load also reads every page
+//! of the mapped shards into the page cache, so the first requests don't
+//! stall on disk, and reports its progress as it goes. Loads can rewrite
+//! tensors into kernel-friendly layouts, cached on disk by [`layout`].
 
+use memmap2::Mmap;
+use rayon::prelude::*;
+use safetensors::tensor::TensorView;
 use safetensors::SafeTensors;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::Path;
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 use tracing::{debug, info};
 
 use crate::config::ModelConfig;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::model::gguf::GgufFile;
+use crate::model::layout::{self, LayoutOp};
+use crate::model::quant::{
+    self, Quantization, QuantizedMatrix, METADATA_KEY, QWEIGHT_SUFFIX, SCALES_SUFFIX,
+};
 
-/// Tensor data loaded from safetensors
+/// Bytes tensors point into
 #[derive(Debug)]
+pub(crate) enum Storage {
+    /// A memory-mapped weights file
+    Mapped(Mmap),
+    /// Tensor data converted at load time
+    Owned(Vec<u8>),
+}
+
+impl Storage {
+    pub(crate) fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A tensor backed by a memory-mapped weights file
+#[derive(Debug, Clone)]
 pub struct TensorData {
     pub name: String,
     pub shape: Vec<usize>,
     pub dtype: TensorDtype,
-    pub data: Vec<u8>,
+    raw_dtype: safetensors::Dtype,
+    storage: Arc<Storage>,
+    range: Range<usize>,
+}
+
+impl TensorData {
+    /// A tensor at `range` of shared storage
+    pub(crate) fn new(
+        name: String,
+        shape: Vec<usize>,
+        raw_dtype: safetensors::Dtype,
+        storage: Arc<Storage>,
+        range: Range<usize>,
+    ) -> Self {
+        Self {
+            name,
+            shape,
+            dtype: TensorDtype::from_safetensors(raw_dtype),
+            raw_dtype,
+            storage,
+            range,
+        }
+    }
+
+    /// A tensor owning its bytes
+    pub(crate) fn owned(
+        name: String,
+        shape: Vec<usize>,
+        raw_dtype: safetensors::Dtype,
+        bytes: Vec<u8>,
+    ) -> Self {
+        let range = 0..bytes.len();
+        Self::new(
+            name,
+            shape,
+            raw_dtype,
+            Arc::new(Storage::Owned(bytes)),
+            range,
+        )
+    }
+
+    /// Element type as stored in the file
+    pub fn raw_dtype(&self) -> safetensors::Dtype {
+        self.raw_dtype
+    }
+
+    /// Raw little-endian bytes, borrowed from the mapping
+    pub fn data(&self) -> &[u8] {
+        &self.storage.bytes()[self.range.clone()]
+    }
+
+    /// Size of the tensor in bytes
+    pub fn len_bytes(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Zero-copy safetensors view of the tensor
+    pub fn view(&self) -> Result<TensorView<'_>> {
+        Ok(TensorView::new(
+            self.raw_dtype,
+            self.shape.clone(),
+            self.data(),
+        )?)
+    }
+
+    /// Copy the tensor into owned memory
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data().to_vec()
+    }
+
+    /// Materialize the tensor as `f32` values, converting from half precision
+    pub fn to_f32(&self) -> Result<Vec<f32>> {
+        let data = self.data();
+        let values = match self.dtype {
+            TensorDtype::Float32 => data
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            TensorDtype::Float16 => data
+                .chunks_exact(2)
+                .map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]])))
+                .collect(),
+            TensorDtype::BFloat16 => data
+                .chunks_exact(2)
+                .map(|b| f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16))
+                .collect(),
+            other => {
+                return Err(Error::ModelLoadError(format!(
+                    "Tensor {} has non-float dtype {:?}",
+                    self.name, other
+                )))
+            }
+        };
+        Ok(values)
+    }
+}
+
+/// Convert an IEEE 754 half-precision value to `f32`
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    let value = match (exponent, mantissa) {
+        (0, 0) => sign,
+        // Subnormal: renormalize into the f32 range
+        (0, m) => {
+            let shift = m.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((m << shift) & 0x3ff) << 13
+        }
+        (0x1f, m) => sign | 0x7f80_0000 | (m << 13),
+        (e, m) => sign | ((e + 112) << 23) | (m << 13),
+    };
+    f32::from_bits(value)
 }
 
 /// Supported tensor data types
@@ -25,6 +177,7 @@ pub enum TensorDtype {
     BFloat16,
     Int32,
     Int64,
+    Int8,
     Uint8,
 }
 
@@ -36,30 +189,138 @@ impl TensorDtype {
             safetensors::Dtype::BF16 => Self::BFloat16,
             safetensors::Dtype::I32 => Self::Int32,
             safetensors::Dtype::I64 => Self::Int64,
+            safetensors::Dtype::I8 => Self::Int8,
             safetensors::Dtype::U8 => Self::Uint8,
             _ => Self::Float32, // Default fallback
         }
     }
 
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::Float32 | Self::Float16 | Self::BFloat16)
+    }
+
     pub fn size_bytes(&self) -> usize {
         match self {
             Self::Float32 | Self::Int32 => 4,
             Self::Float16 | Self::BFloat16 => 2,
             Self::Int64 => 8,
-            Self::Uint8 => 1,
+            Self::Int8 | Self::Uint8 => 1,
+        }
+    }
+}
+
+/// Bytes prefetched between progress reports
+const PREFETCH_CHUNK_BYTES: usize = 64 * 1024 * 1024;
+
+/// Stride of the reads that fault pages in
+const PAGE_BYTES: usize = 4096;
+
+/// How far a weight load has got
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LoadProgress {
+    /// Tensors in shards that are fully loaded
+    pub tensors_loaded: usize,
+    pub total_tensors: usize,
+    pub bytes_loaded: u64,
+    pub total_bytes: u64,
+}
+
+impl LoadProgress {
+    /// Share of the bytes loaded, from 0 to 100
+    pub fn percent(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        (self.bytes_loaded as f64 / self.total_bytes as f64 * 100.0) as f32
+    }
+}
+
+/// Progress counters shared by the loading threads
+struct ProgressTracker<'a> {
+    tensors_loaded: AtomicUsize,
+    total_tensors: usize,
+    bytes_loaded: AtomicU64,
+    total_bytes: u64,
+    callback: &'a (dyn Fn(LoadProgress) + Sync),
+}
+
+impl ProgressTracker<'_> {
+    fn add(&self, tensors: usize, bytes: u64) {
+        let tensors_loaded = self.tensors_loaded.fetch_add(tensors, Ordering::Relaxed) + tensors;
+        let bytes_loaded = self.bytes_loaded.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        (self.callback)(LoadProgress {
+            tensors_loaded,
+            total_tensors: self.total_tensors,
+            bytes_loaded,
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+/// One indexed safetensors shard
+struct Shard {
+    storage: Arc<Storage>,
+    tensors: HashMap<String, TensorData>,
+    quantization: Quantization,
+}
+
+impl Shard {
+    /// Read every page of the shard, reporting progress per chunk
+    fn prefetch(&self, progress: &ProgressTracker) {
+        let Storage::Mapped(mmap) = self.storage.as_ref() else {
+            progress.add(self.tensors.len(), self.storage.bytes().len() as u64);
+            return;
+        };
+        #[cfg(unix)]
+        if let Err(e) = mmap.advise(memmap2::Advice::WillNeed) {
+            debug!("madvise(WILLNEED) failed: {}", e);
+        }
+        let chunks = mmap.chunks(PREFETCH_CHUNK_BYTES);
+        let count = chunks.len();
+        for (i, chunk) in chunks.enumerate() {
+            let touched = chunk.iter().step_by(PAGE_BYTES).fold(0u8, |acc, b| acc ^ b);
+            std::hint::black_box(touched);
+            let tensors = if i + 1 == count {
+                self.tensors.len()
+            } else {
+                0
+            };
+            progress.add(tensors, chunk.len() as u64);
         }
     }
 }
 
+/// How a model directory is loaded
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Read every page into the page cache before returning
+    pub prefetch: bool,
+    /// Layout rewrites applied to safetensors weights
+    pub layout: Vec<LayoutOp>,
+}
+
 /// Loaded model weights
 pub struct ModelWeights {
     pub config: ModelConfig,
     pub tensors: HashMap<String, TensorData>,
+    /// Storage format of quantized tensors (`None` for full-precision files)
+    pub quantization: Quantization,
 }
 
 impl ModelWeights {
-    /// Load model weights from a directory
+    /// Load model weights from a directory, leaving tensor pages on disk
+    /// until they are used
     pub fn load(model_dir: &Path) -> Result<Self> {
+        Self::load_with(model_dir, &LoadOptions::default(), &|_| {})
+    }
+
+    /// Load model weights from a directory. `progress` is called from the
+    /// loading threads as shards are read.
+    pub fn load_with(
+        model_dir: &Path,
+        options: &LoadOptions,
+        progress: &(dyn Fn(LoadProgress) + Sync),
+    ) -> Result<Self> {
         info!("Loading model weights from {:?}", model_dir);
 
         // Load config
@@ -73,33 +334,136 @@ impl ModelWeights {
 
         debug!("Model config: {:?}", config);
 
-        // Find and load safetensors files
+        // Find and load safetensors files, falling back to GGUF
+        let safetensor_files = Self::find_files(model_dir, "safetensors")?;
+        if safetensor_files.is_empty() {
+            let gguf_files = Self::find_files(model_dir, "gguf")?;
+            if !gguf_files.is_empty() {
+                return Self::load_gguf(
+                    &gguf_files,
+                    config_path.exists().then_some(config),
+                    progress,
+                );
+            }
+        }
+
+        let shards = safetensor_files
+            .par_iter()
+            .map(|file_path| {
+                debug!("Loading weights from {:?}", file_path);
+                Self::map_safetensors(file_path)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let tracker = ProgressTracker {
+            tensors_loaded: AtomicUsize::new(0),
+            total_tensors: shards.iter().map(|s| s.tensors.len()).sum(),
+            bytes_loaded: AtomicU64::new(0),
+            total_bytes: shards.iter().map(|s| s.storage.bytes().len() as u64).sum(),
+            callback: progress,
+        };
+        if options.prefetch {
+            shards.par_iter().for_each(|shard| shard.prefetch(&tracker));
+        } else {
+            for shard in &shards {
+                tracker.add(shard.tensors.len(), shard.storage.bytes().len() as u64);
+            }
+        }
+
         let mut tensors = HashMap::new();
-        let safetensor_files = Self::find_safetensor_files(model_dir)?;
+        let mut quantization = Quantization::None;
+        for shard in shards {
+            if shard.quantization != Quantization::None {
+                quantization = shard.quantization;
+            }
+            tensors.extend(shard.tensors);
+        }
+        if !safetensor_files.is_empty() {
+            layout::apply(
+                &mut tensors,
+                &safetensor_files,
+                &layout::cache_path(model_dir),
+                &options.layout,
+            )?;
+        }
+
+        info!(
+            "Loaded {} tensors (quantization: {})",
+            tensors.len(),
+            quantization
+        );
+
+        Ok(Self {
+            config,
+            tensors,
+            quantization,
+        })
+    }
 
-        for file_path in safetensor_files {
-            debug!("Loading weights from {:?}", file_path);
-            let file_tensors = Self::load_safetensors(&file_path)?;
-            tensors.extend(file_tensors);
+    /// Load GGUF files (shards of one model), taking the config from
+    /// `config.json` when present and from the GGUF metadata otherwise
+    fn load_gguf(
+        files: &[PathBuf],
+        config: Option<ModelConfig>,
+        progress: &(dyn Fn(LoadProgress) + Sync),
+    ) -> Result<Self> {
+        let ggufs = files
+            .par_iter()
+            .map(|path| {
+                debug!("Loading GGUF weights from {:?}", path);
+                GgufFile::open(path)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // One quantization for the whole model; other block formats are
+        // dequantized
+        let quantization = ggufs
+            .iter()
+            .map(GgufFile::quantization)
+            .find(|q| *q != Quantization::None)
+            .unwrap_or_default();
+        let mut tensors = HashMap::new();
+        for gguf in &ggufs {
+            tensors.extend(gguf.tensors(quantization)?);
         }
+        let bytes = tensors.values().map(|t| t.len_bytes() as u64).sum();
+        progress(LoadProgress {
+            tensors_loaded: tensors.len(),
+            total_tensors: tensors.len(),
+            bytes_loaded: bytes,
+            total_bytes: bytes,
+        });
 
-        info!("Loaded {} tensors", tensors.len());
+        let config = config.unwrap_or_else(|| {
+            let architecture = ggufs.iter().find_map(GgufFile::architecture);
+            ModelConfig {
+                architectures: architecture.map(str::to_string).into_iter().collect(),
+                model_type: architecture.map(str::to_string),
+                ..Default::default()
+            }
+        });
+
+        info!(
+            "Loaded {} tensors from GGUF (quantization: {})",
+            tensors.len(),
+            quantization
+        );
 
-        Ok(Self { config, tensors })
+        Ok(Self {
+            config,
+            tensors,
+            quantization,
+        })
     }
 
-    /// Find all safetensors files in directory
-    fn find_safetensor_files(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    /// Find all files with `extension` in directory
+    fn find_files(dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
         let mut files = Vec::new();
 
         for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
-            if path
-                .extension()
-                .map(|e| e == "safetensors")
-                .unwrap_or(false)
-            {
+            if path.extension().map(|e| e == extension).unwrap_or(false) {
                 files.push(path);
             }
         }
@@ -109,30 +473,49 @@ impl ModelWeights {
         Ok(files)
     }
 
-    /// Load tensors from a single safetensors file
-    fn load_safetensors(path: &Path) -> Result<HashMap<String, TensorData>> {
-        let data = std::fs::read(path)?;
-        let tensors = SafeTensors::deserialize(&data)?;
+    /// Map a safetensors file and index its tensors without copying them
+    pub(crate) fn load_safetensors(
+        path: &Path,
+    ) -> Result<(HashMap<String, TensorData>, Quantization)> {
+        let shard = Self::map_safetensors(path)?;
+        Ok((shard.tensors, shard.quantization))
+    }
 
-        let mut result = HashMap::new();
+    fn map_safetensors(path: &Path) -> Result<Shard> {
+        let file = File::open(path)?;
+        // Safety: model files are not expected to change while mapped
+        let storage = Arc::new(Storage::Mapped(unsafe { Mmap::map(&file)? }));
+        let (header_size, metadata) = SafeTensors::read_metadata(storage.bytes())?;
+        let data_start = 8 + header_size;
+        let quantization = metadata
+            .metadata()
+            .as_ref()
+            .and_then(|m| m.get(METADATA_KEY))
+            .map(|q| q.parse())
+            .transpose()?
+            .unwrap_or_default();
 
-        for (name, tensor) in tensors.tensors() {
-            let shape: Vec<usize> = tensor.shape().to_vec();
-            let dtype = TensorDtype::from_safetensors(tensor.dtype());
-            let tensor_data = tensor.data().to_vec();
+        let mut result = HashMap::new();
 
+        for (name, info) in metadata.tensors() {
+            let (start, end) = info.data_offsets;
             result.insert(
-                name.to_string(),
-                TensorData {
-                    name: name.to_string(),
-                    shape,
-                    dtype,
-                    data: tensor_data,
-                },
+                name.clone(),
+                TensorData::new(
+                    name,
+                    info.shape.clone(),
+                    info.dtype,
+                    storage.clone(),
+                    data_start + start..data_start + end,
+                ),
             );
         }
 
-        Ok(result)
+        Ok(Shard {
+            storage,
+            tensors: result,
+            quantization,
+        })
     }
 
     /// Get a tensor by name
@@ -140,6 +523,69 @@ impl ModelWeights {
         self.tensors.get(name)
     }
 
+    /// Whether a weight is stored quantized
+    pub fn is_quantized(&self, name: &str) -> bool {
+        !self.tensors.contains_key(name)
+            && self
+                .tensors
+                .contains_key(&format!("{}{}", name, QWEIGHT_SUFFIX))
+    }
+
+    /// Logical shape of a weight, quantized or not
+    pub fn shape(&self, name: &str) -> Option<Vec<usize>> {
+        if let Some(tensor) = self.get(name) {
+            return Some(tensor.shape.clone());
+        }
+        let qweight = self.get(&format!("{}{}", name, QWEIGHT_SUFFIX))?;
+        Some(self.logical_shape(qweight))
+    }
+
+    /// Q4 packs two values per byte along the last dimension
+    fn logical_shape(&self, qweight: &TensorData) -> Vec<usize> {
+        let mut shape = qweight.shape.clone();
+        if self.quantization == Quantization::Q4 {
+            if let Some(last) = shape.last_mut() {
+                *last *= 2;
+            }
+        }
+        shape
+    }
+
+    /// Materialize a weight as `f32`, dequantizing it if needed
+    pub fn get_f32(&self, name: &str) -> Result<Vec<f32>> {
+        if let Some(tensor) = self.get(name) {
+            return tensor.to_f32();
+        }
+        let (qweight, scales) = self.quantized_parts(name)?;
+        let len = self.logical_shape(qweight).iter().product();
+        quant::dequantize(self.quantization, qweight.data(), &scales, len)
+    }
+
+    /// Borrow a 2-D quantized weight for direct matrix-vector products
+    pub fn quantized_matrix(&self, name: &str) -> Result<QuantizedMatrix<'_>> {
+        let (qweight, scales) = self.quantized_parts(name)?;
+        let shape = self.logical_shape(qweight);
+        let [rows, cols] = shape[..] else {
+            return Err(Error::ModelLoadError(format!(
+                "Quantized weight {} is not a matrix: {:?}",
+                name, shape
+            )));
+        };
+        QuantizedMatrix::new(self.quantization, rows, cols, qweight.data(), scales)
+    }
+
+    fn quantized_parts(&self, name: &str) -> Result<(&TensorData, Vec<f32>)> {
+        let missing = || Error::ModelLoadError(format!("Weight {} not found", name));
+        let qweight = self
+            .get(&format!("{}{}", name, QWEIGHT_SUFFIX))
+            .ok_or_else(missing)?;
+        let scales = self
+            .get(&format!("{}{}", name, SCALES_SUFFIX))
+            .ok_or_else(missing)?
+            .to_f32()?;
+        Ok((qweight, scales))
+    }
+
     /// Get tensor names matching a prefix
     pub fn get_by_prefix(&self, prefix: &str) -> Vec<&TensorData> {
         self.tensors
@@ -149,8 +595,127 @@ impl ModelWeights {
             .collect()
     }
 
-    /// Total memory used by weights
+    /// Total size of all tensors (mapped, not necessarily resident)
     pub fn memory_bytes(&self) -> usize {
-        self.tensors.values().map(|t| t.data.len()).sum()
+        self.tensors.values().map(|t| t.len_bytes()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use safetensors::Dtype;
+
+    #[test]
+    fn test_mapped_tensors() {
+        let dir = std::env::temp_dir().join(format!("izwi-weights-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let values: Vec<u8> = [1.5f32, -2.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let half: Vec<u8> = [0x3c00u16, 0xc000, 0x0001]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let tensors = vec![
+            (
+                "layer.weight",
+                TensorView::new(Dtype::F32, vec![2], &values).unwrap(),
+            ),
+            (
+                "layer.bias",
+                TensorView::new(Dtype::F16, vec![3], &half).unwrap(),
+            ),
+        ];
+        safetensors::serialize_to_file(tensors, &None, &dir.join("model.safetensors")).unwrap();
+
+        let weights = ModelWeights::load(&dir).unwrap();
+        let weight = weights.get("layer.weight").unwrap();
+        assert_eq!(weight.data(), values.as_slice());
+        assert_eq!(weight.to_f32().unwrap(), vec![1.5, -2.0]);
+        assert_eq!(weight.view().unwrap().shape(), &[2]);
+
+        let bias = weights.get("layer.bias").unwrap().to_f32().unwrap();
+        assert_eq!(&bias[..2], &[1.0, -2.0]);
+        assert_eq!(bias[2], 2f32.powi(-24));
+
+        assert_eq!(weights.get_by_prefix("layer.").len(), 2);
+        assert_eq!(weights.memory_bytes(), 14);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sharded_load_reports_progress() {
+        let dir = std::env::temp_dir().join(format!("izwi-shards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let values: Vec<u8> = (0..256u32).flat_map(|v| (v as f32).to_le_bytes()).collect();
+        for shard in 0..3 {
+            let name = format!("layers.{}.weight", shard);
+            let tensors = vec![(
+                name.as_str(),
+                TensorView::new(Dtype::F32, vec![256], &values).unwrap(),
+            )];
+            let path = dir.join(format!("model-0000{}-of-00003.safetensors", shard + 1));
+            safetensors::serialize_to_file(tensors, &None, &path).unwrap();
+        }
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let options = LoadOptions {
+            prefetch: true,
+            ..Default::default()
+        };
+        let weights =
+            ModelWeights::load_with(&dir, &options, &|p| reports.lock().unwrap().push(p)).unwrap();
+        assert_eq!(weights.tensors.len(), 3);
+        assert_eq!(weights.get_f32("layers.2.weight").unwrap()[255], 255.0);
+
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), 3);
+        let last = reports.iter().max_by_key(|p| p.bytes_loaded).unwrap();
+        assert_eq!(last.tensors_loaded, 3);
+        assert_eq!(last.bytes_loaded, last.total_bytes);
+        assert_eq!(last.percent(), 100.0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_quantized_weights() {
+        let dir = std::env::temp_dir().join(format!("izwi-qweights-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let values: Vec<f32> = (0..64).map(|i| i as f32 / 64.0 - 0.5).collect();
+        let (packed, scales) = quant::quantize(Quantization::Q4, &values);
+        let scale_bytes: Vec<u8> = scales.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let tensors = vec![
+            (
+                "proj.weight.qweight",
+                TensorView::new(Dtype::U8, vec![2, 16], &packed).unwrap(),
+            ),
+            (
+                "proj.weight.scales",
+                TensorView::new(Dtype::F32, vec![2], &scale_bytes).unwrap(),
+            ),
+        ];
+        let metadata = HashMap::from([(METADATA_KEY.to_string(), "q4".to_string())]);
+        safetensors::serialize_to_file(tensors, &Some(metadata), &dir.join("model.safetensors"))
+            .unwrap();
+
+        let weights = ModelWeights::load(&dir).unwrap();
+        assert_eq!(weights.quantization, Quantization::Q4);
+        assert!(weights.is_quantized("proj.weight"));
+        assert_eq!(weights.shape("proj.weight").unwrap(), vec![2, 32]);
+
+        let dense = weights.get_f32("proj.weight").unwrap();
+        assert!(dense.iter().zip(&values).all(|(a, b)| (a - b).abs() < 0.05));
+        let out = weights
+            .quantized_matrix("proj.weight")
+            .unwrap()
+            .matvec(&[1.0; 32])
+            .unwrap();
+        assert_eq!(out.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,341 @@
+//! Per-tenant encryption of data held on behalf of tenants
+//!
+//! Stored outputs and saved voices are sealed with AES-256-GCM under the
+//! owning tenant's active key. Older key versions stay available for
+//! decryption until the tenant's data has been re-sealed after a rotation.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::RwLock;
+use tracing::info;
+
+use crate::config::EncryptionConfig;
+use crate::error::{Error, Result};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// A versioned 256-bit tenant key
+#[derive(Clone)]
+struct TenantKey {
+    version: u32,
+    cipher: Aes256Gcm,
+}
+
+/// Bytes stored for a tenant, encrypted when the tenant has a key
+#[derive(Debug, Clone)]
+pub struct SealedData {
+    key_version: Option<u32>,
+    nonce: [u8; NONCE_LEN],
+    bytes: Vec<u8>,
+}
+
+impl SealedData {
+    /// Whether the data is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.key_version.is_some()
+    }
+
+    /// Key version used to seal the data
+    pub fn key_version(&self) -> Option<u32> {
+        self.key_version
+    }
+
+    /// Stored size in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether no bytes are stored
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Tenant encryption keys, loaded from config or fetched by an external command.
+///
+/// Data without a tenant is stored in the clear.
+pub struct TenantKeyring {
+    keys: RwLock<HashMap<String, Vec<TenantKey>>>,
+    key_command: Option<String>,
+    require_key: bool,
+}
+
+impl std::fmt::Debug for TenantKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tenants: Vec<String> = self.read_keys().keys().cloned().collect();
+        f.debug_struct("TenantKeyring")
+            .field("tenants", &tenants)
+            .field("key_command", &self.key_command)
+            .field("require_key", &self.require_key)
+            .finish()
+    }
+}
+
+impl Default for TenantKeyring {
+    fn default() -> Self {
+        Self {
+            keys: RwLock::new(HashMap::new()),
+            key_command: None,
+            require_key: false,
+        }
+    }
+}
+
+impl TenantKeyring {
+    /// Build a keyring from the encryption settings
+    pub fn from_config(config: &EncryptionConfig) -> Result<Self> {
+        let mut keys = HashMap::new();
+        for (tenant, encoded) in &config.tenant_keys {
+            validate_tenant(tenant)?;
+            let versions = encoded
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    Ok(TenantKey {
+                        version: i as u32 + 1,
+                        cipher: cipher_from_base64(key)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            keys.insert(tenant.clone(), versions);
+        }
+
+        Ok(Self {
+            keys: RwLock::new(keys),
+            key_command: config.key_command.clone(),
+            require_key: config.require_key,
+        })
+    }
+
+    fn read_keys(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, Vec<TenantKey>>> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_keys(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, Vec<TenantKey>>> {
+        self.keys.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run the key command for a tenant, returning its base64 key
+    fn fetch_key(&self, tenant: &str) -> Result<Option<Aes256Gcm>> {
+        let Some(command) = &self.key_command else {
+            return Ok(None);
+        };
+        // The tenant ID is passed as $1 rather than interpolated
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", command))
+            .arg("sh")
+            .arg(tenant)
+            .output()
+            .map_err(|e| Error::EncryptionError(format!("key command failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::EncryptionError(format!(
+                "key command for tenant '{}' exited with {}",
+                tenant, output.status
+            )));
+        }
+        let key = String::from_utf8_lossy(&output.stdout);
+        cipher_from_base64(key.trim()).map(Some)
+    }
+
+    /// Active key for a tenant, fetching it on first use
+    fn active_key(&self, tenant: &str) -> Result<Option<TenantKey>> {
+        if let Some(key) = self.read_keys().get(tenant).and_then(|v| v.last()) {
+            return Ok(Some(key.clone()));
+        }
+        let Some(cipher) = self.fetch_key(tenant)? else {
+            return Ok(None);
+        };
+        let mut keys = self.write_keys();
+        let versions = keys.entry(tenant.to_string()).or_default();
+        if versions.is_empty() {
+            versions.push(TenantKey { version: 1, cipher });
+        }
+        Ok(versions.last().cloned())
+    }
+
+    /// Encrypt data for a tenant (stored in the clear without a tenant)
+    pub fn seal(&self, tenant: Option<&str>, plaintext: &[u8]) -> Result<SealedData> {
+        let Some(tenant) = tenant else {
+            return Ok(SealedData {
+                key_version: None,
+                nonce: [0; NONCE_LEN],
+                bytes: plaintext.to_vec(),
+            });
+        };
+        validate_tenant(tenant)?;
+
+        match self.active_key(tenant)? {
+            Some(key) => {
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+                let bytes = key
+                    .cipher
+                    .encrypt(&nonce, plaintext)
+                    .map_err(|_| Error::EncryptionError("encryption failed".into()))?;
+                Ok(SealedData {
+                    key_version: Some(key.version),
+                    nonce: nonce.into(),
+                    bytes,
+                })
+            }
+            None if self.require_key => Err(Error::EncryptionError(format!(
+                "no encryption key for tenant '{}'",
+                tenant
+            ))),
+            None => Ok(SealedData {
+                key_version: None,
+                nonce: [0; NONCE_LEN],
+                bytes: plaintext.to_vec(),
+            }),
+        }
+    }
+
+    /// Decrypt data sealed for a tenant
+    pub fn open(&self, tenant: Option<&str>, sealed: &SealedData) -> Result<Vec<u8>> {
+        let Some(version) = sealed.key_version else {
+            return Ok(sealed.bytes.clone());
+        };
+        let tenant = tenant
+            .ok_or_else(|| Error::EncryptionError("encrypted data requires a tenant".into()))?;
+
+        let keys = self.read_keys();
+        let key = keys
+            .get(tenant)
+            .and_then(|v| v.iter().find(|k| k.version == version))
+            .ok_or_else(|| {
+                Error::EncryptionError(format!(
+                    "key version {} for tenant '{}' is not available",
+                    version, tenant
+                ))
+            })?;
+        key.cipher
+            .decrypt(Nonce::from_slice(&sealed.nonce), sealed.bytes.as_slice())
+            .map_err(|_| Error::EncryptionError("decryption failed".into()))
+    }
+
+    /// Re-encrypt data under the tenant's active key
+    pub fn reseal(&self, tenant: &str, sealed: &SealedData) -> Result<SealedData> {
+        let plaintext = self.open(Some(tenant), sealed)?;
+        self.seal(Some(tenant), &plaintext)
+    }
+
+    /// Make a new key active for a tenant, returning its version.
+    ///
+    /// Without an explicit key, the key command is asked for one, and a
+    /// random key is generated if that yields nothing new. Previous versions
+    /// remain usable for decryption until [`retire_old_keys`](Self::retire_old_keys).
+    pub fn rotate(&self, tenant: &str, key: Option<&str>) -> Result<u32> {
+        validate_tenant(tenant)?;
+        let cipher = match key {
+            Some(key) => cipher_from_base64(key)?,
+            None => match self.fetch_key(tenant)? {
+                Some(cipher) => cipher,
+                None => Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng)),
+            },
+        };
+
+        let mut keys = self.write_keys();
+        let versions = keys.entry(tenant.to_string()).or_default();
+        let version = versions.last().map(|k| k.version + 1).unwrap_or(1);
+        versions.push(TenantKey { version, cipher });
+        info!("Rotated key for tenant '{}' to version {}", tenant, version);
+        Ok(version)
+    }
+
+    /// Drop all but the active key of a tenant
+    pub fn retire_old_keys(&self, tenant: &str) {
+        if let Some(versions) = self.write_keys().get_mut(tenant) {
+            let keep = versions.len().saturating_sub(1);
+            versions.drain(..keep);
+        }
+    }
+
+    /// Forget every key of a tenant, making its sealed data unreadable
+    pub fn forget(&self, tenant: &str) -> bool {
+        self.write_keys().remove(tenant).is_some()
+    }
+}
+
+/// Tenant IDs are restricted so they are safe in logs, paths and commands
+pub fn validate_tenant(tenant: &str) -> Result<()> {
+    let valid = !tenant.is_empty()
+        && tenant.len() <= 64
+        && tenant
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::InvalidInput(format!(
+            "Invalid tenant ID '{}': use up to 64 letters, digits, '-', '_' or '.'",
+            tenant
+        )));
+    }
+    Ok(())
+}
+
+fn cipher_from_base64(encoded: &str) -> Result<Aes256Gcm> {
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| Error::EncryptionError(format!("invalid base64 key: {}", e)))?;
+    if bytes.len() != KEY_LEN {
+        return Err(Error::EncryptionError(format!(
+            "keys must be {} bytes, got {}",
+            KEY_LEN,
+            bytes.len()
+        )));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring(tenant: &str, key_byte: u8) -> TenantKeyring {
+        let mut config = EncryptionConfig::default();
+        config
+            .tenant_keys
+            .insert(tenant.to_string(), vec![BASE64.encode([key_byte; KEY_LEN])]);
+        TenantKeyring::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_seal_roundtrip_and_isolation() {
+        let keyring = keyring("acme", 7);
+        let sealed = keyring.seal(Some("acme"), b"hello").unwrap();
+        assert!(sealed.is_encrypted());
+        assert_ne!(sealed.bytes, b"hello");
+        assert_eq!(keyring.open(Some("acme"), &sealed).unwrap(), b"hello");
+
+        // Other tenants and tenant-less callers cannot read it
+        assert!(keyring.open(Some("globex"), &sealed).is_err());
+        assert!(keyring.open(None, &sealed).is_err());
+
+        // Tenants without keys are stored in the clear unless keys are required
+        assert!(!keyring.seal(Some("globex"), b"x").unwrap().is_encrypted());
+        assert!(validate_tenant("../etc").is_err());
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_data_readable_until_retired() {
+        let keyring = keyring("acme", 1);
+        let old = keyring.seal(Some("acme"), b"data").unwrap();
+
+        let version = keyring.rotate("acme", None).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(keyring.open(Some("acme"), &old).unwrap(), b"data");
+
+        let resealed = keyring.reseal("acme", &old).unwrap();
+        assert_eq!(resealed.key_version(), Some(2));
+
+        keyring.retire_old_keys("acme");
+        assert!(keyring.open(Some("acme"), &old).is_err());
+        assert_eq!(keyring.open(Some("acme"), &resealed).unwrap(), b"data");
+
+        assert!(keyring.forget("acme"));
+        assert!(keyring.open(Some("acme"), &resealed).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! Profanity filtering for ASR transcripts and TTS input
+//!
+//! The word list is a text file with one word or phrase per line; blank
+//! lines and lines starting with `#` are ignored. A trailing `*` matches any
+//! word with that prefix (`damn*` covers "damned" and "damnit"). Matches are
+//! whole-word and case-insensitive. Without a file, a short built-in list is
+//! used.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// File name of the word list inside the models directory
+pub const PROFANITY_FILE: &str = "profanity.txt";
+
+/// Word spoken in place of a filtered word in TTS input
+pub const BLEEP: &str = "bleep";
+
+/// Replacement for a filtered word in a tagged transcript
+pub const PROFANITY_TAG: &str = "[profanity]";
+
+const DEFAULT_WORDS: &[&str] = &[
+    "arse",
+    "arsehole*",
+    "ass",
+    "asshole*",
+    "bastard*",
+    "bitch*",
+    "bollocks",
+    "bullshit*",
+    "crap*",
+    "cunt*",
+    "damn*",
+    "dick",
+    "dickhead*",
+    "fuck*",
+    "goddamn*",
+    "motherfuck*",
+    "piss*",
+    "shit*",
+    "twat*",
+    "wank*",
+];
+
+/// How profanity in an ASR transcript is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptFilter {
+    /// Keep the first letter and star out the rest (`f***`)
+    Mask,
+    /// Replace the word with [`PROFANITY_TAG`]
+    Tag,
+}
+
+/// How profanity in TTS input is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechFilter {
+    /// Refuse the request
+    Reject,
+    /// Speak [`BLEEP`] instead of the word
+    Bleep,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Lowercased words of the entry
+    words: Vec<String>,
+    /// The last word matches as a prefix
+    prefix: bool,
+}
+
+/// Word list matched against text
+#[derive(Debug, Clone)]
+pub struct ProfanityFilter {
+    entries: Vec<Entry>,
+}
+
+impl Default for ProfanityFilter {
+    fn default() -> Self {
+        Self::from_words(DEFAULT_WORDS.iter().copied())
+    }
+}
+
+impl ProfanityFilter {
+    /// Build a filter from word list entries
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut entries: Vec<Entry> = words
+            .into_iter()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let prefix = line.ends_with('*');
+                let words = line
+                    .trim_end_matches('*')
+                    .split_whitespace()
+                    .map(str::to_lowercase)
+                    .collect();
+                Entry { words, prefix }
+            })
+            .filter(|entry| !entry.words.is_empty())
+            .collect();
+        // Longest phrase first, so "mother fucker" wins over "fucker"
+        entries.sort_by_key(|e| std::cmp::Reverse(e.words.len()));
+        Self { entries }
+    }
+
+    /// Load the word list at `path`; a missing file yields the built-in list
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Error::ConfigError(format!("Invalid word list {}: {}", path.display(), e))
+        })?;
+        Ok(Self::from_words(content.lines()))
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `text` contains a listed word
+    pub fn contains(&self, text: &str) -> bool {
+        !self.find(text).is_empty()
+    }
+
+    /// Apply `mode` to a transcript
+    pub fn filter_transcript(&self, text: &str, mode: TranscriptFilter) -> String {
+        self.replace(text, |matched| match mode {
+            TranscriptFilter::Mask => mask(matched),
+            TranscriptFilter::Tag => PROFANITY_TAG.to_string(),
+        })
+    }
+
+    /// Apply `mode` to text about to be spoken
+    pub fn filter_speech(&self, text: &str, mode: SpeechFilter) -> Result<String> {
+        match mode {
+            SpeechFilter::Reject if self.contains(text) => Err(Error::InvalidInput(
+                "Text contains words blocked by the profanity filter".to_string(),
+            )),
+            SpeechFilter::Reject => Ok(text.to_string()),
+            SpeechFilter::Bleep => Ok(self.replace(text, |_| BLEEP.to_string())),
+        }
+    }
+
+    fn replace(&self, text: &str, replacement: impl Fn(&str) -> String) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in self.find(text) {
+            out.push_str(&text[last..range.start]);
+            out.push_str(&replacement(&text[range.clone()]));
+            last = range.end;
+        }
+        out.push_str(&text[last..]);
+        out
+    }
+
+    /// Byte ranges of matches, in order and not overlapping
+    fn find(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        let words = word_spans(text);
+        let lowered: Vec<String> = words
+            .iter()
+            .map(|w| text[w.clone()].to_lowercase())
+            .collect();
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let matched = self.entries.iter().find_map(|entry| {
+                let n = entry.words.len();
+                let candidate = lowered.get(i..i + n)?;
+                let (last, rest) = entry.words.split_last()?;
+                let head_matches = candidate[..n - 1] == *rest;
+                let tail = &candidate[n - 1];
+                let tail_matches = if entry.prefix {
+                    tail.starts_with(last.as_str())
+                } else {
+                    tail == last
+                };
+                (head_matches && tail_matches).then_some(n)
+            });
+            match matched {
+                Some(n) => {
+                    found.push(words[i].start..words[i + n - 1].end);
+                    i += n;
+                }
+                None => i += 1,
+            }
+        }
+        found
+    }
+}
+
+/// Byte ranges of the words in `text`; apostrophes inside a word belong to it
+fn word_spans(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_word = chars.peek().is_some_and(|(_, n)| n.is_alphanumeric());
+        let in_word = c.is_alphanumeric() || (c == '\'' && start.is_some() && next_is_word);
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                spans.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push(s..text.len());
+    }
+    spans
+}
+
+/// First letter kept, the rest starred out
+fn mask(word: &str) -> String {
+    let mut chars = word.chars();
+    let first = chars.next().map(String::from).unwrap_or_default();
+    first
+        + &chars
+            .map(|c| if c.is_whitespace() { c } else { '*' })
+            .collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_transcript_and_speech() {
+        let filter = ProfanityFilter::from_words(["# comment", "darn*", "heck", "gosh darn"]);
+        assert_eq!(filter.len(), 3);
+
+        let text = "Well, HECK, that's darned good. Gosh darn it, Hecktor!";
+        assert_eq!(
+            filter.filter_transcript(text, TranscriptFilter::Mask),
+            "Well, H***, that's d***** good. G*** **** it, Hecktor!"
+        );
+        assert_eq!(
+            filter.filter_transcript(text, TranscriptFilter::Tag),
+            "Well, [profanity], that's [profanity] good. [profanity] it, Hecktor!"
+        );
+
+        assert_eq!(
+            filter
+                .filter_speech("Oh heck.", SpeechFilter::Bleep)
+                .unwrap(),
+            "Oh bleep."
+        );
+        assert!(filter
+            .filter_speech("Oh heck.", SpeechFilter::Reject)
+            .is_err());
+        assert_eq!(
+            filter
+                .filter_speech("Oh, hello.", SpeechFilter::Reject)
+                .unwrap(),
+            "Oh, hello."
+        );
+
+        assert!(ProfanityFilter::default().contains("What the fuck?"));
+        assert!(!ProfanityFilter::default().contains("Class assignment at the Arsenal"));
+    }
+}
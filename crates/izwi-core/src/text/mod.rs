@@ -0,0 +1,12 @@
+//! Text processing around inference: filters applied to TTS input and ASR
+//! transcripts, spelling out codes, sentence segmentation and subtitles
+
+pub mod filter;
+pub mod sentences;
+pub mod spell;
+pub mod subtitles;
+
+pub use filter::{ProfanityFilter, SpeechFilter, TranscriptFilter, PROFANITY_FILE};
+pub use sentences::{RuleSegmenter, Segmenters, SentenceSegmenter};
+pub use spell::SpellOut;
+pub use subtitles::SubtitleFormat;
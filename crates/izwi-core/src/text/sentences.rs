@@ -0,0 +1,277 @@
+//! Sentence boundaries, per language
+//!
+//! The built-in rules end a sentence at `.`, `!`, `?` or `…` followed by a
+//! space, and at the full-width `。！？` used in Chinese and Japanese with
+//! or without one. A full stop does not end a sentence after an
+//! abbreviation of the language ("Dr.", "z.B."), an initial ("J. Smith"),
+//! a German ordinal ("am 3. Mai"), or before a lowercase word; decimal
+//! points never do, as no space follows them. Abbreviations can be added
+//! per language in `[engine.segmentation]`, and a language's rules replaced
+//! altogether by registering a [`SentenceSegmenter`] for it.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::config::SegmentationConfig;
+use crate::language::normalize_language;
+
+/// Splits text into sentences
+pub trait SentenceSegmenter: Send + Sync {
+    /// The sentences of `text` in order, without surrounding whitespace
+    fn segment<'a>(&self, text: &'a str) -> Vec<&'a str>;
+}
+
+const ENGLISH_ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "inc", "ltd",
+    "co", "corp", "no", "fig", "approx", "dept", "gen", "gov", "capt", "lt", "col", "sgt", "a.m",
+    "p.m", "jan", "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct", "nov", "dec",
+];
+
+const GERMAN_ABBREVIATIONS: &[&str] = &[
+    "dr", "prof", "hr", "fr", "nr", "str", "bzw", "usw", "vgl", "z.b", "d.h", "u.a", "ca", "evtl",
+    "ggf", "inkl", "bspw", "bzgl", "etc", "jh", "mio", "mrd", "s", "u.s.w",
+];
+
+const FRENCH_ABBREVIATIONS: &[&str] = &[
+    "m", "mme", "mlle", "mm", "dr", "pr", "me", "st", "ste", "etc", "cf", "p.ex", "env", "av",
+    "bd", "janv", "févr", "avr", "juil", "sept", "oct", "nov", "déc",
+];
+
+const SPANISH_ABBREVIATIONS: &[&str] = &[
+    "sr", "sra", "srta", "dr", "dra", "ud", "uds", "etc", "p.ej", "pág", "núm", "av", "ej", "aprox",
+];
+
+const ITALIAN_ABBREVIATIONS: &[&str] = &[
+    "sig", "sig.ra", "sigg", "dott", "dott.ssa", "prof", "ecc", "es", "pag", "avv", "ing", "ca",
+];
+
+const PORTUGUESE_ABBREVIATIONS: &[&str] = &[
+    "sr", "sra", "srta", "dr", "dra", "etc", "p.ex", "pág", "av", "exmo", "exma", "aprox",
+];
+
+const RUSSIAN_ABBREVIATIONS: &[&str] = &[
+    "т.е", "т.д", "т.п", "г", "гг", "ул", "им", "др", "стр", "см", "напр", "проф", "тыс", "млн",
+];
+
+/// Sentence splitting by punctuation, with a language's abbreviations
+#[derive(Debug, Clone, Default)]
+pub struct RuleSegmenter {
+    /// Lowercase, without the final full stop
+    abbreviations: HashSet<String>,
+    /// A number followed by a full stop is an ordinal ("3." = "third")
+    ordinal_dot: bool,
+}
+
+impl RuleSegmenter {
+    /// Built-in rules for a model language name ("German"); other languages
+    /// get the punctuation rules without abbreviations
+    pub fn for_language(language: &str) -> Self {
+        let abbreviations = match language {
+            "English" => ENGLISH_ABBREVIATIONS,
+            "German" => GERMAN_ABBREVIATIONS,
+            "French" => FRENCH_ABBREVIATIONS,
+            "Spanish" => SPANISH_ABBREVIATIONS,
+            "Italian" => ITALIAN_ABBREVIATIONS,
+            "Portuguese" => PORTUGUESE_ABBREVIATIONS,
+            "Russian" => RUSSIAN_ABBREVIATIONS,
+            _ => &[],
+        };
+        Self {
+            abbreviations: HashSet::new(),
+            ordinal_dot: language == "German",
+        }
+        .with_abbreviations(abbreviations)
+    }
+
+    /// Also treat `words` as abbreviations ("approx" or "approx.")
+    pub fn with_abbreviations<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.abbreviations.extend(
+            words
+                .into_iter()
+                .map(|word| word.as_ref().trim().trim_end_matches('.').to_lowercase()),
+        );
+        self
+    }
+
+    /// Whether the full stop at byte `pos` of `text` ends the word before it
+    /// rather than a sentence
+    fn is_abbreviation(&self, text: &str, pos: usize) -> bool {
+        let word = text[..pos]
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches(|c: char| !c.is_alphanumeric());
+        let mut chars = word.chars();
+        let initial = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase());
+        let ordinal =
+            self.ordinal_dot && !word.is_empty() && word.chars().all(|c| c.is_ascii_digit());
+        initial || ordinal || self.abbreviations.contains(&word.to_lowercase())
+    }
+}
+
+/// Punctuation that ends a sentence when a space follows
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…') || is_full_width_terminator(c)
+}
+
+/// Punctuation that ends a sentence whatever follows
+fn is_full_width_terminator(c: char) -> bool {
+    matches!(c, '。' | '！' | '？' | '．')
+}
+
+/// Quotes and brackets that close a sentence after its punctuation
+fn is_closer(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '”' | '’' | '»' | '」' | '』' | '）'
+    )
+}
+
+impl SentenceSegmenter for RuleSegmenter {
+    fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let (pos, c) = chars[i];
+            if !is_terminator(c) {
+                i += 1;
+                continue;
+            }
+            // The whole run of punctuation ("?!", "...") and closing quotes
+            let mut j = i + 1;
+            while j < chars.len() && (is_terminator(chars[j].1) || is_closer(chars[j].1)) {
+                j += 1;
+            }
+            let end = chars.get(j).map_or(text.len(), |&(p, _)| p);
+            let ends = if chars[i..j]
+                .iter()
+                .any(|&(_, c)| is_full_width_terminator(c))
+            {
+                true
+            } else {
+                let next_word = text[end..].trim_start().chars().next();
+                let at_space = end == text.len() || text[end..].starts_with(char::is_whitespace);
+                let single_stop =
+                    c == '.' && !chars[i + 1..j].iter().any(|&(_, c)| is_terminator(c));
+                at_space
+                    && !next_word.is_some_and(char::is_lowercase)
+                    && !(single_stop && next_word.is_some() && self.is_abbreviation(text, pos))
+            };
+            if ends {
+                let sentence = text[start..end].trim();
+                if !sentence.is_empty() {
+                    sentences.push(sentence);
+                }
+                start = end;
+            }
+            i = j;
+        }
+        let rest = text[start..].trim();
+        if !rest.is_empty() {
+            sentences.push(rest);
+        }
+        sentences
+    }
+}
+
+/// Sentence segmenters by language: those registered by the application,
+/// and the built-in rules with any configured abbreviations otherwise
+pub struct Segmenters {
+    abbreviations: HashMap<String, Vec<String>>,
+    custom: RwLock<HashMap<String, Arc<dyn SentenceSegmenter>>>,
+}
+
+impl Segmenters {
+    pub fn new(config: &SegmentationConfig) -> Self {
+        Self {
+            abbreviations: config
+                .abbreviations
+                .iter()
+                .map(|(language, words)| (normalize_language(language), words.clone()))
+                .collect(),
+            custom: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Use `segmenter` for `language` (a model language name or ISO code)
+    /// in place of the built-in rules
+    pub fn register(&self, language: &str, segmenter: Arc<dyn SentenceSegmenter>) {
+        self.custom
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(normalize_language(language), segmenter);
+    }
+
+    /// Segmenter for `language`
+    pub fn get(&self, language: &str) -> Arc<dyn SentenceSegmenter> {
+        let language = normalize_language(language);
+        if let Some(segmenter) = self
+            .custom
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&language)
+        {
+            return segmenter.clone();
+        }
+        let extra = self.abbreviations.get(&language).into_iter().flatten();
+        Arc::new(RuleSegmenter::for_language(&language).with_abbreviations(extra))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_segmenter() {
+        let english = RuleSegmenter::for_language("English");
+        assert_eq!(
+            english.segment("Dr. J. Smith paid $3.50, e.g. for tea. \"Really?!\" Yes... Fine."),
+            vec![
+                "Dr. J. Smith paid $3.50, e.g. for tea.",
+                "\"Really?!\"",
+                "Yes...",
+                "Fine."
+            ]
+        );
+        assert_eq!(
+            RuleSegmenter::for_language("Chinese").segment("你好。今天天气很好！走吧"),
+            vec!["你好。", "今天天气很好！", "走吧"]
+        );
+        let german = RuleSegmenter::for_language("German");
+        assert_eq!(
+            german.segment("Er kam am 3. Mai, z.B. mit Dr. Weber. Dann ging er."),
+            vec!["Er kam am 3. Mai, z.B. mit Dr. Weber.", "Dann ging er."]
+        );
+        // English has no ordinal full stops
+        assert_eq!(english.segment("We had 3. Then 4.").len(), 2);
+    }
+
+    #[test]
+    fn test_segmenters() {
+        struct Lines;
+        impl SentenceSegmenter for Lines {
+            fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+                text.lines().collect()
+            }
+        }
+
+        let config = SegmentationConfig {
+            abbreviations: HashMap::from([("en".to_string(), vec!["min.".to_string()])]),
+        };
+        let segmenters = Segmenters::new(&config);
+        assert_eq!(
+            segmenters
+                .get("English")
+                .segment("Wait 5 min. Then go. Now."),
+            vec!["Wait 5 min. Then go.", "Now."]
+        );
+        segmenters.register("fr", Arc::new(Lines));
+        assert_eq!(
+            segmenters.get("French").segment("Un. Deux\nTrois"),
+            vec!["Un. Deux", "Trois"]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Spelling out codes in TTS input
+//!
+//! Serial numbers, booking references and similar codes ("A4-7Z") are read
+//! by the models as if they were words or numbers. With spell-out on, such
+//! tokens are rewritten as the names of their characters, separated by
+//! commas so each is spoken clearly: "ay, four, dash, seven, zee". Letter
+//! and digit names follow the request's language; the NATO alphabet uses
+//! the same code words in every language.
+
+use serde::{Deserialize, Serialize};
+
+/// How codes in the text are read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpellOut {
+    /// As written
+    #[default]
+    Off,
+    /// Character by character, with the language's letter names
+    Letters,
+    /// Character by character, with NATO code words for letters
+    Nato,
+}
+
+/// Names of the characters a code is made of, in one language
+struct Alphabet {
+    letters: [&'static str; 26],
+    digits: [&'static str; 10],
+    dash: &'static str,
+    slash: &'static str,
+    dot: &'static str,
+}
+
+const ENGLISH: Alphabet = Alphabet {
+    letters: [
+        "ay",
+        "bee",
+        "see",
+        "dee",
+        "ee",
+        "eff",
+        "gee",
+        "aitch",
+        "eye",
+        "jay",
+        "kay",
+        "el",
+        "em",
+        "en",
+        "oh",
+        "pee",
+        "cue",
+        "ar",
+        "ess",
+        "tee",
+        "you",
+        "vee",
+        "double-you",
+        "ex",
+        "why",
+        "zee",
+    ],
+    digits: [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ],
+    dash: "dash",
+    slash: "slash",
+    dot: "dot",
+};
+
+const GERMAN: Alphabet = Alphabet {
+    letters: [
+        "A", "Be", "Ce", "De", "E", "Ef", "Ge", "Ha", "I", "Jot", "Ka", "El", "Em", "En", "O",
+        "Pe", "Ku", "Er", "Es", "Te", "U", "Vau", "We", "Ix", "Ypsilon", "Zett",
+    ],
+    digits: [
+        "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+    ],
+    dash: "Strich",
+    slash: "Schrägstrich",
+    dot: "Punkt",
+};
+
+const FRENCH: Alphabet = Alphabet {
+    letters: [
+        "a",
+        "bé",
+        "cé",
+        "dé",
+        "e",
+        "effe",
+        "gé",
+        "ache",
+        "i",
+        "ji",
+        "ka",
+        "elle",
+        "emme",
+        "enne",
+        "o",
+        "pé",
+        "ku",
+        "erre",
+        "esse",
+        "té",
+        "u",
+        "vé",
+        "double vé",
+        "ixe",
+        "i grec",
+        "zède",
+    ],
+    digits: [
+        "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+    ],
+    dash: "tiret",
+    slash: "barre oblique",
+    dot: "point",
+};
+
+const SPANISH: Alphabet = Alphabet {
+    letters: [
+        "a",
+        "be",
+        "ce",
+        "de",
+        "e",
+        "efe",
+        "ge",
+        "hache",
+        "i",
+        "jota",
+        "ka",
+        "ele",
+        "eme",
+        "ene",
+        "o",
+        "pe",
+        "cu",
+        "erre",
+        "ese",
+        "te",
+        "u",
+        "uve",
+        "uve doble",
+        "equis",
+        "i griega",
+        "zeta",
+    ],
+    digits: [
+        "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+    ],
+    dash: "guion",
+    slash: "barra",
+    dot: "punto",
+};
+
+const ITALIAN: Alphabet = Alphabet {
+    letters: [
+        "a",
+        "bi",
+        "ci",
+        "di",
+        "e",
+        "effe",
+        "gi",
+        "acca",
+        "i",
+        "i lunga",
+        "cappa",
+        "elle",
+        "emme",
+        "enne",
+        "o",
+        "pi",
+        "cu",
+        "erre",
+        "esse",
+        "ti",
+        "u",
+        "vu",
+        "doppia vu",
+        "ics",
+        "ipsilon",
+        "zeta",
+    ],
+    digits: [
+        "zero", "uno", "due", "tre", "quattro", "cinque", "sei", "sette", "otto", "nove",
+    ],
+    dash: "trattino",
+    slash: "barra",
+    dot: "punto",
+};
+
+const PORTUGUESE: Alphabet = Alphabet {
+    letters: [
+        "a", "bê", "cê", "dê", "é", "efe", "gê", "agá", "i", "jota", "cá", "ele", "eme", "ene",
+        "ó", "pê", "quê", "erre", "esse", "tê", "u", "vê", "dáblio", "xis", "ípsilon", "zê",
+    ],
+    digits: [
+        "zero", "um", "dois", "três", "quatro", "cinco", "seis", "sete", "oito", "nove",
+    ],
+    dash: "hífen",
+    slash: "barra",
+    dot: "ponto",
+};
+
+const NATO_LETTERS: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+/// Character names for a model language name ("German"); languages
+/// without their own table use English names
+fn alphabet(language: &str) -> &'static Alphabet {
+    match language {
+        "German" => &GERMAN,
+        "French" => &FRENCH,
+        "Spanish" => &SPANISH,
+        "Italian" => &ITALIAN,
+        "Portuguese" => &PORTUGUESE,
+        _ => &ENGLISH,
+    }
+}
+
+/// A code mixes capital letters and digits ("A47Z", "B12") or joins digit
+/// groups with dashes ("4471-2290"). Lowercase suffixes ("1st", "10km")
+/// and plain numbers ("3.14", "12/05") are read normally.
+fn is_code(token: &str) -> bool {
+    let chars_ok = token
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | '.'));
+    let starts_and_ends_alnum = token
+        .chars()
+        .next()
+        .zip(token.chars().last())
+        .is_some_and(|(first, last)| first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric());
+    chars_ok
+        && starts_and_ends_alnum
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().any(|c| c.is_ascii_uppercase() || c == '-')
+}
+
+/// Names of the characters of `code`, comma separated
+fn spell(code: &str, mode: SpellOut, alphabet: &Alphabet) -> String {
+    code.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' => {
+                let index = (c.to_ascii_lowercase() as u8 - b'a') as usize;
+                match mode {
+                    SpellOut::Nato => NATO_LETTERS[index],
+                    _ => alphabet.letters[index],
+                }
+            }
+            '0'..='9' => alphabet.digits[(c as u8 - b'0') as usize],
+            '-' => alphabet.dash,
+            '/' => alphabet.slash,
+            _ => alphabet.dot,
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Rewrite the codes in `text` as spoken character names in `language`
+/// (a model language name such as "English")
+pub fn spell_out(text: &str, mode: SpellOut, language: &str) -> String {
+    if mode == SpellOut::Off {
+        return text.to_string();
+    }
+    let alphabet = alphabet(language);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        // Copy whitespace through, then take the next token
+        let token_start = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        out.push_str(&rest[..token_start]);
+        rest = &rest[token_start..];
+        let token_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let token = &rest[..token_end];
+        rest = &rest[token_end..];
+
+        // Punctuation around the token stays where it is
+        let core = token.trim_matches(|c: char| !c.is_alphanumeric());
+        if core.is_empty() || !is_code(core) {
+            out.push_str(token);
+            continue;
+        }
+        let start = token.find(core).unwrap_or(0);
+        out.push_str(&token[..start]);
+        out.push_str(&spell(core, mode, alphabet));
+        out.push_str(&token[start + core.len()..]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spell_out_codes() {
+        assert_eq!(
+            spell_out("Your code is A4-7Z.", SpellOut::Letters, "English"),
+            "Your code is ay, four, dash, seven, zee."
+        );
+        assert_eq!(
+            spell_out("Serial (4471-20).", SpellOut::Letters, "German"),
+            "Serial (vier, vier, sieben, eins, Strich, zwei, null)."
+        );
+        assert_eq!(
+            spell_out("Flight BA12 to Paris", SpellOut::Nato, "French"),
+            "Flight Bravo, Alfa, un, deux to Paris"
+        );
+        // Ordinary words and numbers are left alone
+        let text = "On the 1st, 3.14 km at 10km/h, 12/05 and 2024.";
+        assert_eq!(spell_out(text, SpellOut::Letters, "English"), text);
+        assert_eq!(spell_out("A4", SpellOut::Off, "English"), "A4");
+    }
+}
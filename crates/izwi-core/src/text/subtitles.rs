@@ -0,0 +1,299 @@
+//! SRT and WebVTT subtitles for transcripts and synthesized speech
+//!
+//! Neither the ASR daemon nor the TTS models report word alignments, so
+//! word times are estimated: a stretch of speech known to hold some text
+//! is shared out between its words by length, with extra time after
+//! punctuation for the pause a speaker makes there. Words are then grouped
+//! into cues short enough to read, ending at sentence boundaries (found by
+//! the language's [`SentenceSegmenter`]) where possible.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::sentences::SentenceSegmenter;
+
+/// Longest subtitle line, in characters
+const LINE_CHARS: usize = 42;
+
+/// Longest cue, in characters (two lines)
+const MAX_CUE_CHARS: usize = 2 * LINE_CHARS;
+
+/// Longest cue, in seconds
+const MAX_CUE_SECS: f64 = 6.0;
+
+/// Time after a word, in characters' worth: the gap between words, and the
+/// pauses after clause and sentence punctuation
+const WORD_GAP_WEIGHT: f64 = 1.0;
+const CLAUSE_PAUSE_WEIGHT: f64 = 3.0;
+const SENTENCE_PAUSE_WEIGHT: f64 = 6.0;
+
+/// Subtitle file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    /// SubRip
+    Srt,
+    /// WebVTT
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Srt => "application/x-subrip",
+            Self::Vtt => "text/vtt",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+        }
+    }
+}
+
+/// When a word is spoken
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    /// Last word of its sentence
+    pub ends_sentence: bool,
+}
+
+/// Text shown on screen for a stretch of time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub text: String,
+    /// Who is speaking, for diarized transcripts
+    pub speaker: Option<String>,
+}
+
+fn ends_clause(word: &str) -> bool {
+    word.ends_with([',', ';', ':', '，', '；', '：'])
+}
+
+/// Spread the words of `sentences` over `span` (in seconds) by their length
+pub fn estimate_word_timings(sentences: &[&str], span: Range<f64>) -> Vec<WordTiming> {
+    let words: Vec<(&str, bool)> = sentences
+        .iter()
+        .flat_map(|sentence| {
+            let count = sentence.split_whitespace().count();
+            sentence
+                .split_whitespace()
+                .enumerate()
+                .map(move |(i, word)| (word, i + 1 == count))
+        })
+        .collect();
+    let pause = |&(word, ends_sentence): &(&str, bool)| {
+        if ends_sentence {
+            SENTENCE_PAUSE_WEIGHT
+        } else if ends_clause(word) {
+            CLAUSE_PAUSE_WEIGHT
+        } else {
+            WORD_GAP_WEIGHT
+        }
+    };
+    let length = |&(word, _): &(&str, bool)| word.chars().count() as f64;
+    // The pause after the last word falls outside the span
+    let total =
+        words.iter().map(|w| length(w) + pause(w)).sum::<f64>() - words.last().map_or(0.0, pause);
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    let secs_per_char = (span.end - span.start).max(0.0) / total;
+    let mut time = span.start;
+    words
+        .into_iter()
+        .map(|word| {
+            let start_secs = time;
+            let end_secs = start_secs + length(&word) * secs_per_char;
+            time = end_secs + pause(&word) * secs_per_char;
+            WordTiming {
+                word: word.0.to_string(),
+                start_secs,
+                end_secs: end_secs.min(span.end),
+                ends_sentence: word.1,
+            }
+        })
+        .collect()
+}
+
+/// Group words into cues, breaking at sentence ends and before a cue would
+/// grow too long to read
+pub fn cues_from_words(words: &[WordTiming]) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut current: Option<Cue> = None;
+    for word in words {
+        if let Some(cue) = &current {
+            let chars = cue.text.chars().count() + 1 + word.word.chars().count();
+            if chars > MAX_CUE_CHARS || word.end_secs - cue.start_secs > MAX_CUE_SECS {
+                cues.extend(current.take());
+            }
+        }
+        match current.as_mut() {
+            Some(cue) => {
+                cue.text.push(' ');
+                cue.text.push_str(&word.word);
+                cue.end_secs = word.end_secs;
+            }
+            None => {
+                current = Some(Cue {
+                    start_secs: word.start_secs,
+                    end_secs: word.end_secs,
+                    text: word.word.clone(),
+                    speaker: None,
+                })
+            }
+        }
+        if word.ends_sentence {
+            cues.extend(current.take());
+        }
+    }
+    cues.extend(current);
+    cues
+}
+
+/// Cues for `text` spoken over `span`, attributed to `speaker`
+pub fn timed_cues(
+    text: &str,
+    span: Range<f64>,
+    speaker: Option<&str>,
+    segmenter: &dyn SentenceSegmenter,
+) -> Vec<Cue> {
+    let sentences = segmenter.segment(text);
+    let mut cues = cues_from_words(&estimate_word_timings(&sentences, span));
+    for cue in &mut cues {
+        cue.speaker = speaker.map(String::from);
+    }
+    cues
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (WebVTT)
+fn timestamp(secs: f64, format: SubtitleFormat) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::Vtt => '.',
+    };
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// Break `text` into lines of at most `LINE_CHARS` where it can
+fn wrap(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= LINE_CHARS => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Write `cues` as a subtitle file
+pub fn render(cues: &[Cue], format: SubtitleFormat) -> String {
+    let mut out = String::new();
+    if format == SubtitleFormat::Vtt {
+        out.push_str("WEBVTT\n\n");
+    }
+    for (i, cue) in cues.iter().enumerate() {
+        if format == SubtitleFormat::Srt {
+            let _ = writeln!(out, "{}", i + 1);
+        }
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            timestamp(cue.start_secs, format),
+            timestamp(cue.end_secs, format)
+        );
+        let text = match (format, &cue.speaker) {
+            (SubtitleFormat::Srt, None) => wrap(&cue.text),
+            (SubtitleFormat::Srt, Some(speaker)) => wrap(&format!("[{}] {}", speaker, cue.text)),
+            (SubtitleFormat::Vtt, None) => escape_vtt(&wrap(&cue.text)),
+            (SubtitleFormat::Vtt, Some(speaker)) => format!(
+                "<v {}>{}",
+                escape_vtt(speaker),
+                escape_vtt(&wrap(&cue.text))
+            ),
+        };
+        let _ = writeln!(out, "{}\n", text);
+    }
+    out
+}
+
+/// WebVTT cue text treats `&`, `<` and `>` as markup
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::RuleSegmenter;
+
+    #[test]
+    fn test_word_timings() {
+        let words = estimate_word_timings(&["Hi there.", "Bye"], 1.0..3.0);
+        // 2 + 6 + 3 characters, a sentence pause and a word gap: 2 / 18 s each
+        let secs: Vec<(f64, f64)> = words.iter().map(|w| (w.start_secs, w.end_secs)).collect();
+        let at = |chars: f64| 1.0 + chars * 2.0 / 18.0;
+        let expected = [(at(0.0), at(2.0)), (at(3.0), at(9.0)), (at(15.0), 3.0)];
+        for ((start, end), (want_start, want_end)) in secs.into_iter().zip(expected) {
+            assert!((start - want_start).abs() < 1e-9 && (end - want_end).abs() < 1e-9);
+        }
+        assert!(estimate_word_timings(&["  "], 0.0..1.0).is_empty());
+    }
+
+    #[test]
+    fn test_render_srt_and_vtt() {
+        let english = RuleSegmenter::for_language("English");
+        let mut cues = timed_cues("Hello world. How are you?", 0.0..2.5, None, &english);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text, "Hello world.");
+        assert!((cues[1].end_secs - 2.5).abs() < 1e-9);
+        cues[0].end_secs = 1.0;
+        cues[1].start_secs = 1.25;
+        cues[1].end_secs = 2.5;
+
+        assert_eq!(
+            render(&cues, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,000\nHello world.\n\n\
+             2\n00:00:01,250 --> 00:00:02,500\nHow are you?\n\n"
+        );
+        cues[1].speaker = Some("speaker_1".into());
+        cues[1].text = "A < B".into();
+        assert_eq!(
+            render(&cues, SubtitleFormat::Vtt),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\nHello world.\n\n\
+             00:00:01.250 --> 00:00:02.500\n<v speaker_1>A &lt; B\n\n"
+        );
+
+        // Long cues are split and wrapped onto two lines
+        let text = "word ".repeat(40);
+        let cues = timed_cues(&text, 0.0..60.0, None, &english);
+        assert!(cues
+            .iter()
+            .all(|cue| cue.end_secs - cue.start_secs <= MAX_CUE_SECS));
+        assert!(wrap(&cues[0].text).lines().all(|l| l.len() <= LINE_CHARS));
+        assert_eq!(timestamp(3723.5, SubtitleFormat::Srt), "01:02:03,500");
+    }
+}
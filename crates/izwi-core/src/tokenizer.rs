@@ -6,6 +6,7 @@ use tokenizers::Tokenizer as HfTokenizer;
 use tracing::{debug, info};
 
 use crate::error::{Error, Result};
+use crate::language::AUTO_LANGUAGE;
 
 #[derive(Debug, Clone, Default)]
 pub struct SpecialTokens {
@@ -93,12 +94,32 @@ impl Tokenizer {
         self.inner.get_vocab_size(true)
     }
 
+    /// Text of every token by ID, for masking tokens under an output constraint
+    pub fn token_bytes(&self) -> Vec<Vec<u8>> {
+        (0..self.vocab_size() as u32)
+            .map(|id| {
+                self.inner
+                    .decode(&[id], false)
+                    .map(String::into_bytes)
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
     pub fn special_tokens(&self) -> &SpecialTokens {
         &self.special_tokens
     }
 
-    pub fn format_tts_prompt(&self, text: &str, speaker: Option<&str>) -> String {
+    pub fn format_tts_prompt(
+        &self,
+        text: &str,
+        speaker: Option<&str>,
+        language: Option<&str>,
+    ) -> String {
         let speaker_tag = speaker.unwrap_or("default");
-        format!("[speaker:{}] {}", speaker_tag, text)
+        match language.filter(|l| *l != AUTO_LANGUAGE) {
+            Some(language) => format!("[speaker:{}] [language:{}] {}", speaker_tag, language, text),
+            None => format!("[speaker:{}] {}", speaker_tag, text),
+        }
     }
 }
@@ -0,0 +1,407 @@
+//! Resumable chunked uploads of large audio inputs.
+//!
+//! Base64 in a JSON body caps how much audio a request can practically
+//! carry. An upload instead arrives as numbered parts, each a plain HTTP
+//! body that can be retried or re-sent on its own. Completing the upload
+//! joins the parts into one file on disk, which transcription reads
+//! directly. Uploads belong to the API key that created them and are
+//! deleted `ttl` after creation.
+
+use bytes::Bytes;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::UploadConfig;
+use crate::engine::clock::{self, SharedClock};
+use crate::error::{Error, ErrorCode, Result};
+
+/// Highest part number
+pub const MAX_PARTS: u32 = 10_000;
+
+/// Name of the assembled file in an upload's directory
+const ASSEMBLED_FILE: &str = "audio";
+
+/// Directory of one upload, deleted with the last handle to it
+#[derive(Debug)]
+struct UploadDir(PathBuf);
+
+impl UploadDir {
+    fn part(&self, part: u32) -> PathBuf {
+        self.0.join(format!("part-{:05}", part))
+    }
+}
+
+impl Drop for UploadDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove upload {:?}: {}", self.0, e);
+            }
+        }
+    }
+}
+
+/// An assembled upload. Its file stays on disk while a handle is held, even
+/// if the upload expires or is deleted meanwhile.
+#[derive(Debug)]
+pub struct UploadedFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    _dir: Arc<UploadDir>,
+}
+
+#[derive(Debug)]
+enum UploadState {
+    /// Accepting parts
+    Open,
+    /// Parts are being joined
+    Completing,
+    Completed(Arc<UploadedFile>),
+}
+
+#[derive(Debug)]
+struct Upload {
+    owner: Option<String>,
+    created_at: Instant,
+    dir: Arc<UploadDir>,
+    /// Size of each received part
+    parts: BTreeMap<u32, u64>,
+    state: UploadState,
+}
+
+/// A received part
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UploadPart {
+    pub part: u32,
+    pub bytes: u64,
+}
+
+/// Progress of an upload, for resuming it
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadStatus {
+    pub upload_id: String,
+    /// Parts received so far, in order
+    pub parts: Vec<UploadPart>,
+    pub bytes: u64,
+    pub completed: bool,
+    /// Largest size the upload may reach
+    pub max_bytes: u64,
+    pub expires_in_secs: u64,
+}
+
+/// Uploads in progress or waiting to be transcribed.
+///
+/// Expired uploads are purged lazily on access.
+pub struct UploadStore {
+    dir: PathBuf,
+    max_bytes: u64,
+    ttl: Duration,
+    clock: SharedClock,
+    uploads: Mutex<HashMap<String, Upload>>,
+}
+
+impl UploadStore {
+    /// Keep uploads under `dir`, removing any left by a previous run
+    pub fn new(dir: PathBuf, max_bytes: u64, ttl: Duration) -> Result<Self> {
+        // The ASR daemon opens uploads by path from its own working directory
+        let dir = std::path::absolute(dir)?;
+        std::fs::create_dir_all(&dir)?;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            }
+        }
+        Ok(Self {
+            dir,
+            max_bytes,
+            ttl,
+            clock: clock::system_clock(),
+            uploads: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn from_config(config: &UploadConfig) -> Result<Self> {
+        Self::new(
+            config.dir.clone(),
+            config.max_bytes,
+            Duration::from_secs(config.ttl_secs),
+        )
+    }
+
+    /// Use `clock` for creation times and expiry.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn uploads(&self) -> std::sync::MutexGuard<'_, HashMap<String, Upload>> {
+        let mut uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        let now = self.clock.now();
+        let ttl = self.ttl;
+        uploads.retain(|_, upload| now.saturating_duration_since(upload.created_at) < ttl);
+        uploads
+    }
+
+    /// Upload `id` if it belongs to `owner`; others' uploads aren't found
+    fn owned<'a>(
+        uploads: &'a mut HashMap<String, Upload>,
+        id: &str,
+        owner: Option<&str>,
+    ) -> Result<&'a mut Upload> {
+        uploads
+            .get_mut(id)
+            .filter(|upload| upload.owner.as_deref() == owner)
+            .ok_or_else(|| Error::UploadNotFound(id.to_string()))
+    }
+
+    fn status(&self, id: &str, upload: &Upload) -> UploadStatus {
+        let (bytes, completed) = match &upload.state {
+            UploadState::Completed(file) => (file.bytes, true),
+            _ => (upload.parts.values().sum(), false),
+        };
+        let age = self.clock.elapsed_since(upload.created_at);
+        UploadStatus {
+            upload_id: id.to_string(),
+            parts: upload
+                .parts
+                .iter()
+                .map(|(&part, &bytes)| UploadPart { part, bytes })
+                .collect(),
+            bytes,
+            completed,
+            max_bytes: self.max_bytes,
+            expires_in_secs: self.ttl.saturating_sub(age).as_secs(),
+        }
+    }
+
+    /// Start an upload owned by `owner` (an API key name)
+    pub fn create(&self, owner: Option<&str>) -> Result<UploadStatus> {
+        let id = Uuid::new_v4().to_string();
+        let dir = self.dir.join(&id);
+        std::fs::create_dir_all(&dir)?;
+        let upload = Upload {
+            owner: owner.map(String::from),
+            created_at: self.clock.now(),
+            dir: Arc::new(UploadDir(dir)),
+            parts: BTreeMap::new(),
+            state: UploadState::Open,
+        };
+        let status = self.status(&id, &upload);
+        self.uploads().insert(id, upload);
+        Ok(status)
+    }
+
+    /// Progress of an upload
+    pub fn get(&self, id: &str, owner: Option<&str>) -> Result<UploadStatus> {
+        let mut uploads = self.uploads();
+        let upload = Self::owned(&mut uploads, id, owner)?;
+        Ok(self.status(id, upload))
+    }
+
+    /// Store part `part` (numbered from 1), replacing an earlier copy
+    pub async fn put_part(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        part: u32,
+        data: Bytes,
+    ) -> Result<UploadPart> {
+        if !(1..=MAX_PARTS).contains(&part) {
+            return Err(Error::InvalidInput(format!(
+                "Part numbers run from 1 to {}",
+                MAX_PARTS
+            )));
+        }
+        if data.is_empty() {
+            return Err(Error::InvalidInput(format!("Part {} is empty", part)));
+        }
+        let bytes = data.len() as u64;
+        let dir = {
+            let mut uploads = self.uploads();
+            let upload = Self::owned(&mut uploads, id, owner)?;
+            if !matches!(upload.state, UploadState::Open) {
+                return Err(Error::InvalidInput(format!(
+                    "Upload {} is already completed",
+                    id
+                )));
+            }
+            let others: u64 = upload
+                .parts
+                .iter()
+                .filter(|(&n, _)| n != part)
+                .map(|(_, &size)| size)
+                .sum();
+            if others + bytes > self.max_bytes {
+                return Err(Error::InputRejected {
+                    code: ErrorCode::AudioTooLarge,
+                    message: format!(
+                        "Upload {} would exceed the limit of {} bytes",
+                        id, self.max_bytes
+                    ),
+                });
+            }
+            // Counted now so concurrent parts can't overshoot the limit
+            upload.parts.insert(part, bytes);
+            upload.dir.clone()
+        };
+
+        let path = dir.part(part);
+        let staging = path.with_extension("partial");
+        let written = async {
+            tokio::fs::write(&staging, &data).await?;
+            tokio::fs::rename(&staging, &path).await
+        }
+        .await;
+        if let Err(e) = written {
+            if let Ok(upload) = Self::owned(&mut self.uploads(), id, owner) {
+                upload.parts.remove(&part);
+            }
+            return Err(e.into());
+        }
+        Ok(UploadPart { part, bytes })
+    }
+
+    /// Join the parts, which must run from 1 without gaps, into one file.
+    /// Completing a completed upload returns its status again.
+    pub async fn complete(&self, id: &str, owner: Option<&str>) -> Result<UploadStatus> {
+        let (dir, parts) = {
+            let mut uploads = self.uploads();
+            let upload = Self::owned(&mut uploads, id, owner)?;
+            match upload.state {
+                UploadState::Open => {}
+                UploadState::Completing => {
+                    return Err(Error::InvalidInput(format!(
+                        "Upload {} is already being completed",
+                        id
+                    )))
+                }
+                UploadState::Completed(_) => return Ok(self.status(id, upload)),
+            }
+            if upload.parts.is_empty() {
+                return Err(Error::InvalidInput(format!("Upload {} has no parts", id)));
+            }
+            if let Some(missing) = (1..).zip(upload.parts.keys()).find(|(n, &p)| *n != p) {
+                return Err(Error::InvalidInput(format!(
+                    "Upload {} is missing part {}",
+                    id, missing.0
+                )));
+            }
+            upload.state = UploadState::Completing;
+            (
+                upload.dir.clone(),
+                upload.parts.keys().copied().collect::<Vec<_>>(),
+            )
+        };
+
+        let path = dir.0.join(ASSEMBLED_FILE);
+        let joined = async {
+            let mut out = tokio::fs::File::create(&path).await?;
+            let mut bytes = 0;
+            for &part in &parts {
+                let mut input = tokio::fs::File::open(dir.part(part)).await?;
+                bytes += tokio::io::copy(&mut input, &mut out).await?;
+            }
+            out.flush().await?;
+            for &part in &parts {
+                tokio::fs::remove_file(dir.part(part)).await?;
+            }
+            Ok::<_, std::io::Error>(bytes)
+        }
+        .await;
+
+        let mut uploads = self.uploads();
+        let upload = Self::owned(&mut uploads, id, owner)?;
+        match joined {
+            Ok(bytes) => {
+                upload.state = UploadState::Completed(Arc::new(UploadedFile {
+                    path,
+                    bytes,
+                    _dir: dir,
+                }));
+                Ok(self.status(id, upload))
+            }
+            Err(e) => {
+                upload.state = UploadState::Open;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The assembled file of a completed upload
+    pub fn file(&self, id: &str, owner: Option<&str>) -> Result<Arc<UploadedFile>> {
+        let mut uploads = self.uploads();
+        match &Self::owned(&mut uploads, id, owner)?.state {
+            UploadState::Completed(file) => Ok(file.clone()),
+            _ => Err(Error::InvalidInput(format!(
+                "Upload {} has not been completed",
+                id
+            ))),
+        }
+    }
+
+    /// Delete an upload and its parts
+    pub fn remove(&self, id: &str, owner: Option<&str>) -> Result<()> {
+        let mut uploads = self.uploads();
+        Self::owned(&mut uploads, id, owner)?;
+        uploads.remove(id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MockClock;
+
+    #[tokio::test]
+    async fn test_chunked_upload() {
+        let dir = std::env::temp_dir().join(format!("izwi-uploads-{}", Uuid::new_v4()));
+        let clock = Arc::new(MockClock::new());
+        let store = UploadStore::new(dir.clone(), 10, Duration::from_secs(60))
+            .unwrap()
+            .with_clock(clock.clone());
+        let id = store.create(Some("alice")).unwrap().upload_id;
+        let put = |part, data: &'static [u8]| store.put_part(&id, Some("alice"), part, data.into());
+
+        // Parts arrive in any order and may be re-sent
+        put(2, b"World").await.unwrap();
+        put(1, b"Hi").await.unwrap();
+        put(1, b"Hello").await.unwrap();
+        assert!(store.complete(&id, Some("alice")).await.is_ok());
+        let file = store.file(&id, Some("alice")).unwrap();
+        assert_eq!(std::fs::read(&file.path).unwrap(), b"HelloWorld");
+        assert_eq!(store.get(&id, Some("alice")).unwrap().bytes, 10);
+        assert!(put(3, b"!").await.is_err());
+
+        // Uploads are private to their owner
+        assert!(matches!(
+            store.file(&id, Some("bob")),
+            Err(Error::UploadNotFound(_))
+        ));
+
+        // Gaps and oversized uploads are refused
+        let other = store.create(None).unwrap().upload_id;
+        store.put_part(&other, None, 2, "x".into()).await.unwrap();
+        assert!(store.complete(&other, None).await.is_err());
+        assert!(store
+            .put_part(&other, None, 1, "0123456789".into())
+            .await
+            .is_err());
+
+        // Expired uploads are deleted, once nothing reads their file
+        clock.advance(Duration::from_secs(61));
+        assert!(store.get(&other, None).is_err());
+        assert!(file.path.exists());
+        let path = file.path.clone();
+        drop(file);
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,257 @@
+//! Per-request usage accounting
+//!
+//! Every finished request is recorded with the characters it sent, its
+//! prompt and generated audio tokens, the seconds of audio involved and its
+//! wall time. The ledger keeps records for a retention window and sums them
+//! per API key and endpoint for usage reports.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::UsageConfig;
+
+/// Key under which requests without an API key are reported
+pub const ANONYMOUS_KEY: &str = "anonymous";
+
+/// Resources used by one request, or summed over many
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Characters of input text
+    pub input_chars: usize,
+    /// Tokens of the prompt
+    pub prompt_tokens: usize,
+    /// Audio tokens generated
+    pub audio_tokens: usize,
+    /// Seconds of audio generated or transcribed
+    pub audio_secs: f64,
+    /// Time from request to response
+    pub wall_ms: u64,
+}
+
+impl Usage {
+    /// Usage of a request with the given input text
+    pub fn for_text(text: &str) -> Self {
+        Self {
+            input_chars: text.chars().count(),
+            prompt_tokens: estimate_prompt_tokens(text),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_audio(mut self, audio_tokens: usize, audio_secs: f64) -> Self {
+        self.audio_tokens = audio_tokens;
+        self.audio_secs = audio_secs;
+        self
+    }
+
+    pub fn with_wall_time(mut self, elapsed: Duration) -> Self {
+        self.wall_ms = elapsed.as_millis() as u64;
+        self
+    }
+
+    /// Add another request's usage to this one
+    pub fn add(&mut self, other: &Usage) {
+        self.input_chars += other.input_chars;
+        self.prompt_tokens += other.prompt_tokens;
+        self.audio_tokens += other.audio_tokens;
+        self.audio_secs += other.audio_secs;
+        self.wall_ms += other.wall_ms;
+    }
+}
+
+/// Prompt tokens of a text, estimated until the model's tokenizer is used
+pub fn estimate_prompt_tokens(text: &str) -> usize {
+    (text.len() / 4).max(1)
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Usage of one finished request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub request_id: String,
+    /// Name of the API key that made the request
+    pub api_key: Option<String>,
+    pub endpoint: String,
+    /// Unix seconds when the request finished
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// Usage summed over a number of requests
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub requests: u64,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+impl UsageSummary {
+    fn add(&mut self, usage: &Usage) {
+        self.requests += 1;
+        self.usage.add(usage);
+    }
+}
+
+/// Usage within a time window, in total and broken down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Start of the window, inclusive, in Unix seconds
+    pub since: u64,
+    /// End of the window, exclusive, in Unix seconds
+    pub until: u64,
+    pub total: UsageSummary,
+    pub by_api_key: BTreeMap<String, UsageSummary>,
+    pub by_endpoint: BTreeMap<String, UsageSummary>,
+}
+
+/// Recent usage records, oldest first.
+#[derive(Debug)]
+pub struct UsageLedger {
+    retention_secs: u64,
+    max_records: usize,
+    records: Mutex<VecDeque<UsageRecord>>,
+}
+
+impl UsageLedger {
+    pub fn new(config: &UsageConfig) -> Self {
+        Self {
+            retention_secs: config.retention_secs,
+            max_records: config.max_records,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a finished request
+    pub fn record(&self, request_id: &str, api_key: Option<&str>, endpoint: &str, usage: Usage) {
+        self.insert(UsageRecord {
+            request_id: request_id.to_string(),
+            api_key: api_key.map(str::to_string),
+            endpoint: endpoint.to_string(),
+            timestamp: unix_now(),
+            usage,
+        });
+    }
+
+    fn insert(&self, record: UsageRecord) {
+        let mut records = self.records.lock().unwrap();
+        let now = record.timestamp;
+        records.push_back(record);
+        if self.max_records > 0 {
+            while records.len() > self.max_records {
+                records.pop_front();
+            }
+        }
+        if self.retention_secs > 0 {
+            let cutoff = now.saturating_sub(self.retention_secs);
+            while records.front().is_some_and(|r| r.timestamp < cutoff) {
+                records.pop_front();
+            }
+        }
+    }
+
+    /// Records held
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Usage of requests finished in `[since, until)`, optionally of one key
+    pub fn report(&self, since: u64, until: u64, api_key: Option<&str>) -> UsageReport {
+        let mut report = UsageReport {
+            since,
+            until,
+            total: UsageSummary::default(),
+            by_api_key: BTreeMap::new(),
+            by_endpoint: BTreeMap::new(),
+        };
+        let records = self.records.lock().unwrap();
+        for record in records
+            .iter()
+            .filter(|r| r.timestamp >= since && r.timestamp < until)
+        {
+            let key = record.api_key.as_deref().unwrap_or(ANONYMOUS_KEY);
+            if api_key.is_some_and(|wanted| wanted != key) {
+                continue;
+            }
+            report.total.add(&record.usage);
+            report
+                .by_api_key
+                .entry(key.to_string())
+                .or_default()
+                .add(&record.usage);
+            report
+                .by_endpoint
+                .entry(record.endpoint.clone())
+                .or_default()
+                .add(&record.usage);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(key: Option<&str>, endpoint: &str, timestamp: u64, chars: usize) -> UsageRecord {
+        UsageRecord {
+            request_id: format!("req-{}", timestamp),
+            api_key: key.map(str::to_string),
+            endpoint: endpoint.to_string(),
+            timestamp,
+            usage: Usage {
+                input_chars: chars,
+                audio_secs: 1.5,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_report_aggregates_by_key_and_window() {
+        let ledger = UsageLedger::new(&UsageConfig::default());
+        ledger.insert(record(Some("alice"), "tts", 100, 10));
+        ledger.insert(record(Some("alice"), "asr", 110, 0));
+        ledger.insert(record(Some("bob"), "tts", 120, 5));
+        ledger.insert(record(None, "tts", 200, 7));
+
+        let report = ledger.report(100, 150, None);
+        assert_eq!(report.total.requests, 3);
+        assert_eq!(report.total.usage.input_chars, 15);
+        assert_eq!(report.total.usage.audio_secs, 4.5);
+        assert_eq!(report.by_api_key["alice"].requests, 2);
+        assert_eq!(report.by_endpoint["tts"].usage.input_chars, 15);
+        assert!(!report.by_api_key.contains_key(ANONYMOUS_KEY));
+
+        let report = ledger.report(0, u64::MAX, Some(ANONYMOUS_KEY));
+        assert_eq!(report.total.requests, 1);
+        assert_eq!(report.total.usage.input_chars, 7);
+    }
+
+    #[test]
+    fn test_ledger_prunes_old_records() {
+        let ledger = UsageLedger::new(&UsageConfig {
+            retention_secs: 60,
+            max_records: 3,
+        });
+        for timestamp in [10, 20, 30, 40] {
+            ledger.insert(record(None, "tts", timestamp, 1));
+        }
+        assert_eq!(ledger.len(), 3);
+        ledger.insert(record(None, "tts", 95, 1));
+        assert_eq!(ledger.report(0, u64::MAX, None).total.requests, 2);
+    }
+}
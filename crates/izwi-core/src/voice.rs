@@ -0,0 +1,312 @@
+//! Voice catalog, alias resolution and saved cloned voices
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+use crate::config::VoiceAlias;
+use crate::error::{Error, Result};
+use crate::tenant::{SealedData, TenantKeyring};
+
+/// Maximum alias hops followed before assuming a misconfiguration
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// Built-in speaker presets shipped with the CustomVoice models
+pub const BUILTIN_VOICES: &[&str] = &[
+    "Vivian", "Serena", "Ryan", "Aiden", "Dylan", "Eric", "Sohee", "Ono_anna", "Uncle_fu",
+];
+
+/// Outcome of resolving a requested voice name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedVoice {
+    /// Name as sent by the client
+    pub requested: String,
+    /// Canonical name passed to the model
+    pub name: String,
+    /// Deprecation warning to surface in response metadata
+    pub warning: Option<String>,
+}
+
+impl ResolvedVoice {
+    /// Whether the requested name was rewritten
+    pub fn was_aliased(&self) -> bool {
+        self.requested != self.name
+    }
+}
+
+/// Registry of known voices and their aliases.
+///
+/// Lookups are case-insensitive. Names that are neither known voices nor
+/// aliases pass through unchanged so model-specific voices keep working.
+#[derive(Debug, Clone)]
+pub struct VoiceRegistry {
+    voices: HashMap<String, String>,
+    aliases: HashMap<String, (String, VoiceAlias)>,
+}
+
+impl VoiceRegistry {
+    /// Create a registry containing the built-in voices
+    pub fn new() -> Self {
+        let voices = BUILTIN_VOICES
+            .iter()
+            .map(|v| (v.to_lowercase(), v.to_string()))
+            .collect();
+        Self {
+            voices,
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Create a registry with the given alias table, validating it
+    pub fn with_aliases(aliases: &HashMap<String, VoiceAlias>) -> Result<Self> {
+        let mut registry = Self::new();
+        for (name, alias) in aliases {
+            registry.add_alias(name, alias.clone())?;
+        }
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    /// Register an additional voice
+    pub fn add_voice(&mut self, name: impl Into<String>) {
+        let name = name.into();
+        self.voices.insert(name.to_lowercase(), name);
+    }
+
+    /// Register an alias from `name` to `alias.target`
+    pub fn add_alias(&mut self, name: &str, alias: VoiceAlias) -> Result<()> {
+        if alias.target.trim().is_empty() {
+            return Err(Error::ConfigError(format!(
+                "Voice alias '{}' has an empty target",
+                name
+            )));
+        }
+        if self.voices.contains_key(&name.to_lowercase()) {
+            return Err(Error::ConfigError(format!(
+                "Voice alias '{}' shadows an existing voice",
+                name
+            )));
+        }
+        self.aliases
+            .insert(name.to_lowercase(), (name.to_string(), alias));
+        Ok(())
+    }
+
+    /// Check the alias table for cycles
+    fn validate(&self) -> Result<()> {
+        for (name, _) in self.aliases.values() {
+            self.resolve(name)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve a requested voice to its canonical name
+    pub fn resolve(&self, requested: &str) -> Result<ResolvedVoice> {
+        let mut current = requested.to_string();
+        let mut warning = None;
+
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let key = current.to_lowercase();
+            if let Some(name) = self.voices.get(&key) {
+                return Ok(ResolvedVoice {
+                    requested: requested.to_string(),
+                    name: name.clone(),
+                    warning,
+                });
+            }
+
+            match self.aliases.get(&key) {
+                Some((alias_name, alias)) => {
+                    if alias.deprecated && warning.is_none() {
+                        let message = alias.message.clone().unwrap_or_else(|| {
+                            format!(
+                                "Voice '{}' is deprecated; use '{}' instead",
+                                alias_name, alias.target
+                            )
+                        });
+                        warn!("{}", message);
+                        warning = Some(message);
+                    }
+                    current = alias.target.clone();
+                }
+                None => {
+                    return Ok(ResolvedVoice {
+                        requested: requested.to_string(),
+                        name: current,
+                        warning,
+                    });
+                }
+            }
+        }
+
+        Err(Error::ConfigError(format!(
+            "Voice alias chain for '{}' is too deep or cyclic",
+            requested
+        )))
+    }
+
+    /// Canonical names of all known voices
+    pub fn voices(&self) -> Vec<&str> {
+        let mut voices: Vec<&str> = self.voices.values().map(String::as_str).collect();
+        voices.sort_unstable();
+        voices
+    }
+}
+
+impl Default for VoiceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reference audio and transcript for a cloned voice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedVoice {
+    /// Base64-encoded reference audio
+    pub reference_audio: String,
+    /// Transcript of the reference audio
+    pub reference_text: String,
+}
+
+/// Cloned voices saved by clients, scoped to a tenant.
+///
+/// Entries are encrypted with the owning tenant's key.
+#[derive(Debug)]
+pub struct VoiceStore {
+    keyring: Arc<TenantKeyring>,
+    voices: RwLock<HashMap<(Option<String>, String), SealedData>>,
+}
+
+impl VoiceStore {
+    /// Create an empty store sealing entries with `keyring`
+    pub fn new(keyring: Arc<TenantKeyring>) -> Self {
+        Self {
+            keyring,
+            voices: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn key(tenant: Option<&str>, name: &str) -> (Option<String>, String) {
+        (tenant.map(String::from), name.to_lowercase())
+    }
+
+    /// Save or replace a voice
+    pub fn save(&self, tenant: Option<&str>, name: &str, voice: &SavedVoice) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(Error::InvalidInput("Voice name cannot be empty".into()));
+        }
+        let plaintext = serde_json::to_vec(voice)?;
+        let sealed = self.keyring.seal(tenant, &plaintext)?;
+        self.voices
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(Self::key(tenant, name), sealed);
+        Ok(())
+    }
+
+    /// Look up a saved voice
+    pub fn get(&self, tenant: Option<&str>, name: &str) -> Result<Option<SavedVoice>> {
+        let voices = self.voices.read().unwrap_or_else(|e| e.into_inner());
+        let Some(sealed) = voices.get(&Self::key(tenant, name)) else {
+            return Ok(None);
+        };
+        let plaintext = self.keyring.open(tenant, sealed)?;
+        Ok(Some(serde_json::from_slice(&plaintext)?))
+    }
+
+    /// Names of a tenant's saved voices
+    pub fn list(&self, tenant: Option<&str>) -> Vec<String> {
+        let voices = self.voices.read().unwrap_or_else(|e| e.into_inner());
+        let mut names: Vec<String> = voices
+            .keys()
+            .filter(|(t, _)| t.as_deref() == tenant)
+            .map(|(_, name)| name.clone())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Delete a saved voice, returning whether it existed
+    pub fn delete(&self, tenant: Option<&str>, name: &str) -> bool {
+        self.voices
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&Self::key(tenant, name))
+            .is_some()
+    }
+
+    /// Drop every voice of a tenant, returning how many were removed
+    pub fn purge_tenant(&self, tenant: &str) -> usize {
+        let mut voices = self.voices.write().unwrap_or_else(|e| e.into_inner());
+        let before = voices.len();
+        voices.retain(|(t, _), _| t.as_deref() != Some(tenant));
+        before - voices.len()
+    }
+
+    /// Re-encrypt a tenant's voices under its active key
+    pub fn reseal_tenant(&self, tenant: &str) -> Result<usize> {
+        let mut voices = self.voices.write().unwrap_or_else(|e| e.into_inner());
+        let mut count = 0;
+        for ((t, _), sealed) in voices.iter_mut() {
+            if t.as_deref() == Some(tenant) {
+                *sealed = self.keyring.reseal(tenant, sealed)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(target: &str, deprecated: bool) -> VoiceAlias {
+        VoiceAlias {
+            target: target.to_string(),
+            deprecated,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_builtin_case_insensitive() {
+        let registry = VoiceRegistry::new();
+        let voice = registry.resolve("vivian").unwrap();
+        assert_eq!(voice.name, "Vivian");
+        assert!(voice.warning.is_none());
+
+        // Unknown voices pass through
+        assert_eq!(registry.resolve("Custom").unwrap().name, "Custom");
+    }
+
+    #[test]
+    fn test_alias_chain_with_deprecation() {
+        let mut aliases = HashMap::new();
+        aliases.insert("Anna".to_string(), alias("Ono_anna", false));
+        aliases.insert("OldAnna".to_string(), alias("anna", true));
+        let registry = VoiceRegistry::with_aliases(&aliases).unwrap();
+
+        let voice = registry.resolve("Anna").unwrap();
+        assert_eq!(voice.name, "Ono_anna");
+        assert!(voice.was_aliased());
+        assert!(voice.warning.is_none());
+
+        let voice = registry.resolve("oldanna").unwrap();
+        assert_eq!(voice.name, "Ono_anna");
+        assert!(voice.warning.unwrap().contains("deprecated"));
+    }
+
+    #[test]
+    fn test_invalid_alias_tables() {
+        let mut cyclic = HashMap::new();
+        cyclic.insert("A".to_string(), alias("B", false));
+        cyclic.insert("B".to_string(), alias("A", false));
+        assert!(VoiceRegistry::with_aliases(&cyclic).is_err());
+
+        let mut shadowing = HashMap::new();
+        shadowing.insert("Ryan".to_string(), alias("Eric", false));
+        assert!(VoiceRegistry::with_aliases(&shadowing).is_err());
+    }
+}
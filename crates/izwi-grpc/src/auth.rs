@@ -0,0 +1,172 @@
+//! Caller authentication for the gRPC API
+//!
+//! The server checks the bearer token of every call through an
+//! [`Authenticator`] and attaches the resulting [`Caller`] to the request.
+//! Handlers then enforce what the caller may do: take a stream slot for the
+//! streaming RPCs, stay within its priority and reach the admin service.
+
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use izwi_core::engine::Priority;
+
+/// Checks the credentials presented with a call
+pub trait Authenticator: Send + Sync + 'static {
+    /// Admit a call carrying `token`, the bearer token of its
+    /// `authorization` metadata, and apply the key's rate limit
+    #[allow(clippy::result_large_err)] // tonic handlers return `Status` unboxed
+    fn authenticate(&self, token: Option<&str>) -> Result<Caller, Status>;
+}
+
+/// What an authenticated call may do
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// Name of the key that authenticated the call
+    pub name: String,
+    /// Highest scheduling priority the caller may request
+    pub max_priority: Priority,
+    /// Whether the caller may use the admin service
+    pub admin: bool,
+    /// Concurrent-stream slots of the caller (`None` = unlimited)
+    pub streams: Option<Arc<Semaphore>>,
+}
+
+/// Interceptor attaching the [`Caller`] of each call. Without an
+/// authenticator every call is let through with no caller attached.
+#[derive(Clone, Default)]
+pub struct AuthInterceptor {
+    authenticator: Option<Arc<dyn Authenticator>>,
+}
+
+impl AuthInterceptor {
+    pub fn new(authenticator: Option<Arc<dyn Authenticator>>) -> Self {
+        Self { authenticator }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(authenticator) = &self.authenticator else {
+            return Ok(request);
+        };
+        let caller = authenticator.authenticate(bearer_token(&request))?;
+        request.extensions_mut().insert(caller);
+        Ok(request)
+    }
+}
+
+/// Bearer token from the `authorization` metadata
+fn bearer_token<T>(request: &Request<T>) -> Option<&str> {
+    let value = request.metadata().get("authorization")?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Take one of the caller's stream slots, held until the permit is dropped
+#[allow(clippy::result_large_err)]
+pub(crate) fn take_stream<T>(request: &Request<T>) -> Result<Option<OwnedSemaphorePermit>, Status> {
+    let Some(streams) = request
+        .extensions()
+        .get::<Caller>()
+        .and_then(|caller| caller.streams.clone())
+    else {
+        return Ok(None);
+    };
+    streams
+        .try_acquire_owned()
+        .map(Some)
+        .map_err(|_| Status::resource_exhausted("Concurrent stream limit reached"))
+}
+
+/// Refuse a priority above the caller's maximum
+#[allow(clippy::result_large_err)]
+pub(crate) fn check_priority(caller: Option<&Caller>, priority: Priority) -> Result<(), Status> {
+    match caller {
+        Some(caller) if priority > caller.max_priority => Err(Status::permission_denied(format!(
+            "Priority '{}' exceeds this API key's maximum of '{}'",
+            priority.as_str(),
+            caller.max_priority.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Refuse callers without the admin scope
+#[allow(clippy::result_large_err)]
+pub(crate) fn require_admin<T>(request: &Request<T>) -> Result<(), Status> {
+    match request.extensions().get::<Caller>() {
+        Some(caller) if !caller.admin => Err(Status::permission_denied(
+            "This API key may not use the admin service",
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct OneKey;
+
+    impl Authenticator for OneKey {
+        fn authenticate(&self, token: Option<&str>) -> Result<Caller, Status> {
+            match token {
+                Some("secret") => Ok(Caller {
+                    name: "client".into(),
+                    max_priority: Priority::Normal,
+                    admin: false,
+                    streams: Some(Arc::new(Semaphore::new(1))),
+                }),
+                _ => Err(Status::unauthenticated("Missing or invalid API key")),
+            }
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn call(interceptor: &mut AuthInterceptor, token: Option<&str>) -> Result<Request<()>, Status> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request.metadata_mut().insert(
+                "authorization",
+                format!("Bearer {}", token).parse().unwrap(),
+            );
+        }
+        interceptor.call(request)
+    }
+
+    #[test]
+    fn test_interceptor_enforces_key() {
+        let mut open = AuthInterceptor::new(None);
+        let request = call(&mut open, None).unwrap();
+        assert!(take_stream(&request).unwrap().is_none());
+        assert!(require_admin(&request).is_ok());
+
+        let mut interceptor = AuthInterceptor::new(Some(Arc::new(OneKey)));
+        for token in [None, Some("wrong")] {
+            let status = call(&mut interceptor, token).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        }
+
+        let request = call(&mut interceptor, Some("secret")).unwrap();
+        let caller = request.extensions().get::<Caller>();
+        assert!(check_priority(caller, Priority::Normal).is_ok());
+        assert!(check_priority(caller, Priority::High).is_err());
+        assert_eq!(
+            require_admin(&request).unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+
+        // The slot is shared by every call of the key until released
+        let permit = take_stream(&request).unwrap();
+        assert!(permit.is_some());
+        assert!(take_stream(&request).is_err());
+        drop(permit);
+        assert!(take_stream(&request).unwrap().is_some());
+    }
+}
@@ -0,0 +1,231 @@
+//! Conversions between protobuf messages and engine types
+
+use base64::Engine as _;
+use tonic::Status;
+
+use crate::proto;
+use izwi_core::engine::Priority;
+use izwi_core::inference::{AudioChunk, GenerationConfig, GenerationRequest, GenerationResult};
+use izwi_core::model::{ModelInfo, ModelStatus};
+use izwi_core::{EngineMetrics, Error};
+
+/// Map an engine error onto a gRPC status
+pub fn status_from_error(err: Error) -> Status {
+    match &err {
+        Error::ModelNotFound(_) | Error::RequestNotFound(_) => Status::not_found(err.to_string()),
+        Error::InvalidInput(_)
+        | Error::ConfigError(_)
+        | Error::InvalidAudio(_)
+        | Error::InputRejected { .. } => Status::invalid_argument(err.to_string()),
+        Error::BufferOverflow(_) | Error::Overloaded { .. } | Error::KvCacheExhausted(_) => {
+            Status::resource_exhausted(err.to_string())
+        }
+        Error::ModelNotLoaded(_) | Error::DaemonUnavailable(_) => {
+            Status::unavailable(err.to_string())
+        }
+        Error::Timeout(_) => Status::deadline_exceeded(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+/// Build a TTS generation request from an `EngineRequest`
+#[allow(clippy::result_large_err)] // tonic handlers return `Status` unboxed
+pub fn generation_request(request: proto::EngineRequest) -> Result<GenerationRequest, Status> {
+    let text = request
+        .text
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| Status::invalid_argument("TTS request requires text"))?;
+
+    let mut gen_request = GenerationRequest::new(text);
+    if !request.id.is_empty() {
+        gen_request.id = request.id;
+    }
+
+    let params = request.params.unwrap_or_default();
+    let mut config = GenerationConfig::default();
+    if let Some(temperature) = params.temperature {
+        config.temperature = temperature;
+    }
+    if let Some(top_p) = params.top_p {
+        config.top_p = top_p;
+    }
+    if let Some(top_k) = params.top_k {
+        config.top_k = top_k as usize;
+    }
+    if let Some(penalty) = params.repetition_penalty {
+        config.repetition_penalty = penalty;
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        config.max_tokens = max_tokens as usize;
+    }
+    if let Some(speed) = params.speed {
+        config.speed = speed;
+    }
+    config.speaker = params.speaker.or(params.voice);
+    gen_request.config = config;
+
+    if !request.reference_audio.is_empty() {
+        gen_request.reference_audio =
+            Some(base64::engine::general_purpose::STANDARD.encode(&request.reference_audio));
+        gen_request.reference_text = request.reference_text;
+    }
+    gen_request.voice_description = request.voice_description;
+    gen_request.language = request.language;
+    gen_request.priority = priority_from_proto(request.priority);
+
+    Ok(gen_request)
+}
+
+/// Convert a completed generation into an `EngineOutput`
+pub fn generation_output(result: GenerationResult) -> proto::EngineOutput {
+    let duration_secs = result.samples.len() as f32 / result.sample_rate as f32;
+    proto::EngineOutput {
+        request_id: result.request_id,
+        sequence_id: 0,
+        audio: Some(proto::AudioOutput {
+            samples: result.samples,
+            sample_rate: result.sample_rate,
+            duration_secs,
+        }),
+        text: None,
+        num_tokens: result.total_tokens as u32,
+        generation_time_ms: result.total_time_ms,
+        is_finished: true,
+        finish_reason: proto::FinishReason::StopToken as i32,
+        token_stats: Some(proto::TokenStats {
+            generated_tokens: result.total_tokens as u32,
+            decode_time_ms: result.total_time_ms,
+            ..Default::default()
+        }),
+        voice: result.voice,
+        warnings: result.warnings,
+        language: result.language,
+    }
+}
+
+/// Convert a streamed chunk
+pub fn audio_chunk(chunk: AudioChunk, sample_rate: u32) -> proto::AudioChunk {
+    proto::AudioChunk {
+        request_id: chunk.request_id,
+        sequence: chunk.sequence as u64,
+        samples: chunk.samples.into(),
+        sample_rate,
+        is_final: chunk.is_final,
+    }
+}
+
+/// Map a protobuf priority onto the engine priority (unspecified = normal)
+pub fn priority_from_proto(value: i32) -> Priority {
+    match proto::Priority::try_from(value).unwrap_or(proto::Priority::Unspecified) {
+        proto::Priority::Low => Priority::Low,
+        proto::Priority::High => Priority::High,
+        proto::Priority::Critical => Priority::Critical,
+        proto::Priority::Normal | proto::Priority::Unspecified => Priority::Normal,
+    }
+}
+
+/// Map an engine priority onto the protobuf enum
+pub fn priority_to_proto(priority: Priority) -> proto::Priority {
+    match priority {
+        Priority::Low => proto::Priority::Low,
+        Priority::Normal => proto::Priority::Normal,
+        Priority::High => proto::Priority::High,
+        Priority::Critical => proto::Priority::Critical,
+    }
+}
+
+/// Convert engine metrics, adding queue depths
+pub fn engine_metrics(
+    metrics: EngineMetrics,
+    pending_requests: usize,
+    running_requests: usize,
+) -> proto::EngineMetrics {
+    proto::EngineMetrics {
+        total_steps: metrics.total_steps,
+        requests_processed: metrics.requests_processed,
+        tokens_generated: metrics.tokens_generated,
+        audio_seconds_generated: metrics.audio_seconds_generated,
+        avg_tokens_per_second: metrics.avg_tokens_per_second,
+        avg_rtf: metrics.avg_rtf,
+        kv_cache_memory_bytes: metrics.kv_cache_memory_bytes as u64,
+        kv_cache_blocks_allocated: metrics.kv_cache_blocks_allocated as u64,
+        kv_cache_blocks_free: metrics.kv_cache_blocks_free as u64,
+        output_buffer_bytes: metrics.output_buffer_bytes as u64,
+        output_buffer_peak_bytes: metrics.output_buffer_peak_bytes as u64,
+        pending_requests: pending_requests as u64,
+        running_requests: running_requests as u64,
+    }
+}
+
+/// Convert model information
+pub fn model_info(info: ModelInfo) -> proto::ModelInfo {
+    let status = match info.status {
+        ModelStatus::NotDownloaded => proto::ModelStatus::NotDownloaded,
+        ModelStatus::Downloading => proto::ModelStatus::Downloading,
+        ModelStatus::Downloaded => proto::ModelStatus::Downloaded,
+        ModelStatus::Loading => proto::ModelStatus::Loading,
+        ModelStatus::Ready => proto::ModelStatus::Ready,
+        ModelStatus::Error => proto::ModelStatus::Error,
+    };
+    proto::ModelInfo {
+        variant: info.variant.dir_name().to_string(),
+        status: status as i32,
+        local_path: info.local_path.map(|p| p.display().to_string()),
+        size_bytes: info.size_bytes,
+        download_progress: info.download_progress,
+        error_message: info.error_message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_request_applies_params() {
+        let request = proto::EngineRequest {
+            id: "req-1".to_string(),
+            text: Some("Hello".to_string()),
+            params: Some(proto::GenerationParams {
+                temperature: Some(0.3),
+                voice: Some("Vivian".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let gen_request = generation_request(request).unwrap();
+        assert_eq!(gen_request.id, "req-1");
+        assert_eq!(gen_request.config.temperature, 0.3);
+        assert_eq!(gen_request.config.top_p, GenerationConfig::default().top_p);
+        assert_eq!(gen_request.config.speaker.as_deref(), Some("Vivian"));
+        assert!(gen_request.reference_audio.is_none());
+    }
+
+    #[test]
+    fn test_generation_request_requires_text() {
+        let request = proto::EngineRequest {
+            text: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let status = generation_request(request).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_priority_round_trip() {
+        for priority in [
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Critical,
+        ] {
+            assert_eq!(
+                priority_from_proto(priority_to_proto(priority) as i32),
+                priority
+            );
+        }
+        assert_eq!(priority_from_proto(0), Priority::Normal);
+        assert_eq!(priority_from_proto(42), Priority::Normal);
+    }
+}
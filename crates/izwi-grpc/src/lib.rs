@@ -0,0 +1,44 @@
+//! gRPC API for the Izwi inference engine
+//!
+//! Exposes TTS, ASR, streaming TTS, and engine administration RPCs over
+//! tonic, alongside the HTTP API served by `izwi-server`.
+
+mod auth;
+mod convert;
+mod service;
+
+/// Generated protobuf types and service stubs
+pub mod proto {
+    tonic::include_proto!("izwi.v1");
+}
+
+pub use auth::{AuthInterceptor, Authenticator, Caller};
+pub use service::IzwiGrpcService;
+pub use tonic::Status;
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use proto::engine_admin_server::EngineAdminServer;
+use proto::izwi_server::IzwiServer;
+
+/// Serve the gRPC API until `shutdown` resolves. Calls are checked by
+/// `authenticator` when one is given.
+pub async fn serve(
+    addr: SocketAddr,
+    service: IzwiGrpcService,
+    authenticator: Option<Arc<dyn Authenticator>>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), tonic::transport::Error> {
+    tracing::info!("gRPC server listening on {}", addr);
+    let interceptor = AuthInterceptor::new(authenticator);
+    tonic::transport::Server::builder()
+        .add_service(IzwiServer::with_interceptor(
+            service.clone(),
+            interceptor.clone(),
+        ))
+        .add_service(EngineAdminServer::with_interceptor(service, interceptor))
+        .serve_with_shutdown(addr, shutdown)
+        .await
+}
@@ -0,0 +1,259 @@
+//! gRPC service implementations
+
+use base64::Engine as _;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, warn};
+
+use crate::auth::{self, Caller};
+use crate::convert::{self, status_from_error};
+use crate::proto;
+use crate::proto::engine_admin_server::EngineAdmin;
+use crate::proto::izwi_server::Izwi;
+use izwi_core::inference::GenerationRequest;
+use izwi_core::{Engine, InferenceEngine};
+
+type ChunkStream = ReceiverStream<Result<proto::AudioChunk, Status>>;
+
+/// Shared handle implementing the inference and admin services
+#[derive(Clone)]
+pub struct IzwiGrpcService {
+    engine: Arc<InferenceEngine>,
+    engine_core: Arc<Engine>,
+}
+
+impl IzwiGrpcService {
+    /// Create a service over the same engines used by the HTTP API
+    pub fn new(engine: Arc<InferenceEngine>, engine_core: Arc<Engine>) -> Self {
+        Self {
+            engine,
+            engine_core,
+        }
+    }
+}
+
+/// Run a streaming generation, forwarding chunks to `out`
+async fn stream_synthesis(
+    engine: Arc<InferenceEngine>,
+    request: GenerationRequest,
+    out: &mpsc::Sender<Result<proto::AudioChunk, Status>>,
+) {
+    let sample_rate = engine.sample_rate();
+    let (chunk_tx, mut chunk_rx) = mpsc::channel(32);
+
+    let generation = engine.generate_streaming(request, chunk_tx);
+    let forward = async {
+        while let Some(chunk) = chunk_rx.recv().await {
+            if out
+                .send(Ok(convert::audio_chunk(chunk, sample_rate)))
+                .await
+                .is_err()
+            {
+                debug!("gRPC client disconnected during streaming synthesis");
+                break;
+            }
+        }
+    };
+
+    let (result, ()) = tokio::join!(generation, forward);
+    if let Err(e) = result {
+        warn!("Streaming generation error: {}", e);
+        let _ = out.send(Err(status_from_error(e))).await;
+    }
+}
+
+#[tonic::async_trait]
+impl Izwi for IzwiGrpcService {
+    async fn synthesize(
+        &self,
+        request: Request<proto::EngineRequest>,
+    ) -> Result<Response<proto::EngineOutput>, Status> {
+        let caller = request.extensions().get::<Caller>().cloned();
+        let gen_request = convert::generation_request(request.into_inner())?;
+        auth::check_priority(caller.as_ref(), gen_request.priority)?;
+
+        let engine = &self.engine;
+        let result = engine
+            .generate(gen_request)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(convert::generation_output(result)))
+    }
+
+    type SynthesizeStreamStream = ChunkStream;
+
+    async fn synthesize_stream(
+        &self,
+        request: Request<proto::EngineRequest>,
+    ) -> Result<Response<Self::SynthesizeStreamStream>, Status> {
+        let caller = request.extensions().get::<Caller>().cloned();
+        let permit = auth::take_stream(&request)?;
+        let gen_request = convert::generation_request(request.into_inner())?;
+        auth::check_priority(caller.as_ref(), gen_request.priority)?;
+        let (tx, rx) = mpsc::channel(32);
+
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            stream_synthesis(engine, gen_request, &tx).await;
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type SynthesizeDuplexStream = ChunkStream;
+
+    async fn synthesize_duplex(
+        &self,
+        request: Request<Streaming<proto::EngineRequest>>,
+    ) -> Result<Response<Self::SynthesizeDuplexStream>, Status> {
+        let caller = request.extensions().get::<Caller>().cloned();
+        let permit = auth::take_stream(&request)?;
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::channel(32);
+
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            loop {
+                let message = match inbound.message().await {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                };
+
+                let checked = match convert::generation_request(message) {
+                    Ok(gen_request) => auth::check_priority(caller.as_ref(), gen_request.priority)
+                        .map(|()| gen_request),
+                    Err(status) => Err(status),
+                };
+                match checked {
+                    Ok(gen_request) => stream_synthesis(engine.clone(), gen_request, &tx).await,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                }
+
+                if tx.is_closed() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn transcribe(
+        &self,
+        request: Request<proto::EngineRequest>,
+    ) -> Result<Response<proto::EngineOutput>, Status> {
+        let request = request.into_inner();
+        if request.audio_input.is_empty() {
+            return Err(Status::invalid_argument("ASR request requires audio input"));
+        }
+
+        let request_id = if request.id.is_empty() {
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            request.id
+        };
+        let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&request.audio_input);
+
+        let started = std::time::Instant::now();
+        let engine = &self.engine;
+        let response = engine
+            .asr_transcribe(
+                &audio_base64,
+                request.model_id.as_deref(),
+                request.language.as_deref(),
+            )
+            .await
+            .map_err(status_from_error)?;
+
+        if let Some(error) = response.error {
+            return Err(Status::internal(error));
+        }
+
+        Ok(Response::new(proto::EngineOutput {
+            request_id,
+            text: Some(response.transcription.unwrap_or_default()),
+            generation_time_ms: started.elapsed().as_secs_f32() * 1000.0,
+            is_finished: true,
+            finish_reason: proto::FinishReason::StopToken as i32,
+            language: response.language,
+            ..Default::default()
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl EngineAdmin for IzwiGrpcService {
+    async fn get_metrics(
+        &self,
+        request: Request<proto::GetMetricsRequest>,
+    ) -> Result<Response<proto::EngineMetrics>, Status> {
+        auth::require_admin(&request)?;
+        let metrics = self.engine_core.metrics().await;
+        let pending = self.engine_core.pending_requests().await;
+        let running = self.engine_core.running_requests().await;
+        Ok(Response::new(convert::engine_metrics(
+            metrics, pending, running,
+        )))
+    }
+
+    async fn list_models(
+        &self,
+        request: Request<proto::ListModelsRequest>,
+    ) -> Result<Response<proto::ListModelsResponse>, Status> {
+        auth::require_admin(&request)?;
+        let engine = &self.engine;
+        let models = engine
+            .list_models()
+            .await
+            .into_iter()
+            .map(convert::model_info)
+            .collect();
+        Ok(Response::new(proto::ListModelsResponse { models }))
+    }
+
+    async fn set_request_priority(
+        &self,
+        request: Request<proto::SetRequestPriorityRequest>,
+    ) -> Result<Response<proto::SetRequestPriorityResponse>, Status> {
+        auth::require_admin(&request)?;
+        let request = request.into_inner();
+        let priority = convert::priority_from_proto(request.priority);
+
+        let previous = self
+            .engine_core
+            .set_request_priority(&request.request_id, priority, request.reason)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(proto::SetRequestPriorityResponse {
+            previous_priority: convert::priority_to_proto(previous) as i32,
+            priority: convert::priority_to_proto(priority) as i32,
+        }))
+    }
+
+    async fn abort_request(
+        &self,
+        request: Request<proto::AbortRequestRequest>,
+    ) -> Result<Response<proto::AbortRequestResponse>, Status> {
+        auth::require_admin(&request)?;
+        let request = request.into_inner();
+        let aborted = self
+            .engine_core
+            .abort_request(&request.request_id)
+            .await
+            .map_err(status_from_error)?;
+        Ok(Response::new(proto::AbortRequestResponse { aborted }))
+    }
+}
@@ -0,0 +1,69 @@
+//! Engine administration endpoints
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::Serialize;
+
+use crate::auth::{require_admin, AdminScope, ApiKeyIdentity};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audit::{AuditQuery, AuditRecord};
+use izwi_core::engine::{EngineStats, WarmupReport};
+use izwi_core::inference::DaemonStatus;
+
+/// Run dummy requests through the resident models now (admin keys only)
+pub async fn warmup(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+) -> Result<Json<WarmupReport>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    Ok(Json(state.engine_core.warmup().await?))
+}
+
+/// Engine state for operator dashboards
+#[derive(Debug, Serialize)]
+pub struct AdminStats {
+    #[serde(flatten)]
+    pub engine: EngineStats,
+    pub daemons: Vec<DaemonStatus>,
+}
+
+/// Queues, KV cache usage, running request progress and daemon health
+/// (admin keys only)
+pub async fn get_stats(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+) -> Result<Json<AdminStats>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    let daemons = state.engine.daemon_health();
+    Ok(Json(AdminStats {
+        engine: state.engine_core.stats().await,
+        daemons,
+    }))
+}
+
+/// Search the audit log, oldest first
+///
+/// Callers authenticated with an API key only see their own requests.
+pub async fn get_audit(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Query(mut query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditRecord>>, ApiError> {
+    let audit = state
+        .audit
+        .clone()
+        .ok_or_else(|| ApiError::not_found("Audit logging is not enabled"))?;
+    if let Some(Extension(ApiKeyIdentity(name))) = identity {
+        if query.api_key.as_ref().is_some_and(|wanted| *wanted != name) {
+            return Err(ApiError::forbidden("Cannot read another key's requests"));
+        }
+        query.api_key = Some(name);
+    }
+    let records = tokio::task::spawn_blocking(move || audit.query(&query))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))??;
+    Ok(Json(records))
+}
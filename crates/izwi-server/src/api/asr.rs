@@ -3,39 +3,256 @@
 use axum::{
     extract::State,
     response::sse::{Event, KeepAlive, Sse},
-    Json,
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
+use base64::Engine;
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
-use std::path::Path;
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::borrow::Cow;
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::{info, warn};
 
+use super::tts::{parse_format, subtitle_response};
+use crate::auth::ApiKeyIdentity;
 use crate::error::ApiError;
 use crate::state::AppState;
-
-const ASR_SOCKET_PATH: &str = "/tmp/izwi_qwen3_asr_daemon.sock";
+use izwi_core::audio::{
+    conform_wav, decode_raw, decode_wav, diarize, resample, AudioEncoder, AudioFormat,
+    DiarizeConfig,
+};
+use izwi_core::engine::validation;
+use izwi_core::engine::{
+    Constraint, OutputResponse, OutputTimings, TokenLogprob, MAX_TOP_LOGPROBS,
+};
+use izwi_core::inference::asr_bridge::validate_hotwords;
+use izwi_core::inference::{AsrTask, Hotword, ASR_SAMPLE_RATE};
+use izwi_core::language::{detect_language, normalize_language, AUTO_LANGUAGE};
+use izwi_core::text::subtitles::{timed_cues, Cue};
+use izwi_core::text::{ProfanityFilter, Segmenters, SubtitleFormat, TranscriptFilter};
+use izwi_core::upload::UploadedFile;
+use izwi_core::usage::Usage;
+use izwi_core::ErrorCode;
 
 /// ASR transcription request
 #[derive(Debug, Deserialize)]
 pub struct TranscribeRequest {
+    /// Base64 audio (empty when `upload_id` is given)
+    #[serde(default)]
     pub audio_base64: String,
+    /// Completed chunked upload to transcribe instead of `audio_base64`
+    #[serde(default)]
+    pub upload_id: Option<String>,
+    /// Encoding of the audio: an audio file (default), mulaw or alaw
+    /// (8 kHz G.711), or raw_i16/raw_f32 with `sample_rate`
+    #[serde(default)]
+    pub audio_format: Option<String>,
+    /// Sample rate of raw PCM input
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
     #[serde(default)]
     pub model_id: Option<String>,
+    /// Spoken language (detected when unset)
     #[serde(default)]
     pub language: Option<String>,
+    /// `transcribe` (default) or `translate`, which also returns an
+    /// English translation
+    #[serde(default)]
+    pub task: AsrTask,
+    /// Split the transcript into segments labelled by speaker
+    #[serde(default)]
+    pub diarize: bool,
+    /// Upper bound on the number of speakers when diarizing
+    #[serde(default)]
+    pub max_speakers: Option<usize>,
+    /// Terms to favor: strings or `{"word", "boost"}`
+    #[serde(default)]
+    pub hotwords: Vec<Hotword>,
+    /// `mask` or `tag` profanity in the transcript
+    #[serde(default)]
+    pub profanity: Option<TranscriptFilter>,
+    /// Limit on the transcript (regex, GBNF grammar or JSON schema)
+    #[serde(default)]
+    pub constraint: Option<Constraint>,
+    /// Return the log probability of each transcript token, with this many
+    /// most likely alternatives (when the model reports them)
+    #[serde(default)]
+    pub logprobs: Option<usize>,
+    /// `srt` or `vtt` to return subtitles instead of JSON (non-streaming
+    /// only)
+    #[serde(default)]
+    pub response_format: Option<SubtitleFormat>,
+}
+
+impl TranscribeRequest {
+    /// Language hint in the form the daemon expects
+    fn language(&self) -> Option<String> {
+        self.language.as_deref().map(normalize_language)
+    }
+
+    /// Transcript text with the requested profanity filter applied
+    pub(super) fn clean(&self, filter: &ProfanityFilter, text: String) -> String {
+        match self.profanity {
+            Some(mode) => filter.filter_transcript(&text, mode),
+            None => text,
+        }
+    }
+
+    /// Daemon message transcribing `audio`
+    pub(super) fn message(&self, command: &str, audio: &DaemonAudio) -> serde_json::Value {
+        let mut message = serde_json::json!({
+            "command": command,
+            "model_id": self.model_id,
+            "language": self.language(),
+            "task": self.task,
+            "hotwords": self.hotwords,
+            "constraint": self.constraint,
+            "logprobs": self.logprobs,
+        });
+        match audio {
+            DaemonAudio::Inline(audio_base64) => {
+                message["audio_base64"] = audio_base64.as_ref().into();
+            }
+            DaemonAudio::Upload(file) => {
+                message["audio_path"] = file.path.to_string_lossy().as_ref().into();
+            }
+        }
+        message
+    }
+
+    fn check_logprobs(&self) -> Result<(), ApiError> {
+        match self.logprobs {
+            Some(top) if top > MAX_TOP_LOGPROBS => Err(ApiError::bad_request(format!(
+                "logprobs must be at most {}",
+                MAX_TOP_LOGPROBS
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Audio in a form the daemon reads: WAV at other rates or with more
+    /// channels is resampled to mono at the model rate, and headerless
+    /// input is wrapped in WAV. Inline audio is checked against the input
+    /// limits, uploads were checked against the upload limit as they arrived.
+    pub(super) async fn audio(
+        &self,
+        state: &AppState,
+        owner: Option<&str>,
+    ) -> Result<DaemonAudio<'_>, ApiError> {
+        let limits = &state.engine.config().limits;
+        let upload = match (&self.upload_id, self.audio_base64.is_empty()) {
+            (Some(_), false) => {
+                return Err(ApiError::bad_request(
+                    "Send either audio_base64 or upload_id, not both",
+                ))
+            }
+            (Some(id), true) => Some(state.uploads.file(id, owner)?),
+            (None, _) => {
+                validation::check_audio(&self.audio_base64, "audio_base64", limits)?;
+                None
+            }
+        };
+        let original = match &upload {
+            Some(file) => DaemonAudio::Upload(file.clone()),
+            None => DaemonAudio::Inline(Cow::Borrowed(&self.audio_base64)),
+        };
+        let bytes = original.bytes().await?;
+        let wav = match self.audio_format.as_deref().map(parse_format).transpose()? {
+            None | Some(AudioFormat::Wav) => match conform_wav(&bytes, ASR_SAMPLE_RATE)? {
+                Some(wav) => wav,
+                None => return Ok(original),
+            },
+            Some(format) => {
+                let sample_rate = format
+                    .required_sample_rate()
+                    .or(self.sample_rate)
+                    .ok_or_else(|| {
+                        ApiError::bad_request("sample_rate is required for raw PCM audio")
+                    })?;
+                validation::check_sample_rate(sample_rate, "sample_rate", limits)?;
+                let samples = resample(&decode_raw(&bytes, format)?, sample_rate, ASR_SAMPLE_RATE)?;
+                AudioEncoder::new(ASR_SAMPLE_RATE, 1).encode(&samples, AudioFormat::Wav)?
+            }
+        };
+        let wav = base64::engine::general_purpose::STANDARD.encode(wav);
+        if upload.is_none() {
+            validation::check_audio(&wav, "audio_base64", limits)?;
+        }
+        Ok(DaemonAudio::Inline(Cow::Owned(wav)))
+    }
+}
+
+/// Audio as the ASR daemon receives it
+pub(super) enum DaemonAudio<'a> {
+    /// Base64 audio file
+    Inline(Cow<'a, str>),
+    /// Completed upload, read by the daemon from disk
+    Upload(Arc<UploadedFile>),
+}
+
+impl DaemonAudio<'_> {
+    fn into_owned(self) -> DaemonAudio<'static> {
+        match self {
+            Self::Inline(audio) => DaemonAudio::Inline(Cow::Owned(audio.into_owned())),
+            Self::Upload(file) => DaemonAudio::Upload(file),
+        }
+    }
+
+    /// The audio file's bytes
+    pub(super) async fn bytes(&self) -> Result<Vec<u8>, ApiError> {
+        match self {
+            Self::Inline(audio) => base64::engine::general_purpose::STANDARD
+                .decode(audio.as_ref())
+                .map_err(|e| {
+                    ApiError::with_code(ErrorCode::InvalidAudio, format!("Invalid audio: {}", e))
+                }),
+            Self::Upload(file) => Ok(tokio::fs::read(&file.path)
+                .await
+                .map_err(izwi_core::Error::from)?),
+        }
+    }
+}
+
+/// Language reported by the daemon, or detected from the transcript
+fn transcript_language(response: &serde_json::Value, transcript: &str) -> Option<String> {
+    response
+        .get("language")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .or_else(|| detect_language(transcript).map(|d| d.name.to_string()))
 }
 
 /// ASR transcription response
 #[derive(Debug, Serialize)]
 pub struct TranscribeResponse {
+    /// Transcript as `text`
+    #[serde(flatten)]
+    pub output: OutputResponse,
+    /// Transcript in the spoken language
     pub transcription: String,
+    /// English translation, for the `translate` task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+    pub task: AsrTask,
     pub language: Option<String>,
+    /// Speaker turns, when diarization was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<TranscriptSegment>>,
     pub stats: Option<AsrStats>,
+    pub usage: Usage,
+}
+
+/// Part of the transcript spoken by one speaker
+#[derive(Debug, Serialize)]
+pub struct TranscriptSegment {
+    /// `speaker_0`, `speaker_1`, ... in order of first appearance
+    pub speaker: String,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
 }
 
 /// ASR processing statistics
@@ -58,48 +275,27 @@ pub struct AsrStatusResponse {
     pub cached_models: Vec<String>,
 }
 
-/// Send a message to the ASR daemon via Unix socket
-fn send_daemon_message(message: &serde_json::Value) -> Result<serde_json::Value, ApiError> {
-    let mut stream = UnixStream::connect(ASR_SOCKET_PATH)
-        .map_err(|e| ApiError::internal(format!("ASR daemon not running: {}", e)))?;
-
-    stream.set_read_timeout(Some(Duration::from_secs(120))).ok();
-    stream.set_write_timeout(Some(Duration::from_secs(30))).ok();
-
-    let msg_bytes = serde_json::to_vec(message)
-        .map_err(|e| ApiError::internal(format!("Failed to serialize message: {}", e)))?;
-
-    let length = (msg_bytes.len() as u32).to_be_bytes();
-    stream
-        .write_all(&length)
-        .map_err(|e| ApiError::internal(format!("Failed to write length: {}", e)))?;
-    stream
-        .write_all(&msg_bytes)
-        .map_err(|e| ApiError::internal(format!("Failed to write message: {}", e)))?;
-
-    let mut length_buf = [0u8; 4];
-    stream
-        .read_exact(&mut length_buf)
-        .map_err(|e| ApiError::internal(format!("Failed to read response length: {}", e)))?;
-    let response_length = u32::from_be_bytes(length_buf) as usize;
-
-    let mut response_buf = vec![0u8; response_length];
-    stream
-        .read_exact(&mut response_buf)
-        .map_err(|e| ApiError::internal(format!("Failed to read response: {}", e)))?;
-
-    serde_json::from_slice(&response_buf)
-        .map_err(|e| ApiError::internal(format!("Failed to parse response: {}", e)))
+/// Send a message to the ASR daemon over the engine's pooled connections
+pub(super) async fn send_daemon_message(
+    state: &AppState,
+    message: &serde_json::Value,
+) -> Result<serde_json::Value, ApiError> {
+    let engine = &state.engine;
+    engine
+        .asr_client()
+        .call(message)
+        .await
+        .map_err(|e| ApiError::with_code(e.code(), format!("ASR daemon request failed: {}", e)))
 }
 
 /// Check if the ASR daemon is running
-fn is_daemon_running() -> bool {
-    Path::new(ASR_SOCKET_PATH).exists() && UnixStream::connect(ASR_SOCKET_PATH).is_ok()
+pub(super) async fn is_daemon_running(state: &AppState) -> bool {
+    state.engine.asr_client().ping().await
 }
 
 /// Get ASR daemon status
-pub async fn status(State(_state): State<AppState>) -> Result<Json<AsrStatusResponse>, ApiError> {
-    if !is_daemon_running() {
+pub async fn status(State(state): State<AppState>) -> Result<Json<AsrStatusResponse>, ApiError> {
+    if !is_daemon_running(&state).await {
         return Ok(Json(AsrStatusResponse {
             running: false,
             status: "stopped".to_string(),
@@ -112,7 +308,7 @@ pub async fn status(State(_state): State<AppState>) -> Result<Json<AsrStatusResp
         "command": "status"
     });
 
-    match send_daemon_message(&message) {
+    match send_daemon_message(&state, &message).await {
         Ok(response) => {
             let device = response
                 .get("device")
@@ -146,9 +342,9 @@ pub async fn status(State(_state): State<AppState>) -> Result<Json<AsrStatusResp
 
 /// Start the ASR daemon
 pub async fn start_daemon(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    if is_daemon_running() {
+    if is_daemon_running(&state).await {
         return Ok(Json(serde_json::json!({
             "success": true,
             "message": "ASR daemon already running"
@@ -157,44 +353,23 @@ pub async fn start_daemon(
 
     info!("Starting Qwen3-ASR daemon");
 
-    let scripts_dir = std::env::current_dir().unwrap_or_default().join("scripts");
-    let daemon_script = scripts_dir.join("qwen3_asr_daemon.py");
+    // The supervisor spawns it with the configured interpreter and socket
+    let engine = state.engine.clone();
+    tokio::task::spawn_blocking(move || engine.ensure_asr_daemon_running())
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to start ASR daemon: {}", e)))??;
 
-    if !daemon_script.exists() {
-        return Err(ApiError::internal(format!(
-            "ASR daemon script not found: {:?}",
-            daemon_script
-        )));
-    }
-
-    let _child = Command::new("python3")
-        .arg(&daemon_script)
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|e| ApiError::internal(format!("Failed to start ASR daemon: {}", e)))?;
-
-    // Wait for daemon to start
-    for _ in 0..50 {
-        std::thread::sleep(Duration::from_millis(100));
-        if is_daemon_running() {
-            return Ok(Json(serde_json::json!({
-                "success": true,
-                "message": "ASR daemon started"
-            })));
-        }
-    }
-
-    Err(ApiError::internal(
-        "ASR daemon failed to start within timeout",
-    ))
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "ASR daemon started"
+    })))
 }
 
 /// Stop the ASR daemon
 pub async fn stop_daemon(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    if !is_daemon_running() {
+    if !is_daemon_running(&state).await {
         return Ok(Json(serde_json::json!({
             "success": true,
             "message": "ASR daemon not running"
@@ -205,7 +380,7 @@ pub async fn stop_daemon(
         "command": "shutdown"
     });
 
-    match send_daemon_message(&message) {
+    match send_daemon_message(&state, &message).await {
         Ok(_) => Ok(Json(serde_json::json!({
             "success": true,
             "message": "ASR daemon stopped"
@@ -233,6 +408,8 @@ pub enum TranscribeStreamEvent {
     },
     Final {
         text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        translation: Option<String>,
         language: Option<String>,
         audio_duration_secs: Option<f64>,
     },
@@ -244,19 +421,42 @@ pub enum TranscribeStreamEvent {
 
 /// Stream transcription with SSE - sends partial results as text is decoded
 pub async fn transcribe_stream(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
     Json(request): Json<TranscribeRequest>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>>, ApiError> {
-    if !is_daemon_running() {
-        return Err(ApiError::internal(
+    if !is_daemon_running(&state).await {
+        return Err(ApiError::daemon_unavailable(
             "ASR daemon not running. Please start it first.",
         ));
     }
+    if request.diarize {
+        return Err(ApiError::bad_request(
+            "Diarization is not supported for streaming transcription",
+        ));
+    }
+    if request.logprobs.is_some() {
+        return Err(ApiError::bad_request(
+            "Log probabilities are not supported for streaming transcription",
+        ));
+    }
+    if request.response_format.is_some() {
+        return Err(ApiError::bad_request(
+            "response_format is not supported for streaming transcription",
+        ));
+    }
+    validate_hotwords(&request.hotwords)?;
+    let owner = identity
+        .as_ref()
+        .map(|Extension(ApiKeyIdentity(name))| name.as_str());
+    let audio = request.audio(&state, owner).await?.into_owned();
+    let profanity = state.engine.profanity().clone();
 
     // Create an async stream that reads from the daemon using tokio async I/O
+    let transport = state.engine.asr_client().transport().clone();
     let stream = async_stream::stream! {
-        // Connect to daemon using tokio's async UnixStream
-        let stream_result = tokio::net::UnixStream::connect(ASR_SOCKET_PATH).await;
+        // Connect over the daemon's transport with tokio async I/O
+        let stream_result = transport.connect().await;
         let mut daemon_stream = match stream_result {
             Ok(s) => s,
             Err(e) => {
@@ -270,12 +470,7 @@ pub async fn transcribe_stream(
         };
 
         // Send streaming transcription request
-        let message = serde_json::json!({
-            "command": "transcribe_stream",
-            "audio_base64": request.audio_base64,
-            "model_id": request.model_id,
-            "language": request.language,
-        });
+        let message = request.message("transcribe_stream", &audio);
 
         let msg_bytes = match serde_json::to_vec(&message) {
             Ok(b) => b,
@@ -330,14 +525,18 @@ pub async fn transcribe_stream(
                 }
                 "partial" => {
                     let text = response.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    let text = request.clean(&profanity, text);
                     let is_final = response.get("is_final").and_then(|v| v.as_bool()).unwrap_or(false);
                     TranscribeStreamEvent::Partial { text, is_final }
                 }
                 "final" => {
                     let text = response.get("text").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let language = response.get("language").and_then(|v| v.as_str()).map(String::from);
+                    let translation = response.get("translation").and_then(|v| v.as_str()).map(String::from);
+                    let language = transcript_language(&response, &text);
+                    let text = request.clean(&profanity, text);
+                    let translation = translation.map(|t| request.clean(&profanity, t));
                     let audio_duration_secs = response.get("audio_duration_secs").and_then(|v| v.as_f64());
-                    TranscribeStreamEvent::Final { text, language, audio_duration_secs }
+                    TranscribeStreamEvent::Final { text, translation, language, audio_duration_secs }
                 }
                 "error" => {
                     let error = response.get("error").and_then(|v| v.as_str()).unwrap_or("Unknown error").to_string();
@@ -359,27 +558,38 @@ pub async fn transcribe_stream(
 
 /// Transcribe audio to text
 pub async fn transcribe(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
     Json(request): Json<TranscribeRequest>,
-) -> Result<Json<TranscribeResponse>, ApiError> {
+) -> Result<Response, ApiError> {
     use std::time::Instant;
 
-    if !is_daemon_running() {
-        return Err(ApiError::internal(
+    if !is_daemon_running(&state).await {
+        return Err(ApiError::daemon_unavailable(
             "ASR daemon not running. Please start it first.",
         ));
     }
 
+    let constraint = request
+        .constraint
+        .as_ref()
+        .map(Constraint::compile)
+        .transpose()?;
+    validate_hotwords(&request.hotwords)?;
+    request.check_logprobs()?;
+    let owner = identity
+        .as_ref()
+        .map(|Extension(ApiKeyIdentity(name))| name.as_str());
+    let audio = request.audio(&state, owner).await?;
     let start_time = Instant::now();
 
-    let message = serde_json::json!({
-        "command": "transcribe",
-        "audio_base64": request.audio_base64,
-        "model_id": request.model_id,
-        "language": request.language,
-    });
-
-    let response = send_daemon_message(&message)?;
+    let (response, segments) = if request.diarize {
+        let (response, segments) = transcribe_by_speaker(&state, &request, &audio).await?;
+        (response, Some(segments))
+    } else {
+        let message = request.message("transcribe", &audio);
+        (send_daemon_message(&state, &message).await?, None)
+    };
 
     let processing_time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
 
@@ -392,11 +602,42 @@ pub async fn transcribe(
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .to_string();
+    // The daemon may not support constraints, so check the transcript here
+    if constraint.is_some_and(|c| !c.matches(&transcription)) {
+        return Err(ApiError::internal(
+            "Transcript does not satisfy the output constraint",
+        ));
+    }
 
-    let language = response
-        .get("language")
-        .and_then(|v| v.as_str())
-        .map(String::from);
+    let translation = match request.task {
+        AsrTask::Transcribe => None,
+        AsrTask::Translate => Some(
+            response
+                .get("translation")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ApiError::internal("ASR daemon returned no translation"))?
+                .to_string(),
+        ),
+    };
+    let language = transcript_language(&response, &transcription);
+    let logprobs: Vec<TokenLogprob> = match (request.logprobs, response.get("logprobs")) {
+        (Some(_), Some(logprobs)) => serde_json::from_value(logprobs.clone())
+            .map_err(|e| ApiError::internal(format!("Invalid logprobs from ASR daemon: {}", e)))?,
+        _ => Vec::new(),
+    };
+    let profanity = state.engine.profanity();
+    let transcription = request.clean(profanity, transcription);
+    let translation = translation.map(|t| request.clean(profanity, t));
+    let segments = segments.map(|segments| {
+        segments
+            .into_iter()
+            .map(|segment| TranscriptSegment {
+                text: request.clean(profanity, segment.text),
+                translation: segment.translation.map(|t| request.clean(profanity, t)),
+                ..segment
+            })
+            .collect()
+    });
 
     // Extract audio duration from daemon response if available
     let audio_duration_secs = response.get("audio_duration_secs").and_then(|v| v.as_f64());
@@ -410,13 +651,279 @@ pub async fn transcribe(
         }
     });
 
-    Ok(Json(TranscribeResponse {
+    let usage = Usage::default()
+        .with_audio(0, audio_duration_secs.unwrap_or(0.0))
+        .with_wall_time(start_time.elapsed());
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let api_key = identity.map(|Extension(ApiKeyIdentity(name))| name);
+    state
+        .usage
+        .record(&request_id, api_key.as_deref(), "asr", usage);
+
+    let output = OutputResponse::new(request_id)
+        .with_text(translation.clone().unwrap_or_else(|| transcription.clone()))
+        .with_timings(OutputTimings {
+            decode_ms: processing_time_ms,
+            total_ms: processing_time_ms,
+            ..Default::default()
+        })
+        .with_logprobs(logprobs);
+    let response = TranscribeResponse {
+        output,
         transcription,
+        translation,
+        task: request.task,
         language,
+        segments,
         stats: Some(AsrStats {
             processing_time_ms,
             audio_duration_secs,
             rtf,
         }),
-    }))
+        usage,
+    };
+    match request.response_format {
+        Some(format) => {
+            let cues = transcript_cues(&response, audio_duration_secs, state.engine.segmenters())?;
+            Ok(subtitle_response(
+                &cues,
+                format,
+                &response.output.request_id,
+            ))
+        }
+        None => Ok(Json(response).into_response()),
+    }
+}
+
+/// Subtitle cues of a transcription, in the language of its `text`
+fn transcript_cues(
+    response: &TranscribeResponse,
+    audio_duration_secs: Option<f64>,
+    segmenters: &Segmenters,
+) -> Result<Vec<Cue>, ApiError> {
+    let language = match response.task {
+        AsrTask::Translate => "English",
+        _ => response.language.as_deref().unwrap_or(AUTO_LANGUAGE),
+    };
+    let segmenter = segmenters.get(language);
+    if let Some(segments) = &response.segments {
+        return Ok(segments
+            .iter()
+            .flat_map(|segment| {
+                let text = segment.translation.as_ref().unwrap_or(&segment.text);
+                timed_cues(
+                    text,
+                    segment.start_secs..segment.end_secs,
+                    Some(&segment.speaker),
+                    segmenter.as_ref(),
+                )
+            })
+            .collect());
+    }
+    let duration = audio_duration_secs.ok_or_else(|| {
+        ApiError::internal("ASR daemon did not report the audio duration needed for subtitles")
+    })?;
+    let text = response.output.text.as_deref().unwrap_or_default();
+    Ok(timed_cues(text, 0.0..duration, None, segmenter.as_ref()))
+}
+
+/// Transcribe each speaker turn separately. Returns the turns and a daemon
+/// style response covering the whole audio.
+async fn transcribe_by_speaker(
+    state: &AppState,
+    request: &TranscribeRequest,
+    audio: &DaemonAudio<'_>,
+) -> Result<(serde_json::Value, Vec<TranscriptSegment>), ApiError> {
+    let base64 = &base64::engine::general_purpose::STANDARD;
+    let invalid = |e: String| ApiError::with_code(ErrorCode::InvalidAudio, e);
+    let bytes = audio.bytes().await?;
+    let (samples, sample_rate) = decode_wav(&bytes)
+        .map_err(|e| invalid(format!("Diarization needs WAV or raw PCM audio: {}", e)))?;
+    let config = DiarizeConfig {
+        max_speakers: request.max_speakers,
+        ..Default::default()
+    };
+
+    let encoder = AudioEncoder::new(sample_rate, 1);
+    let secs = |sample: usize| sample as f64 / sample_rate as f64;
+    let mut segments = Vec::new();
+    let mut language = None;
+    for turn in diarize(&samples, sample_rate, &config) {
+        let wav = encoder.encode(&samples[turn.range.clone()], AudioFormat::Wav)?;
+        let turn_audio = DaemonAudio::Inline(Cow::Owned(base64.encode(wav)));
+        let message = request.message("transcribe", &turn_audio);
+        let response = send_daemon_message(state, &message).await?;
+        if let Some(error) = response.get("error").and_then(|v| v.as_str()) {
+            return Err(ApiError::internal(error.to_string()));
+        }
+        let field = |name: &str| {
+            response
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(String::from)
+        };
+        language = language.or_else(|| field("language"));
+        segments.push(TranscriptSegment {
+            speaker: format!("speaker_{}", turn.speaker),
+            start_secs: secs(turn.range.start),
+            end_secs: secs(turn.range.end),
+            text: field("transcription").unwrap_or_default(),
+            translation: field("translation"),
+        });
+    }
+
+    let join = |texts: Vec<&str>| {
+        texts
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let transcription = join(segments.iter().map(|s| s.text.as_str()).collect());
+    let translation = (request.task == AsrTask::Translate).then(|| {
+        join(
+            segments
+                .iter()
+                .filter_map(|s| s.translation.as_deref())
+                .collect(),
+        )
+    });
+    let response = serde_json::json!({
+        "transcription": transcription,
+        "translation": translation,
+        "language": language,
+        "audio_duration_secs": secs(samples.len()),
+    });
+    Ok((response, segments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UnixListener;
+
+    /// State whose ASR daemon is a stub answering every `transcribe` with
+    /// `reply`. Must be called outside a runtime; the stub is started on
+    /// `runtime`.
+    fn stub_daemon(runtime: &tokio::runtime::Runtime, reply: serde_json::Value) -> AppState {
+        let mut engine = izwi_core::EngineConfig::default();
+        engine.bridge.socket_dir =
+            std::env::temp_dir().join(format!("izwi-asr-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&engine.bridge.socket_dir).unwrap();
+        let socket = engine.bridge.socket_path("asr");
+        let state = AppState::for_tests(&Default::default(), engine);
+
+        let listener = runtime.block_on(async { UnixListener::bind(&socket).unwrap() });
+        runtime.spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let reply = reply.clone();
+                tokio::spawn(async move {
+                    while let Ok(length) = stream.read_u32().await {
+                        let mut body = vec![0u8; length as usize];
+                        stream.read_exact(&mut body).await.unwrap();
+                        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        let mut response = match request["command"].as_str() {
+                            Some("transcribe") => {
+                                assert_eq!(request["task"], "translate");
+                                reply.clone()
+                            }
+                            _ => serde_json::json!({ "status": "ok" }),
+                        };
+                        response["request_id"] = request["request_id"].clone();
+                        let response = serde_json::to_vec(&response).unwrap();
+                        stream.write_u32(response.len() as u32).await.unwrap();
+                        stream.write_all(&response).await.unwrap();
+                    }
+                });
+            }
+        });
+        state
+    }
+
+    #[test]
+    fn test_translate_task() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let wav = AudioEncoder::new(ASR_SAMPLE_RATE, 1)
+            .encode(&vec![0.25f32; ASR_SAMPLE_RATE as usize], AudioFormat::Wav)
+            .unwrap();
+        let request = || {
+            serde_json::from_value::<TranscribeRequest>(serde_json::json!({
+                "audio_base64": base64::engine::general_purpose::STANDARD.encode(&wav),
+                "task": "translate",
+            }))
+            .unwrap()
+        };
+
+        // The translation is returned alongside the transcript and is the text
+        let state = stub_daemon(
+            &runtime,
+            serde_json::json!({
+                "transcription": "hola mundo",
+                "translation": "hello world",
+                "language": "es",
+            }),
+        );
+        let body = runtime.block_on(async {
+            let Ok(response) = transcribe(State(state), None, Json(request())).await else {
+                panic!("translation failed");
+            };
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap()
+        });
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["task"], "translate");
+        assert_eq!(body["transcription"], "hola mundo");
+        assert_eq!(body["translation"], "hello world");
+        assert_eq!(body["text"], "hello world");
+
+        // A daemon that leaves out the translation fails the request
+        let state = stub_daemon(&runtime, serde_json::json!({ "transcription": "hola" }));
+        let result = runtime.block_on(transcribe(State(state), None, Json(request())));
+        let Err(error) = result else {
+            panic!("a missing translation was accepted");
+        };
+        assert_eq!(error.code, ErrorCode::Internal);
+        assert_eq!(error.message, "ASR daemon returned no translation");
+    }
+
+    #[test]
+    fn test_audio_is_resampled_to_model_rate() {
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&Default::default(), Default::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let audio = |request: serde_json::Value| {
+            let request: TranscribeRequest = serde_json::from_value(request).unwrap();
+            let audio = runtime.block_on(async {
+                let Ok(audio) = request.audio(&state, None).await else {
+                    panic!("audio was rejected");
+                };
+                audio.bytes().await.ok().unwrap()
+            });
+            decode_wav(&audio).unwrap()
+        };
+
+        // One second of 44.1 kHz stereo
+        let wav = AudioEncoder::new(44100, 2)
+            .encode(&vec![0.25f32; 2 * 44100], AudioFormat::Wav)
+            .unwrap();
+        let (samples, rate) = audio(serde_json::json!({
+            "audio_base64": base64::engine::general_purpose::STANDARD.encode(wav),
+        }));
+        assert_eq!(rate, ASR_SAMPLE_RATE);
+        assert_eq!(samples.len(), ASR_SAMPLE_RATE as usize);
+
+        // Half a second of raw 44.1 kHz PCM
+        let pcm: Vec<u8> = std::iter::repeat_n(1000i16.to_le_bytes(), 22050)
+            .flatten()
+            .collect();
+        let (samples, rate) = audio(serde_json::json!({
+            "audio_base64": base64::engine::general_purpose::STANDARD.encode(pcm),
+            "audio_format": "raw_i16",
+            "sample_rate": 44100,
+        }));
+        assert_eq!(rate, ASR_SAMPLE_RATE);
+        assert_eq!(samples.len(), ASR_SAMPLE_RATE as usize / 2);
+    }
 }
@@ -0,0 +1,169 @@
+//! Stateful audio chat sessions
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::models::parse_variant;
+use crate::auth::{request_priority, MaxPriority};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audio::{AudioEncoder, AudioFormat};
+use izwi_core::engine::{
+    ChatInput, ChatSession, Constraint, ContextTruncation, OutputResponse, Priority, ToolCall,
+    ToolResult, ToolSpec,
+};
+
+/// Options for a new session
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateSessionRequest {
+    /// Model answering the turns (the default model when unset)
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Functions the model may call
+    #[serde(default)]
+    pub tools: Vec<ToolSpec>,
+}
+
+/// One user turn: text, audio, results of the last tool calls, or a mix
+#[derive(Debug, Deserialize)]
+pub struct TurnRequest {
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Spoken input (base64 WAV)
+    #[serde(default)]
+    pub audio: Option<String>,
+    #[serde(default)]
+    pub tool_results: Vec<ToolResult>,
+    /// Limit on the text of the reply (regex, GBNF grammar or JSON schema)
+    #[serde(default)]
+    pub constraint: Option<Constraint>,
+    /// Text that ends the reply (not included in it)
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Longest spoken reply, in seconds
+    #[serde(default)]
+    pub max_audio_seconds: Option<f32>,
+    /// Return the log probability of each reply token, with this many
+    /// most likely alternatives
+    #[serde(default)]
+    pub logprobs: Option<usize>,
+    /// Scheduling priority (capped by the API key's `max_priority`)
+    #[serde(default)]
+    pub priority: Option<Priority>,
+}
+
+/// The assistant's reply
+#[derive(Serialize)]
+pub struct TurnResponse {
+    pub session_id: String,
+    /// Reply text, and spoken reply as WAV unless text-only
+    #[serde(flatten)]
+    pub output: OutputResponse,
+    /// Tools to run, whose results are sent with the next turn
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    pub turns: usize,
+    /// History tokens reused from the KV cache by the next turn
+    pub cached_tokens: usize,
+    /// How older turns were shortened to fit the context window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<ContextTruncation>,
+}
+
+/// Open a session
+pub async fn create_session(
+    State(state): State<AppState>,
+    body: Option<Json<CreateSessionRequest>>,
+) -> Result<(StatusCode, Json<ChatSession>), ApiError> {
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let model = req.model.as_deref().map(parse_variant).transpose()?;
+    let session = state
+        .engine_core
+        .create_session(model, req.system_prompt, req.tools)
+        .await?;
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+/// History of a session
+pub async fn get_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<ChatSession>, ApiError> {
+    state
+        .engine_core
+        .session(&id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Chat session not found: {}", id)))
+}
+
+/// Send the next user turn and wait for the reply
+pub async fn turn(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    max_priority: Option<Extension<MaxPriority>>,
+    Json(req): Json<TurnRequest>,
+) -> Result<Json<TurnResponse>, ApiError> {
+    let priority = request_priority(req.priority, max_priority)?;
+    let (session, output) = state
+        .engine_core
+        .chat_turn(
+            &id,
+            ChatInput {
+                text: req.text,
+                audio: req.audio,
+                tool_results: req.tool_results,
+                constraint: req.constraint,
+                stop: req.stop,
+                max_audio_seconds: req.max_audio_seconds,
+                logprobs: req.logprobs,
+                priority,
+            },
+        )
+        .await?;
+    let tool_calls = session
+        .turns
+        .last()
+        .map(|turn| turn.tool_calls.clone())
+        .unwrap_or_default();
+
+    let mut response = OutputResponse::from_output(&output);
+    if !output.audio.samples.is_empty() {
+        let wav = AudioEncoder::new(output.audio.sample_rate, 1)
+            .encode(&output.audio.samples, AudioFormat::Wav)?;
+        response = response.with_audio(
+            &wav,
+            "wav",
+            output.audio.sample_rate,
+            output.audio.duration_secs,
+        );
+    }
+    Ok(Json(TurnResponse {
+        session_id: session.id,
+        output: response,
+        tool_calls,
+        turns: session.turns.len(),
+        cached_tokens: session.cached_tokens,
+        truncation: session.truncation,
+    }))
+}
+
+/// Close a session and free its cached history
+pub async fn delete_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.engine_core.delete_session(&id).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!(
+            "Chat session not found: {}",
+            id
+        )))
+    }
+}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::state::AppState;
+use izwi_core::inference::DaemonStatus as DaemonHealthStatus;
 
 /// Daemon status response
 #[derive(Debug, Serialize)]
@@ -29,9 +30,9 @@ pub struct DaemonResponse {
 
 /// Get daemon status
 pub async fn get_status(State(state): State<AppState>) -> Json<DaemonStatus> {
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
-    match engine.get_daemon_status() {
+    match engine.get_daemon_status().await {
         Ok(response) => Json(DaemonStatus {
             running: response.status.as_deref() == Some("ok"),
             device: response.device,
@@ -45,13 +46,19 @@ pub async fn get_status(State(state): State<AppState>) -> Json<DaemonStatus> {
     }
 }
 
+/// Health of every supervised daemon (TTS, ASR)
+pub async fn get_health(State(state): State<AppState>) -> Json<Vec<DaemonHealthStatus>> {
+    let engine = &state.engine;
+    Json(engine.daemon_health())
+}
+
 /// Start the daemon
 pub async fn start_daemon(
     State(state): State<AppState>,
 ) -> Result<Json<DaemonResponse>, (StatusCode, Json<DaemonResponse>)> {
     info!("Starting TTS daemon via API");
 
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
     match engine.ensure_daemon_running() {
         Ok(_) => Ok(Json(DaemonResponse {
@@ -74,7 +81,7 @@ pub async fn stop_daemon(
 ) -> Result<Json<DaemonResponse>, (StatusCode, Json<DaemonResponse>)> {
     info!("Stopping TTS daemon via API");
 
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
     match engine.stop_daemon() {
         Ok(_) => Ok(Json(DaemonResponse {
@@ -98,9 +105,9 @@ pub async fn preload_model(
 ) -> Result<Json<DaemonResponse>, (StatusCode, Json<DaemonResponse>)> {
     info!("Preloading model via API: {}", request.model_path);
 
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
-    match engine.preload_model(&request.model_path) {
+    match engine.preload_model(&request.model_path).await {
         Ok(_) => Ok(Json(DaemonResponse {
             success: true,
             message: format!("Model preloaded: {}", request.model_path),
@@ -0,0 +1,60 @@
+//! Engine debugging endpoints
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{require_admin, AdminScope};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::engine::{CacheStats, ProfileSnapshot};
+
+/// Profile query
+#[derive(Debug, Deserialize)]
+pub struct ProfileQuery {
+    /// Only return the most recent steps
+    #[serde(default)]
+    pub last: Option<usize>,
+}
+
+/// Per-step timings and batch sizes of the engine loop
+pub async fn get_profile(
+    State(state): State<AppState>,
+    Query(query): Query<ProfileQuery>,
+) -> Result<Json<ProfileSnapshot>, ApiError> {
+    Ok(Json(state.engine_core.profile_snapshot(query.last).await?))
+}
+
+/// Discard recorded step timings (admin keys only)
+pub async fn reset_profile(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    state.engine_core.reset_profile().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Output cache counters
+#[derive(Debug, Serialize)]
+pub struct CacheReport {
+    #[serde(flatten)]
+    pub stats: CacheStats,
+    pub hit_rate: f64,
+}
+
+/// Hit, miss and size counters of the output cache
+pub async fn get_cache_stats(State(state): State<AppState>) -> Result<Json<CacheReport>, ApiError> {
+    let cache = state
+        .engine_core
+        .output_cache()
+        .ok_or_else(|| ApiError::not_found("Output cache is disabled"))?;
+    let stats = cache.stats();
+    Ok(Json(CacheReport {
+        hit_rate: stats.hit_rate(),
+        stats,
+    }))
+}
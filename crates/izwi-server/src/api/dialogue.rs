@@ -0,0 +1,95 @@
+//! Multi-speaker dialogue endpoint
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use super::models::parse_variant;
+use super::tts::{parse_format, validate_target_lufs};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audio::AudioEncoder;
+use izwi_core::engine::{OutputResponse, OutputTimings};
+use izwi_core::inference::{DialogueLine, DialogueRequest, LineTiming};
+
+/// Dialogue script request
+#[derive(Debug, Deserialize)]
+pub struct DialogueScript {
+    /// Lines in order: `{speaker, text, pause_ms}`
+    pub lines: Vec<DialogueLine>,
+    /// Script speaker -> voice; unmapped speakers are used as voice names
+    #[serde(default)]
+    pub voices: HashMap<String, String>,
+    /// Default silence between lines in milliseconds
+    #[serde(default)]
+    pub pause_ms: Option<u32>,
+    /// Loudness every line is matched to, in LUFS (default -16)
+    #[serde(default)]
+    pub target_lufs: Option<f32>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Output format (wav, raw_f32, raw_i16)
+    #[serde(default = "default_format")]
+    pub format: String,
+}
+
+fn default_format() -> String {
+    "wav".to_string()
+}
+
+/// Stitched dialogue with per-line timings
+#[derive(Debug, Serialize)]
+pub struct DialogueResponse {
+    #[serde(flatten)]
+    pub output: OutputResponse,
+    pub generation_time_ms: f32,
+    pub lines: Vec<LineTiming>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Synthesize a dialogue script into a single output
+pub async fn generate_dialogue(
+    State(state): State<AppState>,
+    Json(script): Json<DialogueScript>,
+) -> Result<Json<DialogueResponse>, ApiError> {
+    info!("Dialogue request: {} lines", script.lines.len());
+    let format = parse_format(&script.format)?;
+    validate_target_lufs(script.target_lufs)?;
+
+    let request = DialogueRequest {
+        lines: script.lines,
+        voices: script.voices,
+        pause_ms: script.pause_ms,
+        target_lufs: script.target_lufs,
+        language: script.language,
+        model: script.model.as_deref().map(parse_variant).transpose()?,
+    };
+
+    let engine = &state.engine;
+    let result = engine.generate_dialogue(request).await?;
+    let audio = AudioEncoder::new(result.sample_rate, 1).encode(&result.samples, format)?;
+
+    let output = OutputResponse::new(uuid::Uuid::new_v4().to_string())
+        .with_audio(
+            &audio,
+            script.format,
+            format.required_sample_rate().unwrap_or(result.sample_rate),
+            result.duration_secs(),
+        )
+        .with_timings(OutputTimings {
+            decode_ms: result.total_time_ms as f64,
+            total_ms: result.total_time_ms as f64,
+            ..Default::default()
+        });
+
+    Ok(Json(DialogueResponse {
+        output,
+        generation_time_ms: result.total_time_ms,
+        lines: result.lines,
+        warnings: result.warnings,
+    }))
+}
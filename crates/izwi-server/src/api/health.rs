@@ -1,7 +1,15 @@
-//! Health check endpoint
+//! Health check, liveness and readiness endpoints
 
-use axum::Json;
+use axum::{extract::State, http::StatusCode, Json};
+use izwi_core::inference::{CircuitState, DaemonClient};
+use izwi_core::model::{LoadProgress, ModelVariant};
 use serde::Serialize;
+use std::time::Duration;
+
+use crate::state::AppState;
+
+/// Time a daemon has to answer a readiness ping
+const DAEMON_PING_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -15,3 +23,164 @@ pub async fn health_check() -> Json<HealthResponse> {
         version: env!("CARGO_PKG_VERSION"),
     })
 }
+
+/// State of one dependency checked for readiness
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub name: &'static str,
+    pub ready: bool,
+    /// Whether the server is unready while this dependency is down
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl DependencyStatus {
+    fn new(name: &'static str, required: bool, ready: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ready,
+            required,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+/// A model whose weights are being loaded
+#[derive(Debug, Serialize)]
+pub struct ModelLoad {
+    pub model: ModelVariant,
+    pub percent: f32,
+    #[serde(flatten)]
+    pub progress: LoadProgress,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub dependencies: Vec<DependencyStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub loading: Vec<ModelLoad>,
+}
+
+/// Liveness probe: the process is up and serving HTTP
+pub async fn healthz() -> Json<HealthResponse> {
+    health_check().await
+}
+
+/// Readiness probe: 200 once a model is loaded, the TTS daemon answers and
+/// the KV cache is allocated, 503 otherwise
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let mut dependencies = Vec::new();
+
+    let engine = &state.engine;
+    let loaded = engine.loaded_models();
+    let loading: Vec<ModelLoad> = engine
+        .model_manager()
+        .load_progress()
+        .into_iter()
+        .map(|(model, progress)| ModelLoad {
+            model,
+            percent: progress.percent(),
+            progress,
+        })
+        .collect();
+    dependencies.push(DependencyStatus::new(
+        "model",
+        true,
+        !loaded.is_empty(),
+        if let (true, Some(load)) = (loaded.is_empty(), loading.first()) {
+            format!("loading {} ({:.0}%)", load.model, load.percent)
+        } else if loaded.is_empty() {
+            "no model loaded".to_string()
+        } else {
+            loaded
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+    ));
+    dependencies.push(ping("tts_daemon", true, engine.tts_client()).await);
+    dependencies.push(ping("asr_daemon", false, engine.asr_client()).await);
+
+    let kv_cache = state.engine_core.kv_cache_stats().await;
+    dependencies.push(DependencyStatus::new(
+        "kv_cache",
+        true,
+        kv_cache.total_blocks > 0,
+        format!("{} blocks", kv_cache.total_blocks),
+    ));
+
+    let ready = dependencies.iter().all(|d| d.ready || !d.required);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(ReadinessResponse {
+            ready,
+            dependencies,
+            loading,
+        }),
+    )
+}
+
+/// Check that a daemon answers within the ping timeout
+async fn ping(name: &'static str, required: bool, client: &DaemonClient) -> DependencyStatus {
+    let ready = tokio::time::timeout(DAEMON_PING_TIMEOUT, client.ping())
+        .await
+        .unwrap_or(false);
+    let detail = if ready {
+        "responding"
+    } else if client.circuit_state() == CircuitState::Open {
+        "circuit open after repeated failures"
+    } else {
+        "not responding"
+    };
+    DependencyStatus::new(name, required, ready, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_ready_without_model_or_daemon() {
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&Default::default(), Default::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (status, Json(readiness)) = runtime.block_on(readyz(State(state)));
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let body = serde_json::to_value(&readiness).unwrap();
+        assert_eq!(body["ready"], false);
+        let dependency = |name: &str| {
+            body["dependencies"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|d| d["name"] == name)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            dependency("model"),
+            serde_json::json!({
+                "name": "model",
+                "ready": false,
+                "required": true,
+                "detail": "no model loaded",
+            })
+        );
+        let tts = dependency("tts_daemon");
+        assert_eq!(tts["ready"], false);
+        assert_eq!(tts["required"], true);
+        assert_eq!(tts["detail"], "not responding");
+        // The ASR daemon is reported but does not gate readiness
+        assert_eq!(dependency("asr_daemon")["required"], false);
+        assert_eq!(dependency("kv_cache")["ready"], true);
+    }
+}
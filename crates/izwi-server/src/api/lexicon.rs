@@ -0,0 +1,45 @@
+//! Pronunciation lexicon endpoints
+
+use axum::{extract::State, Json};
+use izwi_core::lexicon::{Lexicon, Pronunciation};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Lexicon entries currently applied to TTS requests
+#[derive(Debug, Serialize)]
+pub struct LexiconResponse {
+    pub fingerprint: String,
+    pub entries: BTreeMap<String, Pronunciation>,
+}
+
+impl From<&Lexicon> for LexiconResponse {
+    fn from(lexicon: &Lexicon) -> Self {
+        Self {
+            fingerprint: lexicon.fingerprint().to_string(),
+            entries: lexicon
+                .entries()
+                .map(|(word, p)| (word.to_string(), p.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// List the lexicon entries
+pub async fn get_lexicon(State(state): State<AppState>) -> Json<LexiconResponse> {
+    let engine = &state.engine;
+    Json(LexiconResponse::from(engine.lexicon().as_ref()))
+}
+
+/// Re-read the lexicon file after it was edited
+pub async fn reload_lexicon(
+    State(state): State<AppState>,
+) -> Result<Json<LexiconResponse>, ApiError> {
+    let engine = &state.engine;
+    let entries = engine.reload_lexicon()?;
+    info!("Lexicon reloaded with {} entries", entries);
+    Ok(Json(LexiconResponse::from(engine.lexicon().as_ref())))
+}
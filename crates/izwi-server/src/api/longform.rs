@@ -0,0 +1,235 @@
+//! Long-form transcription of recordings too long for one ASR pass
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::Engine;
+use futures::{StreamExt, TryStreamExt};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use super::asr::{is_daemon_running, send_daemon_message, DaemonAudio, TranscribeRequest};
+use super::tts::subtitle_response;
+use crate::auth::ApiKeyIdentity;
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audio::{decode_wav, plan_segments, AudioEncoder, AudioFormat, SegmentConfig};
+use izwi_core::engine::JobStatus;
+use izwi_core::inference::asr_bridge::validate_hotwords;
+use izwi_core::language::AUTO_LANGUAGE;
+use izwi_core::longform::{LongformSegment, LongformStatus, LongformTranscript};
+use izwi_core::text::subtitles::timed_cues;
+use izwi_core::text::SubtitleFormat;
+use izwi_core::usage::Usage;
+use izwi_core::ErrorCode;
+
+/// Start transcribing a long recording in the background.
+///
+/// The recording is cut into segments at pauses and the segments are
+/// transcribed in parallel; poll the returned job for progress and the
+/// merged transcript.
+pub async fn start_longform(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Json(request): Json<TranscribeRequest>,
+) -> Result<(StatusCode, Json<LongformStatus>), ApiError> {
+    if !is_daemon_running(&state).await {
+        return Err(ApiError::daemon_unavailable(
+            "ASR daemon not running. Please start it first.",
+        ));
+    }
+    if request.diarize || request.constraint.is_some() || request.logprobs.is_some() {
+        return Err(ApiError::bad_request(
+            "diarize, constraint and logprobs are not available for long-form transcription",
+        ));
+    }
+    if request.response_format.is_some() {
+        return Err(ApiError::bad_request(
+            "Pass response_format when fetching a long-form job",
+        ));
+    }
+    validate_hotwords(&request.hotwords)?;
+
+    let api_key = identity.map(|Extension(ApiKeyIdentity(name))| name);
+    let bytes = request
+        .audio(&state, api_key.as_deref())
+        .await?
+        .bytes()
+        .await?;
+    let (samples, sample_rate) = decode_wav(&bytes).map_err(|e| {
+        ApiError::with_code(
+            ErrorCode::InvalidAudio,
+            format!("Long-form transcription needs WAV or raw PCM audio: {}", e),
+        )
+    })?;
+    drop(bytes);
+
+    let config = state.longform.config();
+    let segments = plan_segments(
+        &samples,
+        sample_rate,
+        &SegmentConfig {
+            max_segment_ms: (config.max_segment_secs * 1000.0) as u32,
+            overlap_ms: (config.overlap_secs * 1000.0) as u32,
+            ..Default::default()
+        },
+    );
+    let job = state.longform.create(api_key.as_deref(), segments.len());
+    info!(
+        "Long-form job {}: {:.0}s of audio in {} segments",
+        job.job_id,
+        samples.len() as f64 / sample_rate as f64,
+        segments.len()
+    );
+
+    let job_id = job.job_id.clone();
+    tokio::spawn(async move {
+        let result =
+            transcribe_segments(&state, &job_id, &request, &samples, sample_rate, &segments).await;
+        match result {
+            Ok(transcript) => {
+                let usage = Usage::default().with_audio(0, transcript.duration_secs);
+                state
+                    .usage
+                    .record(&job_id, api_key.as_deref(), "asr", usage);
+                state.longform.complete(&job_id, transcript);
+            }
+            Err(e) => {
+                warn!("Long-form job {} failed: {}", job_id, e.message);
+                state.longform.fail(&job_id, e.message);
+            }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Query for fetching a long-form job
+#[derive(Debug, Deserialize)]
+pub struct LongformQuery {
+    /// `srt` or `vtt` to fetch the finished transcript as subtitles
+    #[serde(default)]
+    pub response_format: Option<SubtitleFormat>,
+}
+
+/// Progress of a long-form job, with the transcript once completed
+pub async fn get_longform(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+    Query(query): Query<LongformQuery>,
+) -> Result<Response, ApiError> {
+    let owner = identity
+        .as_ref()
+        .map(|Extension(ApiKeyIdentity(name))| name.as_str());
+    let job = state.longform.get(&id, owner)?;
+    let Some(format) = query.response_format else {
+        return Ok(Json(job).into_response());
+    };
+    let transcript = match (job.status, job.transcript) {
+        (JobStatus::Completed, Some(transcript)) => transcript,
+        (JobStatus::Failed, _) => {
+            return Err(ApiError::conflict(format!(
+                "Job {} failed: {}",
+                id,
+                job.error.unwrap_or_default()
+            )))
+        }
+        _ => {
+            return Err(ApiError::conflict(format!(
+                "Job {} has not completed yet",
+                id
+            )))
+        }
+    };
+    let language = match transcript.translation {
+        Some(_) => "English",
+        None => transcript.language.as_deref().unwrap_or(AUTO_LANGUAGE),
+    };
+    let segmenter = state.engine.segmenters().get(language);
+    let cues: Vec<_> = transcript
+        .segments
+        .iter()
+        .flat_map(|segment| {
+            let text = segment.translation.as_ref().unwrap_or(&segment.text);
+            timed_cues(
+                text,
+                segment.start_secs..segment.end_secs,
+                None,
+                segmenter.as_ref(),
+            )
+        })
+        .collect();
+    Ok(subtitle_response(&cues, format, &id))
+}
+
+/// Transcribe `segments` of `samples`, `concurrency` at a time, and merge
+/// the transcripts in order
+async fn transcribe_segments(
+    state: &AppState,
+    job_id: &str,
+    request: &TranscribeRequest,
+    samples: &[f32],
+    sample_rate: u32,
+    segments: &[Range<usize>],
+) -> Result<LongformTranscript, ApiError> {
+    let start_time = Instant::now();
+    let encoder = AudioEncoder::new(sample_rate, 1);
+    let secs = |sample: usize| sample as f64 / sample_rate as f64;
+    let profanity = state.engine.profanity();
+
+    let transcribed: Vec<(LongformSegment, Option<String>)> =
+        futures::stream::iter(segments.iter().cloned())
+            .map(|range| {
+                let encoder = &encoder;
+                async move {
+                    let wav = encoder.encode(&samples[range.clone()], AudioFormat::Wav)?;
+                    let audio = DaemonAudio::Inline(Cow::Owned(
+                        base64::engine::general_purpose::STANDARD.encode(wav),
+                    ));
+                    let response =
+                        send_daemon_message(state, &request.message("transcribe", &audio)).await?;
+                    if let Some(error) = response.get("error").and_then(|v| v.as_str()) {
+                        return Err(ApiError::internal(error.to_string()));
+                    }
+                    state.longform.advance(job_id);
+                    let field = |name: &str| {
+                        response
+                            .get(name)
+                            .and_then(|v| v.as_str())
+                            .map(String::from)
+                    };
+                    let segment = LongformSegment {
+                        start_secs: secs(range.start),
+                        end_secs: secs(range.end),
+                        text: request.clean(profanity, field("transcription").unwrap_or_default()),
+                        translation: field("translation").map(|t| request.clean(profanity, t)),
+                    };
+                    Ok((segment, field("language")))
+                }
+            })
+            .buffered(state.longform.config().concurrency)
+            .try_collect()
+            .await?;
+
+    let language = transcribed
+        .iter()
+        .find_map(|(_, language)| language.clone());
+    let segments = transcribed
+        .into_iter()
+        .map(|(segment, _)| segment)
+        .collect();
+    let transcript = LongformTranscript::merge(segments, language, secs(samples.len()));
+    info!(
+        "Long-form job {} finished in {:.1}s",
+        job_id,
+        start_time.elapsed().as_secs_f64()
+    );
+    Ok(transcript)
+}
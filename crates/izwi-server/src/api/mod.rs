@@ -1,62 +1,186 @@
 //! API routes and handlers
 
+mod admin;
 mod asr;
+mod chat;
 mod daemon;
+mod debug;
+mod dialogue;
 mod health;
+mod lexicon;
+mod longform;
 mod models;
+mod requests;
+mod tenants;
 mod tts;
+mod twilio;
+mod uploads;
+mod usage;
 
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    http::HeaderValue,
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
-use tower_http::cors::{Any, CorsLayer};
+use izwi_core::ServerConfig;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
+use tracing::warn;
 
+use crate::audit;
+use crate::auth;
+use crate::error;
 use crate::state::AppState;
 
+/// Build the CORS layer from server settings (`None` when disabled)
+fn cors_layer(config: &ServerConfig) -> Option<CorsLayer> {
+    if !config.cors_enabled {
+        return None;
+    }
+
+    let allow_origin =
+        if config.cors_origins.is_empty() || config.cors_origins.iter().any(|o| o == "*") {
+            AllowOrigin::from(Any)
+        } else {
+            let origins: Vec<HeaderValue> = config
+                .cors_origins
+                .iter()
+                .filter_map(|origin| match HeaderValue::from_str(origin) {
+                    Ok(value) => Some(value),
+                    Err(_) => {
+                        warn!("Ignoring invalid CORS origin: {}", origin);
+                        None
+                    }
+                })
+                .collect();
+            AllowOrigin::list(origins)
+        };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    )
+}
+
 /// Create the main API router
-pub fn create_router(state: AppState) -> Router {
+pub fn create_router(state: AppState, config: &ServerConfig) -> Router {
+    // Room for the largest audio upload the engine accepts, base64-encoded
+    let body_limit = state.engine.config().limits.max_body_bytes();
     let api_routes = Router::new()
-        // Health check
-        .route("/health", get(health::health_check))
         // Daemon management
         .route("/daemon/status", get(daemon::get_status))
+        .route("/daemon/health", get(daemon::get_health))
         .route("/daemon/start", post(daemon::start_daemon))
         .route("/daemon/stop", post(daemon::stop_daemon))
         .route("/daemon/preload", post(daemon::preload_model))
         // Model management
         .route("/models", get(models::list_models))
+        .route("/models/presets", get(models::list_presets))
+        .route("/models/discover", post(models::discover_models))
         .route("/models/:variant/download", post(models::download_model))
         .route("/models/:variant/load", post(models::load_model))
         .route("/models/:variant/unload", post(models::unload_model))
+        .route("/models/:variant/swap", post(models::swap_model))
+        .route("/models/:variant/repair", post(models::repair_model))
+        .route("/models/:variant/quantize", post(models::quantize_model))
         .route(
             "/models/:variant",
             get(models::get_model_info).delete(models::delete_model),
         )
+        // Queued request management
+        .route("/requests/interrupted", get(requests::list_interrupted))
+        .route("/requests/:id", get(requests::get_request))
+        .route("/requests/:id/priority", post(requests::set_priority))
+        .route("/requests/:id/latency", get(requests::get_latency))
+        .route("/requests/:id/result", get(requests::get_result))
+        .route(
+            "/debug/profile",
+            get(debug::get_profile).delete(debug::reset_profile),
+        )
+        .route("/debug/cache", get(debug::get_cache_stats))
+        .route("/admin/warmup", post(admin::warmup))
+        .route("/admin/stats", get(admin::get_stats))
+        .route("/admin/audit", get(admin::get_audit))
+        .route("/usage", get(usage::get_usage))
         // TTS generation (Qwen3-TTS)
+        .route("/tts", post(tts::generate))
         .route("/tts/generate", post(tts::generate))
-        .route("/tts/stream", post(tts::generate_stream))
+        .route(
+            "/tts/stream",
+            post(tts::generate_stream).layer(middleware::from_fn(auth::stream_route)),
+        )
+        .route("/tts/dialogue", post(dialogue::generate_dialogue))
+        .route("/tts/outputs/:id/range", get(tts::get_range))
+        // Saved voices and tenant data
+        .route(
+            "/voices",
+            get(tenants::list_voices).post(tenants::save_voice),
+        )
+        .route("/voices/:name", delete(tenants::delete_voice))
+        .route("/chat/sessions", post(chat::create_session))
+        .route(
+            "/chat/sessions/:id",
+            get(chat::get_session).delete(chat::delete_session),
+        )
+        .route("/chat/sessions/:id/turns", post(chat::turn))
+        .route("/lexicon", get(lexicon::get_lexicon))
+        .route("/lexicon/reload", post(lexicon::reload_lexicon))
+        .route("/tenants/:tenant/rotate-key", post(tenants::rotate_key))
+        .route("/tenants/:tenant", delete(tenants::purge_tenant))
         // Qwen3-ASR endpoints
         .route("/asr/status", get(asr::status))
         .route("/asr/start", post(asr::start_daemon))
         .route("/asr/stop", post(asr::stop_daemon))
         .route("/asr/transcribe", post(asr::transcribe))
-        .route("/asr/transcribe/stream", post(asr::transcribe_stream));
+        .route(
+            "/asr/transcribe/stream",
+            post(asr::transcribe_stream).layer(middleware::from_fn(auth::stream_route)),
+        )
+        .route("/asr/longform", post(longform::start_longform))
+        .route("/asr/longform/:id", get(longform::get_longform))
+        // Chunked uploads of audio too large for a JSON body
+        .route("/uploads", post(uploads::create_upload))
+        .route(
+            "/uploads/:id",
+            get(uploads::get_upload).delete(uploads::delete_upload),
+        )
+        .route("/uploads/:id/parts/:part", put(uploads::put_part))
+        .route("/uploads/:id/complete", post(uploads::complete_upload))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit::record_request,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
+        // Health check stays open for probes
+        .route("/health", get(health::health_check))
+        // Twilio can't set headers, so the key comes in the stream's start
+        // message instead
+        .route("/integrations/twilio/media", get(twilio::media_stream));
 
-    Router::new()
+    let mut router = Router::new()
+        // Orchestrator probes
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
         .nest("/api/v1", api_routes)
         // Serve static files for UI
         .fallback_service(
             tower_http::services::ServeDir::new("ui/dist")
                 .fallback(tower_http::services::ServeFile::new("ui/dist/index.html")),
         )
-        .layer(TraceLayer::new_for_http())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
-        .with_state(state)
+        .layer(DefaultBodyLimit::max(body_limit))
+        .layer(middleware::from_fn(error::request_id))
+        .layer(TraceLayer::new_for_http());
+
+    if let Some(cors) = cors_layer(config) {
+        router = router.layer(cors);
+    }
+
+    router.with_state(state)
 }
@@ -1,27 +1,61 @@
 //! Model management API endpoints
 
 use axum::{
-    extract::{Path, State},
-    Json,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
 };
-use serde::Serialize;
+use futures::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
 use tracing::info;
 
+use crate::auth::{require_admin, AdminScope};
 use crate::error::ApiError;
 use crate::state::AppState;
-use izwi_core::{ModelInfo, ModelVariant};
+use izwi_core::engine::SwapReport;
+use izwi_core::model::{
+    DiscoveredModel, DownloadProgress, Quantization, QuantizeReport, RepairReport,
+};
+use izwi_core::{GenerationPreset, ModelInfo, ModelVariant};
 
 /// Response for model list
 #[derive(Serialize)]
 pub struct ModelsResponse {
     pub models: Vec<ModelInfo>,
+    /// Model folders found on disk
+    pub discovered: Vec<DiscoveredModel>,
 }
 
 /// List all available models
 pub async fn list_models(State(state): State<AppState>) -> Result<Json<ModelsResponse>, ApiError> {
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
     let models = engine.list_models().await;
-    Ok(Json(ModelsResponse { models }))
+    let discovered = engine.model_manager().discovered_models().await;
+    Ok(Json(ModelsResponse { models, discovered }))
+}
+
+/// List the sampling presets TTS requests can name
+pub async fn list_presets(
+    State(state): State<AppState>,
+) -> Json<BTreeMap<String, GenerationPreset>> {
+    Json(state.engine.presets())
+}
+
+/// Rescan the model directories for model folders
+pub async fn discover_models(
+    State(state): State<AppState>,
+) -> Result<Json<ModelsResponse>, ApiError> {
+    let engine = &state.engine;
+    let discovered = engine.model_manager().rescan().await;
+    let models = engine.list_models().await;
+    Ok(Json(ModelsResponse { models, discovered }))
 }
 
 /// Get info for a specific model
@@ -30,7 +64,7 @@ pub async fn get_model_info(
     Path(variant): Path<String>,
 ) -> Result<Json<ModelInfo>, ApiError> {
     let variant = parse_variant(&variant)?;
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
     let info = engine
         .model_manager()
@@ -48,33 +82,111 @@ pub struct DownloadResponse {
     pub message: String,
 }
 
-/// Download a model from HuggingFace
+/// Header carrying a HuggingFace token for one download
+pub const HF_TOKEN_HEADER: &str = "X-HF-Token";
+
+/// Download progress event sent over SSE
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    Progress(DownloadProgress),
+    Completed { message: String },
+    Error { error: String },
+}
+
+/// Download a model from HuggingFace.
+///
+/// Clients sending `Accept: text/event-stream` get progress events as the
+/// download runs; others get a single response once it finishes.
 pub async fn download_model(
     State(state): State<AppState>,
     Path(variant): Path<String>,
-) -> Result<Json<DownloadResponse>, ApiError> {
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     let variant = parse_variant(&variant)?;
+    let token = headers
+        .get(HF_TOKEN_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .map(|token| token.trim().to_string())
+                .map_err(|_| ApiError::bad_request(format!("Invalid {} header", HF_TOKEN_HEADER)))
+        })
+        .transpose()?
+        .filter(|token| !token.is_empty());
     info!("Downloading model: {}", variant);
 
-    let engine = state.engine.read().await;
-    engine.download_model(variant).await?;
+    let wants_events = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if wants_events {
+        return Ok(download_events(state, variant, token).into_response());
+    }
+
+    let engine = &state.engine;
+    engine.download_model_with_token(variant, token).await?;
 
     Ok(Json(DownloadResponse {
         status: "completed",
         message: format!("Model {} downloaded successfully", variant),
-    }))
+    })
+    .into_response())
+}
+
+/// Run a download in the background and stream its progress. The download
+/// carries on if the client disconnects.
+fn download_events(
+    state: AppState,
+    variant: ModelVariant,
+    token: Option<String>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (progress_tx, mut progress_rx) = mpsc::channel(32);
+    let download = tokio::spawn(async move {
+        let engine = &state.engine;
+        engine
+            .model_manager()
+            .download_model_with_progress(variant, token, progress_tx)
+            .await
+    });
+
+    let stream = async_stream::stream! {
+        while let Some(progress) = progress_rx.recv().await {
+            yield Ok(Event::default().json_data(DownloadEvent::Progress(progress)).unwrap());
+        }
+        let event = match download.await {
+            Ok(Ok(_)) => DownloadEvent::Completed {
+                message: format!("Model {} downloaded successfully", variant),
+            },
+            Ok(Err(e)) => DownloadEvent::Error { error: e.to_string() },
+            Err(e) => DownloadEvent::Error { error: e.to_string() },
+        };
+        yield Ok(Event::default().json_data(event).unwrap());
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Weight precision selected when loading
+#[derive(Deserialize)]
+pub struct LoadQuery {
+    pub quantization: Option<Quantization>,
 }
 
 /// Load a model into memory
 pub async fn load_model(
     State(state): State<AppState>,
     Path(variant): Path<String>,
+    Query(query): Query<LoadQuery>,
 ) -> Result<Json<DownloadResponse>, ApiError> {
     let variant = parse_variant(&variant)?;
     info!("Loading model: {}", variant);
 
-    let mut engine = state.engine.write().await;
-    engine.load_model(variant).await?;
+    let engine = &state.engine;
+    match query.quantization {
+        Some(quant) => engine.load_model_with(variant, quant).await?,
+        None => engine.load_model(variant).await?,
+    }
 
     Ok(Json(DownloadResponse {
         status: "loaded",
@@ -82,16 +194,34 @@ pub async fn load_model(
     }))
 }
 
-/// Unload a model from memory
+/// Switch the engine to another model without dropping in-flight requests
+/// (admin keys only)
+pub async fn swap_model(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+    Path(variant): Path<String>,
+) -> Result<Json<SwapReport>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    let variant = parse_variant(&variant)?;
+    info!("Swapping to model: {}", variant);
+
+    let report = state.engine_core.swap_model(variant).await?;
+
+    Ok(Json(report))
+}
+
+/// Unload a model from memory (admin keys only)
 pub async fn unload_model(
     State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
     Path(variant): Path<String>,
 ) -> Result<Json<DownloadResponse>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
     let variant = parse_variant(&variant)?;
     info!("Unloading model: {}", variant);
 
-    let engine = state.engine.read().await;
-    engine.model_manager().unload_model(variant).await?;
+    let engine = &state.engine;
+    engine.unload_model(variant).await?;
 
     Ok(Json(DownloadResponse {
         status: "unloaded",
@@ -99,15 +229,54 @@ pub async fn unload_model(
     }))
 }
 
-/// Delete a downloaded model from disk
+/// Verify a downloaded model and re-download missing or corrupt files
+pub async fn repair_model(
+    State(state): State<AppState>,
+    Path(variant): Path<String>,
+) -> Result<Json<RepairReport>, ApiError> {
+    let variant = parse_variant(&variant)?;
+    info!("Repairing model: {}", variant);
+
+    let engine = &state.engine;
+    let report = engine.model_manager().repair_model(variant).await?;
+
+    Ok(Json(report))
+}
+
+/// Quantization request
+#[derive(Deserialize)]
+pub struct QuantizeRequest {
+    pub quantization: Quantization,
+}
+
+/// Write a quantized copy of a downloaded model (admin keys only)
+pub async fn quantize_model(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+    Path(variant): Path<String>,
+    Json(request): Json<QuantizeRequest>,
+) -> Result<Json<QuantizeReport>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    let variant = parse_variant(&variant)?;
+    info!("Quantizing model {} to {}", variant, request.quantization);
+
+    let engine = &state.engine;
+    let report = engine.quantize_model(variant, request.quantization).await?;
+
+    Ok(Json(report))
+}
+
+/// Delete a downloaded model from disk (admin keys only)
 pub async fn delete_model(
     State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
     Path(variant): Path<String>,
 ) -> Result<Json<DownloadResponse>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
     let variant = parse_variant(&variant)?;
     info!("Deleting model: {}", variant);
 
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
 
     // First unload if loaded
     let _ = engine.model_manager().unload_model(variant).await;
@@ -122,7 +291,7 @@ pub async fn delete_model(
 }
 
 /// Parse model variant from string
-fn parse_variant(s: &str) -> Result<ModelVariant, ApiError> {
+pub(crate) fn parse_variant(s: &str) -> Result<ModelVariant, ApiError> {
     // Exact matches for HuggingFace model names (used in URLs)
     match s {
         "Qwen3-TTS-12Hz-0.6B-Base" => return Ok(ModelVariant::Qwen3Tts12Hz06BBase),
@@ -134,12 +303,24 @@ fn parse_variant(s: &str) -> Result<ModelVariant, ApiError> {
         "LFM2-Audio-1.5B" => return Ok(ModelVariant::Lfm2Audio15B),
         "Qwen3-ASR-0.6B" => return Ok(ModelVariant::Qwen3Asr06B),
         "Qwen3-ASR-1.7B" => return Ok(ModelVariant::Qwen3Asr17B),
+        "Qwen3-TTS-12Hz-0.6B-Base-GGUF" => return Ok(ModelVariant::Qwen3Tts12Hz06BBaseGguf),
+        "LFM2-Audio-1.5B-GGUF" => return Ok(ModelVariant::Lfm2Audio15BGguf),
         _ => {}
     }
 
     // Fallback: normalize and try pattern matching
     let normalized = s.to_lowercase().replace("-", "_").replace(".", "");
 
+    // GGUF builds (check before their safetensors counterparts)
+    if normalized.contains("gguf") {
+        if normalized.contains("lfm2") {
+            return Ok(ModelVariant::Lfm2Audio15BGguf);
+        }
+        if normalized.contains("06b") && normalized.contains("base") {
+            return Ok(ModelVariant::Qwen3Tts12Hz06BBaseGguf);
+        }
+    }
+
     // Qwen3-ASR models (check before TTS to avoid conflicts)
     if normalized.contains("qwen3") && normalized.contains("asr") {
         if normalized.contains("06b") {
@@ -177,7 +358,7 @@ fn parse_variant(s: &str) -> Result<ModelVariant, ApiError> {
     }
 
     Err(ApiError::bad_request(format!(
-        "Unknown model variant: {}. Valid variants: Qwen3-TTS-12Hz-0.6B-Base, Qwen3-TTS-12Hz-0.6B-CustomVoice, Qwen3-TTS-12Hz-1.7B-Base, Qwen3-TTS-12Hz-1.7B-CustomVoice, Qwen3-TTS-12Hz-1.7B-VoiceDesign, Qwen3-TTS-Tokenizer-12Hz, LFM2-Audio-1.5B, Qwen3-ASR-0.6B, Qwen3-ASR-1.7B",
+        "Unknown model variant: {}. Valid variants: Qwen3-TTS-12Hz-0.6B-Base, Qwen3-TTS-12Hz-0.6B-CustomVoice, Qwen3-TTS-12Hz-1.7B-Base, Qwen3-TTS-12Hz-1.7B-CustomVoice, Qwen3-TTS-12Hz-1.7B-VoiceDesign, Qwen3-TTS-Tokenizer-12Hz, LFM2-Audio-1.5B, Qwen3-ASR-0.6B, Qwen3-ASR-1.7B, Qwen3-TTS-12Hz-0.6B-Base-GGUF, LFM2-Audio-1.5B-GGUF",
         s
     )))
 }
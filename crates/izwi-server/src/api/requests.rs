@@ -0,0 +1,430 @@
+//! Queued request management endpoints
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Redirect, Response},
+    Extension, Json,
+};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+
+use super::tenants::tenant_id;
+use super::tts::parse_format;
+use crate::auth::{ApiKeyIdentity, KeyTenant};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audio::{AudioEncoder, AudioFormat, SpooledAudio};
+use izwi_core::engine::{AuditEntry, JobResult, JobStatus, LatencyReport, Priority};
+use izwi_core::journal::JournalEntry;
+use izwi_core::RequestStatus;
+
+/// Read size when streaming a spooled or locally stored result
+const SPOOL_READ_BYTES: usize = 64 * 1024;
+
+/// Status of a request queued in the scheduling engine
+#[derive(Serialize)]
+pub struct QueuedRequest {
+    pub request_id: String,
+    pub status: RequestStatus,
+    pub audit: Vec<AuditEntry>,
+}
+
+/// Status of an asynchronous job
+#[derive(Serialize)]
+pub struct JobState {
+    pub request_id: String,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f32>,
+    /// Where the audio can be downloaded, when output storage gives it a URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Final or current status of a request.
+///
+/// Asynchronous jobs are reported until their results expire. Journaled
+/// requests are found even after a restart; without a journal only requests
+/// still held by the scheduling engine are known.
+pub async fn get_request(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    Path(request_id): Path<String>,
+) -> Result<Response, ApiError> {
+    if state.engine_core.result_store().contains(&request_id) {
+        let job = owned_job(&state, &headers, identity, key_tenant, &request_id)?;
+        let url = stored_url(&state, &job);
+        return Ok(Json(JobState {
+            request_id,
+            status: job.status,
+            error: job.error,
+            duration_secs: job
+                .audio
+                .map(|audio| audio.duration_secs)
+                .or_else(|| job.spooled.map(|spooled| spooled.duration_secs()))
+                .or_else(|| job.stored.map(|stored| stored.duration_secs))
+                .or_else(|| job.sealed.map(|sealed| sealed.duration_secs)),
+            url,
+        })
+        .into_response());
+    }
+
+    let journaled = state
+        .engine
+        .journal()
+        .and_then(|journal| journal.get(&request_id));
+    if let Some(entry) = journaled {
+        return Ok(Json(entry).into_response());
+    }
+
+    let (status, audit) = state
+        .engine_core
+        .request_audit(&request_id)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("Request {} not found", request_id)))?;
+    Ok(Json(QueuedRequest {
+        request_id,
+        status,
+        audit,
+    })
+    .into_response())
+}
+
+/// Result download query
+#[derive(Debug, Deserialize)]
+pub struct ResultQuery {
+    /// Output format (wav, raw_f32, raw_i16)
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Audio of a completed asynchronous job, or a redirect to it in output
+/// storage
+pub async fn get_result(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    Path(request_id): Path<String>,
+    Query(query): Query<ResultQuery>,
+) -> Result<Response, ApiError> {
+    let format = parse_format(query.format.as_deref().unwrap_or("wav"))?;
+    let job = owned_job(&state, &headers, identity, key_tenant, &request_id)?;
+    if let (Some(stored), Some(storage)) = (&job.stored, &state.storage) {
+        if format != AudioFormat::Wav {
+            return Err(ApiError::bad_request(format!(
+                "Request {} was published to output storage and is only available as wav",
+                request_id
+            )));
+        }
+        if let Some(url) = storage.url(&stored.key) {
+            return Ok(Redirect::temporary(&url).into_response());
+        }
+        if let Some(path) = storage.local_path(&stored.key) {
+            let file_len = tokio::fs::metadata(&path)
+                .await
+                .map_err(|_| {
+                    ApiError::not_found(format!("Audio of request {} was removed", request_id))
+                })?
+                .len();
+            return stream_wav(path, file_len, (), request_id).await;
+        }
+    }
+    if let (JobStatus::Completed, Some(spooled)) = (job.status, job.spooled) {
+        return spooled_result(spooled, format, request_id).await;
+    }
+    if let (JobStatus::Completed, Some(_)) = (job.status, job.sealed) {
+        let audio = state
+            .engine
+            .output_store()
+            .get(&request_id, job.tenant.as_deref())?
+            .ok_or_else(|| {
+                ApiError::not_found(format!("Audio of request {} has expired", request_id))
+            })?;
+        return audio_response(&audio.samples, audio.sample_rate, format, request_id);
+    }
+    let audio = match (job.status, job.audio) {
+        (JobStatus::Completed, Some(audio)) => audio,
+        (JobStatus::Failed, _) => {
+            return Err(ApiError::conflict(format!(
+                "Request {} failed: {}",
+                request_id,
+                job.error.unwrap_or_default()
+            )))
+        }
+        _ => {
+            return Err(ApiError::conflict(format!(
+                "Request {} has not completed yet",
+                request_id
+            )))
+        }
+    };
+
+    audio_response(&audio.samples, audio.sample_rate, format, request_id)
+}
+
+/// Job `request_id` if it was submitted by the caller's key and tenant.
+/// Other callers' jobs are not found.
+fn owned_job(
+    state: &AppState,
+    headers: &HeaderMap,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    request_id: &String,
+) -> Result<JobResult, ApiError> {
+    let tenant = tenant_id(headers, key_tenant.as_ref())?;
+    let client_id = identity.map(|Extension(ApiKeyIdentity(name))| name);
+    state
+        .engine_core
+        .result_store()
+        .get_owned(request_id, client_id.as_deref(), tenant.as_deref())
+        .ok_or_else(|| ApiError::not_found(format!("No result for request {}", request_id)))
+}
+
+/// Encoded audio of a finished job
+fn audio_response(
+    samples: &[f32],
+    sample_rate: u32,
+    format: AudioFormat,
+    request_id: String,
+) -> Result<Response, ApiError> {
+    let bytes = AudioEncoder::new(sample_rate, 1).encode(samples, format)?;
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                AudioEncoder::content_type(format).to_string(),
+            ),
+            (header::HeaderName::from_static("x-request-id"), request_id),
+        ],
+        Body::from(bytes),
+    )
+        .into_response())
+}
+
+/// Download URL of a job whose audio went to output storage
+fn stored_url(state: &AppState, job: &JobResult) -> Option<String> {
+    let stored = job.stored.as_ref()?;
+    state
+        .storage
+        .as_ref()
+        .and_then(|storage| storage.url(&stored.key))
+}
+
+/// Stream a spooled job's WAV file without loading it into memory. The
+/// handle is held until the download ends so the file outlives it even if
+/// the result expires meanwhile.
+async fn spooled_result(
+    spooled: Arc<SpooledAudio>,
+    format: AudioFormat,
+    request_id: String,
+) -> Result<Response, ApiError> {
+    if format != AudioFormat::Wav {
+        return Err(ApiError::bad_request(format!(
+            "Request {} was spooled to disk and is only available as wav",
+            request_id
+        )));
+    }
+    let path = spooled.path.clone();
+    let file_len = spooled.file_len;
+    stream_wav(path, file_len, spooled, request_id).await
+}
+
+/// Stream the WAV file at `path`, holding `guard` until the download ends
+async fn stream_wav(
+    path: PathBuf,
+    file_len: u64,
+    guard: impl Send + 'static,
+    request_id: String,
+) -> Result<Response, ApiError> {
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .map_err(izwi_core::Error::from)?;
+
+    let stream = async_stream::stream! {
+        let _guard = guard;
+        let mut buf = vec![0u8; SPOOL_READ_BYTES];
+        loop {
+            match file.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => yield Ok(Bytes::copy_from_slice(&buf[..n])),
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+    };
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                AudioEncoder::content_type(AudioFormat::Wav).to_string(),
+            ),
+            (header::CONTENT_LENGTH, file_len.to_string()),
+            (header::HeaderName::from_static("x-request-id"), request_id),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Requests that were in progress when the server last stopped
+pub async fn list_interrupted(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<JournalEntry>>, ApiError> {
+    let engine = &state.engine;
+    let journal = engine
+        .journal()
+        .ok_or_else(|| ApiError::not_found("Request journal is not enabled"))?;
+    Ok(Json(journal.interrupted()))
+}
+
+/// Priority change request body
+#[derive(Debug, Deserialize)]
+pub struct PriorityUpdate {
+    /// New priority class (low, normal, high, critical)
+    pub priority: Priority,
+    /// Free-form reason recorded in the audit trail
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Priority change response
+#[derive(Serialize)]
+pub struct PriorityResponse {
+    pub request_id: String,
+    pub previous_priority: Priority,
+    pub priority: Priority,
+    pub status: RequestStatus,
+    pub audit: Vec<AuditEntry>,
+}
+
+/// Change the priority of a waiting request
+pub async fn set_priority(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+    Json(update): Json<PriorityUpdate>,
+) -> Result<Json<PriorityResponse>, ApiError> {
+    let previous_priority = state
+        .engine_core
+        .set_request_priority(&request_id, update.priority, update.reason)
+        .await?;
+
+    let (status, audit) = state
+        .engine_core
+        .request_audit(&request_id)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("Request {} not found", request_id)))?;
+
+    Ok(Json(PriorityResponse {
+        request_id,
+        previous_priority,
+        priority: update.priority,
+        status,
+        audit,
+    }))
+}
+
+/// Latency report query
+#[derive(Debug, Deserialize)]
+pub struct LatencyQuery {
+    /// `json` (default) or `text`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Explain where a running or recently finished request spent its time
+pub async fn get_latency(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+    Query(query): Query<LatencyQuery>,
+) -> Result<Response, ApiError> {
+    let report: LatencyReport = state
+        .engine_core
+        .latency_report(&request_id)
+        .await
+        .ok_or_else(|| {
+            ApiError::not_found(format!("No latency trace for request {}", request_id))
+        })?;
+
+    match query.format.as_deref() {
+        None | Some("json") => Ok(Json(report).into_response()),
+        Some("text") => Ok(report.to_string().into_response()),
+        Some(other) => Err(ApiError::bad_request(format!(
+            "Unknown report format: {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{Request, StatusCode};
+    use izwi_core::engine::SealedResult;
+    use izwi_core::{ApiKeyConfig, AuthConfig, ServerConfig};
+    use tower::ServiceExt;
+
+    fn key(name: &str) -> ApiKeyConfig {
+        ApiKeyConfig {
+            name: name.into(),
+            key: format!("{}-secret", name),
+            requests_per_minute: None,
+            max_concurrent_streams: None,
+            max_priority: None,
+            tenant: Some(name.into()),
+            admin: false,
+        }
+    }
+
+    #[test]
+    fn test_jobs_are_only_visible_to_their_owner() {
+        let auth = AuthConfig {
+            api_keys: vec![key("acme"), key("globex")],
+            ..Default::default()
+        };
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&auth, Default::default());
+        let router = crate::api::create_router(state.clone(), &ServerConfig::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let status = |uri: String, key: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}-secret", key))
+                .body(Body::empty())
+                .unwrap();
+            runtime
+                .block_on(router.clone().oneshot(request))
+                .unwrap()
+                .status()
+        };
+
+        // A tenant job's audio is kept in the encrypted output store
+        let id = "job-1".to_string();
+        let results = state.engine_core.result_store();
+        results.submit(&id, Some("acme"), Some("acme"));
+        state
+            .engine
+            .output_store()
+            .insert(&id, Some("acme"), &[0.0; 2400], 24000)
+            .unwrap();
+        results.complete_sealed(&id, SealedResult { duration_secs: 0.1 });
+
+        let job = format!("/api/v1/requests/{}", id);
+        let result = format!("/api/v1/requests/{}/result", id);
+        assert_eq!(status(job.clone(), "acme"), StatusCode::OK);
+        assert_eq!(status(result.clone(), "acme"), StatusCode::OK);
+        assert_eq!(status(job, "globex"), StatusCode::NOT_FOUND);
+        assert_eq!(status(result, "globex"), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,248 @@
+//! Tenant-scoped voice storage and key management endpoints
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{require_admin, AdminScope, KeyTenant};
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::tenant::validate_tenant;
+use izwi_core::voice::SavedVoice;
+
+/// Header naming the tenant a request acts for
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+
+/// Tenant a request acts for (`None` for single-tenant use).
+///
+/// With authentication on it is the tenant of the API key, and a header
+/// naming any other tenant is refused. Without authentication the header
+/// names it.
+pub fn tenant_id(
+    headers: &HeaderMap,
+    key: Option<&Extension<KeyTenant>>,
+) -> Result<Option<String>, ApiError> {
+    let requested = match headers.get(TENANT_HEADER) {
+        Some(value) => {
+            let tenant = value
+                .to_str()
+                .map_err(|_| ApiError::bad_request(format!("Invalid {} header", TENANT_HEADER)))?;
+            validate_tenant(tenant)?;
+            Some(tenant.to_string())
+        }
+        None => None,
+    };
+    let Some(Extension(KeyTenant(tenant))) = key else {
+        return Ok(requested);
+    };
+    match requested {
+        Some(requested) if tenant.as_ref() != Some(&requested) => Err(ApiError::forbidden(
+            format!("This API key may not act for tenant '{}'", requested),
+        )),
+        _ => Ok(tenant.clone()),
+    }
+}
+
+/// Save voice request body
+#[derive(Debug, Deserialize)]
+pub struct SaveVoiceRequest {
+    /// Name used as `saved_voice` in TTS requests
+    pub name: String,
+    /// Reference audio (base64)
+    pub reference_audio: String,
+    /// Transcript of the reference audio
+    pub reference_text: String,
+}
+
+#[derive(Serialize)]
+pub struct VoiceList {
+    pub voices: Vec<String>,
+}
+
+/// List the caller's saved voices
+pub async fn list_voices(
+    State(state): State<AppState>,
+    key: Option<Extension<KeyTenant>>,
+    headers: HeaderMap,
+) -> Result<Json<VoiceList>, ApiError> {
+    let tenant = tenant_id(&headers, key.as_ref())?;
+    let voices = state.engine.voice_store().list(tenant.as_deref());
+    Ok(Json(VoiceList { voices }))
+}
+
+/// Save a cloned voice for later requests
+pub async fn save_voice(
+    State(state): State<AppState>,
+    key: Option<Extension<KeyTenant>>,
+    headers: HeaderMap,
+    Json(req): Json<SaveVoiceRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let tenant = tenant_id(&headers, key.as_ref())?;
+    let voice = SavedVoice {
+        reference_audio: req.reference_audio,
+        reference_text: req.reference_text,
+    };
+    state
+        .engine
+        .voice_store()
+        .save(tenant.as_deref(), &req.name, &voice)?;
+    Ok(Json(serde_json::json!({ "name": req.name, "saved": true })))
+}
+
+/// Delete a saved voice
+pub async fn delete_voice(
+    State(state): State<AppState>,
+    key: Option<Extension<KeyTenant>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let tenant = tenant_id(&headers, key.as_ref())?;
+    let deleted = state.engine.voice_store().delete(tenant.as_deref(), &name);
+    if !deleted {
+        return Err(ApiError::not_found(format!("Voice {} not found", name)));
+    }
+    Ok(Json(serde_json::json!({ "name": name, "deleted": true })))
+}
+
+/// Key rotation request body
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateKeyRequest {
+    /// New base64 key; fetched from the key command or generated when unset
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Rotate a tenant's key and re-encrypt its stored data (admin keys only)
+pub async fn rotate_key(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+    Path(tenant): Path<String>,
+    body: Option<Json<RotateKeyRequest>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let version = state
+        .engine
+        .rotate_tenant_key(&tenant, req.key.as_deref())?;
+    Ok(Json(
+        serde_json::json!({ "tenant": tenant, "key_version": version }),
+    ))
+}
+
+/// Delete every stored output, voice and key of a tenant (admin keys only)
+pub async fn purge_tenant(
+    State(state): State<AppState>,
+    admin: Option<Extension<AdminScope>>,
+    Path(tenant): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    require_admin(&state.api_keys, admin)?;
+    validate_tenant(&tenant)?;
+    let (outputs, voices) = state.engine.purge_tenant(&tenant);
+    let cached = state
+        .engine_core
+        .output_cache()
+        .map_or(0, |cache| cache.purge_tenant(&tenant));
+    Ok(Json(serde_json::json!({
+        "tenant": tenant,
+        "outputs_deleted": outputs,
+        "voices_deleted": voices,
+        "cache_entries_deleted": cached
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use izwi_core::{ApiKeyConfig, AuthConfig, ServerConfig};
+    use tower::ServiceExt;
+
+    fn key(name: &str, tenant: &str, admin: bool) -> ApiKeyConfig {
+        ApiKeyConfig {
+            name: name.into(),
+            key: format!("{}-secret", name),
+            requests_per_minute: None,
+            max_concurrent_streams: None,
+            max_priority: None,
+            tenant: Some(tenant.into()),
+            admin,
+        }
+    }
+
+    async fn send(router: &axum::Router, request: Request<Body>) -> StatusCode {
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    fn request(method: &str, uri: &str, key: &str, tenant: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}-secret", key));
+        if let Some(tenant) = tenant {
+            builder = builder.header(TENANT_HEADER, tenant);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_keys_are_confined_to_their_tenant() {
+        let auth = AuthConfig {
+            api_keys: vec![
+                key("acme", "acme", false),
+                key("globex", "globex", false),
+                key("ops", "ops", true),
+            ],
+            ..Default::default()
+        };
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&auth, Default::default());
+        let router = crate::api::create_router(state, &ServerConfig::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let status = |request| runtime.block_on(send(&router, request));
+
+        // A key acts for its own tenant, with or without the header
+        let own = request("GET", "/api/v1/voices", "acme", Some("acme"));
+        assert_eq!(status(own), StatusCode::OK);
+        let implicit = request("GET", "/api/v1/voices", "acme", None);
+        assert_eq!(status(implicit), StatusCode::OK);
+
+        // A second tenant's key cannot reach the first tenant's data
+        let other = request("GET", "/api/v1/voices", "globex", Some("acme"));
+        assert_eq!(status(other), StatusCode::FORBIDDEN);
+        let other = request("DELETE", "/api/v1/voices/narrator", "globex", Some("acme"));
+        assert_eq!(status(other), StatusCode::FORBIDDEN);
+
+        // Rotating and purging take the admin scope
+        let rotate = request("POST", "/api/v1/tenants/acme/rotate-key", "globex", None);
+        assert_eq!(status(rotate), StatusCode::FORBIDDEN);
+        let purge = request("DELETE", "/api/v1/tenants/acme", "globex", None);
+        assert_eq!(status(purge), StatusCode::FORBIDDEN);
+        let purge = request("DELETE", "/api/v1/tenants/acme", "ops", None);
+        assert_eq!(status(purge), StatusCode::OK);
+
+        // So do the operator endpoints
+        for (method, uri) in [
+            ("POST", "/api/v1/models/Qwen3-TTS-12Hz-0.6B-Base/swap"),
+            ("POST", "/api/v1/models/Qwen3-TTS-12Hz-0.6B-Base/unload"),
+            ("DELETE", "/api/v1/models/Qwen3-TTS-12Hz-0.6B-Base"),
+            ("POST", "/api/v1/admin/warmup"),
+            ("GET", "/api/v1/admin/stats"),
+            ("DELETE", "/api/v1/debug/profile"),
+        ] {
+            let operator = request(method, uri, "acme", None);
+            assert_eq!(
+                status(operator),
+                StatusCode::FORBIDDEN,
+                "{} {}",
+                method,
+                uri
+            );
+        }
+        let stats = request("GET", "/api/v1/admin/stats", "ops", None);
+        assert_eq!(status(stats), StatusCode::OK);
+    }
+}
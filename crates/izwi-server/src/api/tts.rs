@@ -2,20 +2,45 @@
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{header, Response},
-    Json,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, Response, StatusCode},
+    Extension, Json,
 };
+use bytes::{BufMut, Bytes, BytesMut};
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::info;
 
+use super::models::parse_variant;
+use super::tenants::tenant_id;
+use crate::auth::{request_priority, ApiKeyIdentity, KeyTenant, MaxPriority};
 use crate::error::ApiError;
 use crate::state::AppState;
-use izwi_core::audio::AudioFormat;
-use izwi_core::inference::{AudioChunk, GenerationConfig, GenerationRequest};
+use izwi_core::audio::{
+    detect_voiced_range, estimate_speech_secs, extract_range, frame_aligned_range, mix_background,
+    normalize_loudness, pad_silence, parse_timestamp, resample, trim_silence, AudioEncoder,
+    AudioFormat, BackgroundTrack, BitDepth, ChunkEncoder, LoudnessConfig, LoudnessNormalizer,
+    MixConfig, RangeConfig, Resampler, SilenceConfig, SpooledAudio,
+};
+use izwi_core::engine::{
+    AudioOutput, CacheControl, CacheKey, JobStatus, LatencyPhase, OutputCache, OutputResponse,
+    OutputTimings, Priority, SealedResult, StoredResult,
+};
+use izwi_core::inference::{
+    AudioChunk, GenerationRequest, GenerationResult, VerificationResult, VerifyConfig,
+};
+use izwi_core::journal::{JournalEntry, JournalStatus, JournalTicket};
+use izwi_core::language::{resolve_language, AUTO_LANGUAGE};
+use izwi_core::lexicon::Pronunciation;
+use izwi_core::text::subtitles::{render, timed_cues, Cue};
+use izwi_core::text::{SpeechFilter, SpellOut, SubtitleFormat};
+use izwi_core::usage::Usage;
+use izwi_core::{InferenceEngine, OutputStorage};
 
 /// TTS generation request
 #[derive(Debug, Deserialize)]
@@ -23,6 +48,10 @@ pub struct TTSRequest {
     /// Text to synthesize
     pub text: String,
 
+    /// Loaded model to synthesize with (the most recently loaded when unset)
+    #[serde(default)]
+    pub model: Option<String>,
+
     /// Speaker/voice ID
     #[serde(default)]
     pub speaker: Option<String>,
@@ -31,6 +60,14 @@ pub struct TTSRequest {
     #[serde(default)]
     pub voice_description: Option<String>,
 
+    /// Speaking style or emotion, e.g. cheerful, whisper or newscaster
+    #[serde(default, alias = "emotion")]
+    pub style: Option<String>,
+
+    /// Free-text direction on how to speak
+    #[serde(default)]
+    pub instruct: Option<String>,
+
     /// Reference audio for voice cloning (base64)
     #[serde(default)]
     pub reference_audio: Option<String>,
@@ -39,57 +76,301 @@ pub struct TTSRequest {
     #[serde(default)]
     pub reference_text: Option<String>,
 
+    /// Name of a saved voice supplying the reference audio and text
+    #[serde(default)]
+    pub saved_voice: Option<String>,
+
     /// Output format (wav, raw_f32, raw_i16)
     #[serde(default = "default_format")]
     pub format: String,
 
-    /// Temperature for sampling
+    /// Sampling preset (`fast`, `balanced`, `quality` or one from the
+    /// config) laid over the model's defaults
+    #[serde(default)]
+    pub preset: Option<String>,
+
+    /// Temperature for sampling, overriding the preset
     #[serde(default)]
     pub temperature: Option<f32>,
 
     /// Speed factor
     #[serde(default)]
     pub speed: Option<f32>,
+
+    /// Output sample rate in Hz (defaults to the model's native rate)
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+
+    /// WAV sample encoding: 16, 24 or "32f" (defaults to 16-bit)
+    #[serde(default)]
+    pub bit_depth: BitDepth,
+
+    /// Target integrated loudness in LUFS (no normalization when unset)
+    #[serde(default)]
+    pub target_lufs: Option<f32>,
+
+    /// Transcribe the output and retry if it doesn't match the input text
+    #[serde(default)]
+    pub verify: bool,
+
+    /// Maximum word error rate accepted by verification
+    #[serde(default)]
+    pub verify_wer_threshold: Option<f32>,
+
+    /// Trim leading and trailing silence (streaming trims leading only)
+    #[serde(default)]
+    pub trim_silence: bool,
+
+    /// Silence added to both ends of the output, in milliseconds
+    #[serde(default)]
+    pub pad_ms: u32,
+
+    /// End generation after this much silence following speech, in
+    /// milliseconds (0 = off)
+    #[serde(default)]
+    pub stop_on_silence_ms: u32,
+
+    /// Longest audio to generate, in seconds
+    #[serde(default)]
+    pub max_audio_seconds: Option<f32>,
+
+    /// Scheduling priority (low, normal, high or critical; defaults to
+    /// normal and is capped by the API key's `max_priority`)
+    #[serde(default)]
+    pub priority: Option<Priority>,
+
+    /// Output cache use: default, no_cache or no_store (defaults to the
+    /// request's `Cache-Control` header)
+    #[serde(default)]
+    pub cache: CacheControl,
+
+    /// Language of the text (detected from it when unset)
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Background track mixed under the speech (non-streaming only)
+    #[serde(default)]
+    pub background: Option<BackgroundOptions>,
+
+    /// One-off pronunciations (word -> respelling or `{ phonemes }`)
+    /// overriding the lexicon
+    #[serde(default)]
+    pub pronunciations: HashMap<String, Pronunciation>,
+
+    /// `reject` or `bleep` profanity in the text
+    #[serde(default)]
+    pub profanity: Option<SpeechFilter>,
+
+    /// `letters` or `nato` to read codes like "A4-7Z" character by
+    /// character
+    #[serde(default)]
+    pub spell_out: SpellOut,
+
+    /// `srt` or `vtt` to return subtitles timed to the speech instead of
+    /// the audio (non-streaming only)
+    #[serde(default)]
+    pub response_format: Option<SubtitleFormat>,
 }
 
 fn default_format() -> String {
     "wav".to_string()
 }
 
-/// TTS generation response (non-streaming)
+/// Background track and how it is mixed under the speech
+#[derive(Debug, Deserialize)]
+pub struct BackgroundOptions {
+    /// Base64-encoded WAV file
+    #[serde(default)]
+    pub audio_base64: Option<String>,
+    /// URL of a WAV file (requires `engine.allow_background_urls`)
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub mix: MixConfig,
+}
+
+/// Query options for non-streaming generation
+#[derive(Debug, Default, Deserialize)]
+pub struct GenerateQuery {
+    /// Queue the job and return at once; poll `/requests/{id}` for its status
+    #[serde(default, rename = "async")]
+    pub run_async: bool,
+}
+
+/// Response to an asynchronously submitted job
 #[derive(Serialize)]
-pub struct TTSResponse {
+pub struct JobAccepted {
     pub request_id: String,
-    pub audio: String, // base64 encoded
-    pub format: String,
-    pub sample_rate: u32,
-    pub duration_secs: f32,
-    pub stats: TTSStats,
+    pub status: JobStatus,
+}
+
+/// Processing applied to generated audio before encoding
+#[derive(Debug, Clone)]
+struct PostProcess {
+    sample_rate: Option<u32>,
+    trim_silence: bool,
+    pad_ms: u32,
+    target_lufs: Option<f32>,
+    background: Option<(Arc<BackgroundTrack>, MixConfig)>,
+}
+
+impl PostProcess {
+    fn from_request(req: &TTSRequest, background: Option<(BackgroundTrack, MixConfig)>) -> Self {
+        Self {
+            sample_rate: req.sample_rate,
+            trim_silence: req.trim_silence,
+            pad_ms: req.pad_ms,
+            target_lufs: req.target_lufs,
+            background: background.map(|(track, mix)| (Arc::new(track), mix)),
+        }
+    }
+
+    /// Resample, trim, pad, mix and normalize the generated audio
+    fn apply(&self, result: &mut GenerationResult) -> Result<(), ApiError> {
+        // Resample to the client-requested rate
+        if let Some(rate) = self.sample_rate {
+            if rate != result.sample_rate {
+                result.samples = resample(&result.samples, result.sample_rate, rate)?;
+                result.sample_rate = rate;
+            }
+        }
+
+        if self.trim_silence {
+            result.samples = trim_silence(
+                &result.samples,
+                result.sample_rate,
+                &SilenceConfig::default(),
+            );
+        }
+        if self.pad_ms > 0 {
+            result.samples = pad_silence(&result.samples, result.sample_rate, self.pad_ms);
+        }
+        if let Some((track, mix)) = &self.background {
+            result.samples = mix_background(&result.samples, result.sample_rate, track, mix)?;
+        }
+
+        if let Some(target) = self.target_lufs {
+            normalize_loudness(
+                &mut result.samples,
+                result.sample_rate,
+                &LoudnessConfig::with_target(target),
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether `apply` leaves audio at `sample_rate` untouched
+    fn is_noop(&self, sample_rate: u32) -> bool {
+        self.sample_rate.is_none_or(|rate| rate == sample_rate)
+            && !self.trim_silence
+            && self.pad_ms == 0
+            && self.background.is_none()
+            && self.target_lufs.is_none()
+    }
+}
+
+/// Decode or download the requested background track
+async fn load_background(
+    state: &AppState,
+    options: Option<BackgroundOptions>,
+) -> Result<Option<(BackgroundTrack, MixConfig)>, ApiError> {
+    let Some(options) = options else {
+        return Ok(None);
+    };
+    options.mix.validate()?;
+    let (allow_urls, max_bytes) = {
+        let engine = &state.engine;
+        let config = engine.config();
+        (config.allow_background_urls, config.max_background_bytes)
+    };
+
+    let track = match (options.audio_base64, options.url) {
+        (Some(audio), None) => {
+            if max_bytes > 0 && audio.len() / 4 * 3 > max_bytes {
+                return Err(ApiError::bad_request(format!(
+                    "Background track exceeds the {} byte limit",
+                    max_bytes
+                )));
+            }
+            BackgroundTrack::from_base64(&audio)?
+        }
+        (None, Some(url)) => {
+            if !allow_urls {
+                return Err(ApiError::bad_request(
+                    "Background URLs are disabled (engine.allow_background_urls)",
+                ));
+            }
+            BackgroundTrack::fetch(&url, max_bytes)
+                .await
+                .map_err(|e| ApiError::bad_request(format!("Failed to fetch background: {}", e)))?
+        }
+        _ => {
+            return Err(ApiError::bad_request(
+                "background needs exactly one of audio_base64 or url",
+            ))
+        }
+    };
+    Ok(Some((track, options.mix)))
 }
 
+/// TTS generation response (non-streaming)
 #[derive(Serialize)]
-pub struct TTSStats {
-    pub tokens_generated: usize,
-    pub generation_time_ms: f32,
-    pub rtf: f32,
+pub struct TTSResponse {
+    #[serde(flatten)]
+    pub output: OutputResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationResult>,
+    /// Served from the output cache
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    pub usage: Usage,
 }
 
 /// Generate audio (non-streaming)
+///
+/// With `?async=true` the job is queued and a `202 Accepted` returned at
+/// once; its audio is fetched later from `/requests/{id}/result`.
 pub async fn generate(
     State(state): State<AppState>,
-    Json(req): Json<TTSRequest>,
+    headers: HeaderMap,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    max_priority: Option<Extension<MaxPriority>>,
+    Query(query): Query<GenerateQuery>,
+    Json(mut req): Json<TTSRequest>,
 ) -> Result<Response<Body>, ApiError> {
+    let tenant = tenant_id(&headers, key_tenant.as_ref())?;
+    let priority = request_priority(req.priority, max_priority)?;
     info!("TTS request: {} chars", req.text.len());
+    if query.run_async && req.response_format.is_some() {
+        return Err(ApiError::bad_request(
+            "response_format is not supported for async requests",
+        ));
+    }
     info!(
         "Voice clone - ref_audio: {}, ref_text: {}",
         req.reference_audio.is_some(),
         req.reference_text.is_some()
     );
 
-    let engine = state.engine.read().await;
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let started = Instant::now();
+    let latency = state.engine_core.latency_tracker();
+    latency.start(&request_id, started);
+
+    let engine = &state.engine;
+    latency.scheduled(&request_id, Instant::now());
+    apply_saved_voice(engine, tenant.as_deref(), &mut req)?;
 
     // Build generation request
-    let mut gen_config = GenerationConfig::default();
+    let model = req.model.as_deref().map(parse_variant).transpose()?;
+    let mut gen_config = engine.generation_config(model, req.preset.as_deref())?;
     gen_config.streaming = false;
     if let Some(t) = req.temperature {
         gen_config.temperature = t;
@@ -98,26 +379,126 @@ pub async fn generate(
         gen_config.speed = s;
     }
     gen_config.speaker = req.speaker.clone();
+    gen_config.stop_on_silence_ms = req.stop_on_silence_ms;
+    gen_config.max_audio_seconds = req.max_audio_seconds;
+    let format = parse_format(&req.format)?;
+    req.sample_rate = output_sample_rate(format, req.sample_rate)?;
+    let background = load_background(&state, req.background.take()).await?;
+    let post = PostProcess::from_request(&req, background);
+    let verify = verify_config(&req)?;
+    let cache_control = cache_control(&headers, req.cache);
+    // Subtitles show the text as sent, split into sentences by its language
+    let subtitle_text = req.response_format.map(|_| {
+        let language = resolve_language(req.language.as_deref(), &req.text);
+        (req.text.clone(), language)
+    });
 
     let gen_request = GenerationRequest {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: request_id.clone(),
         text: req.text,
         config: gen_config,
         reference_audio: req.reference_audio,
         reference_text: req.reference_text,
         voice_description: req.voice_description,
+        style: req.style.as_deref().map(str::parse).transpose()?,
+        instruct: req.instruct,
+        model,
+        client_id: identity.map(|Extension(ApiKeyIdentity(name))| name),
+        priority,
+        language: req.language,
+        pronunciations: req.pronunciations,
+        profanity: req.profanity,
+        spell_out: req.spell_out,
     };
 
+    validate_target_lufs(req.target_lufs)?;
+    validate_pad_ms(req.pad_ms)?;
+    validate_max_audio_seconds(req.max_audio_seconds)?;
+    let ticket = journal_accept(engine, &gen_request, "tts")?;
+    let cache = CachePlan::new(
+        &state,
+        engine,
+        cache_control,
+        &gen_request,
+        tenant.as_deref(),
+        verify.as_ref(),
+    );
+
+    if query.run_async {
+        return Ok(spawn_job(
+            &state,
+            tenant,
+            gen_request,
+            verify,
+            post,
+            cache,
+            ticket,
+        ));
+    }
+
     // Generate audio
-    let result = engine.generate(gen_request).await?;
+    let input_usage = Usage::for_text(&gen_request.text);
+    let api_key = gen_request.client_id.clone();
+    let generation_start = Instant::now();
+    let generated = generate_cached(engine, cache.as_ref(), gen_request, verify.as_ref()).await;
+    latency.record(
+        &request_id,
+        LatencyPhase::Decode,
+        generation_start.elapsed(),
+    );
+    let (mut result, cached) = match generated {
+        Ok(generated) => generated,
+        Err(e) => {
+            latency.finish(&request_id, Instant::now());
+            if let Some(ticket) = ticket {
+                ticket.fail(&e);
+            }
+            return Err(e.into());
+        }
+    };
+    let encode_start = Instant::now();
+    post.apply(&mut result)?;
+
+    // Keep the final audio so clients can fetch sub-ranges later
+    engine.output_store().insert(
+        &result.request_id,
+        tenant.as_deref(),
+        &result.samples,
+        result.sample_rate,
+    )?;
 
     // Encode to requested format
-    let format = parse_format(&req.format)?;
-    let encoder = engine.audio_encoder();
-    let audio_bytes = encoder.encode(&result.samples, format)?;
+    let audio_bytes = AudioEncoder::new(result.sample_rate, 1)
+        .with_bit_depth(req.bit_depth)
+        .encode(&result.samples, format)?;
+    latency.record(&request_id, LatencyPhase::Encode, encode_start.elapsed());
+    latency.finish(&request_id, Instant::now());
+    if let Some(ticket) = ticket {
+        ticket.finish();
+    }
+    let usage = input_usage
+        .with_audio(result.total_tokens, result.duration_secs() as f64)
+        .with_wall_time(started.elapsed());
+    state
+        .usage
+        .record(&request_id, api_key.as_deref(), "tts", usage);
+
+    if let (Some(format), Some((text, language))) = (req.response_format, subtitle_text) {
+        let rate = result.sample_rate as f64;
+        let span = detect_voiced_range(
+            &result.samples,
+            result.sample_rate,
+            &SilenceConfig::default(),
+        )
+        .map(|voiced| voiced.start as f64 / rate..voiced.end as f64 / rate)
+        .unwrap_or(0.0..result.duration_secs() as f64);
+        let segmenter = state.engine.segmenters().get(&language);
+        let cues = timed_cues(&text, span, None, segmenter.as_ref());
+        return Ok(subtitle_response(&cues, format, &result.request_id));
+    }
 
     // Return based on format
-    let content_type = izwi_core::audio::AudioEncoder::content_type(format);
+    let content_type = AudioEncoder::content_type(format);
 
     // Calculate stats for headers
     let duration_secs = result.duration_secs();
@@ -127,37 +508,68 @@ pub async fn generate(
 
     if format == AudioFormat::Wav {
         // Return as binary WAV file with timing headers
-        Ok(Response::builder()
+        let mut builder = Response::builder();
+        if let Some(voice) = &result.voice {
+            builder = builder.header("X-Voice", header_safe(voice));
+        }
+        if let Some(language) = &result.language {
+            builder = builder.header("X-Language", header_safe(language));
+        }
+        for warning in &result.warnings {
+            builder = builder.header(header::WARNING, warning_header(warning));
+        }
+        if let Some(verification) = &result.verification {
+            builder = builder
+                .header("X-Verify-WER", format!("{:.3}", verification.wer))
+                .header("X-Verify-Passed", verification.passed.to_string())
+                .header("X-Verify-Attempts", verification.attempts.to_string());
+        }
+        if cache.is_some() {
+            builder = builder.header("X-Cache", if cached { "HIT" } else { "MISS" });
+        }
+        Ok(builder
             .header(header::CONTENT_TYPE, content_type)
             .header(
                 header::CONTENT_DISPOSITION,
                 "attachment; filename=\"speech.wav\"",
             )
+            .header("X-Request-Id", &result.request_id)
             // Custom headers for timing stats (exposed via Access-Control-Expose-Headers)
             .header("X-Generation-Time-Ms", format!("{:.1}", generation_time_ms))
             .header("X-Audio-Duration-Secs", format!("{:.2}", duration_secs))
             .header("X-RTF", format!("{:.3}", rtf))
             .header("X-Tokens-Generated", tokens_generated.to_string())
+            .header("X-Usage-Input-Chars", usage.input_chars.to_string())
+            .header("X-Usage-Prompt-Tokens", usage.prompt_tokens.to_string())
             .header(
                 "Access-Control-Expose-Headers",
-                "X-Generation-Time-Ms, X-Audio-Duration-Secs, X-RTF, X-Tokens-Generated",
+                "X-Request-Id, X-Generation-Time-Ms, X-Audio-Duration-Secs, X-RTF, X-Tokens-Generated, X-Usage-Input-Chars, X-Usage-Prompt-Tokens, X-Voice, X-Language, Warning, X-Verify-WER, X-Verify-Passed, X-Verify-Attempts, X-Cache",
             )
             .body(Body::from(audio_bytes))
             .unwrap())
     } else {
         // Return as JSON with base64 audio
-        use base64::Engine;
+        let timings = latency
+            .report(&request_id, Instant::now())
+            .map(|report| OutputTimings::from(&report))
+            .unwrap_or_default();
+        let output = OutputResponse::new(result.request_id.clone())
+            .with_audio(
+                &audio_bytes,
+                req.format,
+                result.sample_rate,
+                result.duration_secs(),
+            )
+            .with_tokens(usage.prompt_tokens, result.total_tokens)
+            .with_timings(timings);
         let response = TTSResponse {
-            request_id: result.request_id.clone(),
-            audio: base64::engine::general_purpose::STANDARD.encode(&audio_bytes),
-            format: req.format,
-            sample_rate: result.sample_rate,
-            duration_secs: result.duration_secs(),
-            stats: TTSStats {
-                tokens_generated: result.total_tokens,
-                generation_time_ms: result.total_time_ms,
-                rtf: result.rtf(),
-            },
+            output,
+            voice: result.voice.clone(),
+            language: result.language.clone(),
+            warnings: result.warnings.clone(),
+            verification: result.verification.clone(),
+            cached,
+            usage,
         };
         Ok(Response::builder()
             .header(header::CONTENT_TYPE, "application/json")
@@ -169,14 +581,44 @@ pub async fn generate(
 /// Generate audio with streaming
 pub async fn generate_stream(
     State(state): State<AppState>,
-    Json(req): Json<TTSRequest>,
+    headers: HeaderMap,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    max_priority: Option<Extension<MaxPriority>>,
+    Json(mut req): Json<TTSRequest>,
 ) -> Result<Response<Body>, ApiError> {
+    let tenant = tenant_id(&headers, key_tenant.as_ref())?;
+    let priority = request_priority(req.priority, max_priority)?;
     info!("Streaming TTS request: {} chars", req.text.len());
 
-    let engine = state.engine.read().await;
+    if req.verify {
+        return Err(ApiError::bad_request(
+            "verify is not supported for streaming requests",
+        ));
+    }
+    if req.background.is_some() {
+        return Err(ApiError::bad_request(
+            "background is not supported for streaming requests",
+        ));
+    }
+    if req.response_format.is_some() {
+        return Err(ApiError::bad_request(
+            "response_format is not supported for streaming requests",
+        ));
+    }
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let started = Instant::now();
+    let latency = state.engine_core.latency_tracker();
+    latency.start(&request_id, started);
+
+    let engine = &state.engine;
+    latency.scheduled(&request_id, Instant::now());
+    apply_saved_voice(engine, tenant.as_deref(), &mut req)?;
 
     // Build generation request
-    let mut gen_config = GenerationConfig::default();
+    let model = req.model.as_deref().map(parse_variant).transpose()?;
+    let mut gen_config = engine.generation_config(model, req.preset.as_deref())?;
     gen_config.streaming = true;
     if let Some(t) = req.temperature {
         gen_config.temperature = t;
@@ -185,18 +627,45 @@ pub async fn generate_stream(
         gen_config.speed = s;
     }
     gen_config.speaker = req.speaker.clone();
+    gen_config.stop_on_silence_ms = req.stop_on_silence_ms;
+    gen_config.max_audio_seconds = req.max_audio_seconds;
 
     let gen_request = GenerationRequest {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: request_id.clone(),
         text: req.text,
         config: gen_config,
         reference_audio: req.reference_audio,
         reference_text: req.reference_text,
         voice_description: req.voice_description,
+        style: req.style.as_deref().map(str::parse).transpose()?,
+        instruct: req.instruct,
+        model,
+        client_id: identity.map(|Extension(ApiKeyIdentity(name))| name),
+        priority,
+        language: req.language,
+        pronunciations: req.pronunciations,
+        profanity: req.profanity,
+        spell_out: req.spell_out,
     };
 
     let format = parse_format(&req.format)?;
-    let sample_rate = engine.sample_rate();
+    let requested_rate = output_sample_rate(format, req.sample_rate)?;
+    validate_target_lufs(req.target_lufs)?;
+    validate_pad_ms(req.pad_ms)?;
+    validate_max_audio_seconds(req.max_audio_seconds)?;
+    let ticket = journal_accept(engine, &gen_request, "tts_stream")?;
+    let voice = req
+        .speaker
+        .as_deref()
+        .map(|s| engine.voice_registry().resolve(s))
+        .transpose()?;
+    let language = resolve_language(gen_request.language.as_deref(), &gen_request.text);
+    let native_rate = engine.sample_rate();
+    let sample_rate = requested_rate.unwrap_or(native_rate);
+    let mut resampler = Resampler::new(native_rate, sample_rate)?;
+    let mut normalizer = req
+        .target_lufs
+        .map(|target| LoudnessNormalizer::new(LoudnessConfig::with_target(target), sample_rate));
 
     // Create channel for streaming chunks
     let (tx, rx) = mpsc::channel::<AudioChunk>(32);
@@ -204,37 +673,748 @@ pub async fn generate_stream(
     // Spawn generation task
     let engine_clone = state.engine.clone();
     let request_clone = gen_request.clone();
+    let journal = engine.journal().cloned();
     tokio::spawn(async move {
-        let engine = engine_clone.read().await;
+        let engine = engine_clone;
+        let request_id = request_clone.id.clone();
         if let Err(e) = engine.generate_streaming(request_clone, tx).await {
             tracing::error!("Streaming generation error: {}", e);
+            if let Some(journal) = journal {
+                journal.complete(&request_id, JournalStatus::Failed, Some(e.to_string()));
+            }
         }
     });
 
     // Create stream from receiver
-    let encoder = izwi_core::audio::AudioEncoder::new(sample_rate, 1);
+    let mut encoder = ChunkEncoder::new(
+        AudioEncoder::new(sample_rate, 1).with_bit_depth(req.bit_depth),
+        format,
+    );
+    let silence = SilenceConfig::default();
+    let mut trim_leading = req.trim_silence;
+    let pad_samples = req.pad_ms as usize * sample_rate as usize / 1000;
+    let mut pad_leading = pad_samples > 0;
+    let response_id = request_id.clone();
+    let chunk_latency = latency.clone();
+    let chunk_request_id = request_id.clone();
+    let mut last_chunk = Instant::now();
+    let ledger = state.usage.clone();
+    let api_key = gen_request.client_id.clone();
+    let mut usage = Usage::for_text(&gen_request.text);
+    let mut parts = (format == AudioFormat::L16).then(|| PcmParts::new(sample_rate));
     let stream = ReceiverStream::new(rx).map(move |chunk| {
-        let bytes = encoder.encode(&chunk.samples, format).unwrap_or_default();
+        let received = Instant::now();
+        chunk_latency.record(
+            &chunk_request_id,
+            LatencyPhase::Decode,
+            received.duration_since(last_chunk),
+        );
+        last_chunk = received;
+
+        usage.audio_tokens += chunk.stats.as_ref().map_or(0, |s| s.tokens_generated);
+        usage.audio_secs += chunk.samples.len() as f64 / native_rate as f64;
+        if chunk.is_final {
+            usage = usage.with_wall_time(started.elapsed());
+            ledger.record(&chunk_request_id, api_key.as_deref(), "tts_stream", usage);
+        }
+
+        let mut samples = resampler.process(&chunk.samples).unwrap_or_default();
+        if chunk.is_final {
+            samples.extend(resampler.flush().unwrap_or_default());
+        }
+        // Drop silence until the first voiced frame
+        if trim_leading {
+            match detect_voiced_range(&samples, sample_rate, &silence) {
+                Some(range) => {
+                    samples.drain(..range.start);
+                    trim_leading = false;
+                }
+                None => samples.clear(),
+            }
+        }
+        if pad_leading && (!samples.is_empty() || chunk.is_final) {
+            samples.splice(0..0, std::iter::repeat_n(0.0, pad_samples));
+            pad_leading = false;
+        }
+        if chunk.is_final {
+            samples.resize(samples.len() + pad_samples, 0.0);
+        }
+        if let Some(normalizer) = normalizer.as_mut() {
+            normalizer.process(&mut samples);
+        }
+        let mut bytes = encoder.encode(&samples).unwrap_or_default();
+        if let Some(parts) = parts.as_mut() {
+            bytes = parts.frame(bytes, samples.len(), chunk.is_final);
+        }
+        chunk_latency.record(&chunk_request_id, LatencyPhase::Encode, received.elapsed());
         Ok::<_, std::convert::Infallible>(bytes)
     });
 
-    let content_type = izwi_core::audio::AudioEncoder::content_type(format);
+    // The next poll comes once the previous chunk has been handed to the
+    // connection, which gives the flush time of each chunk
+    let stream = futures::stream::unfold(
+        (Box::pin(stream), None::<Instant>, ticket),
+        move |(mut stream, yielded, mut ticket)| {
+            let latency = latency.clone();
+            let request_id = request_id.clone();
+            async move {
+                if let Some(yielded) = yielded {
+                    latency.record(&request_id, LatencyPhase::Flush, yielded.elapsed());
+                }
+                match stream.next().await {
+                    Some(item) => Some((item, (stream, Some(Instant::now()), ticket))),
+                    None => {
+                        latency.finish(&request_id, Instant::now());
+                        if let Some(ticket) = ticket.take() {
+                            ticket.finish();
+                        }
+                        None
+                    }
+                }
+            }
+        },
+    );
 
-    Ok(Response::builder()
+    let content_type = if format == AudioFormat::L16 {
+        format!("multipart/mixed; boundary={}", PART_BOUNDARY)
+    } else {
+        AudioEncoder::content_type(format).to_string()
+    };
+
+    let mut builder = Response::builder();
+    if let Some(voice) = voice {
+        builder = builder.header("X-Voice", header_safe(&voice.name));
+        if let Some(warning) = &voice.warning {
+            builder = builder.header(header::WARNING, warning_header(warning));
+        }
+    }
+    if language != AUTO_LANGUAGE {
+        builder = builder.header("X-Language", header_safe(&language));
+    }
+
+    Ok(builder
         .header(header::CONTENT_TYPE, content_type)
         .header(header::TRANSFER_ENCODING, "chunked")
+        .header("X-Request-Id", &response_id)
         .body(Body::from_stream(stream))
         .unwrap())
 }
 
-fn parse_format(s: &str) -> Result<AudioFormat, ApiError> {
+/// Boundary between the parts of a multipart L16 stream
+const PART_BOUNDARY: &str = "izwi-audio-chunk";
+
+/// Frames L16 chunks as `multipart/mixed` parts carrying their sequence
+/// number and start time, for clients that can't use SSE or WebSockets
+struct PcmParts {
+    sample_rate: u32,
+    sequence: usize,
+    samples_sent: usize,
+}
+
+impl PcmParts {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            sequence: 0,
+            samples_sent: 0,
+        }
+    }
+
+    /// Wrap one encoded chunk of `samples` samples in a part; the final
+    /// chunk also closes the multipart body
+    fn frame(&mut self, audio: Bytes, samples: usize, is_final: bool) -> Bytes {
+        let mut out = BytesMut::new();
+        if samples > 0 {
+            let header = format!(
+                concat!(
+                    "--{}\r\n",
+                    "Content-Type: audio/L16; rate={}; channels=1\r\n",
+                    "Content-Length: {}\r\n",
+                    "X-Chunk-Sequence: {}\r\n",
+                    "X-Chunk-Timestamp: {:.3}\r\n\r\n",
+                ),
+                PART_BOUNDARY,
+                self.sample_rate,
+                audio.len(),
+                self.sequence,
+                self.samples_sent as f64 / self.sample_rate as f64
+            );
+            out.reserve(header.len() + audio.len() + 2);
+            out.put_slice(header.as_bytes());
+            out.put_slice(&audio);
+            out.put_slice(b"\r\n");
+            self.sequence += 1;
+            self.samples_sent += samples;
+        }
+        if is_final {
+            out.put_slice(format!("--{}--\r\n", PART_BOUNDARY).as_bytes());
+        }
+        out.freeze()
+    }
+}
+
+/// Audio of a finished background job
+enum JobOutput {
+    InMemory(GenerationResult),
+    Spooled(SpooledAudio),
+}
+
+impl JobOutput {
+    fn total_tokens(&self) -> usize {
+        match self {
+            Self::InMemory(result) => result.total_tokens,
+            Self::Spooled(audio) => audio.num_samples / 256, // approximate
+        }
+    }
+
+    fn duration_secs(&self) -> f32 {
+        match self {
+            Self::InMemory(result) => result.duration_secs(),
+            Self::Spooled(audio) => audio.duration_secs(),
+        }
+    }
+}
+
+/// Upload a finished job's audio to output storage as WAV. `None` when the
+/// upload fails, in which case the result is kept in memory instead.
+async fn publish(
+    storage: &dyn OutputStorage,
+    job_id: &str,
+    output: &JobOutput,
+) -> Option<StoredResult> {
+    let key = format!("{}.wav", job_id);
+    let content_type = AudioEncoder::content_type(AudioFormat::Wav);
+    let uploaded = async {
+        match output {
+            JobOutput::InMemory(result) => {
+                let bytes = AudioEncoder::new(result.sample_rate, 1)
+                    .encode_bytes(&result.samples, AudioFormat::Wav)?;
+                storage.put(&key, bytes, content_type).await
+            }
+            JobOutput::Spooled(audio) => storage.put_file(&key, &audio.path, content_type).await,
+        }
+    };
+    match uploaded.await {
+        Ok(()) => Some(StoredResult {
+            key,
+            duration_secs: output.duration_secs(),
+        }),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to store result of job {} in {}, keeping it in memory: {}",
+                job_id,
+                storage,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Whether a job is long enough to spool to disk and only needs what
+/// spooling supports: no verification or post-processing, which work on the
+/// whole take, and no tenant, whose outputs are kept encrypted at rest.
+/// Spooled jobs bypass the output cache and aren't kept for range
+/// extraction.
+fn spools(
+    engine: &InferenceEngine,
+    tenant: Option<&str>,
+    request: &GenerationRequest,
+    verify: Option<&VerifyConfig>,
+    post: &PostProcess,
+) -> bool {
+    let threshold = engine.config().spool_threshold_secs;
+    threshold > 0.0
+        && estimate_speech_secs(&request.text) > threshold
+        && request
+            .config
+            .max_audio_seconds
+            .is_none_or(|max| max as f64 > threshold)
+        && tenant.is_none()
+        && verify.is_none()
+        && post.is_noop(engine.sample_rate())
+}
+
+/// Run a generation in the background, keeping its audio in the result
+/// store until it expires. Long jobs are written to a spool file as they
+/// generate rather than assembled in memory. With output storage configured
+/// the finished audio is uploaded and the job's status links to it.
+fn spawn_job(
+    state: &AppState,
+    tenant: Option<String>,
+    request: GenerationRequest,
+    verify: Option<VerifyConfig>,
+    post: PostProcess,
+    cache: Option<CachePlan>,
+    ticket: Option<JournalTicket>,
+) -> Response<Body> {
+    let request_id = request.id.clone();
+    let results = state.engine_core.result_store();
+    results.submit(&request_id, request.client_id.as_deref(), tenant.as_deref());
+
+    let engine = state.engine.clone();
+    let spool = spools(&engine, tenant.as_deref(), &request, verify.as_ref(), &post);
+    if spool {
+        info!("Queued async TTS job {} (spooled to disk)", request_id);
+    } else {
+        info!("Queued async TTS job {}", request_id);
+    }
+    let latency = state.engine_core.latency_tracker();
+    let ledger = state.usage.clone();
+    let storage = state.storage.clone();
+    let input_usage = Usage::for_text(&request.text);
+    let api_key = request.client_id.clone();
+    let started = Instant::now();
+    let job_id = request_id.clone();
+    tokio::spawn(async move {
+        results.start(&job_id);
+        let generation_start = Instant::now();
+        let outcome = if spool {
+            let generated = engine.generate_spooled(request).await;
+            latency.record(&job_id, LatencyPhase::Decode, generation_start.elapsed());
+            generated.map(JobOutput::Spooled).map_err(ApiError::from)
+        } else {
+            let generated =
+                generate_cached(&engine, cache.as_ref(), request, verify.as_ref()).await;
+            latency.record(&job_id, LatencyPhase::Decode, generation_start.elapsed());
+            generated
+                .map_err(ApiError::from)
+                .and_then(|(mut result, _)| {
+                    post.apply(&mut result)?;
+                    engine.output_store().insert(
+                        &result.request_id,
+                        tenant.as_deref(),
+                        &result.samples,
+                        result.sample_rate,
+                    )?;
+                    Ok(JobOutput::InMemory(result))
+                })
+        };
+        latency.finish(&job_id, Instant::now());
+
+        match outcome {
+            Ok(output) => {
+                let usage = input_usage
+                    .with_audio(output.total_tokens(), output.duration_secs() as f64)
+                    .with_wall_time(started.elapsed());
+                ledger.record(&job_id, api_key.as_deref(), "tts", usage);
+                // Tenant audio is only served from the encrypted output store
+                let stored = match storage.filter(|_| tenant.is_none()) {
+                    Some(storage) => publish(storage.as_ref(), &job_id, &output).await,
+                    None => None,
+                };
+                match (stored, output) {
+                    (Some(stored), _) => results.complete_stored(&job_id, stored),
+                    (None, output) if tenant.is_some() => results.complete_sealed(
+                        &job_id,
+                        SealedResult {
+                            duration_secs: output.duration_secs(),
+                        },
+                    ),
+                    (None, JobOutput::InMemory(result)) => results.complete(
+                        &job_id,
+                        AudioOutput::new(result.samples, result.sample_rate),
+                    ),
+                    (None, JobOutput::Spooled(audio)) => results.complete_spooled(&job_id, audio),
+                }
+                if let Some(ticket) = ticket {
+                    ticket.finish();
+                }
+            }
+            Err(e) => {
+                tracing::error!("Async TTS job {} failed: {}", job_id, e.message);
+                results.fail(&job_id, e.message.clone());
+                if let Some(ticket) = ticket {
+                    ticket.fail(&e.message);
+                }
+            }
+        }
+    });
+
+    let body = JobAccepted {
+        request_id: request_id.clone(),
+        status: JobStatus::Queued,
+    };
+    Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            header::LOCATION,
+            format!("/api/v1/requests/{}", header_safe(&request_id)),
+        )
+        .header("X-Request-Id", &request_id)
+        .body(Body::from(serde_json::to_string(&body).unwrap()))
+        .unwrap()
+}
+
+/// Verification settings, when requested
+fn verify_config(req: &TTSRequest) -> Result<Option<VerifyConfig>, ApiError> {
+    if !req.verify {
+        return Ok(None);
+    }
+    let mut verify = VerifyConfig::default();
+    if let Some(threshold) = req.verify_wer_threshold {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(ApiError::bad_request(
+                "verify_wer_threshold must be between 0 and 1",
+            ));
+        }
+        verify.wer_threshold = threshold;
+    }
+    Ok(Some(verify))
+}
+
+/// Cache use requested by the body, or else by the `Cache-Control` header
+fn cache_control(headers: &HeaderMap, requested: CacheControl) -> CacheControl {
+    if requested != CacheControl::Default {
+        return requested;
+    }
+    let directives = headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if directives.contains("no-store") {
+        CacheControl::NoStore
+    } else if directives.contains("no-cache") {
+        CacheControl::NoCache
+    } else {
+        CacheControl::Default
+    }
+}
+
+/// Where a request's audio is looked up and stored in the output cache
+struct CachePlan {
+    cache: Arc<OutputCache>,
+    key: CacheKey,
+    control: CacheControl,
+}
+
+impl CachePlan {
+    /// `None` when the cache is disabled or the request opts out of it
+    fn new(
+        state: &AppState,
+        engine: &InferenceEngine,
+        control: CacheControl,
+        request: &GenerationRequest,
+        tenant: Option<&str>,
+        verify: Option<&VerifyConfig>,
+    ) -> Option<Self> {
+        let cache = state.engine_core.output_cache()?;
+        if !control.writes() {
+            return None;
+        }
+        let key = CacheKey::for_generation(
+            request,
+            tenant,
+            &serde_json::json!({
+                "verify_wer_threshold": verify.map(|v| v.wer_threshold),
+                "lexicon": engine.lexicon().fingerprint(),
+            }),
+        );
+        Some(Self {
+            cache,
+            key,
+            control,
+        })
+    }
+}
+
+/// Generate audio, answering repeated requests from the output cache.
+///
+/// Returns the raw model output (before post-processing) and whether it
+/// came from the cache. Audio failing verification is not cached.
+async fn generate_cached(
+    engine: &InferenceEngine,
+    cache: Option<&CachePlan>,
+    request: GenerationRequest,
+    verify: Option<&VerifyConfig>,
+) -> izwi_core::Result<(GenerationResult, bool)> {
+    if let Some(plan) = cache.filter(|plan| plan.control.reads()) {
+        if let Some(audio) = plan.cache.get(&plan.key) {
+            let voice = request
+                .config
+                .speaker
+                .as_deref()
+                .map(|s| engine.voice_registry().resolve(s))
+                .transpose()?;
+            let result = GenerationResult {
+                request_id: request.id,
+                samples: audio.samples.clone(),
+                sample_rate: audio.sample_rate,
+                total_tokens: 0,
+                total_time_ms: 0.0,
+                voice: voice.as_ref().map(|v| v.name.clone()),
+                language: Some(resolve_language(request.language.as_deref(), &request.text))
+                    .filter(|l| l != AUTO_LANGUAGE),
+                warnings: voice.and_then(|v| v.warning).into_iter().collect(),
+                verification: None,
+            };
+            return Ok((result, true));
+        }
+    }
+
+    let result = match verify {
+        Some(verify) => engine.generate_verified(request, verify).await?,
+        None => engine.generate(request).await?,
+    };
+    let passed = result.verification.as_ref().is_none_or(|v| v.passed);
+    if let Some(plan) = cache.filter(|_| passed) {
+        plan.cache.put(
+            plan.key.clone(),
+            AudioOutput::new(result.samples.clone(), result.sample_rate),
+        );
+    }
+    Ok((result, false))
+}
+
+/// Journal a request before generation starts (no-op without a journal)
+fn journal_accept(
+    engine: &InferenceEngine,
+    request: &GenerationRequest,
+    kind: &str,
+) -> Result<Option<JournalTicket>, ApiError> {
+    let Some(journal) = engine.journal() else {
+        return Ok(None);
+    };
+    let entry = JournalEntry::accepted(&request.id, kind)
+        .with_model(request.model.map(|model| model.to_string()))
+        .with_client_id(request.client_id.clone());
+    Ok(Some(journal.accept(entry)?))
+}
+
+/// Query for extracting part of a stored output
+#[derive(Debug, Deserialize)]
+pub struct RangeQuery {
+    /// Start timestamp (`150`, `2:30`, `1:02:30.5`), default start of output
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End timestamp, default end of output
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// Output format (wav, raw_f32, raw_i16)
+    #[serde(default = "default_format")]
+    pub format: String,
+
+    /// Fade applied at each cut point, in milliseconds
+    #[serde(default)]
+    pub fade_ms: Option<u32>,
+}
+
+/// Return a span of a completed (non-streaming) generation without re-synthesis.
+///
+/// Cut points are widened to codec frame boundaries and faded to avoid clicks.
+pub async fn get_range(
+    State(state): State<AppState>,
+    key_tenant: Option<Extension<KeyTenant>>,
+    headers: HeaderMap,
+    Path(request_id): Path<String>,
+    Query(query): Query<RangeQuery>,
+) -> Result<Response<Body>, ApiError> {
+    let tenant = tenant_id(&headers, key_tenant.as_ref())?;
+    let format = parse_format(&query.format)?;
+    let start = query
+        .start
+        .as_deref()
+        .map(parse_timestamp)
+        .transpose()?
+        .unwrap_or(0.0);
+    let end = query.end.as_deref().map(parse_timestamp).transpose()?;
+
+    let mut config = RangeConfig::default();
+    if let Some(fade_ms) = query.fade_ms {
+        if fade_ms > 1000 {
+            return Err(ApiError::bad_request(format!(
+                "fade_ms must be at most 1000, got {}",
+                fade_ms
+            )));
+        }
+        config.fade_ms = fade_ms;
+    }
+
+    let output = state
+        .engine
+        .output_store()
+        .get(&request_id, tenant.as_deref())?
+        .ok_or_else(|| {
+            ApiError::not_found(format!("No stored output for request {}", request_id))
+        })?;
+
+    let range = frame_aligned_range(
+        output.samples.len(),
+        output.sample_rate,
+        start,
+        end,
+        &config,
+    )?;
+    let clip = extract_range(&output.samples, output.sample_rate, start, end, &config)?;
+    let audio_bytes = AudioEncoder::new(output.sample_rate, 1).encode(&clip, format)?;
+
+    let rate = output.sample_rate as f32;
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, AudioEncoder::content_type(format))
+        .header("X-Request-Id", header_safe(&request_id))
+        .header(
+            "X-Range-Start-Secs",
+            format!("{:.3}", range.start as f32 / rate),
+        )
+        .header(
+            "X-Range-End-Secs",
+            format!("{:.3}", range.end as f32 / rate),
+        )
+        .header(
+            "Access-Control-Expose-Headers",
+            "X-Request-Id, X-Range-Start-Secs, X-Range-End-Secs",
+        )
+        .body(Body::from(audio_bytes))
+        .unwrap())
+}
+
+/// Fill the voice-cloning reference from a saved voice
+fn apply_saved_voice(
+    engine: &InferenceEngine,
+    tenant: Option<&str>,
+    req: &mut TTSRequest,
+) -> Result<(), ApiError> {
+    let Some(name) = &req.saved_voice else {
+        return Ok(());
+    };
+    let voice = engine
+        .voice_store()
+        .get(tenant, name)?
+        .ok_or_else(|| ApiError::not_found(format!("Voice {} not found", name)))?;
+    req.reference_audio = Some(voice.reference_audio);
+    req.reference_text = Some(voice.reference_text);
+    Ok(())
+}
+
+pub(super) fn parse_format(s: &str) -> Result<AudioFormat, ApiError> {
     match s.to_lowercase().as_str() {
         "wav" => Ok(AudioFormat::Wav),
         "raw_f32" | "pcm_f32" => Ok(AudioFormat::RawF32),
         "raw_i16" | "pcm_i16" => Ok(AudioFormat::RawI16),
+        "l16" | "pcm_s16be" => Ok(AudioFormat::L16),
+        "mulaw" | "ulaw" | "pcmu" => Ok(AudioFormat::MuLaw),
+        "alaw" | "pcma" => Ok(AudioFormat::ALaw),
         _ => Err(ApiError::bad_request(format!(
             "Unknown audio format: {}",
             s
         ))),
     }
 }
+
+/// Format a deprecation notice as an RFC 7234 `Warning` header value
+fn warning_header(message: &str) -> String {
+    format!("299 izwi \"{}\"", header_safe(message).replace('"', "'"))
+}
+
+/// Strip characters that are not valid in a header value
+fn header_safe(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_graphic() || *c == ' ')
+        .collect()
+}
+
+/// Subtitle file for request `request_id`
+pub(super) fn subtitle_response(
+    cues: &[Cue],
+    format: SubtitleFormat,
+    request_id: &str,
+) -> Response<Body> {
+    Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            format!("{}; charset=utf-8", format.content_type()),
+        )
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"subtitles.{}\"", format.extension()),
+        )
+        .header("X-Request-Id", header_safe(request_id))
+        .header("Access-Control-Expose-Headers", "X-Request-Id")
+        .body(Body::from(render(cues, format)))
+        .unwrap()
+}
+
+fn validate_pad_ms(pad_ms: u32) -> Result<(), ApiError> {
+    if pad_ms > 5000 {
+        return Err(ApiError::bad_request(format!(
+            "pad_ms must be at most 5000, got {}",
+            pad_ms
+        )));
+    }
+    Ok(())
+}
+
+fn validate_max_audio_seconds(secs: Option<f32>) -> Result<(), ApiError> {
+    match secs {
+        Some(s) if !s.is_finite() || s <= 0.0 => Err(ApiError::bad_request(format!(
+            "max_audio_seconds must be positive, got {}",
+            s
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Validate the requested output rate; formats with a fixed rate (G.711)
+/// default to it and reject any other
+fn output_sample_rate(format: AudioFormat, rate: Option<u32>) -> Result<Option<u32>, ApiError> {
+    match (rate, format.required_sample_rate()) {
+        (Some(r), _) if !(8000..=48000).contains(&r) => Err(ApiError::bad_request(format!(
+            "sample_rate must be between 8000 and 48000 Hz, got {}",
+            r
+        ))),
+        (Some(r), Some(required)) if r != required => Err(ApiError::bad_request(format!(
+            "{:?} output is {} Hz, got sample_rate {}",
+            format, required, r
+        ))),
+        (rate, required) => Ok(rate.or(required)),
+    }
+}
+
+pub(super) fn validate_target_lufs(target: Option<f32>) -> Result<(), ApiError> {
+    match target {
+        Some(t) if !(-70.0..=0.0).contains(&t) => Err(ApiError::bad_request(format!(
+            "target_lufs must be between -70 and 0, got {}",
+            t
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use izwi_core::{AuthConfig, EngineConfig, ServerConfig};
+    use tower::ServiceExt;
+
+    use crate::state::AppState;
+
+    #[test]
+    fn test_full_engine_returns_too_many_requests() {
+        let engine = EngineConfig {
+            max_waiting_requests: 1,
+            ..Default::default()
+        };
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&AuthConfig::default(), engine);
+        let router = crate::api::create_router(state.clone(), &ServerConfig::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        // Another generation holds the only slot
+        let _running = state.engine.admission().admit(1).unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/tts")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"text": "Hello there"}"#))
+            .unwrap();
+        let response = runtime.block_on(router.oneshot(request)).unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    }
+}
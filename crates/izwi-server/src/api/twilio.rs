@@ -0,0 +1,357 @@
+//! Twilio Media Streams bridge for phone calls
+//!
+//! Twilio opens a WebSocket per call and sends the caller's audio as base64
+//! mu-law at 8 kHz. Each utterance becomes a turn of a chat session, and the
+//! spoken reply is sent back as media frames followed by a mark, so barge-in
+//! can clear whatever Twilio has not played yet.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use super::models::parse_variant;
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::audio::{
+    decode_raw, AudioEncoder, AudioFormat, SilenceConfig, UtteranceDetector, UtteranceEvent,
+};
+use izwi_core::engine::ChatInput;
+
+/// Silence that ends the caller's turn
+const END_OF_TURN_MS: u32 = 700;
+
+/// Longest utterance sent as one turn
+const MAX_UTTERANCE_MS: u32 = 30_000;
+
+/// Level below which call audio counts as silence (line noise sits higher
+/// than in studio recordings)
+const SPEECH_THRESHOLD_DB: f32 = -40.0;
+
+/// Media Streams carry 8 kHz mu-law
+const CALL_SAMPLE_RATE: u32 = 8000;
+const CALL_ENCODING: &str = "audio/x-mulaw";
+
+/// Outbound media frame: 20 ms of mu-law
+const FRAME_BYTES: usize = 160;
+
+/// Messages from Twilio
+#[derive(Debug, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum InboundMessage {
+    Connected,
+    Start {
+        start: StartInfo,
+    },
+    Media {
+        media: InboundMedia,
+    },
+    /// A mark we sent, echoed once the audio before it has played
+    Mark {
+        mark: MarkInfo,
+    },
+    Dtmf,
+    Stop,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartInfo {
+    stream_sid: String,
+    #[serde(default)]
+    call_sid: String,
+    /// `<Parameter>` values from the TwiML `<Stream>`
+    #[serde(default)]
+    custom_parameters: HashMap<String, String>,
+    media_format: MediaFormat,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MediaFormat {
+    encoding: String,
+    sample_rate: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboundMedia {
+    #[serde(default)]
+    track: Option<String>,
+    payload: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MarkInfo {
+    name: String,
+}
+
+/// Messages to Twilio
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum OutboundMessage<'a> {
+    Media {
+        #[serde(rename = "streamSid")]
+        stream_sid: &'a str,
+        media: OutboundMedia,
+    },
+    Mark {
+        #[serde(rename = "streamSid")]
+        stream_sid: &'a str,
+        mark: MarkInfo,
+    },
+    /// Drop audio sent but not yet played
+    Clear {
+        #[serde(rename = "streamSid")]
+        stream_sid: &'a str,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct OutboundMedia {
+    payload: String,
+}
+
+/// Accept a Media Streams connection
+pub async fn media_stream(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        if let Err(e) = run_call(state, socket).await {
+            warn!("Twilio media stream failed: {}", e.message);
+        }
+    })
+}
+
+/// One call in progress
+struct Call {
+    stream_sid: String,
+    session_id: String,
+    detector: UtteranceDetector,
+    /// Caller audio waiting for the reply being generated
+    queued: Vec<f32>,
+    replying: bool,
+    /// Marks sent after replies that Twilio has not played yet
+    playing: HashSet<String>,
+    replies: usize,
+}
+
+async fn run_call(state: AppState, mut socket: WebSocket) -> Result<(), ApiError> {
+    let Some(start) = wait_for_start(&mut socket).await? else {
+        return Ok(());
+    };
+    let params = &start.custom_parameters;
+    let (identity, _permit) = state
+        .api_keys
+        .authenticate_stream(params.get("api_key").map(String::as_str))?;
+    if start.media_format.encoding != CALL_ENCODING
+        || start.media_format.sample_rate != CALL_SAMPLE_RATE
+    {
+        return Err(ApiError::bad_request(format!(
+            "Unsupported media format {} at {} Hz",
+            start.media_format.encoding, start.media_format.sample_rate
+        )));
+    }
+
+    let model = params.get("model").map(|m| parse_variant(m)).transpose()?;
+    let session = state
+        .engine_core
+        .create_session(model, params.get("system_prompt").cloned(), Vec::new())
+        .await?;
+    // Call SIDs lead back to phone numbers
+    let privacy = &state.engine.config().privacy;
+    info!(
+        "Twilio call {} connected to session {}{}",
+        privacy.redact(&start.call_sid),
+        session.id,
+        identity
+            .map(|id| format!(" for '{}'", id.0))
+            .unwrap_or_default()
+    );
+
+    let silence = SilenceConfig {
+        threshold_db: SPEECH_THRESHOLD_DB,
+        ..Default::default()
+    };
+    let mut call = Call {
+        stream_sid: start.stream_sid,
+        session_id: session.id,
+        detector: UtteranceDetector::new(
+            END_OF_TURN_MS,
+            MAX_UTTERANCE_MS,
+            CALL_SAMPLE_RATE,
+            &silence,
+        ),
+        queued: Vec::new(),
+        replying: false,
+        playing: HashSet::new(),
+        replies: 0,
+    };
+    let result = bridge(&state, &mut socket, &mut call).await;
+    state.engine_core.delete_session(&call.session_id).await;
+    info!(
+        "Twilio call {} ended",
+        state.engine.config().privacy.redact(&start.call_sid)
+    );
+    result
+}
+
+/// Read until the `start` message, or `None` if the call ends first
+async fn wait_for_start(socket: &mut WebSocket) -> Result<Option<StartInfo>, ApiError> {
+    while let Some(message) = socket.recv().await {
+        let text = match message.map_err(|e| ApiError::bad_request(e.to_string()))? {
+            Message::Text(text) => text,
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        };
+        match serde_json::from_str(&text) {
+            Ok(InboundMessage::Start { start }) => return Ok(Some(start)),
+            Ok(InboundMessage::Stop) => return Ok(None),
+            Ok(_) => {}
+            Err(e) => debug!("Ignoring Twilio message: {}", e),
+        }
+    }
+    Ok(None)
+}
+
+/// Relay caller audio to chat turns and replies back until the call ends
+async fn bridge(state: &AppState, socket: &mut WebSocket, call: &mut Call) -> Result<(), ApiError> {
+    let (reply_tx, mut reply_rx) = mpsc::channel(1);
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let Some(message) = message else {
+                    return Ok(());
+                };
+                let text = match message.map_err(|e| ApiError::bad_request(e.to_string()))? {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Ok(()),
+                    _ => continue,
+                };
+                match serde_json::from_str(&text) {
+                    Ok(InboundMessage::Media { media }) => {
+                        if media.track.as_deref().is_some_and(|t| t != "inbound") {
+                            continue;
+                        }
+                        let bytes = base64::engine::general_purpose::STANDARD
+                            .decode(&media.payload)
+                            .map_err(|e| ApiError::bad_request(format!("Invalid media: {}", e)))?;
+                        let samples = decode_raw(&bytes, AudioFormat::MuLaw)?;
+                        for event in call.detector.push(&samples) {
+                            match event {
+                                UtteranceEvent::Started if !call.playing.is_empty() => {
+                                    // Barge-in: stop the reply the caller is talking over
+                                    call.playing.clear();
+                                    let clear = OutboundMessage::Clear {
+                                        stream_sid: &call.stream_sid,
+                                    };
+                                    send(socket, &clear).await?;
+                                }
+                                UtteranceEvent::Started => {}
+                                UtteranceEvent::Ended(utterance) => {
+                                    call.queued.extend(utterance);
+                                    if !call.replying {
+                                        start_turn(state, call, reply_tx.clone())?;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Ok(InboundMessage::Mark { mark }) => {
+                        call.playing.remove(&mark.name);
+                    }
+                    Ok(InboundMessage::Stop) => return Ok(()),
+                    Ok(_) => {}
+                    Err(e) => debug!("Ignoring Twilio message: {}", e),
+                }
+            }
+            Some(reply) = reply_rx.recv() => {
+                call.replying = false;
+                match reply {
+                    // The caller has moved on, so answer what they said since
+                    Ok(_) if !call.queued.is_empty() || call.detector.in_speech() => {
+                        debug!("Dropping reply the caller talked over");
+                    }
+                    Ok(audio) => play(socket, call, audio).await?,
+                    Err(e) => warn!("Twilio call turn failed: {}", e.message),
+                }
+                if !call.queued.is_empty() {
+                    start_turn(state, call, reply_tx.clone())?;
+                }
+            }
+        }
+    }
+}
+
+/// Send the queued caller audio as the next turn; the mu-law reply arrives
+/// on `replies`
+fn start_turn(
+    state: &AppState,
+    call: &mut Call,
+    replies: mpsc::Sender<Result<Vec<u8>, ApiError>>,
+) -> Result<(), ApiError> {
+    let samples = std::mem::take(&mut call.queued);
+    let wav = AudioEncoder::new(CALL_SAMPLE_RATE, 1).encode(&samples, AudioFormat::Wav)?;
+    let input = ChatInput {
+        audio: Some(base64::engine::general_purpose::STANDARD.encode(wav)),
+        ..Default::default()
+    };
+    let engine = state.engine_core.clone();
+    let session_id = call.session_id.clone();
+    call.replying = true;
+    tokio::spawn(async move {
+        let reply = async {
+            let (_, output) = engine.chat_turn(&session_id, input).await?;
+            let audio = &output.audio;
+            Ok(AudioEncoder::new(audio.sample_rate, 1)
+                .encode(&audio.samples, AudioFormat::MuLaw)?)
+        };
+        let _ = replies.send(reply.await).await;
+    });
+    Ok(())
+}
+
+/// Send a reply as media frames followed by a mark
+async fn play(socket: &mut WebSocket, call: &mut Call, audio: Vec<u8>) -> Result<(), ApiError> {
+    if audio.is_empty() {
+        return Ok(());
+    }
+    for frame in audio.chunks(FRAME_BYTES) {
+        let media = OutboundMedia {
+            payload: base64::engine::general_purpose::STANDARD.encode(frame),
+        };
+        send(
+            socket,
+            &OutboundMessage::Media {
+                stream_sid: &call.stream_sid,
+                media,
+            },
+        )
+        .await?;
+    }
+    call.replies += 1;
+    let name = format!("reply-{}", call.replies);
+    call.playing.insert(name.clone());
+    send(
+        socket,
+        &OutboundMessage::Mark {
+            stream_sid: &call.stream_sid,
+            mark: MarkInfo { name },
+        },
+    )
+    .await
+}
+
+async fn send(socket: &mut WebSocket, message: &OutboundMessage<'_>) -> Result<(), ApiError> {
+    let text = serde_json::to_string(message)
+        .map_err(|e| ApiError::internal(format!("Failed to encode message: {}", e)))?;
+    socket
+        .send(Message::Text(text))
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Failed to send to Twilio: {}", e)))
+}
@@ -0,0 +1,71 @@
+//! Resumable chunked uploads of large ASR inputs
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+
+use crate::auth::ApiKeyIdentity;
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::upload::{UploadPart, UploadStatus};
+
+/// API key an upload belongs to
+fn owner(identity: &Option<Extension<ApiKeyIdentity>>) -> Option<&str> {
+    identity
+        .as_ref()
+        .map(|Extension(ApiKeyIdentity(name))| name.as_str())
+}
+
+/// Start an upload
+pub async fn create_upload(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+) -> Result<(StatusCode, Json<UploadStatus>), ApiError> {
+    let status = state.uploads.create(owner(&identity))?;
+    Ok((StatusCode::CREATED, Json(status)))
+}
+
+/// Parts received so far, to resume an interrupted upload
+pub async fn get_upload(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadStatus>, ApiError> {
+    Ok(Json(state.uploads.get(&id, owner(&identity))?))
+}
+
+/// Store one part, sent as the raw request body
+pub async fn put_part(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path((id, part)): Path<(String, u32)>,
+    body: Bytes,
+) -> Result<Json<UploadPart>, ApiError> {
+    let part = state
+        .uploads
+        .put_part(&id, owner(&identity), part, body)
+        .await?;
+    Ok(Json(part))
+}
+
+/// Join the parts so the upload can be transcribed
+pub async fn complete_upload(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadStatus>, ApiError> {
+    Ok(Json(state.uploads.complete(&id, owner(&identity)).await?))
+}
+
+/// Delete an upload
+pub async fn delete_upload(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state.uploads.remove(&id, owner(&identity))?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,51 @@
+//! Usage accounting endpoints
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::auth::ApiKeyIdentity;
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::usage::unix_now;
+use izwi_core::UsageReport;
+
+/// Usage query (times in Unix seconds)
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Start of the window (defaults to 24 hours before `until`)
+    #[serde(default)]
+    pub since: Option<u64>,
+    /// End of the window (defaults to now)
+    #[serde(default)]
+    pub until: Option<u64>,
+    /// Only count requests made with this key
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// Usage of requests finished within a time window
+///
+/// Callers authenticated with an API key only see their own usage.
+pub async fn get_usage(
+    State(state): State<AppState>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, ApiError> {
+    let until = query.until.unwrap_or_else(|| unix_now() + 1);
+    let since = query.since.unwrap_or(until.saturating_sub(24 * 60 * 60));
+    if since >= until {
+        return Err(ApiError::bad_request("since must be before until"));
+    }
+
+    let api_key = match (identity, query.api_key) {
+        (Some(Extension(ApiKeyIdentity(name))), Some(wanted)) if wanted != name => {
+            return Err(ApiError::forbidden("Cannot read another key's usage"));
+        }
+        (Some(Extension(ApiKeyIdentity(name))), _) => Some(name),
+        (None, wanted) => wanted,
+    };
+    Ok(Json(state.usage.report(since, until, api_key.as_deref())))
+}
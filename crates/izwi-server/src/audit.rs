@@ -0,0 +1,112 @@
+//! Audit logging of API requests
+
+use axum::{
+    body::Body,
+    extract::{OriginalUri, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::time::Instant;
+
+use crate::auth::ApiKeyIdentity;
+use crate::error::{current_request_id, ApiError};
+use crate::state::AppState;
+use izwi_core::audit::{now_ms, AuditOutcome, AuditRecord};
+use izwi_core::AuditPrivacy;
+
+/// Whether the request body is JSON that may carry input text
+fn is_json(request: &Request) -> bool {
+    request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// Every `text` string in a JSON body, e.g. a dialogue's lines, joined by
+/// newlines
+fn input_text(body: &[u8]) -> Option<String> {
+    fn collect<'a>(value: &'a Value, texts: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match value {
+                        Value::String(text) if key == "text" => texts.push(text),
+                        _ => collect(value, texts),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, texts)),
+            _ => {}
+        }
+    }
+
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let mut texts = Vec::new();
+    collect(&value, &mut texts);
+    (!texts.is_empty()).then(|| texts.join("\n"))
+}
+
+/// Append who made the request, what it asked for and how it ended to the
+/// audit log. Runs after authentication so the API key is known; durations
+/// end when the response headers are ready.
+pub async fn record_request(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(audit) = state.audit.clone() else {
+        return next.run(request).await;
+    };
+    let started = Instant::now();
+    let timestamp_ms = now_ms();
+    let method = request.method().to_string();
+    let endpoint = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().path(), |uri| uri.path())
+        .to_string();
+    let api_key = request
+        .extensions()
+        .get::<ApiKeyIdentity>()
+        .map(|ApiKeyIdentity(name)| name.clone());
+
+    let (request, text) = if audit.privacy() != AuditPrivacy::None && is_json(&request) {
+        let limit = state.engine.config().limits.max_body_bytes();
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, limit).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                return ApiError::bad_request("Failed to read request body").into_response();
+            }
+        };
+        let text = input_text(&bytes);
+        (Request::from_parts(parts, Body::from(bytes)), text)
+    } else {
+        (request, None)
+    };
+
+    let response = next.run(request).await;
+    let status = response.status().as_u16();
+    let record = AuditRecord {
+        timestamp_ms,
+        request_id: current_request_id(),
+        api_key,
+        method,
+        endpoint,
+        status,
+        outcome: AuditOutcome::from_status(status),
+        duration_ms: started.elapsed().as_millis() as u64,
+        text_sha256: None,
+        text: None,
+    }
+    .with_text(
+        text.as_deref(),
+        audit.privacy(),
+        &state.engine.config().privacy,
+    );
+    audit.record(record);
+    response
+}
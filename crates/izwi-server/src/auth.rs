@@ -0,0 +1,442 @@
+//! API key authentication and per-key rate limiting
+
+use axum::{
+    body::Body,
+    extract::{Query, Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info};
+
+use crate::error::ApiError;
+use crate::state::AppState;
+use izwi_core::engine::Priority;
+use izwi_core::{ApiKeyConfig, AuthConfig};
+
+/// Identity of the API key that authenticated a request
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity(pub String);
+
+/// Highest scheduling priority the authenticated key may request
+#[derive(Debug, Clone, Copy)]
+pub struct MaxPriority(pub Priority);
+
+/// Tenant the authenticated key acts for (`None` for the shared store)
+#[derive(Debug, Clone)]
+pub struct KeyTenant(pub Option<String>);
+
+/// Present when the authenticated key may use the operator endpoints
+#[derive(Debug, Clone, Copy)]
+pub struct AdminScope;
+
+/// Layout of `api_keys_file`
+#[derive(Debug, Deserialize)]
+struct KeysFile {
+    #[serde(default)]
+    keys: Vec<ApiKeyConfig>,
+}
+
+/// Requests-per-minute limiter refilling continuously
+#[derive(Debug)]
+struct TokenBucket {
+    per_minute: u32,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            tokens: per_minute as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take one request, or return the seconds until one is available
+    fn try_take(&mut self, now: Instant) -> Result<(), u64> {
+        let rate = self.per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.per_minute as f64);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / rate).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+/// One accepted key and its limits
+#[derive(Debug)]
+struct KeyEntry {
+    name: String,
+    /// `None` when the key has no request rate limit
+    bucket: Option<Mutex<TokenBucket>>,
+    /// `None` when the key has no stream limit
+    streams: Option<StreamSlots>,
+    max_priority: Priority,
+    tenant: Option<String>,
+    admin: bool,
+}
+
+impl KeyEntry {
+    /// Apply the key's rate limit
+    fn admit(&self) -> Result<(), ApiError> {
+        if let Some(bucket) = &self.bucket {
+            let mut bucket = bucket.lock().unwrap_or_else(|e| e.into_inner());
+            if let Err(retry_after) = bucket.try_take(Instant::now()) {
+                debug!("Rate limit reached for API key '{}'", self.name);
+                return Err(ApiError::too_many_requests(
+                    format!(
+                        "Rate limit of {} requests per minute reached",
+                        bucket.per_minute
+                    ),
+                    retry_after,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a stream slot, if the key has a stream limit
+    fn take_stream(&self) -> Result<Option<OwnedSemaphorePermit>, ApiError> {
+        self.streams.as_ref().map(StreamSlots::take).transpose()
+    }
+}
+
+/// Concurrent-stream slots of a key. Left on requests that have not taken
+/// one yet, for streaming routes to take.
+#[derive(Debug, Clone)]
+struct StreamSlots {
+    semaphore: Arc<Semaphore>,
+    max: u32,
+}
+
+impl StreamSlots {
+    fn take(&self) -> Result<OwnedSemaphorePermit, ApiError> {
+        self.semaphore.clone().try_acquire_owned().map_err(|_| {
+            ApiError::too_many_requests(
+                format!("Limit of {} concurrent streams reached", self.max),
+                1,
+            )
+        })
+    }
+}
+
+/// Accepted API keys, indexed by the SHA-256 of the secret
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    keys: HashMap<[u8; 32], KeyEntry>,
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+impl ApiKeys {
+    /// Collect inline keys and those in `api_keys_file`
+    pub fn load(config: &AuthConfig) -> anyhow::Result<Self> {
+        let mut entries = config.api_keys.clone();
+        if let Some(path) = &config.api_keys_file {
+            let text = std::fs::read_to_string(path).map_err(|e| {
+                anyhow::anyhow!("Failed to read API keys file {}: {}", path.display(), e)
+            })?;
+            let file: KeysFile = toml::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("Invalid API keys file {}: {}", path.display(), e))?;
+            entries.extend(file.keys);
+        }
+
+        let mut keys = HashMap::new();
+        for entry in entries {
+            entry.validate()?;
+            let per_minute = entry
+                .requests_per_minute
+                .unwrap_or(config.requests_per_minute);
+            let max_streams = entry
+                .max_concurrent_streams
+                .unwrap_or(config.max_concurrent_streams);
+            let key = KeyEntry {
+                name: entry.name.clone(),
+                bucket: (per_minute > 0).then(|| Mutex::new(TokenBucket::new(per_minute))),
+                streams: (max_streams > 0).then(|| StreamSlots {
+                    semaphore: Arc::new(Semaphore::new(max_streams as usize)),
+                    max: max_streams,
+                }),
+                max_priority: entry
+                    .max_priority
+                    .unwrap_or_else(|| config.default_max_priority()),
+                tenant: entry.tenant.clone(),
+                admin: entry.admin,
+            };
+            if keys.insert(digest(&entry.key), key).is_some() {
+                anyhow::bail!("Duplicate API key for '{}'", entry.name);
+            }
+        }
+
+        if !keys.is_empty() {
+            info!("API key authentication enabled ({} keys)", keys.len());
+        }
+        Ok(Self { keys })
+    }
+
+    /// Whether requests must present a key
+    pub fn enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Check a key presented somewhere other than the `Authorization`
+    /// header, such as in the opening message of a WebSocket, and take one
+    /// of its stream slots. Yields no identity when authentication is off.
+    pub fn authenticate_stream(
+        &self,
+        key: Option<&str>,
+    ) -> Result<(Option<ApiKeyIdentity>, Option<OwnedSemaphorePermit>), ApiError> {
+        if !self.enabled() {
+            return Ok((None, None));
+        }
+        let entry = key
+            .and_then(|key| self.lookup(key))
+            .ok_or_else(|| ApiError::unauthorized("Missing or invalid API key"))?;
+        entry.admit()?;
+        let permit = entry.take_stream()?;
+        Ok((Some(ApiKeyIdentity(entry.name.clone())), permit))
+    }
+
+    fn lookup(&self, key: &str) -> Option<&KeyEntry> {
+        self.keys.get(&digest(key))
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl izwi_grpc::Authenticator for ApiKeys {
+    fn authenticate(&self, token: Option<&str>) -> Result<izwi_grpc::Caller, izwi_grpc::Status> {
+        let key = token
+            .and_then(|token| self.lookup(token))
+            .ok_or_else(|| izwi_grpc::Status::unauthenticated("Missing or invalid API key"))?;
+        key.admit()
+            .map_err(|e| izwi_grpc::Status::resource_exhausted(e.message))?;
+        Ok(izwi_grpc::Caller {
+            name: key.name.clone(),
+            max_priority: key.max_priority,
+            admin: key.admin,
+            streams: key.streams.as_ref().map(|slots| slots.semaphore.clone()),
+        })
+    }
+}
+
+/// Bearer token from the `Authorization` header
+fn bearer_token(request: &Request) -> Option<&str> {
+    let value = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Requests asking for a stream on any route: server-sent events, a
+/// WebSocket upgrade or `?stream=true`. Routes that always stream are marked
+/// with [`stream_route`] instead.
+fn is_stream(request: &Request) -> bool {
+    let headers = request.headers();
+    let wants_events = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    let upgrade = headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+    let stream_query =
+        Query::<HashMap<String, String>>::try_from_uri(request.uri()).is_ok_and(|Query(query)| {
+            query
+                .get("stream")
+                .is_some_and(|value| value == "true" || value == "1")
+        });
+    wants_events || upgrade || stream_query
+}
+
+/// Keep a stream slot until the response body has been sent or dropped
+fn hold_permit(response: Response, permit: OwnedSemaphorePermit) -> Response {
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().map(move |chunk| {
+        let _permit = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+/// Reject requests without a valid key, enforce the key's limits and attach
+/// its identity to the request
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let api_keys = &state.api_keys;
+    if !api_keys.enabled() {
+        return Ok(next.run(request).await);
+    }
+
+    let key = bearer_token(&request)
+        .and_then(|token| api_keys.lookup(token))
+        .ok_or_else(|| ApiError::unauthorized("Missing or invalid API key"))?;
+    key.admit()?;
+    let permit = match &key.streams {
+        Some(_) if is_stream(&request) => key.take_stream()?,
+        Some(slots) => {
+            request.extensions_mut().insert(slots.clone());
+            None
+        }
+        None => None,
+    };
+
+    request
+        .extensions_mut()
+        .insert(ApiKeyIdentity(key.name.clone()));
+    request
+        .extensions_mut()
+        .insert(MaxPriority(key.max_priority));
+    request
+        .extensions_mut()
+        .insert(KeyTenant(key.tenant.clone()));
+    if key.admin {
+        request.extensions_mut().insert(AdminScope);
+    }
+    let response = next.run(request).await;
+    Ok(match permit {
+        Some(permit) => hold_permit(response, permit),
+        None => response,
+    })
+}
+
+/// Layer for routes that always stream: the request takes one of its key's
+/// stream slots unless it already holds one
+pub async fn stream_route(mut request: Request, next: Next) -> Result<Response, ApiError> {
+    let permit = request
+        .extensions_mut()
+        .remove::<StreamSlots>()
+        .map(|slots| slots.take())
+        .transpose()?;
+    let response = next.run(request).await;
+    Ok(match permit {
+        Some(permit) => hold_permit(response, permit),
+        None => response,
+    })
+}
+
+/// Refuse keys without the admin scope. Without authentication every
+/// request is allowed.
+pub fn require_admin(
+    api_keys: &ApiKeys,
+    admin: Option<Extension<AdminScope>>,
+) -> Result<(), ApiError> {
+    if api_keys.enabled() && admin.is_none() {
+        return Err(ApiError::forbidden(
+            "This API key may not use admin endpoints",
+        ));
+    }
+    Ok(())
+}
+
+/// Priority for a request, defaulting to normal. Without authentication any
+/// priority is accepted; otherwise it may not exceed the key's maximum.
+pub fn request_priority(
+    requested: Option<Priority>,
+    max_priority: Option<Extension<MaxPriority>>,
+) -> Result<Priority, ApiError> {
+    let priority = requested.unwrap_or_default();
+    match max_priority {
+        Some(Extension(MaxPriority(max))) if priority > max => Err(ApiError::forbidden(format!(
+            "Priority '{}' exceeds this API key's maximum of '{}'",
+            priority.as_str(),
+            max.as_str()
+        ))),
+        _ => Ok(priority),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_streams_count_against_key_limit() {
+        let auth = AuthConfig {
+            api_keys: vec![ApiKeyConfig {
+                name: "client".into(),
+                key: "client-secret".into(),
+                requests_per_minute: None,
+                max_concurrent_streams: Some(1),
+                max_priority: None,
+                tenant: None,
+                admin: false,
+            }],
+            ..Default::default()
+        };
+        // The engine builds a blocking HTTP client, which cannot be done inside a runtime
+        let state = AppState::for_tests(&auth, Default::default());
+        let router = Router::new()
+            .route(
+                "/speak",
+                get(|| async { "audio" }).layer(middleware::from_fn(stream_route)),
+            )
+            .route("/status", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_api_key,
+            ))
+            .with_state(state);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let send = |uri: &str, header: Option<(header::HeaderName, &str)>| {
+            let mut builder = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, "Bearer client-secret");
+            if let Some((name, value)) = header {
+                builder = builder.header(name, value);
+            }
+            let request = builder.body(Body::empty()).unwrap();
+            runtime.block_on(router.clone().oneshot(request)).unwrap()
+        };
+
+        // The slot is held until the streamed body is dropped
+        let stream = send("/speak", None);
+        assert_eq!(stream.status(), StatusCode::OK);
+        assert_eq!(send("/speak", None).status(), StatusCode::TOO_MANY_REQUESTS);
+        for (uri, header) in [
+            ("/status?stream=true", None),
+            ("/status", Some((header::ACCEPT, "text/event-stream"))),
+            ("/status", Some((header::UPGRADE, "websocket"))),
+        ] {
+            assert_eq!(
+                send(uri, header).status(),
+                StatusCode::TOO_MANY_REQUESTS,
+                "{}",
+                uri
+            );
+        }
+        assert_eq!(send("/status", None).status(), StatusCode::OK);
+
+        drop(stream);
+        assert_eq!(send("/status?stream=true", None).status(), StatusCode::OK);
+    }
+}
@@ -1,59 +1,146 @@
 //! API error handling
 
 use axum::{
-    http::StatusCode,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use izwi_core::ErrorCode;
 use serde_json::json;
 
+/// Header carrying the request ID in both directions
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request ID that is echoed back
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    /// ID of the HTTP request being handled, reported in error bodies
+    static REQUEST_ID: String;
+}
+
 /// API error type
 pub struct ApiError {
     pub status: StatusCode,
+    /// Machine-readable code sent alongside the message
+    pub code: ErrorCode,
     pub message: String,
+    /// Seconds sent in the Retry-After header
+    pub retry_after: Option<u64>,
 }
 
 impl ApiError {
-    pub fn bad_request(msg: impl Into<String>) -> Self {
+    /// Error reported with `code` and the status that code maps to
+    pub fn with_code(code: ErrorCode, msg: impl Into<String>) -> Self {
         Self {
-            status: StatusCode::BAD_REQUEST,
+            status: StatusCode::from_u16(code.http_status())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            code,
             message: msg.into(),
+            retry_after: None,
         }
     }
 
+    pub fn bad_request(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::InvalidInput, msg)
+    }
+
+    pub fn unauthorized(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::Unauthorized, msg)
+    }
+
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::Forbidden, msg)
+    }
+
     pub fn not_found(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::NotFound, msg)
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::Conflict, msg)
+    }
+
+    pub fn daemon_unavailable(msg: impl Into<String>) -> Self {
+        Self::with_code(ErrorCode::DaemonUnavailable, msg)
+    }
+
+    pub fn too_many_requests(msg: impl Into<String>, retry_after_secs: u64) -> Self {
         Self {
-            status: StatusCode::NOT_FOUND,
-            message: msg.into(),
+            retry_after: Some(retry_after_secs),
+            ..Self::with_code(ErrorCode::Overloaded, msg)
         }
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: msg.into(),
-        }
+        Self::with_code(ErrorCode::Internal, msg)
     }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let request_id = current_request_id();
         let body = Json(json!({
             "error": {
                 "message": self.message,
-                "code": self.status.as_u16()
+                "code": self.code,
+                "status": self.status.as_u16(),
+                "request_id": request_id
             }
         }));
-        (self.status, body).into_response()
+        let mut response = (self.status, body).into_response();
+        if let Some(secs) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
     }
 }
 
 impl From<izwi_core::Error> for ApiError {
     fn from(err: izwi_core::Error) -> Self {
         match &err {
-            izwi_core::Error::ModelNotFound(_) => ApiError::not_found(err.to_string()),
-            izwi_core::Error::ConfigError(_) => ApiError::bad_request(err.to_string()),
-            _ => ApiError::internal(err.to_string()),
+            izwi_core::Error::Overloaded {
+                retry_after_secs, ..
+            } => ApiError::too_many_requests(err.to_string(), *retry_after_secs),
+            _ => ApiError::with_code(err.code(), err.to_string()),
         }
     }
 }
+
+/// Tag each request with an ID, reusing the client's `X-Request-Id` when it
+/// is usable, so error bodies and logs can be correlated.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response
+            .headers_mut()
+            .entry(&REQUEST_ID_HEADER)
+            .or_insert(value);
+    }
+    response
+}
+
+/// ID of the HTTP request being handled, outside of a request `None`
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
@@ -1,18 +1,72 @@
 //! Izwi TTS Server - HTTP API for Qwen3-TTS inference
 
+use clap::Parser;
+use std::path::PathBuf;
 use tokio::signal;
 use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod api;
+mod audit;
+mod auth;
 mod error;
 mod state;
 
-use izwi_core::{EngineConfig, InferenceEngine};
+use izwi_core::{
+    AuditLog, ConfigLoader, Engine, EngineCoreConfig, InferenceEngine, LongformJobs, UploadStore,
+    UsageLedger,
+};
 use state::AppState;
 
+/// Izwi TTS server
+#[derive(Parser, Debug)]
+#[command(
+    name = "izwi",
+    version,
+    about = "HTTP API server for Izwi TTS inference"
+)]
+struct Args {
+    /// Configuration file (TOML, YAML or JSON)
+    #[arg(short, long, env = "IZWI_CONFIG_PATH")]
+    config: Option<PathBuf>,
+
+    /// Address to bind
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Port to listen on
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Directory to store downloaded models
+    #[arg(long)]
+    models_dir: Option<PathBuf>,
+}
+
+impl Args {
+    /// Layer file, environment and flag settings
+    fn loader(&self) -> ConfigLoader {
+        let mut loader = ConfigLoader::new();
+        if let Some(path) = &self.config {
+            loader = loader.with_file(path);
+        }
+        if let Some(host) = &self.host {
+            loader = loader.set_override("server.host", host);
+        }
+        if let Some(port) = self.port {
+            loader = loader.set_override("server.port", port);
+        }
+        if let Some(models_dir) = &self.models_dir {
+            loader = loader.set_override("engine.models_dir", models_dir.display());
+        }
+        loader
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
     // Initialize logging
     tracing_subscriber::registry()
         .with(
@@ -25,16 +79,60 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting Izwi TTS Server");
 
     // Load configuration
-    let config = EngineConfig::default();
+    let izwi_core::IzwiConfig {
+        engine: config,
+        server: server_config,
+    } = args.loader().load()?;
+    if let Some(path) = &args.config {
+        info!("Loaded configuration from {:?}", path);
+    }
     info!("Models directory: {:?}", config.models_dir);
+    let core_config = EngineCoreConfig {
+        use_metal: config.use_metal,
+        device: config.device,
+        devices: config.devices.clone(),
+        kv_cache_dtype: config.kv_dtype(),
+        scheduling_policy: config.scheduling_policy,
+        max_seq_len: config.max_sequence_length,
+        chat_context_tokens: config.chat_context_tokens,
+        chat_context_policy: config.chat_context_policy,
+        kv_window_tokens: config.kv_window_tokens,
+        kv_sink_tokens: config.kv_sink_tokens,
+        output_cache: config.output_cache.clone(),
+        warmup: config.warmup.clone(),
+        limits: config.limits.clone(),
+        max_waiting_requests: config.max_waiting_requests,
+        max_queued_tokens: config.max_queued_tokens,
+        ..Default::default()
+    };
 
     // Create inference engine
     let engine = InferenceEngine::new(config)?;
-    let state = AppState::new(engine);
+    // Both engines count buffered output against the one global cap
+    let engine_core = Engine::with_output_memory(core_config, engine.output_memory())?;
+    let api_keys = auth::ApiKeys::load(&server_config.auth)?;
+    let usage = UsageLedger::new(&server_config.usage);
+    let audit = AuditLog::from_config(&server_config.audit)?;
+    let storage = izwi_core::storage::from_config(&server_config.storage)?;
+    if let Some(storage) = &storage {
+        info!("Publishing async job results to {}", storage);
+    }
+    let uploads = UploadStore::from_config(&server_config.uploads)?;
+    let longform = LongformJobs::new(server_config.longform.clone());
+    let state = AppState::new(
+        engine,
+        engine_core,
+        api_keys,
+        usage,
+        audit,
+        storage,
+        uploads,
+        longform,
+    );
 
     // Start all daemons on server startup
     info!("Starting daemons...");
-    let engine_ref = state.engine.read().await;
+    let engine_ref = &state.engine;
 
     // Start TTS daemon
     if let Err(e) = engine_ref.ensure_daemon_running() {
@@ -50,14 +148,45 @@ async fn main() -> anyhow::Result<()> {
         info!("ASR daemon started");
     }
 
-    drop(engine_ref);
+    // Warm up in the background so the listener comes up right away
+    if state.engine_core.config().warmup.enabled {
+        let engine_core = state.engine_core.clone();
+        tokio::spawn(async move {
+            match engine_core.warmup().await {
+                Ok(report) => info!("Warmup finished in {:.0} ms", report.total_ms),
+                Err(e) => warn!("Warmup failed: {}", e),
+            }
+        });
+    }
+
+    // Start gRPC server alongside HTTP
+    #[cfg(feature = "grpc")]
+    {
+        let grpc_addr: std::net::SocketAddr =
+            format!("{}:{}", server_config.host, server_config.grpc_port).parse()?;
+        let service =
+            izwi_grpc::IzwiGrpcService::new(state.engine.clone(), state.engine_core.clone());
+        // Calls present the same API keys as HTTP requests
+        let authenticator = state
+            .api_keys
+            .enabled()
+            .then(|| state.api_keys.clone() as std::sync::Arc<dyn izwi_grpc::Authenticator>);
+        tokio::spawn(async move {
+            let shutdown = async {
+                let _ = signal::ctrl_c().await;
+            };
+            if let Err(e) = izwi_grpc::serve(grpc_addr, service, authenticator, shutdown).await {
+                warn!("gRPC server error: {}", e);
+            }
+        });
+    }
 
     // Build router
-    let app = api::create_router(state.clone());
+    let app = api::create_router(state.clone(), &server_config);
 
     // Start server
-    let addr = "0.0.0.0:8080";
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let addr = server_config.bind_address();
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
     info!("Server listening on http://{}", addr);
 
     // Clone state for shutdown handler
@@ -102,7 +231,7 @@ async fn shutdown_signal(state: AppState) {
 
     // Cleanup: stop all daemons
     info!("Stopping all daemons...");
-    let engine = state.engine.read().await;
+    let engine = &state.engine;
     if let Err(e) = engine.stop_all_daemons() {
         warn!("Error stopping daemons: {}", e);
     } else {
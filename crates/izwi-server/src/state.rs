@@ -1,19 +1,81 @@
 //! Application state management
 
-use izwi_core::InferenceEngine;
+use izwi_core::{
+    AuditLog, Engine, InferenceEngine, LongformJobs, SharedStorage, UploadStore, UsageLedger,
+};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+
+use crate::auth::ApiKeys;
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
-    pub engine: Arc<RwLock<InferenceEngine>>,
+    /// Generation engine, shared without a global lock
+    pub engine: Arc<InferenceEngine>,
+    /// Scheduling engine holding queued requests
+    pub engine_core: Arc<Engine>,
+    /// Accepted API keys (authentication is off when empty)
+    pub api_keys: Arc<ApiKeys>,
+    /// Usage of finished requests
+    pub usage: Arc<UsageLedger>,
+    /// Audit log of API requests (`None` when auditing is off)
+    pub audit: Option<Arc<AuditLog>>,
+    /// Where async job results are published (`None` keeps them in memory)
+    pub storage: Option<SharedStorage>,
+    /// Chunked uploads of large ASR inputs
+    pub uploads: Arc<UploadStore>,
+    pub longform: Arc<LongformJobs>,
 }
 
 impl AppState {
-    pub fn new(engine: InferenceEngine) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        engine: InferenceEngine,
+        engine_core: Engine,
+        api_keys: ApiKeys,
+        usage: UsageLedger,
+        audit: Option<AuditLog>,
+        storage: Option<SharedStorage>,
+        uploads: UploadStore,
+        longform: LongformJobs,
+    ) -> Self {
         Self {
-            engine: Arc::new(RwLock::new(engine)),
+            engine: Arc::new(engine),
+            engine_core: Arc::new(engine_core),
+            api_keys: Arc::new(api_keys),
+            usage: Arc::new(usage),
+            audit: audit.map(Arc::new),
+            storage,
+            uploads: Arc::new(uploads),
+            longform: Arc::new(longform),
         }
     }
 }
+
+#[cfg(test)]
+impl AppState {
+    /// State over a scratch models directory, with `auth` and `engine`
+    /// settings and the remaining services at their defaults
+    pub fn for_tests(auth: &izwi_core::AuthConfig, engine: izwi_core::EngineConfig) -> Self {
+        let dir = std::env::temp_dir().join(format!("izwi-server-test-{}", uuid::Uuid::new_v4()));
+        let engine = InferenceEngine::new(izwi_core::EngineConfig {
+            models_dir: dir.join("models"),
+            ..engine
+        })
+        .unwrap();
+        let uploads = izwi_core::UploadConfig {
+            dir: dir.join("uploads"),
+            ..Default::default()
+        };
+        Self::new(
+            engine,
+            Engine::new(Default::default()).unwrap(),
+            ApiKeys::load(auth).unwrap(),
+            UsageLedger::new(&Default::default()),
+            None,
+            None,
+            UploadStore::from_config(&uploads).unwrap(),
+            LongformJobs::new(Default::default()),
+        )
+    }
+}
@@ -0,0 +1,163 @@
+"""
+Listeners for the inference daemons.
+
+A daemon listens on a Unix socket (--socket PATH), a loopback TCP port
+(--tcp HOST:PORT) or, on Windows, a named pipe (--pipe NAME). Every listener
+hands out connections with the socket methods the daemons use (recv, sendall,
+close), and its accept() raises socket.timeout once a second so the daemon can
+check whether it should keep running.
+"""
+
+import os
+import socket
+import sys
+
+ACCEPT_TIMEOUT_SECS = 1.0
+PIPE_BUFFER_SIZE = 65536
+
+
+def add_arguments(parser, default_socket: str):
+    """Add the mutually exclusive --socket, --tcp and --pipe options."""
+    group = parser.add_mutually_exclusive_group()
+    group.add_argument("--socket", help=f"Unix socket path (default: {default_socket})")
+    group.add_argument("--tcp", metavar="HOST:PORT", help="Listen on a TCP address")
+    group.add_argument("--pipe", metavar="NAME", help="Listen on a Windows named pipe")
+    parser.set_defaults(default_socket=default_socket)
+
+
+def listen(args):
+    """Open the listener selected by the parsed arguments."""
+    if args.tcp:
+        host, _, port = args.tcp.rpartition(":")
+        return TcpListener(host or "127.0.0.1", int(port))
+    if args.pipe:
+        return PipeListener(args.pipe)
+    return UnixListener(args.socket or args.default_socket)
+
+
+class _SocketListener:
+    def __init__(self, sock: socket.socket, address: str):
+        sock.settimeout(ACCEPT_TIMEOUT_SECS)
+        self.sock = sock
+        self.address = address
+
+    def accept(self):
+        conn, addr = self.sock.accept()
+        # Connections block; only accept() polls
+        conn.settimeout(None)
+        return conn, addr
+
+    def close(self):
+        self.sock.close()
+
+
+class UnixListener(_SocketListener):
+    def __init__(self, path: str):
+        if os.path.exists(path):
+            os.unlink(path)
+        sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        sock.bind(path)
+        sock.listen(5)
+        os.chmod(path, 0o600)
+        self.path = path
+        super().__init__(sock, path)
+
+    def close(self):
+        super().close()
+        if os.path.exists(self.path):
+            os.unlink(self.path)
+
+
+class TcpListener(_SocketListener):
+    def __init__(self, host: str, port: int):
+        sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        sock.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+        sock.bind((host, port))
+        sock.listen(5)
+        super().__init__(sock, f"{host}:{port}")
+
+    def accept(self):
+        conn, addr = super().accept()
+        conn.setsockopt(socket.IPPROTO_TCP, socket.TCP_NODELAY, 1)
+        return conn, addr
+
+
+class PipeListener:
+    """Byte-mode named pipe server, one pipe instance per connection."""
+
+    def __init__(self, name: str):
+        if sys.platform != "win32":
+            raise OSError("Named pipes are only available on Windows")
+        import _winapi
+
+        self._winapi = _winapi
+        self.address = name if name.startswith("\\\\") else f"\\\\.\\pipe\\{name}"
+        self._handle = self._new_instance(first=True)
+
+    def _new_instance(self, first: bool = False):
+        w = self._winapi
+        flags = w.PIPE_ACCESS_DUPLEX | w.FILE_FLAG_OVERLAPPED
+        if first:
+            flags |= w.FILE_FLAG_FIRST_PIPE_INSTANCE
+        # Byte type and read mode are both 0
+        return w.CreateNamedPipe(
+            self.address,
+            flags,
+            w.PIPE_WAIT,
+            w.PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            w.NMPWAIT_WAIT_FOREVER,
+            w.NULL,
+        )
+
+    def accept(self):
+        w = self._winapi
+        handle = self._handle
+        ov = w.ConnectNamedPipe(handle, overlapped=True)
+        result = w.WaitForMultipleObjects(
+            [ov.event], False, int(ACCEPT_TIMEOUT_SECS * 1000)
+        )
+        if result == w.WAIT_TIMEOUT:
+            ov.cancel()
+            ov.GetOverlappedResult(True)
+            raise socket.timeout()
+        ov.GetOverlappedResult(True)
+        # The connected instance goes to the client; listen on a fresh one
+        self._handle = self._new_instance()
+        return PipeConnection(w, handle), self.address
+
+    def close(self):
+        if self._handle is not None:
+            self._winapi.CloseHandle(self._handle)
+            self._handle = None
+
+
+class PipeConnection:
+    """Connected pipe instance with the socket methods the daemons use."""
+
+    def __init__(self, winapi, handle):
+        self._winapi = winapi
+        self._handle = handle
+
+    def recv(self, size: int) -> bytes:
+        try:
+            ov, _ = self._winapi.ReadFile(self._handle, size, overlapped=True)
+            ov.GetOverlappedResult(True)
+        except BrokenPipeError:
+            return b""
+        return ov.getbuffer()
+
+    def sendall(self, data: bytes):
+        ov, _ = self._winapi.WriteFile(self._handle, data, overlapped=True)
+        written, _ = ov.GetOverlappedResult(True)
+        if written != len(data):
+            raise BrokenPipeError("Short write on named pipe")
+
+    def setsockopt(self, *args):
+        raise OSError("Named pipes have no socket options")
+
+    def close(self):
+        if self._handle is not None:
+            self._winapi.CloseHandle(self._handle)
+            self._handle = None
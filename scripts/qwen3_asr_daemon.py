@@ -1,7 +1,7 @@
 #!/usr/bin/env python3
 """
 Persistent Qwen3-ASR Daemon for speech-to-text transcription.
-Supports Qwen3-ASR-0.6B and Qwen3-ASR-1.7B models via Unix socket.
+Supports Qwen3-ASR-0.6B and Qwen3-ASR-1.7B models via Unix socket, TCP or named pipe.
 """
 
 import sys
@@ -24,6 +24,11 @@ from pathlib import Path
 from typing import Dict, Optional, Any, List
 from collections import OrderedDict
 
+try:
+    from . import daemon_transport
+except ImportError:
+    import daemon_transport
+
 import warnings
 
 warnings.filterwarnings("ignore")
@@ -102,10 +107,10 @@ class ASRModelCache:
 
 
 class Qwen3ASRDaemon:
-    """Qwen3-ASR Daemon that handles requests via Unix socket."""
+    """Qwen3-ASR Daemon that handles requests via Unix socket, TCP or named pipe."""
 
-    def __init__(self, socket_path: str = DEFAULT_SOCKET_PATH):
-        self.socket_path = socket_path
+    def __init__(self, listen_args):
+        self.listen_args = listen_args
         self.model_cache = ASRModelCache()
         self.running = False
         self.server_socket = None
@@ -243,16 +248,46 @@ class Qwen3ASRDaemon:
             self.model_cache.clear()
             return {"status": "ok", "unloaded": "all"}
 
+    def _bias_kwargs(self, request: dict) -> dict:
+        """Model arguments biasing decoding toward the request's hotwords.
+
+        Qwen3-ASR takes free-text context rather than per-word weights, so
+        hotwords are listed strongest first.
+        """
+        hotwords = request.get("hotwords") or []
+        if not hotwords:
+            return {}
+        ranked = sorted(hotwords, key=lambda h: h.get("boost", 1.0), reverse=True)
+        return {"context": ", ".join(h["word"] for h in ranked)}
+
+    def _translate(
+        self, model, request: dict, audio_path: str, text: str, language
+    ) -> str:
+        """Translate speech to English by forcing English output."""
+        if language and str(language).lower() in ("english", "en"):
+            return text
+        results = model.transcribe(
+            audio=audio_path, language="English", **self._bias_kwargs(request)
+        )
+        if results and len(results) > 0:
+            return results[0].text
+        return ""
+
     def _handle_transcribe(self, request: dict) -> dict:
         """Handle transcription request."""
         import torch
 
         audio_b64 = request.get("audio_base64", "")
+        # Completed chunked uploads arrive as a file owned by the server
+        uploaded_path = request.get("audio_path")
         model_id = request.get("model_id", DEFAULT_MODEL_06B)
         language = request.get("language", None)
+        task = request.get("task") or "transcribe"
 
-        if not audio_b64:
+        if not audio_b64 and not uploaded_path:
             return {"error": "No audio provided"}
+        if task not in ("transcribe", "translate"):
+            return {"error": f"Unknown task: {task}"}
 
         try:
             model_data = self._load_model(model_id)
@@ -261,7 +296,7 @@ class Qwen3ASRDaemon:
 
         model = model_data["model"]
 
-        audio_path = self._decode_audio_to_file(audio_b64)
+        audio_path = uploaded_path or self._decode_audio_to_file(audio_b64)
         if audio_path is None:
             return {"error": "Could not decode audio"}
 
@@ -279,6 +314,7 @@ class Qwen3ASRDaemon:
             results = model.transcribe(
                 audio=audio_path,
                 language=language,
+                **self._bias_kwargs(request),
             )
 
             if results and len(results) > 0:
@@ -297,13 +333,22 @@ class Qwen3ASRDaemon:
                     "audio_duration_secs": audio_duration_secs,
                 }
 
+            if task == "translate":
+                response["translation"] = self._translate(
+                    model,
+                    request,
+                    audio_path,
+                    response["transcription"],
+                    response["language"] or language,
+                )
+
             return response
 
         except Exception as e:
             traceback.print_exc(file=sys.stderr)
             return {"error": f"Transcription failed: {str(e)}"}
         finally:
-            if audio_path and os.path.exists(audio_path):
+            if audio_path and not uploaded_path and os.path.exists(audio_path):
                 os.unlink(audio_path)
 
     def _handle_transcribe_stream(self, request: dict, conn: socket.socket) -> None:
@@ -312,10 +357,13 @@ class Qwen3ASRDaemon:
         from transformers import TextIteratorStreamer
 
         audio_b64 = request.get("audio_base64", "")
+        # Completed chunked uploads arrive as a file owned by the server
+        uploaded_path = request.get("audio_path")
         model_id = request.get("model_id", DEFAULT_MODEL_06B)
         language = request.get("language", None)
+        task = request.get("task") or "transcribe"
 
-        if not audio_b64:
+        if not audio_b64 and not uploaded_path:
             self._send_stream_event(conn, "error", {"error": "No audio provided"})
             self._send_stream_event(conn, "done", {})
             return
@@ -330,7 +378,7 @@ class Qwen3ASRDaemon:
             return
 
         model = model_data["model"]
-        audio_path = self._decode_audio_to_file(audio_b64)
+        audio_path = uploaded_path or self._decode_audio_to_file(audio_b64)
 
         if audio_path is None:
             self._send_stream_event(conn, "error", {"error": "Could not decode audio"})
@@ -360,6 +408,7 @@ class Qwen3ASRDaemon:
                 results = model.transcribe(
                     audio=audio_path,
                     language=language,
+                    **self._bias_kwargs(request),
                 )
 
                 if results and len(results) > 0:
@@ -395,15 +444,20 @@ class Qwen3ASRDaemon:
                         time.sleep(char_delay)
 
                     # Send final result
-                    self._send_stream_event(
-                        conn,
-                        "final",
-                        {
-                            "text": text,
-                            "language": detected_language,
-                            "audio_duration_secs": audio_duration_secs,
-                        },
-                    )
+                    final = {
+                        "text": text,
+                        "language": detected_language,
+                        "audio_duration_secs": audio_duration_secs,
+                    }
+                    if task == "translate":
+                        final["translation"] = self._translate(
+                            model,
+                            request,
+                            audio_path,
+                            text,
+                            detected_language or language,
+                        )
+                    self._send_stream_event(conn, "final", final)
                 else:
                     self._send_stream_event(
                         conn,
@@ -429,7 +483,7 @@ class Qwen3ASRDaemon:
                 conn, "error", {"error": f"Transcription failed: {str(e)}"}
             )
         finally:
-            if audio_path and os.path.exists(audio_path):
+            if audio_path and not uploaded_path and os.path.exists(audio_path):
                 os.unlink(audio_path)
             self._send_stream_event(conn, "done", {})
 
@@ -550,7 +604,9 @@ class Qwen3ASRDaemon:
                     break
 
                 if request.get("command") == "shutdown":
-                    self._send_message(conn, {"status": "shutdown"})
+                    self._send_message(
+                        conn, {"status": "shutdown", "request_id": request.get("request_id")}
+                    )
                     self.running = False
                     break
 
@@ -562,26 +618,20 @@ class Qwen3ASRDaemon:
 
                 # Only send response if not handled by streaming (response is not None)
                 if response is not None:
+                    # Echo the request ID so clients can match responses
+                    if "request_id" in request:
+                        response["request_id"] = request["request_id"]
                     self._send_message(conn, response)
         finally:
             conn.close()
 
     def start(self):
         """Start the daemon server."""
-        if os.path.exists(self.socket_path):
-            os.unlink(self.socket_path)
-
-        self.server_socket = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
-        self.server_socket.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
-        self.server_socket.bind(self.socket_path)
-        self.server_socket.listen(5)
-        self.server_socket.settimeout(1.0)
-
-        os.chmod(self.socket_path, 0o600)
+        self.server_socket = daemon_transport.listen(self.listen_args)
 
         self.running = True
         print(
-            f"[ASR Daemon] Started on {self.socket_path} (PID: {os.getpid()})",
+            f"[ASR Daemon] Started on {self.server_socket.address} (PID: {os.getpid()})",
             file=sys.stderr,
         )
 
@@ -607,8 +657,7 @@ class Qwen3ASRDaemon:
         self.running = False
         if self.server_socket:
             self.server_socket.close()
-        if os.path.exists(self.socket_path):
-            os.unlink(self.socket_path)
+            self.server_socket = None
         self.model_cache.clear()
         print("[ASR Daemon] Stopped", file=sys.stderr)
 
@@ -625,9 +674,7 @@ def main():
     import argparse
 
     parser = argparse.ArgumentParser(description="Qwen3-ASR Daemon")
-    parser.add_argument(
-        "--socket", default=DEFAULT_SOCKET_PATH, help="Unix socket path"
-    )
+    daemon_transport.add_arguments(parser, DEFAULT_SOCKET_PATH)
     parser.add_argument(
         "--preload", action="store_true", help="Preload model on startup"
     )
@@ -638,7 +685,7 @@ def main():
     )
     args = parser.parse_args()
 
-    daemon = Qwen3ASRDaemon(socket_path=args.socket)
+    daemon = Qwen3ASRDaemon(listen_args=args)
 
     if args.preload:
         print(f"[ASR Daemon] Preloading model: {args.model_id}", file=sys.stderr)
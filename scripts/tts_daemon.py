@@ -1,7 +1,7 @@
 #!/usr/bin/env python3
 """
 Persistent TTS Daemon for Qwen3-TTS.
-Keeps models loaded in memory and accepts requests via Unix socket.
+Keeps models loaded in memory and accepts requests via Unix socket, TCP or named pipe.
 """
 
 import sys
@@ -18,6 +18,11 @@ from pathlib import Path
 from typing import Dict, Optional, Any
 from collections import OrderedDict
 
+try:
+    from . import daemon_transport
+except ImportError:
+    import daemon_transport
+
 # Suppress all warnings before importing heavy libraries
 import warnings
 
@@ -98,16 +103,18 @@ class LRUModelCache:
 
 
 class TTSDaemon:
-    """TTS Daemon that handles requests via Unix socket."""
+    """TTS Daemon that handles requests via Unix socket, TCP or named pipe."""
 
-    def __init__(self, socket_path: str = DEFAULT_SOCKET_PATH):
-        self.socket_path = socket_path
+    def __init__(self, listen_args, devices=None):
+        self.listen_args = listen_args
         self.model_cache = LRUModelCache()
         self.running = False
         self.server_socket = None
         self.device = None
         self.dtype = None
         self.attn_impl = None
+        # CUDA devices to split models over layer by layer
+        self.devices = devices or []
         self._init_device()
 
     def _init_device(self):
@@ -115,7 +122,11 @@ class TTSDaemon:
         try:
             import torch
 
-            if torch.cuda.is_available():
+            if len(self.devices) > 1 and torch.cuda.is_available():
+                self.device = ",".join(self.devices)
+                self.dtype = torch.bfloat16
+                self.attn_impl = "flash_attention_2"
+            elif torch.cuda.is_available():
                 self.device = "cuda:0"
                 self.dtype = torch.bfloat16
                 self.attn_impl = "flash_attention_2"
@@ -158,11 +169,15 @@ class TTSDaemon:
         from qwen_tts import Qwen3TTSModel
         import torch
 
+        placement = {"device_map": self.device}
+        if len(self.devices) > 1:
+            placement = {"device_map": "auto", "max_memory": self._max_memory()}
+
         model = Qwen3TTSModel.from_pretrained(
             model_id,
-            device_map=self.device,
             dtype=self.dtype,
             attn_implementation=self.attn_impl,
+            **placement,
         )
 
         load_time = time.time() - start_time
@@ -171,6 +186,24 @@ class TTSDaemon:
         self.model_cache.put(model_id, model)
         return model
 
+    def _max_memory(self) -> dict:
+        """Free memory per GPU, limiting "auto" placement to the listed devices.
+
+        Accelerate then fills the devices in order with contiguous layers,
+        giving a layer-wise pipeline across them.
+        """
+        import torch
+
+        max_memory = {}
+        for index in range(torch.cuda.device_count()):
+            if f"cuda:{index}" in self.devices:
+                free, _total = torch.cuda.mem_get_info(index)
+                max_memory[index] = int(free * 0.9)
+            else:
+                max_memory[index] = 0
+        max_memory["cpu"] = 0
+        return max_memory
+
     def _handle_check(self, request: dict) -> dict:
         """Handle dependency check request."""
         try:
@@ -214,13 +247,8 @@ class TTSDaemon:
             self.model_cache.clear()
             return {"status": "ok", "unloaded": "all"}
 
-    def _handle_generate(self, request: dict) -> dict:
-        """Handle TTS generation request."""
-        import numpy as np
-        import soundfile as sf
-        import tempfile
-        import base64
-
+    def _synthesize(self, request: dict):
+        """Run generation, returning (samples, sample_rate) or an error dict."""
         model_path = request.get("model_path", "")
         text = request.get("text", "")
         speaker = request.get("speaker", "Vivian")
@@ -239,7 +267,7 @@ class TTSDaemon:
             file=sys.stderr,
         )
         if ref_text:
-            print(f"[Daemon] ref_text: {ref_text[:50]}...", file=sys.stderr)
+            print(f"[Daemon] ref_text: {len(ref_text)} chars", file=sys.stderr)
 
         model_id = self._get_hf_model_id(model_path)
         print(f"[Daemon] Resolved model_id: {model_id}", file=sys.stderr)
@@ -293,12 +321,25 @@ class TTSDaemon:
         except Exception as e:
             return {"error": f"Generation failed: {str(e)}"}
 
+        return wavs[0], sr
+
+    def _handle_generate(self, request: dict) -> dict:
+        """Handle TTS generation request."""
+        import soundfile as sf
+        import tempfile
+        import base64
+
+        result = self._synthesize(request)
+        if isinstance(result, dict):
+            return result
+        wav, sr = result
+
         # Convert to WAV bytes
         with tempfile.NamedTemporaryFile(suffix=".wav", delete=False) as f:
             temp_path = f.name
 
         try:
-            sf.write(temp_path, wavs[0], sr)
+            sf.write(temp_path, wav, sr)
             with open(temp_path, "rb") as f:
                 audio_bytes = f.read()
             audio_b64 = base64.b64encode(audio_bytes).decode("utf-8")
@@ -311,6 +352,36 @@ class TTSDaemon:
             "format": "wav",
         }
 
+    def _handle_generate_stream(self, request: dict):
+        """Handle streaming TTS generation.
+
+        Returns an error dict, or a generator of frames holding 16-bit PCM.
+        qwen_tts returns whole utterances, so frames follow synthesis; the
+        protocol lets models with incremental decoding yield as they go.
+        """
+        result = self._synthesize(request)
+        if isinstance(result, dict):
+            return result
+        wav, sr = result
+        return self._pcm_frames(wav, sr, int(request.get("chunk_ms") or 200))
+
+    def _pcm_frames(self, wav, sr: int, chunk_ms: int):
+        """Split samples into base64 16-bit PCM frames of chunk_ms each."""
+        import numpy as np
+        import base64
+
+        pcm = (np.clip(np.asarray(wav, dtype=np.float32), -1.0, 1.0) * 32767).astype(
+            "<i2"
+        )
+        step = max(1, sr * chunk_ms // 1000)
+        for start in range(0, len(pcm), step):
+            yield {
+                "audio_pcm16_base64": base64.b64encode(
+                    pcm[start : start + step].tobytes()
+                ).decode("ascii"),
+                "sample_rate": sr,
+            }
+
     def _decode_reference_audio(self, ref_audio_b64: str):
         """Decode reference audio from base64."""
         import numpy as np
@@ -358,8 +429,12 @@ class TTSDaemon:
 
         return None, None
 
-    def handle_request(self, request: dict, conn: socket.socket = None) -> dict:
-        """Route request to appropriate handler."""
+    def handle_request(self, request: dict, conn: socket.socket = None):
+        """Route request to appropriate handler.
+
+        Handlers return a response dict, or a generator of frames for
+        streamed responses.
+        """
         command = request.get("command", "generate")
 
         handlers = {
@@ -368,6 +443,7 @@ class TTSDaemon:
             "preload": self._handle_preload,
             "unload": self._handle_unload,
             "generate": self._handle_generate,
+            "generate_stream": self._handle_generate_stream,
             "shutdown": lambda r: {"status": "shutdown"},
         }
 
@@ -407,6 +483,13 @@ class TTSDaemon:
         except Exception as e:
             print(f"[Daemon] Error sending message: {e}", file=sys.stderr)
 
+    def _reply(self, conn: socket.socket, request: dict, response: dict):
+        """Send a response frame, echoing the request ID so clients can
+        match it to the request."""
+        if "request_id" in request:
+            response["request_id"] = request["request_id"]
+        self._send_message(conn, response)
+
     def _handle_client(self, conn: socket.socket, addr):
         """Handle a single client connection."""
         try:
@@ -417,38 +500,37 @@ class TTSDaemon:
 
                 # Handle shutdown specially
                 if request.get("command") == "shutdown":
-                    self._send_message(conn, {"status": "shutdown"})
+                    self._send_message(
+                        conn, {"status": "shutdown", "request_id": request.get("request_id")}
+                    )
                     self.running = False
                     break
 
                 try:
                     response = self.handle_request(request, conn)
+                    if isinstance(response, dict):
+                        self._reply(conn, request, response)
+                    else:
+                        # Streamed: non-final frames, then an empty final one
+                        for frame in response:
+                            frame["final"] = False
+                            self._reply(conn, request, frame)
+                        self._reply(conn, request, {"final": True})
                 except Exception as e:
-                    response = {"error": f"Internal error: {str(e)}"}
                     traceback.print_exc(file=sys.stderr)
-
-                self._send_message(conn, response)
+                    self._reply(
+                        conn, request, {"error": f"Internal error: {str(e)}", "final": True}
+                    )
         finally:
             conn.close()
 
     def start(self):
         """Start the daemon server."""
-        # Remove existing socket file
-        if os.path.exists(self.socket_path):
-            os.unlink(self.socket_path)
-
-        self.server_socket = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
-        self.server_socket.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
-        self.server_socket.bind(self.socket_path)
-        self.server_socket.listen(5)
-        self.server_socket.settimeout(1.0)  # Allow checking self.running
-
-        # Set socket permissions
-        os.chmod(self.socket_path, 0o600)
+        self.server_socket = daemon_transport.listen(self.listen_args)
 
         self.running = True
         print(
-            f"[Daemon] Started on {self.socket_path} (PID: {os.getpid()})",
+            f"[Daemon] Started on {self.server_socket.address} (PID: {os.getpid()})",
             file=sys.stderr,
         )
 
@@ -476,8 +558,7 @@ class TTSDaemon:
         self.running = False
         if self.server_socket:
             self.server_socket.close()
-        if os.path.exists(self.socket_path):
-            os.unlink(self.socket_path)
+            self.server_socket = None
         self.model_cache.clear()
         print("[Daemon] Stopped", file=sys.stderr)
 
@@ -492,13 +573,16 @@ def main():
     import argparse
 
     parser = argparse.ArgumentParser(description="TTS Daemon for Qwen3-TTS")
+    daemon_transport.add_arguments(parser, DEFAULT_SOCKET_PATH)
+    parser.add_argument("--preload", help="Model to preload on startup")
     parser.add_argument(
-        "--socket", default=DEFAULT_SOCKET_PATH, help="Unix socket path"
+        "--devices",
+        help="Comma-separated CUDA devices to split models over, e.g. cuda:0,cuda:1",
     )
-    parser.add_argument("--preload", help="Model to preload on startup")
     args = parser.parse_args()
 
-    daemon = TTSDaemon(socket_path=args.socket)
+    devices = [d.strip() for d in args.devices.split(",")] if args.devices else []
+    daemon = TTSDaemon(listen_args=args, devices=devices)
 
     # Preload model if specified
     if args.preload:
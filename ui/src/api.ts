@@ -38,17 +38,23 @@ export interface TTSRequest {
   speed?: number;
 }
 
+export interface OutputTimings {
+  queue_ms: number;
+  prefill_ms: number;
+  decode_ms: number;
+  total_ms: number;
+}
+
 export interface TTSResponse {
   request_id: string;
   audio: string;
   format: string;
   sample_rate: number;
   duration_secs: number;
-  stats: {
-    tokens_generated: number;
-    generation_time_ms: number;
-    rtf: number;
-  };
+  prompt_tokens: number;
+  generated_tokens: number;
+  finish_reason: "stop" | "length" | "abort" | "error";
+  timings: OutputTimings;
 }
 
 // ============================================================================